@@ -40,15 +40,20 @@ pub struct ApplicationConfiguration {
     pub test_chatbot: bool,
     pub test_sisu: bool,
     pub test_suotar: bool,
+    pub test_lti: bool,
+    pub test_lrs: bool,
     pub disable_embedding_vector_creation_when_seeding: bool,
     pub development_uuid_login: bool,
     pub enable_admin_email_verification: bool,
     pub enable_email_ownership_verification: bool,
     pub azure_configuration: Option<AzureConfiguration>,
     pub suotar_configuration: SuotarConfiguration,
+    /// Where the xAPI statements are sent. If not set, they are kept in the outbox.
+    pub xapi_configuration: Option<XapiConfiguration>,
     pub tmc_account_creation_origin: Option<String>,
     pub tmc_admin_access_token: SecretString,
     pub oauth_server_configuration: OAuthServerConfiguration,
+    pub certificate_credential_configuration: CertificateCredentialConfiguration,
 }
 
 impl ApplicationConfiguration {
@@ -70,6 +75,10 @@ impl ApplicationConfiguration {
         // No mock fallback unlike Azure: credit registration writes to the real student registry.
         let test_suotar = test_mode && bool_env_false_by_default("USE_MOCK_SUOTAR_ENDPOINT");
 
+        let test_lti = test_mode && bool_env_false_by_default("USE_MOCK_LTI_PLATFORM");
+
+        let test_lrs = test_mode && bool_env_false_by_default("USE_MOCK_LRS");
+
         let disable_embedding_vector_creation_when_seeding = false;
 
         let azure_configuration = if test_chatbot {
@@ -84,6 +93,12 @@ impl ApplicationConfiguration {
             SuotarConfiguration::try_from_env()?
         };
 
+        let xapi_configuration = if test_lrs {
+            Some(XapiConfiguration::mock_conf(&base_url)?)
+        } else {
+            XapiConfiguration::try_from_env()?
+        };
+
         let tmc_account_creation_origin = Some(
             env::var("TMC_ACCOUNT_CREATION_ORIGIN")
                 .context("TMC_ACCOUNT_CREATION_ORIGIN must be defined")?,
@@ -102,6 +117,9 @@ impl ApplicationConfiguration {
         );
         let oauth_server_configuration = OAuthServerConfiguration::try_from_env()
             .context("Failed to load OAuth server configuration")?;
+        let certificate_credential_configuration =
+            CertificateCredentialConfiguration::try_from_env()
+                .context("Failed to load certificate credential configuration")?;
 
         Ok(Self {
             base_url,
@@ -109,15 +127,19 @@ impl ApplicationConfiguration {
             test_chatbot,
             test_sisu,
             test_suotar,
+            test_lti,
+            test_lrs,
             disable_embedding_vector_creation_when_seeding,
             development_uuid_login,
             enable_admin_email_verification,
             enable_email_ownership_verification,
             azure_configuration,
             suotar_configuration,
+            xapi_configuration,
             tmc_account_creation_origin,
             tmc_admin_access_token,
             oauth_server_configuration,
+            certificate_credential_configuration,
         })
     }
 
@@ -131,9 +153,12 @@ impl ApplicationConfiguration {
         let test_chatbot = true;
         let test_sisu = true;
         let test_suotar = false;
+        let test_lti = false;
+        let test_lrs = false;
         let disable_embedding_vector_creation_when_seeding = true;
         let suotar_configuration = SuotarConfiguration::mock_conf("http://project-331.local")
             .expect("Failed to build the mock Suotar configuration");
+        let xapi_configuration = None;
         let tmc_account_creation_origin = None;
         let tmc_admin_access_token = SecretString::new("mock-access-token".to_string().into());
         let oauth_server_configuration = OAuthServerConfiguration {
@@ -144,21 +169,29 @@ impl ApplicationConfiguration {
                 "test-key".into(),
             ))),
         };
+        let certificate_credential_configuration = CertificateCredentialConfiguration {
+            rsa_public_key: "temp-change-when-needed".into(),
+            rsa_private_key: SecretString::new("test-change".into()),
+        };
         Ok(Self {
             base_url,
             test_mode,
             test_chatbot,
             test_sisu,
             test_suotar,
+            test_lti,
+            test_lrs,
             disable_embedding_vector_creation_when_seeding,
             development_uuid_login,
             enable_admin_email_verification,
             enable_email_ownership_verification,
             azure_configuration,
             suotar_configuration,
+            xapi_configuration,
             tmc_account_creation_origin,
             tmc_admin_access_token,
             oauth_server_configuration,
+            certificate_credential_configuration,
         })
     }
 }
@@ -509,6 +542,101 @@ impl OAuthServerConfiguration {
     }
 }
 
+/// The credentials the mock learning record store accepts. Public on purpose: never a real credential.
+pub const MOCK_LRS_USERNAME: &str = "mock-lrs-user";
+pub const MOCK_LRS_PASSWORD: &str = "mock-lrs-password";
+
+/// The learning record store the xAPI statements are sent to.
+#[derive(Clone)]
+pub struct XapiConfiguration {
+    /// The xAPI endpoint of the store. Ends in `/` so that `statements` can be joined to it.
+    pub endpoint: Url,
+    pub username: String,
+    pub password: SecretString,
+}
+
+impl XapiConfiguration {
+    /// Points the sender at our own mock controller. Only reachable with `TEST_MODE` and
+    /// `USE_MOCK_LRS` both on.
+    pub fn mock_conf(base_url: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            endpoint: Url::parse(base_url)
+                .context("Invalid URL in BASE_URL")?
+                .join("/api/v0/mock-lrs/xapi/")?,
+            username: MOCK_LRS_USERNAME.to_string(),
+            password: SecretString::new(MOCK_LRS_PASSWORD.to_string().into()),
+        })
+    }
+
+    /// Returns `None` if `XAPI_LRS_ENDPOINT` is not set. The credentials are required if it is.
+    pub fn try_from_env() -> anyhow::Result<Option<Self>> {
+        Self::from_values(
+            non_empty_env("XAPI_LRS_ENDPOINT"),
+            non_empty_env("XAPI_LRS_USERNAME"),
+            non_empty_env("XAPI_LRS_PASSWORD"),
+        )
+    }
+
+    fn from_values(
+        endpoint: Option<String>,
+        username: Option<String>,
+        password: Option<String>,
+    ) -> anyhow::Result<Option<Self>> {
+        let Some(endpoint) = endpoint else {
+            return Ok(None);
+        };
+        let username = username
+            .context("XAPI_LRS_USERNAME must be defined when XAPI_LRS_ENDPOINT is defined")?;
+        let password = password
+            .context("XAPI_LRS_PASSWORD must be defined when XAPI_LRS_ENDPOINT is defined")?;
+        let endpoint = if endpoint.ends_with('/') {
+            endpoint
+        } else {
+            format!("{endpoint}/")
+        };
+        Ok(Some(Self {
+            endpoint: Url::parse(&endpoint).context("Invalid URL in XAPI_LRS_ENDPOINT")?,
+            username,
+            password: SecretString::new(password.into()),
+        }))
+    }
+}
+
+/// The key that signs the verifiable credentials issued for generated certificates. Kept apart from
+/// the OAuth key so that either can be rotated without invalidating what the other has signed.
+#[derive(Clone)]
+pub struct CertificateCredentialConfiguration {
+    pub rsa_public_key: String,
+    /// RSA private key (PEM). Secret: zeroized on drop, redacted from `Debug`; only exposed when
+    /// handed to the signing key builder.
+    pub rsa_private_key: SecretString,
+}
+
+impl PartialEq for CertificateCredentialConfiguration {
+    fn eq(&self, other: &Self) -> bool {
+        self.rsa_public_key == other.rsa_public_key
+            && self.rsa_private_key.expose_secret() == other.rsa_private_key.expose_secret()
+    }
+}
+
+impl CertificateCredentialConfiguration {
+    /// Attempts to create a CertificateCredentialConfiguration.
+    /// Return `Err` if either key is not set.
+    pub fn try_from_env() -> anyhow::Result<Self> {
+        let rsa_public_key = env::var("CERTIFICATE_CREDENTIAL_RSA_PUBLIC_PEM")
+            .context("CERTIFICATE_CREDENTIAL_RSA_PUBLIC_PEM must be defined")?;
+        let rsa_private_key = SecretString::new(
+            env::var("CERTIFICATE_CREDENTIAL_RSA_PRIVATE_PEM")
+                .context("CERTIFICATE_CREDENTIAL_RSA_PRIVATE_PEM must be defined")?
+                .into(),
+        );
+        Ok(Self {
+            rsa_public_key,
+            rsa_private_key,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -563,6 +691,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn xapi_configuration_is_optional_but_needs_credentials() {
+        assert!(
+            XapiConfiguration::from_values(None, None, None)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            XapiConfiguration::from_values(
+                Some("https://lrs.example.com/xapi".to_string()),
+                None,
+                None
+            )
+            .is_err()
+        );
+        let conf = XapiConfiguration::from_values(
+            Some("https://lrs.example.com/xapi".to_string()),
+            Some("user".to_string()),
+            Some("password".to_string()),
+        )
+        .unwrap()
+        .expect("the endpoint is set");
+        assert_eq!(
+            conf.endpoint.join("statements").unwrap().as_str(),
+            "https://lrs.example.com/xapi/statements"
+        );
+    }
+
     #[test]
     fn mock_conf_points_at_our_own_mock_controller() {
         let conf = SuotarConfiguration::mock_conf("http://project-331.local")
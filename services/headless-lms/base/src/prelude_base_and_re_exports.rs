@@ -10,6 +10,7 @@ pub use uuid::Uuid;
 
 pub use crate::config::{
     ApplicationConfiguration, AzureBlobStorageConfiguration, AzureChatbotConfiguration,
-    AzureConfiguration, AzureSearchConfiguration, OAuthServerConfiguration,
+    AzureConfiguration, AzureSearchConfiguration, CertificateCredentialConfiguration,
+    OAuthServerConfiguration,
 };
 pub use crate::error::backend_error::BackendError;
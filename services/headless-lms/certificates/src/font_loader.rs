@@ -14,6 +14,25 @@ pub async fn get_font_database_with_fonts(
     file_store: &dyn FileStore,
 ) -> UtilResult<fontdb::Database> {
     let mut fontdb = fontdb::Database::new();
+    for font_data in get_font_data(conn, file_store).await? {
+        fontdb.load_font_data(font_data);
+    }
+
+    info!("Loaded {} fonts", fontdb.faces().count());
+    fontdb.faces().for_each(|f| {
+        info!("Font: {:?}, weight: {:?}", f.families, f.weight);
+    });
+
+    Ok(fontdb)
+}
+
+/// Fetches the files of all the fonts in the database table `certificate_fonts`. Fonts that cannot be fetched are skipped with a warning.
+///
+/// The PDF renderer needs the raw data because svg2pdf depends on its own version of usvg and thus has its own font database type.
+pub async fn get_font_data(
+    conn: &mut PgConnection,
+    file_store: &dyn FileStore,
+) -> UtilResult<Vec<Vec<u8>>> {
     let certificate_fonts = headless_lms_models::certificate_fonts::get_all(&mut *conn)
         .await
         .map_err(|original_error| {
@@ -23,24 +42,19 @@ pub async fn get_font_database_with_fonts(
                 Some(original_error.into()),
             )
         })?;
+    let mut res = vec![];
     for certificate_font in certificate_fonts {
         match file_store
             .fetch_file_content_or_use_filesystem_cache(Path::new(&certificate_font.file_path))
             .await
         {
             Ok(font_data) => {
-                fontdb.load_font_data(font_data);
+                res.push(font_data);
             }
             Err(e) => {
                 warn!("Could not load font: {}", e);
             }
         };
     }
-
-    info!("Loaded {} fonts", fontdb.faces().count());
-    fontdb.faces().for_each(|f| {
-        info!("Font: {:?}, weight: {:?}", f.families, f.weight);
-    });
-
-    Ok(fontdb)
+    Ok(res)
 }
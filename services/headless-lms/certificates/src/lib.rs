@@ -4,14 +4,20 @@
 //!
 pub mod date_utils;
 pub mod font_loader;
+mod pdf;
 pub mod prelude;
+pub mod templates;
+mod transcript;
 
 use crate::prelude::*;
 use chrono::NaiveDate;
 use date_utils::get_date_as_localized_string;
 use futures::future::OptionFuture;
-use headless_lms_models::certificate_configurations::{CertificateTextAnchor, PaperSize};
-use headless_lms_models::generated_certificates::GeneratedCertificate;
+use headless_lms_models::certificate_configurations::{
+    CertificateConfiguration, CertificateTextAnchor, PaperSize,
+};
+use headless_lms_models::course_module_completions::CourseModuleCompletion;
+use headless_lms_models::generated_certificates::{CertificateStatus, GeneratedCertificate};
 use headless_lms_utils::file_store::FileStore;
 use headless_lms_utils::icu4x::Icu4xBlob;
 
@@ -22,6 +28,7 @@ use usvg::fontdb;
 
 use quick_xml::{Writer, events::BytesText};
 use std::io::Cursor;
+use transcript::Transcript;
 
 use rust_i18n::{i18n, t};
 i18n!("locales");
@@ -42,6 +49,67 @@ pub async fn generate_certificate(
     debug: bool,
     icu4x_blob: Icu4xBlob,
 ) -> UtilResult<Vec<u8>> {
+    let prepared =
+        prepare_certificate(&mut *conn, file_store, certificate, debug, icu4x_blob).await?;
+    let fontdb = font_loader::get_font_database_with_fonts(&mut *conn, file_store).await?;
+    let res = generate_certificate_impl(
+        &prepared.background_svg,
+        prepared.overlay_svg.as_deref(),
+        &prepared.texts,
+        &prepared.config.paper_size,
+        debug,
+        Arc::new(fontdb),
+    )?;
+    Ok(res)
+}
+
+/**
+Generates a certificate as a pdf. The first page looks like the png version but the texts stay selectable and the fonts are embedded.
+If the certificate configuration has `pdf_transcript_pages_enabled`, the student's completed modules with their grades and credits are listed on the following pages.
+*/
+pub async fn generate_certificate_pdf(
+    conn: &mut PgConnection,
+    file_store: &dyn FileStore,
+    certificate: &GeneratedCertificate,
+    icu4x_blob: Icu4xBlob,
+) -> UtilResult<Vec<u8>> {
+    let prepared =
+        prepare_certificate(&mut *conn, file_store, certificate, false, icu4x_blob).await?;
+    let font_data = font_loader::get_font_data(&mut *conn, file_store).await?;
+    let transcript = if prepared.config.pdf_transcript_pages_enabled {
+        Some(
+            transcript::get_transcript(&mut *conn, certificate, &prepared.config, icu4x_blob)
+                .await?,
+        )
+    } else {
+        None
+    };
+    generate_certificate_pdf_impl(
+        &prepared.background_svg,
+        prepared.overlay_svg.as_deref(),
+        &prepared.texts,
+        transcript.as_ref(),
+        &prepared.config.paper_size,
+        &prepared.config.certificate_locale,
+        font_data,
+    )
+}
+
+/// Everything needed to render the first page of a certificate.
+struct PreparedCertificate {
+    config: CertificateConfiguration,
+    background_svg: Vec<u8>,
+    overlay_svg: Option<Vec<u8>>,
+    texts: Vec<TextToRender>,
+}
+
+async fn prepare_certificate(
+    conn: &mut PgConnection,
+    file_store: &dyn FileStore,
+    certificate: &GeneratedCertificate,
+    debug: bool,
+    icu4x_blob: Icu4xBlob,
+) -> UtilResult<PreparedCertificate> {
     let config = headless_lms_models::certificate_configurations::get_by_id(
         &mut *conn,
         certificate.certificate_configuration_id,
@@ -66,7 +134,21 @@ pub async fn generate_certificate(
     .await
     .transpose()?;
 
-    let grade = if config.render_certificate_grade {
+    let text_elements =
+        headless_lms_models::certificate_text_elements::get_by_certificate_configuration_id(
+            &mut *conn, config.id,
+        )
+        .await
+        .map_err(|original_error| {
+            UtilError::new(
+                UtilErrorType::Other,
+                "Could not get the certificate texts".to_string(),
+                Some(original_error.into()),
+            )
+        })?;
+
+    // The fixed grade field is only rendered for configurations without text elements.
+    let grade = if config.render_certificate_grade && text_elements.is_empty() {
         let requirements = headless_lms_models::certificate_configuration_to_requirements::get_all_requirements_for_certificate_configuration(
         conn,
         certificate.certificate_configuration_id,
@@ -101,18 +183,7 @@ pub async fn generate_certificate(
             rust_i18n::set_locale(&config.certificate_locale);
             let grade_label = t!("grade");
 
-            grade_option.map(|grade| {
-                if let Some(numeral_grade) = grade.grade {
-                    format!("{} {}", grade_label, numeral_grade)
-                } else {
-                    let passed_text = if grade.passed {
-                        t!("passed")
-                    } else {
-                        t!("failed")
-                    };
-                    format!("{} {}", grade_label, passed_text)
-                }
-            })
+            grade_option.map(|grade| format!("{} {}", grade_label, grade_value_text(&grade)))
         } else {
             None
         }
@@ -120,7 +191,6 @@ pub async fn generate_certificate(
         None
     };
 
-    let fontdb = font_loader::get_font_database_with_fonts(&mut *conn, file_store).await?;
     let url = if debug {
         "https://courses.mooc.fi/certificates/validate/debug".to_string()
     } else {
@@ -142,65 +212,120 @@ pub async fn generate_certificate(
     } else {
         certificate.created_at.date_naive()
     };
-    let mut texts_to_render = vec![
-        TextToRender {
-            text: certificate.name_on_certificate.to_string(),
-            y_pos: config.certificate_owner_name_y_pos,
-            x_pos: config.certificate_owner_name_x_pos,
-            font_size: config.certificate_owner_name_font_size,
-            text_anchor: config.certificate_owner_name_text_anchor,
-            text_color: config.certificate_owner_name_text_color,
-            ..Default::default()
-        },
-        TextToRender {
-            text: url,
-            y_pos: config.certificate_validate_url_y_pos,
-            x_pos: config.certificate_validate_url_x_pos,
-            font_size: config.certificate_validate_url_font_size,
-            text_anchor: config.certificate_validate_url_text_anchor,
-            text_color: config.certificate_validate_url_text_color,
-            ..Default::default()
-        },
-        TextToRender {
-            text: get_date_as_localized_string(&config.certificate_locale, date, icu4x_blob)?,
-            y_pos: config.certificate_date_y_pos,
-            x_pos: config.certificate_date_x_pos,
-            font_size: config.certificate_date_font_size,
-            text_anchor: config.certificate_date_text_anchor,
-            text_color: config.certificate_date_text_color,
-            ..Default::default()
-        },
-    ];
-    if let Some(grade_text) = grade {
+    let mut texts_to_render = if text_elements.is_empty() {
+        let mut texts_to_render = vec![
+            TextToRender {
+                text: certificate.name_on_certificate.to_string(),
+                y_pos: config.certificate_owner_name_y_pos.clone(),
+                x_pos: config.certificate_owner_name_x_pos.clone(),
+                font_size: config.certificate_owner_name_font_size.clone(),
+                text_anchor: config.certificate_owner_name_text_anchor,
+                text_color: config.certificate_owner_name_text_color.clone(),
+                ..Default::default()
+            },
+            TextToRender {
+                text: url,
+                y_pos: config.certificate_validate_url_y_pos.clone(),
+                x_pos: config.certificate_validate_url_x_pos.clone(),
+                font_size: config.certificate_validate_url_font_size.clone(),
+                text_anchor: config.certificate_validate_url_text_anchor,
+                text_color: config.certificate_validate_url_text_color.clone(),
+                ..Default::default()
+            },
+            TextToRender {
+                text: get_date_as_localized_string(&config.certificate_locale, date, icu4x_blob)?,
+                y_pos: config.certificate_date_y_pos.clone(),
+                x_pos: config.certificate_date_x_pos.clone(),
+                font_size: config.certificate_date_font_size.clone(),
+                text_anchor: config.certificate_date_text_anchor,
+                text_color: config.certificate_date_text_color.clone(),
+                ..Default::default()
+            },
+        ];
+        if let Some(grade_text) = grade {
+            texts_to_render.push(TextToRender {
+                text: grade_text,
+                x_pos: config.certificate_grade_x_pos.clone().unwrap_or_default(),
+                y_pos: config.certificate_grade_y_pos.clone().unwrap_or_default(),
+                font_size: config
+                    .certificate_grade_font_size
+                    .clone()
+                    .unwrap_or_default(),
+                text_anchor: config
+                    .certificate_grade_text_anchor
+                    .unwrap_or(CertificateTextAnchor::Middle),
+                text_color: config
+                    .certificate_grade_text_color
+                    .clone()
+                    .unwrap_or_default(),
+                ..Default::default()
+            });
+        }
+        texts_to_render
+    } else {
+        let values = templates::get_template_values(
+            &mut *conn,
+            certificate,
+            &config,
+            url,
+            date,
+            debug,
+            icu4x_blob,
+        )
+        .await?;
+        text_elements
+            .iter()
+            .map(|element| TextToRender {
+                // generate_text_svg writes the text as is, and the templates are free text.
+                text: quick_xml::escape::escape(
+                    templates::fill_template(&element.template, &values).as_str(),
+                )
+                .to_string(),
+                x_pos: element.x_pos.clone(),
+                y_pos: element.y_pos.clone(),
+                font_size: element.font_size.clone(),
+                text_color: element.text_color.clone(),
+                text_anchor: element.text_anchor,
+                font_family: element
+                    .font_family
+                    .clone()
+                    .unwrap_or_else(|| TextToRender::default().font_family),
+            })
+            .collect()
+    };
+    // Revoked and superseded certificates can still be viewed through their verification links, so
+    // they are stamped to make it obvious that they are no longer valid.
+    rust_i18n::set_locale(&config.certificate_locale);
+    let stamp = match certificate.status() {
+        CertificateStatus::Valid => None,
+        CertificateStatus::Revoked => Some(t!("revoked")),
+        CertificateStatus::Superseded => Some(t!("superseded")),
+    };
+    if let Some(stamp) = stamp {
         texts_to_render.push(TextToRender {
-            text: grade_text,
-            x_pos: config.certificate_grade_x_pos.clone().unwrap_or_default(),
-            y_pos: config.certificate_grade_y_pos.clone().unwrap_or_default(),
-            font_size: config
-                .certificate_grade_font_size
-                .clone()
-                .unwrap_or_default(),
-            text_anchor: config
-                .certificate_grade_text_anchor
-                .unwrap_or(CertificateTextAnchor::Middle),
-            text_color: config
-                .certificate_grade_text_color
-                .clone()
-                .unwrap_or_default(),
+            text: stamp.to_string(),
+            font_size: "300px".to_string(),
+            text_color: "#da2e2e".to_string(),
             ..Default::default()
         });
     }
-    let paper_size = config.paper_size;
+    Ok(PreparedCertificate {
+        config,
+        background_svg,
+        overlay_svg,
+        texts: texts_to_render,
+    })
+}
 
-    let res = generate_certificate_impl(
-        &background_svg,
-        overlay_svg.as_deref(),
-        &texts_to_render,
-        &paper_size,
-        debug,
-        Arc::new(fontdb),
-    )?;
-    Ok(res)
+/// The grade without a label, e.g. "5" or "Passed". Uses the current rust_i18n locale.
+fn grade_value_text(completion: &CourseModuleCompletion) -> String {
+    if let Some(numeral_grade) = completion.grade {
+        numeral_grade.to_string()
+    } else if completion.passed {
+        t!("passed").to_string()
+    } else {
+        t!("failed").to_string()
+    }
 }
 
 fn generate_certificate_impl(
@@ -323,6 +448,42 @@ fn generate_certificate_impl(
     Ok(png)
 }
 
+fn generate_certificate_pdf_impl(
+    background_svg: &[u8],
+    overlay_svg: Option<&[u8]>,
+    texts: &[TextToRender],
+    transcript: Option<&Transcript>,
+    paper_size: &PaperSize,
+    locale: &str,
+    font_data: Vec<Vec<u8>>,
+) -> UtilResult<Vec<u8>> {
+    // Only used for resolving the font families, the pdf renderer loads the fonts separately.
+    let mut fontdb = fontdb::Database::new();
+    for data in &font_data {
+        fontdb.load_font_data(data.clone());
+    }
+    let mut first_page = vec![
+        background_svg.to_vec(),
+        generate_text_svg(texts, false, paper_size, &fontdb)?,
+    ];
+    if let Some(overlay_svg) = overlay_svg {
+        first_page.push(overlay_svg.to_vec());
+    }
+    let mut pages = vec![first_page];
+    if let Some(transcript) = transcript {
+        rust_i18n::set_locale(locale);
+        for page_texts in transcript::transcript_pages(transcript, paper_size) {
+            pages.push(vec![generate_text_svg(
+                &page_texts,
+                false,
+                paper_size,
+                &fontdb,
+            )?]);
+        }
+    }
+    pdf::render_pdf(&pages, paper_size, font_data)
+}
+
 pub struct TextToRender {
     pub text: String,
     pub font_family: String,
@@ -476,7 +637,11 @@ fn generate_text_svg(
 
 #[cfg(test)]
 mod tests {
-    use super::{TextToRender, face_covers, generate_certificate_impl, resolve_font_family};
+    use super::{
+        TextToRender, face_covers, generate_certificate_impl, generate_certificate_pdf_impl,
+        resolve_font_family,
+    };
+    use crate::transcript::{Transcript, TranscriptRow};
     use headless_lms_models::certificate_configurations::PaperSize;
     use std::sync::Arc;
     use usvg::fontdb;
@@ -509,22 +674,30 @@ mod tests {
         ("Myanmar", "မြန်မာ"),
     ];
 
-    /// Loads every seeded certificate font, mirroring what the render path loads in production.
-    fn all_certificate_fonts() -> fontdb::Database {
+    /// Reads every seeded certificate font, mirroring what the render path loads in production.
+    fn all_certificate_font_data() -> Vec<Vec<u8>> {
         let dir = concat!(
             env!("CARGO_MANIFEST_DIR"),
             "/../server/src/programs/seed/data"
         );
-        let mut db = fontdb::Database::new();
+        let mut res = vec![];
         for entry in std::fs::read_dir(dir).expect("seed data dir must exist") {
             let path = entry.expect("readable seed dir entry").path();
             if matches!(
                 path.extension().and_then(|e| e.to_str()),
                 Some("ttf" | "otf")
             ) {
-                db.load_font_data(std::fs::read(&path).expect("seeded font must be readable"));
+                res.push(std::fs::read(&path).expect("seeded font must be readable"));
             }
         }
+        res
+    }
+
+    fn all_certificate_fonts() -> fontdb::Database {
+        let mut db = fontdb::Database::new();
+        for data in all_certificate_font_data() {
+            db.load_font_data(data);
+        }
         db
     }
 
@@ -579,6 +752,35 @@ mod tests {
         assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n", "output should be a PNG");
     }
 
+    /// The pdf has the certificate page and the transcript pages.
+    #[test]
+    fn renders_certificate_with_transcript_to_pdf() {
+        let background =
+            br#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100"></svg>"#;
+        let transcript = Transcript {
+            student_name: "Example Student".to_string(),
+            rows: (0..40)
+                .map(|i| TranscriptRow {
+                    module_name: format!("Module {}", i),
+                    completion_date: "1.1.2026".to_string(),
+                    grade: "5".to_string(),
+                    ects_credits: Some(5.0),
+                })
+                .collect(),
+        };
+        let pdf = generate_certificate_pdf_impl(
+            background,
+            None,
+            &[TextToRender::default()],
+            Some(&transcript),
+            &PaperSize::HorizontalA4,
+            "en",
+            all_certificate_font_data(),
+        )
+        .expect("pdf rendering should succeed");
+        assert!(pdf.starts_with(b"%PDF-"), "output should be a pdf");
+    }
+
     /// Every supported script must resolve to a font that fully covers it, so a real name in that
     /// script renders without .notdef boxes. Guards both the seeded certificate_fonts set and
     /// resolve_font_family.
@@ -0,0 +1,105 @@
+//! Writes certificates as PDF files.
+//!
+//! Every page is a stack of SVG layers drawn on top of each other. The layers are converted with svg2pdf, which keeps the texts as text and embeds the fonts, so the texts can be selected and copied.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use headless_lms_models::certificate_configurations::PaperSize;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref};
+// svg2pdf depends on its own version of usvg, so the layers must be parsed with its re-export.
+use svg2pdf::usvg;
+
+use crate::prelude::*;
+
+/// Renders the pages to a PDF. Each layer is stretched to cover the whole page, in the same way as the background is stretched when rendering a png.
+pub(crate) fn render_pdf(
+    pages: &[Vec<Vec<u8>>],
+    paper_size: &PaperSize,
+    font_data: Vec<Vec<u8>>,
+) -> UtilResult<Vec<u8>> {
+    let mut fontdb = usvg::fontdb::Database::new();
+    for data in font_data {
+        fontdb.load_font_data(data);
+    }
+    let opt = usvg::Options {
+        // Last-resort fallback; each text element's family is resolved per-string in generate_text_svg.
+        font_family: "Inter Variable".to_string(),
+        fontdb: Arc::new(fontdb),
+        ..Default::default()
+    };
+    let page_width = paper_size.width_pt();
+    let page_height = paper_size.height_pt();
+
+    let mut alloc = Ref::new(1);
+    let catalog_id = alloc.bump();
+    let page_tree_id = alloc.bump();
+    let mut pdf = Pdf::new();
+    let mut page_ids = vec![];
+
+    for layers in pages {
+        let page_id = alloc.bump();
+        let content_id = alloc.bump();
+        page_ids.push(page_id);
+
+        let mut x_objects = vec![];
+        let mut content = Content::new();
+        for (i, layer) in layers.iter().enumerate() {
+            let tree = usvg::Tree::from_data(layer, &opt).map_err(|original_error| {
+                UtilError::new(
+                    UtilErrorType::Other,
+                    "Could not parse svg for pdf".to_string(),
+                    Some(original_error.into()),
+                )
+            })?;
+            let (chunk, svg_id) = svg2pdf::to_chunk(&tree, svg2pdf::ConversionOptions::default())
+                .map_err(|original_error| {
+                UtilError::new(
+                    UtilErrorType::Other,
+                    format!("Could not convert svg to pdf: {:?}", original_error),
+                    None,
+                )
+            })?;
+            // The chunk numbers its objects from 1, so they need to be moved after the objects we have already allocated.
+            let mut renumbered = HashMap::new();
+            let chunk =
+                chunk.renumber(|old| *renumbered.entry(old).or_insert_with(|| alloc.bump()));
+            let svg_id = *renumbered.get(&svg_id).ok_or_else(|| {
+                UtilError::new(
+                    UtilErrorType::Other,
+                    "Converted svg is missing its root object".to_string(),
+                    None,
+                )
+            })?;
+            pdf.extend(&chunk);
+
+            let name = format!("L{}", i);
+            // The converted svg is a 1x1 form, so it is scaled to the page size.
+            content
+                .save_state()
+                .transform([page_width, 0.0, 0.0, page_height, 0.0, 0.0])
+                .x_object(Name(name.as_bytes()))
+                .restore_state();
+            x_objects.push((name, svg_id));
+        }
+
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, page_width, page_height));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        let mut resources = page.resources();
+        let mut resource_x_objects = resources.x_objects();
+        for (name, id) in &x_objects {
+            resource_x_objects.pair(Name(name.as_bytes()), *id);
+        }
+        resource_x_objects.finish();
+        resources.finish();
+        page.finish();
+        pdf.stream(content_id, &content.finish());
+    }
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    let page_count = page_ids.len() as i32;
+    pdf.pages(page_tree_id).kids(page_ids).count(page_count);
+    Ok(pdf.finish())
+}
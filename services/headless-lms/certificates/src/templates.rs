@@ -0,0 +1,223 @@
+//! Fills in the placeholders of templated certificate texts, see
+//! [headless_lms_models::certificate_text_elements].
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use headless_lms_models::{
+    certificate_configuration_to_requirements::get_all_requirements_for_certificate_configuration,
+    certificate_configurations::CertificateConfiguration, course_module_completions,
+    course_modules, courses, generated_certificates::GeneratedCertificate, user_details,
+    verified_student_numbers,
+};
+use headless_lms_utils::icu4x::Icu4xBlob;
+
+use crate::{date_utils::get_date_as_localized_string, grade_value_text, prelude::*};
+
+/// The placeholders that can be used in the certificate texts, e.g. `{name}`.
+pub const PLACEHOLDERS: &[&str] = &[
+    "name",
+    "first_name",
+    "last_name",
+    "student_number",
+    "date",
+    "validate_url",
+    "course_name",
+    "course_module_name",
+    "ects_credits",
+    "grade",
+    "completion_date",
+];
+
+/// Replaces the `{placeholder}`s in the template with their values. Unknown placeholders and
+/// unmatched braces are kept as they are, so the template can also contain literal braces.
+pub fn fill_template(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut res = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        res.push_str(&rest[..start]);
+        let after_brace = &rest[start + 1..];
+        match after_brace
+            .find('}')
+            .and_then(|end| values.get(&after_brace[..end]).map(|value| (end, value)))
+        {
+            Some((end, value)) => {
+                res.push_str(value);
+                rest = &after_brace[end + 1..];
+            }
+            None => {
+                res.push('{');
+                rest = after_brace;
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+/// Collects the values of all the placeholders for the certificate. Values that are not known,
+/// like the student number of a student who has not linked one, are empty. When rendering a debug
+/// certificate the student specific values are replaced with examples.
+pub(crate) async fn get_template_values(
+    conn: &mut PgConnection,
+    certificate: &GeneratedCertificate,
+    config: &CertificateConfiguration,
+    validate_url: String,
+    date: NaiveDate,
+    debug: bool,
+    icu4x_blob: Icu4xBlob,
+) -> UtilResult<HashMap<&'static str, String>> {
+    let locale = &config.certificate_locale;
+    let mut values = HashMap::new();
+    values.insert("name", certificate.name_on_certificate.clone());
+    values.insert("validate_url", validate_url);
+    values.insert(
+        "date",
+        get_date_as_localized_string(locale, date, icu4x_blob)?,
+    );
+
+    let requirements = get_all_requirements_for_certificate_configuration(&mut *conn, config.id)
+        .await
+        .map_err(|original_error| {
+            UtilError::new(
+                UtilErrorType::Other,
+                "No certificate conf requirements".to_string(),
+                Some(original_error.into()),
+            )
+        })?;
+    if let Some(course_module_id) = requirements.course_module_ids.first() {
+        let course_module = course_modules::get_by_id(&mut *conn, *course_module_id)
+            .await
+            .map_err(|original_error| {
+                UtilError::new(
+                    UtilErrorType::Other,
+                    "Could not get the course module".to_string(),
+                    Some(original_error.into()),
+                )
+            })?;
+        let course = courses::get_course(&mut *conn, course_module.course_id)
+            .await
+            .map_err(|original_error| {
+                UtilError::new(
+                    UtilErrorType::Other,
+                    "Could not get the course".to_string(),
+                    Some(original_error.into()),
+                )
+            })?;
+        values.insert(
+            "course_module_name",
+            course_module.name.unwrap_or_else(|| course.name.clone()),
+        );
+        values.insert("course_name", course.name);
+        if let Some(ects_credits) = course_module.ects_credits {
+            values.insert("ects_credits", ects_credits.to_string());
+        }
+
+        let completions = course_module_completions::get_all_by_user_id_and_course_module_id(
+            &mut *conn,
+            certificate.user_id,
+            *course_module_id,
+        )
+        .await
+        .map_err(|original_error| {
+            UtilError::new(
+                UtilErrorType::Other,
+                "No completion found for user".to_string(),
+                Some(original_error.into()),
+            )
+        })?;
+        if let Some(best) = course_module_completions::select_best_completion(completions) {
+            rust_i18n::set_locale(locale);
+            values.insert("grade", grade_value_text(&best));
+            values.insert(
+                "completion_date",
+                get_date_as_localized_string(
+                    locale,
+                    best.completion_date.date_naive(),
+                    icu4x_blob,
+                )?,
+            );
+        }
+    }
+
+    if debug {
+        values.insert("first_name", "Example".to_string());
+        values.insert("last_name", "User".to_string());
+        values.insert("student_number", "012345678".to_string());
+        values.entry("grade").or_insert_with(|| "5".to_string());
+        let date = values["date"].clone();
+        values.entry("completion_date").or_insert(date);
+    } else {
+        let details = user_details::get_user_details_by_user_id(&mut *conn, certificate.user_id)
+            .await
+            .optional()
+            .map_err(|original_error| {
+                UtilError::new(
+                    UtilErrorType::Other,
+                    "Could not get the user details".to_string(),
+                    Some(original_error.into()),
+                )
+            })?;
+        if let Some(details) = details {
+            values.insert("first_name", details.first_name.unwrap_or_default());
+            values.insert("last_name", details.last_name.unwrap_or_default());
+        }
+        let student_number =
+            verified_student_numbers::get_by_user_id(&mut *conn, certificate.user_id)
+                .await
+                .map_err(|original_error| {
+                    UtilError::new(
+                        UtilErrorType::Other,
+                        "Could not get the student number".to_string(),
+                        Some(original_error.into()),
+                    )
+                })?;
+        if let Some(student_number) = student_number {
+            values.insert("student_number", student_number.student_number);
+        }
+    }
+
+    // Every placeholder resolves, so a missing value does not leave a raw placeholder on the certificate.
+    for placeholder in PLACEHOLDERS {
+        values.entry(placeholder).or_default();
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders() {
+        let values = HashMap::from([
+            ("name", "Example Student".to_string()),
+            ("course_name", "Introduction to Programming".to_string()),
+            ("ects_credits", "5".to_string()),
+        ]);
+        assert_eq!(
+            fill_template(
+                "{name} has completed {course_name} ({ects_credits} ECTS)",
+                &values
+            ),
+            "Example Student has completed Introduction to Programming (5 ECTS)"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_placeholders_and_stray_braces() {
+        let values = HashMap::from([("name", "Example Student".to_string())]);
+        assert_eq!(
+            fill_template("{unknown} {name} { {{name}} }", &values),
+            "{unknown} Example Student { {Example Student} }"
+        );
+        assert_eq!(fill_template("{name", &values), "{name");
+        assert_eq!(fill_template("", &values), "");
+    }
+
+    #[test]
+    fn values_are_not_filled_again() {
+        let values = HashMap::from([("name", "{grade}".to_string()), ("grade", "5".to_string())]);
+        assert_eq!(fill_template("{name}", &values), "{grade}");
+    }
+}
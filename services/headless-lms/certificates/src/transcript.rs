@@ -0,0 +1,265 @@
+//! The transcript pages of PDF certificates. They list the modules the student has completed on the courses the certificate is for, with the grades and credits.
+
+use std::collections::HashMap;
+
+use headless_lms_models::{
+    certificate_configuration_to_requirements::get_all_requirements_for_certificate_configuration,
+    certificate_configurations::{CertificateConfiguration, CertificateTextAnchor, PaperSize},
+    course_module_completions, course_modules, courses,
+    generated_certificates::GeneratedCertificate,
+};
+use headless_lms_utils::icu4x::Icu4xBlob;
+use rust_i18n::t;
+
+use crate::{TextToRender, date_utils::get_date_as_localized_string, grade_value_text, prelude::*};
+
+const TITLE_FONT_SIZE_PX: u32 = 90;
+const FONT_SIZE_PX: u32 = 50;
+const ROW_HEIGHT_PX: u32 = 90;
+/// Longer module names are cut so that they do not run over the other columns.
+const MAX_MODULE_NAME_CHARS: usize = 55;
+
+pub(crate) struct Transcript {
+    pub student_name: String,
+    pub rows: Vec<TranscriptRow>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TranscriptRow {
+    pub module_name: String,
+    pub completion_date: String,
+    pub grade: String,
+    pub ects_credits: Option<f32>,
+}
+
+/// Finds the best completion of each module on the courses of the certificate's required modules.
+pub(crate) async fn get_transcript(
+    conn: &mut PgConnection,
+    certificate: &GeneratedCertificate,
+    config: &CertificateConfiguration,
+    icu4x_blob: Icu4xBlob,
+) -> UtilResult<Transcript> {
+    let requirements = get_all_requirements_for_certificate_configuration(&mut *conn, config.id)
+        .await
+        .map_err(|original_error| {
+            UtilError::new(
+                UtilErrorType::Other,
+                "No certificate conf requirements".to_string(),
+                Some(original_error.into()),
+            )
+        })?;
+    let required_modules = course_modules::get_by_ids(&mut *conn, &requirements.course_module_ids)
+        .await
+        .map_err(|original_error| {
+            UtilError::new(
+                UtilErrorType::Other,
+                "Could not get the required course modules".to_string(),
+                Some(original_error.into()),
+            )
+        })?;
+    let mut course_ids: Vec<Uuid> = required_modules.iter().map(|m| m.course_id).collect();
+    course_ids.sort();
+    course_ids.dedup();
+    let mut modules = course_modules::get_by_course_ids(&mut *conn, &course_ids)
+        .await
+        .map_err(|original_error| {
+            UtilError::new(
+                UtilErrorType::Other,
+                "Could not get the course modules".to_string(),
+                Some(original_error.into()),
+            )
+        })?;
+    modules.sort_by_key(|m| (m.course_id, m.order_number));
+
+    let mut completions_by_module: HashMap<Uuid, Vec<_>> = HashMap::new();
+    for completion in course_module_completions::get_all_by_user_id(&mut *conn, certificate.user_id)
+        .await
+        .map_err(|original_error| {
+            UtilError::new(
+                UtilErrorType::Other,
+                "Could not get the completions of the user".to_string(),
+                Some(original_error.into()),
+            )
+        })?
+    {
+        completions_by_module
+            .entry(completion.course_module_id)
+            .or_default()
+            .push(completion);
+    }
+
+    rust_i18n::set_locale(&config.certificate_locale);
+    let mut course_names: HashMap<Uuid, String> = HashMap::new();
+    let mut rows = vec![];
+    for module in modules {
+        let Some(best) = completions_by_module
+            .remove(&module.id)
+            .and_then(course_module_completions::select_best_completion)
+        else {
+            continue;
+        };
+        // The default module has no name of its own, so the course name is used for it.
+        let module_name = match module.name {
+            Some(name) => name,
+            None => match course_names.get(&module.course_id) {
+                Some(name) => name.clone(),
+                None => {
+                    let course = courses::get_course(&mut *conn, module.course_id)
+                        .await
+                        .map_err(|original_error| {
+                            UtilError::new(
+                                UtilErrorType::Other,
+                                "Could not get the course".to_string(),
+                                Some(original_error.into()),
+                            )
+                        })?;
+                    course_names.insert(module.course_id, course.name.clone());
+                    course.name
+                }
+            },
+        };
+        rows.push(TranscriptRow {
+            module_name,
+            completion_date: get_date_as_localized_string(
+                &config.certificate_locale,
+                best.completion_date.date_naive(),
+                icu4x_blob,
+            )?,
+            grade: grade_value_text(&best),
+            ects_credits: module.ects_credits,
+        });
+    }
+    Ok(Transcript {
+        student_name: certificate.name_on_certificate.clone(),
+        rows,
+    })
+}
+
+/// Lays out the transcript on as many pages as needed. Uses the current rust_i18n locale for the headings.
+pub(crate) fn transcript_pages(
+    transcript: &Transcript,
+    paper_size: &PaperSize,
+) -> Vec<Vec<TextToRender>> {
+    let height = paper_size.height_px();
+    let margin = height / 12;
+    let header_y = margin + TITLE_FONT_SIZE_PX * 3;
+    let first_row_y = header_y + ROW_HEIGHT_PX * 3 / 2;
+    let rows_per_page = (((height - margin).saturating_sub(first_row_y)) / ROW_HEIGHT_PX).max(1);
+
+    transcript
+        .rows
+        .chunks(rows_per_page as usize)
+        .map(|rows| {
+            let mut texts = vec![
+                text(
+                    t!("transcript").to_string(),
+                    "8%",
+                    margin,
+                    TITLE_FONT_SIZE_PX,
+                ),
+                text(
+                    transcript.student_name.clone(),
+                    "8%",
+                    margin + TITLE_FONT_SIZE_PX * 3 / 2,
+                    FONT_SIZE_PX,
+                ),
+            ];
+            texts.extend(row_texts(
+                t!("course-module").to_string(),
+                t!("completion-date").to_string(),
+                t!("grade").to_string(),
+                t!("ects-credits").to_string(),
+                header_y,
+            ));
+            for (i, row) in rows.iter().enumerate() {
+                let module_name = if row.module_name.chars().count() > MAX_MODULE_NAME_CHARS {
+                    let cut: String = row
+                        .module_name
+                        .chars()
+                        .take(MAX_MODULE_NAME_CHARS)
+                        .collect();
+                    format!("{}…", cut.trim_end())
+                } else {
+                    row.module_name.clone()
+                };
+                texts.extend(row_texts(
+                    module_name,
+                    row.completion_date.clone(),
+                    row.grade.clone(),
+                    row.ects_credits.map(|c| c.to_string()).unwrap_or_default(),
+                    first_row_y + ROW_HEIGHT_PX * i as u32,
+                ));
+            }
+            texts
+        })
+        .collect()
+}
+
+fn row_texts(
+    module_name: String,
+    completion_date: String,
+    grade: String,
+    ects_credits: String,
+    y: u32,
+) -> Vec<TextToRender> {
+    vec![
+        text(module_name, "8%", y, FONT_SIZE_PX),
+        text(completion_date, "60%", y, FONT_SIZE_PX),
+        text(grade, "76%", y, FONT_SIZE_PX),
+        TextToRender {
+            text_anchor: CertificateTextAnchor::End,
+            ..text(ects_credits, "92%", y, FONT_SIZE_PX)
+        },
+    ]
+}
+
+fn text(text: String, x_pos: &str, y: u32, font_size_px: u32) -> TextToRender {
+    TextToRender {
+        // generate_text_svg writes the text as is, and module names may contain characters like `&`.
+        text: quick_xml::escape::escape(text.as_str()).to_string(),
+        x_pos: x_pos.to_string(),
+        y_pos: y.to_string(),
+        font_size: format!("{}px", font_size_px),
+        text_anchor: CertificateTextAnchor::Start,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(row_count: usize) -> Transcript {
+        Transcript {
+            student_name: "Example Student".to_string(),
+            rows: (0..row_count)
+                .map(|i| TranscriptRow {
+                    module_name: format!("Module {} & more", i),
+                    completion_date: "1.1.2026".to_string(),
+                    grade: "5".to_string(),
+                    ects_credits: Some(5.0),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn splits_long_transcripts_to_pages() {
+        let one_page = transcript_pages(&transcript(3), &PaperSize::VerticalA4);
+        assert_eq!(one_page.len(), 1);
+        // Title, name, 4 header texts and 4 texts per row.
+        assert_eq!(one_page[0].len(), 2 + 4 + 3 * 4);
+
+        let pages = transcript_pages(&transcript(100), &PaperSize::VerticalA4);
+        assert!(pages.len() > 1);
+        let rows: usize = pages.iter().map(|page| (page.len() - 6) / 4).sum();
+        assert_eq!(rows, 100);
+        assert!(transcript_pages(&transcript(0), &PaperSize::VerticalA4).is_empty());
+    }
+
+    #[test]
+    fn escapes_module_names() {
+        let pages = transcript_pages(&transcript(1), &PaperSize::HorizontalA4);
+        assert!(pages[0].iter().any(|t| t.text == "Module 0 &amp; more"));
+    }
+}
@@ -8,9 +8,10 @@ use std::task::{Context, Poll};
 use bytes::Bytes;
 use chrono::Utc;
 use futures::stream::{BoxStream, Peekable};
-use futures::{Stream, StreamExt, TryStreamExt};
+use futures::{Stream, StreamExt};
 use headless_lms_base::config::ApplicationConfiguration;
 use headless_lms_models::chatbot_configurations::{ReasoningEffortLevel, VerbosityLevel};
+use headless_lms_models::chatbot_configurations_models::ModelProvider;
 use headless_lms_models::chatbot_conversation_message_messages::{
     ChatbotConversationMessageMessage, MessageRole,
 };
@@ -29,14 +30,15 @@ use url::Url;
 use utoipa::ToSchema;
 
 use crate::chatbot_error::ChatbotResult;
+use crate::chatbot_tools::custom_tools::course_material_search::CourseMaterialSearchTool;
 use crate::chatbot_tools::provider_tools::azure_ai_search::get_azure_ai_search_tool_definition;
 use crate::chatbot_tools::{
-    AzureLLMToolDefinition, call_chatbot_tool, get_chatbot_tool_definitions,
+    AzureLLMToolDefinition, ChatbotTool, call_chatbot_tool, get_chatbot_tool_definitions,
 };
 use crate::citations::chatbot_cited_documents_to_citations;
+use crate::llm_provider::{self, LlmProvider};
 use crate::llm_utils::{
     APIInputMessage, APIOutputMessage, MessageContent, estimate_tokens, get_params_for_model,
-    make_streaming_llm_request,
 };
 
 use crate::prelude::*;
@@ -72,6 +74,7 @@ const ALL_EXPECTED_EVENTS: &[&str] = &[
 /// Appended to the system prompt when course-material search is enabled, to ground answers
 /// in retrieved course material.
 const SEARCH_GROUNDING_INSTRUCTION: &str = "\n\nSearch the course material with the azure_ai_search tool before answering, and ground your answer in the results with citations. Put only what you want to find in the query; the search is already limited to this course, so don't include the course name. Searching more than once is fine when it helps — to cover distinct sub-questions or angles, to refine when the first results don't answer, or when a follow-up or new instruction needs material you don't already have. When one search already answers, stop there. If you need more information about a specific document or a topic covered in it, use the document_lookup tool to retrieve the full document. Skip searching only for messages that don't need course material, like greetings or thanks. If you need more information about the course, like what pages and chapters are in it, use the course_structure tool.";
+const BUILTIN_SEARCH_GROUNDING_INSTRUCTION: &str = "\n\nSearch the course material with the course_material_search tool before answering, and ground your answer in the results. Put only what you want to find in the query; the search is already limited to this course, so don't include the course name. Searching more than once is fine when it helps — to cover distinct sub-questions or angles, to refine when the first results don't answer, or when a follow-up or new instruction needs material you don't already have. When one search already answers, stop there. If you need more information about a specific document or a topic covered in it, use the document_lookup tool with the page id from the results to retrieve the full document. Skip searching only for messages that don't need course material, like greetings or thanks. If you need more information about the course, like what pages and chapters are in it, use the course_structure tool.";
 
 enum ParsedResponseLine {
     Event(String),
@@ -107,6 +110,7 @@ impl ParsedResponseLine {
 
 /// Context about the user and course for a chatbot interaction.
 /// Passed to tool implementations so they can access user-specific data.
+#[derive(Clone)]
 pub struct ChatbotUserContext {
     pub user_id: Option<Uuid>,
     pub course_id: Option<Uuid>,
@@ -459,6 +463,12 @@ impl LLMRequest {
             chatbot_configuration_id,
         )
         .await?;
+        if configuration.use_azure_search && model.provider != ModelProvider::Azure {
+            return Err(chatbot_err!(
+                LLMRequestBuildError,
+                "Searching the course material requires a model that is served from Azure"
+            ));
+        }
 
         let conversation_messages =
             models::chatbot_conversation_messages::get_by_conversation_id(conn, conversation_id)
@@ -505,6 +515,8 @@ impl LLMRequest {
         let mut system_prompt = configuration.prompt.clone();
         if configuration.use_azure_search {
             system_prompt.push_str(SEARCH_GROUNDING_INSTRUCTION);
+        } else if configuration.use_builtin_search {
+            system_prompt.push_str(BUILTIN_SEARCH_GROUNDING_INSTRUCTION);
         }
 
         api_chat_messages.insert(
@@ -533,9 +545,16 @@ impl LLMRequest {
                     configuration.use_semantic_reranking,
                 )?,
             )]);
+        } else if configuration.use_builtin_search {
+            tools.push(AzureLLMToolDefinition::Function(
+                CourseMaterialSearchTool::get_tool_definition(),
+            ));
         };
 
-        let tool_choice = if configuration.use_azure_search || configuration.use_tools {
+        let tool_choice = if configuration.use_azure_search
+            || configuration.use_builtin_search
+            || configuration.use_tools
+        {
             Some(LLMToolChoice::Auto)
         } else {
             None
@@ -592,8 +611,8 @@ pub enum ChatbotChatStreamEvent {
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
 pub struct StreamEventError {
-    message: String,
-    details: Option<String>,
+    pub message: String,
+    pub details: Option<String>,
 }
 
 /// Custom stream that encapsulates both the response stream and the cancellation guard. Makes sure that the guard is always dropped when the stream is dropped.
@@ -1237,31 +1256,16 @@ struct StreamItem {
     finished: bool,
 }
 
-/// Makes a request to Azure and returns the resulting stream.
+/// Makes a request to the LLM provider and returns the resulting stream.
 pub async fn make_request_and_create_stream<'a>(
     chat_request: LLMRequest,
-    app_config: &ApplicationConfiguration,
+    provider: &dyn LlmProvider,
 ) -> ChatbotResult<PeekableLinesStream<'a>> {
-    let response = make_streaming_llm_request(chat_request, app_config).await?;
-
-    trace!("Receiving chat response with {:?}", response.version());
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_message = response.text().await?;
-        return Err(chatbot_err!(
-            StreamingError,
-            format!(
-                "Failed to send chat request. Status: {}. Error: {}",
-                status, error_message
-            )
-        ));
-    }
-
-    let stream = response
-        .bytes_stream()
-        .map_err(std::io::Error::other)
-        .boxed();
+    debug!(
+        "Preparing streaming LLM request with {} messages",
+        chat_request.input.len()
+    );
+    let stream = provider.stream_response(chat_request).await?;
     let reader = StreamReader::new(stream);
     let lines = reader.lines();
     let lines_stream = LinesStream::new(lines);
@@ -1355,6 +1359,12 @@ pub async fn send_chat_request_and_parse_stream(
             &app_config,
         )
         .await?;
+    let model = models::chatbot_configurations_models::get_by_chatbot_configuration_id(
+        &mut conn,
+        chatbot_configuration_id,
+    )
+    .await?;
+    let provider = llm_provider::for_model(&model, &app_config)?;
 
     let mut max_iterations_left = 15;
 
@@ -1390,7 +1400,7 @@ pub async fn send_chat_request_and_parse_stream(
                 break 'outer;
             }
 
-            let lines = match make_request_and_create_stream(chat_request.clone(), &app_config).await {
+            let lines = match make_request_and_create_stream(chat_request.clone(), provider.as_ref()).await {
                 Ok(val) => val,
                 Err(error) => {
                     if check_error_should_terminate_stream(error.error_type()) {
@@ -1613,6 +1623,74 @@ pub async fn send_chat_request_and_parse_stream(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm_provider::{ScriptedProvider, ScriptedResponse};
+
+    fn request() -> LLMRequest {
+        LLMRequest {
+            input: vec![],
+            model: "scripted".to_string(),
+            tools: vec![],
+            tool_choice: None,
+            parallel_tool_calls: None,
+            max_output_tokens: None,
+            text: None,
+            params: LLMRequestParams::GPTNonThinking(NonThinkingParams {
+                temperature: None,
+                top_p: None,
+                frequency_penalty: None,
+                presence_penalty: None,
+            }),
+        }
+    }
+
+    /// Detects the response type of a scripted response and returns the events up to it.
+    fn detect(response: ScriptedResponse) -> Vec<ChatbotResult<String>> {
+        let provider = ScriptedProvider::new([response]);
+        futures::executor::block_on(async {
+            let lines = make_request_and_create_stream(request(), &provider)
+                .await
+                .unwrap();
+            stream_and_detect_response_stream_type(lines)
+                .map(|event| {
+                    event.map(|event| match event {
+                        StreamEvent::ResponseIdStream((
+                            id,
+                            ResponseStreamType::TextResponse(_),
+                        )) => {
+                            format!("text {id}")
+                        }
+                        StreamEvent::ResponseIdStream((id, ResponseStreamType::Toolcall(_))) => {
+                            format!("tool call {id}")
+                        }
+                        _ => "other".to_string(),
+                    })
+                })
+                .collect()
+                .await
+        })
+    }
+
+    #[test]
+    fn scripted_responses_are_parsed_like_azure_responses() {
+        let text = detect(ScriptedResponse::Text("Hello world".to_string()));
+        assert_eq!(text[0].as_ref().unwrap(), "text scripted-response-1");
+
+        let tool_calls = detect(ScriptedResponse::ToolCalls(vec![(
+            "course_structure".to_string(),
+            "{}".to_string(),
+        )]));
+        assert_eq!(
+            tool_calls[0].as_ref().unwrap(),
+            "tool call scripted-response-1"
+        );
+
+        let error = detect(ScriptedResponse::Error("Model not found".to_string()));
+        let error = error[0].as_ref().unwrap_err();
+        assert_eq!(
+            error.azure_source().unwrap().message.as_deref(),
+            Some("Model not found")
+        );
+    }
 
     /// A `response.failed` line carries `error` as an object, not a string. Deserializing it
     /// must succeed so the error can be surfaced instead of crashing the stream parser.
@@ -0,0 +1,408 @@
+/*!
+Searching the course material from this database instead of Azure AI Search.
+
+The Markdown of each public course page is split into chunks at its headings, and each chunk is
+stored in `chatbot_page_chunks` with an embedding vector. A search combines semantic search over the
+embeddings with keyword search (see [`models::chatbot_page_chunks::hybrid_search`]) and reranks the
+combined results so that near-duplicate chunks don't crowd out the rest.
+*/
+
+use async_trait::async_trait;
+use headless_lms_base::config::ApplicationConfiguration;
+use headless_lms_models::chatbot_page_chunks::{ChatbotPageChunkSearchResult, NewChatbotPageChunk};
+use headless_lms_utils::azure_embedding::create_embeddings;
+
+use crate::prelude::*;
+
+/// The length of the vectors in `chatbot_page_chunks.embedding`.
+pub const EMBEDDING_DIMENSIONS: usize = 1536;
+
+/// Chunks longer than this are split further at paragraphs and then at words.
+const MAX_CHUNK_CHARS: usize = 2000;
+
+/// How many results the hybrid search is asked for before reranking.
+const RERANK_CANDIDATES: i64 = 30;
+
+/// How much reranking weighs the relevance of a chunk against its similarity to the chunks that
+/// are already ranked higher. 1.0 ranks by relevance only.
+const RERANK_RELEVANCE_WEIGHT: f32 = 0.7;
+
+/// Turns texts into embedding vectors of [`EMBEDDING_DIMENSIONS`] dimensions.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Returns an embedding for each input, in the same order.
+    async fn embed(&self, inputs: Vec<String>) -> ChatbotResult<Vec<Vec<f32>>>;
+}
+
+/// Creates embeddings with the Azure embedding model that Azure AI Search also uses.
+pub struct AzureEmbedder<'a> {
+    app_config: &'a ApplicationConfiguration,
+}
+
+impl<'a> AzureEmbedder<'a> {
+    pub fn new(app_config: &'a ApplicationConfiguration) -> Self {
+        Self { app_config }
+    }
+}
+
+#[async_trait]
+impl Embedder for AzureEmbedder<'_> {
+    async fn embed(&self, inputs: Vec<String>) -> ChatbotResult<Vec<Vec<f32>>> {
+        Ok(create_embeddings(self.app_config, inputs).await?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownChunk {
+    /// The page title followed by the headings the chunk is under, separated with ` > `.
+    pub context: String,
+    pub content: String,
+}
+
+impl MarkdownChunk {
+    /// The text that the embedding of the chunk is made from.
+    fn embedding_input(&self) -> String {
+        format!("{}\n\n{}", self.context, self.content)
+    }
+}
+
+/// Splits Markdown into chunks at its headings. Sections longer than [`MAX_CHUNK_CHARS`] are split
+/// further. Headings inside code blocks are not treated as headings.
+pub fn chunk_markdown(page_title: &str, markdown: &str) -> Vec<MarkdownChunk> {
+    let mut chunks = Vec::new();
+    // (level, text) of the headings the current line is under
+    let mut headings: Vec<(usize, String)> = Vec::new();
+    let mut section = String::new();
+    let mut in_code_block = false;
+
+    let context = |headings: &[(usize, String)]| {
+        std::iter::once(page_title)
+            .chain(headings.iter().map(|(_, text)| text.as_str()))
+            .collect::<Vec<_>>()
+            .join(" > ")
+    };
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        if let Some((level, text)) = parse_heading(line).filter(|_| !in_code_block) {
+            push_section(&mut chunks, &context(&headings), &section);
+            section.clear();
+            headings.retain(|(l, _)| *l < level);
+            headings.push((level, text.to_string()));
+        } else {
+            section.push_str(line);
+            section.push('\n');
+        }
+    }
+    push_section(&mut chunks, &context(&headings), &section);
+    chunks
+}
+
+/// Returns the level and the text of an ATX heading like `## Text`.
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    let text = rest.trim().trim_end_matches('#').trim_end();
+    (!text.is_empty()).then_some((level, text))
+}
+
+fn push_section(chunks: &mut Vec<MarkdownChunk>, context: &str, section: &str) {
+    let mut current = String::new();
+    for paragraph in section
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+    {
+        if !current.is_empty()
+            && current.chars().count() + paragraph.chars().count() + 2 > MAX_CHUNK_CHARS
+        {
+            chunks.push(MarkdownChunk {
+                context: context.to_string(),
+                content: std::mem::take(&mut current),
+            });
+        }
+        for piece in split_at_words(paragraph, MAX_CHUNK_CHARS) {
+            if !current.is_empty() {
+                if current.chars().count() + piece.chars().count() + 2 > MAX_CHUNK_CHARS {
+                    chunks.push(MarkdownChunk {
+                        context: context.to_string(),
+                        content: std::mem::take(&mut current),
+                    });
+                } else {
+                    current.push_str("\n\n");
+                }
+            }
+            current.push_str(piece);
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(MarkdownChunk {
+            context: context.to_string(),
+            content: current,
+        });
+    }
+}
+
+/// Splits text into pieces of at most `max_chars` characters at whitespace. A single word longer
+/// than that is split in the middle.
+fn split_at_words(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.chars().count() > max_chars {
+        let limit = rest
+            .char_indices()
+            .nth(max_chars)
+            .map(|(i, _)| i)
+            .unwrap_or(rest.len());
+        let split = rest[..limit]
+            .rfind(char::is_whitespace)
+            .filter(|i| *i > 0)
+            .unwrap_or(limit);
+        pieces.push(rest[..split].trim_end());
+        rest = rest[split..].trim_start();
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// Replaces the page's chunks in the index with chunks made from `markdown`. Returns the number
+/// of chunks.
+pub async fn index_page(
+    conn: &mut PgConnection,
+    embedder: &dyn Embedder,
+    page_id: Uuid,
+    page_history_id: Uuid,
+    page_title: &str,
+    markdown: &str,
+) -> ChatbotResult<usize> {
+    let mut chunks = chunk_markdown(page_title, markdown);
+    if chunks.is_empty() {
+        // A page without content is still indexed so that it's not retried until it changes.
+        chunks.push(MarkdownChunk {
+            context: page_title.to_string(),
+            content: page_title.to_string(),
+        });
+    }
+    let inputs = chunks.iter().map(MarkdownChunk::embedding_input).collect();
+    let embeddings = embed_checked(embedder, inputs).await?;
+    let chunk_count = chunks.len();
+    let new_chunks = chunks
+        .into_iter()
+        .zip(embeddings)
+        .map(|(chunk, embedding)| NewChatbotPageChunk {
+            context: chunk.context,
+            content: chunk.content,
+            embedding,
+        })
+        .collect();
+    models::chatbot_page_chunks::replace_for_page(conn, page_id, page_history_id, new_chunks)
+        .await?;
+    Ok(chunk_count)
+}
+
+/// Searches the course material of the course and returns at most `limit` of the best chunks.
+pub async fn search(
+    conn: &mut PgConnection,
+    embedder: &dyn Embedder,
+    course_id: Uuid,
+    query: &str,
+    limit: usize,
+) -> ChatbotResult<Vec<ChatbotPageChunkSearchResult>> {
+    let query_embedding = embed_checked(embedder, vec![query.to_string()])
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| chatbot_err!(EmbeddingError, "No embedding was returned for the query"))?;
+    let candidates = models::chatbot_page_chunks::hybrid_search(
+        conn,
+        course_id,
+        query,
+        query_embedding,
+        RERANK_CANDIDATES,
+    )
+    .await?;
+    Ok(rerank(candidates, limit))
+}
+
+/// Embeds the inputs and checks that the embedder returned one vector of the right size for each.
+pub(crate) async fn embed_checked(
+    embedder: &dyn Embedder,
+    inputs: Vec<String>,
+) -> ChatbotResult<Vec<Vec<f32>>> {
+    let input_count = inputs.len();
+    let embeddings = embedder.embed(inputs).await?;
+    if embeddings.len() != input_count {
+        return Err(chatbot_err!(
+            EmbeddingError,
+            format!(
+                "The embedder returned {} embeddings for {} inputs",
+                embeddings.len(),
+                input_count
+            )
+        ));
+    }
+    if let Some(embedding) = embeddings.iter().find(|e| e.len() != EMBEDDING_DIMENSIONS) {
+        return Err(chatbot_err!(
+            EmbeddingError,
+            format!(
+                "The embedder returned an embedding with {} dimensions, expected {}",
+                embedding.len(),
+                EMBEDDING_DIMENSIONS
+            )
+        ));
+    }
+    Ok(embeddings)
+}
+
+/**
+Picks `limit` of the candidates with maximal marginal relevance.
+
+Chunks are picked one at a time. Each time, the pick is the chunk with the best balance between its
+relevance, which is its hybrid search score relative to the best score, and its difference from the
+chunks picked before it. This way a chunk that repeats what a higher ranked chunk already says, for
+example the same text on two pages, ranks lower than a slightly less relevant chunk with new
+information.
+*/
+pub fn rerank(
+    mut candidates: Vec<ChatbotPageChunkSearchResult>,
+    limit: usize,
+) -> Vec<ChatbotPageChunkSearchResult> {
+    let max_score = candidates
+        .iter()
+        .map(|c| c.score)
+        .fold(f64::MIN, f64::max)
+        .max(f64::MIN_POSITIVE);
+    let mut picked: Vec<ChatbotPageChunkSearchResult> = Vec::new();
+    while picked.len() < limit && !candidates.is_empty() {
+        let marginal_relevance = |candidate: &ChatbotPageChunkSearchResult| {
+            let relevance = (candidate.score / max_score) as f32;
+            let redundancy = picked
+                .iter()
+                .map(|p| cosine_similarity(candidate.embedding.as_slice(), p.embedding.as_slice()))
+                .fold(0.0, f32::max);
+            RERANK_RELEVANCE_WEIGHT * relevance - (1.0 - RERANK_RELEVANCE_WEIGHT) * redundancy
+        };
+        let best = candidates
+            .iter()
+            .map(marginal_relevance)
+            .enumerate()
+            .fold((0, f32::MIN), |best, (i, value)| {
+                if value > best.1 { (i, value) } else { best }
+            })
+            .0;
+        picked.push(candidates.remove(best));
+    }
+    picked
+}
+
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pgvector::Vector;
+
+    #[test]
+    fn chunks_are_split_at_headings_with_the_heading_path_as_context() {
+        let markdown = "Intro text.\n\n# Loops\n\nLoops repeat.\n\n## While\n\nWhile loops check first.\n\n```python\n# not a heading\nwhile True: pass\n```\n\n# Functions\n\nFunctions are reusable.";
+        let chunks = chunk_markdown("Basics", markdown);
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| (c.context.as_str(), c.content.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Basics", "Intro text."),
+                ("Basics > Loops", "Loops repeat."),
+                (
+                    "Basics > Loops > While",
+                    "While loops check first.\n\n```python\n# not a heading\nwhile True: pass\n```"
+                ),
+                ("Basics > Functions", "Functions are reusable."),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_sections_are_split_into_chunks_of_at_most_max_chars() {
+        let paragraph = "word ".repeat(300);
+        let markdown = format!(
+            "# Long\n\n{paragraph}\n\n{paragraph}\n\n{}",
+            "x".repeat(4500)
+        );
+        let chunks = chunk_markdown("Page", &markdown);
+        assert!(chunks.len() > 3);
+        for chunk in &chunks {
+            assert!(chunk.content.chars().count() <= MAX_CHUNK_CHARS);
+            assert_eq!(chunk.context, "Page > Long");
+        }
+        let total_words: usize = chunks
+            .iter()
+            .map(|c| c.content.matches("word").count())
+            .sum();
+        assert_eq!(total_words, 600);
+    }
+
+    #[test]
+    fn headings_need_a_space_after_the_hashes() {
+        assert_eq!(parse_heading("## Title ##"), Some((2, "Title")));
+        assert_eq!(parse_heading("#hashtag"), None);
+        assert_eq!(parse_heading("####### Too deep"), None);
+    }
+
+    fn result(chunk_number: i32, embedding: Vec<f32>, score: f64) -> ChatbotPageChunkSearchResult {
+        ChatbotPageChunkSearchResult {
+            id: Uuid::new_v4(),
+            page_id: Uuid::nil(),
+            chunk_number,
+            context: String::new(),
+            content: String::new(),
+            embedding: Vector::from(embedding),
+            page_title: String::new(),
+            page_url_path: String::new(),
+            course_slug: String::new(),
+            organization_slug: String::new(),
+            score,
+        }
+    }
+
+    #[test]
+    fn reranking_moves_near_duplicates_down() {
+        let candidates = vec![
+            result(0, vec![1.0, 0.0], 0.033),
+            result(1, vec![0.99, 0.01], 0.032),
+            result(2, vec![0.0, 1.0], 0.030),
+        ];
+        let reranked = rerank(candidates, 3);
+        assert_eq!(
+            reranked.iter().map(|r| r.chunk_number).collect::<Vec<_>>(),
+            vec![0, 2, 1]
+        );
+    }
+
+    #[test]
+    fn reranking_returns_at_most_limit_results() {
+        let candidates = (0..5)
+            .map(|i| result(i, vec![1.0, i as f32], 0.03 - f64::from(i) * 0.001))
+            .collect();
+        assert_eq!(rerank(candidates, 2).len(), 2);
+        assert!(rerank(Vec::new(), 2).is_empty());
+    }
+}
@@ -0,0 +1,473 @@
+/*!
+Analytics of what students ask a chatbot, for teachers.
+
+The questions are grouped into topics by the similarity of their embeddings, and each topic is
+shown with its keywords and how many of its questions went unanswered. Like all the figures of the
+analytics, a topic is only shown if the questions in it were asked by at least [`MIN_GROUP_SIZE`]
+distinct users, so that individual students can't be identified from it. For the same reason the
+questions themselves are never shown, and the analytics are only given for whole weeks: otherwise
+a student could be singled out by comparing the results of two time ranges that differ only a
+little.
+*/
+
+use chrono::{Datelike, Days, NaiveDate, NaiveTime};
+use headless_lms_models::{
+    chatbot_analytics::{
+        self, ChatbotAnalyticsQuestion, ChatbotCitedPage, ChatbotTokenUsagePeriod, MIN_GROUP_SIZE,
+        TokenUsagePeriod,
+    },
+    chatbot_configurations::ChatbotConfiguration,
+};
+use headless_lms_utils::strings::truncate_utf8_at_boundary;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
+
+use crate::{
+    builtin_search::{Embedder, cosine_similarity, embed_checked},
+    prelude::*,
+};
+
+/// How many of the latest questions in the time range the topics are made from.
+const MAX_QUESTIONS: i64 = 2000;
+
+/// How similar a question must be to the questions of a topic to be added to it.
+const TOPIC_SIMILARITY_THRESHOLD: f32 = 0.82;
+
+/// How many questions are embedded in one request.
+const EMBEDDING_BATCH_SIZE: usize = 100;
+
+/// Questions are cut to this many bytes before embedding.
+const MAX_EMBEDDED_QUESTION_BYTES: usize = 4000;
+
+const MAX_TOPICS: usize = 20;
+
+const MAX_TOPIC_KEYWORDS: usize = 5;
+
+/// Shorter words are not used as keywords.
+const MIN_KEYWORD_CHARS: usize = 3;
+
+const MAX_CITED_PAGES: i64 = 20;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChatbotAnalytics {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// The smallest number of distinct users a figure is shown for.
+    pub min_group_size: i64,
+    pub question_count: i64,
+    /// Questions the chatbot didn't reply to, for example because the response failed.
+    pub unanswered_question_count: i64,
+    /// Questions whose reply was cut off, for example by a content filter.
+    pub incomplete_answer_count: i64,
+    /// The most common topics of the questions, most asked first. `None` if the questions
+    /// couldn't be embedded.
+    pub topics: Option<Vec<ChatbotQuestionTopic>>,
+    pub cited_pages: Vec<ChatbotCitedPage>,
+    pub daily_token_usage: Vec<ChatbotTokenUsage>,
+    pub weekly_token_usage: Vec<ChatbotTokenUsage>,
+    pub daily_tokens_per_user: i32,
+    pub weekly_tokens_per_user: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChatbotQuestionTopic {
+    /// The words that set the questions of the topic apart from the other questions, most telling
+    /// first. Only words used by at least [`MIN_GROUP_SIZE`] of the users of the topic are included.
+    pub keywords: Vec<String>,
+    pub question_count: i64,
+    pub user_count: i64,
+    pub unanswered_count: i64,
+    pub incomplete_answer_count: i64,
+}
+
+/// The tokens used during a day or a week. The figures are `None` if fewer than
+/// [`MIN_GROUP_SIZE`] users used the chatbot during the period.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChatbotTokenUsage {
+    pub period_start: NaiveDate,
+    pub user_count: Option<i64>,
+    pub total_tokens: Option<i64>,
+    /// How many users used at least their daily or weekly token limit.
+    pub users_at_limit: Option<i64>,
+}
+
+impl From<ChatbotTokenUsagePeriod> for ChatbotTokenUsage {
+    fn from(usage: ChatbotTokenUsagePeriod) -> Self {
+        let shown = usage.user_count >= MIN_GROUP_SIZE;
+        Self {
+            period_start: usage.period_start,
+            user_count: shown.then_some(usage.user_count),
+            total_tokens: shown.then_some(usage.total_tokens),
+            users_at_limit: shown.then_some(usage.users_at_limit),
+        }
+    }
+}
+
+/// Collects the analytics of the chatbot between `start` and `end`, which are first moved to whole
+/// weeks, see [`whole_weeks`]. The questions that don't have an embedding yet are embedded and the
+/// embeddings are saved for the next time.
+pub async fn get_chatbot_analytics(
+    conn: &mut PgConnection,
+    embedder: &dyn Embedder,
+    configuration: &ChatbotConfiguration,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> ChatbotResult<ChatbotAnalytics> {
+    let (start, end) = whole_weeks(start, end);
+    let questions =
+        chatbot_analytics::get_questions(conn, configuration.id, start, end, MAX_QUESTIONS).await?;
+    let question_count = questions.len() as i64;
+    let unanswered_question_count = questions.iter().filter(|q| !q.has_reply).count() as i64;
+    let incomplete_answer_count = questions
+        .iter()
+        .filter(|q| q.reply_is_complete == Some(false))
+        .count() as i64;
+
+    let topics = match embed_questions(conn, embedder, &questions).await {
+        Ok(embeddings) => Some(topics(&questions, &embeddings)),
+        Err(e) => {
+            warn!(
+                chatbot_configuration_id = %configuration.id,
+                "Couldn't embed the chatbot questions for the analytics: {e}"
+            );
+            None
+        }
+    };
+    let cited_pages =
+        chatbot_analytics::get_cited_pages(conn, configuration.id, start, end, MAX_CITED_PAGES)
+            .await?;
+    let daily_token_usage = chatbot_analytics::get_token_usage(
+        conn,
+        configuration.id,
+        start,
+        end,
+        TokenUsagePeriod::Day,
+        configuration.daily_tokens_per_user,
+    )
+    .await?
+    .into_iter()
+    .map(ChatbotTokenUsage::from)
+    .collect();
+    let weekly_token_usage = chatbot_analytics::get_token_usage(
+        conn,
+        configuration.id,
+        start,
+        end,
+        TokenUsagePeriod::Week,
+        configuration.weekly_tokens_per_user,
+    )
+    .await?
+    .into_iter()
+    .map(ChatbotTokenUsage::from)
+    .collect();
+
+    Ok(ChatbotAnalytics {
+        start,
+        end,
+        min_group_size: MIN_GROUP_SIZE,
+        question_count,
+        unanswered_question_count,
+        incomplete_answer_count,
+        topics,
+        cited_pages,
+        daily_token_usage,
+        weekly_token_usage,
+        daily_tokens_per_user: configuration.daily_tokens_per_user,
+        weekly_tokens_per_user: configuration.weekly_tokens_per_user,
+    })
+}
+
+/// Moves the time range to start and end at the start of a week, on Monday at midnight UTC. The
+/// week that is still going on is left out unless the range is within it, so the figures of a time
+/// range don't change as new questions are asked.
+fn whole_weeks(start: DateTime<Utc>, end: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let week_start = |timestamp: DateTime<Utc>| {
+        let date = timestamp.date_naive();
+        (date - Days::new(date.weekday().num_days_from_monday().into()))
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+    };
+    let start = week_start(start);
+    let end = week_start(end).max(start + chrono::Duration::weeks(1));
+    (start, end)
+}
+
+/// Returns the embeddings of the questions in the same order, embedding the ones that don't have
+/// one yet.
+async fn embed_questions(
+    conn: &mut PgConnection,
+    embedder: &dyn Embedder,
+    questions: &[ChatbotAnalyticsQuestion],
+) -> ChatbotResult<Vec<Vec<f32>>> {
+    let mut embeddings: Vec<Option<Vec<f32>>> = questions
+        .iter()
+        .map(|q| q.embedding.as_ref().map(|e| e.to_vec()))
+        .collect();
+    let missing: Vec<usize> = (0..questions.len())
+        .filter(|i| embeddings[*i].is_none())
+        .collect();
+    for batch in missing.chunks(EMBEDDING_BATCH_SIZE) {
+        let inputs = batch
+            .iter()
+            .map(|i| {
+                truncate_utf8_at_boundary(&questions[*i].text, MAX_EMBEDDED_QUESTION_BYTES)
+                    .to_string()
+            })
+            .collect();
+        let new_embeddings = embed_checked(embedder, inputs).await?;
+        chatbot_analytics::insert_message_embeddings(
+            conn,
+            batch
+                .iter()
+                .zip(&new_embeddings)
+                .map(|(i, e)| (questions[*i].message_id, e.clone()))
+                .collect(),
+        )
+        .await?;
+        for (i, embedding) in batch.iter().zip(new_embeddings) {
+            embeddings[*i] = Some(embedding);
+        }
+    }
+    Ok(embeddings.into_iter().flatten().collect())
+}
+
+/// Groups the questions into topics and returns the topics asked by enough users, most asked
+/// first. `embeddings` are the embeddings of `questions` in the same order.
+fn topics(
+    questions: &[ChatbotAnalyticsQuestion],
+    embeddings: &[Vec<f32>],
+) -> Vec<ChatbotQuestionTopic> {
+    let all_questions = questions.iter().collect::<Vec<_>>();
+    let users_by_word = users_by_word(&all_questions);
+    let user_count = distinct_users(&all_questions);
+    let mut topics: Vec<ChatbotQuestionTopic> = cluster(embeddings, TOPIC_SIMILARITY_THRESHOLD)
+        .into_iter()
+        .filter_map(|cluster| {
+            let members: Vec<&ChatbotAnalyticsQuestion> =
+                cluster.members.iter().map(|i| &questions[*i]).collect();
+            let topic_user_count = distinct_users(&members);
+            if topic_user_count < MIN_GROUP_SIZE {
+                return None;
+            }
+            Some(ChatbotQuestionTopic {
+                keywords: keywords(&members, &users_by_word, user_count),
+                question_count: members.len() as i64,
+                user_count: topic_user_count,
+                unanswered_count: members.iter().filter(|q| !q.has_reply).count() as i64,
+                incomplete_answer_count: members
+                    .iter()
+                    .filter(|q| q.reply_is_complete == Some(false))
+                    .count() as i64,
+            })
+        })
+        .collect();
+    topics.sort_by(|a, b| b.question_count.cmp(&a.question_count));
+    topics.truncate(MAX_TOPICS);
+    topics
+}
+
+fn distinct_users(questions: &[&ChatbotAnalyticsQuestion]) -> i64 {
+    questions
+        .iter()
+        .map(|q| q.user_key.as_str())
+        .collect::<HashSet<_>>()
+        .len() as i64
+}
+
+/// The distinct users that used each word in their questions.
+fn users_by_word<'a>(
+    questions: &[&'a ChatbotAnalyticsQuestion],
+) -> HashMap<String, HashSet<&'a str>> {
+    let mut users_by_word: HashMap<String, HashSet<&str>> = HashMap::new();
+    for question in questions {
+        for word in question
+            .text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| word.chars().count() >= MIN_KEYWORD_CHARS)
+        {
+            users_by_word
+                .entry(word.to_lowercase())
+                .or_default()
+                .insert(question.user_key.as_str());
+        }
+    }
+    users_by_word
+}
+
+/// The words that a larger share of the users of the topic used than of all the users, the largest
+/// difference first. A word must be used by at least [`MIN_GROUP_SIZE`] users of the topic, so that
+/// what a single student wrote, like their name, is never shown.
+fn keywords(
+    members: &[&ChatbotAnalyticsQuestion],
+    all_users_by_word: &HashMap<String, HashSet<&str>>,
+    all_user_count: i64,
+) -> Vec<String> {
+    let topic_user_count = distinct_users(members) as f64;
+    let mut keywords = users_by_word(members)
+        .into_iter()
+        .filter(|(_, users)| users.len() as i64 >= MIN_GROUP_SIZE)
+        .filter_map(|(word, users)| {
+            let all_users = all_users_by_word.get(&word).map_or(0, HashSet::len);
+            let score = users.len() as f64 / topic_user_count
+                - all_users as f64 / all_user_count.max(1) as f64;
+            (score > 0.0).then_some((word, score))
+        })
+        .collect::<Vec<_>>();
+    keywords.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    keywords
+        .into_iter()
+        .take(MAX_TOPIC_KEYWORDS)
+        .map(|(word, _)| word)
+        .collect()
+}
+
+#[derive(Debug, PartialEq)]
+struct Cluster {
+    /// Indices of the members.
+    members: Vec<usize>,
+}
+
+/// Groups the vectors greedily: each vector joins the cluster whose center it is most similar to,
+/// or starts a new cluster if no center is at least `threshold` similar.
+fn cluster(embeddings: &[Vec<f32>], threshold: f32) -> Vec<Cluster> {
+    // The sum of the members is used as the center, as only its direction matters.
+    let mut centers: Vec<Vec<f32>> = Vec::new();
+    let mut members: Vec<Vec<usize>> = Vec::new();
+    for (i, embedding) in embeddings.iter().enumerate() {
+        let best = centers
+            .iter()
+            .enumerate()
+            .map(|(c, center)| (c, cosine_similarity(embedding, center)))
+            .filter(|(_, similarity)| *similarity >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((c, _)) => {
+                for (sum, x) in centers[c].iter_mut().zip(embedding) {
+                    *sum += x;
+                }
+                members[c].push(i);
+            }
+            None => {
+                centers.push(embedding.clone());
+                members.push(vec![i]);
+            }
+        }
+    }
+    members
+        .into_iter()
+        .map(|members| Cluster { members })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn question(user: &str, text: &str, has_reply: bool) -> ChatbotAnalyticsQuestion {
+        ChatbotAnalyticsQuestion {
+            message_id: Uuid::new_v4(),
+            user_key: user.to_string(),
+            text: text.to_string(),
+            has_reply,
+            reply_is_complete: has_reply.then_some(true),
+            embedding: None,
+        }
+    }
+
+    #[test]
+    fn similar_vectors_are_clustered_together() {
+        let embeddings = vec![
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, 0.0],
+            vec![0.9, 0.1, 0.0],
+            vec![1.0, 0.05, 0.0],
+        ];
+        let clusters = cluster(&embeddings, 0.9);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].members, vec![0, 2, 3]);
+        assert_eq!(clusters[1].members, vec![1]);
+    }
+
+    #[test]
+    fn topics_of_too_few_users_are_suppressed() {
+        let mut questions = Vec::new();
+        let mut embeddings = Vec::new();
+        // Six questions on one topic by five users, one of them unanswered.
+        for (i, user) in ["a", "b", "c", "d", "e", "e"].iter().enumerate() {
+            questions.push(question(user, &format!("When is the exam {i}?"), i != 0));
+            embeddings.push(vec![1.0, 0.0]);
+        }
+        // Four questions on another topic by four users.
+        for user in ["a", "b", "c", "d"] {
+            questions.push(question(user, "What is recursion?", true));
+            embeddings.push(vec![0.0, 1.0]);
+        }
+        let topics = topics(&questions, &embeddings);
+        assert_eq!(topics.len(), 1);
+        assert_eq!(topics[0].question_count, 6);
+        assert_eq!(topics[0].user_count, 5);
+        assert_eq!(topics[0].unanswered_count, 1);
+    }
+
+    #[test]
+    fn keywords_leave_out_what_few_users_wrote() {
+        let mut questions = Vec::new();
+        let mut embeddings = Vec::new();
+        for user in ["a", "b", "c", "d", "e"] {
+            questions.push(question(user, "When is the exam?", true));
+            embeddings.push(vec![1.0, 0.0]);
+        }
+        questions.push(question(
+            "f",
+            "Hi, I am Maija Meikäläinen, when is the exam?",
+            true,
+        ));
+        embeddings.push(vec![1.0, 0.0]);
+        for user in ["g", "h", "i", "j", "k"] {
+            questions.push(question(user, "What is the recursion?", true));
+            embeddings.push(vec![0.0, 1.0]);
+        }
+        let topics = topics(&questions, &embeddings);
+        // "the" is left out as everyone uses it, and the name as only one user wrote it.
+        let exam_topic = topics.iter().find(|t| t.question_count == 6).unwrap();
+        assert_eq!(exam_topic.keywords, vec!["exam", "when"]);
+        let recursion_topic = topics.iter().find(|t| t.question_count == 5).unwrap();
+        assert_eq!(recursion_topic.keywords, vec!["recursion", "what"]);
+    }
+
+    #[test]
+    fn time_ranges_are_whole_weeks() {
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        // From a Wednesday to a Thursday two weeks later.
+        assert_eq!(
+            whole_weeks(at("2026-10-07T15:00:00Z"), at("2026-10-22T09:00:00Z")),
+            (at("2026-10-05T00:00:00Z"), at("2026-10-19T00:00:00Z"))
+        );
+        // Within a week.
+        assert_eq!(
+            whole_weeks(at("2026-10-20T15:00:00Z"), at("2026-10-22T09:00:00Z")),
+            (at("2026-10-19T00:00:00Z"), at("2026-10-26T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn token_usage_of_too_few_users_is_suppressed() {
+        let period_start = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap();
+        let usage = ChatbotTokenUsage::from(ChatbotTokenUsagePeriod {
+            period_start,
+            user_count: MIN_GROUP_SIZE - 1,
+            total_tokens: 1000,
+            users_at_limit: 1,
+        });
+        assert_eq!(usage.total_tokens, None);
+        assert_eq!(usage.users_at_limit, None);
+        let usage = ChatbotTokenUsage::from(ChatbotTokenUsagePeriod {
+            period_start,
+            user_count: MIN_GROUP_SIZE,
+            total_tokens: 1000,
+            users_at_limit: 1,
+        });
+        assert_eq!(usage.total_tokens, Some(1000));
+    }
+}
@@ -41,6 +41,10 @@ pub enum ChatbotErrorType {
     ContentCleaning,
     AzureRequestBuildError,
     FailedAzureResponse,
+    LLMRequestBuildError,
+    FailedLLMResponse,
+    EmbeddingError,
+    ChatbotEvaluationError,
     SisuDescriptionError,
     ChatbotUtilError,
 }
@@ -0,0 +1,473 @@
+/*!
+Runs chatbot evaluation sets against a chatbot configuration and scores the answers.
+
+Each question of the set is sent to the chatbot in a new conversation, through the same pipeline
+that answers students, so the run measures the configuration's prompt, model and search settings
+as the students would experience them. The answer is then scored:
+
+- Citation precision and recall compare the course pages the answer cited to the pages the
+  question expects (see [`crate::citations::score_citations`]).
+- Groundedness, correctness and the question's rubric are graded from 1 to 5 by the judge model
+  of the `chatbot-evaluation` application task.
+
+The conversations are made in the name of the teacher who started the run and deleted after the
+answer, so they don't show up as the teacher's own conversation with the chatbot.
+*/
+
+use std::collections::HashMap;
+
+use futures::StreamExt;
+use headless_lms_base::config::ApplicationConfiguration;
+use headless_lms_models::{
+    application_task_default_language_models::{self, ApplicationTask, TaskLMSpec},
+    chatbot_configurations,
+    chatbot_conversation_message_messages::{ChatbotConversationMessageMessage, MessageRole},
+    chatbot_conversation_messages::{self, ChatbotConversationMessage, Message},
+    chatbot_conversation_messages_citations, chatbot_conversations,
+    chatbot_evaluations::{
+        self, ChatbotEvaluationQuestion, ChatbotEvaluationRun, NewChatbotEvaluationResult,
+    },
+    courses, organizations, pages,
+};
+use headless_lms_utils::json_schema_types::{JSONType, JsonItem, Schema, SchemaPropertyType};
+use serde::Deserialize;
+use sqlx::PgPool;
+use url::Url;
+
+use crate::{
+    azure_chatbot::{
+        ChatbotChatStreamEvent, ChatbotUserContext, InputItem, LLMRequest, LLMRequestParams,
+        LLMRequestResponseFormatParam, NonThinkingParams, RequestTextOptions, ThinkingParams,
+        send_chat_request_and_parse_stream,
+    },
+    citations::{cited_page_ids, cited_urls, score_citations},
+    content_cleaner::calculate_safe_token_limit,
+    llm_provider,
+    llm_utils::{
+        APIInputMessage, MessageContent, estimate_tokens, make_blocking_llm_request,
+        model_is_thinking, parse_text_completion,
+    },
+    prelude::*,
+};
+
+const JUDGE_SYSTEM_PROMPT: &str = r#"You are grading the answer a teaching assistant chatbot gave to a student's question on a course. You are given the question, a correct answer written by the teacher, the answer of the chatbot and the course material the chatbot retrieved while answering.
+
+Grade the answer on each criterion with an integer from 1 to 5:
+- groundedness_score: How well the claims of the answer are supported by the retrieved course material. 5 means every claim is supported, 1 means the answer is mostly unsupported or contradicts the material. If no course material was retrieved, give 1.
+- correctness_score: How well the answer agrees with the teacher's answer. 5 means it contains the same facts and nothing that contradicts them, 1 means it is wrong. The wording doesn't matter and the chatbot may give more detail than the teacher.
+- rubric_score: How well the answer meets the rubric. If there is no rubric, give 1.
+
+In feedback, explain the grades briefly, pointing out the unsupported or incorrect parts of the answer.
+"#;
+
+/// The judge model's grades, in the shape of the JSON schema in [`judge_answer`].
+#[derive(Debug, Deserialize, PartialEq)]
+struct JudgeVerdict {
+    groundedness_score: i32,
+    correctness_score: i32,
+    rubric_score: i32,
+    feedback: String,
+}
+
+/// What a run needs to know about the course to ask and score the questions.
+struct EvaluationContext {
+    user_context: ChatbotUserContext,
+    user_id: Uuid,
+    chatbot_configuration_id: Uuid,
+    initial_message: String,
+    /// The path of the course's front page, such as `/org/uh-cs/courses/intro`.
+    course_path: String,
+    page_ids_by_url_path: HashMap<String, Uuid>,
+    judge: TaskLMSpec,
+}
+
+/// The chatbot's answer to a question and what it was based on.
+struct ChatbotAnswer {
+    text: String,
+    cited_urls: Vec<Url>,
+    /// The outputs of the tools the chatbot used and the contents of the documents it cited.
+    sources: Vec<String>,
+}
+
+/// Asks the questions of the run that have not been answered yet and saves the scored answers.
+/// A question that fails is saved with the error, and the run continues with the next one.
+pub async fn run_evaluation(
+    pool: &PgPool,
+    app_config: &ApplicationConfiguration,
+    run: &ChatbotEvaluationRun,
+) -> ChatbotResult<()> {
+    let mut conn = pool.acquire().await?;
+    let set = chatbot_evaluations::get_set_by_id(&mut conn, run.evaluation_set_id).await?;
+    let configuration =
+        chatbot_configurations::get_by_id(&mut conn, run.chatbot_configuration_id).await?;
+    if configuration.course_id != Some(set.course_id) {
+        return Err(chatbot_err!(
+            ChatbotEvaluationError,
+            "The chatbot configuration doesn't belong to the course of the evaluation set."
+                .to_string()
+        ));
+    }
+    let course = courses::get_course(&mut conn, set.course_id).await?;
+    let organization = organizations::get_organization(&mut conn, course.organization_id).await?;
+    let page_ids_by_url_path = pages::get_pages_by_course_id(&mut conn, course.id)
+        .await?
+        .into_iter()
+        .map(|p| (p.url_path, p.id))
+        .collect();
+    let judge = application_task_default_language_models::get_for_task(
+        &mut conn,
+        ApplicationTask::ChatbotEvaluation,
+    )
+    .await?;
+    let context = EvaluationContext {
+        user_context: ChatbotUserContext {
+            user_id: Some(run.created_by_user_id),
+            course_id: Some(course.id),
+            course_name: Some(course.name),
+        },
+        user_id: run.created_by_user_id,
+        chatbot_configuration_id: configuration.id,
+        initial_message: configuration.initial_message,
+        course_path: format!("/org/{}/courses/{}", organization.slug, course.slug),
+        page_ids_by_url_path,
+        judge,
+    };
+
+    let questions = chatbot_evaluations::get_unanswered_questions(&mut conn, run.id).await?;
+    info!(
+        run_id = %run.id,
+        "Evaluating {} questions of the chatbot evaluation run",
+        questions.len()
+    );
+    for question in questions {
+        let result = evaluate_question(pool, app_config, &context, &question).await?;
+        chatbot_evaluations::insert_result(&mut conn, run.id, question.id, &result).await?;
+    }
+    Ok(())
+}
+
+/// Asks the question in a new conversation and scores the answer. Only fails if the conversation
+/// can't be created or deleted. Other errors are returned in the result.
+async fn evaluate_question(
+    pool: &PgPool,
+    app_config: &ApplicationConfiguration,
+    context: &EvaluationContext,
+    question: &ChatbotEvaluationQuestion,
+) -> ChatbotResult<NewChatbotEvaluationResult> {
+    let mut conn = pool.acquire().await?;
+    let conversation = chatbot_conversations::create_for_user_and_configuration(
+        &mut conn,
+        PKeyPolicy::Generate,
+        Some(context.user_id),
+        None,
+        context.chatbot_configuration_id,
+    )
+    .await?;
+    chatbot_conversation_messages::insert_for_conversation_user_and_configuration(
+        &mut conn,
+        ChatbotConversationMessage {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            conversation_id: conversation.id,
+            order_number: 0,
+            message: Message::Text(ChatbotConversationMessageMessage {
+                text: context.initial_message.clone(),
+                message_role: MessageRole::Assistant,
+                message_is_complete: true,
+                used_tokens: estimate_tokens(&context.initial_message),
+                response_id: Some("initial-message".to_string()),
+                ..Default::default()
+            }),
+        },
+        Some(context.user_id),
+        None,
+        context.chatbot_configuration_id,
+    )
+    .await?;
+
+    let answer = ask_question(pool, app_config, context, conversation.id, question).await;
+    let result = match answer {
+        Ok(answer) => score_answer(app_config, context, question, answer).await,
+        Err(e) => Err(e),
+    };
+    chatbot_conversations::delete(&mut conn, conversation.id).await?;
+
+    let mut result = result.unwrap_or_else(|e| {
+        warn!(
+            question_id = %question.id,
+            "Failed to evaluate a chatbot evaluation question: {e}"
+        );
+        NewChatbotEvaluationResult {
+            error_message: Some(e.message().to_string()),
+            ..Default::default()
+        }
+    });
+    result.conversation_id = Some(conversation.id);
+    Ok(result)
+}
+
+/// Sends the question to the chatbot and waits for the whole answer.
+async fn ask_question(
+    pool: &PgPool,
+    app_config: &ApplicationConfiguration,
+    context: &EvaluationContext,
+    conversation_id: Uuid,
+    question: &ChatbotEvaluationQuestion,
+) -> ChatbotResult<ChatbotAnswer> {
+    let mut stream = send_chat_request_and_parse_stream(
+        pool.clone(),
+        app_config,
+        context.chatbot_configuration_id,
+        conversation_id,
+        &question.question,
+        context.user_context.clone(),
+    )
+    .await?;
+    let mut response = Vec::new();
+    while let Some(bytes) = stream.next().await {
+        response.extend_from_slice(&bytes?);
+    }
+    drop(stream);
+    if let Some(error) = stream_error(&String::from_utf8_lossy(&response)) {
+        return Err(chatbot_err!(ChatbotEvaluationError, error));
+    }
+
+    let mut conn = pool.acquire().await?;
+    let messages =
+        chatbot_conversation_messages::get_by_conversation_id(&mut conn, conversation_id).await?;
+    let citations =
+        chatbot_conversation_messages_citations::get_by_conversation_id(&mut conn, conversation_id)
+            .await?;
+    let text = messages
+        .iter()
+        .rev()
+        .find_map(|m| match &m.message {
+            Message::Text(t) if t.message_role == MessageRole::Assistant => Some(t.text.clone()),
+            _ => None,
+        })
+        .filter(|text| !text.trim().is_empty())
+        .ok_or_else(|| {
+            chatbot_err!(
+                ChatbotEvaluationError,
+                "The chatbot didn't answer the question.".to_string()
+            )
+        })?;
+    let mut sources: Vec<String> = messages
+        .into_iter()
+        .filter_map(|m| match m.message {
+            Message::ToolOutput(o) => Some(o.output),
+            _ => None,
+        })
+        .collect();
+    sources.extend(
+        citations
+            .iter()
+            .map(|c| format!("{}\n{}", c.title, c.content)),
+    );
+    Ok(ChatbotAnswer {
+        cited_urls: cited_urls(&text, &citations),
+        text,
+        sources,
+    })
+}
+
+/// Returns the message of the first error event in the chatbot's response stream, if any.
+fn stream_error(response: &str) -> Option<String> {
+    response
+        .lines()
+        .filter_map(|line| serde_json::from_str::<ChatbotChatStreamEvent>(line).ok())
+        .find_map(|event| match event {
+            ChatbotChatStreamEvent::Error(e) => Some(match e.details {
+                Some(details) => format!("{}: {}", e.message, details),
+                None => e.message,
+            }),
+            _ => None,
+        })
+}
+
+async fn score_answer(
+    app_config: &ApplicationConfiguration,
+    context: &EvaluationContext,
+    question: &ChatbotEvaluationQuestion,
+    answer: ChatbotAnswer,
+) -> ChatbotResult<NewChatbotEvaluationResult> {
+    let cited_page_ids = cited_page_ids(
+        &answer.cited_urls,
+        &context.course_path,
+        &context.page_ids_by_url_path,
+    );
+    let citation_scores = score_citations(&question.expected_page_ids, &cited_page_ids);
+    let verdict = judge_answer(app_config, &context.judge, question, &answer).await?;
+    Ok(NewChatbotEvaluationResult {
+        conversation_id: None,
+        cited_page_ids,
+        citation_precision: citation_scores.precision,
+        citation_recall: citation_scores.recall,
+        groundedness_score: (!answer.sources.is_empty())
+            .then_some(verdict.groundedness_score.clamp(1, 5)),
+        correctness_score: Some(verdict.correctness_score.clamp(1, 5)),
+        rubric_score: question
+            .rubric
+            .as_ref()
+            .map(|_| verdict.rubric_score.clamp(1, 5)),
+        judge_feedback: Some(verdict.feedback),
+        answer: Some(answer.text),
+        error_message: None,
+    })
+}
+
+/// Asks the judge model to grade the answer.
+async fn judge_answer(
+    app_config: &ApplicationConfiguration,
+    task_lm: &TaskLMSpec,
+    question: &ChatbotEvaluationQuestion,
+    answer: &ChatbotAnswer,
+) -> ChatbotResult<JudgeVerdict> {
+    let mut prompt = format!(
+        "Question:\n{}\n\nTeacher's answer:\n{}\n\nRubric:\n{}\n\nChatbot's answer:\n{}\n\nRetrieved course material:\n",
+        question.question,
+        question.expected_answer,
+        question.rubric.as_deref().unwrap_or("No rubric."),
+        answer.text
+    );
+    // The sources are left out from the end if they don't fit in the context.
+    let token_budget =
+        calculate_safe_token_limit(task_lm.context_size, task_lm.context_utilization);
+    let mut used_tokens = estimate_tokens(JUDGE_SYSTEM_PROMPT) + estimate_tokens(&prompt);
+    if answer.sources.is_empty() {
+        prompt.push_str("None.");
+    }
+    for source in &answer.sources {
+        let source = format!("{source}\n\n---\n\n");
+        used_tokens += estimate_tokens(&source);
+        if used_tokens > token_budget {
+            break;
+        }
+        prompt.push_str(&source);
+    }
+
+    let (params, max_output_tokens) = if model_is_thinking(task_lm.model_type) {
+        (
+            LLMRequestParams::GPTThinking(ThinkingParams { reasoning: None }),
+            Some(7000),
+        )
+    } else {
+        (
+            LLMRequestParams::GPTNonThinking(NonThinkingParams {
+                temperature: None,
+                top_p: None,
+                frequency_penalty: None,
+                presence_penalty: None,
+            }),
+            Some(2000),
+        )
+    };
+    let score_property = |description: &str| {
+        SchemaPropertyType::Item(JsonItem {
+            type_field: JSONType::Integer,
+            description: Some(description.to_string()),
+        })
+    };
+    let chat_request = LLMRequest {
+        input: vec![
+            APIInputMessage {
+                message_type: InputItem::Message {
+                    role: MessageRole::System,
+                    content: MessageContent::Text(JUDGE_SYSTEM_PROMPT.to_string()),
+                },
+            },
+            APIInputMessage {
+                message_type: InputItem::Message {
+                    role: MessageRole::User,
+                    content: MessageContent::Text(prompt),
+                },
+            },
+        ],
+        model: task_lm.model.to_owned(),
+        max_output_tokens,
+        tools: vec![],
+        tool_choice: None,
+        parallel_tool_calls: None,
+        params,
+        text: Some(RequestTextOptions {
+            verbosity: None,
+            format: Some(LLMRequestResponseFormatParam {
+                format_type: JSONType::JsonSchema,
+                name: "ChatbotEvaluationJudgeVerdict".to_string(),
+                schema: Schema {
+                    type_field: JSONType::Object,
+                    description: None,
+                    properties: HashMap::from([
+                        (
+                            "groundedness_score".to_string(),
+                            score_property("From 1 to 5"),
+                        ),
+                        (
+                            "correctness_score".to_string(),
+                            score_property("From 1 to 5"),
+                        ),
+                        ("rubric_score".to_string(), score_property("From 1 to 5")),
+                        (
+                            "feedback".to_string(),
+                            SchemaPropertyType::Item(JsonItem {
+                                type_field: JSONType::String,
+                                description: None,
+                            }),
+                        ),
+                    ]),
+                    required: vec![
+                        "groundedness_score".to_string(),
+                        "correctness_score".to_string(),
+                        "rubric_score".to_string(),
+                        "feedback".to_string(),
+                    ],
+                    additional_properties: false,
+                },
+                strict: true,
+            }),
+        }),
+    };
+
+    let provider = llm_provider::for_task(task_lm, app_config)?;
+    let completion = make_blocking_llm_request(chat_request, provider.as_ref()).await?;
+    let completion_content = parse_text_completion(completion)?;
+    serde_json::from_str(&completion_content).map_err(|_| {
+        chatbot_err!(
+            ChatbotEvaluationError,
+            "The judge LLM returned an incorrectly formatted response.".to_string()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_errors_are_found_from_the_response() {
+        let response = r#"{"type":"Delta","data":{"text":"Hel","message_id":"00000000-0000-0000-0000-000000000000"}}
+{"type":"Error","data":{"message":"Rate limited","details":null}}
+"#;
+        assert_eq!(stream_error(response), Some("Rate limited".to_string()));
+        assert_eq!(stream_error("{\"type\":\"Done\"}\n"), None);
+    }
+
+    #[test]
+    fn judge_verdicts_are_parsed() {
+        let verdict: JudgeVerdict = serde_json::from_str(
+            r#"{"groundedness_score":4,"correctness_score":5,"rubric_score":1,"feedback":"Good."}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            verdict,
+            JudgeVerdict {
+                groundedness_score: 4,
+                correctness_score: 5,
+                rubric_score: 1,
+                feedback: "Good.".to_string(),
+            }
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use headless_lms_base::config::ApplicationConfiguration;
+use headless_lms_utils::json_schema_types::{JSONType, JsonItem, SchemaPropertyType};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    azure_chatbot::ChatbotUserContext,
+    builtin_search::{AzureEmbedder, search},
+    chatbot_tools::{
+        AzureLLMFunctionToolDefinition, ChatbotTool, LLMToolParamType, LLMToolParams, LLMToolType,
+        ToolProperties,
+    },
+    prelude::{BackendError, ChatbotError, ChatbotErrorType, ChatbotResult, chatbot_err},
+};
+
+/// How many chunks a search returns.
+const SEARCH_RESULT_COUNT: usize = 8;
+
+pub type CourseMaterialSearchTool =
+    ToolProperties<CourseMaterialSearchState, CourseMaterialSearchArguments>;
+
+pub struct CourseMaterialSearchState {
+    results: Vec<CourseMaterialSearchResult>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CourseMaterialSearchArguments {
+    query: String,
+}
+
+struct CourseMaterialSearchResult {
+    title: String,
+    url: String,
+    page_id: Uuid,
+    context: String,
+    content: String,
+}
+
+/// Search the material of the course the chatbot is on from the built-in search index.
+impl ChatbotTool for CourseMaterialSearchTool {
+    type State = CourseMaterialSearchState;
+    type Arguments = CourseMaterialSearchArguments;
+
+    fn parse_arguments(args_string: String) -> ChatbotResult<Self::Arguments> {
+        serde_json::from_str::<Self::Arguments>(&args_string).map_err(|e| {
+            chatbot_err!(
+                InvalidToolArguments,
+                format!("Couldn't parse tool arguments. Arguments: {args_string}"),
+                e
+            )
+        })
+    }
+
+    async fn from_db_and_arguments(
+        conn: &mut PgConnection,
+        app_config: &ApplicationConfiguration,
+        arguments: Self::Arguments,
+        user_context: &ChatbotUserContext,
+    ) -> ChatbotResult<Self> {
+        let Some(course_id) = user_context.course_id else {
+            return Err(chatbot_err!(
+                ToolUseError,
+                "Course id is missing.".to_string()
+            ));
+        };
+        let base_url = Url::parse(&app_config.base_url)?;
+        let embedder = AzureEmbedder::new(app_config);
+        let results = search(
+            conn,
+            &embedder,
+            course_id,
+            &arguments.query,
+            SEARCH_RESULT_COUNT,
+        )
+        .await?
+        .into_iter()
+        .map(|r| {
+            let mut url = base_url.clone();
+            url.set_path(&format!(
+                "/org/{}/courses/{}{}",
+                r.organization_slug, r.course_slug, r.page_url_path
+            ));
+            CourseMaterialSearchResult {
+                title: r.page_title,
+                url: url.to_string(),
+                page_id: r.page_id,
+                context: r.context,
+                content: r.content,
+            }
+        })
+        .collect();
+
+        Ok(CourseMaterialSearchTool {
+            state: CourseMaterialSearchState { results },
+            arguments,
+        })
+    }
+
+    fn output(&self) -> String {
+        if self.state.results.is_empty() {
+            return "No results were found from the course material.".to_string();
+        }
+        self.state
+            .results
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                format!(
+                    "Result {}\nTitle: {}\nURL: {}\nPage id: {}\nSection: {}\n\n{}",
+                    i + 1,
+                    r.title,
+                    r.url,
+                    r.page_id,
+                    r.context,
+                    r.content
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n---\n\n")
+    }
+
+    fn output_description_instructions(&self) -> Option<String> {
+        Some("Ground your answer in the search results. When you use information from a result, link to the page it is from with a Markdown link to its URL. If you need the full content of a page, use the document_lookup tool with the page id. If the results don't answer the question, say so instead of guessing.".to_string())
+    }
+
+    fn get_arguments(&self) -> &Self::Arguments {
+        &self.arguments
+    }
+
+    fn get_tool_definition() -> AzureLLMFunctionToolDefinition {
+        AzureLLMFunctionToolDefinition {
+            tool_type: LLMToolType::Function,
+            name: "course_material_search".to_string(),
+            description: "Search the material of the course the user is on. Returns the most relevant sections of the course pages with the title, URL and id of each page. The search matches both the meaning and the words of the query.".to_string(),
+            parameters: LLMToolParams {
+                tool_type: LLMToolParamType::Object,
+                properties: HashMap::from([(
+                    "query".to_string(),
+                    SchemaPropertyType::Item(JsonItem {
+                        type_field: JSONType::String,
+                        description: Some("What to find from the course material. The search is already limited to this course, so don't include the course name.".to_string()),
+                    }),
+                )]),
+                required: vec!["query".to_string()],
+                additional_properties: false,
+            },
+            strict: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_are_listed_with_their_pages() {
+        let tool = CourseMaterialSearchTool {
+            state: CourseMaterialSearchState {
+                results: vec![CourseMaterialSearchResult {
+                    title: "Loops".to_string(),
+                    url: "https://example.com/org/uh-cs/courses/intro/chapter-1/loops".to_string(),
+                    page_id: Uuid::nil(),
+                    context: "Loops > While".to_string(),
+                    content: "While loops check the condition first.".to_string(),
+                }],
+            },
+            arguments: CourseMaterialSearchArguments {
+                query: "while loop".to_string(),
+            },
+        };
+        assert_eq!(
+            tool.output(),
+            "Result 1\nTitle: Loops\nURL: https://example.com/org/uh-cs/courses/intro/chapter-1/loops\nPage id: 00000000-0000-0000-0000-000000000000\nSection: Loops > While\n\nWhile loops check the condition first."
+        );
+
+        let empty = CourseMaterialSearchTool {
+            state: CourseMaterialSearchState {
+                results: Vec::new(),
+            },
+            arguments: CourseMaterialSearchArguments {
+                query: "recursion".to_string(),
+            },
+        };
+        assert_eq!(
+            empty.output(),
+            "No results were found from the course material."
+        );
+    }
+}
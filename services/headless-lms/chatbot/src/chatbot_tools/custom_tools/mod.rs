@@ -1,4 +1,6 @@
 pub mod course_finder;
+pub mod course_material_search;
 pub mod course_progress;
 pub mod course_structure;
 pub mod document_lookup;
+pub mod exercise_tutoring;
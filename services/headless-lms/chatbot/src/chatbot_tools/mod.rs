@@ -5,8 +5,9 @@ use crate::{
     chatbot_error::chatbot_err,
     chatbot_tools::{
         custom_tools::{
-            course_finder::CourseFinderTool, course_progress::CourseProgressTool,
-            course_structure::CourseStructureTool, document_lookup::DocumentLookupTool,
+            course_finder::CourseFinderTool, course_material_search::CourseMaterialSearchTool,
+            course_progress::CourseProgressTool, course_structure::CourseStructureTool,
+            document_lookup::DocumentLookupTool, exercise_tutoring::ExerciseTutoringTool,
         },
         provider_tools::azure_ai_search::AzureAISearchToolDefinition,
     },
@@ -138,6 +139,7 @@ pub fn get_chatbot_tool_definitions() -> Vec<AzureLLMToolDefinition> {
         AzureLLMToolDefinition::Function(DocumentLookupTool::get_tool_definition()),
         AzureLLMToolDefinition::Function(CourseStructureTool::get_tool_definition()),
         AzureLLMToolDefinition::Function(CourseFinderTool::get_tool_definition()),
+        AzureLLMToolDefinition::Function(ExerciseTutoringTool::get_tool_definition()),
     ]
 }
 
@@ -177,6 +179,18 @@ pub async fn call_chatbot_tool(
             let args = tool.get_arguments();
             (serde_json::to_string(args)?, tool.output())
         }
+        "exercise_tutoring" => {
+            let tool = ExerciseTutoringTool::new(conn, app_config, fn_args, user_context).await?;
+            let args = tool.get_arguments();
+            // The instructions carry the course's AI policy, so they're always included.
+            (serde_json::to_string(args)?, tool.get_tool_output())
+        }
+        "course_material_search" => {
+            let tool =
+                CourseMaterialSearchTool::new(conn, app_config, fn_args, user_context).await?;
+            let args = tool.get_arguments();
+            (serde_json::to_string(args)?, tool.output())
+        }
         _ => {
             return Err(chatbot_err!(
                 InvalidToolName,
@@ -1,4 +1,7 @@
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
 use secrecy::SecretString;
 
@@ -203,3 +206,149 @@ async fn save_documents_mock(
     }
     Ok(res)
 }
+
+/// The URLs an answer cites: the documents of its citations and the targets of the Markdown links
+/// in its text. Links that are not absolute URLs are left out.
+pub fn cited_urls(answer: &str, citations: &[ChatbotConversationMessageCitation]) -> Vec<Url> {
+    let mut urls: Vec<Url> = citations
+        .iter()
+        .filter_map(|c| Url::parse(&c.document_url).ok())
+        .collect();
+    let mut rest = answer;
+    while let Some(start) = rest.find("](") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        // A link may have a title after the URL: [text](url "title")
+        let target = rest[..end].split_whitespace().next().unwrap_or_default();
+        if let Ok(url) = Url::parse(target.trim_start_matches('<').trim_end_matches('>')) {
+            urls.push(url);
+        }
+        rest = &rest[end..];
+    }
+    urls
+}
+
+/// Maps cited URLs to the pages of a course by their path. `course_path` is the path of the
+/// course's front page, for example `/org/uh-cs/courses/intro`. URLs that are not pages of the
+/// course are left out.
+pub fn cited_page_ids(
+    urls: &[Url],
+    course_path: &str,
+    page_ids_by_url_path: &HashMap<String, Uuid>,
+) -> Vec<Uuid> {
+    let course_path = course_path.trim_end_matches('/');
+    let mut page_ids = Vec::new();
+    for url in urls {
+        let path = url_decode(url.path()).unwrap_or_else(|_| url.path().to_string());
+        let Some(page_path) = path.strip_prefix(course_path) else {
+            continue;
+        };
+        let page_path = match page_path.trim_end_matches('/') {
+            "" => "/",
+            p if p.starts_with('/') => p,
+            // The path continued with another course's slug.
+            _ => continue,
+        };
+        if let Some(page_id) = page_ids_by_url_path.get(page_path)
+            && !page_ids.contains(page_id)
+        {
+            page_ids.push(*page_id);
+        }
+    }
+    page_ids
+}
+
+/// How well the cited pages match the pages the answer was expected to cite.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CitationScores {
+    /// The share of the cited pages that were expected. `None` if nothing was cited.
+    pub precision: Option<f32>,
+    /// The share of the expected pages that were cited. `None` if nothing was expected.
+    pub recall: Option<f32>,
+}
+
+pub fn score_citations(expected_page_ids: &[Uuid], cited_page_ids: &[Uuid]) -> CitationScores {
+    let expected: HashSet<&Uuid> = expected_page_ids.iter().collect();
+    let cited: HashSet<&Uuid> = cited_page_ids.iter().collect();
+    let correct = expected.intersection(&cited).count() as f32;
+    CitationScores {
+        precision: (!cited.is_empty()).then_some(correct / cited.len() as f32),
+        recall: (!expected.is_empty()).then_some(correct / expected.len() as f32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cited_urls_include_citations_and_markdown_links() {
+        let citations = vec![ChatbotConversationMessageCitation {
+            document_url: "https://example.com/org/uh-cs/courses/intro/chapter-1".to_string(),
+            ..Default::default()
+        }];
+        let answer = "See [loops](https://example.com/org/uh-cs/courses/intro/chapter-1/loops \"Loops\") and [this](chapter-2).";
+        let urls: Vec<String> = cited_urls(answer, &citations)
+            .into_iter()
+            .map(|u| u.to_string())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/org/uh-cs/courses/intro/chapter-1",
+                "https://example.com/org/uh-cs/courses/intro/chapter-1/loops"
+            ]
+        );
+    }
+
+    #[test]
+    fn cited_urls_are_mapped_to_the_course_pages() {
+        let front_page = Uuid::new_v4();
+        let loops = Uuid::new_v4();
+        let pages = HashMap::from([
+            ("/".to_string(), front_page),
+            ("/chapter-1/loops".to_string(), loops),
+        ]);
+        let urls = [
+            "https://example.com/org/uh-cs/courses/intro/chapter-1/loops/#while",
+            "https://example.com/org/uh-cs/courses/intro",
+            "https://example.com/org/uh-cs/courses/intro-2/chapter-1/loops",
+            "https://example.com/org/uh-cs/courses/intro/chapter-1/loops",
+        ]
+        .map(|u| Url::parse(u).unwrap());
+        assert_eq!(
+            cited_page_ids(&urls, "/org/uh-cs/courses/intro", &pages),
+            vec![loops, front_page]
+        );
+    }
+
+    #[test]
+    fn citations_are_scored_against_the_expected_pages() {
+        let a = Uuid::new_v4();
+        let b = Uuid::new_v4();
+        let c = Uuid::new_v4();
+        assert_eq!(
+            score_citations(&[a, b], &[a, c, c]),
+            CitationScores {
+                precision: Some(0.5),
+                recall: Some(0.5)
+            }
+        );
+        assert_eq!(
+            score_citations(&[], &[]),
+            CitationScores {
+                precision: None,
+                recall: None
+            }
+        );
+        assert_eq!(
+            score_citations(&[a], &[]),
+            CitationScores {
+                precision: None,
+                recall: Some(0.0)
+            }
+        );
+    }
+}
@@ -7,6 +7,7 @@ use crate::{
     },
     chatbot_error::chatbot_err,
     content_cleaner::calculate_safe_token_limit,
+    llm_provider,
     llm_utils::{
         APIInputMessage, MessageContent, estimate_tokens, make_blocking_llm_request,
         model_is_thinking, parse_text_completion,
@@ -287,7 +288,8 @@ pub async fn generate_paragraph_suggestions(
         }),
     };
 
-    let completion = make_blocking_llm_request(chat_request, app_config).await?;
+    let provider = llm_provider::for_task(&task_lm, app_config)?;
+    let completion = make_blocking_llm_request(chat_request, provider.as_ref()).await?;
 
     let completion_content: &String = &parse_text_completion(completion)?;
     let response: CmsParagraphSuggestionResponse = serde_json::from_str(completion_content)
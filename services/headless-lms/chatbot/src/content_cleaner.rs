@@ -1,5 +1,6 @@
 use crate::azure_chatbot::{InputItem, LLMRequest, LLMRequestParams, NonThinkingParams};
 
+use crate::llm_provider;
 use crate::llm_utils::{
     APIInputMessage, MessageContent, estimate_tokens, get_params_for_model,
     make_blocking_llm_request, parse_text_completion,
@@ -367,7 +368,8 @@ async fn process_block_chunk(
         estimate_tokens(chunk)
     );
 
-    let completion = match make_blocking_llm_request(llm_base_request, app_config).await {
+    let provider = llm_provider::for_task(task_lm, app_config)?;
+    let completion = match make_blocking_llm_request(llm_base_request, provider.as_ref()).await {
         Ok(completion) => completion,
         Err(e) => {
             error!("Failed to process chunk: {}", e);
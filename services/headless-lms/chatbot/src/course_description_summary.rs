@@ -10,6 +10,7 @@ use crate::{
         RequestTextOptions, ThinkingParams,
     },
     chatbot_error::chatbot_err,
+    llm_provider,
     llm_utils::{
         APIInputMessage, MessageContent, make_blocking_llm_request, model_is_thinking,
         parse_text_completion,
@@ -209,7 +210,8 @@ pub async fn generate_description(
         }),
     };
 
-    let completion = make_blocking_llm_request(chat_request, app_config).await?;
+    let provider = llm_provider::for_task(&task_lm, app_config)?;
+    let completion = make_blocking_llm_request(chat_request, provider.as_ref()).await?;
 
     let completion_content: &String = &parse_text_completion(completion)?;
 
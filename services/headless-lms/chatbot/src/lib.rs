@@ -6,12 +6,16 @@ pub mod azure_datasources;
 pub mod azure_search_index;
 pub mod azure_search_indexer;
 pub mod azure_skillset;
+pub mod builtin_search;
+pub mod chatbot_analytics;
 pub mod chatbot_error;
+pub mod chatbot_evaluation;
 pub mod chatbot_tools;
 pub mod citations;
 pub mod cms_ai_suggestion;
 pub mod content_cleaner;
 pub mod course_description_summary;
+pub mod llm_provider;
 pub mod llm_utils;
 pub mod message_suggestion;
 pub mod search_filter;
@@ -0,0 +1,142 @@
+//! Models deployed in Azure AI, called through the Azure OpenAI Responses API.
+
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
+use headless_lms_base::config::ApplicationConfiguration;
+use reqwest::Response;
+use secrecy::SecretString;
+use tracing::{debug, error, instrument, trace};
+use url::Url;
+
+use super::{LlmByteStream, LlmProvider};
+use crate::{
+    azure_chatbot::{LLMRequest, Response as AzureResponse, ResponseError},
+    llm_utils::{AzureCompletionRequest, LLMResponse, build_llm_headers},
+    prelude::*,
+};
+
+pub struct AzureProvider {
+    endpoint: Url,
+    api_key: SecretString,
+}
+
+impl AzureProvider {
+    /// Uses the Azure chatbot configuration from the environment.
+    pub fn from_app_config(app_config: &ApplicationConfiguration) -> ChatbotResult<Self> {
+        let azure_config = app_config.azure_configuration.as_ref().ok_or_else(|| {
+            error!("Azure configuration missing");
+            chatbot_err!(
+                AzureRequestBuildError,
+                "Azure configuration is missing from the application configuration"
+            )
+        })?;
+
+        let chatbot_config = azure_config.chatbot_config.as_ref().ok_or_else(|| {
+            error!("Chatbot configuration missing");
+            chatbot_err!(
+                AzureRequestBuildError,
+                "Chatbot configuration is missing from the Azure configuration"
+            )
+        })?;
+
+        Ok(Self {
+            endpoint: chatbot_config.responses_endpoint()?,
+            api_key: chatbot_config.api_key.clone(),
+        })
+    }
+
+    #[instrument(skip(self, chat_request), fields(
+        num_messages = chat_request.input.len(),
+        endpoint = %self.endpoint
+    ))]
+    async fn send(&self, chat_request: LLMRequest, stream: bool) -> ChatbotResult<Response> {
+        trace!("Base request: {:?}", chat_request);
+        let request = AzureCompletionRequest {
+            base: chat_request,
+            stream,
+        };
+
+        let headers = build_llm_headers(&self.api_key)?;
+        debug!("Sending request to LLM endpoint: {}", self.endpoint);
+
+        let response = REQWEST_CLIENT
+            .post(self.endpoint.clone())
+            .headers(headers)
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AzureProvider {
+    async fn create_response(&self, request: LLMRequest) -> ChatbotResult<LLMResponse> {
+        let response = self.send(request, false).await?;
+        trace!("Processing successful LLM response");
+        let completion: LLMResponse = response.json().await?;
+        debug!(
+            "Successfully processed LLM response with {} choices",
+            completion.output.len()
+        );
+        Ok(completion)
+    }
+
+    async fn stream_response(&self, request: LLMRequest) -> ChatbotResult<LlmByteStream> {
+        let response = self.send(request, true).await?;
+        debug!("Successfully initiated streaming response");
+        Ok(response
+            .bytes_stream()
+            .map_err(std::io::Error::other)
+            .boxed())
+    }
+}
+
+/// Builds an error from a failed response.
+async fn error_from_response(response: Response) -> ChatbotError {
+    let status = response.status();
+    let error_text = match response.text().await {
+        Ok(text) => text,
+        Err(err) => return err.into(),
+    };
+    error!(
+        status = %status,
+        error = %error_text,
+        "Error calling LLM API"
+    );
+    // Azure can return a JSON Response, so parse it and take the error from it.
+    match serde_json::from_str::<AzureResponse>(&error_text) {
+        Ok(response) => {
+            let azure_error: Option<ResponseError> = response.error;
+            // Format the error message to be minimal and add the Azure source.
+            let mut error = chatbot_err!(
+                FailedAzureResponse,
+                format!(
+                    "Error calling LLM API: Status: {}. Error: {}",
+                    status,
+                    &azure_error
+                        .as_ref()
+                        .and_then(|e| e.code.to_owned())
+                        .or_else(|| azure_error.as_ref().and_then(|e| e.error_type.to_owned()))
+                        .unwrap_or(error_text)
+                )
+            );
+            if let Some(e) = azure_error {
+                error.add_azure_source(e);
+            };
+            error
+        }
+        // If Azure returned data in some other shape, just show the unparsed text.
+        Err(_) => chatbot_err!(
+            FailedAzureResponse,
+            format!(
+                "Error calling LLM API: Status: {}. Error: {}",
+                status, &error_text
+            )
+        ),
+    }
+}
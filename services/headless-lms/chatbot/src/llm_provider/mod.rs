@@ -0,0 +1,164 @@
+/*!
+Backends that LLM requests are sent to.
+
+The rest of the crate builds requests as [`LLMRequest`]s in the shape of the Azure OpenAI Responses
+API and parses streamed responses as Responses API server-sent events. A provider translates
+between that and its own API, so which provider serves a model only matters here. The provider is
+selected by the `provider` of the model in `chatbot_configurations_models`.
+*/
+
+pub mod azure;
+pub mod openai_compatible;
+pub mod scripted;
+
+use std::env;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use headless_lms_base::config::ApplicationConfiguration;
+use headless_lms_models::{
+    application_task_default_language_models::TaskLMSpec,
+    chatbot_configurations_models::{ChatbotConfigurationModel, ModelProvider},
+};
+use secrecy::SecretString;
+use serde_json::json;
+
+use crate::{
+    azure_chatbot::{LLMRequest, OutputItem, ResponseError},
+    llm_utils::LLMResponse,
+    prelude::*,
+};
+
+pub use azure::AzureProvider;
+pub use openai_compatible::OpenAICompatibleProvider;
+pub use scripted::{ScriptedProvider, ScriptedResponse};
+
+/// Streamed response body in the Responses API server-sent event format.
+pub type LlmByteStream = BoxStream<'static, Result<Bytes, std::io::Error>>;
+
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Sends the request and waits for the whole response.
+    async fn create_response(&self, request: LLMRequest) -> ChatbotResult<LLMResponse>;
+
+    /// Sends the request and returns the response as Responses API server-sent events.
+    async fn stream_response(&self, request: LLMRequest) -> ChatbotResult<LlmByteStream>;
+}
+
+/// Returns the provider that serves the model.
+pub fn for_model(
+    model: &ChatbotConfigurationModel,
+    app_config: &ApplicationConfiguration,
+) -> ChatbotResult<Box<dyn LlmProvider>> {
+    build(
+        model.provider,
+        model.api_base_url.as_deref(),
+        model.api_key_env_var.as_deref(),
+        app_config,
+    )
+}
+
+/// Returns the provider that serves the default model of an application task.
+pub fn for_task(
+    task_lm: &TaskLMSpec,
+    app_config: &ApplicationConfiguration,
+) -> ChatbotResult<Box<dyn LlmProvider>> {
+    build(
+        task_lm.provider,
+        task_lm.api_base_url.as_deref(),
+        task_lm.api_key_env_var.as_deref(),
+        app_config,
+    )
+}
+
+fn build(
+    provider: ModelProvider,
+    api_base_url: Option<&str>,
+    api_key_env_var: Option<&str>,
+    app_config: &ApplicationConfiguration,
+) -> ChatbotResult<Box<dyn LlmProvider>> {
+    match provider {
+        ModelProvider::Azure => Ok(Box::new(AzureProvider::from_app_config(app_config)?)),
+        ModelProvider::OpenAICompatible => {
+            let api_base_url = api_base_url.ok_or_else(|| {
+                chatbot_err!(
+                    LLMRequestBuildError,
+                    "The model has no API base URL configured"
+                )
+            })?;
+            // The key is read on every request so that it can be rotated without a restart.
+            let api_key = api_key_env_var
+                .map(|name| {
+                    env::var(name)
+                        .map(|key| SecretString::new(key.into()))
+                        .map_err(|_| {
+                            let message = format!(
+                                "The environment variable {name} for the model's API key is not set"
+                            );
+                            chatbot_err!(LLMRequestBuildError, message)
+                        })
+                })
+                .transpose()?;
+            Ok(Box::new(OpenAICompatibleProvider::new(
+                api_base_url,
+                api_key,
+            )?))
+        }
+    }
+}
+
+/// Formats a server-sent event the way the Responses API streams them.
+fn sse_event(event_type: &str, mut data: serde_json::Value) -> String {
+    if let serde_json::Value::Object(map) = &mut data {
+        map.insert("type".to_string(), json!(event_type));
+    }
+    format!("event: {event_type}\ndata: {data}\n\n")
+}
+
+fn response_created_event(response_id: &str) -> String {
+    sse_event(
+        "response.created",
+        json!({ "response": { "id": response_id } }),
+    )
+}
+
+fn output_text_delta_event(delta: &str) -> String {
+    sse_event("response.output_text.delta", json!({ "delta": delta }))
+}
+
+fn function_call_arguments_delta_event(delta: &str) -> String {
+    sse_event(
+        "response.function_call_arguments.delta",
+        json!({ "delta": delta }),
+    )
+}
+
+fn output_item_done_event(item: &OutputItem) -> String {
+    sse_event("response.output_item.done", json!({ "item": item }))
+}
+
+fn response_completed_event(response_id: &str) -> String {
+    sse_event(
+        "response.completed",
+        json!({ "response": { "id": response_id } }),
+    )
+}
+
+fn response_incomplete_event(response_id: &str, reason: &str) -> String {
+    sse_event(
+        "response.incomplete",
+        json!({
+            "response": { "id": response_id },
+            "incomplete_response": {
+                "id": response_id,
+                "incomplete_details": { "reason": reason },
+                "content_filters": [],
+            },
+        }),
+    )
+}
+
+fn error_event(error: &ResponseError) -> String {
+    sse_event("error", json!({ "error": error }))
+}
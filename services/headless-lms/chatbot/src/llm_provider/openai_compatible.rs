@@ -0,0 +1,816 @@
+/*!
+Models served from an OpenAI-compatible Chat Completions API, such as vLLM, llama.cpp or Ollama.
+
+These servers don't implement the Responses API consistently, so requests are translated to Chat
+Completions requests, and streamed chunks are translated back to the Responses API events that
+the chatbot parses. Azure AI Search and reasoning summaries are only available through Azure.
+*/
+
+use std::collections::BTreeMap;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
+use headless_lms_models::{
+    chatbot_configurations::ReasoningEffortLevel,
+    chatbot_conversation_message_messages::MessageRole,
+};
+use headless_lms_utils::json_schema_types::Schema;
+use reqwest::Response;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncBufReadExt;
+use tokio_stream::wrappers::LinesStream;
+use tokio_util::io::StreamReader;
+use tracing::{debug, error, instrument, trace, warn};
+use url::Url;
+
+use super::{
+    LlmByteStream, LlmProvider, error_event, function_call_arguments_delta_event,
+    output_item_done_event, output_text_delta_event, response_completed_event,
+    response_created_event, response_incomplete_event,
+};
+use crate::{
+    azure_chatbot::{
+        InputItem, LLMRequest, LLMRequestParams, LLMToolChoice, OutputItem, ResponseError,
+    },
+    chatbot_tools::{AzureLLMToolDefinition, LLMToolParams, LLMToolType},
+    llm_utils::{APIInputMessage, APIOutputMessage, LLMResponse, MessageContent},
+    prelude::*,
+};
+
+pub struct OpenAICompatibleProvider {
+    endpoint: Url,
+    api_key: Option<SecretString>,
+}
+
+impl OpenAICompatibleProvider {
+    /// `api_base_url` is the URL that the API paths are relative to, for example
+    /// `http://localhost:11434/v1/`.
+    pub fn new(api_base_url: &str, api_key: Option<SecretString>) -> ChatbotResult<Self> {
+        // Without the trailing slash, join would replace the last segment of the path.
+        let api_base_url = if api_base_url.ends_with('/') {
+            api_base_url.to_string()
+        } else {
+            format!("{api_base_url}/")
+        };
+        Ok(Self {
+            endpoint: Url::parse(&api_base_url)?.join("chat/completions")?,
+            api_key,
+        })
+    }
+
+    #[instrument(skip(self, chat_request), fields(
+        num_messages = chat_request.input.len(),
+        endpoint = %self.endpoint
+    ))]
+    async fn send(&self, chat_request: LLMRequest, stream: bool) -> ChatbotResult<Response> {
+        let request = ChatCompletionRequest::from_llm_request(chat_request, stream)?;
+        trace!("Chat completion request: {:?}", request);
+        debug!("Sending request to LLM endpoint: {}", self.endpoint);
+
+        let mut builder = REQWEST_CLIENT.post(self.endpoint.clone()).json(&request);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key.expose_secret());
+        }
+        let response = builder.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await?;
+            error!(
+                status = %status,
+                error = %error_text,
+                "Error calling LLM API"
+            );
+            let api_error = serde_json::from_str::<ChatErrorBody>(&error_text)
+                .ok()
+                .map(|body| response_error(&body.error));
+            let mut error = chatbot_err!(
+                FailedLLMResponse,
+                format!(
+                    "Error calling LLM API: Status: {}. Error: {}",
+                    status,
+                    api_error
+                        .as_ref()
+                        .and_then(|e| e.message.to_owned())
+                        .unwrap_or(error_text)
+                )
+            );
+            if let Some(e) = api_error {
+                error.add_azure_source(e);
+            }
+            return Err(error);
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAICompatibleProvider {
+    async fn create_response(&self, request: LLMRequest) -> ChatbotResult<LLMResponse> {
+        let response = self.send(request, false).await?;
+        let completion: ChatCompletion = response.json().await?;
+        completion.into_llm_response()
+    }
+
+    async fn stream_response(&self, request: LLMRequest) -> ChatbotResult<LlmByteStream> {
+        let response = self.send(request, true).await?;
+        debug!("Successfully initiated streaming response");
+        let mut lines = LinesStream::new(
+            StreamReader::new(response.bytes_stream().map_err(std::io::Error::other)).lines(),
+        );
+        let mut translator = StreamTranslator::default();
+        let stream = async_stream::try_stream! {
+            while let Some(line) = lines.next().await {
+                let events = translator
+                    .translate_line(&line?)
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
+                if !events.is_empty() {
+                    yield Bytes::from(events);
+                }
+            }
+        };
+        Ok(stream.boxed())
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct ChatCompletionRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ChatTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<LLMToolChoice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parallel_tool_calls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reasoning_effort: Option<ReasoningEffortLevel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ChatResponseFormat>,
+    stream: bool,
+}
+
+impl ChatCompletionRequest {
+    fn from_llm_request(request: LLMRequest, stream: bool) -> ChatbotResult<Self> {
+        let tools = request
+            .tools
+            .into_iter()
+            .map(|tool| match tool {
+                AzureLLMToolDefinition::Function(function) => Ok(ChatTool {
+                    tool_type: function.tool_type,
+                    function: ChatFunction {
+                        name: function.name,
+                        description: function.description,
+                        parameters: function.parameters,
+                        strict: function.strict,
+                    },
+                }),
+                AzureLLMToolDefinition::Search(_) => Err(chatbot_err!(
+                    LLMRequestBuildError,
+                    "Azure AI Search is only available for models served from Azure"
+                )),
+            })
+            .collect::<ChatbotResult<Vec<_>>>()?;
+        let response_format =
+            request
+                .text
+                .and_then(|text| text.format)
+                .map(|format| ChatResponseFormat {
+                    format_type: "json_schema",
+                    json_schema: ChatJsonSchema {
+                        name: format.name,
+                        schema: format.schema,
+                        strict: format.strict,
+                    },
+                });
+        let mut res = Self {
+            model: request.model,
+            messages: chat_messages(request.input),
+            // Some servers reject tool options in requests without tools.
+            tool_choice: request.tool_choice.filter(|_| !tools.is_empty()),
+            parallel_tool_calls: request.parallel_tool_calls.filter(|_| !tools.is_empty()),
+            tools,
+            max_tokens: request.max_output_tokens,
+            temperature: None,
+            top_p: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            reasoning_effort: None,
+            response_format,
+            stream,
+        };
+        match request.params {
+            LLMRequestParams::GPTNonThinking(params) => {
+                res.temperature = params.temperature;
+                res.top_p = params.top_p;
+                res.frequency_penalty = params.frequency_penalty;
+                res.presence_penalty = params.presence_penalty;
+            }
+            LLMRequestParams::GPTThinking(params) => {
+                res.reasoning_effort = params.reasoning.map(|r| r.effort);
+            }
+            LLMRequestParams::Mistral(_) => {}
+        }
+        Ok(res)
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ChatMessage {
+    role: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ChatToolCall>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ChatToolCall {
+    id: String,
+    #[serde(rename = "type")]
+    call_type: LLMToolType,
+    function: ChatFunctionCall,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ChatFunctionCall {
+    name: String,
+    arguments: String,
+}
+
+#[derive(Serialize, Debug)]
+struct ChatTool {
+    #[serde(rename = "type")]
+    tool_type: LLMToolType,
+    function: ChatFunction,
+}
+
+#[derive(Serialize, Debug)]
+struct ChatFunction {
+    name: String,
+    description: String,
+    parameters: LLMToolParams,
+    strict: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct ChatResponseFormat {
+    #[serde(rename = "type")]
+    format_type: &'static str,
+    json_schema: ChatJsonSchema,
+}
+
+#[derive(Serialize, Debug)]
+struct ChatJsonSchema {
+    name: String,
+    schema: Schema,
+    strict: bool,
+}
+
+fn chat_role(role: MessageRole) -> &'static str {
+    match role {
+        MessageRole::Assistant => "assistant",
+        MessageRole::User => "user",
+        // Not all servers support the developer role.
+        MessageRole::Developer | MessageRole::System => "system",
+    }
+}
+
+fn chat_messages(input: Vec<APIInputMessage>) -> Vec<ChatMessage> {
+    let mut messages: Vec<ChatMessage> = Vec::new();
+    for message in input {
+        match message.message_type {
+            InputItem::Message { role, content } => messages.push(ChatMessage {
+                role: chat_role(role),
+                content: Some(content.get_content_text()),
+                tool_calls: vec![],
+                tool_call_id: None,
+            }),
+            InputItem::FunctionCall {
+                call_id,
+                tool_name,
+                arguments,
+            } => {
+                let call = ChatToolCall {
+                    id: call_id,
+                    call_type: LLMToolType::Function,
+                    function: ChatFunctionCall {
+                        name: tool_name,
+                        arguments,
+                    },
+                };
+                // Calls made in the same response go in the same assistant message.
+                match messages.last_mut() {
+                    Some(last) if !last.tool_calls.is_empty() => last.tool_calls.push(call),
+                    _ => messages.push(ChatMessage {
+                        role: "assistant",
+                        content: None,
+                        tool_calls: vec![call],
+                        tool_call_id: None,
+                    }),
+                }
+            }
+            InputItem::FunctionCallOutput { call_id, output } => messages.push(ChatMessage {
+                role: "tool",
+                content: Some(output),
+                tool_calls: vec![],
+                tool_call_id: Some(call_id),
+            }),
+            // Reasoning items can only be passed back to the Responses API.
+            InputItem::Reasoning { .. } => {}
+        }
+    }
+    messages
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletion {
+    id: Option<String>,
+    choices: Vec<ChatCompletionChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionChoice {
+    message: ChatCompletionMessage,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionMessage {
+    content: Option<String>,
+    tool_calls: Option<Vec<ChatToolCallPart>>,
+}
+
+/// A tool call, or in a stream, a part of one.
+#[derive(Deserialize, Debug)]
+struct ChatToolCallPart {
+    #[serde(default)]
+    index: usize,
+    id: Option<String>,
+    function: Option<ChatFunctionCallPart>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatFunctionCallPart {
+    name: Option<String>,
+    arguments: Option<String>,
+}
+
+impl ChatCompletion {
+    fn into_llm_response(self) -> ChatbotResult<LLMResponse> {
+        let response_id = self.id.unwrap_or_else(new_response_id);
+        let choice =
+            self.choices.into_iter().next().ok_or_else(|| {
+                chatbot_err!(FailedLLMResponse, "The LLM API returned no choices")
+            })?;
+        let mut output = Vec::new();
+        if let Some(content) = choice.message.content.filter(|c| !c.trim().is_empty()) {
+            output.push(APIOutputMessage {
+                message_type: OutputItem::Message {
+                    response_id: response_id.clone(),
+                    role: MessageRole::Assistant,
+                    content: MessageContent::Text(content),
+                    phase: None,
+                },
+            });
+        }
+        for (index, call) in choice
+            .message
+            .tool_calls
+            .unwrap_or_default()
+            .into_iter()
+            .enumerate()
+        {
+            let function = call.function.ok_or_else(|| {
+                chatbot_err!(
+                    FailedLLMResponse,
+                    "The LLM API returned a tool call without a function"
+                )
+            })?;
+            output.push(APIOutputMessage {
+                message_type: OutputItem::FunctionCall {
+                    response_id: response_id.clone(),
+                    call_id: call.id.unwrap_or_else(|| format!("call_{index}")),
+                    tool_name: function.name.unwrap_or_default(),
+                    arguments: function.arguments.unwrap_or_default(),
+                },
+            });
+        }
+        Ok(LLMResponse {
+            id: response_id,
+            output,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionChunk {
+    id: Option<String>,
+    #[serde(default)]
+    choices: Vec<ChatCompletionChunkChoice>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionChunkChoice {
+    delta: Option<ChatCompletionChunkDelta>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionChunkDelta {
+    content: Option<String>,
+    tool_calls: Option<Vec<ChatToolCallPart>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatErrorBody {
+    error: serde_json::Value,
+}
+
+/// Servers differ in the shape of their errors, e.g. llama.cpp uses numeric codes.
+fn response_error(value: &serde_json::Value) -> ResponseError {
+    let field = |name: &str| {
+        value.get(name).and_then(|v| match v {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        })
+    };
+    ResponseError {
+        code: field("code"),
+        message: field("message").or_else(|| value.as_str().map(str::to_string)),
+        error_type: field("type"),
+        param: field("param"),
+    }
+}
+
+fn new_response_id() -> String {
+    format!("chatcmpl-{}", Uuid::new_v4())
+}
+
+/// Translates a streamed chat completion to Responses API events, line by line.
+#[derive(Default)]
+struct StreamTranslator {
+    response_id: Option<String>,
+    /// Whitespace that the model sent before anything else. It is held back because local models
+    /// often send some before calling a tool, and once text is streamed to the user the response
+    /// can no longer turn into tool calls.
+    leading_whitespace: String,
+    text_started: bool,
+    tool_calls: BTreeMap<usize, PendingToolCall>,
+    finished: bool,
+}
+
+#[derive(Default)]
+struct PendingToolCall {
+    call_id: String,
+    name: String,
+    arguments: String,
+}
+
+impl StreamTranslator {
+    /// Returns the events for the line, which may be none.
+    fn translate_line(&mut self, line: &str) -> ChatbotResult<String> {
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            return Ok(String::new());
+        };
+        if self.finished {
+            return Ok(String::new());
+        }
+        let mut events = String::new();
+        if data == "[DONE]" {
+            // Some servers end the stream without giving a finish reason.
+            let response_id = self.start_response(None, &mut events);
+            events.push_str(&self.finish(&response_id, "stop"));
+            return Ok(events);
+        }
+        let chunk = serde_json::from_str::<ChatCompletionChunk>(data).inspect_err(|e| {
+            error!(raw_line = %data, error = %e, "Failed to deserialize streamed chat completion chunk");
+        })?;
+        if let Some(error) = chunk.error {
+            self.finished = true;
+            return Ok(error_event(&response_error(&error)));
+        }
+
+        let response_id = self.start_response(chunk.id, &mut events);
+        let Some(choice) = chunk.choices.into_iter().next() else {
+            return Ok(events);
+        };
+        if let Some(delta) = choice.delta {
+            if let Some(content) = delta.content.filter(|c| !c.is_empty()) {
+                if !self.tool_calls.is_empty() {
+                    warn!("Ignoring text that the LLM sent after calling tools");
+                } else if !self.text_started && content.trim().is_empty() {
+                    self.leading_whitespace.push_str(&content);
+                } else {
+                    self.text_started = true;
+                    let text = std::mem::take(&mut self.leading_whitespace) + &content;
+                    events.push_str(&output_text_delta_event(&text));
+                }
+            }
+            for part in delta.tool_calls.unwrap_or_default() {
+                let call = self.tool_calls.entry(part.index).or_default();
+                if let Some(id) = part.id {
+                    call.call_id = id;
+                }
+                if let Some(function) = part.function {
+                    call.name.push_str(&function.name.unwrap_or_default());
+                    let arguments = function.arguments.unwrap_or_default();
+                    call.arguments.push_str(&arguments);
+                    events.push_str(&function_call_arguments_delta_event(&arguments));
+                }
+            }
+        }
+        if let Some(reason) = choice.finish_reason {
+            events.push_str(&self.finish(&response_id, &reason));
+        }
+        Ok(events)
+    }
+
+    /// Returns the ID of the response, starting the response if it has not been started yet.
+    fn start_response(&mut self, chunk_id: Option<String>, events: &mut String) -> String {
+        match &self.response_id {
+            Some(id) => id.clone(),
+            None => {
+                let id = chunk_id
+                    .filter(|id| !id.is_empty())
+                    .unwrap_or_else(new_response_id);
+                events.push_str(&response_created_event(&id));
+                self.response_id = Some(id.clone());
+                id
+            }
+        }
+    }
+
+    fn finish(&mut self, response_id: &str, finish_reason: &str) -> String {
+        self.finished = true;
+        let mut events = String::new();
+        if self.tool_calls.is_empty() && !self.text_started {
+            // An empty answer is still a text response.
+            events.push_str(&output_text_delta_event(&std::mem::take(
+                &mut self.leading_whitespace,
+            )));
+        }
+        for (index, call) in std::mem::take(&mut self.tool_calls) {
+            events.push_str(&output_item_done_event(&OutputItem::FunctionCall {
+                response_id: response_id.to_string(),
+                call_id: if call.call_id.is_empty() {
+                    format!("call_{index}")
+                } else {
+                    call.call_id
+                },
+                tool_name: call.name,
+                arguments: call.arguments,
+            }));
+        }
+        match finish_reason {
+            "length" => {
+                events.push_str(&response_incomplete_event(response_id, "max_output_tokens"))
+            }
+            "content_filter" => {
+                events.push_str(&response_incomplete_event(response_id, "content_filter"))
+            }
+            _ => events.push_str(&response_completed_event(response_id)),
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::azure_chatbot::{NonThinkingParams, ResponseOutput};
+
+    fn translate(lines: &[&str]) -> Vec<(String, ResponseOutput)> {
+        let mut translator = StreamTranslator::default();
+        let events = lines
+            .iter()
+            .map(|line| translator.translate_line(line).unwrap())
+            .collect::<String>();
+        events
+            .split("\n\n")
+            .filter(|event| !event.is_empty())
+            .map(|event| {
+                let (event_type, data) = event.split_once('\n').unwrap();
+                (
+                    event_type.trim_start_matches("event: ").to_string(),
+                    serde_json::from_str(data.trim_start_matches("data: ")).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn translates_request() {
+        let request = LLMRequest {
+            input: vec![
+                APIInputMessage {
+                    message_type: InputItem::Message {
+                        role: MessageRole::System,
+                        content: MessageContent::Text("Be nice".to_string()),
+                    },
+                },
+                APIInputMessage {
+                    message_type: InputItem::FunctionCall {
+                        call_id: "a".to_string(),
+                        tool_name: "course_structure".to_string(),
+                        arguments: "{}".to_string(),
+                    },
+                },
+                APIInputMessage {
+                    message_type: InputItem::FunctionCall {
+                        call_id: "b".to_string(),
+                        tool_name: "course_progress".to_string(),
+                        arguments: "{}".to_string(),
+                    },
+                },
+                APIInputMessage {
+                    message_type: InputItem::FunctionCallOutput {
+                        call_id: "a".to_string(),
+                        output: "Chapter 1".to_string(),
+                    },
+                },
+                APIInputMessage {
+                    message_type: InputItem::Reasoning {
+                        id: "r".to_string(),
+                        summary: vec![],
+                    },
+                },
+            ],
+            model: "qwen3".to_string(),
+            tools: vec![],
+            tool_choice: Some(LLMToolChoice::Auto),
+            parallel_tool_calls: Some(true),
+            max_output_tokens: Some(100),
+            text: None,
+            params: LLMRequestParams::GPTNonThinking(NonThinkingParams {
+                temperature: Some(0.5),
+                top_p: None,
+                frequency_penalty: None,
+                presence_penalty: None,
+            }),
+        };
+        let request =
+            serde_json::to_value(ChatCompletionRequest::from_llm_request(request, true).unwrap())
+                .unwrap();
+        assert_eq!(
+            request,
+            json!({
+                "model": "qwen3",
+                "messages": [
+                    { "role": "system", "content": "Be nice" },
+                    {
+                        "role": "assistant",
+                        "tool_calls": [
+                            { "id": "a", "type": "function", "function": { "name": "course_structure", "arguments": "{}" } },
+                            { "id": "b", "type": "function", "function": { "name": "course_progress", "arguments": "{}" } },
+                        ],
+                    },
+                    { "role": "tool", "content": "Chapter 1", "tool_call_id": "a" },
+                ],
+                "max_tokens": 100,
+                "temperature": 0.5,
+                "stream": true,
+            })
+        );
+    }
+
+    #[test]
+    fn translates_text_stream() {
+        let events = translate(&[
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"role":"assistant","content":"\n"}}]}"#,
+            "",
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":"Hello"}}]}"#,
+            r#"data: {"id":"chatcmpl-1","choices":[{"index":0,"delta":{"content":" world"},"finish_reason":"stop"}]}"#,
+            "data: [DONE]",
+        ]);
+        let types = events.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                "response.created",
+                "response.output_text.delta",
+                "response.output_text.delta",
+                "response.completed"
+            ]
+        );
+        assert_eq!(
+            events[0].1.response.as_ref().unwrap().id.as_deref(),
+            Some("chatcmpl-1")
+        );
+        assert_eq!(events[1].1.delta.as_deref(), Some("\nHello"));
+        assert_eq!(events[2].1.delta.as_deref(), Some(" world"));
+    }
+
+    #[test]
+    fn translates_tool_call_stream() {
+        let events = translate(&[
+            r#"data: {"id":"chatcmpl-2","choices":[{"index":0,"delta":{"content":" "}}]}"#,
+            r#"data: {"id":"chatcmpl-2","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_x","type":"function","function":{"name":"course_structure","arguments":""}}]}}]}"#,
+            r#"data: {"id":"chatcmpl-2","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"a\":"}}]}}]}"#,
+            r#"data: {"id":"chatcmpl-2","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"1}"}}]},"finish_reason":"tool_calls"}]}"#,
+        ]);
+        let types = events.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                "response.created",
+                "response.function_call_arguments.delta",
+                "response.function_call_arguments.delta",
+                "response.function_call_arguments.delta",
+                "response.output_item.done",
+                "response.completed"
+            ]
+        );
+        match events[4].1.item.as_ref().unwrap() {
+            OutputItem::FunctionCall {
+                response_id,
+                call_id,
+                tool_name,
+                arguments,
+            } => {
+                assert_eq!(response_id, "chatcmpl-2");
+                assert_eq!(call_id, "call_x");
+                assert_eq!(tool_name, "course_structure");
+                assert_eq!(arguments, r#"{"a":1}"#);
+            }
+            other => panic!("expected FunctionCall, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn finishes_stream_without_finish_reason() {
+        let events = translate(&[
+            r#"data: {"id":"chatcmpl-4","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_y","type":"function","function":{"name":"course_structure","arguments":"{}"}}]}}]}"#,
+            "data: [DONE]",
+            "data: [DONE]",
+        ]);
+        let types = events.iter().map(|(t, _)| t.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                "response.created",
+                "response.function_call_arguments.delta",
+                "response.output_item.done",
+                "response.completed"
+            ]
+        );
+        assert_eq!(
+            events[3].1.response.as_ref().unwrap().id.as_deref(),
+            Some("chatcmpl-4")
+        );
+    }
+
+    #[test]
+    fn translates_stream_errors() {
+        let events = translate(&[
+            r#"data: {"error":{"code":500,"message":"Context size has been exceeded.","type":"server_error"}}"#,
+        ]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, "error");
+        let error = events[0].1.error.as_ref().unwrap();
+        assert_eq!(error.code.as_deref(), Some("500"));
+        assert_eq!(
+            error.message.as_deref(),
+            Some("Context size has been exceeded.")
+        );
+    }
+
+    #[test]
+    fn translates_completion() {
+        let completion: ChatCompletion = serde_json::from_value(json!({
+            "id": "chatcmpl-3",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "Hi!", "tool_calls": null },
+                "finish_reason": "stop",
+            }],
+        }))
+        .unwrap();
+        let response = completion.into_llm_response().unwrap();
+        assert_eq!(response.id, "chatcmpl-3");
+        assert_eq!(response.output.len(), 1);
+        match &response.output[0].message_type {
+            OutputItem::Message { content, .. } => {
+                assert_eq!(content.clone().get_content_text(), "Hi!")
+            }
+            other => panic!("expected Message, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! A deterministic fake provider for tests.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use headless_lms_models::chatbot_conversation_message_messages::MessageRole;
+
+use super::{
+    LlmByteStream, LlmProvider, error_event, function_call_arguments_delta_event,
+    output_item_done_event, output_text_delta_event, response_completed_event,
+    response_created_event,
+};
+use crate::{
+    azure_chatbot::{LLMRequest, OutputItem, ResponseError},
+    llm_utils::{APIOutputMessage, LLMResponse, MessageContent},
+    prelude::*,
+};
+
+/// What the fake model does for one request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptedResponse {
+    /// Answers with the text. It is streamed one word at a time.
+    Text(String),
+    /// Calls tools. The pairs are the tool names and the arguments as JSON.
+    ToolCalls(Vec<(String, String)>),
+    /// The API fails with the message.
+    Error(String),
+}
+
+/// Answers requests with the scripted responses in order and records the requests so that tests
+/// can check what was sent. The ids of the nth response are `scripted-response-n` and
+/// `scripted-call-n-i`.
+#[derive(Default)]
+pub struct ScriptedProvider {
+    responses: Mutex<VecDeque<ScriptedResponse>>,
+    requests: Mutex<Vec<LLMRequest>>,
+}
+
+impl ScriptedProvider {
+    pub fn new(responses: impl IntoIterator<Item = ScriptedResponse>) -> Self {
+        Self {
+            responses: Mutex::new(responses.into_iter().collect()),
+            requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The requests received so far.
+    pub fn requests(&self) -> Vec<LLMRequest> {
+        self.requests
+            .lock()
+            .expect("Scripted provider lock poisoned")
+            .clone()
+    }
+
+    fn next(&self, request: LLMRequest) -> ChatbotResult<(String, ScriptedResponse)> {
+        let mut requests = self
+            .requests
+            .lock()
+            .expect("Scripted provider lock poisoned");
+        requests.push(request);
+        let response_id = format!("scripted-response-{}", requests.len());
+        let response = self
+            .responses
+            .lock()
+            .expect("Scripted provider lock poisoned")
+            .pop_front()
+            .ok_or_else(|| {
+                chatbot_err!(
+                    FailedLLMResponse,
+                    format!("No scripted response left for request {response_id}")
+                )
+            })?;
+        Ok((response_id, response))
+    }
+}
+
+fn tool_call_items(response_id: &str, calls: Vec<(String, String)>) -> Vec<OutputItem> {
+    calls
+        .into_iter()
+        .enumerate()
+        .map(|(i, (tool_name, arguments))| OutputItem::FunctionCall {
+            response_id: response_id.to_string(),
+            call_id: format!("{}-{i}", response_id.replace("response", "call")),
+            tool_name,
+            arguments,
+        })
+        .collect()
+}
+
+#[async_trait]
+impl LlmProvider for ScriptedProvider {
+    async fn create_response(&self, request: LLMRequest) -> ChatbotResult<LLMResponse> {
+        let (response_id, response) = self.next(request)?;
+        let output = match response {
+            ScriptedResponse::Text(text) => vec![OutputItem::Message {
+                response_id: response_id.clone(),
+                role: MessageRole::Assistant,
+                content: MessageContent::Text(text),
+                phase: None,
+            }],
+            ScriptedResponse::ToolCalls(calls) => tool_call_items(&response_id, calls),
+            ScriptedResponse::Error(message) => {
+                return Err(chatbot_err!(FailedLLMResponse, message));
+            }
+        };
+        Ok(LLMResponse {
+            id: response_id,
+            output: output
+                .into_iter()
+                .map(|message_type| APIOutputMessage { message_type })
+                .collect(),
+        })
+    }
+
+    async fn stream_response(&self, request: LLMRequest) -> ChatbotResult<LlmByteStream> {
+        let (response_id, response) = self.next(request)?;
+        let mut events = response_created_event(&response_id);
+        match response {
+            ScriptedResponse::Text(text) => {
+                let mut words = text.split_inclusive(' ').collect::<Vec<_>>();
+                if words.is_empty() {
+                    // An empty answer still needs a delta to be detected as a text response.
+                    words.push("");
+                }
+                for word in words {
+                    events.push_str(&output_text_delta_event(word));
+                }
+                events.push_str(&response_completed_event(&response_id));
+            }
+            ScriptedResponse::ToolCalls(calls) => {
+                for item in tool_call_items(&response_id, calls) {
+                    if let OutputItem::FunctionCall { arguments, .. } = &item {
+                        events.push_str(&function_call_arguments_delta_event(arguments));
+                    }
+                    events.push_str(&output_item_done_event(&item));
+                }
+                events.push_str(&response_completed_event(&response_id));
+            }
+            ScriptedResponse::Error(message) => {
+                events.push_str(&error_event(&ResponseError {
+                    code: None,
+                    message: Some(message),
+                    error_type: None,
+                    param: None,
+                }));
+            }
+        }
+        Ok(futures::stream::iter([Ok(Bytes::from(events))]).boxed())
+    }
+}
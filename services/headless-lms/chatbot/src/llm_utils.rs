@@ -3,14 +3,13 @@ use secrecy::{ExposeSecret, SecretString};
 use crate::{
     azure_chatbot::{
         InputItem, LLMRequest, LLMRequestParams, MistralParams, NonThinkingParams, OutputItem,
-        Reasoning, ReasoningOutput, Response as AzureResponse, ResponseError, SummaryType,
-        ThinkingParams,
+        Reasoning, ReasoningOutput, SummaryType, ThinkingParams,
     },
     chatbot_error::ChatbotResult,
+    llm_provider::LlmProvider,
     prelude::*,
 };
 use core::default::Default;
-use headless_lms_base::config::ApplicationConfiguration;
 use headless_lms_models::{
     chatbot_configurations::{ChatbotConfiguration, ReasoningEffortLevel},
     chatbot_configurations_models::ModelType,
@@ -20,7 +19,6 @@ use headless_lms_models::{
     chatbot_conversation_message_tool_outputs::ChatbotConversationMessageToolOutput,
     chatbot_conversation_messages::{ChatbotConversationMessage, Message},
 };
-use reqwest::Response;
 use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, instrument, trace, warn};
@@ -569,231 +567,20 @@ pub fn estimate_tokens(text: &str) -> i32 {
 }
 
 /// Makes a non-streaming request to an LLM
-#[instrument(skip(chat_request, endpoint, api_key), fields(
-    num_messages = chat_request.input.len(),
-    temperature,
-    max_tokens,
-    endpoint = %endpoint
-))]
-async fn make_llm_request(
-    chat_request: LLMRequest,
-    endpoint: &url::Url,
-    api_key: &SecretString,
-) -> ChatbotResult<LLMResponse> {
-    debug!(
-        "Preparing LLM request with {} messages",
-        chat_request.input.len()
-    );
-
-    trace!("Base request: {:?}", chat_request);
-
-    let request = AzureCompletionRequest {
-        base: chat_request,
-        stream: false,
-    };
-
-    let headers = build_llm_headers(api_key)?;
-    debug!("Sending request to LLM endpoint: {}", endpoint);
-
-    let response = REQWEST_CLIENT
-        .post(endpoint.clone())
-        .headers(headers)
-        .json(&request)
-        .send()
-        .await?;
-
-    trace!("Received response from LLM");
-    process_llm_response(response).await
-}
-
-/// Process a non-streaming LLM response
-#[instrument(skip(response), fields(status = %response.status()))]
-async fn process_llm_response(response: Response) -> ChatbotResult<LLMResponse> {
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await?;
-        error!(
-            status = %status,
-            error = %error_text,
-            "Error calling LLM API"
-        );
-        // Azure can return a JSON Response, so parse it and take the error from it.
-        let azure_response = serde_json::from_str::<AzureResponse>(&error_text);
-        let error = match azure_response {
-            Ok(response) => {
-                let azure_error: Option<ResponseError> = response.error;
-                // Format the error message to be minimal and add the Azure source.
-                let mut error = chatbot_err!(
-                    FailedAzureResponse,
-                    format!(
-                        "Error calling LLM API: Status: {}. Error: {}",
-                        status,
-                        &azure_error
-                            .as_ref()
-                            .and_then(|e| e.code.to_owned())
-                            .or_else(|| azure_error.as_ref().and_then(|e| e.error_type.to_owned()))
-                            .unwrap_or(error_text)
-                    )
-                );
-                if let Some(e) = azure_error {
-                    error.add_azure_source(e);
-                };
-                error
-            }
-            // If Azure returned data in some other shape, just show the unparsed text.
-            Err(_) => chatbot_err!(
-                FailedAzureResponse,
-                format!(
-                    "Error calling LLM API: Status: {}. Error: {}",
-                    status, &error_text
-                )
-            ),
-        };
-
-        return Err(error);
-    }
-
-    trace!("Processing successful LLM response");
-    // Parse the response
-    let completion: LLMResponse = response.json().await?;
-    debug!(
-        "Successfully processed LLM response with {} choices",
-        completion.output.len()
-    );
-    Ok(completion)
-}
-
-/// Makes a streaming request to an LLM
-#[instrument(skip(chat_request, app_config), fields(
-    num_messages = chat_request.input.len(),
-    temperature,
-    max_tokens
-))]
-pub async fn make_streaming_llm_request(
-    chat_request: LLMRequest,
-    app_config: &ApplicationConfiguration,
-) -> ChatbotResult<Response> {
-    debug!(
-        "Preparing streaming LLM request with {} messages",
-        chat_request.input.len()
-    );
-    let azure_config = app_config.azure_configuration.as_ref().ok_or_else(|| {
-        error!("Azure configuration missing");
-        chatbot_err!(
-            AzureRequestBuildError,
-            "Azure configuration is missing from the application configuration"
-        )
-    })?;
-
-    let chatbot_config = azure_config.chatbot_config.as_ref().ok_or_else(|| {
-        error!("Chatbot configuration missing");
-        chatbot_err!(
-            AzureRequestBuildError,
-            "Chatbot configuration is missing from the Azure configuration"
-        )
-    })?;
-
-    let request = AzureCompletionRequest {
-        base: chat_request,
-        stream: true,
-    };
-
-    let headers = build_llm_headers(&chatbot_config.api_key)?;
-    let api_endpoint = chatbot_config.responses_endpoint()?;
-    debug!(
-        "Sending streaming request to LLM endpoint: {}",
-        api_endpoint
-    );
-
-    let response = REQWEST_CLIENT
-        .post(api_endpoint)
-        .headers(headers)
-        .json(&request)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await?;
-        error!(
-            status = %status,
-            error = %error_text,
-            "Error calling streaming LLM API"
-        );
-        // Azure can return a JSON Response, so parse it and take the error from it.
-        let azure_response = serde_json::from_str::<AzureResponse>(&error_text);
-        let error = match azure_response {
-            Ok(response) => {
-                let azure_error: Option<ResponseError> = response.error;
-                // Format the error message to be minimal and add the Azure source.
-                let mut error = chatbot_err!(
-                    FailedAzureResponse,
-                    format!(
-                        "Error calling LLM API: Status: {}. Error: {}",
-                        status,
-                        &azure_error
-                            .as_ref()
-                            .and_then(|e| e.code.to_owned())
-                            .or_else(|| azure_error.as_ref().and_then(|e| e.error_type.to_owned()))
-                            .unwrap_or(error_text)
-                    )
-                );
-                if let Some(e) = azure_error {
-                    error.add_azure_source(e);
-                };
-                error
-            }
-            // If Azure returned data in some other shape, just show the unparsed text.
-            Err(_) => chatbot_err!(
-                FailedAzureResponse,
-                format!(
-                    "Error calling LLM API: Status: {}. Error: {}",
-                    status, &error_text
-                )
-            ),
-        };
-
-        return Err(error);
-    }
-
-    debug!("Successfully initiated streaming response");
-    Ok(response)
-}
-
-/// Makes a non-streaming request to an LLM using application configuration
-#[instrument(skip(chat_request, app_config), fields(
+#[instrument(skip(chat_request, provider), fields(
     num_messages = chat_request.input.len(),
     temperature,
     max_tokens
 ))]
 pub async fn make_blocking_llm_request(
     chat_request: LLMRequest,
-    app_config: &ApplicationConfiguration,
+    provider: &dyn LlmProvider,
 ) -> ChatbotResult<LLMResponse> {
     debug!(
         "Preparing blocking LLM request with {} messages",
         chat_request.input.len()
     );
-    let azure_config = app_config.azure_configuration.as_ref().ok_or_else(|| {
-        error!("Azure configuration missing");
-        chatbot_err!(
-            AzureRequestBuildError,
-            "Azure configuration is missing from the application configuration"
-        )
-    })?;
-
-    let chatbot_config = azure_config.chatbot_config.as_ref().ok_or_else(|| {
-        error!("Chatbot configuration missing");
-        chatbot_err!(
-            AzureRequestBuildError,
-            "Chatbot configuration is missing from the Azure configuration"
-        )
-    })?;
-
-    let api_endpoint = chatbot_config.responses_endpoint()?;
-
-    trace!("Making LLM request to endpoint: {}", api_endpoint);
-    make_llm_request(chat_request, &api_endpoint, &chatbot_config.api_key).await
+    provider.create_response(chat_request).await
 }
 
 /// Collects all the completion choices to a string. Assumes the completion has only
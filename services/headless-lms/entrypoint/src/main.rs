@@ -28,6 +28,10 @@ struct Program {
 
 fn main() -> Result<()> {
     let programs_list = vec![
+        Program {
+            name: "certificate-bulk-exporter",
+            execute: Box::new(|| tokio_run(programs::certificate_bulk_exporter::main())),
+        },
         Program {
             name: "credit-registrar",
             execute: Box::new(|| tokio_run(programs::credit_registrar::main())),
@@ -50,6 +54,10 @@ fn main() -> Result<()> {
                 || tokio_run(programs::exercise_service_client_upload_reaper::main()),
             ),
         },
+        Program {
+            name: "lti-grade-syncer",
+            execute: Box::new(|| tokio_run(programs::lti_grade_syncer::main())),
+        },
         Program {
             name: "open-university-registration-link-fetcher",
             execute: Box::new(|| {
@@ -101,10 +109,22 @@ fn main() -> Result<()> {
             name: "chatbot-syncer",
             execute: Box::new(|| tokio_run(programs::chatbot_syncer::main())),
         },
+        Program {
+            name: "chatbot-evaluation-runner",
+            execute: Box::new(|| tokio_run(programs::chatbot_evaluation_runner::main())),
+        },
         Program {
             name: "mailchimp-syncer",
             execute: Box::new(|| tokio_run(programs::mailchimp_syncer::main())),
         },
+        Program {
+            name: "webhook-deliverer",
+            execute: Box::new(|| tokio_run(programs::webhook_deliverer::main())),
+        },
+        Program {
+            name: "xapi-sender",
+            execute: Box::new(|| tokio_run(programs::xapi_sender::main())),
+        },
         Program {
             name: "export-openapi",
             execute: Box::new(export_openapi_specs),
@@ -37,6 +37,7 @@ pub struct ExerciseSlide {
     pub course_id: Uuid,
     pub exercise_name: String,
     pub exercise_order_number: i32,
+    /// The deadline that applies to the requesting user, individual deadline exceptions included.
     pub deadline: Option<DateTime<Utc>>,
     pub tasks: Vec<ExerciseTask>,
 }
@@ -0,0 +1 @@
+DROP TABLE deadline_exceptions;
@@ -0,0 +1,65 @@
+CREATE TABLE deadline_exceptions (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  course_id UUID REFERENCES courses(id),
+  user_id UUID REFERENCES users(id),
+  course_instance_id UUID REFERENCES course_instances(id),
+  exercise_id UUID REFERENCES exercises(id),
+  chapter_id UUID REFERENCES chapters(id),
+  exam_id UUID REFERENCES exams(id),
+  deadline TIMESTAMP WITH TIME ZONE NOT NULL,
+  reason TEXT,
+  granted_by UUID NOT NULL REFERENCES users(id),
+  revoked_by UUID REFERENCES users(id),
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  CONSTRAINT deadline_exceptions_one_target CHECK (num_nonnulls(exercise_id, chapter_id, exam_id) = 1),
+  CONSTRAINT deadline_exceptions_one_scope CHECK (num_nonnulls(user_id, course_instance_id) = 1),
+  -- Exams are not run through course instances, so an exam exception is always for one person.
+  CONSTRAINT deadline_exceptions_exam_is_per_user CHECK (
+    exam_id IS NULL
+    OR user_id IS NOT NULL
+  ),
+  CONSTRAINT deadline_exceptions_course_unless_exam CHECK ((course_id IS NULL) = (exam_id IS NOT NULL)),
+  CONSTRAINT deadline_exceptions_revoked_only_when_deleted CHECK (
+    revoked_by IS NULL
+    OR deleted_at IS NOT NULL
+  )
+);
+
+-- At most one live exception per scope and target. Granting again soft-deletes the previous row,
+-- which is what keeps the audit trail.
+CREATE UNIQUE INDEX uq_deadline_exceptions_live_scope_target ON deadline_exceptions (
+  user_id,
+  course_instance_id,
+  exercise_id,
+  chapter_id,
+  exam_id
+) NULLS NOT DISTINCT
+WHERE deleted_at IS NULL;
+CREATE INDEX idx_deadline_exceptions_course_id ON deadline_exceptions (course_id, created_at DESC);
+CREATE INDEX idx_deadline_exceptions_exam_id ON deadline_exceptions (exam_id, created_at DESC)
+WHERE exam_id IS NOT NULL;
+CREATE INDEX idx_deadline_exceptions_user_id ON deadline_exceptions (user_id)
+WHERE deleted_at IS NULL;
+CREATE INDEX idx_deadline_exceptions_course_instance_id ON deadline_exceptions (course_instance_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON deadline_exceptions FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE deadline_exceptions IS 'Individual deadlines that replace the shared exercise, chapter or exam deadline, for example because of an approved accommodation. Rows are never updated in place: granting a new exception for the same scope and target soft-deletes the previous one, and revoking soft-deletes it, so the table doubles as the audit trail of who granted what.';
+COMMENT ON COLUMN deadline_exceptions.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN deadline_exceptions.course_id IS 'The course the exception belongs to. Null exactly for exam exceptions.';
+COMMENT ON COLUMN deadline_exceptions.user_id IS 'The student the exception is for. Null if the exception applies to a whole course instance.';
+COMMENT ON COLUMN deadline_exceptions.course_instance_id IS 'The course instance whose students the exception applies to. Null if the exception is for a single student.';
+COMMENT ON COLUMN deadline_exceptions.exercise_id IS 'The exercise whose deadline is replaced. Exactly one of exercise_id, chapter_id and exam_id is set.';
+COMMENT ON COLUMN deadline_exceptions.chapter_id IS 'The chapter whose deadline is replaced. Applies to every exercise in the chapter, but never moves an exercise deadline earlier.';
+COMMENT ON COLUMN deadline_exceptions.exam_id IS 'The exam whose end time is replaced for the student.';
+COMMENT ON COLUMN deadline_exceptions.deadline IS 'The deadline that applies instead of the shared one.';
+COMMENT ON COLUMN deadline_exceptions.reason IS 'Free-text justification typed by the person granting the exception, for example a reference to the accommodation decision.';
+COMMENT ON COLUMN deadline_exceptions.granted_by IS 'The teacher who granted the exception.';
+COMMENT ON COLUMN deadline_exceptions.revoked_by IS 'The teacher who revoked or replaced the exception. Null while the exception is live.';
+COMMENT ON COLUMN deadline_exceptions.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN deadline_exceptions.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN deadline_exceptions.deleted_at IS 'Timestamp when the exception was revoked or replaced. If null, the exception is in effect.';
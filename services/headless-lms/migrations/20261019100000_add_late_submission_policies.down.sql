@@ -0,0 +1,3 @@
+DROP TABLE late_submission_penalties;
+DROP TABLE late_submission_policies;
+DROP TYPE late_submission_penalty_type;
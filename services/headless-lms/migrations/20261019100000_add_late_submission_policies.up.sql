@@ -0,0 +1,75 @@
+CREATE TYPE late_submission_penalty_type AS ENUM ('linear-decay', 'percent-per-day', 'hard-cap');
+
+CREATE TABLE late_submission_policies (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  course_id UUID NOT NULL REFERENCES courses(id),
+  exercise_id UUID REFERENCES exercises(id),
+  penalty_type late_submission_penalty_type NOT NULL,
+  late_window_hours INTEGER NOT NULL CHECK (late_window_hours >= 0),
+  penalty_percent REAL NOT NULL CHECK (
+    penalty_percent >= 0
+    AND penalty_percent <= 100
+  ),
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  deleted_at TIMESTAMP WITH TIME ZONE
+);
+
+-- One course default (exercise_id null) and at most one override per exercise.
+CREATE UNIQUE INDEX uq_late_submission_policies_course_exercise ON late_submission_policies (course_id, exercise_id) NULLS NOT DISTINCT
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON late_submission_policies FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE late_submission_policies IS 'How submissions made after the deadline are handled. A course can have a default policy and each exercise can override it. Without a policy, submissions are rejected once the deadline has passed.';
+COMMENT ON COLUMN late_submission_policies.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN late_submission_policies.course_id IS 'The course the policy belongs to.';
+COMMENT ON COLUMN late_submission_policies.exercise_id IS 'The exercise the policy overrides the course default for. Null for the course default.';
+COMMENT ON COLUMN late_submission_policies.penalty_type IS 'How the points of a late submission are reduced. linear-decay: the penalty grows linearly from zero at the deadline to penalty_percent at the end of the late window. percent-per-day: penalty_percent is deducted for every started day after the deadline. hard-cap: a late submission can get at most (100 - penalty_percent) percent of the maximum points.';
+COMMENT ON COLUMN late_submission_policies.late_window_hours IS 'For how many hours after the deadline late submissions are still accepted. Zero means late submissions are not accepted, which lets an exercise opt out of the course default.';
+COMMENT ON COLUMN late_submission_policies.penalty_percent IS 'The size of the penalty as a percentage. See penalty_type for how it is interpreted.';
+COMMENT ON COLUMN late_submission_policies.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN late_submission_policies.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN late_submission_policies.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+
+CREATE TABLE late_submission_penalties (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  exercise_slide_submission_id UUID NOT NULL REFERENCES exercise_slide_submissions(id),
+  late_submission_policy_id UUID NOT NULL REFERENCES late_submission_policies(id),
+  user_id UUID NOT NULL REFERENCES users(id),
+  exercise_id UUID NOT NULL REFERENCES exercises(id),
+  deadline TIMESTAMP WITH TIME ZONE NOT NULL,
+  points_multiplier REAL NOT NULL CHECK (
+    points_multiplier >= 0
+    AND points_multiplier <= 1
+  ),
+  max_points_ratio REAL NOT NULL CHECK (
+    max_points_ratio >= 0
+    AND max_points_ratio <= 1
+  ),
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now(),
+  deleted_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE UNIQUE INDEX uq_late_submission_penalties_exercise_slide_submission_id ON late_submission_penalties (exercise_slide_submission_id)
+WHERE deleted_at IS NULL;
+CREATE INDEX idx_late_submission_penalties_exercise_id ON late_submission_penalties (exercise_id, user_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON late_submission_penalties FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE late_submission_penalties IS 'The penalty applied to a submission that was made after the deadline but inside the late window. The penalty is fixed when the submission is made, so later changes to the policy do not affect submissions that were already made. The penalty is applied to the points every time the submission is graded, including regradings and peer review outcomes.';
+COMMENT ON COLUMN late_submission_penalties.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN late_submission_penalties.exercise_slide_submission_id IS 'The late submission.';
+COMMENT ON COLUMN late_submission_penalties.late_submission_policy_id IS 'The policy that was in effect when the submission was made.';
+COMMENT ON COLUMN late_submission_penalties.user_id IS 'The student who made the submission.';
+COMMENT ON COLUMN late_submission_penalties.exercise_id IS 'The exercise the submission was made to.';
+COMMENT ON COLUMN late_submission_penalties.deadline IS 'The deadline that applied to the student when the submission was made, deadline exceptions included.';
+COMMENT ON COLUMN late_submission_penalties.points_multiplier IS 'The points of the submission are multiplied by this. 1 means no reduction.';
+COMMENT ON COLUMN late_submission_penalties.max_points_ratio IS 'The points of the submission are capped at this share of the exercise maximum points. 1 means no cap.';
+COMMENT ON COLUMN late_submission_penalties.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN late_submission_penalties.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN late_submission_penalties.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
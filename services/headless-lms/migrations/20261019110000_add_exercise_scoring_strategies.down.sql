@@ -0,0 +1,6 @@
+ALTER TABLE exercises DROP CONSTRAINT exercises_penalty_per_attempt_has_penalty_percent,
+  DROP CONSTRAINT exercises_average_of_last_attempts_has_attempt_count,
+  DROP COLUMN scoring_strategy_penalty_percent,
+  DROP COLUMN scoring_strategy_attempt_count,
+  DROP COLUMN scoring_strategy;
+DROP TYPE exercise_scoring_strategy;
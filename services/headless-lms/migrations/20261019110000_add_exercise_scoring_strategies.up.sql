@@ -0,0 +1,26 @@
+CREATE TYPE exercise_scoring_strategy AS ENUM (
+  'best-attempt',
+  'latest-attempt',
+  'average-of-last-attempts',
+  'penalty-per-attempt'
+);
+
+ALTER TABLE exercises
+ADD COLUMN scoring_strategy exercise_scoring_strategy NOT NULL DEFAULT 'best-attempt',
+  ADD COLUMN scoring_strategy_attempt_count INTEGER CHECK (scoring_strategy_attempt_count > 0),
+  ADD COLUMN scoring_strategy_penalty_percent REAL CHECK (
+    scoring_strategy_penalty_percent >= 0
+    AND scoring_strategy_penalty_percent <= 100
+  ),
+  ADD CONSTRAINT exercises_average_of_last_attempts_has_attempt_count CHECK (
+    scoring_strategy <> 'average-of-last-attempts'
+    OR scoring_strategy_attempt_count IS NOT NULL
+  ),
+  ADD CONSTRAINT exercises_penalty_per_attempt_has_penalty_percent CHECK (
+    scoring_strategy <> 'penalty-per-attempt'
+    OR scoring_strategy_penalty_percent IS NOT NULL
+  );
+
+COMMENT ON COLUMN exercises.scoring_strategy IS 'Which of the student''s attempts decide the points they keep. best-attempt: the best attempt counts, or the latest one when the submission''s points update strategy allows removing points. latest-attempt: the latest attempt counts. average-of-last-attempts: the average of the last scoring_strategy_attempt_count attempts counts. penalty-per-attempt: every attempt after the first loses scoring_strategy_penalty_percent percent more of its points, and the best result counts. Only used for course exercises, exam exercises are scored as before.';
+COMMENT ON COLUMN exercises.scoring_strategy_attempt_count IS 'How many of the latest attempts are averaged when scoring_strategy is average-of-last-attempts.';
+COMMENT ON COLUMN exercises.scoring_strategy_penalty_percent IS 'The percentage of points lost for every attempt after the first when scoring_strategy is penalty-per-attempt.';
@@ -0,0 +1,26 @@
+DROP TABLE grading_rubric_assessments;
+DROP TABLE grading_rubric_criterion_levels;
+DROP TABLE grading_rubric_criteria;
+DROP TABLE grading_rubrics;
+
+-- Replace enum value 'rubric' with 'custom-points'
+-- to ensure compatibility with the previous teacher_decision_type definition
+UPDATE teacher_grading_decisions
+SET teacher_decision = 'custom-points'
+WHERE teacher_decision = 'rubric';
+
+ALTER TYPE teacher_decision_type
+RENAME TO teacher_decision_type_old;
+
+CREATE TYPE teacher_decision_type AS ENUM (
+  'full-points',
+  'zero-points',
+  'custom-points',
+  'suspected-plagiarism',
+  'reject-and-reset'
+);
+
+ALTER TABLE teacher_grading_decisions
+ALTER COLUMN teacher_decision TYPE teacher_decision_type USING teacher_decision::text::teacher_decision_type;
+
+DROP TYPE teacher_decision_type_old;
@@ -0,0 +1,109 @@
+ALTER TYPE teacher_decision_type
+ADD VALUE IF NOT EXISTS 'rubric';
+
+CREATE TABLE grading_rubrics (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  exercise_id UUID NOT NULL REFERENCES exercises(id)
+);
+
+CREATE UNIQUE INDEX grading_rubrics_exercise_id_unique ON grading_rubrics (exercise_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON grading_rubrics FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE grading_rubrics IS 'A rubric that teachers use to grade the answers of an exercise manually. The rubric consists of criteria, and each criterion has levels that give points.';
+COMMENT ON COLUMN grading_rubrics.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN grading_rubrics.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN grading_rubrics.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN grading_rubrics.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN grading_rubrics.exercise_id IS 'The exercise graded with the rubric. An exercise has at most one rubric.';
+
+CREATE TABLE grading_rubric_criteria (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  grading_rubric_id UUID NOT NULL REFERENCES grading_rubrics(id),
+  order_number INTEGER NOT NULL,
+  title VARCHAR(255) NOT NULL,
+  description TEXT
+);
+
+CREATE INDEX grading_rubric_criteria_grading_rubric_id ON grading_rubric_criteria (grading_rubric_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON grading_rubric_criteria FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE grading_rubric_criteria IS 'A criterion of a grading rubric, such as the structure or the argumentation of an essay. When a rubric is edited, its old criteria are deleted and new ones are created so that earlier gradings keep referring to the criteria they were made with.';
+COMMENT ON COLUMN grading_rubric_criteria.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN grading_rubric_criteria.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN grading_rubric_criteria.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN grading_rubric_criteria.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN grading_rubric_criteria.grading_rubric_id IS 'The rubric the criterion belongs to.';
+COMMENT ON COLUMN grading_rubric_criteria.order_number IS 'The order in which the criteria are shown.';
+COMMENT ON COLUMN grading_rubric_criteria.title IS 'The name of the criterion shown to teachers and students.';
+COMMENT ON COLUMN grading_rubric_criteria.description IS 'Instructions that explain what is assessed with the criterion.';
+
+CREATE TABLE grading_rubric_criterion_levels (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  grading_rubric_criterion_id UUID NOT NULL REFERENCES grading_rubric_criteria(id),
+  order_number INTEGER NOT NULL,
+  title VARCHAR(255) NOT NULL,
+  description TEXT,
+  points REAL NOT NULL CHECK (points >= 0)
+);
+
+CREATE INDEX grading_rubric_criterion_levels_grading_rubric_criterion_id ON grading_rubric_criterion_levels (grading_rubric_criterion_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON grading_rubric_criterion_levels FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE grading_rubric_criterion_levels IS 'A level of achievement for a grading rubric criterion, such as excellent or insufficient. The teacher picks one level for each criterion when grading.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.grading_rubric_criterion_id IS 'The criterion the level belongs to.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.order_number IS 'The order in which the levels of the criterion are shown.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.title IS 'The name of the level shown to teachers and students.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.description IS 'Describes what kind of an answer reaches the level.';
+COMMENT ON COLUMN grading_rubric_criterion_levels.points IS 'The points given for the criterion when the answer reaches the level.';
+
+CREATE TABLE grading_rubric_assessments (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  teacher_grading_decision_id UUID NOT NULL REFERENCES teacher_grading_decisions(id),
+  grading_rubric_criterion_id UUID NOT NULL REFERENCES grading_rubric_criteria(id),
+  grading_rubric_criterion_level_id UUID NOT NULL REFERENCES grading_rubric_criterion_levels(id),
+  feedback TEXT
+);
+
+CREATE UNIQUE INDEX grading_rubric_assessments_decision_and_criterion_unique ON grading_rubric_assessments (
+  teacher_grading_decision_id,
+  grading_rubric_criterion_id
+)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON grading_rubric_assessments FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE grading_rubric_assessments IS 'The level a teacher picked for one criterion of a grading rubric when grading an answer. The points of the picked levels add up to the score of the teacher grading decision.';
+COMMENT ON COLUMN grading_rubric_assessments.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN grading_rubric_assessments.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN grading_rubric_assessments.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN grading_rubric_assessments.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN grading_rubric_assessments.teacher_grading_decision_id IS 'The rubric grading decision the assessment is part of.';
+COMMENT ON COLUMN grading_rubric_assessments.grading_rubric_criterion_id IS 'The criterion that was assessed.';
+COMMENT ON COLUMN grading_rubric_assessments.grading_rubric_criterion_level_id IS 'The level the teacher picked for the criterion.';
+COMMENT ON COLUMN grading_rubric_assessments.feedback IS 'Free-text feedback from the teacher about the criterion. Shown to the student.';
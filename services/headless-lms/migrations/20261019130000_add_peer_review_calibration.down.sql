@@ -0,0 +1,7 @@
+DROP TABLE peer_reviewer_reliabilities;
+DROP TABLE peer_review_calibration_submissions;
+DROP TABLE peer_review_calibration_reference_scores;
+DROP TABLE peer_review_calibration_answers;
+
+ALTER TABLE peer_or_self_review_configs DROP COLUMN calibration_reviews_to_give,
+  DROP COLUMN manual_review_reliability_threshold;
@@ -0,0 +1,131 @@
+ALTER TABLE peer_or_self_review_configs
+ADD COLUMN calibration_reviews_to_give INTEGER NOT NULL DEFAULT 0 CHECK (calibration_reviews_to_give >= 0),
+  ADD COLUMN manual_review_reliability_threshold REAL CHECK (
+    manual_review_reliability_threshold >= 0
+    AND manual_review_reliability_threshold <= 1
+  );
+
+COMMENT ON COLUMN peer_or_self_review_configs.calibration_reviews_to_give IS 'How many teacher-graded calibration answers a student has to review before they can review their peers. The student''s agreement with the teacher becomes their reliability weight as a reviewer. If zero, there is no calibration step.';
+COMMENT ON COLUMN peer_or_self_review_configs.manual_review_reliability_threshold IS 'If the average reliability of the reviewers of an answer is below this, the answer is sent to manual review instead of being graded automatically. If null, the reliability of the reviewers never sends answers to manual review.';
+
+CREATE TABLE peer_review_calibration_answers (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  exercise_id UUID NOT NULL REFERENCES exercises(id),
+  exercise_slide_submission_id UUID NOT NULL REFERENCES exercise_slide_submissions(id)
+);
+
+CREATE UNIQUE INDEX peer_review_calibration_answers_exercise_slide_submission_id_unique ON peer_review_calibration_answers (exercise_slide_submission_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX peer_review_calibration_answers_exercise_id ON peer_review_calibration_answers (exercise_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON peer_review_calibration_answers FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE peer_review_calibration_answers IS 'An answer to an exercise that a teacher has scored with the peer review questions of the exercise. Students review these before reviewing their peers, and their agreement with the teacher''s scores tells how reliable they are as reviewers.';
+COMMENT ON COLUMN peer_review_calibration_answers.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN peer_review_calibration_answers.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN peer_review_calibration_answers.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN peer_review_calibration_answers.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN peer_review_calibration_answers.exercise_id IS 'The exercise whose reviewers are calibrated with the answer.';
+COMMENT ON COLUMN peer_review_calibration_answers.exercise_slide_submission_id IS 'The answer that is shown to the students being calibrated.';
+
+CREATE TABLE peer_review_calibration_reference_scores (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  peer_review_calibration_answer_id UUID NOT NULL REFERENCES peer_review_calibration_answers(id),
+  peer_or_self_review_question_id UUID NOT NULL REFERENCES peer_or_self_review_questions(id),
+  number_data REAL NOT NULL CHECK (
+    number_data >= 1
+    AND number_data <= 5
+  )
+);
+
+CREATE UNIQUE INDEX peer_review_calibration_reference_scores_answer_and_question_unique ON peer_review_calibration_reference_scores (
+  peer_review_calibration_answer_id,
+  peer_or_self_review_question_id
+)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON peer_review_calibration_reference_scores FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE peer_review_calibration_reference_scores IS 'The score a teacher gave to a calibration answer for one scale question of the peer review.';
+COMMENT ON COLUMN peer_review_calibration_reference_scores.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN peer_review_calibration_reference_scores.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN peer_review_calibration_reference_scores.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN peer_review_calibration_reference_scores.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN peer_review_calibration_reference_scores.peer_review_calibration_answer_id IS 'The calibration answer the score was given to.';
+COMMENT ON COLUMN peer_review_calibration_reference_scores.peer_or_self_review_question_id IS 'The scale question the score answers.';
+COMMENT ON COLUMN peer_review_calibration_reference_scores.number_data IS 'The teacher''s answer to the scale question, from 1 to 5.';
+
+CREATE TABLE peer_review_calibration_submissions (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  user_id UUID NOT NULL REFERENCES users(id),
+  course_id UUID NOT NULL REFERENCES courses(id),
+  exercise_id UUID NOT NULL REFERENCES exercises(id),
+  peer_review_calibration_answer_id UUID NOT NULL REFERENCES peer_review_calibration_answers(id),
+  agreement REAL NOT NULL CHECK (
+    agreement >= 0
+    AND agreement <= 1
+  )
+);
+
+CREATE UNIQUE INDEX peer_review_calibration_submissions_user_and_answer_unique ON peer_review_calibration_submissions (user_id, peer_review_calibration_answer_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX peer_review_calibration_submissions_user_id_course_id ON peer_review_calibration_submissions (user_id, course_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON peer_review_calibration_submissions FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE peer_review_calibration_submissions IS 'A review a student gave to a calibration answer, and how well it agreed with the teacher''s scores.';
+COMMENT ON COLUMN peer_review_calibration_submissions.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN peer_review_calibration_submissions.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN peer_review_calibration_submissions.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN peer_review_calibration_submissions.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN peer_review_calibration_submissions.user_id IS 'The student who reviewed the calibration answer.';
+COMMENT ON COLUMN peer_review_calibration_submissions.course_id IS 'The course the review was given in.';
+COMMENT ON COLUMN peer_review_calibration_submissions.exercise_id IS 'The exercise the calibration answer belongs to.';
+COMMENT ON COLUMN peer_review_calibration_submissions.peer_review_calibration_answer_id IS 'The calibration answer that was reviewed.';
+COMMENT ON COLUMN peer_review_calibration_submissions.agreement IS 'How well the student''s scores agreed with the teacher''s scores. 1 means the scores were the same and 0 means they were as far apart as possible.';
+
+CREATE TABLE peer_reviewer_reliabilities (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  user_id UUID NOT NULL REFERENCES users(id),
+  course_id UUID NOT NULL REFERENCES courses(id),
+  reliability REAL NOT NULL CHECK (
+    reliability >= 0
+    AND reliability <= 1
+  ),
+  calibration_reviews_given INTEGER NOT NULL CHECK (calibration_reviews_given > 0)
+);
+
+CREATE UNIQUE INDEX peer_reviewer_reliabilities_user_id_course_id_unique ON peer_reviewer_reliabilities (user_id, course_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON peer_reviewer_reliabilities FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE peer_reviewer_reliabilities IS 'How reliable a student is as a peer reviewer on a course. Used to weight the peer reviews the student gives.';
+COMMENT ON COLUMN peer_reviewer_reliabilities.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN peer_reviewer_reliabilities.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN peer_reviewer_reliabilities.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN peer_reviewer_reliabilities.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN peer_reviewer_reliabilities.user_id IS 'The student the reliability is for.';
+COMMENT ON COLUMN peer_reviewer_reliabilities.course_id IS 'The course the reliability is for.';
+COMMENT ON COLUMN peer_reviewer_reliabilities.reliability IS 'The average agreement of the student''s calibration reviews on the course, from 0 to 1. Used as the weight of the student''s peer reviews. Students without a reliability are weighted as 1.';
+COMMENT ON COLUMN peer_reviewer_reliabilities.calibration_reviews_given IS 'How many calibration reviews the reliability is based on.';
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS offered_answers_to_peer_review_temporary_submission_id_idx;
+DROP INDEX IF EXISTS peer_or_self_review_submissions_user_id_course_id_idx;
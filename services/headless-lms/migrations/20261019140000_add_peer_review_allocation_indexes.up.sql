@@ -0,0 +1,5 @@
+-- Indexes for the peer review allocator, which balances the reviews an answer is receiving and avoids pairing the same reviewer and author repeatedly
+CREATE INDEX IF NOT EXISTS offered_answers_to_peer_review_temporary_submission_id_idx ON offered_answers_to_peer_review_temporary(exercise_slide_submission_id);
+
+CREATE INDEX IF NOT EXISTS peer_or_self_review_submissions_user_id_course_id_idx ON peer_or_self_review_submissions(user_id, course_id)
+WHERE deleted_at IS NULL;
@@ -0,0 +1,41 @@
+DROP TABLE peer_review_disputes;
+DROP TYPE peer_review_dispute_status;
+
+-- Enum values cannot be removed, so the type is rebuilt without the value. Templates of the retired
+-- type are soft-deleted the same way the credit registration migration does it.
+UPDATE email_templates t
+SET email_template_type = 'generic',
+  deleted_at = now() - (r.n * INTERVAL '1 microsecond')
+FROM (
+    SELECT id,
+      row_number() OVER (
+        ORDER BY id
+      ) AS n
+    FROM email_templates
+    WHERE email_template_type = 'peer_review_dispute_resolved'
+  ) r
+WHERE t.id = r.id;
+
+CREATE TYPE email_template_type_old AS ENUM (
+  'reset_password_email',
+  'delete_user_email',
+  'generic',
+  'confirm_email_code',
+  'credit_registration_account_linking',
+  'verify_email_address',
+  'credit_registration_action_needed',
+  'credit_registration_registered',
+  'credit_registration_student_number_linked'
+);
+
+ALTER TABLE email_templates
+ALTER COLUMN email_template_type TYPE email_template_type_old USING (
+    email_template_type::text::email_template_type_old
+  );
+
+DROP TYPE email_template_type;
+
+ALTER TYPE email_template_type_old
+RENAME TO email_template_type;
+
+COMMENT ON TYPE email_template_type IS 'Type of email template: generic templates do not support automated placeholder replacements, others do.';
@@ -0,0 +1,68 @@
+-- Added ahead of use: a new enum value cannot be used in the transaction that adds it.
+ALTER TYPE email_template_type
+ADD VALUE 'peer_review_dispute_resolved';
+
+CREATE TYPE peer_review_dispute_status AS ENUM (
+  'pending',
+  'review-discarded',
+  'grade-overridden',
+  'rejected'
+);
+
+COMMENT ON TYPE peer_review_dispute_status IS 'State of a peer review dispute. Pending disputes wait for a teacher; the other states tell how the teacher resolved the dispute.';
+
+CREATE TABLE peer_review_disputes (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  user_id UUID NOT NULL REFERENCES users(id),
+  exercise_id UUID NOT NULL REFERENCES exercises(id),
+  course_id UUID NOT NULL REFERENCES courses(id),
+  exercise_slide_submission_id UUID NOT NULL REFERENCES exercise_slide_submissions(id),
+  peer_or_self_review_submission_id UUID NOT NULL REFERENCES peer_or_self_review_submissions(id),
+  reason TEXT NOT NULL CHECK (TRIM(reason) <> ''),
+  status peer_review_dispute_status NOT NULL DEFAULT 'pending',
+  resolved_by_user_id UUID REFERENCES users(id),
+  resolved_at TIMESTAMP WITH TIME ZONE,
+  teacher_response TEXT,
+  teacher_grading_decision_id UUID REFERENCES teacher_grading_decisions(id),
+  email_delivery_id UUID REFERENCES email_deliveries(id),
+  CHECK (
+    (status = 'pending') = (resolved_at IS NULL)
+    AND (status = 'pending') = (resolved_by_user_id IS NULL)
+  ),
+  CHECK (
+    (status = 'grade-overridden') = (teacher_grading_decision_id IS NOT NULL)
+  )
+);
+
+CREATE UNIQUE INDEX peer_review_disputes_peer_or_self_review_submission_id_unique ON peer_review_disputes (peer_or_self_review_submission_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX peer_review_disputes_exercise_id_status ON peer_review_disputes (exercise_id, status)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX peer_review_disputes_user_id_exercise_id ON peer_review_disputes (user_id, exercise_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON peer_review_disputes FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE peer_review_disputes IS 'An objection a student has filed against a peer review they received. Pending disputes are shown to the teachers of the course, who can discard the review, override the grade of the answer or reject the dispute.';
+COMMENT ON COLUMN peer_review_disputes.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN peer_review_disputes.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN peer_review_disputes.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN peer_review_disputes.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN peer_review_disputes.user_id IS 'The student who filed the dispute. The author of the reviewed answer.';
+COMMENT ON COLUMN peer_review_disputes.exercise_id IS 'The exercise the reviewed answer belongs to.';
+COMMENT ON COLUMN peer_review_disputes.course_id IS 'The course the review was given in.';
+COMMENT ON COLUMN peer_review_disputes.exercise_slide_submission_id IS 'The answer that received the disputed review.';
+COMMENT ON COLUMN peer_review_disputes.peer_or_self_review_submission_id IS 'The disputed peer review. A review can be disputed only once.';
+COMMENT ON COLUMN peer_review_disputes.reason IS 'Why the student thinks the review is unfair.';
+COMMENT ON COLUMN peer_review_disputes.status IS 'Whether the dispute is waiting for a teacher, and if not, how the teacher resolved it.';
+COMMENT ON COLUMN peer_review_disputes.resolved_by_user_id IS 'The teacher who resolved the dispute. Null while the dispute is pending.';
+COMMENT ON COLUMN peer_review_disputes.resolved_at IS 'Timestamp when the dispute was resolved. Null while the dispute is pending.';
+COMMENT ON COLUMN peer_review_disputes.teacher_response IS 'The teacher''s explanation of the resolution, shown to the student.';
+COMMENT ON COLUMN peer_review_disputes.teacher_grading_decision_id IS 'The grading decision the teacher made when overriding the grade. Set only when the status is grade-overridden.';
+COMMENT ON COLUMN peer_review_disputes.email_delivery_id IS 'The email that told the student about the resolution. Null if the dispute is pending or no email template exists for the resolution.';
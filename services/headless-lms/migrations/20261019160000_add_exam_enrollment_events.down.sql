@@ -0,0 +1,2 @@
+DROP TABLE exam_enrollment_events;
+DROP TYPE exam_enrollment_event_type;
@@ -0,0 +1,48 @@
+CREATE TYPE exam_enrollment_event_type AS ENUM (
+  'started',
+  'submission',
+  'ended',
+  'visibility-hidden',
+  'visibility-visible',
+  'focus-lost',
+  'focus-regained',
+  'heartbeat'
+);
+
+COMMENT ON TYPE exam_enrollment_event_type IS 'What happened during an exam. Started, submission and ended events are recorded by the server; the other events are reported by the exam page in the browser.';
+
+CREATE TABLE exam_enrollment_events (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  exam_id UUID NOT NULL REFERENCES exams(id),
+  user_id UUID NOT NULL REFERENCES users(id),
+  event_type exam_enrollment_event_type NOT NULL,
+  client_session_id UUID,
+  ip_address TEXT,
+  country_code VARCHAR(8),
+  exercise_slide_submission_id UUID REFERENCES exercise_slide_submissions(id),
+  CHECK (
+    (event_type = 'submission') = (exercise_slide_submission_id IS NOT NULL)
+  )
+);
+
+CREATE INDEX exam_enrollment_events_exam_id_user_id_created_at ON exam_enrollment_events (exam_id, user_id, created_at)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON exam_enrollment_events FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE exam_enrollment_events IS 'A log of what happened during a student''s exam, used to build the anomaly report teachers can review after the exam. The log never affects grading by itself.';
+COMMENT ON COLUMN exam_enrollment_events.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN exam_enrollment_events.created_at IS 'Timestamp when the record was created. The time of the event.';
+COMMENT ON COLUMN exam_enrollment_events.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN exam_enrollment_events.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN exam_enrollment_events.exam_id IS 'The exam the event happened in.';
+COMMENT ON COLUMN exam_enrollment_events.user_id IS 'The student taking the exam.';
+COMMENT ON COLUMN exam_enrollment_events.event_type IS 'What happened.';
+COMMENT ON COLUMN exam_enrollment_events.client_session_id IS 'Random identifier the exam page generates when it is opened, used to detect the exam being open in several browsers at the same time. Null for events recorded by the server.';
+COMMENT ON COLUMN exam_enrollment_events.ip_address IS 'The IP address the request came from, if known.';
+COMMENT ON COLUMN exam_enrollment_events.country_code IS 'The country the IP address maps to, if known.';
+COMMENT ON COLUMN exam_enrollment_events.exercise_slide_submission_id IS 'The answer that was submitted. Set only for submission events.';
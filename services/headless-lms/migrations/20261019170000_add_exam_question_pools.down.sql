@@ -0,0 +1,3 @@
+DROP TABLE exam_question_pool_exercises;
+DROP TABLE exam_question_pools;
+ALTER TABLE exam_enrollments DROP COLUMN question_pool_seed;
@@ -0,0 +1,55 @@
+ALTER TABLE exam_enrollments
+ADD COLUMN question_pool_seed BIGINT NOT NULL DEFAULT floor(random() * 9007199254740991)::BIGINT;
+
+COMMENT ON COLUMN exam_enrollments.question_pool_seed IS 'Seed for drawing the student''s exercises from the question pools of the exam. The draw is deterministic, so the same seed always gives the same exercises and exercise slides.';
+
+CREATE TABLE exam_question_pools (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  exam_id UUID NOT NULL REFERENCES exams(id),
+  name VARCHAR(255) NOT NULL,
+  draw_count INTEGER NOT NULL CHECK (draw_count >= 0)
+);
+
+CREATE INDEX exam_question_pools_exam_id ON exam_question_pools (exam_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON exam_question_pools FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE exam_question_pools IS 'A bank of interchangeable exam exercises. Each enrolled student gets draw_count of the exercises in the pool, drawn with the seed of their enrollment. Exercises that are not in any pool are given to every student.';
+COMMENT ON COLUMN exam_question_pools.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN exam_question_pools.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN exam_question_pools.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN exam_question_pools.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN exam_question_pools.exam_id IS 'The exam the pool belongs to.';
+COMMENT ON COLUMN exam_question_pools.name IS 'Name of the pool shown to teachers, e.g. the topic of the exercises.';
+COMMENT ON COLUMN exam_question_pools.draw_count IS 'How many exercises of the pool each student gets.';
+
+CREATE TABLE exam_question_pool_exercises (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  exam_question_pool_id UUID NOT NULL REFERENCES exam_question_pools(id),
+  exercise_id UUID NOT NULL REFERENCES exercises(id)
+);
+
+CREATE UNIQUE INDEX exam_question_pool_exercises_exercise_id_unique ON exam_question_pool_exercises (exercise_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX exam_question_pool_exercises_exam_question_pool_id ON exam_question_pool_exercises (exam_question_pool_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON exam_question_pool_exercises FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE exam_question_pool_exercises IS 'Tags an exam exercise as a member of a question pool. An exercise can be in at most one pool.';
+COMMENT ON COLUMN exam_question_pool_exercises.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN exam_question_pool_exercises.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN exam_question_pool_exercises.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN exam_question_pool_exercises.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN exam_question_pool_exercises.exam_question_pool_id IS 'The pool the exercise is in.';
+COMMENT ON COLUMN exam_question_pool_exercises.exercise_id IS 'The exam exercise in the pool.';
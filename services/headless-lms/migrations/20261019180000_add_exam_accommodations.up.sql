@@ -0,0 +1,40 @@
+CREATE TABLE exam_accommodations (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  exam_id UUID NOT NULL REFERENCES exams(id),
+  user_id UUID NOT NULL REFERENCES users(id),
+  time_multiplier REAL NOT NULL DEFAULT 1 CHECK (
+    time_multiplier >= 1
+    AND time_multiplier <= 10
+  ),
+  starts_at TIMESTAMP WITH TIME ZONE,
+  ends_at TIMESTAMP WITH TIME ZONE,
+  reason TEXT,
+  granted_by UUID NOT NULL REFERENCES users(id),
+  CHECK (
+    starts_at IS NULL
+    OR ends_at IS NULL
+    OR starts_at < ends_at
+  )
+);
+
+CREATE UNIQUE INDEX exam_accommodations_exam_id_user_id_unique ON exam_accommodations (exam_id, user_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON exam_accommodations FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE exam_accommodations IS 'Individual exam arrangements for a student: extra time and a personal window in place of the exam''s shared start and end times. Can be granted before the student enrolls.';
+COMMENT ON COLUMN exam_accommodations.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN exam_accommodations.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN exam_accommodations.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN exam_accommodations.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN exam_accommodations.exam_id IS 'The exam the accommodation applies to.';
+COMMENT ON COLUMN exam_accommodations.user_id IS 'The student the accommodation is for.';
+COMMENT ON COLUMN exam_accommodations.time_multiplier IS 'The exam''s time limit is multiplied by this and rounded up to whole minutes, e.g. 1.5 for 50% extra time.';
+COMMENT ON COLUMN exam_accommodations.starts_at IS 'When the student can start the exam, in place of exams.starts_at. If null, the exam''s own start time applies.';
+COMMENT ON COLUMN exam_accommodations.ends_at IS 'When the exam closes for the student, in place of exams.ends_at. If null, the exam''s own end time applies.';
+COMMENT ON COLUMN exam_accommodations.reason IS 'Optional note for teachers on why the accommodation was granted. Not shown to the student.';
+COMMENT ON COLUMN exam_accommodations.granted_by IS 'The teacher who granted or last changed the accommodation.';
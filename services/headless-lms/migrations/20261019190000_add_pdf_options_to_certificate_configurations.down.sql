@@ -0,0 +1,2 @@
+ALTER TABLE certificate_configurations DROP COLUMN pdf_enabled,
+  DROP COLUMN pdf_transcript_pages_enabled;
@@ -0,0 +1,6 @@
+ALTER TABLE certificate_configurations
+ADD COLUMN pdf_enabled BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN pdf_transcript_pages_enabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN certificate_configurations.pdf_enabled IS 'Whether the certificate can also be downloaded as a vector PDF with embedded fonts and selectable text.';
+COMMENT ON COLUMN certificate_configurations.pdf_transcript_pages_enabled IS 'Whether the PDF gets extra pages listing the grades and credits of the student''s completed course modules. Only used when pdf_enabled is true.';
@@ -0,0 +1 @@
+DROP TABLE certificate_text_elements;
@@ -0,0 +1,36 @@
+CREATE TABLE certificate_text_elements (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  certificate_configuration_id UUID NOT NULL REFERENCES certificate_configurations(id),
+  order_number INTEGER NOT NULL,
+  template VARCHAR(1000) NOT NULL,
+  x_pos VARCHAR(255) NOT NULL,
+  y_pos VARCHAR(255) NOT NULL,
+  font_size VARCHAR(255) NOT NULL,
+  text_color VARCHAR(255) NOT NULL,
+  text_anchor certificate_text_anchor NOT NULL DEFAULT 'middle',
+  font_family VARCHAR(255)
+);
+
+CREATE UNIQUE INDEX certificate_text_elements_configuration_order_unique ON certificate_text_elements (certificate_configuration_id, order_number)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON certificate_text_elements FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE certificate_text_elements IS 'Positioned texts printed on a certificate. The texts are templates whose placeholders, like {name} or {course_name}, are filled in from the student, the completion and the course module when rendering. If a certificate configuration has no text elements, the fixed owner name, date, validate URL and grade fields of the configuration are rendered instead.';
+COMMENT ON COLUMN certificate_text_elements.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN certificate_text_elements.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN certificate_text_elements.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN certificate_text_elements.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN certificate_text_elements.certificate_configuration_id IS 'The certificate configuration the text belongs to.';
+COMMENT ON COLUMN certificate_text_elements.order_number IS 'The order in which the texts are drawn. Later texts are drawn on top of earlier ones.';
+COMMENT ON COLUMN certificate_text_elements.template IS 'The text to print. Placeholders in curly braces, e.g. {name}, are replaced when rendering. Unknown placeholders are printed as is.';
+COMMENT ON COLUMN certificate_text_elements.x_pos IS 'The x coordinate of the text, as an SVG length, e.g. 50% or 1200px.';
+COMMENT ON COLUMN certificate_text_elements.y_pos IS 'The y coordinate of the text baseline, as an SVG length, e.g. 50% or 1200px.';
+COMMENT ON COLUMN certificate_text_elements.font_size IS 'The font size of the text, as an SVG length, e.g. 150px.';
+COMMENT ON COLUMN certificate_text_elements.text_color IS 'The color of the text, as an SVG color, e.g. black or #1a2333.';
+COMMENT ON COLUMN certificate_text_elements.text_anchor IS 'How the text is aligned relative to the coordinates. See https://developer.mozilla.org/en-US/docs/Web/SVG/Attribute/text-anchor.';
+COMMENT ON COLUMN certificate_text_elements.font_family IS 'The preferred font family, one of the fonts in certificate_fonts. If null, the default font is preferred. A font covering the whole text is used if the preferred one does not.';
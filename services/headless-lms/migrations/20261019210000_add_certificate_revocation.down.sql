@@ -0,0 +1,7 @@
+DROP INDEX generated_certificates_user_id_configuration_id_idx;
+
+ALTER TABLE generated_certificates DROP COLUMN revoked_at,
+  DROP COLUMN revocation_reason,
+  DROP COLUMN revoked_by_user_id,
+  DROP COLUMN superseded_by_id,
+  DROP COLUMN version;
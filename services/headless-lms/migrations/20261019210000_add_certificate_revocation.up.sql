@@ -0,0 +1,19 @@
+ALTER TABLE generated_certificates
+ADD COLUMN revoked_at TIMESTAMP WITH TIME ZONE,
+  ADD COLUMN revocation_reason TEXT,
+  ADD COLUMN revoked_by_user_id UUID REFERENCES users(id),
+  ADD COLUMN superseded_by_id UUID REFERENCES generated_certificates(id),
+  ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+  ADD CONSTRAINT generated_certificates_revocation_reason_check CHECK (
+    revoked_at IS NULL
+    OR revocation_reason IS NOT NULL
+  );
+
+CREATE INDEX generated_certificates_user_id_configuration_id_idx ON generated_certificates (user_id, certificate_configuration_id)
+WHERE deleted_at IS NULL;
+
+COMMENT ON COLUMN generated_certificates.revoked_at IS 'When the certificate was revoked. A revoked certificate stays verifiable, but verification shows it as revoked. If null, the certificate has not been revoked.';
+COMMENT ON COLUMN generated_certificates.revocation_reason IS 'Why the certificate was revoked. Shown to teachers only, not on the public verification page. Required when revoked_at is set.';
+COMMENT ON COLUMN generated_certificates.revoked_by_user_id IS 'The teacher who revoked the certificate. Null if the certificate was revoked automatically, for example because the completion it was issued for was removed.';
+COMMENT ON COLUMN generated_certificates.superseded_by_id IS 'The certificate that was issued to replace this one. A superseded certificate stays verifiable, but verification shows it as superseded and points to the newer version.';
+COMMENT ON COLUMN generated_certificates.version IS 'The version number of the certificate among the certificates issued to the user for the same certificate configuration. The first certificate is version 1 and each reissue or new certificate increments it.';
@@ -0,0 +1,4 @@
+DROP TABLE certificate_bulk_export_items;
+DROP TABLE certificate_bulk_exports;
+DROP TYPE certificate_bulk_export_format;
+DROP TYPE certificate_bulk_export_status;
@@ -0,0 +1,78 @@
+CREATE TYPE certificate_bulk_export_status AS ENUM ('queued', 'running', 'completed', 'failed');
+CREATE TYPE certificate_bulk_export_format AS ENUM ('png', 'pdf');
+
+CREATE TABLE certificate_bulk_exports (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  course_instance_id UUID NOT NULL REFERENCES course_instances(id),
+  certificate_configuration_id UUID NOT NULL REFERENCES certificate_configurations(id),
+  format certificate_bulk_export_format NOT NULL,
+  status certificate_bulk_export_status NOT NULL DEFAULT 'queued',
+  requested_by_user_id UUID NOT NULL REFERENCES users(id),
+  attempts INTEGER NOT NULL DEFAULT 0,
+  locked_at TIMESTAMP WITH TIME ZONE,
+  started_at TIMESTAMP WITH TIME ZONE,
+  finished_at TIMESTAMP WITH TIME ZONE,
+  error_message VARCHAR(1000),
+  zip_file_path VARCHAR(1000)
+);
+
+CREATE INDEX certificate_bulk_exports_course_instance_id_idx ON certificate_bulk_exports (course_instance_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX certificate_bulk_exports_pending_idx ON certificate_bulk_exports (created_at)
+WHERE deleted_at IS NULL
+  AND status IN ('queued', 'running');
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON certificate_bulk_exports FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE certificate_bulk_exports IS 'A background job that generates the certificates of every eligible student of a course instance and packs them into a ZIP file with a manifest. Picked up by the certificate-bulk-exporter program. A job whose worker stops heartbeating through locked_at is picked up again and continues from the items that are not processed yet.';
+COMMENT ON COLUMN certificate_bulk_exports.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN certificate_bulk_exports.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN certificate_bulk_exports.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN certificate_bulk_exports.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN certificate_bulk_exports.course_instance_id IS 'The course instance whose enrolled students are exported.';
+COMMENT ON COLUMN certificate_bulk_exports.certificate_configuration_id IS 'The certificate configuration the certificates are generated with.';
+COMMENT ON COLUMN certificate_bulk_exports.format IS 'Whether the certificates are rendered as PNG images or PDF documents.';
+COMMENT ON COLUMN certificate_bulk_exports.status IS 'Where the job is: queued, running, completed or failed.';
+COMMENT ON COLUMN certificate_bulk_exports.requested_by_user_id IS 'The teacher who requested the export.';
+COMMENT ON COLUMN certificate_bulk_exports.attempts IS 'How many times a worker has picked up the job. The job is marked as failed after too many attempts.';
+COMMENT ON COLUMN certificate_bulk_exports.locked_at IS 'When the worker processing the job last reported progress. If null or too old, another worker may pick up the job.';
+COMMENT ON COLUMN certificate_bulk_exports.started_at IS 'When a worker first picked up the job.';
+COMMENT ON COLUMN certificate_bulk_exports.finished_at IS 'When the job completed or failed.';
+COMMENT ON COLUMN certificate_bulk_exports.error_message IS 'Why the job failed. Errors with individual certificates are recorded on the items instead.';
+COMMENT ON COLUMN certificate_bulk_exports.zip_file_path IS 'Path of the finished ZIP file in the file store. Set when the job is completed.';
+
+CREATE TABLE certificate_bulk_export_items (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  certificate_bulk_export_id UUID NOT NULL REFERENCES certificate_bulk_exports(id),
+  user_id UUID NOT NULL REFERENCES users(id),
+  generated_certificate_id UUID REFERENCES generated_certificates(id),
+  file_path VARCHAR(1000),
+  error_message VARCHAR(1000),
+  processed_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE UNIQUE INDEX certificate_bulk_export_items_export_user_unique ON certificate_bulk_export_items (certificate_bulk_export_id, user_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON certificate_bulk_export_items FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE certificate_bulk_export_items IS 'One student in a certificate bulk export. Created for every eligible student when the export is requested, and marked as processed once the certificate file has been rendered or the student has been skipped.';
+COMMENT ON COLUMN certificate_bulk_export_items.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN certificate_bulk_export_items.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN certificate_bulk_export_items.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN certificate_bulk_export_items.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN certificate_bulk_export_items.certificate_bulk_export_id IS 'The export the item belongs to.';
+COMMENT ON COLUMN certificate_bulk_export_items.user_id IS 'The student whose certificate is exported.';
+COMMENT ON COLUMN certificate_bulk_export_items.generated_certificate_id IS 'The certificate that was exported. Either an existing certificate of the student or one generated for the export.';
+COMMENT ON COLUMN certificate_bulk_export_items.file_path IS 'Path of the rendered certificate in the file store. Null if the item is not processed yet or it failed.';
+COMMENT ON COLUMN certificate_bulk_export_items.error_message IS 'Why the certificate could not be exported, for example because the student has no name. Listed in the manifest of the ZIP file.';
+COMMENT ON COLUMN certificate_bulk_export_items.processed_at IS 'When the item was processed, successfully or not. Items with a value are skipped when a job is resumed.';
@@ -0,0 +1,6 @@
+DROP TABLE lti_deep_linking_requests;
+DROP TABLE lti_resource_link_users;
+DROP TABLE lti_resource_links;
+DROP TABLE lti_users;
+DROP TABLE lti_login_states;
+DROP TABLE lti_platforms;
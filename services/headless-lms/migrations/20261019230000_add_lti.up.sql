@@ -0,0 +1,203 @@
+CREATE TABLE lti_platforms (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  organization_id UUID NOT NULL REFERENCES organizations(id),
+  name VARCHAR(255) NOT NULL,
+  issuer VARCHAR(1000) NOT NULL,
+  client_id VARCHAR(255) NOT NULL,
+  deployment_ids VARCHAR(255) [] NOT NULL DEFAULT '{}',
+  auth_login_url VARCHAR(1000) NOT NULL,
+  auth_token_url VARCHAR(1000) NOT NULL,
+  jwks_url VARCHAR(1000) NOT NULL
+);
+
+CREATE UNIQUE INDEX lti_platforms_issuer_client_id_unique ON lti_platforms (issuer, client_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX lti_platforms_organization_id_idx ON lti_platforms (organization_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON lti_platforms FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE lti_platforms IS 'An LTI 1.3 platform, such as a Moodle or Canvas installation of a partner university, that is allowed to launch our course material. Registered by an organization admin with the values the platform shows when our tool is added to it. The platform may only launch courses of the organization.';
+COMMENT ON COLUMN lti_platforms.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN lti_platforms.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN lti_platforms.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN lti_platforms.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN lti_platforms.organization_id IS 'The organization whose courses the platform may launch.';
+COMMENT ON COLUMN lti_platforms.name IS 'A name for the platform shown to the admins, for example the name of the university.';
+COMMENT ON COLUMN lti_platforms.issuer IS 'The iss claim of the tokens the platform signs.';
+COMMENT ON COLUMN lti_platforms.client_id IS 'The client id the platform assigned to our tool. The aud claim of the tokens the platform signs.';
+COMMENT ON COLUMN lti_platforms.deployment_ids IS 'The deployments of our tool on the platform that may launch it. Launches from other deployments are rejected.';
+COMMENT ON COLUMN lti_platforms.auth_login_url IS 'The OIDC authorization endpoint of the platform the login initiation is redirected to.';
+COMMENT ON COLUMN lti_platforms.auth_token_url IS 'The OAuth 2.0 token endpoint of the platform, used to get access tokens for the grade and roster services.';
+COMMENT ON COLUMN lti_platforms.jwks_url IS 'Where the public keys of the platform are published. Used to verify the launch tokens.';
+
+CREATE TABLE lti_login_states (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  lti_platform_id UUID NOT NULL REFERENCES lti_platforms(id),
+  state VARCHAR(255) NOT NULL,
+  nonce VARCHAR(255) NOT NULL,
+  target_link_uri VARCHAR(2000) NOT NULL,
+  expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  used_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE UNIQUE INDEX lti_login_states_state_unique ON lti_login_states (state)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON lti_login_states FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE lti_login_states IS 'A third-party-initiated login that a platform has started and that has not been completed with a launch yet. Stored in the database instead of the session because the launch often happens in an iframe where the platform and our cookies are not shared.';
+COMMENT ON COLUMN lti_login_states.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN lti_login_states.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN lti_login_states.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN lti_login_states.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN lti_login_states.lti_platform_id IS 'The platform that initiated the login.';
+COMMENT ON COLUMN lti_login_states.state IS 'The random state sent to the platform in the authentication request. The platform returns it with the launch.';
+COMMENT ON COLUMN lti_login_states.nonce IS 'The random nonce sent to the platform in the authentication request. The launch token must contain it.';
+COMMENT ON COLUMN lti_login_states.target_link_uri IS 'Where the platform asked the user to be taken, as given in the login initiation.';
+COMMENT ON COLUMN lti_login_states.expires_at IS 'The launch must happen before this.';
+COMMENT ON COLUMN lti_login_states.used_at IS 'When a launch consumed the state. A state can only be used once.';
+
+CREATE TABLE lti_users (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  lti_platform_id UUID NOT NULL REFERENCES lti_platforms(id),
+  subject VARCHAR(255) NOT NULL,
+  user_id UUID NOT NULL REFERENCES users(id)
+);
+
+CREATE UNIQUE INDEX lti_users_platform_subject_unique ON lti_users (lti_platform_id, subject)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX lti_users_user_id_idx ON lti_users (user_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON lti_users FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE lti_users IS 'Links a user of an LTI platform to a user of ours. Created on the first launch of the user. Never linked to an existing user by email, because the platforms do not prove that the user owns the email address.';
+COMMENT ON COLUMN lti_users.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN lti_users.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN lti_users.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN lti_users.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN lti_users.lti_platform_id IS 'The platform the user comes from.';
+COMMENT ON COLUMN lti_users.subject IS 'The sub claim of the launch tokens, which identifies the user on the platform.';
+COMMENT ON COLUMN lti_users.user_id IS 'Our user the platform user is logged in as.';
+
+CREATE TABLE lti_resource_links (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  lti_platform_id UUID NOT NULL REFERENCES lti_platforms(id),
+  deployment_id VARCHAR(255) NOT NULL,
+  resource_link_id VARCHAR(255) NOT NULL,
+  context_id VARCHAR(255),
+  context_title VARCHAR(1000),
+  course_id UUID NOT NULL REFERENCES courses(id),
+  chapter_id UUID REFERENCES chapters(id),
+  exercise_id UUID REFERENCES exercises(id),
+  ags_lineitem_url VARCHAR(2000),
+  nrps_context_memberships_url VARCHAR(2000)
+);
+
+CREATE UNIQUE INDEX lti_resource_links_platform_resource_link_unique ON lti_resource_links (lti_platform_id, resource_link_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX lti_resource_links_course_id_idx ON lti_resource_links (course_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON lti_resource_links FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE lti_resource_links IS 'A link in a course of an LTI platform that launches our course material. Created or updated on every launch. Points to a whole course, a chapter or a single exercise, and the score of that content is passed back to the gradebook of the platform.';
+COMMENT ON COLUMN lti_resource_links.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN lti_resource_links.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN lti_resource_links.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN lti_resource_links.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN lti_resource_links.lti_platform_id IS 'The platform the link is on.';
+COMMENT ON COLUMN lti_resource_links.deployment_id IS 'The deployment of our tool the link was launched through.';
+COMMENT ON COLUMN lti_resource_links.resource_link_id IS 'The id of the link on the platform.';
+COMMENT ON COLUMN lti_resource_links.context_id IS 'The id of the course on the platform the link is in, if the platform sent one.';
+COMMENT ON COLUMN lti_resource_links.context_title IS 'The title of the course on the platform the link is in, if the platform sent one.';
+COMMENT ON COLUMN lti_resource_links.course_id IS 'The course the link launches.';
+COMMENT ON COLUMN lti_resource_links.chapter_id IS 'The chapter the link launches. If null and exercise_id is null, the link launches the whole course.';
+COMMENT ON COLUMN lti_resource_links.exercise_id IS 'The exercise the link launches. If set, only the score of this exercise is passed back.';
+COMMENT ON COLUMN lti_resource_links.ags_lineitem_url IS 'The gradebook column of the link on the platform, from the Assignment and Grade Services claim. If null, scores are not passed back.';
+COMMENT ON COLUMN lti_resource_links.nrps_context_memberships_url IS 'Where the roster of the platform course can be fetched, from the Names and Role Provisioning Services claim.';
+
+CREATE TABLE lti_resource_link_users (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  lti_resource_link_id UUID NOT NULL REFERENCES lti_resource_links(id),
+  user_id UUID NOT NULL REFERENCES users(id),
+  lti_user_id UUID NOT NULL REFERENCES lti_users(id),
+  last_launched_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  score_synced_at TIMESTAMP WITH TIME ZONE,
+  score_sync_failed_at TIMESTAMP WITH TIME ZONE,
+  score_sync_error VARCHAR(1000)
+);
+
+CREATE UNIQUE INDEX lti_resource_link_users_link_user_unique ON lti_resource_link_users (lti_resource_link_id, user_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON lti_resource_link_users FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE lti_resource_link_users IS 'A user who has launched a resource link. The lti-grade-syncer program passes the score of the user back to the platform whenever it has changed since the last sync.';
+COMMENT ON COLUMN lti_resource_link_users.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN lti_resource_link_users.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN lti_resource_link_users.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN lti_resource_link_users.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN lti_resource_link_users.lti_resource_link_id IS 'The resource link the user launched.';
+COMMENT ON COLUMN lti_resource_link_users.user_id IS 'The user who launched the link.';
+COMMENT ON COLUMN lti_resource_link_users.lti_user_id IS 'The platform user, whose subject the scores are reported for.';
+COMMENT ON COLUMN lti_resource_link_users.last_launched_at IS 'When the user last launched the link.';
+COMMENT ON COLUMN lti_resource_link_users.score_synced_at IS 'When the score of the user was last passed back successfully. Scores that have not changed since are not sent again.';
+COMMENT ON COLUMN lti_resource_link_users.score_sync_failed_at IS 'When passing back the score last failed. The sync is retried after a while.';
+COMMENT ON COLUMN lti_resource_link_users.score_sync_error IS 'Why passing back the score last failed. Cleared on a successful sync.';
+
+CREATE TABLE lti_deep_linking_requests (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  lti_platform_id UUID NOT NULL REFERENCES lti_platforms(id),
+  deployment_id VARCHAR(255) NOT NULL,
+  user_id UUID NOT NULL REFERENCES users(id),
+  deep_link_return_url VARCHAR(2000) NOT NULL,
+  data VARCHAR(2000),
+  accept_multiple BOOLEAN NOT NULL DEFAULT FALSE,
+  expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  used_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON lti_deep_linking_requests FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE lti_deep_linking_requests IS 'A Deep Linking launch where a teacher on a platform is picking which of our chapters or exercises to add to their course. Completed when the teacher has picked the content and the response is posted back to the platform.';
+COMMENT ON COLUMN lti_deep_linking_requests.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN lti_deep_linking_requests.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN lti_deep_linking_requests.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN lti_deep_linking_requests.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN lti_deep_linking_requests.lti_platform_id IS 'The platform the teacher is on.';
+COMMENT ON COLUMN lti_deep_linking_requests.deployment_id IS 'The deployment of our tool the request came through. Included in the response.';
+COMMENT ON COLUMN lti_deep_linking_requests.user_id IS 'The teacher who is picking the content. Only they can complete the request.';
+COMMENT ON COLUMN lti_deep_linking_requests.deep_link_return_url IS 'Where the response with the picked content is posted to.';
+COMMENT ON COLUMN lti_deep_linking_requests.data IS 'An opaque value from the platform that must be returned unchanged in the response.';
+COMMENT ON COLUMN lti_deep_linking_requests.accept_multiple IS 'Whether the platform accepts more than one content item.';
+COMMENT ON COLUMN lti_deep_linking_requests.expires_at IS 'The response must be sent before this.';
+COMMENT ON COLUMN lti_deep_linking_requests.used_at IS 'When the response was sent. A request can only be completed once.';
@@ -0,0 +1,4 @@
+DROP TABLE xapi_statements;
+DROP TABLE xapi_source_cursors;
+DROP TABLE course_xapi_opt_ins;
+DROP TYPE xapi_statement_source;
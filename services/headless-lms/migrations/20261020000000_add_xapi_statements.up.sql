@@ -0,0 +1,112 @@
+CREATE TYPE xapi_statement_source AS ENUM (
+  'exercise-slide-submission',
+  'exercise-task-grading',
+  'page-visit',
+  'peer-or-self-review-submission',
+  'course-module-completion'
+);
+
+COMMENT ON TYPE xapi_statement_source IS 'The table an xAPI statement was made from.';
+
+CREATE TABLE course_xapi_opt_ins (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  course_id UUID NOT NULL REFERENCES courses(id),
+  opted_in_by_user_id UUID NOT NULL REFERENCES users(id)
+);
+
+CREATE UNIQUE INDEX course_xapi_opt_ins_course_id_unique ON course_xapi_opt_ins (course_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON course_xapi_opt_ins FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE course_xapi_opt_ins IS 'A course whose learning events are sent to the learning record store as xAPI statements. Only the events that happen while the opt-in is active are sent. Opting out deletes the record.';
+COMMENT ON COLUMN course_xapi_opt_ins.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN course_xapi_opt_ins.created_at IS 'Timestamp when the record was created. Events before this are not sent.';
+COMMENT ON COLUMN course_xapi_opt_ins.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN course_xapi_opt_ins.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN course_xapi_opt_ins.course_id IS 'The course that has opted in.';
+COMMENT ON COLUMN course_xapi_opt_ins.opted_in_by_user_id IS 'The teacher who opted the course in.';
+
+CREATE TABLE xapi_source_cursors (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  source xapi_statement_source NOT NULL,
+  cursor_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  cursor_id UUID NOT NULL
+);
+
+CREATE UNIQUE INDEX xapi_source_cursors_source_unique ON xapi_source_cursors (source)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON xapi_source_cursors FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE xapi_source_cursors IS 'How far the xAPI sender has read each source table. The rows of a source are read in the order of (cursor_at, cursor_id), so the sender continues from the last row it has turned into statements.';
+COMMENT ON COLUMN xapi_source_cursors.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN xapi_source_cursors.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN xapi_source_cursors.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN xapi_source_cursors.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN xapi_source_cursors.source IS 'The source table.';
+COMMENT ON COLUMN xapi_source_cursors.cursor_at IS 'The timestamp of the last row read. The created_at of the row, or the grading_completed_at for gradings.';
+COMMENT ON COLUMN xapi_source_cursors.cursor_id IS 'The id of the last row read. Breaks ties between rows with the same timestamp.';
+
+CREATE TABLE xapi_statements (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  source xapi_statement_source NOT NULL,
+  source_id UUID NOT NULL,
+  course_id UUID NOT NULL REFERENCES courses(id),
+  user_id UUID REFERENCES users(id),
+  statement JSONB NOT NULL,
+  sent_at TIMESTAMP WITH TIME ZONE,
+  retry_count INTEGER NOT NULL DEFAULT 0,
+  next_retry_at TIMESTAMP WITH TIME ZONE,
+  retryable BOOLEAN NOT NULL DEFAULT TRUE,
+  first_failed_at TIMESTAMP WITH TIME ZONE,
+  last_attempt_at TIMESTAMP WITH TIME ZONE,
+  last_error TEXT
+);
+
+CREATE UNIQUE INDEX xapi_statements_source_source_id_unique ON xapi_statements (source, source_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX xapi_statements_send_queue_idx ON xapi_statements (next_retry_at)
+WHERE sent_at IS NULL
+  AND retryable = TRUE
+  AND deleted_at IS NULL;
+
+CREATE INDEX xapi_statements_course_id_idx ON xapi_statements (course_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX xapi_statements_user_id_idx ON xapi_statements (user_id)
+WHERE sent_at IS NULL
+  AND deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON xapi_statements FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE xapi_statements IS 'The outbox of the xAPI statements sent to the learning record store. A statement is made once from each row of a source table, and is sent in batches with retries and an exponential backoff like the email deliveries. Unsent statements of users who withdraw their research consent are deleted.';
+COMMENT ON COLUMN xapi_statements.id IS 'A unique, stable identifier for the record. Also the id of the statement, so a statement sent twice is stored only once in the learning record store.';
+COMMENT ON COLUMN xapi_statements.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN xapi_statements.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN xapi_statements.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN xapi_statements.source IS 'The table the statement was made from.';
+COMMENT ON COLUMN xapi_statements.source_id IS 'The id of the row the statement was made from.';
+COMMENT ON COLUMN xapi_statements.course_id IS 'The course the event happened on.';
+COMMENT ON COLUMN xapi_statements.user_id IS 'The user the statement is about. Null for page visits, which are not tied to a user and use the daily anonymous visitor identifier as the actor.';
+COMMENT ON COLUMN xapi_statements.statement IS 'The xAPI statement as sent to the learning record store.';
+COMMENT ON COLUMN xapi_statements.sent_at IS 'Timestamp when the learning record store accepted the statement. If null, the statement has not been sent yet.';
+COMMENT ON COLUMN xapi_statements.retry_count IS 'Number of failed attempts to send the statement so far.';
+COMMENT ON COLUMN xapi_statements.next_retry_at IS 'When the statement is sent next, or null if it can be sent right away. Also used as a lease while a sender is sending the statement.';
+COMMENT ON COLUMN xapi_statements.retryable IS 'Whether the statement is still retried. Set to false when the learning record store rejects the statement or the retry window has passed.';
+COMMENT ON COLUMN xapi_statements.first_failed_at IS 'Timestamp of the first failed attempt, used as the start of the retry window.';
+COMMENT ON COLUMN xapi_statements.last_attempt_at IS 'Timestamp of the most recent attempt to send the statement.';
+COMMENT ON COLUMN xapi_statements.last_error IS 'The error of the most recent failed attempt.';
@@ -0,0 +1,5 @@
+DROP TABLE webhook_deliveries;
+DROP TABLE webhook_source_cursors;
+DROP TABLE webhook_subscriptions;
+DROP TYPE webhook_delivery_status;
+DROP TYPE webhook_event_type;
@@ -0,0 +1,124 @@
+CREATE TYPE webhook_event_type AS ENUM (
+  'course-module-completion-created',
+  'certificate-generated',
+  'course-instance-enrollment-created',
+  'exam-ended',
+  'exercise-task-grading-updated',
+  'credit-registration-state-changed'
+);
+
+COMMENT ON TYPE webhook_event_type IS 'An event that webhook subscriptions can be notified of. Each event type is read from one source table.';
+
+CREATE TYPE webhook_delivery_status AS ENUM ('pending', 'delivered', 'dead');
+
+COMMENT ON TYPE webhook_delivery_status IS 'Pending deliveries are still being attempted, delivered ones were accepted by the receiver, and dead ones are not attempted again unless redelivered by hand.';
+
+CREATE TABLE webhook_subscriptions (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  organization_id UUID NOT NULL REFERENCES organizations(id),
+  course_id UUID REFERENCES courses(id),
+  url VARCHAR(2048) NOT NULL,
+  secret VARCHAR(255) NOT NULL,
+  event_types webhook_event_type [] NOT NULL CHECK (cardinality(event_types) > 0),
+  enabled BOOLEAN NOT NULL DEFAULT TRUE,
+  created_by_user_id UUID NOT NULL REFERENCES users(id)
+);
+
+CREATE INDEX webhook_subscriptions_organization_id_idx ON webhook_subscriptions (organization_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON webhook_subscriptions FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE webhook_subscriptions IS 'An endpoint that is sent the events of an organization, or of one of its courses, as signed HTTP POST requests.';
+COMMENT ON COLUMN webhook_subscriptions.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN webhook_subscriptions.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN webhook_subscriptions.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN webhook_subscriptions.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN webhook_subscriptions.organization_id IS 'The organization whose events are sent.';
+COMMENT ON COLUMN webhook_subscriptions.course_id IS 'If set, only the events of this course are sent. Exams belong to organizations, so exam events are only sent to subscriptions without a course.';
+COMMENT ON COLUMN webhook_subscriptions.url IS 'The endpoint the events are posted to.';
+COMMENT ON COLUMN webhook_subscriptions.secret IS 'The key the deliveries are signed with using HMAC-SHA256. Shown to the subscriber only when the subscription is created or the secret is rotated.';
+COMMENT ON COLUMN webhook_subscriptions.event_types IS 'The event types that are sent.';
+COMMENT ON COLUMN webhook_subscriptions.enabled IS 'Whether new events are sent. Events that happen while the subscription is disabled are not sent later.';
+COMMENT ON COLUMN webhook_subscriptions.created_by_user_id IS 'The user who created the subscription.';
+
+CREATE TABLE webhook_source_cursors (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  event_type webhook_event_type NOT NULL,
+  cursor_at TIMESTAMP WITH TIME ZONE NOT NULL,
+  cursor_id UUID NOT NULL
+);
+
+CREATE UNIQUE INDEX webhook_source_cursors_event_type_unique ON webhook_source_cursors (event_type)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON webhook_source_cursors FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE webhook_source_cursors IS 'How far the webhook deliverer has read the source table of each event type. The rows are read in the order of (cursor_at, cursor_id).';
+COMMENT ON COLUMN webhook_source_cursors.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN webhook_source_cursors.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN webhook_source_cursors.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN webhook_source_cursors.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN webhook_source_cursors.event_type IS 'The event type whose source table the cursor is for.';
+COMMENT ON COLUMN webhook_source_cursors.cursor_at IS 'The timestamp of the last row read. The created_at of the row, or the updated_at for gradings.';
+COMMENT ON COLUMN webhook_source_cursors.cursor_id IS 'The id of the last row read. Breaks ties between rows with the same timestamp.';
+
+CREATE TABLE webhook_deliveries (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  subscription_id UUID NOT NULL REFERENCES webhook_subscriptions(id),
+  event_id UUID NOT NULL,
+  event_type webhook_event_type NOT NULL,
+  source_id UUID NOT NULL,
+  payload JSONB NOT NULL,
+  status webhook_delivery_status NOT NULL DEFAULT 'pending',
+  attempt_count INTEGER NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  first_failed_at TIMESTAMP WITH TIME ZONE,
+  last_attempt_at TIMESTAMP WITH TIME ZONE,
+  last_response_status INTEGER,
+  last_error TEXT,
+  delivered_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE UNIQUE INDEX webhook_deliveries_subscription_id_event_id_unique ON webhook_deliveries (subscription_id, event_id)
+WHERE deleted_at IS NULL;
+
+CREATE INDEX webhook_deliveries_send_queue_idx ON webhook_deliveries (next_attempt_at)
+WHERE status = 'pending'
+  AND deleted_at IS NULL;
+
+CREATE INDEX webhook_deliveries_subscription_id_created_at_idx ON webhook_deliveries (subscription_id, created_at DESC)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON webhook_deliveries FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE webhook_deliveries IS 'The outbox of the webhook deliveries. An event is added once for every subscription it matches, and is posted with retries and an exponential backoff. Deliveries that fail for longer than the retry window, or are rejected by the receiver, are dead letters that can be redelivered by hand.';
+COMMENT ON COLUMN webhook_deliveries.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN webhook_deliveries.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN webhook_deliveries.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN webhook_deliveries.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN webhook_deliveries.subscription_id IS 'The subscription the event is delivered to.';
+COMMENT ON COLUMN webhook_deliveries.event_id IS 'The id of the event. The same for every subscription the event is delivered to, and sent in the payload so receivers can ignore events they have already handled.';
+COMMENT ON COLUMN webhook_deliveries.event_type IS 'The type of the event.';
+COMMENT ON COLUMN webhook_deliveries.source_id IS 'The id of the row the event was read from.';
+COMMENT ON COLUMN webhook_deliveries.payload IS 'The JSON body that is posted. The signature is computed over it when the delivery is attempted.';
+COMMENT ON COLUMN webhook_deliveries.status IS 'Whether the delivery is pending, delivered or dead.';
+COMMENT ON COLUMN webhook_deliveries.attempt_count IS 'Number of attempts to deliver the event so far.';
+COMMENT ON COLUMN webhook_deliveries.next_attempt_at IS 'When the delivery is attempted next. Also used as a lease while a deliverer is attempting it.';
+COMMENT ON COLUMN webhook_deliveries.first_failed_at IS 'Timestamp of the first failed attempt, used as the start of the retry window.';
+COMMENT ON COLUMN webhook_deliveries.last_attempt_at IS 'Timestamp of the most recent attempt.';
+COMMENT ON COLUMN webhook_deliveries.last_response_status IS 'The HTTP status the receiver responded with on the most recent attempt. Null if there was no response.';
+COMMENT ON COLUMN webhook_deliveries.last_error IS 'The error of the most recent failed attempt.';
+COMMENT ON COLUMN webhook_deliveries.delivered_at IS 'Timestamp when the receiver accepted the delivery.';
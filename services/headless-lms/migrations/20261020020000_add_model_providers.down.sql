@@ -0,0 +1,5 @@
+COMMENT ON TABLE chatbot_configurations_models IS 'Stores Azure OpenAI LLMs that have been deployed in Azure AI. The chatbot_configurations table references this table to indicate which LLM the configuration uses.';
+ALTER TABLE chatbot_configurations_models DROP COLUMN provider,
+  DROP COLUMN api_base_url,
+  DROP COLUMN api_key_env_var;
+DROP TYPE model_provider;
@@ -0,0 +1,16 @@
+CREATE TYPE model_provider AS ENUM ('azure', 'openai-compatible');
+
+COMMENT ON TYPE model_provider IS 'The kind of API a language model is served from. Azure models are called through the Azure OpenAI Responses API configured in the environment, and OpenAI-compatible models through the Chat Completions API of a server such as vLLM, llama.cpp or Ollama.';
+
+ALTER TABLE chatbot_configurations_models
+ADD COLUMN provider model_provider NOT NULL DEFAULT 'azure',
+  ADD COLUMN api_base_url VARCHAR(2048),
+  ADD COLUMN api_key_env_var VARCHAR(255),
+  ADD CONSTRAINT chatbot_configurations_models_api_base_url_check CHECK (
+    (provider = 'azure') = (api_base_url IS NULL)
+  );
+
+COMMENT ON COLUMN chatbot_configurations_models.provider IS 'The kind of API the model is served from.';
+COMMENT ON COLUMN chatbot_configurations_models.api_base_url IS 'The base URL of an OpenAI-compatible API, for example http://localhost:11434/v1/. Null for Azure models, which use the endpoint from the environment.';
+COMMENT ON COLUMN chatbot_configurations_models.api_key_env_var IS 'The name of the environment variable that holds the API key of an OpenAI-compatible API. The key itself is never stored in the database. If null, requests are sent without an API key.';
+COMMENT ON TABLE chatbot_configurations_models IS 'Stores the LLMs that the chatbot and other LLM features can use, either deployed in Azure AI or served from an OpenAI-compatible API. The chatbot_configurations table references this table to indicate which LLM the configuration uses.';
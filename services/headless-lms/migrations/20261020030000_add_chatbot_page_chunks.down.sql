@@ -0,0 +1,4 @@
+DROP TABLE chatbot_page_chunks;
+
+ALTER TABLE chatbot_configurations DROP CONSTRAINT chatbot_configurations_one_search_backend_check,
+  DROP COLUMN use_builtin_search;
@@ -0,0 +1,51 @@
+ALTER TABLE chatbot_configurations
+ADD COLUMN use_builtin_search BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD CONSTRAINT chatbot_configurations_one_search_backend_check CHECK (
+    NOT (
+      use_azure_search
+      AND use_builtin_search
+    )
+  );
+
+COMMENT ON COLUMN chatbot_configurations.use_builtin_search IS 'If true, the chatbot searches the course material from the chatbot_page_chunks table in this database instead of Azure AI Search. Cannot be used together with use_azure_search.';
+
+CREATE TABLE chatbot_page_chunks (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  course_id UUID NOT NULL REFERENCES courses,
+  page_id UUID NOT NULL REFERENCES pages,
+  page_history_id UUID NOT NULL REFERENCES page_history,
+  chunk_number INTEGER NOT NULL CHECK (chunk_number >= 0),
+  context TEXT NOT NULL,
+  content TEXT NOT NULL,
+  search_document tsvector NOT NULL,
+  embedding vector(1536) NOT NULL
+);
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON chatbot_page_chunks FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE UNIQUE INDEX chatbot_page_chunks_page_id_chunk_number_idx ON chatbot_page_chunks (page_id, chunk_number)
+WHERE deleted_at IS NULL;
+CREATE INDEX chatbot_page_chunks_course_id_idx ON chatbot_page_chunks (course_id)
+WHERE deleted_at IS NULL;
+CREATE INDEX chatbot_page_chunks_search_document_idx ON chatbot_page_chunks USING GIN (search_document)
+WHERE deleted_at IS NULL;
+CREATE INDEX chatbot_page_chunks_embedding_idx ON chatbot_page_chunks USING hnsw (embedding vector_cosine_ops)
+WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE chatbot_page_chunks IS 'The course material index of chatbots that use the built-in search. The Markdown content of each public course page is split into chunks, and each chunk is stored with an embedding vector for semantic search and a text search document for keyword search.';
+COMMENT ON COLUMN chatbot_page_chunks.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN chatbot_page_chunks.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN chatbot_page_chunks.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN chatbot_page_chunks.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN chatbot_page_chunks.course_id IS 'The course the page belongs to. Searches are limited to one course.';
+COMMENT ON COLUMN chatbot_page_chunks.page_id IS 'The page the chunk is from.';
+COMMENT ON COLUMN chatbot_page_chunks.page_history_id IS 'The revision of the page the chunk was made from. When the page has a newer revision, its chunks are replaced.';
+COMMENT ON COLUMN chatbot_page_chunks.chunk_number IS 'The position of the chunk in the page, starting from 0.';
+COMMENT ON COLUMN chatbot_page_chunks.context IS 'Where the chunk is in the page, as the page title followed by the headings the chunk is under.';
+COMMENT ON COLUMN chatbot_page_chunks.content IS 'The Markdown content of the chunk.';
+COMMENT ON COLUMN chatbot_page_chunks.search_document IS 'Text search document of the context and the content, used for keyword search. Stemmed in the language in courses.content_search_language.';
+COMMENT ON COLUMN chatbot_page_chunks.embedding IS 'Embedding vector of the context and the content.';
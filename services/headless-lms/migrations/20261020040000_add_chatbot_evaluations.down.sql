@@ -0,0 +1,23 @@
+DROP TABLE chatbot_evaluation_results;
+DROP TABLE chatbot_evaluation_runs;
+DROP TABLE chatbot_evaluation_questions;
+DROP TABLE chatbot_evaluation_sets;
+DROP TYPE chatbot_evaluation_run_status;
+
+DELETE FROM application_task_default_language_models
+WHERE task = 'chatbot-evaluation';
+
+ALTER TYPE application_task
+RENAME TO application_task_old;
+
+CREATE TYPE application_task AS ENUM (
+  'content-cleaning',
+  'message-suggestion',
+  'cms-paragraph-suggestion',
+  'sisu-description-summary'
+);
+
+ALTER TABLE application_task_default_language_models
+ALTER COLUMN task TYPE application_task USING task::text::application_task;
+
+DROP TYPE application_task_old;
@@ -0,0 +1,139 @@
+ALTER TYPE application_task
+ADD VALUE IF NOT EXISTS 'chatbot-evaluation';
+
+CREATE TYPE chatbot_evaluation_run_status AS ENUM ('pending', 'running', 'completed', 'failed');
+
+COMMENT ON TYPE chatbot_evaluation_run_status IS 'The state of a chatbot evaluation run. Runs are created as pending and the chatbot-evaluation-runner program picks them up.';
+
+CREATE TABLE chatbot_evaluation_sets (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  course_id UUID NOT NULL REFERENCES courses,
+  name VARCHAR(255) NOT NULL CHECK (TRIM(name) <> '')
+);
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON chatbot_evaluation_sets FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE INDEX chatbot_evaluation_sets_course_id_idx ON chatbot_evaluation_sets (course_id)
+WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE chatbot_evaluation_sets IS 'A set of golden questions with expected answers that a course''s chatbots are evaluated with. Running the set against a chatbot configuration measures the quality of its answers so that changes to prompts, models and search settings can be compared.';
+COMMENT ON COLUMN chatbot_evaluation_sets.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN chatbot_evaluation_sets.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN chatbot_evaluation_sets.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN chatbot_evaluation_sets.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN chatbot_evaluation_sets.course_id IS 'The course whose chatbots the set evaluates.';
+COMMENT ON COLUMN chatbot_evaluation_sets.name IS 'Name of the set shown to teachers.';
+
+CREATE TABLE chatbot_evaluation_questions (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  evaluation_set_id UUID NOT NULL REFERENCES chatbot_evaluation_sets,
+  question TEXT NOT NULL CHECK (TRIM(question) <> ''),
+  expected_answer TEXT NOT NULL,
+  expected_page_ids UUID [] NOT NULL DEFAULT '{}',
+  rubric TEXT
+);
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON chatbot_evaluation_questions FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE INDEX chatbot_evaluation_questions_evaluation_set_id_idx ON chatbot_evaluation_questions (evaluation_set_id)
+WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE chatbot_evaluation_questions IS 'A golden question in a chatbot evaluation set, with the answer and the sources a good chatbot answer is expected to have.';
+COMMENT ON COLUMN chatbot_evaluation_questions.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN chatbot_evaluation_questions.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN chatbot_evaluation_questions.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN chatbot_evaluation_questions.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN chatbot_evaluation_questions.evaluation_set_id IS 'The set the question belongs to.';
+COMMENT ON COLUMN chatbot_evaluation_questions.question IS 'The message that is sent to the chatbot as a student would send it.';
+COMMENT ON COLUMN chatbot_evaluation_questions.expected_answer IS 'A correct answer written by a teacher. The judge compares the chatbot''s answer to it.';
+COMMENT ON COLUMN chatbot_evaluation_questions.expected_page_ids IS 'The course pages the answer should cite. Empty if the answer should not need any.';
+COMMENT ON COLUMN chatbot_evaluation_questions.rubric IS 'Additional criteria the answer is graded on, for example "Mentions that the deadline cannot be extended". If null, the answer is not graded on a rubric.';
+
+CREATE TABLE chatbot_evaluation_runs (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  evaluation_set_id UUID NOT NULL REFERENCES chatbot_evaluation_sets,
+  chatbot_configuration_id UUID NOT NULL REFERENCES chatbot_configurations,
+  created_by_user_id UUID NOT NULL REFERENCES users,
+  configuration_snapshot JSONB NOT NULL,
+  status chatbot_evaluation_run_status NOT NULL DEFAULT 'pending',
+  started_at TIMESTAMP WITH TIME ZONE,
+  finished_at TIMESTAMP WITH TIME ZONE,
+  error_message TEXT
+);
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON chatbot_evaluation_runs FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE INDEX chatbot_evaluation_runs_evaluation_set_id_idx ON chatbot_evaluation_runs (evaluation_set_id)
+WHERE deleted_at IS NULL;
+CREATE INDEX chatbot_evaluation_runs_pending_idx ON chatbot_evaluation_runs (created_at)
+WHERE status = 'pending'
+  AND deleted_at IS NULL;
+
+COMMENT ON TABLE chatbot_evaluation_runs IS 'A run of a chatbot evaluation set against a chatbot configuration. Each question of the set is sent to the chatbot in a conversation of its own and the answer is scored.';
+COMMENT ON COLUMN chatbot_evaluation_runs.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN chatbot_evaluation_runs.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN chatbot_evaluation_runs.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN chatbot_evaluation_runs.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN chatbot_evaluation_runs.evaluation_set_id IS 'The set of questions that is run.';
+COMMENT ON COLUMN chatbot_evaluation_runs.chatbot_configuration_id IS 'The chatbot configuration the questions are sent to.';
+COMMENT ON COLUMN chatbot_evaluation_runs.created_by_user_id IS 'The teacher who started the run. The evaluation conversations are made in their name.';
+COMMENT ON COLUMN chatbot_evaluation_runs.configuration_snapshot IS 'The chatbot configuration and its model when the run was started, so that runs can be compared after the configuration has changed.';
+COMMENT ON COLUMN chatbot_evaluation_runs.status IS 'Whether the run is waiting, running, finished or failed.';
+COMMENT ON COLUMN chatbot_evaluation_runs.started_at IS 'When the runner started the run.';
+COMMENT ON COLUMN chatbot_evaluation_runs.finished_at IS 'When the run completed or failed.';
+COMMENT ON COLUMN chatbot_evaluation_runs.error_message IS 'Why the run failed. Failures of single questions are stored in chatbot_evaluation_results instead.';
+
+CREATE TABLE chatbot_evaluation_results (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  evaluation_run_id UUID NOT NULL REFERENCES chatbot_evaluation_runs,
+  evaluation_question_id UUID NOT NULL REFERENCES chatbot_evaluation_questions,
+  conversation_id UUID REFERENCES chatbot_conversations,
+  answer TEXT,
+  cited_page_ids UUID [] NOT NULL DEFAULT '{}',
+  citation_precision REAL CHECK (citation_precision BETWEEN 0 AND 1),
+  citation_recall REAL CHECK (citation_recall BETWEEN 0 AND 1),
+  groundedness_score INTEGER CHECK (groundedness_score BETWEEN 1 AND 5),
+  correctness_score INTEGER CHECK (correctness_score BETWEEN 1 AND 5),
+  rubric_score INTEGER CHECK (rubric_score BETWEEN 1 AND 5),
+  judge_feedback TEXT,
+  error_message TEXT
+);
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON chatbot_evaluation_results FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE UNIQUE INDEX chatbot_evaluation_results_run_question_idx ON chatbot_evaluation_results (evaluation_run_id, evaluation_question_id)
+WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE chatbot_evaluation_results IS 'The chatbot''s answer to one question of an evaluation run and its scores.';
+COMMENT ON COLUMN chatbot_evaluation_results.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN chatbot_evaluation_results.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN chatbot_evaluation_results.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN chatbot_evaluation_results.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN chatbot_evaluation_results.evaluation_run_id IS 'The run the result belongs to.';
+COMMENT ON COLUMN chatbot_evaluation_results.evaluation_question_id IS 'The question that was asked.';
+COMMENT ON COLUMN chatbot_evaluation_results.conversation_id IS 'The conversation the question was asked in. The conversation is deleted after the answer so that it is not shown to the teacher as their own conversation, but it is kept for inspecting the answer.';
+COMMENT ON COLUMN chatbot_evaluation_results.answer IS 'The chatbot''s answer. Null if the chatbot failed to answer.';
+COMMENT ON COLUMN chatbot_evaluation_results.cited_page_ids IS 'The course pages the answer cited, either as citations or as links to the pages.';
+COMMENT ON COLUMN chatbot_evaluation_results.citation_precision IS 'The share of the cited pages that were expected. Null if the answer cited no pages.';
+COMMENT ON COLUMN chatbot_evaluation_results.citation_recall IS 'The share of the expected pages that were cited. Null if no pages were expected.';
+COMMENT ON COLUMN chatbot_evaluation_results.groundedness_score IS 'How well the claims of the answer are supported by the course material the chatbot retrieved, from 1 to 5, as graded by the judge model. Null if the chatbot retrieved nothing.';
+COMMENT ON COLUMN chatbot_evaluation_results.correctness_score IS 'How well the answer agrees with the expected answer, from 1 to 5, as graded by the judge model.';
+COMMENT ON COLUMN chatbot_evaluation_results.rubric_score IS 'How well the answer meets the rubric of the question, from 1 to 5, as graded by the judge model. Null if the question has no rubric.';
+COMMENT ON COLUMN chatbot_evaluation_results.judge_feedback IS 'The judge model''s explanation of the scores.';
+COMMENT ON COLUMN chatbot_evaluation_results.error_message IS 'Why answering or scoring the question failed. Null if it succeeded.';
@@ -0,0 +1 @@
+DROP TABLE chatbot_message_embeddings;
@@ -0,0 +1,22 @@
+CREATE TABLE chatbot_message_embeddings (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  chatbot_conversation_message_id UUID NOT NULL REFERENCES chatbot_conversation_messages,
+  embedding vector(1536) NOT NULL
+);
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON chatbot_message_embeddings FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+CREATE UNIQUE INDEX chatbot_message_embeddings_message_id_idx ON chatbot_message_embeddings (chatbot_conversation_message_id)
+WHERE deleted_at IS NULL;
+
+COMMENT ON TABLE chatbot_message_embeddings IS 'Embeddings of the questions students have sent to chatbots, used to group similar questions into topics in the chatbot analytics. An embedding is created the first time the analytics include the message.';
+COMMENT ON COLUMN chatbot_message_embeddings.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN chatbot_message_embeddings.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN chatbot_message_embeddings.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN chatbot_message_embeddings.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted.';
+COMMENT ON COLUMN chatbot_message_embeddings.chatbot_conversation_message_id IS 'The user message the embedding is made from.';
+COMMENT ON COLUMN chatbot_message_embeddings.embedding IS 'Embedding vector of the text of the message.';
@@ -0,0 +1 @@
+DROP TABLE chatbot_tutoring_exercises;
@@ -0,0 +1,20 @@
+CREATE TABLE chatbot_tutoring_exercises (
+  id UUID DEFAULT uuid_generate_v4() PRIMARY KEY,
+  created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+  deleted_at TIMESTAMP WITH TIME ZONE,
+  exercise_id UUID NOT NULL REFERENCES exercises(id)
+);
+
+CREATE UNIQUE INDEX chatbot_tutoring_exercises_exercise_id_unique ON chatbot_tutoring_exercises (exercise_id)
+WHERE deleted_at IS NULL;
+
+CREATE TRIGGER set_timestamp BEFORE
+UPDATE ON chatbot_tutoring_exercises FOR EACH ROW EXECUTE PROCEDURE trigger_set_timestamp();
+
+COMMENT ON TABLE chatbot_tutoring_exercises IS 'The exercises a teacher has allowed the course chatbot to tutor students on. For these exercises the chatbot can read the assignment, the public spec and the student''s latest submission and grading, but never the model solution.';
+COMMENT ON COLUMN chatbot_tutoring_exercises.id IS 'A unique, stable identifier for the record.';
+COMMENT ON COLUMN chatbot_tutoring_exercises.created_at IS 'Timestamp when the record was created.';
+COMMENT ON COLUMN chatbot_tutoring_exercises.updated_at IS 'Timestamp when the record was last updated. The field is updated automatically by the set_timestamp trigger.';
+COMMENT ON COLUMN chatbot_tutoring_exercises.deleted_at IS 'Timestamp when the record was deleted. If null, the record is not deleted. Deleting the record disables tutoring for the exercise.';
+COMMENT ON COLUMN chatbot_tutoring_exercises.exercise_id IS 'The exercise tutoring is enabled for.';
@@ -1,4 +1,7 @@
-use crate::{chatbot_configurations_models::ModelType, prelude::*};
+use crate::{
+    chatbot_configurations_models::{ModelProvider, ModelType},
+    prelude::*,
+};
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Type)]
 #[sqlx(type_name = "application_task", rename_all = "kebab-case")]
@@ -7,6 +10,7 @@ pub enum ApplicationTask {
     MessageSuggestion,
     CmsParagraphSuggestion,
     SisuDescriptionSummary,
+    ChatbotEvaluation,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -41,6 +45,9 @@ pub struct TaskLMSpec {
     pub model: String,
     pub model_type: ModelType,
     pub context_size: i32,
+    pub provider: ModelProvider,
+    pub api_base_url: Option<String>,
+    pub api_key_env_var: Option<String>,
 }
 
 pub async fn insert(
@@ -98,7 +105,10 @@ SELECT
     a.context_utilization,
     model.model,
     model.model_type as "model_type: ModelType",
-    model.context_size
+    model.context_size,
+    model.provider as "provider: ModelProvider",
+    model.api_base_url,
+    model.api_key_env_var
 FROM application_task_default_language_models AS a
 JOIN chatbot_configurations_models AS model ON model.id = a.model_id
 WHERE a.task = $1
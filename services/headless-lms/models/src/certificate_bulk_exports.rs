@@ -0,0 +1,569 @@
+//! Background jobs that render the certificates of a whole course instance into a ZIP file.
+//!
+//! The items of an export are fixed when it is requested, so the job can be resumed from the
+//! items that are not processed yet if the worker running it stops.
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+/// A job is marked as failed after it has been picked up this many times without finishing.
+pub const MAX_ATTEMPTS: i32 = 3;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, sqlx::Type, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(
+    type_name = "certificate_bulk_export_status",
+    rename_all = "kebab-case"
+)]
+pub enum CertificateBulkExportStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, sqlx::Type, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(
+    type_name = "certificate_bulk_export_format",
+    rename_all = "kebab-case"
+)]
+pub enum CertificateBulkExportFormat {
+    Png,
+    Pdf,
+}
+
+impl CertificateBulkExportFormat {
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Pdf => "pdf",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Pdf => "application/pdf",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct CertificateBulkExport {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub course_instance_id: Uuid,
+    pub certificate_configuration_id: Uuid,
+    pub format: CertificateBulkExportFormat,
+    pub status: CertificateBulkExportStatus,
+    pub requested_by_user_id: Uuid,
+    pub attempts: i32,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error_message: Option<String>,
+    pub zip_file_path: Option<String>,
+    /// How many students the export covers.
+    pub total_count: i64,
+    /// How many of the students have been processed, successfully or not.
+    pub processed_count: i64,
+    /// How many of the processed students could not be exported.
+    pub failed_count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct CertificateBulkExportItem {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub certificate_bulk_export_id: Uuid,
+    pub user_id: Uuid,
+    pub generated_certificate_id: Option<Uuid>,
+    pub file_path: Option<String>,
+    pub error_message: Option<String>,
+    pub processed_at: Option<DateTime<Utc>>,
+}
+
+/// Creates an export and its items. Every student enrolled in the course instance who has
+/// completed all the modules the certificate configuration requires gets an item.
+pub async fn insert(
+    conn: &mut PgConnection,
+    course_instance_id: Uuid,
+    certificate_configuration_id: Uuid,
+    format: CertificateBulkExportFormat,
+    requested_by_user_id: Uuid,
+) -> ModelResult<CertificateBulkExport> {
+    let mut tx = conn.begin().await?;
+    let course_instance =
+        crate::course_instances::get_course_instance(&mut tx, course_instance_id).await?;
+    let requirements =
+        crate::certificate_configuration_to_requirements::get_all_requirements_for_certificate_configuration(
+            &mut tx,
+            certificate_configuration_id,
+        )
+        .await?;
+    if requirements.course_module_ids.is_empty() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The certificate configuration has no requirements.".to_string()
+        ));
+    }
+    let course_modules =
+        crate::course_modules::get_by_ids(&mut tx, &requirements.course_module_ids).await?;
+    if course_modules
+        .iter()
+        .any(|module| module.course_id != course_instance.course_id)
+    {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The certificate configuration does not belong to the course of the course instance."
+                .to_string()
+        ));
+    }
+
+    let id = sqlx::query!(
+        r#"
+INSERT INTO certificate_bulk_exports (
+    course_instance_id,
+    certificate_configuration_id,
+    format,
+    requested_by_user_id
+  )
+VALUES ($1, $2, $3, $4)
+RETURNING id
+        "#,
+        course_instance_id,
+        certificate_configuration_id,
+        format as CertificateBulkExportFormat,
+        requested_by_user_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+    // Mirrors `CertificateAllRequirements::has_user_completed_all_requirements`, which
+    // `generated_certificates::generate_and_insert` checks again for each student.
+    sqlx::query!(
+        r#"
+INSERT INTO certificate_bulk_export_items (certificate_bulk_export_id, user_id)
+SELECT $1,
+  e.user_id
+FROM course_instance_enrollments e
+WHERE e.course_instance_id = $2
+  AND e.deleted_at IS NULL
+  AND NOT EXISTS (
+    SELECT 1
+    FROM certificate_configuration_to_requirements r
+    WHERE r.certificate_configuration_id = $3
+      AND r.course_module_id IS NOT NULL
+      AND r.deleted_at IS NULL
+      AND NOT EXISTS (
+        SELECT 1
+        FROM course_module_completions c
+        WHERE c.user_id = e.user_id
+          AND c.course_module_id = r.course_module_id
+          AND c.needs_to_be_reviewed = FALSE
+          AND c.deleted_at IS NULL
+      )
+  )
+        "#,
+        id,
+        course_instance_id,
+        certificate_configuration_id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    let res = get_by_id(&mut tx, id).await?;
+    tx.commit().await?;
+    Ok(res)
+}
+
+pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<CertificateBulkExport> {
+    let res = sqlx::query_as!(
+        CertificateBulkExport,
+        r#"
+SELECT e.id,
+  e.created_at,
+  e.updated_at,
+  e.course_instance_id,
+  e.certificate_configuration_id,
+  e.format,
+  e.status,
+  e.requested_by_user_id,
+  e.attempts,
+  e.started_at,
+  e.finished_at,
+  e.error_message,
+  e.zip_file_path,
+  COUNT(i.id) AS "total_count!",
+  COUNT(i.processed_at) AS "processed_count!",
+  COUNT(i.error_message) AS "failed_count!"
+FROM certificate_bulk_exports e
+  LEFT JOIN certificate_bulk_export_items i ON i.certificate_bulk_export_id = e.id
+  AND i.deleted_at IS NULL
+WHERE e.id = $1
+  AND e.deleted_at IS NULL
+GROUP BY e.id
+        "#,
+        id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_course_instance_id(
+    conn: &mut PgConnection,
+    course_instance_id: Uuid,
+) -> ModelResult<Vec<CertificateBulkExport>> {
+    let res = sqlx::query_as!(
+        CertificateBulkExport,
+        r#"
+SELECT e.id,
+  e.created_at,
+  e.updated_at,
+  e.course_instance_id,
+  e.certificate_configuration_id,
+  e.format,
+  e.status,
+  e.requested_by_user_id,
+  e.attempts,
+  e.started_at,
+  e.finished_at,
+  e.error_message,
+  e.zip_file_path,
+  COUNT(i.id) AS "total_count!",
+  COUNT(i.processed_at) AS "processed_count!",
+  COUNT(i.error_message) AS "failed_count!"
+FROM certificate_bulk_exports e
+  LEFT JOIN certificate_bulk_export_items i ON i.certificate_bulk_export_id = e.id
+  AND i.deleted_at IS NULL
+WHERE e.course_instance_id = $1
+  AND e.deleted_at IS NULL
+GROUP BY e.id
+ORDER BY e.created_at DESC
+        "#,
+        course_instance_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Claims the oldest queued job, or a running one whose worker has not reported progress since
+/// `stale_lock_cutoff`. Concurrent workers never claim the same job.
+pub async fn claim_next(
+    conn: &mut PgConnection,
+    stale_lock_cutoff: DateTime<Utc>,
+) -> ModelResult<Option<Uuid>> {
+    let res = sqlx::query!(
+        r#"
+UPDATE certificate_bulk_exports
+SET status = 'running',
+  locked_at = now(),
+  started_at = COALESCE(started_at, now()),
+  attempts = attempts + 1
+WHERE id = (
+    SELECT id
+    FROM certificate_bulk_exports
+    WHERE deleted_at IS NULL
+      AND (
+        status = 'queued'
+        OR (
+          status = 'running'
+          AND (
+            locked_at IS NULL
+            OR locked_at < $1
+          )
+        )
+      )
+    ORDER BY created_at
+    LIMIT 1 FOR UPDATE SKIP LOCKED
+  )
+RETURNING id
+        "#,
+        stale_lock_cutoff
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res.map(|r| r.id))
+}
+
+/// Tells other workers that the job is still being worked on.
+pub async fn refresh_lock(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE certificate_bulk_exports
+SET locked_at = now()
+WHERE id = $1
+        ",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Lets the job be picked up again on a later run, continuing from the unprocessed items.
+pub async fn release_lock(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE certificate_bulk_exports
+SET locked_at = NULL
+WHERE id = $1
+        ",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_completed(
+    conn: &mut PgConnection,
+    id: Uuid,
+    zip_file_path: &str,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE certificate_bulk_exports
+SET status = 'completed',
+  zip_file_path = $2,
+  finished_at = now(),
+  locked_at = NULL,
+  error_message = NULL
+WHERE id = $1
+        ",
+        id,
+        zip_file_path
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_failed(
+    conn: &mut PgConnection,
+    id: Uuid,
+    error_message: &str,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE certificate_bulk_exports
+SET status = 'failed',
+  error_message = $2,
+  finished_at = now(),
+  locked_at = NULL
+WHERE id = $1
+        ",
+        id,
+        error_message
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// All the items of the export, in a stable order.
+pub async fn get_items(
+    conn: &mut PgConnection,
+    certificate_bulk_export_id: Uuid,
+) -> ModelResult<Vec<CertificateBulkExportItem>> {
+    let res = sqlx::query_as!(
+        CertificateBulkExportItem,
+        "
+SELECT *
+FROM certificate_bulk_export_items
+WHERE certificate_bulk_export_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at,
+  id
+        ",
+        certificate_bulk_export_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_unprocessed_items(
+    conn: &mut PgConnection,
+    certificate_bulk_export_id: Uuid,
+) -> ModelResult<Vec<CertificateBulkExportItem>> {
+    let res = sqlx::query_as!(
+        CertificateBulkExportItem,
+        "
+SELECT *
+FROM certificate_bulk_export_items
+WHERE certificate_bulk_export_id = $1
+  AND processed_at IS NULL
+  AND deleted_at IS NULL
+ORDER BY created_at,
+  id
+        ",
+        certificate_bulk_export_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn mark_item_exported(
+    conn: &mut PgConnection,
+    item_id: Uuid,
+    generated_certificate_id: Uuid,
+    file_path: &str,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE certificate_bulk_export_items
+SET generated_certificate_id = $2,
+  file_path = $3,
+  error_message = NULL,
+  processed_at = now()
+WHERE id = $1
+        ",
+        item_id,
+        generated_certificate_id,
+        file_path
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_item_failed(
+    conn: &mut PgConnection,
+    item_id: Uuid,
+    generated_certificate_id: Option<Uuid>,
+    error_message: &str,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE certificate_bulk_export_items
+SET generated_certificate_id = $2,
+  error_message = $3,
+  processed_at = now()
+WHERE id = $1
+        ",
+        item_id,
+        generated_certificate_id,
+        error_message
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        course_instance_enrollments::NewCourseInstanceEnrollment,
+        course_module_completions::{CourseModuleCompletionGranter, NewCourseModuleCompletion},
+        test_helper::*,
+    };
+
+    #[tokio::test]
+    async fn only_students_who_completed_the_requirements_are_exported() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module);
+        let configuration = insert_certificate_configuration(tx.as_mut(), course_module.id).await;
+        let other_user = crate::users::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            "other@example.com",
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        for user_id in [user, other_user] {
+            crate::course_instance_enrollments::insert_enrollment_if_it_doesnt_exist(
+                tx.as_mut(),
+                NewCourseInstanceEnrollment {
+                    user_id,
+                    course_id: course,
+                    course_instance_id: instance.id,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        crate::course_module_completions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &NewCourseModuleCompletion {
+                course_id: course,
+                course_module_id: course_module.id,
+                user_id: user,
+                completion_date: Utc::now(),
+                completion_registration_attempt_date: None,
+                completion_language: "en-US".to_string(),
+                eligible_for_ects: true,
+                email: "test@example.com".to_string(),
+                grade: Some(5),
+                passed: true,
+            },
+            CourseModuleCompletionGranter::User(user),
+        )
+        .await
+        .unwrap();
+
+        let export = insert(
+            tx.as_mut(),
+            instance.id,
+            configuration,
+            CertificateBulkExportFormat::Pdf,
+            user,
+        )
+        .await
+        .unwrap();
+        assert_eq!(export.status, CertificateBulkExportStatus::Queued);
+        assert_eq!(export.total_count, 1);
+        assert_eq!(export.processed_count, 0);
+        let items = get_items(tx.as_mut(), export.id).await.unwrap();
+        assert_eq!(
+            items.iter().map(|i| i.user_id).collect::<Vec<_>>(),
+            vec![user]
+        );
+    }
+
+    #[tokio::test]
+    async fn stale_jobs_are_resumed() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module);
+        let configuration = insert_certificate_configuration(tx.as_mut(), course_module.id).await;
+        let export = insert(
+            tx.as_mut(),
+            instance.id,
+            configuration,
+            CertificateBulkExportFormat::Png,
+            user,
+        )
+        .await
+        .unwrap();
+
+        let long_ago = Utc::now() - chrono::Duration::hours(1);
+        assert_eq!(
+            claim_next(tx.as_mut(), long_ago).await.unwrap(),
+            Some(export.id)
+        );
+        // The worker is still heartbeating, so the job is not handed out again.
+        assert_eq!(claim_next(tx.as_mut(), long_ago).await.unwrap(), None);
+
+        let in_the_future = Utc::now() + chrono::Duration::hours(1);
+        assert_eq!(
+            claim_next(tx.as_mut(), in_the_future).await.unwrap(),
+            Some(export.id)
+        );
+        let export = get_by_id(tx.as_mut(), export.id).await.unwrap();
+        assert_eq!(export.status, CertificateBulkExportStatus::Running);
+        assert_eq!(export.attempts, 2);
+
+        mark_completed(tx.as_mut(), export.id, "path/to/export.zip")
+            .await
+            .unwrap();
+        assert_eq!(claim_next(tx.as_mut(), in_the_future).await.unwrap(), None);
+    }
+}
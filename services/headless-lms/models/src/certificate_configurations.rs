@@ -6,6 +6,7 @@ use crate::{
     certificate_configuration_to_requirements::{
         CertificateAllRequirements, get_all_requirements_for_certificate_configuration,
     },
+    certificate_text_elements::CertificateTextElement,
     prelude::*,
 };
 
@@ -30,6 +31,14 @@ impl PaperSize {
             PaperSize::VerticalA4 => 3508,
         }
     }
+    /// Width in PDF points (1/72 inch). The pixel sizes are A4 at 300 DPI.
+    pub fn width_pt(&self) -> f32 {
+        self.width_px() as f32 * 72.0 / 300.0
+    }
+    /// Height in PDF points (1/72 inch). The pixel sizes are A4 at 300 DPI.
+    pub fn height_pt(&self) -> f32 {
+        self.height_px() as f32 * 72.0 / 300.0
+    }
 }
 
 /// How text should be positioned relative to the given coordinates. See <https://developer.mozilla.org/en-US/docs/Web/SVG/Attribute/text-anchor>.
@@ -57,6 +66,8 @@ impl fmt::Display for CertificateTextAnchor {
 pub struct CertificateConfigurationAndRequirements {
     pub certificate_configuration: CertificateConfiguration,
     pub requirements: CertificateAllRequirements,
+    /// If empty, the fixed text fields of the configuration are rendered instead.
+    pub text_elements: Vec<CertificateTextElement>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
@@ -93,6 +104,10 @@ pub struct CertificateConfiguration {
     pub certificate_grade_font_size: Option<String>,
     pub certificate_grade_text_color: Option<String>,
     pub certificate_grade_text_anchor: Option<CertificateTextAnchor>,
+    /// Whether the certificate can also be downloaded as a PDF.
+    pub pdf_enabled: bool,
+    /// Whether the PDF lists the student's module grades and credits on extra pages.
+    pub pdf_transcript_pages_enabled: bool,
 }
 
 pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<CertificateConfiguration> {
@@ -129,7 +144,9 @@ SELECT cc.id,
   cc.certificate_grade_x_pos,
   cc.certificate_grade_font_size,
   cc.certificate_grade_text_color,
-  cc.certificate_grade_text_anchor
+  cc.certificate_grade_text_anchor,
+  cc.pdf_enabled,
+  cc.pdf_transcript_pages_enabled
 FROM certificate_configurations cc
 WHERE id = $1
   AND cc.deleted_at IS NULL "#,
@@ -177,7 +194,9 @@ SELECT cc.id,
   cc.certificate_grade_x_pos,
   cc.certificate_grade_font_size,
   cc.certificate_grade_text_color,
-  cc.certificate_grade_text_anchor
+  cc.certificate_grade_text_anchor,
+  cc.pdf_enabled,
+  cc.pdf_transcript_pages_enabled
 FROM certificate_configurations cc
 JOIN certificate_configuration_to_requirements cctr ON cc.id = cctr.certificate_configuration_id
 WHERE cctr.course_module_id = $1
@@ -244,7 +263,9 @@ SELECT cc.id,
   cc.certificate_grade_x_pos,
   cc.certificate_grade_font_size,
   cc.certificate_grade_text_color,
-  cc.certificate_grade_text_anchor
+  cc.certificate_grade_text_anchor,
+  cc.pdf_enabled,
+  cc.pdf_transcript_pages_enabled
 FROM certificate_configurations cc
   JOIN certificate_configuration_to_requirements cctr ON cc.id = cctr.certificate_configuration_id
 WHERE cctr.course_module_id IN (
@@ -266,9 +287,16 @@ WHERE cctr.course_module_id IN (
             get_all_requirements_for_certificate_configuration(conn, certificate_configuration.id)
                 .await?;
         if requirements.is_default_certificate_configuration() {
+            let text_elements =
+                crate::certificate_text_elements::get_by_certificate_configuration_id(
+                    conn,
+                    certificate_configuration.id,
+                )
+                .await?;
             res.push(CertificateConfigurationAndRequirements {
                 certificate_configuration: certificate_configuration.clone(),
                 requirements,
+                text_elements,
             });
         }
     }
@@ -306,6 +334,8 @@ pub struct DatabaseCertificateConfiguration {
     pub certificate_grade_font_size: Option<String>,
     pub certificate_grade_text_color: Option<String>,
     pub certificate_grade_text_anchor: Option<CertificateTextAnchor>,
+    pub pdf_enabled: bool,
+    pub pdf_transcript_pages_enabled: bool,
 }
 
 impl DatabaseCertificateConfiguration {
@@ -382,6 +412,8 @@ impl DatabaseCertificateConfiguration {
             certificate_grade_text_anchor: self
                 .certificate_grade_text_anchor
                 .unwrap_or(CertificateTextAnchor::Middle),
+            pdf_enabled: self.pdf_enabled,
+            pdf_transcript_pages_enabled: self.pdf_transcript_pages_enabled,
         }
     }
 }
@@ -416,6 +448,8 @@ struct DatabaseCertificateConfigurationInner<'a> {
     pub certificate_grade_font_size: &'a str,
     pub certificate_grade_text_color: &'a str,
     pub certificate_grade_text_anchor: CertificateTextAnchor,
+    pub pdf_enabled: bool,
+    pub pdf_transcript_pages_enabled: bool,
 }
 
 pub async fn insert(
@@ -453,7 +487,9 @@ INSERT INTO public.certificate_configurations (
     certificate_grade_x_pos,
     certificate_grade_font_size,
     certificate_grade_text_color,
-    certificate_grade_text_anchor
+    certificate_grade_text_anchor,
+    pdf_enabled,
+    pdf_transcript_pages_enabled
   )
 VALUES (
     $1,
@@ -482,7 +518,9 @@ VALUES (
     $24,
     $25,
     $26,
-    $27
+    $27,
+    $28,
+    $29
   )
 RETURNING id,
   created_at,
@@ -514,7 +552,9 @@ RETURNING id,
   certificate_grade_x_pos,
   certificate_grade_font_size,
   certificate_grade_text_color,
-  certificate_grade_text_anchor
+  certificate_grade_text_anchor,
+  pdf_enabled,
+  pdf_transcript_pages_enabled
 "#,
         conf.certificate_owner_name_y_pos,
         conf.certificate_owner_name_x_pos,
@@ -542,7 +582,9 @@ RETURNING id,
         conf.certificate_grade_x_pos,
         conf.certificate_grade_font_size,
         conf.certificate_grade_text_color,
-        conf.certificate_grade_text_anchor as CertificateTextAnchor
+        conf.certificate_grade_text_anchor as CertificateTextAnchor,
+        conf.pdf_enabled,
+        conf.pdf_transcript_pages_enabled
     )
     .fetch_one(conn)
     .await?;
@@ -584,8 +626,10 @@ SET certificate_owner_name_y_pos = $1,
   certificate_grade_x_pos = $24,
   certificate_grade_font_size = $25,
   certificate_grade_text_color = $26,
-  certificate_grade_text_anchor = $27
-WHERE id = $28
+  certificate_grade_text_anchor = $27,
+  pdf_enabled = $28,
+  pdf_transcript_pages_enabled = $29
+WHERE id = $30
 "#,
         conf.certificate_owner_name_y_pos,
         conf.certificate_owner_name_x_pos,
@@ -614,6 +658,8 @@ WHERE id = $28
         conf.certificate_grade_font_size,
         conf.certificate_grade_text_color,
         conf.certificate_grade_text_anchor as CertificateTextAnchor,
+        conf.pdf_enabled,
+        conf.pdf_transcript_pages_enabled,
         id
     )
     .execute(conn)
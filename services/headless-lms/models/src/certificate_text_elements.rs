@@ -0,0 +1,260 @@
+//! Positioned texts printed on a certificate. The texts are templates with placeholders like
+//! `{name}` that are filled in when the certificate is rendered. A certificate configuration
+//! without text elements renders its fixed owner name, date, validate URL and grade fields instead.
+
+use utoipa::ToSchema;
+
+use crate::{certificate_configurations::CertificateTextAnchor, prelude::*};
+
+const MAX_TEMPLATE_LENGTH: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct CertificateTextElement {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub certificate_configuration_id: Uuid,
+    pub order_number: i32,
+    /// The text to print, e.g. `Congratulations {first_name}!`.
+    pub template: String,
+    pub x_pos: String,
+    pub y_pos: String,
+    pub font_size: String,
+    pub text_color: String,
+    pub text_anchor: CertificateTextAnchor,
+    pub font_family: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewCertificateTextElement {
+    pub template: String,
+    pub x_pos: String,
+    pub y_pos: String,
+    pub font_size: String,
+    pub text_color: String,
+    pub text_anchor: CertificateTextAnchor,
+    pub font_family: Option<String>,
+}
+
+pub async fn get_by_certificate_configuration_id(
+    conn: &mut PgConnection,
+    certificate_configuration_id: Uuid,
+) -> ModelResult<Vec<CertificateTextElement>> {
+    let res = sqlx::query_as!(
+        CertificateTextElement,
+        r#"
+SELECT id,
+  created_at,
+  updated_at,
+  deleted_at,
+  certificate_configuration_id,
+  order_number,
+  template,
+  x_pos,
+  y_pos,
+  font_size,
+  text_color,
+  text_anchor,
+  font_family
+FROM certificate_text_elements
+WHERE certificate_configuration_id = $1
+  AND deleted_at IS NULL
+ORDER BY order_number
+        "#,
+        certificate_configuration_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Replaces all the text elements of the configuration. The elements are drawn in the given order.
+pub async fn replace_all(
+    conn: &mut PgConnection,
+    certificate_configuration_id: Uuid,
+    elements: &[NewCertificateTextElement],
+) -> ModelResult<Vec<CertificateTextElement>> {
+    for element in elements {
+        validate(element)?;
+    }
+    let mut tx = conn.begin().await?;
+    sqlx::query!(
+        "
+UPDATE certificate_text_elements
+SET deleted_at = now()
+WHERE certificate_configuration_id = $1
+  AND deleted_at IS NULL
+        ",
+        certificate_configuration_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let mut res = vec![];
+    for (order_number, element) in elements.iter().enumerate() {
+        let inserted = sqlx::query_as!(
+            CertificateTextElement,
+            r#"
+INSERT INTO certificate_text_elements (
+    certificate_configuration_id,
+    order_number,
+    template,
+    x_pos,
+    y_pos,
+    font_size,
+    text_color,
+    text_anchor,
+    font_family
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+RETURNING id,
+  created_at,
+  updated_at,
+  deleted_at,
+  certificate_configuration_id,
+  order_number,
+  template,
+  x_pos,
+  y_pos,
+  font_size,
+  text_color,
+  text_anchor,
+  font_family
+            "#,
+            certificate_configuration_id,
+            order_number as i32,
+            element.template,
+            element.x_pos,
+            element.y_pos,
+            element.font_size,
+            element.text_color,
+            element.text_anchor as CertificateTextAnchor,
+            element.font_family,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        res.push(inserted);
+    }
+    tx.commit().await?;
+    Ok(res)
+}
+
+fn validate(element: &NewCertificateTextElement) -> ModelResult<()> {
+    if element.template.trim().is_empty() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "A certificate text cannot be empty.".to_string()
+        ));
+    }
+    if element.template.chars().count() > MAX_TEMPLATE_LENGTH {
+        return Err(model_err!(
+            PreconditionFailed,
+            format!("A certificate text can be at most {MAX_TEMPLATE_LENGTH} characters long.")
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        certificate_configurations::{DatabaseCertificateConfiguration, PaperSize},
+        test_helper::*,
+    };
+
+    fn element(template: &str) -> NewCertificateTextElement {
+        NewCertificateTextElement {
+            template: template.to_string(),
+            x_pos: "50%".to_string(),
+            y_pos: "50%".to_string(),
+            font_size: "150px".to_string(),
+            text_color: "black".to_string(),
+            text_anchor: CertificateTextAnchor::Middle,
+            font_family: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn replaces_elements() {
+        insert_data!(:tx);
+        let file_upload_id = crate::file_uploads::insert(
+            tx.as_mut(),
+            "background.svg",
+            "path/background.svg",
+            "image/svg+xml",
+            None,
+        )
+        .await
+        .unwrap();
+        let configuration = crate::certificate_configurations::insert(
+            tx.as_mut(),
+            &DatabaseCertificateConfiguration {
+                id: Uuid::new_v4(),
+                certificate_owner_name_y_pos: None,
+                certificate_owner_name_x_pos: None,
+                certificate_owner_name_font_size: None,
+                certificate_owner_name_text_color: None,
+                certificate_owner_name_text_anchor: None,
+                certificate_validate_url_y_pos: None,
+                certificate_validate_url_x_pos: None,
+                certificate_validate_url_font_size: None,
+                certificate_validate_url_text_color: None,
+                certificate_validate_url_text_anchor: None,
+                certificate_date_y_pos: None,
+                certificate_date_x_pos: None,
+                certificate_date_font_size: None,
+                certificate_date_text_color: None,
+                certificate_date_text_anchor: None,
+                certificate_locale: None,
+                paper_size: Some(PaperSize::HorizontalA4),
+                background_svg_path: "path/background.svg".to_string(),
+                background_svg_file_upload_id: file_upload_id,
+                overlay_svg_path: None,
+                overlay_svg_file_upload_id: None,
+                render_certificate_grade: false,
+                certificate_grade_y_pos: None,
+                certificate_grade_x_pos: None,
+                certificate_grade_font_size: None,
+                certificate_grade_text_color: None,
+                certificate_grade_text_anchor: None,
+                pdf_enabled: false,
+                pdf_transcript_pages_enabled: false,
+            },
+        )
+        .await
+        .unwrap();
+        replace_all(
+            tx.as_mut(),
+            configuration.id,
+            &[element("{name}"), element("{course_name}")],
+        )
+        .await
+        .unwrap();
+        let replaced = replace_all(
+            tx.as_mut(),
+            configuration.id,
+            &[element("Certificate"), element("{name}")],
+        )
+        .await
+        .unwrap();
+        assert_eq!(replaced.len(), 2);
+
+        let elements = get_by_certificate_configuration_id(tx.as_mut(), configuration.id)
+            .await
+            .unwrap();
+        assert_eq!(
+            elements
+                .iter()
+                .map(|e| e.template.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Certificate", "{name}"]
+        );
+
+        assert!(
+            replace_all(tx.as_mut(), configuration.id, &[element("  ")])
+                .await
+                .is_err()
+        );
+    }
+}
@@ -423,32 +423,71 @@ WHERE course_id = $1
     Ok(chapters)
 }
 
+/// Summarizes the exercise deadlines that differ from the chapter deadline, by chapter. With a user
+/// the deadlines are the ones that apply to the user, see
+/// [`crate::deadline_exceptions::get_effective_exercise_deadlines`].
 pub async fn exercise_deadline_overrides_by_chapter_for_course(
     conn: &mut PgConnection,
     course_id: Uuid,
+    user_id: Option<Uuid>,
 ) -> ModelResult<HashMap<Uuid, ChapterExerciseDeadlineOverrideSummary>> {
     let rows = sqlx::query!(
         r#"
 SELECT
   e.chapter_id,
-  MIN(COALESCE(e.deadline, c.deadline)) FILTER (
-    WHERE COALESCE(e.deadline, c.deadline) IS NOT NULL
+  MIN(COALESCE(d.exercise_deadline, d.chapter_deadline)) FILTER (
+    WHERE COALESCE(d.exercise_deadline, d.chapter_deadline) IS NOT NULL
   ) AS earliest_exercise_deadline_override,
   COUNT(*) FILTER (
-    WHERE e.deadline IS NOT NULL
-      AND (c.deadline IS NULL OR e.deadline <> c.deadline)
+    WHERE d.exercise_deadline IS NOT NULL
+      AND (d.chapter_deadline IS NULL OR d.exercise_deadline <> d.chapter_deadline)
   ) AS exercise_deadline_override_count,
-  COUNT(DISTINCT COALESCE(e.deadline, c.deadline)) FILTER (
-    WHERE COALESCE(e.deadline, c.deadline) IS NOT NULL
+  COUNT(DISTINCT COALESCE(d.exercise_deadline, d.chapter_deadline)) FILTER (
+    WHERE COALESCE(d.exercise_deadline, d.chapter_deadline) IS NOT NULL
   ) AS exercise_deadline_override_distinct_count
 FROM exercises e
 JOIN chapters c ON c.id = e.chapter_id
+LEFT JOIN user_course_settings ucs ON ucs.user_id = $2
+  AND ucs.current_course_id = c.course_id
+  AND ucs.deleted_at IS NULL
+LEFT JOIN LATERAL (
+  SELECT de.deadline
+  FROM deadline_exceptions de
+  WHERE de.exercise_id = e.id
+    AND (
+      de.user_id = $2
+      OR de.course_instance_id = ucs.current_course_instance_id
+    )
+    AND de.deleted_at IS NULL
+  ORDER BY de.user_id IS NOT NULL DESC
+  LIMIT 1
+) exercise_exception ON TRUE
+LEFT JOIN LATERAL (
+  SELECT de.deadline
+  FROM deadline_exceptions de
+  WHERE de.chapter_id = c.id
+    AND (
+      de.user_id = $2
+      OR de.course_instance_id = ucs.current_course_instance_id
+    )
+    AND de.deleted_at IS NULL
+  ORDER BY de.user_id IS NOT NULL DESC
+  LIMIT 1
+) chapter_exception ON TRUE
+CROSS JOIN LATERAL (
+  SELECT COALESCE(chapter_exception.deadline, c.deadline) AS chapter_deadline,
+    COALESCE(
+      exercise_exception.deadline,
+      GREATEST(chapter_exception.deadline, e.deadline)
+    ) AS exercise_deadline
+) d
 WHERE c.course_id = $1
   AND c.deleted_at IS NULL
   AND e.deleted_at IS NULL
-GROUP BY e.chapter_id, c.deadline
+GROUP BY e.chapter_id
         "#,
-        course_id
+        course_id,
+        user_id
     )
     .fetch_all(conn)
     .await?;
@@ -0,0 +1,442 @@
+//! Aggregated statistics of how students use a chatbot, for teachers.
+//!
+//! Figures that are about fewer than [`MIN_GROUP_SIZE`] distinct users are suppressed so that
+//! individual students can't be identified from them. A user is identified by the user id of the
+//! conversation, or by its anonymous token if the chatbot is used without logging in. Deleted
+//! conversations, such as the ones made by chatbot evaluation runs, are left out.
+
+use chrono::NaiveDate;
+use pgvector::Vector;
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+/// The smallest number of distinct users a figure can be shown for.
+pub const MIN_GROUP_SIZE: i64 = 5;
+
+/// A message a user sent to the chatbot, with whether the chatbot answered it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatbotAnalyticsQuestion {
+    pub message_id: Uuid,
+    pub user_key: String,
+    pub text: String,
+    /// Whether the chatbot replied with a text message before the user sent their next message.
+    pub has_reply: bool,
+    /// Whether the reply was streamed to the end. A reply is left incomplete when the stream fails
+    /// or is stopped by a content filter. `None` if there is no reply.
+    pub reply_is_complete: Option<bool>,
+    /// The embedding of the text if it has been created.
+    pub embedding: Option<Vector>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatbotTokenUsagePeriod {
+    /// The first day of the period.
+    pub period_start: NaiveDate,
+    pub user_count: i64,
+    pub total_tokens: i64,
+    /// How many users used at least the limit of the configuration during the period.
+    pub users_at_limit: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChatbotCitedPage {
+    pub page_id: Uuid,
+    pub page_title: String,
+    pub url_path: String,
+    /// How many answers cited the page.
+    pub citing_message_count: i64,
+    pub user_count: i64,
+}
+
+/// The messages users sent to the chatbot between `start` and `end`, newest first.
+pub async fn get_questions(
+    conn: &mut PgConnection,
+    chatbot_configuration_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: i64,
+) -> ModelResult<Vec<ChatbotAnalyticsQuestion>> {
+    let res = sqlx::query_as!(
+        ChatbotAnalyticsQuestion,
+        r#"
+SELECT m.id AS message_id,
+  COALESCE(c.user_id::text, c.anonymous_token, c.id::text) AS "user_key!",
+  mm.text,
+  reply.message_is_complete IS NOT NULL AS "has_reply!",
+  reply.message_is_complete AS "reply_is_complete?",
+  e.embedding AS "embedding?: Vector"
+FROM chatbot_conversation_messages m
+  JOIN chatbot_conversation_message_messages mm ON mm.chatbot_conversation_message_id = m.id
+  AND mm.message_role = 'user'
+  AND mm.deleted_at IS NULL
+  JOIN chatbot_conversations c ON c.id = m.conversation_id
+  LEFT JOIN chatbot_message_embeddings e ON e.chatbot_conversation_message_id = m.id
+  AND e.deleted_at IS NULL
+  LEFT JOIN LATERAL (
+    SELECT rmm.message_is_complete
+    FROM chatbot_conversation_messages r
+      JOIN chatbot_conversation_message_messages rmm ON rmm.chatbot_conversation_message_id = r.id
+      AND rmm.message_role = 'assistant'
+      AND rmm.deleted_at IS NULL
+      AND TRIM(rmm.text) <> ''
+    WHERE r.conversation_id = m.conversation_id
+      AND r.order_number > m.order_number
+      AND r.deleted_at IS NULL
+      AND NOT EXISTS (
+        SELECT 1
+        FROM chatbot_conversation_messages n
+          JOIN chatbot_conversation_message_messages nmm ON nmm.chatbot_conversation_message_id = n.id
+          AND nmm.message_role = 'user'
+          AND nmm.deleted_at IS NULL
+        WHERE n.conversation_id = m.conversation_id
+          AND n.order_number > m.order_number
+          AND n.order_number < r.order_number
+          AND n.deleted_at IS NULL
+      )
+    ORDER BY r.order_number
+    LIMIT 1
+  ) reply ON TRUE
+WHERE c.chatbot_configuration_id = $1
+  AND m.created_at >= $2
+  AND m.created_at < $3
+  AND m.deleted_at IS NULL
+  AND c.deleted_at IS NULL
+ORDER BY m.created_at DESC
+LIMIT $4
+        "#,
+        chatbot_configuration_id,
+        start,
+        end,
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Saves the embeddings of user messages. Messages that already have an embedding are skipped.
+pub async fn insert_message_embeddings(
+    conn: &mut PgConnection,
+    message_embeddings: Vec<(Uuid, Vec<f32>)>,
+) -> ModelResult<()> {
+    let (message_ids, embeddings): (Vec<Uuid>, Vec<Vector>) = message_embeddings
+        .into_iter()
+        .map(|(id, embedding)| (id, Vector::from(embedding)))
+        .unzip();
+    sqlx::query!(
+        r#"
+INSERT INTO chatbot_message_embeddings (chatbot_conversation_message_id, embedding)
+SELECT *
+FROM UNNEST($1::uuid[], $2::vector[])
+ON CONFLICT (chatbot_conversation_message_id)
+WHERE deleted_at IS NULL DO NOTHING
+        "#,
+        &message_ids,
+        &embeddings as _
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The tokens used in the chatbot's conversations per day or per week, and how many users used
+/// at least `limit` tokens in a period. Weeks start on Monday.
+pub async fn get_token_usage(
+    conn: &mut PgConnection,
+    chatbot_configuration_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    period: TokenUsagePeriod,
+    limit: i32,
+) -> ModelResult<Vec<ChatbotTokenUsagePeriod>> {
+    let res = sqlx::query_as!(
+        ChatbotTokenUsagePeriod,
+        r#"
+WITH per_user AS (
+  SELECT date_trunc($4, m.created_at)::date AS period_start,
+    COALESCE(c.user_id::text, c.anonymous_token, c.id::text) AS user_key,
+    SUM(mm.used_tokens)::bigint AS tokens
+  FROM chatbot_conversation_messages m
+    JOIN chatbot_conversation_message_messages mm ON mm.chatbot_conversation_message_id = m.id
+    AND mm.deleted_at IS NULL
+    JOIN chatbot_conversations c ON c.id = m.conversation_id
+  WHERE c.chatbot_configuration_id = $1
+    AND m.created_at >= $2
+    AND m.created_at < $3
+    AND m.deleted_at IS NULL
+    AND c.deleted_at IS NULL
+  GROUP BY 1,
+    2
+)
+SELECT period_start AS "period_start!",
+  COUNT(*) AS "user_count!",
+  SUM(tokens)::bigint AS "total_tokens!",
+  COUNT(*) FILTER (
+    WHERE tokens >= $5
+  ) AS "users_at_limit!"
+FROM per_user
+GROUP BY period_start
+ORDER BY period_start
+        "#,
+        chatbot_configuration_id,
+        start,
+        end,
+        period.as_date_trunc_field(),
+        i64::from(limit)
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenUsagePeriod {
+    Day,
+    Week,
+}
+
+impl TokenUsagePeriod {
+    fn as_date_trunc_field(self) -> &'static str {
+        match self {
+            TokenUsagePeriod::Day => "day",
+            TokenUsagePeriod::Week => "week",
+        }
+    }
+}
+
+/**
+The course pages the chatbot's answers between `start` and `end` cited, most cited first.
+
+A page is cited by a citation of the answer or by a Markdown link to the page in the answer's
+text. Pages cited for fewer than [`MIN_GROUP_SIZE`] users are left out.
+*/
+pub async fn get_cited_pages(
+    conn: &mut PgConnection,
+    chatbot_configuration_id: Uuid,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    limit: i64,
+) -> ModelResult<Vec<ChatbotCitedPage>> {
+    let res = sqlx::query_as!(
+        ChatbotCitedPage,
+        r#"
+WITH answers AS (
+  SELECT m.id,
+    mm.text,
+    COALESCE(c.user_id::text, c.anonymous_token, c.id::text) AS user_key
+  FROM chatbot_conversation_messages m
+    JOIN chatbot_conversation_message_messages mm ON mm.chatbot_conversation_message_id = m.id
+    AND mm.message_role = 'assistant'
+    AND mm.deleted_at IS NULL
+    JOIN chatbot_conversations c ON c.id = m.conversation_id
+  WHERE c.chatbot_configuration_id = $1
+    AND m.created_at >= $2
+    AND m.created_at < $3
+    AND m.deleted_at IS NULL
+    AND c.deleted_at IS NULL
+),
+cited_urls AS (
+  SELECT a.id AS message_id,
+    a.user_key,
+    ci.document_url AS url
+  FROM answers a
+    JOIN chatbot_conversation_messages_citations ci ON ci.conversation_message_id = a.id
+    AND ci.deleted_at IS NULL
+  UNION ALL
+  SELECT a.id,
+    a.user_key,
+    link [1]
+  FROM answers a
+    CROSS JOIN LATERAL regexp_matches(a.text, '\]\((https?://[^)\s]+)', 'g') AS link
+),
+cited_paths AS (
+  -- The path of the URL without the host, query, fragment and trailing slash.
+  SELECT message_id,
+    user_key,
+    rtrim(
+      regexp_replace(url, '^https?://[^/]+|[?#].*$', '', 'g'),
+      '/'
+    ) AS path
+  FROM cited_urls
+),
+course_pages AS (
+  SELECT p.id,
+    p.title,
+    p.url_path,
+    rtrim(
+      '/org/' || o.slug || '/courses/' || co.slug || p.url_path,
+      '/'
+    ) AS path
+  FROM chatbot_configurations cc
+    JOIN courses co ON co.id = cc.course_id
+    JOIN organizations o ON o.id = co.organization_id
+    JOIN pages p ON p.course_id = co.id
+    AND p.deleted_at IS NULL
+  WHERE cc.id = $1
+)
+SELECT cp.id AS page_id,
+  cp.title AS page_title,
+  cp.url_path,
+  COUNT(DISTINCT c.message_id) AS "citing_message_count!",
+  COUNT(DISTINCT c.user_key) AS "user_count!"
+FROM cited_paths c
+  JOIN course_pages cp ON cp.path = c.path
+GROUP BY cp.id,
+  cp.title,
+  cp.url_path
+HAVING COUNT(DISTINCT c.user_key) >= $4
+ORDER BY "citing_message_count!" DESC,
+  cp.title
+LIMIT $5
+        "#,
+        chatbot_configuration_id,
+        start,
+        end,
+        MIN_GROUP_SIZE,
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        chatbot_configurations,
+        chatbot_configurations_models::{self, ModelProvider, ModelType},
+        chatbot_conversation_message_messages::{ChatbotConversationMessageMessage, MessageRole},
+        chatbot_conversation_messages::{self, ChatbotConversationMessage, Message},
+        chatbot_conversations,
+        test_helper::*,
+    };
+
+    async fn insert_text_message(
+        conn: &mut PgConnection,
+        conversation_id: Uuid,
+        order_number: i32,
+        role: MessageRole,
+        text: &str,
+        is_complete: bool,
+    ) {
+        chatbot_conversation_messages::insert(
+            conn,
+            ChatbotConversationMessage {
+                id: Uuid::new_v4(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                deleted_at: None,
+                conversation_id,
+                order_number,
+                message: Message::Text(ChatbotConversationMessageMessage {
+                    text: text.to_string(),
+                    message_role: role,
+                    message_is_complete: is_complete,
+                    used_tokens: 10,
+                    response_id: (role != MessageRole::User).then(|| "response".to_string()),
+                    ..Default::default()
+                }),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn questions_and_token_usage_are_aggregated() {
+        insert_data!(:tx, :user, :org, :course);
+        let model = chatbot_configurations_models::insert(
+            tx.as_mut(),
+            chatbot_configurations_models::NewChatbotConfigurationModel {
+                id: Uuid::new_v4(),
+                model: "gpt-4o".to_string(),
+                model_type: ModelType::GPTNonThinking,
+                default_model: false,
+                context_size: 128000,
+                provider: ModelProvider::Azure,
+                api_base_url: None,
+                api_key_env_var: None,
+            },
+        )
+        .await
+        .unwrap();
+        let configuration = chatbot_configurations::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            chatbot_configurations::NewChatbotConf {
+                course_id: course,
+                model_id: model.id,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let conversation = chatbot_conversations::create_for_user_and_configuration(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            Some(user),
+            None,
+            configuration.id,
+        )
+        .await
+        .unwrap();
+        let id = conversation.id;
+        insert_text_message(tx.as_mut(), id, 0, MessageRole::Assistant, "Hi!", true).await;
+        insert_text_message(
+            tx.as_mut(),
+            id,
+            1,
+            MessageRole::User,
+            "What is recursion?",
+            true,
+        )
+        .await;
+        insert_text_message(
+            tx.as_mut(),
+            id,
+            2,
+            MessageRole::Assistant,
+            "A function...",
+            false,
+        )
+        .await;
+        insert_text_message(tx.as_mut(), id, 3, MessageRole::User, "And loops?", true).await;
+
+        let start = Utc::now() - chrono::Duration::days(1);
+        let end = Utc::now() + chrono::Duration::days(1);
+        let mut questions = get_questions(tx.as_mut(), configuration.id, start, end, 100)
+            .await
+            .unwrap();
+        questions.sort_by(|a, b| a.text.cmp(&b.text));
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].text, "And loops?");
+        assert!(!questions[0].has_reply);
+        assert_eq!(questions[0].reply_is_complete, None);
+        assert_eq!(questions[1].text, "What is recursion?");
+        assert!(questions[1].has_reply);
+        assert_eq!(questions[1].reply_is_complete, Some(false));
+        assert_eq!(questions[1].user_key, user.to_string());
+
+        let usage = get_token_usage(
+            tx.as_mut(),
+            configuration.id,
+            start,
+            end,
+            TokenUsagePeriod::Week,
+            30,
+        )
+        .await
+        .unwrap();
+        let total: i64 = usage.iter().map(|u| u.total_tokens).sum();
+        assert_eq!(total, 40);
+        assert!(usage.iter().all(|u| u.user_count == 1));
+
+        // A single user is below the minimum group size.
+        let cited = get_cited_pages(tx.as_mut(), configuration.id, start, end, 10)
+            .await
+            .unwrap();
+        assert!(cited.is_empty());
+    }
+}
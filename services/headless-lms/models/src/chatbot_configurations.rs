@@ -45,6 +45,7 @@ pub struct ChatbotConfiguration {
     pub reasoning_effort: ReasoningEffortLevel,
     pub use_azure_search: bool,
     pub maintain_azure_search_index: bool,
+    pub use_builtin_search: bool,
     pub hide_citations: bool,
     pub use_semantic_reranking: bool,
     pub use_tools: bool,
@@ -78,6 +79,7 @@ impl Default for ChatbotConfiguration {
             verbosity: VerbosityLevel::Medium,
             use_azure_search: false,
             maintain_azure_search_index: false,
+            use_builtin_search: false,
             hide_citations: false,
             use_semantic_reranking: false,
             use_tools: false,
@@ -109,6 +111,8 @@ pub struct NewChatbotConf {
     pub reasoning_effort: ReasoningEffortLevel,
     pub use_azure_search: bool,
     pub maintain_azure_search_index: bool,
+    #[serde(default)]
+    pub use_builtin_search: bool,
     pub hide_citations: bool,
     pub use_semantic_reranking: bool,
     pub use_tools: bool,
@@ -140,6 +144,7 @@ impl Default for NewChatbotConf {
             reasoning_effort: chatbot_conf.reasoning_effort,
             use_azure_search: chatbot_conf.use_azure_search,
             maintain_azure_search_index: chatbot_conf.maintain_azure_search_index,
+            use_builtin_search: chatbot_conf.use_builtin_search,
             hide_citations: chatbot_conf.hide_citations,
             use_semantic_reranking: chatbot_conf.use_semantic_reranking,
             use_tools: chatbot_conf.use_tools,
@@ -168,6 +173,18 @@ fn validate_max_output_tokens(input: &NewChatbotConf) -> ModelResult<()> {
     Ok(())
 }
 
+/// Rejects configurations that would search the course material from both Azure AI Search and the
+/// built-in index.
+fn validate_search_backend(input: &NewChatbotConf) -> ModelResult<()> {
+    if input.use_azure_search && input.use_builtin_search {
+        return Err(model_err!(
+            PreconditionFailed,
+            "use_azure_search and use_builtin_search cannot both be enabled.".to_string()
+        ));
+    }
+    Ok(())
+}
+
 pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<ChatbotConfiguration> {
     let res = sqlx::query_as!(
         ChatbotConfiguration,
@@ -190,6 +207,7 @@ pub async fn insert(
     input: NewChatbotConf,
 ) -> ModelResult<ChatbotConfiguration> {
     validate_max_output_tokens(&input)?;
+    validate_search_backend(&input)?;
     let maintain_azure_search_index = input.use_azure_search;
     let res = sqlx::query_as!(
         ChatbotConfiguration,
@@ -218,9 +236,10 @@ INSERT INTO chatbot_configurations (
     default_chatbot,
     suggest_next_messages,
     initial_suggested_messages,
-    publicly_accessible
+    publicly_accessible,
+    use_builtin_search
   )
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24)
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25)
 RETURNING *
         "#,
         pkey_policy.into_uuid(),
@@ -246,7 +265,8 @@ RETURNING *
         input.default_chatbot,
         input.suggest_next_messages,
         input.initial_suggested_messages.as_deref(),
-        input.publicly_accessible
+        input.publicly_accessible,
+        input.use_builtin_search
     )
     .fetch_one(conn)
     .await?;
@@ -259,6 +279,7 @@ pub async fn edit(
     chatbot_configuration_id: Uuid,
 ) -> ModelResult<ChatbotConfiguration> {
     validate_max_output_tokens(&input)?;
+    validate_search_backend(&input)?;
     let res = sqlx::query_as!(
         ChatbotConfiguration,
         r#"
@@ -286,8 +307,9 @@ SET
     use_tools = $20,
     suggest_next_messages = $21,
     initial_suggested_messages = $22,
-    publicly_accessible = $23
-WHERE id = $24
+    publicly_accessible = $23,
+    use_builtin_search = $24
+WHERE id = $25
     AND deleted_at IS NULL
 RETURNING *
 "#,
@@ -314,6 +336,7 @@ RETURNING *
         input.suggest_next_messages,
         input.initial_suggested_messages.as_deref(),
         input.publicly_accessible,
+        input.use_builtin_search,
         chatbot_configuration_id,
     )
     .fetch_one(conn)
@@ -393,6 +416,24 @@ AND deleted_at IS NULL
     Ok(res)
 }
 
+/// Configurations whose course material is indexed into `chatbot_page_chunks`.
+pub async fn get_for_builtin_search_maintenance(
+    conn: &mut PgConnection,
+) -> ModelResult<Vec<ChatbotConfiguration>> {
+    let res = sqlx::query_as!(
+        ChatbotConfiguration,
+        r#"
+SELECT *
+FROM chatbot_configurations
+WHERE use_builtin_search = true
+AND deleted_at IS NULL
+"#,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
 pub async fn remove_default_chatbot_from_course(
     conn: &mut PgConnection,
     course_id: Uuid,
@@ -10,6 +10,17 @@ pub enum ModelType {
     Mistral,
 }
 
+/// The kind of API a model is served from.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Type, ToSchema)]
+#[sqlx(type_name = "model_provider", rename_all = "kebab-case")]
+pub enum ModelProvider {
+    /// The Azure OpenAI Responses API configured in the environment.
+    Azure,
+    /// The Chat Completions API of an OpenAI-compatible server, such as vLLM, llama.cpp or Ollama.
+    #[sqlx(rename = "openai-compatible")]
+    OpenAICompatible,
+}
+
 #[derive(Clone, PartialEq, Deserialize, Serialize, ToSchema)]
 pub struct ChatbotConfigurationModel {
     pub id: Uuid,
@@ -20,6 +31,11 @@ pub struct ChatbotConfigurationModel {
     pub model_type: ModelType,
     pub default_model: bool,
     pub context_size: i32,
+    pub provider: ModelProvider,
+    /// Base URL of an OpenAI-compatible API. `None` for Azure models.
+    pub api_base_url: Option<String>,
+    /// Name of the environment variable that holds the API key of an OpenAI-compatible API.
+    pub api_key_env_var: Option<String>,
 }
 
 #[derive(Clone, PartialEq, Deserialize, Serialize)]
@@ -29,6 +45,9 @@ pub struct NewChatbotConfigurationModel {
     pub model_type: ModelType,
     pub default_model: bool,
     pub context_size: i32,
+    pub provider: ModelProvider,
+    pub api_base_url: Option<String>,
+    pub api_key_env_var: Option<String>,
 }
 
 pub async fn get_by_id(
@@ -46,7 +65,10 @@ SELECT
     model,
     model_type as "model_type: ModelType",
     default_model,
-    context_size
+    context_size,
+    provider AS "provider: ModelProvider",
+    api_base_url,
+    api_key_env_var
 FROM chatbot_configurations_models
 WHERE id = $1
 AND deleted_at IS NULL
@@ -70,7 +92,10 @@ SELECT
     model,
     model_type as "model_type: ModelType",
     default_model,
-    context_size
+    context_size,
+    provider AS "provider: ModelProvider",
+    api_base_url,
+    api_key_env_var
 FROM chatbot_configurations_models
 WHERE deleted_at IS NULL
         "#,
@@ -92,7 +117,10 @@ SELECT
     model,
     model_type as "model_type: ModelType",
     default_model,
-    context_size
+    context_size,
+    provider AS "provider: ModelProvider",
+    api_base_url,
+    api_key_env_var
 FROM chatbot_configurations_models
 WHERE default_model = true
 AND deleted_at IS NULL
@@ -117,7 +145,10 @@ SELECT id,
   model,
   model_type AS "model_type: ModelType",
   default_model,
-  context_size
+  context_size,
+  provider AS "provider: ModelProvider",
+  api_base_url,
+  api_key_env_var
 FROM chatbot_configurations_models
 WHERE id = (
     SELECT model_id
@@ -141,7 +172,18 @@ pub async fn insert(
     let res = sqlx::query_as!(
         ChatbotConfigurationModel,
         r#"
-INSERT INTO chatbot_configurations_models (id, model, model_type, default_model, context_size) VALUES ($1, $2, $3, $4, $5) RETURNING
+INSERT INTO chatbot_configurations_models (
+    id,
+    model,
+    model_type,
+    default_model,
+    context_size,
+    provider,
+    api_base_url,
+    api_key_env_var
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+RETURNING
     id,
     created_at,
     updated_at,
@@ -149,13 +191,19 @@ INSERT INTO chatbot_configurations_models (id, model, model_type, default_model,
     model,
     model_type as "model_type: ModelType",
     default_model,
-    context_size
+    context_size,
+    provider AS "provider: ModelProvider",
+    api_base_url,
+    api_key_env_var
         "#,
         input.id,
         input.model,
         input.model_type as ModelType,
         input.default_model,
         input.context_size,
+        input.provider as ModelProvider,
+        input.api_base_url,
+        input.api_key_env_var,
     )
     .fetch_one(conn)
     .await?;
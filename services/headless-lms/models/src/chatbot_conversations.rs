@@ -112,6 +112,21 @@ RETURNING *
     Ok(res)
 }
 
+pub async fn delete(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+UPDATE chatbot_conversations
+SET deleted_at = now()
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
 pub async fn get_latest_conversation_for_user(
     conn: &mut PgConnection,
     user_id: Option<Uuid>,
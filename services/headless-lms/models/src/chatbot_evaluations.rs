@@ -0,0 +1,898 @@
+//! Golden question sets that a course's chatbots are evaluated with, and the runs and scored
+//! answers of the evaluations.
+//!
+//! A run is created as pending and answered by the `chatbot-evaluation-runner` program. Runs of
+//! the same set can be compared with [`compare_runs`], for example to check two chatbot
+//! configurations against each other before changing the course's default chatbot.
+
+use std::collections::HashMap;
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Hash, sqlx::Type, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(type_name = "chatbot_evaluation_run_status", rename_all = "kebab-case")]
+pub enum ChatbotEvaluationRunStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct ChatbotEvaluationSet {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub course_id: Uuid,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct ChatbotEvaluationQuestion {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub evaluation_set_id: Uuid,
+    pub question: String,
+    pub expected_answer: String,
+    pub expected_page_ids: Vec<Uuid>,
+    pub rubric: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct NewChatbotEvaluationQuestion {
+    pub question: String,
+    pub expected_answer: String,
+    #[serde(default)]
+    pub expected_page_ids: Vec<Uuid>,
+    pub rubric: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ChatbotEvaluationRun {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub evaluation_set_id: Uuid,
+    pub chatbot_configuration_id: Uuid,
+    pub created_by_user_id: Uuid,
+    /// The chatbot configuration and its model when the run was started.
+    pub configuration_snapshot: serde_json::Value,
+    pub status: ChatbotEvaluationRunStatus,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ChatbotEvaluationResult {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub evaluation_run_id: Uuid,
+    pub evaluation_question_id: Uuid,
+    pub conversation_id: Option<Uuid>,
+    pub answer: Option<String>,
+    pub cited_page_ids: Vec<Uuid>,
+    pub citation_precision: Option<f32>,
+    pub citation_recall: Option<f32>,
+    pub groundedness_score: Option<i32>,
+    pub correctness_score: Option<i32>,
+    pub rubric_score: Option<i32>,
+    pub judge_feedback: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct NewChatbotEvaluationResult {
+    pub conversation_id: Option<Uuid>,
+    pub answer: Option<String>,
+    pub cited_page_ids: Vec<Uuid>,
+    pub citation_precision: Option<f32>,
+    pub citation_recall: Option<f32>,
+    pub groundedness_score: Option<i32>,
+    pub correctness_score: Option<i32>,
+    pub rubric_score: Option<i32>,
+    pub judge_feedback: Option<String>,
+    pub error_message: Option<String>,
+}
+
+/// A run with the averages of its scores. The averages leave out the questions the score does
+/// not apply to and the questions that failed.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ChatbotEvaluationRunSummary {
+    pub run: ChatbotEvaluationRun,
+    pub answered_count: i64,
+    pub failed_count: i64,
+    pub average_citation_precision: Option<f64>,
+    pub average_citation_recall: Option<f64>,
+    pub average_groundedness_score: Option<f64>,
+    pub average_correctness_score: Option<f64>,
+    pub average_rubric_score: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ChatbotEvaluationQuestionComparison {
+    pub question: ChatbotEvaluationQuestion,
+    pub base_result: Option<ChatbotEvaluationResult>,
+    pub compared_result: Option<ChatbotEvaluationResult>,
+}
+
+/// Two runs of the same set side by side, question by question.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ChatbotEvaluationRunComparison {
+    pub base: ChatbotEvaluationRunSummary,
+    pub compared: ChatbotEvaluationRunSummary,
+    pub questions: Vec<ChatbotEvaluationQuestionComparison>,
+}
+
+pub async fn insert_set(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    name: &str,
+) -> ModelResult<ChatbotEvaluationSet> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationSet,
+        "
+INSERT INTO chatbot_evaluation_sets (course_id, name)
+VALUES ($1, $2)
+RETURNING *
+        ",
+        course_id,
+        name
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_set_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<ChatbotEvaluationSet> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationSet,
+        "
+SELECT *
+FROM chatbot_evaluation_sets
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_sets_by_course_id(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+) -> ModelResult<Vec<ChatbotEvaluationSet>> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationSet,
+        "
+SELECT *
+FROM chatbot_evaluation_sets
+WHERE course_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at
+        ",
+        course_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn rename_set(
+    conn: &mut PgConnection,
+    id: Uuid,
+    name: &str,
+) -> ModelResult<ChatbotEvaluationSet> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationSet,
+        "
+UPDATE chatbot_evaluation_sets
+SET name = $2
+WHERE id = $1
+  AND deleted_at IS NULL
+RETURNING *
+        ",
+        id,
+        name
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn delete_set(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE chatbot_evaluation_sets
+SET deleted_at = now()
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn insert_question(
+    conn: &mut PgConnection,
+    evaluation_set_id: Uuid,
+    new_question: &NewChatbotEvaluationQuestion,
+) -> ModelResult<ChatbotEvaluationQuestion> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationQuestion,
+        "
+INSERT INTO chatbot_evaluation_questions (
+    evaluation_set_id,
+    question,
+    expected_answer,
+    expected_page_ids,
+    rubric
+  )
+VALUES ($1, $2, $3, $4, $5)
+RETURNING *
+        ",
+        evaluation_set_id,
+        new_question.question,
+        new_question.expected_answer,
+        &new_question.expected_page_ids,
+        new_question.rubric
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_question_by_id(
+    conn: &mut PgConnection,
+    id: Uuid,
+) -> ModelResult<ChatbotEvaluationQuestion> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationQuestion,
+        "
+SELECT *
+FROM chatbot_evaluation_questions
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_questions_by_set_id(
+    conn: &mut PgConnection,
+    evaluation_set_id: Uuid,
+) -> ModelResult<Vec<ChatbotEvaluationQuestion>> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationQuestion,
+        "
+SELECT *
+FROM chatbot_evaluation_questions
+WHERE evaluation_set_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at,
+  id
+        ",
+        evaluation_set_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn update_question(
+    conn: &mut PgConnection,
+    id: Uuid,
+    new_question: &NewChatbotEvaluationQuestion,
+) -> ModelResult<ChatbotEvaluationQuestion> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationQuestion,
+        "
+UPDATE chatbot_evaluation_questions
+SET question = $2,
+  expected_answer = $3,
+  expected_page_ids = $4,
+  rubric = $5
+WHERE id = $1
+  AND deleted_at IS NULL
+RETURNING *
+        ",
+        id,
+        new_question.question,
+        new_question.expected_answer,
+        &new_question.expected_page_ids,
+        new_question.rubric
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn delete_question(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE chatbot_evaluation_questions
+SET deleted_at = now()
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn insert_run(
+    conn: &mut PgConnection,
+    evaluation_set_id: Uuid,
+    chatbot_configuration_id: Uuid,
+    created_by_user_id: Uuid,
+    configuration_snapshot: serde_json::Value,
+) -> ModelResult<ChatbotEvaluationRun> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationRun,
+        r#"
+INSERT INTO chatbot_evaluation_runs (
+    evaluation_set_id,
+    chatbot_configuration_id,
+    created_by_user_id,
+    configuration_snapshot
+  )
+VALUES ($1, $2, $3, $4)
+RETURNING id,
+  created_at,
+  updated_at,
+  deleted_at,
+  evaluation_set_id,
+  chatbot_configuration_id,
+  created_by_user_id,
+  configuration_snapshot,
+  status AS "status: ChatbotEvaluationRunStatus",
+  started_at,
+  finished_at,
+  error_message
+        "#,
+        evaluation_set_id,
+        chatbot_configuration_id,
+        created_by_user_id,
+        configuration_snapshot
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_run_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<ChatbotEvaluationRun> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationRun,
+        r#"
+SELECT id,
+  created_at,
+  updated_at,
+  deleted_at,
+  evaluation_set_id,
+  chatbot_configuration_id,
+  created_by_user_id,
+  configuration_snapshot,
+  status AS "status: ChatbotEvaluationRunStatus",
+  started_at,
+  finished_at,
+  error_message
+FROM chatbot_evaluation_runs
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Marks the oldest pending run as running and returns it. Runs claimed by another runner at the
+/// same time are skipped.
+pub async fn claim_next_pending_run(
+    conn: &mut PgConnection,
+) -> ModelResult<Option<ChatbotEvaluationRun>> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationRun,
+        r#"
+UPDATE chatbot_evaluation_runs
+SET status = 'running',
+  started_at = COALESCE(started_at, now())
+WHERE id = (
+    SELECT id
+    FROM chatbot_evaluation_runs
+    WHERE status = 'pending'
+      AND deleted_at IS NULL
+    ORDER BY created_at
+    LIMIT 1 FOR UPDATE SKIP LOCKED
+  )
+RETURNING id,
+  created_at,
+  updated_at,
+  deleted_at,
+  evaluation_set_id,
+  chatbot_configuration_id,
+  created_by_user_id,
+  configuration_snapshot,
+  status AS "status: ChatbotEvaluationRunStatus",
+  started_at,
+  finished_at,
+  error_message
+        "#
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Returns the runs that were left running when the runner stopped to the pending runs. The
+/// questions that were already answered are not asked again.
+pub async fn requeue_interrupted_runs(conn: &mut PgConnection) -> ModelResult<u64> {
+    let res = sqlx::query!(
+        "
+UPDATE chatbot_evaluation_runs
+SET status = 'pending'
+WHERE status = 'running'
+  AND deleted_at IS NULL
+        "
+    )
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+pub async fn finish_run(
+    conn: &mut PgConnection,
+    id: Uuid,
+    error_message: Option<&str>,
+) -> ModelResult<()> {
+    let status = if error_message.is_some() {
+        ChatbotEvaluationRunStatus::Failed
+    } else {
+        ChatbotEvaluationRunStatus::Completed
+    };
+    sqlx::query!(
+        "
+UPDATE chatbot_evaluation_runs
+SET status = $2,
+  finished_at = now(),
+  error_message = $3
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id,
+        status as ChatbotEvaluationRunStatus,
+        error_message
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn delete_run(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE chatbot_evaluation_runs
+SET deleted_at = now()
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The questions of the run's set that the run has no result for yet.
+pub async fn get_unanswered_questions(
+    conn: &mut PgConnection,
+    run_id: Uuid,
+) -> ModelResult<Vec<ChatbotEvaluationQuestion>> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationQuestion,
+        "
+SELECT q.*
+FROM chatbot_evaluation_questions q
+  JOIN chatbot_evaluation_runs r ON r.evaluation_set_id = q.evaluation_set_id
+WHERE r.id = $1
+  AND q.deleted_at IS NULL
+  AND NOT EXISTS (
+    SELECT 1
+    FROM chatbot_evaluation_results res
+    WHERE res.evaluation_run_id = r.id
+      AND res.evaluation_question_id = q.id
+      AND res.deleted_at IS NULL
+  )
+ORDER BY q.created_at,
+  q.id
+        ",
+        run_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn insert_result(
+    conn: &mut PgConnection,
+    run_id: Uuid,
+    question_id: Uuid,
+    result: &NewChatbotEvaluationResult,
+) -> ModelResult<ChatbotEvaluationResult> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationResult,
+        "
+INSERT INTO chatbot_evaluation_results (
+    evaluation_run_id,
+    evaluation_question_id,
+    conversation_id,
+    answer,
+    cited_page_ids,
+    citation_precision,
+    citation_recall,
+    groundedness_score,
+    correctness_score,
+    rubric_score,
+    judge_feedback,
+    error_message
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+RETURNING *
+        ",
+        run_id,
+        question_id,
+        result.conversation_id,
+        result.answer,
+        &result.cited_page_ids,
+        result.citation_precision,
+        result.citation_recall,
+        result.groundedness_score,
+        result.correctness_score,
+        result.rubric_score,
+        result.judge_feedback,
+        result.error_message
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_results_by_run_id(
+    conn: &mut PgConnection,
+    run_id: Uuid,
+) -> ModelResult<Vec<ChatbotEvaluationResult>> {
+    let res = sqlx::query_as!(
+        ChatbotEvaluationResult,
+        "
+SELECT *
+FROM chatbot_evaluation_results
+WHERE evaluation_run_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at
+        ",
+        run_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The set's runs with the averages of their scores, newest first.
+pub async fn get_run_summaries_by_set_id(
+    conn: &mut PgConnection,
+    evaluation_set_id: Uuid,
+) -> ModelResult<Vec<ChatbotEvaluationRunSummary>> {
+    let runs = sqlx::query_as!(
+        ChatbotEvaluationRun,
+        r#"
+SELECT id,
+  created_at,
+  updated_at,
+  deleted_at,
+  evaluation_set_id,
+  chatbot_configuration_id,
+  created_by_user_id,
+  configuration_snapshot,
+  status AS "status: ChatbotEvaluationRunStatus",
+  started_at,
+  finished_at,
+  error_message
+FROM chatbot_evaluation_runs
+WHERE evaluation_set_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at DESC
+        "#,
+        evaluation_set_id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let run_ids: Vec<Uuid> = runs.iter().map(|r| r.id).collect();
+    let mut averages = get_run_averages(conn, &run_ids).await?;
+    Ok(runs
+        .into_iter()
+        .map(|run| {
+            let averages = averages.remove(&run.id).unwrap_or_default();
+            averages.into_summary(run)
+        })
+        .collect())
+}
+
+/// Compares two runs of the same set question by question. Questions that were deleted after
+/// the runs are left out.
+pub async fn compare_runs(
+    conn: &mut PgConnection,
+    base_run_id: Uuid,
+    compared_run_id: Uuid,
+) -> ModelResult<ChatbotEvaluationRunComparison> {
+    let base_run = get_run_by_id(conn, base_run_id).await?;
+    let compared_run = get_run_by_id(conn, compared_run_id).await?;
+    if base_run.evaluation_set_id != compared_run.evaluation_set_id {
+        return Err(model_err!(
+            PreconditionFailed,
+            "Only runs of the same evaluation set can be compared.".to_string()
+        ));
+    }
+    let questions = get_questions_by_set_id(conn, base_run.evaluation_set_id).await?;
+    let mut base_results = results_by_question(get_results_by_run_id(conn, base_run.id).await?);
+    let mut compared_results =
+        results_by_question(get_results_by_run_id(conn, compared_run.id).await?);
+    let mut averages = get_run_averages(conn, &[base_run.id, compared_run.id]).await?;
+    let base = averages
+        .remove(&base_run.id)
+        .unwrap_or_default()
+        .into_summary(base_run);
+    let compared = averages
+        .remove(&compared_run.id)
+        .unwrap_or_default()
+        .into_summary(compared_run);
+    let questions = questions
+        .into_iter()
+        .map(|question| ChatbotEvaluationQuestionComparison {
+            base_result: base_results.remove(&question.id),
+            compared_result: compared_results.remove(&question.id),
+            question,
+        })
+        .collect();
+    Ok(ChatbotEvaluationRunComparison {
+        base,
+        compared,
+        questions,
+    })
+}
+
+fn results_by_question(
+    results: Vec<ChatbotEvaluationResult>,
+) -> HashMap<Uuid, ChatbotEvaluationResult> {
+    results
+        .into_iter()
+        .map(|r| (r.evaluation_question_id, r))
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+struct RunAverages {
+    answered_count: i64,
+    failed_count: i64,
+    average_citation_precision: Option<f64>,
+    average_citation_recall: Option<f64>,
+    average_groundedness_score: Option<f64>,
+    average_correctness_score: Option<f64>,
+    average_rubric_score: Option<f64>,
+}
+
+impl RunAverages {
+    fn into_summary(self, run: ChatbotEvaluationRun) -> ChatbotEvaluationRunSummary {
+        ChatbotEvaluationRunSummary {
+            run,
+            answered_count: self.answered_count,
+            failed_count: self.failed_count,
+            average_citation_precision: self.average_citation_precision,
+            average_citation_recall: self.average_citation_recall,
+            average_groundedness_score: self.average_groundedness_score,
+            average_correctness_score: self.average_correctness_score,
+            average_rubric_score: self.average_rubric_score,
+        }
+    }
+}
+
+async fn get_run_averages(
+    conn: &mut PgConnection,
+    run_ids: &[Uuid],
+) -> ModelResult<HashMap<Uuid, RunAverages>> {
+    let rows = sqlx::query!(
+        r#"
+SELECT evaluation_run_id,
+  COUNT(*) FILTER (
+    WHERE error_message IS NULL
+  ) AS "answered_count!",
+  COUNT(*) FILTER (
+    WHERE error_message IS NOT NULL
+  ) AS "failed_count!",
+  AVG(citation_precision)::float8 AS average_citation_precision,
+  AVG(citation_recall)::float8 AS average_citation_recall,
+  AVG(groundedness_score)::float8 AS average_groundedness_score,
+  AVG(correctness_score)::float8 AS average_correctness_score,
+  AVG(rubric_score)::float8 AS average_rubric_score
+FROM chatbot_evaluation_results
+WHERE evaluation_run_id = ANY($1)
+  AND deleted_at IS NULL
+GROUP BY evaluation_run_id
+        "#,
+        run_ids
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            (
+                row.evaluation_run_id,
+                RunAverages {
+                    answered_count: row.answered_count,
+                    failed_count: row.failed_count,
+                    average_citation_precision: row.average_citation_precision,
+                    average_citation_recall: row.average_citation_recall,
+                    average_groundedness_score: row.average_groundedness_score,
+                    average_correctness_score: row.average_correctness_score,
+                    average_rubric_score: row.average_rubric_score,
+                },
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        chatbot_configurations,
+        chatbot_configurations_models::{self, ModelProvider, ModelType},
+        test_helper::*,
+    };
+
+    #[tokio::test]
+    async fn runs_are_claimed_and_compared() {
+        insert_data!(:tx, :user, :org, :course);
+        let model = chatbot_configurations_models::insert(
+            tx.as_mut(),
+            chatbot_configurations_models::NewChatbotConfigurationModel {
+                id: Uuid::new_v4(),
+                model: "gpt-4o".to_string(),
+                model_type: ModelType::GPTNonThinking,
+                default_model: false,
+                context_size: 128000,
+                provider: ModelProvider::Azure,
+                api_base_url: None,
+                api_key_env_var: None,
+            },
+        )
+        .await
+        .unwrap();
+        let configuration = chatbot_configurations::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            chatbot_configurations::NewChatbotConf {
+                course_id: course,
+                model_id: model.id,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let set = insert_set(tx.as_mut(), course, "Course practicalities")
+            .await
+            .unwrap();
+        let question = insert_question(
+            tx.as_mut(),
+            set.id,
+            &NewChatbotEvaluationQuestion {
+                question: "When is the exam?".to_string(),
+                expected_answer: "There is no exam.".to_string(),
+                expected_page_ids: Vec::new(),
+                rubric: None,
+            },
+        )
+        .await
+        .unwrap();
+
+        let base = insert_run(
+            tx.as_mut(),
+            set.id,
+            configuration.id,
+            user,
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        let compared = insert_run(
+            tx.as_mut(),
+            set.id,
+            configuration.id,
+            user,
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+
+        let claimed = claim_next_pending_run(tx.as_mut()).await.unwrap().unwrap();
+        assert_eq!(claimed.id, base.id);
+        assert_eq!(claimed.status, ChatbotEvaluationRunStatus::Running);
+        let unanswered = get_unanswered_questions(tx.as_mut(), base.id)
+            .await
+            .unwrap();
+        assert_eq!(unanswered.len(), 1);
+        insert_result(
+            tx.as_mut(),
+            base.id,
+            question.id,
+            &NewChatbotEvaluationResult {
+                answer: Some("There is no exam.".to_string()),
+                correctness_score: Some(5),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        assert!(
+            get_unanswered_questions(tx.as_mut(), base.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        finish_run(tx.as_mut(), base.id, None).await.unwrap();
+
+        let claimed = claim_next_pending_run(tx.as_mut()).await.unwrap().unwrap();
+        assert_eq!(claimed.id, compared.id);
+        insert_result(
+            tx.as_mut(),
+            compared.id,
+            question.id,
+            &NewChatbotEvaluationResult {
+                answer: Some("The exam is on Friday.".to_string()),
+                correctness_score: Some(1),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        finish_run(tx.as_mut(), compared.id, None).await.unwrap();
+        assert!(claim_next_pending_run(tx.as_mut()).await.unwrap().is_none());
+
+        let comparison = compare_runs(tx.as_mut(), base.id, compared.id)
+            .await
+            .unwrap();
+        assert_eq!(comparison.base.average_correctness_score, Some(5.0));
+        assert_eq!(comparison.compared.average_correctness_score, Some(1.0));
+        assert_eq!(
+            comparison.base.run.status,
+            ChatbotEvaluationRunStatus::Completed
+        );
+        assert_eq!(comparison.questions.len(), 1);
+        assert_eq!(
+            comparison.questions[0]
+                .compared_result
+                .as_ref()
+                .and_then(|r| r.correctness_score),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+
+use pgvector::Vector;
+
+use crate::prelude::*;
+
+/// How much the rank of a result in one search affects its combined score in reciprocal rank
+/// fusion. Larger values flatten the differences between the top ranks.
+const RECIPROCAL_RANK_FUSION_K: i32 = 60;
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NewChatbotPageChunk {
+    pub context: String,
+    pub content: String,
+    pub embedding: Vec<f32>,
+}
+
+/// A chunk found by [`hybrid_search`], with the page it is from.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ChatbotPageChunkSearchResult {
+    pub id: Uuid,
+    pub page_id: Uuid,
+    pub chunk_number: i32,
+    pub context: String,
+    pub content: String,
+    pub embedding: Vector,
+    pub page_title: String,
+    pub page_url_path: String,
+    pub course_slug: String,
+    pub organization_slug: String,
+    /// The reciprocal rank fusion score of the chunk. Higher is better.
+    pub score: f64,
+}
+
+/// Replaces the chunks of a page with chunks made from the given page revision.
+pub async fn replace_for_page(
+    conn: &mut PgConnection,
+    page_id: Uuid,
+    page_history_id: Uuid,
+    chunks: Vec<NewChatbotPageChunk>,
+) -> ModelResult<()> {
+    let mut tx = conn.begin().await?;
+    sqlx::query!(
+        r#"
+UPDATE chatbot_page_chunks
+SET deleted_at = now()
+WHERE page_id = $1
+  AND deleted_at IS NULL
+"#,
+        page_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let chunk_numbers: Vec<i32> = (0..chunks.len() as i32).collect();
+    let mut contexts = Vec::with_capacity(chunks.len());
+    let mut contents = Vec::with_capacity(chunks.len());
+    let mut embeddings = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        contexts.push(chunk.context);
+        contents.push(chunk.content);
+        embeddings.push(Vector::from(chunk.embedding));
+    }
+    sqlx::query!(
+        r#"
+INSERT INTO chatbot_page_chunks (
+    course_id,
+    page_id,
+    page_history_id,
+    chunk_number,
+    context,
+    content,
+    search_document,
+    embedding
+  )
+SELECT pages.course_id,
+  pages.id,
+  $2,
+  t.chunk_number,
+  t.context,
+  t.content,
+  to_tsvector(
+    courses.content_search_language::regconfig,
+    t.context || ' ' || t.content
+  ),
+  t.embedding
+FROM pages
+  JOIN courses ON courses.id = pages.course_id
+  CROSS JOIN UNNEST($3::integer[], $4::text[], $5::text[], $6::vector[]) AS t(chunk_number, context, content, embedding)
+WHERE pages.id = $1
+"#,
+        page_id,
+        page_history_id,
+        &chunk_numbers,
+        &contexts,
+        &contents,
+        &embeddings as _
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Returns a map from page id to the page revision that the page's chunks were made from.
+pub async fn get_indexed_page_history_ids(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+) -> ModelResult<HashMap<Uuid, Uuid>> {
+    let rows = sqlx::query!(
+        r#"
+SELECT DISTINCT page_id,
+  page_history_id
+FROM chatbot_page_chunks
+WHERE course_id = $1
+  AND deleted_at IS NULL
+"#,
+        course_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.page_id, row.page_history_id))
+        .collect())
+}
+
+/// Deletes the chunks of the course's pages that are not in `page_ids`.
+pub async fn delete_for_other_pages_in_course(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    page_ids: &[Uuid],
+) -> ModelResult<u64> {
+    let res = sqlx::query!(
+        r#"
+UPDATE chatbot_page_chunks
+SET deleted_at = now()
+WHERE course_id = $1
+  AND NOT (page_id = ANY($2))
+  AND deleted_at IS NULL
+"#,
+        course_id,
+        page_ids
+    )
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+/**
+Searches the course's chunks by both meaning and keywords and combines the results with reciprocal
+rank fusion.
+
+The semantic search orders chunks by the cosine distance of their embedding to `query_embedding`,
+and the keyword search by how well they match any of the words in `query_text`, stemmed in the
+course's language. Each search contributes its top `limit` chunks, and a chunk's score is the sum of
+`1 / (k + rank)` over the searches that found it.
+*/
+pub async fn hybrid_search(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    query_text: &str,
+    query_embedding: Vec<f32>,
+    limit: i64,
+) -> ModelResult<Vec<ChatbotPageChunkSearchResult>> {
+    let query_embedding = Vector::from(query_embedding);
+    let res = sqlx::query_as!(
+        ChatbotPageChunkSearchResult,
+        r#"
+WITH semantic AS (
+  SELECT id,
+    ROW_NUMBER() OVER (
+      ORDER BY embedding <=> $2
+    ) AS rank
+  FROM chatbot_page_chunks
+  WHERE course_id = $1
+    AND deleted_at IS NULL
+  ORDER BY embedding <=> $2
+  LIMIT $4
+),
+keyword_query AS (
+  -- plainto_tsquery requires every word to match, so the words are combined with OR instead.
+  SELECT NULLIF(
+      replace(
+        plainto_tsquery(content_search_language::regconfig, $3)::text,
+        ' & ',
+        ' | '
+      ),
+      ''
+    )::tsquery AS query
+  FROM courses
+  WHERE id = $1
+),
+keyword AS (
+  SELECT c.id,
+    ROW_NUMBER() OVER (
+      ORDER BY ts_rank_cd(c.search_document, q.query) DESC
+    ) AS rank
+  FROM chatbot_page_chunks c
+    CROSS JOIN keyword_query q
+  WHERE c.course_id = $1
+    AND c.deleted_at IS NULL
+    AND c.search_document @@ q.query
+  ORDER BY ts_rank_cd(c.search_document, q.query) DESC
+  LIMIT $4
+)
+SELECT c.id,
+  c.page_id,
+  c.chunk_number,
+  c.context,
+  c.content,
+  c.embedding AS "embedding: Vector",
+  p.title AS page_title,
+  p.url_path AS page_url_path,
+  co.slug AS course_slug,
+  o.slug AS organization_slug,
+  (
+    COALESCE(1.0 / ($5 + s.rank), 0) + COALESCE(1.0 / ($5 + k.rank), 0)
+  )::float8 AS "score!"
+FROM semantic s
+  FULL OUTER JOIN keyword k ON s.id = k.id
+  JOIN chatbot_page_chunks c ON c.id = COALESCE(s.id, k.id)
+  JOIN pages p ON p.id = c.page_id
+  JOIN courses co ON co.id = c.course_id
+  JOIN organizations o ON o.id = co.organization_id
+WHERE p.deleted_at IS NULL
+ORDER BY "score!" DESC,
+  c.id
+LIMIT $4
+"#,
+        course_id,
+        query_embedding as _,
+        query_text,
+        limit,
+        RECIPROCAL_RANK_FUSION_K
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{page_history, test_helper::*};
+
+    fn embedding(direction: usize) -> Vec<f32> {
+        let mut embedding = vec![0.0; 1536];
+        embedding[direction] = 1.0;
+        embedding
+    }
+
+    #[tokio::test]
+    async fn hybrid_search_combines_keyword_and_semantic_results() {
+        insert_data!(:tx, :user, :org, :course, instance: _instance, :course_module, :chapter, :page);
+        let history_id =
+            page_history::get_latest_page_history_ids_by_course_ids(tx.as_mut(), &[course])
+                .await
+                .unwrap()[&page];
+
+        replace_for_page(
+            tx.as_mut(),
+            page,
+            history_id,
+            vec![
+                NewChatbotPageChunk {
+                    context: "Page".to_string(),
+                    content: "Recursion is when a function calls itself.".to_string(),
+                    embedding: embedding(0),
+                },
+                NewChatbotPageChunk {
+                    context: "Page".to_string(),
+                    content: "Loops repeat a block of code.".to_string(),
+                    embedding: embedding(1),
+                },
+                NewChatbotPageChunk {
+                    context: "Page".to_string(),
+                    content: "Variables store values.".to_string(),
+                    embedding: embedding(2),
+                },
+            ],
+        )
+        .await
+        .unwrap();
+
+        // The first chunk matches the keyword and the second one the embedding.
+        let results = hybrid_search(tx.as_mut(), course, "recursion", embedding(1), 2)
+            .await
+            .unwrap();
+        let chunk_numbers: Vec<i32> = results.iter().map(|r| r.chunk_number).collect();
+        assert_eq!(results.len(), 2);
+        assert!(chunk_numbers.contains(&0));
+        assert!(chunk_numbers.contains(&1));
+
+        // A chunk found by both searches ranks first.
+        let results = hybrid_search(tx.as_mut(), course, "variables", embedding(2), 3)
+            .await
+            .unwrap();
+        assert_eq!(results[0].chunk_number, 2);
+
+        let indexed = get_indexed_page_history_ids(tx.as_mut(), course)
+            .await
+            .unwrap();
+        assert_eq!(indexed.get(&page), Some(&history_id));
+
+        delete_for_other_pages_in_course(tx.as_mut(), course, &[])
+            .await
+            .unwrap();
+        let results = hybrid_search(tx.as_mut(), course, "variables", embedding(2), 3)
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+}
@@ -0,0 +1,402 @@
+/*!
+Exercises a teacher has allowed the course chatbot to tutor students on.
+
+The tutoring data is read with dedicated queries that never select the model solution spec or the
+private spec of the exercise tasks, so the chatbot can't leak them to the student.
+*/
+
+use utoipa::ToSchema;
+
+use crate::{deadline_exceptions, exercises::GradingProgress, prelude::*};
+
+/// A tutoring-enabled exercise of a course, with the time the user last answered it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ChatbotTutoringExercise {
+    pub exercise_id: Uuid,
+    pub name: String,
+    pub chapter_number: Option<i32>,
+    /// The deadline that applies to the user, with the chapter deadline and deadline exceptions taken into account.
+    pub deadline: Option<DateTime<Utc>>,
+    pub score_maximum: i32,
+    pub latest_submission_at: Option<DateTime<Utc>>,
+}
+
+/// An exercise task of the slide the user is on, with the user's latest answer to it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ChatbotTutoringTask {
+    pub exercise_task_id: Uuid,
+    pub order_number: i32,
+    pub exercise_type: String,
+    pub assignment: serde_json::Value,
+    pub public_spec: Option<serde_json::Value>,
+    pub latest_answer: Option<serde_json::Value>,
+    pub grading_progress: Option<GradingProgress>,
+    pub unscaled_score_given: Option<f32>,
+    pub unscaled_score_maximum: Option<i32>,
+    pub feedback_text: Option<String>,
+    pub feedback_json: Option<serde_json::Value>,
+}
+
+pub async fn enable_for_exercise(conn: &mut PgConnection, exercise_id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+INSERT INTO chatbot_tutoring_exercises (exercise_id)
+VALUES ($1) ON CONFLICT (exercise_id)
+WHERE deleted_at IS NULL DO NOTHING
+        "#,
+        exercise_id,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn disable_for_exercise(conn: &mut PgConnection, exercise_id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+UPDATE chatbot_tutoring_exercises
+SET deleted_at = now()
+WHERE exercise_id = $1
+  AND deleted_at IS NULL
+        "#,
+        exercise_id,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn is_enabled_for_exercise(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+) -> ModelResult<bool> {
+    let res = sqlx::query!(
+        r#"
+SELECT EXISTS (
+    SELECT 1
+    FROM chatbot_tutoring_exercises
+    WHERE exercise_id = $1
+      AND deleted_at IS NULL
+  ) AS "enabled!"
+        "#,
+        exercise_id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res.enabled)
+}
+
+/// The tutoring-enabled exercises of the course that the user can see, the ones the user answered
+/// most recently first: exercises on hidden pages or in chapters that have not opened yet are left
+/// out. The deadlines are the user's own, see [`deadline_exceptions::get_effective_exercise_deadlines`].
+pub async fn get_for_course_and_user(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<Vec<ChatbotTutoringExercise>> {
+    let mut res = sqlx::query_as!(
+        ChatbotTutoringExercise,
+        r#"
+SELECT e.id AS exercise_id,
+  e.name,
+  ch.chapter_number AS "chapter_number?",
+  e.deadline,
+  e.score_maximum,
+  (
+    SELECT MAX(ess.created_at)
+    FROM exercise_slide_submissions ess
+    WHERE ess.exercise_id = e.id
+      AND ess.user_id = $2
+      AND ess.deleted_at IS NULL
+  ) AS latest_submission_at
+FROM chatbot_tutoring_exercises cte
+  JOIN exercises e ON e.id = cte.exercise_id
+  JOIN pages p ON p.id = e.page_id
+  LEFT JOIN chapters ch ON ch.id = e.chapter_id
+WHERE e.course_id = $1
+  AND cte.deleted_at IS NULL
+  AND e.deleted_at IS NULL
+  AND p.deleted_at IS NULL
+  AND p.hidden = false
+  AND ch.deleted_at IS NULL
+  AND (
+    ch.opens_at IS NULL
+    OR ch.opens_at <= now()
+  )
+ORDER BY latest_submission_at DESC NULLS LAST,
+  ch.chapter_number,
+  p.order_number,
+  e.order_number
+        "#,
+        course_id,
+        user_id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let exercise_ids = res.iter().map(|e| e.exercise_id).collect::<Vec<_>>();
+    let deadlines =
+        deadline_exceptions::get_effective_exercise_deadlines(conn, &exercise_ids, Some(user_id))
+            .await?;
+    for exercise in &mut res {
+        exercise.deadline = deadlines.get(&exercise.exercise_id).copied().flatten();
+    }
+    Ok(res)
+}
+
+/**
+The exercise tasks of the slide the user is on in the exercise, with the user's latest answer and
+its grading. Uses the first slide if the user hasn't been assigned one. Returns no tasks if the
+exercise is on a hidden page or in a chapter that has not opened yet.
+
+Only the assignment and the public spec of the tasks are returned: the model solution spec and the
+private spec must never be added here, as the result is shown to the chatbot.
+*/
+pub async fn get_tasks_for_user(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<Vec<ChatbotTutoringTask>> {
+    let res = sqlx::query_as!(
+        ChatbotTutoringTask,
+        r#"
+WITH slide AS (
+  SELECT COALESCE(
+      (
+        SELECT ues.selected_exercise_slide_id
+        FROM user_exercise_states ues
+        WHERE ues.exercise_id = $1
+          AND ues.user_id = $2
+          AND ues.selected_exercise_slide_id IS NOT NULL
+          AND ues.deleted_at IS NULL
+        ORDER BY ues.updated_at DESC
+        LIMIT 1
+      ), (
+        SELECT s.id
+        FROM exercise_slides s
+        WHERE s.exercise_id = $1
+          AND s.deleted_at IS NULL
+        ORDER BY s.order_number
+        LIMIT 1
+      )
+    ) AS id
+)
+SELECT t.id AS exercise_task_id,
+  t.order_number,
+  t.exercise_type,
+  t.assignment,
+  t.public_spec,
+  latest.data_json AS "latest_answer?",
+  g.grading_progress AS "grading_progress?: GradingProgress",
+  g.unscaled_score_given AS "unscaled_score_given?",
+  g.unscaled_score_maximum AS "unscaled_score_maximum?",
+  g.feedback_text AS "feedback_text?",
+  g.feedback_json AS "feedback_json?"
+FROM exercise_tasks t
+  JOIN slide ON slide.id = t.exercise_slide_id
+  LEFT JOIN LATERAL (
+    SELECT ets.data_json,
+      ets.exercise_task_grading_id
+    FROM exercise_task_submissions ets
+      JOIN exercise_slide_submissions ess ON ess.id = ets.exercise_slide_submission_id
+    WHERE ets.exercise_task_id = t.id
+      AND ess.user_id = $2
+      AND ets.deleted_at IS NULL
+      AND ess.deleted_at IS NULL
+    ORDER BY ets.created_at DESC
+    LIMIT 1
+  ) latest ON TRUE
+  LEFT JOIN exercise_task_gradings g ON g.id = latest.exercise_task_grading_id
+  AND g.deleted_at IS NULL
+WHERE t.deleted_at IS NULL
+  AND EXISTS (
+    SELECT 1
+    FROM exercises e
+      JOIN pages p ON p.id = e.page_id
+      LEFT JOIN chapters ch ON ch.id = e.chapter_id
+    WHERE e.id = $1
+      AND e.deleted_at IS NULL
+      AND p.deleted_at IS NULL
+      AND p.hidden = false
+      AND ch.deleted_at IS NULL
+      AND (
+        ch.opens_at IS NULL
+        OR ch.opens_at <= now()
+      )
+  )
+ORDER BY t.order_number
+        "#,
+        exercise_id,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        exercise_slide_submissions::{self, NewExerciseSlideSubmission},
+        exercise_task_gradings::UserPointsUpdateStrategy,
+        exercise_task_submissions,
+        exercise_tasks::{self, NewExerciseTask},
+        test_helper::*,
+    };
+
+    #[tokio::test]
+    async fn tutoring_tasks_never_contain_the_model_solution() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise, :slide);
+        let task = exercise_tasks::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            NewExerciseTask {
+                exercise_slide_id: slide,
+                exercise_type: TEST_HELPER_EXERCISE_SERVICE_NAME.to_string(),
+                assignment: vec![],
+                public_spec: Some(serde_json::json!({ "options": ["a", "b"] })),
+                private_spec: Some(serde_json::json!({ "correct": "private-answer" })),
+                model_solution_spec: Some(serde_json::json!({ "correct": "model-solution" })),
+                order_number: 0,
+            },
+        )
+        .await
+        .unwrap();
+
+        enable_for_exercise(tx.as_mut(), exercise).await.unwrap();
+        // Enabling twice is a no-op.
+        enable_for_exercise(tx.as_mut(), exercise).await.unwrap();
+        let exercises = get_for_course_and_user(tx.as_mut(), course, user)
+            .await
+            .unwrap();
+        assert_eq!(exercises.len(), 1);
+        assert_eq!(exercises[0].latest_submission_at, None);
+        assert_eq!(exercises[0].deadline, None);
+
+        let extended_deadline = Utc::now() + chrono::Duration::days(7);
+        let exception = deadline_exceptions::grant(
+            tx.as_mut(),
+            Some(course),
+            &deadline_exceptions::NewDeadlineException {
+                target: deadline_exceptions::DeadlineExceptionTarget::Exercise(exercise),
+                scope: deadline_exceptions::DeadlineExceptionScope::User(user),
+                deadline: extended_deadline,
+                reason: None,
+            },
+            user,
+        )
+        .await
+        .unwrap();
+        let exercises = get_for_course_and_user(tx.as_mut(), course, user)
+            .await
+            .unwrap();
+        assert_eq!(exercises[0].deadline, Some(exception.deadline));
+
+        let slide_submission = exercise_slide_submissions::insert_exercise_slide_submission(
+            tx.as_mut(),
+            NewExerciseSlideSubmission {
+                exercise_slide_id: slide,
+                course_id: Some(course),
+                exam_id: None,
+                user_id: user,
+                exercise_id: exercise,
+                user_points_update_strategy:
+                    UserPointsUpdateStrategy::CanAddPointsButCannotRemovePoints,
+            },
+        )
+        .await
+        .unwrap();
+        exercise_task_submissions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            slide_submission.id,
+            slide,
+            task,
+            &serde_json::json!({ "selected": "b" }),
+        )
+        .await
+        .unwrap();
+
+        let tasks = get_tasks_for_user(tx.as_mut(), exercise, user)
+            .await
+            .unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(
+            tasks[0].latest_answer,
+            Some(serde_json::json!({ "selected": "b" }))
+        );
+        let serialized = serde_json::to_string(&tasks).unwrap();
+        assert!(serialized.contains("options"));
+        assert!(!serialized.contains("model-solution"));
+        assert!(!serialized.contains("private-answer"));
+
+        disable_for_exercise(tx.as_mut(), exercise).await.unwrap();
+        assert!(
+            !is_enabled_for_exercise(tx.as_mut(), exercise)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn exercises_the_user_cannot_see_are_not_tutored() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise, :slide);
+        exercise_tasks::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            NewExerciseTask {
+                exercise_slide_id: slide,
+                exercise_type: TEST_HELPER_EXERCISE_SERVICE_NAME.to_string(),
+                assignment: vec![],
+                public_spec: Some(serde_json::json!({ "options": ["a", "b"] })),
+                private_spec: None,
+                model_solution_spec: None,
+                order_number: 0,
+            },
+        )
+        .await
+        .unwrap();
+        enable_for_exercise(tx.as_mut(), exercise).await.unwrap();
+        sqlx::query("UPDATE chapters SET opens_at = now() + interval '1 day' WHERE id = $1")
+            .bind(chapter)
+            .execute(tx.as_mut())
+            .await
+            .unwrap();
+        assert!(
+            get_for_course_and_user(tx.as_mut(), course, user)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            get_tasks_for_user(tx.as_mut(), exercise, user)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        sqlx::query("UPDATE chapters SET opens_at = now() - interval '1 day' WHERE id = $1")
+            .bind(chapter)
+            .execute(tx.as_mut())
+            .await
+            .unwrap();
+        assert_eq!(
+            get_tasks_for_user(tx.as_mut(), exercise, user)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        sqlx::query("UPDATE pages SET hidden = true WHERE id = $1")
+            .bind(page)
+            .execute(tx.as_mut())
+            .await
+            .unwrap();
+        assert!(
+            get_for_course_and_user(tx.as_mut(), course, user)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
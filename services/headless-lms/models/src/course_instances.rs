@@ -776,40 +776,30 @@ WHERE user_exercise_state_id IN (
     )
     .execute(&mut *tx)
     .await?;
-    sqlx::query!(
+    let mut course_module_ids = sqlx::query!(
         "
 UPDATE course_module_completions
 SET deleted_at = now()
 WHERE user_id = $1
 AND course_id = $2
 AND deleted_at IS NULL
+RETURNING course_module_id
 ",
         user_id,
         course_id
     )
-    .execute(&mut *tx)
-    .await?;
-    sqlx::query!(
-        "
-UPDATE generated_certificates
-SET deleted_at = NOW()
-WHERE user_id = $1
-  AND certificate_configuration_id IN (
-    SELECT certificate_configuration_id
-    FROM certificate_configuration_to_requirements
-    WHERE course_module_id IN (
-        SELECT id
-        FROM course_modules
-        WHERE course_id = $2
-      )
-      AND deleted_at IS NULL
-  )
-  AND deleted_at IS NULL
-",
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|row| row.course_module_id)
+    .collect::<Vec<_>>();
+    course_module_ids.sort();
+    course_module_ids.dedup();
+    crate::course_module_completions::revoke_certificates_without_completions(
+        &mut tx,
         user_id,
-        course_id
+        &course_module_ids,
     )
-    .execute(&mut *tx)
     .await?;
     sqlx::query!(
         "
@@ -807,19 +807,60 @@ WHERE course_module_id = ANY($1)
     .fetch(conn)
 }
 
+/// Deletes the completion. If the user has no other completion for the module, the certificates
+/// issued for it are revoked.
 pub async fn delete(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
-    sqlx::query!(
+    let mut tx = conn.begin().await?;
+    let deleted = sqlx::query!(
         "
 
 UPDATE course_module_completions
 SET deleted_at = now()
 WHERE id = $1
 AND deleted_at IS NULL
+RETURNING user_id, course_module_id
         ",
         id,
     )
-    .execute(conn)
+    .fetch_optional(&mut *tx)
     .await?;
+    if let Some(deleted) = deleted {
+        revoke_certificates_without_completions(
+            &mut tx,
+            deleted.user_id,
+            &[deleted.course_module_id],
+        )
+        .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Revokes the user's certificates that require any of the given modules the user no longer has a
+/// completion for. Called after the user's completions have been removed.
+pub async fn revoke_certificates_without_completions(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    course_module_ids: &[Uuid],
+) -> ModelResult<()> {
+    let mut without_completions = Vec::new();
+    for course_module_id in course_module_ids {
+        if get_all_by_course_module_and_user_ids(conn, *course_module_id, user_id)
+            .await?
+            .is_empty()
+        {
+            without_completions.push(*course_module_id);
+        }
+    }
+    if !without_completions.is_empty() {
+        crate::generated_certificates::revoke_all_by_user_and_course_module_ids(
+            conn,
+            user_id,
+            &without_completions,
+            "The course module completion the certificate was issued for was removed.",
+        )
+        .await?;
+    }
     Ok(())
 }
 
@@ -113,6 +113,29 @@ AND deleted_at IS NULL
     Ok(res)
 }
 
+/// Returns the latest Markdown converted from each of the page revisions, if any.
+pub async fn get_latest_by_page_history_ids(
+    conn: &mut PgConnection,
+    page_history_ids: &[Uuid],
+) -> ModelResult<Vec<CoursePageMarkdownContent>> {
+    let res = sqlx::query_as!(
+        CoursePageMarkdownContent,
+        r#"
+SELECT DISTINCT ON (page_history_id) *
+FROM course_page_markdown_content
+WHERE page_history_id = ANY($1)
+AND deleted_at IS NULL
+ORDER BY page_history_id,
+  created_at DESC
+    "#,
+        page_history_ids
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(res)
+}
+
 /// Get latest page content, either latest Markdown that has been synced or json format.
 pub async fn get_course_page_content_by_page_id(
     conn: &mut PgConnection,
@@ -0,0 +1,101 @@
+//! Courses whose learning events are sent to the learning record store as xAPI statements, see
+//! [`crate::xapi_statements`].
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct CourseXapiOptIn {
+    pub id: Uuid,
+    /// Only the events after this are sent.
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub course_id: Uuid,
+    pub opted_in_by_user_id: Uuid,
+}
+
+pub async fn get_by_course_id(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+) -> ModelResult<Option<CourseXapiOptIn>> {
+    let res = sqlx::query_as!(
+        CourseXapiOptIn,
+        "
+SELECT id,
+  created_at,
+  updated_at,
+  course_id,
+  opted_in_by_user_id
+FROM course_xapi_opt_ins
+WHERE course_id = $1
+  AND deleted_at IS NULL
+        ",
+        course_id
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Opts the course in. Does nothing if it already is, so that the events since the original opt-in
+/// keep being sent.
+pub async fn opt_in(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<CourseXapiOptIn> {
+    let mut tx = conn.begin().await?;
+    sqlx::query!(
+        "
+INSERT INTO course_xapi_opt_ins (course_id, opted_in_by_user_id)
+VALUES ($1, $2)
+ON CONFLICT (course_id)
+WHERE deleted_at IS NULL DO NOTHING
+        ",
+        course_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+    let res = get_by_course_id(&mut tx, course_id)
+        .await?
+        .ok_or_else(|| model_err!(NotFound, "The course has not opted in.".to_string()))?;
+    tx.commit().await?;
+    Ok(res)
+}
+
+/// Opts the course out. The statements that have already been made are still sent.
+pub async fn opt_out(conn: &mut PgConnection, course_id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE course_xapi_opt_ins
+SET deleted_at = now()
+WHERE course_id = $1
+  AND deleted_at IS NULL
+        ",
+        course_id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helper::*;
+
+    #[tokio::test]
+    async fn opting_in_twice_keeps_the_original_opt_in() {
+        insert_data!(:tx, :user, :org, :course);
+
+        assert_eq!(get_by_course_id(tx.as_mut(), course).await.unwrap(), None);
+        let first = opt_in(tx.as_mut(), course, user).await.unwrap();
+        let second = opt_in(tx.as_mut(), course, user).await.unwrap();
+        assert_eq!(first, second);
+
+        opt_out(tx.as_mut(), course).await.unwrap();
+        assert_eq!(get_by_course_id(tx.as_mut(), course).await.unwrap(), None);
+    }
+}
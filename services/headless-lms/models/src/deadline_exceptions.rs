@@ -0,0 +1,651 @@
+//! Individual deadlines that replace the shared exercise, chapter or exam deadline.
+//!
+//! An exception is scoped either to one student or to a whole course instance, and targets exactly
+//! one exercise, chapter or exam. When several exceptions could apply to an exercise, the most
+//! specific one wins: an exercise exception beats a chapter exception, and within the same target a
+//! per-student exception beats a course instance exception. A chapter exception never moves an
+//! exercise that has its own, later deadline earlier.
+
+use std::collections::HashMap;
+
+use utoipa::ToSchema;
+
+use crate::{exercises::Exercise, prelude::*};
+
+/// Seconds of grace the submission check leaves before the deadline, so that a submission that
+/// arrives at the exact deadline is already rejected rather than depending on request latency.
+const DEADLINE_MARGIN_SECONDS: i64 = 1;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct DeadlineException {
+    pub id: Uuid,
+    pub course_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub course_instance_id: Option<Uuid>,
+    pub exercise_id: Option<Uuid>,
+    pub chapter_id: Option<Uuid>,
+    pub exam_id: Option<Uuid>,
+    pub deadline: DateTime<Utc>,
+    pub reason: Option<String>,
+    pub granted_by: Uuid,
+    pub revoked_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// What an exception replaces the deadline of.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case", tag = "type", content = "id")]
+pub enum DeadlineExceptionTarget {
+    Exercise(Uuid),
+    Chapter(Uuid),
+    Exam(Uuid),
+}
+
+/// Who an exception applies to.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case", tag = "type", content = "id")]
+pub enum DeadlineExceptionScope {
+    User(Uuid),
+    CourseInstance(Uuid),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewDeadlineException {
+    pub target: DeadlineExceptionTarget,
+    pub scope: DeadlineExceptionScope,
+    pub deadline: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+/// Grants an exception, replacing the live exception for the same scope and target if there is
+/// one. The replaced row is soft-deleted with `revoked_by` set to the granter, so the history of
+/// every change stays in the table.
+///
+/// `course_id` must be the course the target belongs to, and `None` for exam exceptions; the
+/// caller is expected to have checked that the target and scope belong to it.
+pub async fn grant(
+    conn: &mut PgConnection,
+    course_id: Option<Uuid>,
+    new_exception: &NewDeadlineException,
+    granted_by: Uuid,
+) -> ModelResult<DeadlineException> {
+    let (exercise_id, chapter_id, exam_id) = match new_exception.target {
+        DeadlineExceptionTarget::Exercise(id) => (Some(id), None, None),
+        DeadlineExceptionTarget::Chapter(id) => (None, Some(id), None),
+        DeadlineExceptionTarget::Exam(id) => (None, None, Some(id)),
+    };
+    let (user_id, course_instance_id) = match new_exception.scope {
+        DeadlineExceptionScope::User(id) => (Some(id), None),
+        DeadlineExceptionScope::CourseInstance(id) => (None, Some(id)),
+    };
+    if exam_id.is_some() && user_id.is_none() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "Exam deadline exceptions can only be granted to individual students.".to_string()
+        ));
+    }
+    if exam_id.is_some() != course_id.is_none() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "Exam deadline exceptions do not belong to a course, other exceptions must."
+                .to_string()
+        ));
+    }
+
+    let mut tx = conn.begin().await?;
+    sqlx::query!(
+        r#"
+UPDATE deadline_exceptions
+SET deleted_at = now(),
+  revoked_by = $6
+WHERE user_id IS NOT DISTINCT FROM $1
+  AND course_instance_id IS NOT DISTINCT FROM $2
+  AND exercise_id IS NOT DISTINCT FROM $3
+  AND chapter_id IS NOT DISTINCT FROM $4
+  AND exam_id IS NOT DISTINCT FROM $5
+  AND deleted_at IS NULL
+        "#,
+        user_id,
+        course_instance_id,
+        exercise_id,
+        chapter_id,
+        exam_id,
+        granted_by,
+    )
+    .execute(&mut *tx)
+    .await?;
+    let row = sqlx::query_as!(
+        DeadlineException,
+        r#"
+INSERT INTO deadline_exceptions (
+    course_id,
+    user_id,
+    course_instance_id,
+    exercise_id,
+    chapter_id,
+    exam_id,
+    deadline,
+    reason,
+    granted_by
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+RETURNING *
+        "#,
+        course_id,
+        user_id,
+        course_instance_id,
+        exercise_id,
+        chapter_id,
+        exam_id,
+        new_exception.deadline,
+        new_exception.reason,
+        granted_by,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(row)
+}
+
+pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<DeadlineException> {
+    let row = sqlx::query_as!(
+        DeadlineException,
+        r#"
+SELECT *
+FROM deadline_exceptions
+WHERE id = $1
+        "#,
+        id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(row)
+}
+
+/// Revokes a live exception, after which the shared deadline applies again.
+///
+/// `false` means there was no live exception with that id.
+pub async fn revoke(conn: &mut PgConnection, id: Uuid, revoked_by: Uuid) -> ModelResult<bool> {
+    let revoked = sqlx::query_scalar!(
+        r#"
+UPDATE deadline_exceptions
+SET deleted_at = now(),
+  revoked_by = $2
+WHERE id = $1
+  AND deleted_at IS NULL
+RETURNING id
+        "#,
+        id,
+        revoked_by,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(revoked.is_some())
+}
+
+/// Every exception ever granted on the course, revoked and replaced ones included, newest first.
+/// This is the audit trail: a live exception has `deleted_at` unset.
+pub async fn get_history_for_course(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+) -> ModelResult<Vec<DeadlineException>> {
+    let rows = sqlx::query_as!(
+        DeadlineException,
+        r#"
+SELECT *
+FROM deadline_exceptions
+WHERE course_id = $1
+ORDER BY created_at DESC,
+  id DESC
+        "#,
+        course_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+/// Every exception ever granted on the exam, revoked and replaced ones included, newest first.
+pub async fn get_history_for_exam(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+) -> ModelResult<Vec<DeadlineException>> {
+    let rows = sqlx::query_as!(
+        DeadlineException,
+        r#"
+SELECT *
+FROM deadline_exceptions
+WHERE exam_id = $1
+ORDER BY created_at DESC,
+  id DESC
+        "#,
+        exam_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(rows)
+}
+
+/// The deadline that applies to the given user for each of the given exercises, keyed by exercise
+/// id. Falls back to the exercise deadline and then the chapter deadline when no exception applies.
+/// Course instance exceptions are matched against the instance the user is currently on in the
+/// exercise's course.
+///
+/// Without a user only the shared deadlines are returned.
+pub async fn get_effective_exercise_deadlines(
+    conn: &mut PgConnection,
+    exercise_ids: &[Uuid],
+    user_id: Option<Uuid>,
+) -> ModelResult<HashMap<Uuid, Option<DateTime<Utc>>>> {
+    if exercise_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let rows = sqlx::query!(
+        r#"
+SELECT e.id,
+  COALESCE(
+    exercise_exception.deadline,
+    GREATEST(chapter_exception.deadline, e.deadline),
+    c.deadline
+  ) AS deadline
+FROM exercises e
+  LEFT JOIN chapters c ON c.id = e.chapter_id
+  LEFT JOIN user_course_settings ucs ON ucs.user_id = $2
+  AND ucs.current_course_id = e.course_id
+  AND ucs.deleted_at IS NULL
+  LEFT JOIN LATERAL (
+    SELECT de.deadline
+    FROM deadline_exceptions de
+    WHERE de.exercise_id = e.id
+      AND (
+        de.user_id = $2
+        OR de.course_instance_id = ucs.current_course_instance_id
+      )
+      AND de.deleted_at IS NULL
+    ORDER BY de.user_id IS NOT NULL DESC
+    LIMIT 1
+  ) exercise_exception ON TRUE
+  LEFT JOIN LATERAL (
+    SELECT de.deadline
+    FROM deadline_exceptions de
+    WHERE de.chapter_id = e.chapter_id
+      AND (
+        de.user_id = $2
+        OR de.course_instance_id = ucs.current_course_instance_id
+      )
+      AND de.deleted_at IS NULL
+    ORDER BY de.user_id IS NOT NULL DESC
+    LIMIT 1
+  ) chapter_exception ON TRUE
+WHERE e.id = ANY($1)
+        "#,
+        exercise_ids,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(rows.into_iter().map(|r| (r.id, r.deadline)).collect())
+}
+
+/// The deadline that applies to the given user for one exercise. See
+/// [`get_effective_exercise_deadlines`].
+pub async fn get_effective_exercise_deadline(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    user_id: Option<Uuid>,
+) -> ModelResult<Option<DateTime<Utc>>> {
+    let deadlines = get_effective_exercise_deadlines(conn, &[exercise.id], user_id).await?;
+    Ok(deadlines.get(&exercise.id).copied().flatten())
+}
+
+/// Replaces the deadlines of the exercises with the ones that apply to the user, so that listings
+/// show the same deadlines as the exercise itself. See [`get_effective_exercise_deadlines`].
+/// Without a user the deadlines are left as they are.
+pub async fn apply_effective_exercise_deadlines<'a>(
+    conn: &mut PgConnection,
+    exercises: impl IntoIterator<Item = &'a mut Exercise>,
+    user_id: Option<Uuid>,
+) -> ModelResult<()> {
+    let Some(user_id) = user_id else {
+        return Ok(());
+    };
+    let mut exercises = exercises.into_iter().collect::<Vec<_>>();
+    let exercise_ids = exercises.iter().map(|e| e.id).collect::<Vec<_>>();
+    let deadlines = get_effective_exercise_deadlines(conn, &exercise_ids, Some(user_id)).await?;
+    for exercise in exercises.iter_mut() {
+        exercise.deadline = deadlines.get(&exercise.id).copied().flatten();
+    }
+    Ok(())
+}
+
+/// The chapter deadlines the user has an exception for in the course, keyed by chapter id. A
+/// per-student exception beats a course instance exception.
+pub async fn get_chapter_deadlines_for_user(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<HashMap<Uuid, DateTime<Utc>>> {
+    let rows = sqlx::query!(
+        r#"
+SELECT DISTINCT ON (de.chapter_id) de.chapter_id AS "chapter_id!",
+  de.deadline
+FROM deadline_exceptions de
+  JOIN chapters c ON c.id = de.chapter_id
+  LEFT JOIN user_course_settings ucs ON ucs.user_id = $2
+  AND ucs.current_course_id = c.course_id
+  AND ucs.deleted_at IS NULL
+WHERE c.course_id = $1
+  AND (
+    de.user_id = $2
+    OR de.course_instance_id = ucs.current_course_instance_id
+  )
+  AND de.deleted_at IS NULL
+ORDER BY de.chapter_id,
+  de.user_id IS NOT NULL DESC
+        "#,
+        course_id,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|r| (r.chapter_id, r.deadline))
+        .collect())
+}
+
+/// The end time granted to the user for the exam, if they have a live exception for it. Callers
+/// use this in place of `exams.ends_at`.
+pub async fn get_exam_deadline_for_user(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<Option<DateTime<Utc>>> {
+    let deadline = sqlx::query_scalar!(
+        r#"
+SELECT deadline
+FROM deadline_exceptions
+WHERE exam_id = $1
+  AND user_id = $2
+  AND deleted_at IS NULL
+        "#,
+        exam_id,
+        user_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(deadline)
+}
+
+/// The exam deadline exceptions of the exam enrollments that have not ended, keyed by exam and user.
+pub async fn get_exam_deadlines_for_ongoing_exam_enrollments(
+    conn: &mut PgConnection,
+) -> ModelResult<HashMap<(Uuid, Uuid), DateTime<Utc>>> {
+    let res = sqlx::query!(
+        r#"
+SELECT de.exam_id AS "exam_id!",
+  de.user_id AS "user_id!",
+  de.deadline
+FROM deadline_exceptions de
+  JOIN exam_enrollments ee ON ee.exam_id = de.exam_id
+  AND ee.user_id = de.user_id
+WHERE ee.ended_at IS NULL
+  AND ee.deleted_at IS NULL
+  AND de.deleted_at IS NULL
+        "#,
+    )
+    .fetch_all(conn)
+    .await?
+    .into_iter()
+    .map(|row| ((row.exam_id, row.user_id), row.deadline))
+    .collect();
+    Ok(res)
+}
+
+/// Whether a submission made at `now` is past the deadline. See [`DEADLINE_MARGIN_SECONDS`].
+pub(crate) fn deadline_has_passed(deadline: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now + chrono::Duration::seconds(DEADLINE_MARGIN_SECONDS) >= deadline
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::{course_instance_enrollments::NewCourseInstanceEnrollment, test_helper::*};
+
+    async fn enroll(tx: &mut PgConnection, user_id: Uuid, course_id: Uuid, instance_id: Uuid) {
+        crate::course_instance_enrollments::insert_enrollment_and_set_as_current(
+            tx,
+            NewCourseInstanceEnrollment {
+                user_id,
+                course_id,
+                course_instance_id: instance_id,
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn a_submission_at_the_exact_deadline_is_late() {
+        let deadline = Utc::now();
+        assert!(deadline_has_passed(deadline, deadline));
+        assert!(!deadline_has_passed(
+            deadline,
+            deadline - Duration::seconds(10)
+        ));
+    }
+
+    /// The per-student exception is the more specific one, so it wins over the instance-wide one
+    /// regardless of which deadline is later.
+    #[tokio::test]
+    async fn user_exception_beats_instance_exception_which_beats_shared_deadline() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise);
+        enroll(tx.as_mut(), user, course, instance.id).await;
+        let exercise = crate::exercises::get_by_id(tx.as_mut(), exercise)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_effective_exercise_deadline(tx.as_mut(), &exercise, Some(user))
+                .await
+                .unwrap(),
+            None
+        );
+
+        let instance_deadline = Utc::now() + Duration::days(10);
+        grant(
+            tx.as_mut(),
+            Some(course),
+            &NewDeadlineException {
+                target: DeadlineExceptionTarget::Exercise(exercise.id),
+                scope: DeadlineExceptionScope::CourseInstance(instance.id),
+                deadline: instance_deadline,
+                reason: None,
+            },
+            user,
+        )
+        .await
+        .unwrap();
+        let found = get_effective_exercise_deadline(tx.as_mut(), &exercise, Some(user))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.timestamp(), instance_deadline.timestamp());
+
+        let user_deadline = Utc::now() + Duration::days(5);
+        grant(
+            tx.as_mut(),
+            Some(course),
+            &NewDeadlineException {
+                target: DeadlineExceptionTarget::Exercise(exercise.id),
+                scope: DeadlineExceptionScope::User(user),
+                deadline: user_deadline,
+                reason: Some("accommodation".to_string()),
+            },
+            user,
+        )
+        .await
+        .unwrap();
+        let found = get_effective_exercise_deadline(tx.as_mut(), &exercise, Some(user))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.timestamp(), user_deadline.timestamp());
+        assert_eq!(
+            get_effective_exercise_deadline(tx.as_mut(), &exercise, None)
+                .await
+                .unwrap(),
+            None,
+            "an anonymous viewer must only see the shared deadline"
+        );
+        tx.rollback().await;
+    }
+
+    /// Granting twice must leave one live row and keep the replaced one as history.
+    #[tokio::test]
+    async fn regranting_replaces_the_live_exception_and_keeps_history() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise);
+        let new_exception = |days| NewDeadlineException {
+            target: DeadlineExceptionTarget::Chapter(chapter),
+            scope: DeadlineExceptionScope::User(user),
+            deadline: Utc::now() + Duration::days(days),
+            reason: None,
+        };
+        let first = grant(tx.as_mut(), Some(course), &new_exception(1), user)
+            .await
+            .unwrap();
+        let second = grant(tx.as_mut(), Some(course), &new_exception(2), user)
+            .await
+            .unwrap();
+
+        let history = get_history_for_course(tx.as_mut(), course).await.unwrap();
+        assert_eq!(history.len(), 2);
+        let replaced = get_by_id(tx.as_mut(), first.id).await.unwrap();
+        assert!(replaced.deleted_at.is_some());
+        assert_eq!(replaced.revoked_by, Some(user));
+
+        assert!(revoke(tx.as_mut(), second.id, user).await.unwrap());
+        assert!(
+            !revoke(tx.as_mut(), second.id, user).await.unwrap(),
+            "revoking twice must be a no-op"
+        );
+        tx.rollback().await;
+    }
+
+    #[tokio::test]
+    async fn listings_show_the_deadlines_of_the_user() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise);
+        enroll(tx.as_mut(), user, course, instance.id).await;
+        let chapter_deadline = Utc::now() + Duration::days(7);
+        grant(
+            tx.as_mut(),
+            Some(course),
+            &NewDeadlineException {
+                target: DeadlineExceptionTarget::Chapter(chapter),
+                scope: DeadlineExceptionScope::CourseInstance(instance.id),
+                deadline: chapter_deadline,
+                reason: None,
+            },
+            user,
+        )
+        .await
+        .unwrap();
+        let chapter_deadlines = get_chapter_deadlines_for_user(tx.as_mut(), course, user)
+            .await
+            .unwrap();
+        assert_eq!(
+            chapter_deadlines[&chapter].timestamp(),
+            chapter_deadline.timestamp()
+        );
+        let overrides = crate::chapters::exercise_deadline_overrides_by_chapter_for_course(
+            tx.as_mut(),
+            course,
+            Some(user),
+        )
+        .await
+        .unwrap();
+        assert_eq!(overrides[&chapter].exercise_deadline_override_count, 0);
+
+        let exercise_deadline = Utc::now() + Duration::days(14);
+        grant(
+            tx.as_mut(),
+            Some(course),
+            &NewDeadlineException {
+                target: DeadlineExceptionTarget::Exercise(exercise),
+                scope: DeadlineExceptionScope::User(user),
+                deadline: exercise_deadline,
+                reason: None,
+            },
+            user,
+        )
+        .await
+        .unwrap();
+        let mut exercises = vec![
+            crate::exercises::get_by_id(tx.as_mut(), exercise)
+                .await
+                .unwrap(),
+        ];
+        apply_effective_exercise_deadlines(tx.as_mut(), &mut exercises, Some(user))
+            .await
+            .unwrap();
+        assert_eq!(
+            exercises[0].deadline.unwrap().timestamp(),
+            exercise_deadline.timestamp()
+        );
+        let overrides = crate::chapters::exercise_deadline_overrides_by_chapter_for_course(
+            tx.as_mut(),
+            course,
+            Some(user),
+        )
+        .await
+        .unwrap();
+        assert_eq!(overrides[&chapter].exercise_deadline_override_count, 1);
+        assert_eq!(
+            overrides[&chapter]
+                .earliest_exercise_deadline_override
+                .unwrap()
+                .timestamp(),
+            chapter_deadline.timestamp()
+        );
+        tx.rollback().await;
+    }
+
+    #[tokio::test]
+    async fn chapter_exception_does_not_shorten_a_later_exercise_deadline() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise);
+        let exercise_deadline = Utc::now() + Duration::days(20);
+        sqlx::query!(
+            "UPDATE exercises SET deadline = $2 WHERE id = $1",
+            exercise,
+            exercise_deadline
+        )
+        .execute(tx.as_mut())
+        .await
+        .unwrap();
+        grant(
+            tx.as_mut(),
+            Some(course),
+            &NewDeadlineException {
+                target: DeadlineExceptionTarget::Chapter(chapter),
+                scope: DeadlineExceptionScope::User(user),
+                deadline: Utc::now() + Duration::days(3),
+                reason: None,
+            },
+            user,
+        )
+        .await
+        .unwrap();
+        let exercise = crate::exercises::get_by_id(tx.as_mut(), exercise)
+            .await
+            .unwrap();
+        let found = get_effective_exercise_deadline(tx.as_mut(), &exercise, Some(user))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.timestamp(), exercise_deadline.timestamp());
+        tx.rollback().await;
+    }
+}
@@ -14,6 +14,7 @@ pub enum EmailTemplateType {
     CreditRegistrationActionNeeded,
     CreditRegistrationRegistered,
     CreditRegistrationStudentNumberLinked,
+    PeerReviewDisputeResolved,
 }
 
 impl EmailTemplateType {
@@ -26,6 +27,7 @@ impl EmailTemplateType {
                 | Self::CreditRegistrationActionNeeded
                 | Self::CreditRegistrationRegistered
                 | Self::CreditRegistrationStudentNumberLinked
+                | Self::PeerReviewDisputeResolved
         )
     }
 }
@@ -19,6 +19,7 @@ RETURNING *
 }
 
 /// Get ids for automatically graded exams that have ended but haven't yet been added to the table for processed ones.
+/// Students with a personal window or a deadline exception keep an exam open until it has ended for them too.
 pub async fn get_unprocessed_ended_exams_by_timestamp(
     conn: &mut PgConnection,
     timestamp: DateTime<Utc>,
@@ -28,7 +29,21 @@ pub async fn get_unprocessed_ended_exams_by_timestamp(
 SELECT exams.id
 FROM exams
   LEFT JOIN ended_processed_exams ON (ended_processed_exams.exam_id = exams.id)
-WHERE exams.ends_at <= $1
+WHERE GREATEST(
+    exams.ends_at,
+    (
+      SELECT MAX(exam_accommodations.ends_at)
+      FROM exam_accommodations
+      WHERE exam_accommodations.exam_id = exams.id
+        AND exam_accommodations.deleted_at IS NULL
+    ),
+    (
+      SELECT MAX(deadline_exceptions.deadline)
+      FROM deadline_exceptions
+      WHERE deadline_exceptions.exam_id = exams.id
+        AND deadline_exceptions.deleted_at IS NULL
+    )
+  ) <= $1
   AND exams.grade_manually IS false
   AND ended_processed_exams.created_at IS NULL
   AND exams.deleted_at IS NULL
@@ -41,3 +56,68 @@ WHERE exams.ends_at <= $1
     .await?;
     Ok(res)
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use super::*;
+    use crate::{
+        deadline_exceptions::{self, DeadlineExceptionScope, DeadlineExceptionTarget},
+        exams::{self, NewExam},
+        test_helper::*,
+    };
+
+    #[tokio::test]
+    async fn deadline_exception_keeps_the_exam_unprocessed() {
+        insert_data!(:tx, :user, :org);
+        let ends_at = Utc::now() - Duration::hours(1);
+        let exam = exams::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &NewExam {
+                name: "Exam".to_string(),
+                starts_at: Some(ends_at - Duration::hours(2)),
+                ends_at: Some(ends_at),
+                time_minutes: 60,
+                organization_id: org,
+                minimum_points_treshold: 0,
+                grade_manually: false,
+            },
+        )
+        .await
+        .unwrap();
+        let exception = deadline_exceptions::grant(
+            tx.as_mut(),
+            None,
+            &deadline_exceptions::NewDeadlineException {
+                target: DeadlineExceptionTarget::Exam(exam),
+                scope: DeadlineExceptionScope::User(user),
+                deadline: ends_at + Duration::hours(2),
+                reason: None,
+            },
+            user,
+        )
+        .await
+        .unwrap();
+
+        let unprocessed = get_unprocessed_ended_exams_by_timestamp(tx.as_mut(), Utc::now())
+            .await
+            .unwrap();
+        assert!(!unprocessed.contains(&exam));
+        let unprocessed =
+            get_unprocessed_ended_exams_by_timestamp(tx.as_mut(), Utc::now() + Duration::hours(2))
+                .await
+                .unwrap();
+        assert!(unprocessed.contains(&exam));
+
+        deadline_exceptions::revoke(tx.as_mut(), exception.id, user)
+            .await
+            .unwrap();
+        let unprocessed = get_unprocessed_ended_exams_by_timestamp(tx.as_mut(), Utc::now())
+            .await
+            .unwrap();
+        assert!(unprocessed.contains(&exam));
+        tx.rollback().await;
+    }
+}
@@ -0,0 +1,388 @@
+//! Individual exam arrangements: extra time for students with accommodations, and personal windows
+//! for students who cannot take the exam at the shared time. [`get_exam_timing_for_user`] combines
+//! them with the exam's own times, and everything that checks a student's exam time should use it.
+
+use std::collections::HashMap;
+
+use chrono::Duration;
+use utoipa::ToSchema;
+
+use crate::{exams::Exam, prelude::*};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ExamAccommodation {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub exam_id: Uuid,
+    pub user_id: Uuid,
+    pub time_multiplier: f32,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+    pub granted_by: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewExamAccommodation {
+    pub user_id: Uuid,
+    /// E.g. 1.5 for 50% extra time. Between 1 and 10.
+    pub time_multiplier: f32,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+}
+
+/// The times that apply to one student in an exam.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ExamTiming {
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// The student's time limit, after the time multiplier.
+    pub time_minutes: i32,
+}
+
+impl ExamTiming {
+    /// An exam deadline exception replaces the end time of the window, see
+    /// [`crate::deadline_exceptions::get_exam_deadline_for_user`].
+    pub fn new(
+        exam_starts_at: Option<DateTime<Utc>>,
+        exam_ends_at: Option<DateTime<Utc>>,
+        exam_time_minutes: i32,
+        accommodation: Option<&ExamAccommodation>,
+        deadline_exception: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            starts_at: accommodation.and_then(|a| a.starts_at).or(exam_starts_at),
+            ends_at: deadline_exception
+                .or(accommodation.and_then(|a| a.ends_at))
+                .or(exam_ends_at),
+            time_minutes: accommodation
+                .map(|a| multiply_time_minutes(exam_time_minutes, a.time_multiplier))
+                .unwrap_or(exam_time_minutes),
+        }
+    }
+
+    /// Like [`Exam::started_at_or`] but for the student's window.
+    pub fn started_at_or(&self, timestamp: DateTime<Utc>, default: bool) -> bool {
+        match self.starts_at {
+            Some(starts_at) => starts_at <= timestamp,
+            None => default,
+        }
+    }
+
+    /// Like [`Exam::ended_at_or`] but for the student's window.
+    pub fn ended_at_or(&self, timestamp: DateTime<Utc>, default: bool) -> bool {
+        match self.ends_at {
+            Some(ends_at) => ends_at < timestamp,
+            None => default,
+        }
+    }
+
+    /// When the student's time runs out: the end of the time limit counted from `started_at`, or
+    /// the end of the window if that comes first.
+    pub fn student_time_ends_at(&self, started_at: DateTime<Utc>) -> DateTime<Utc> {
+        let time_limit_ends_at = started_at + Duration::minutes(self.time_minutes.into());
+        match self.ends_at {
+            Some(ends_at) => time_limit_ends_at.min(ends_at),
+            None => time_limit_ends_at,
+        }
+    }
+}
+
+/// The time limit after the multiplier, rounded up to whole minutes.
+pub fn multiply_time_minutes(time_minutes: i32, time_multiplier: f32) -> i32 {
+    (time_minutes as f64 * time_multiplier as f64).ceil() as i32
+}
+
+pub async fn get_exam_timing_for_user(
+    conn: &mut PgConnection,
+    exam: &Exam,
+    user_id: Uuid,
+) -> ModelResult<ExamTiming> {
+    let accommodation = get_by_exam_and_user_id(conn, exam.id, user_id).await?;
+    let deadline_exception =
+        crate::deadline_exceptions::get_exam_deadline_for_user(conn, exam.id, user_id).await?;
+    Ok(ExamTiming::new(
+        exam.starts_at,
+        exam.ends_at,
+        exam.time_minutes,
+        accommodation.as_ref(),
+        deadline_exception,
+    ))
+}
+
+/// Grants an accommodation, replacing the student's current one on the exam.
+pub async fn upsert(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    new_accommodation: &NewExamAccommodation,
+    granted_by: Uuid,
+) -> ModelResult<ExamAccommodation> {
+    validate(new_accommodation)?;
+    let res = sqlx::query_as!(
+        ExamAccommodation,
+        r#"
+INSERT INTO exam_accommodations (
+    exam_id,
+    user_id,
+    time_multiplier,
+    starts_at,
+    ends_at,
+    reason,
+    granted_by
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (exam_id, user_id)
+WHERE deleted_at IS NULL DO
+UPDATE
+SET time_multiplier = excluded.time_multiplier,
+  starts_at = excluded.starts_at,
+  ends_at = excluded.ends_at,
+  reason = excluded.reason,
+  granted_by = excluded.granted_by
+RETURNING *
+        "#,
+        exam_id,
+        new_accommodation.user_id,
+        new_accommodation.time_multiplier,
+        new_accommodation.starts_at,
+        new_accommodation.ends_at,
+        new_accommodation.reason,
+        granted_by,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Grants all the accommodations or none of them.
+pub async fn upsert_many(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    new_accommodations: &[NewExamAccommodation],
+    granted_by: Uuid,
+) -> ModelResult<Vec<ExamAccommodation>> {
+    let mut tx = conn.begin().await?;
+    let mut res = Vec::with_capacity(new_accommodations.len());
+    for new_accommodation in new_accommodations {
+        res.push(upsert(&mut tx, exam_id, new_accommodation, granted_by).await?);
+    }
+    tx.commit().await?;
+    Ok(res)
+}
+
+pub fn validate(new_accommodation: &NewExamAccommodation) -> ModelResult<()> {
+    if !(1.0..=10.0).contains(&new_accommodation.time_multiplier) {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The time multiplier must be between 1 and 10.".to_string()
+        ));
+    }
+    if let (Some(starts_at), Some(ends_at)) =
+        (new_accommodation.starts_at, new_accommodation.ends_at)
+        && starts_at >= ends_at
+    {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The window must start before it ends.".to_string()
+        ));
+    }
+    Ok(())
+}
+
+pub async fn get_by_exam_id(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+) -> ModelResult<Vec<ExamAccommodation>> {
+    let res = sqlx::query_as!(
+        ExamAccommodation,
+        r#"
+SELECT *
+FROM exam_accommodations
+WHERE exam_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at
+        "#,
+        exam_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_exam_and_user_id(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<Option<ExamAccommodation>> {
+    let res = sqlx::query_as!(
+        ExamAccommodation,
+        r#"
+SELECT *
+FROM exam_accommodations
+WHERE exam_id = $1
+  AND user_id = $2
+  AND deleted_at IS NULL
+        "#,
+        exam_id,
+        user_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The accommodations of the students whose exams are ongoing, keyed by exam id and user id.
+pub async fn get_for_ongoing_exam_enrollments(
+    conn: &mut PgConnection,
+) -> ModelResult<HashMap<(Uuid, Uuid), ExamAccommodation>> {
+    let res = sqlx::query_as!(
+        ExamAccommodation,
+        r#"
+SELECT a.*
+FROM exam_accommodations a
+  JOIN exam_enrollments ee ON ee.exam_id = a.exam_id
+  AND ee.user_id = a.user_id
+WHERE ee.ended_at IS NULL
+  AND ee.deleted_at IS NULL
+  AND a.deleted_at IS NULL
+        "#,
+    )
+    .fetch_all(conn)
+    .await?
+    .into_iter()
+    .map(|a| ((a.exam_id, a.user_id), a))
+    .collect();
+    Ok(res)
+}
+
+pub async fn delete(conn: &mut PgConnection, exam_id: Uuid, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+UPDATE exam_accommodations
+SET deleted_at = now()
+WHERE id = $1
+  AND exam_id = $2
+  AND deleted_at IS NULL
+RETURNING id
+        "#,
+        id,
+        exam_id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::test_helper::*;
+
+    fn accommodation(
+        time_multiplier: f32,
+        starts_at: Option<DateTime<Utc>>,
+        ends_at: Option<DateTime<Utc>>,
+    ) -> ExamAccommodation {
+        ExamAccommodation {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            exam_id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            time_multiplier,
+            starts_at,
+            ends_at,
+            reason: None,
+            granted_by: Uuid::nil(),
+        }
+    }
+
+    #[test]
+    fn timing_combines_the_exam_accommodation_and_deadline_exception() {
+        let at = |hour| Utc.with_ymd_and_hms(2026, 5, 1, hour, 0, 0).unwrap();
+
+        let plain = ExamTiming::new(Some(at(9)), Some(at(17)), 45, None, None);
+        assert_eq!(plain.time_minutes, 45);
+        assert_eq!(
+            plain.student_time_ends_at(at(10)),
+            at(10) + Duration::minutes(45)
+        );
+        // The window closes before the time limit runs out.
+        assert_eq!(
+            plain.student_time_ends_at(at(16) + Duration::minutes(30)),
+            at(17)
+        );
+
+        let extra_time = accommodation(1.5, None, None);
+        let timing = ExamTiming::new(Some(at(9)), Some(at(17)), 45, Some(&extra_time), None);
+        assert_eq!(timing.time_minutes, 68);
+        assert_eq!(timing.starts_at, Some(at(9)));
+
+        let own_window = accommodation(1.0, Some(at(20)), Some(at(23)));
+        let timing = ExamTiming::new(Some(at(9)), Some(at(17)), 45, Some(&own_window), None);
+        assert_eq!(timing.starts_at, Some(at(20)));
+        assert_eq!(timing.ends_at, Some(at(23)));
+        assert!(!timing.started_at_or(at(18), false));
+        assert!(!timing.ended_at_or(at(22), true));
+
+        let timing = ExamTiming::new(
+            Some(at(9)),
+            Some(at(17)),
+            45,
+            Some(&own_window),
+            Some(at(22)),
+        );
+        assert_eq!(timing.ends_at, Some(at(22)));
+    }
+
+    #[tokio::test]
+    async fn upsert_replaces_the_accommodation() {
+        insert_data!(:tx, :user, :org);
+        let exam = crate::exams::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &crate::exams::NewExam {
+                name: "Exam".to_string(),
+                starts_at: None,
+                ends_at: None,
+                time_minutes: 60,
+                organization_id: org,
+                minimum_points_treshold: 0,
+                grade_manually: false,
+            },
+        )
+        .await
+        .unwrap();
+        let mut new_accommodation = NewExamAccommodation {
+            user_id: user,
+            time_multiplier: 1.5,
+            starts_at: None,
+            ends_at: None,
+            reason: None,
+        };
+        upsert(tx.as_mut(), exam, &new_accommodation, user)
+            .await
+            .unwrap();
+        new_accommodation.time_multiplier = 2.0;
+        let replaced = upsert(tx.as_mut(), exam, &new_accommodation, user)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_by_exam_id(tx.as_mut(), exam).await.unwrap(),
+            vec![replaced]
+        );
+
+        new_accommodation.time_multiplier = 0.5;
+        assert!(
+            upsert(tx.as_mut(), exam, &new_accommodation, user)
+                .await
+                .is_err()
+        );
+        tx.rollback().await;
+    }
+}
@@ -0,0 +1,541 @@
+//! A log of what happens during a student's exam, and the anomaly report built from it. The report
+//! only points teachers to enrollments worth a closer look; nothing in it affects grading.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet, HashMap},
+};
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+/// Focus losses (including the page being hidden) during one exam before they are flagged.
+pub const FREQUENT_FOCUS_LOSS_THRESHOLD: usize = 5;
+/// Total time the exam page may be hidden or out of focus before it is flagged.
+pub const LONG_TIME_AWAY_THRESHOLD_SECONDS: i64 = 120;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "exam_enrollment_event_type", rename_all = "kebab-case")]
+pub enum ExamEnrollmentEventType {
+    Started,
+    Submission,
+    Ended,
+    VisibilityHidden,
+    VisibilityVisible,
+    FocusLost,
+    FocusRegained,
+    /// Sent periodically by an open exam page so that parallel sessions can be detected.
+    Heartbeat,
+}
+
+impl ExamEnrollmentEventType {
+    /// The rest of the events are recorded by the server so that they cannot be forged.
+    pub fn can_be_reported_by_client(self) -> bool {
+        matches!(
+            self,
+            Self::VisibilityHidden
+                | Self::VisibilityVisible
+                | Self::FocusLost
+                | Self::FocusRegained
+                | Self::Heartbeat
+        )
+    }
+
+    fn is_leaving(self) -> bool {
+        matches!(self, Self::VisibilityHidden | Self::FocusLost)
+    }
+
+    fn is_returning(self) -> bool {
+        matches!(self, Self::VisibilityVisible | Self::FocusRegained)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ExamEnrollmentEvent {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub exam_id: Uuid,
+    pub user_id: Uuid,
+    pub event_type: ExamEnrollmentEventType,
+    pub client_session_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub country_code: Option<String>,
+    pub exercise_slide_submission_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewExamEnrollmentEvent {
+    pub exam_id: Uuid,
+    pub user_id: Uuid,
+    pub event_type: ExamEnrollmentEventType,
+    pub client_session_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub country_code: Option<String>,
+    pub exercise_slide_submission_id: Option<Uuid>,
+}
+
+/// An event reported by the exam page.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ClientExamEnrollmentEvent {
+    pub event_type: ExamEnrollmentEventType,
+    pub client_session_id: Uuid,
+}
+
+/// A suspicious pattern in the events of one enrollment.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+#[serde(tag = "tag")]
+pub enum ExamAnomaly {
+    /// The student left the exam page often.
+    FrequentFocusLoss { times: i32 },
+    /// The exam page was hidden or out of focus for a long time in total.
+    LongTimeAway { seconds: i64 },
+    /// The requests came from more than one country.
+    CountryChanged { country_codes: Vec<String> },
+    /// The requests came from more than one IP address. Common on mobile connections, so weaker
+    /// evidence than a country change.
+    IpAddressChanged { ip_addresses: Vec<String> },
+    /// The exam was open in several browsers or tabs at the same time.
+    ParallelSessions { client_session_ids: Vec<Uuid> },
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ExamEnrollmentAnomalies {
+    pub user_id: Uuid,
+    pub first_event_at: DateTime<Utc>,
+    pub last_event_at: DateTime<Utc>,
+    pub submissions: i32,
+    pub anomalies: Vec<ExamAnomaly>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ExamAnomalyReport {
+    pub exam_id: Uuid,
+    pub enrollments_with_events: i32,
+    /// Only the enrollments with at least one anomaly, the most anomalies first.
+    pub flagged_enrollments: Vec<ExamEnrollmentAnomalies>,
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    new_event: &NewExamEnrollmentEvent,
+) -> ModelResult<ExamEnrollmentEvent> {
+    let res = sqlx::query_as!(
+        ExamEnrollmentEvent,
+        r#"
+INSERT INTO exam_enrollment_events (
+    exam_id,
+    user_id,
+    event_type,
+    client_session_id,
+    ip_address,
+    country_code,
+    exercise_slide_submission_id
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING *
+        "#,
+        new_event.exam_id,
+        new_event.user_id,
+        new_event.event_type as ExamEnrollmentEventType,
+        new_event.client_session_id,
+        new_event.ip_address,
+        new_event.country_code,
+        new_event.exercise_slide_submission_id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// All events of the exam ordered by student and time.
+pub async fn get_by_exam_id(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+) -> ModelResult<Vec<ExamEnrollmentEvent>> {
+    let res = sqlx::query_as!(
+        ExamEnrollmentEvent,
+        r#"
+SELECT *
+FROM exam_enrollment_events
+WHERE exam_id = $1
+  AND deleted_at IS NULL
+ORDER BY user_id,
+  created_at
+        "#,
+        exam_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_exam_and_user_id(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<Vec<ExamEnrollmentEvent>> {
+    let res = sqlx::query_as!(
+        ExamEnrollmentEvent,
+        r#"
+SELECT *
+FROM exam_enrollment_events
+WHERE exam_id = $1
+  AND user_id = $2
+  AND deleted_at IS NULL
+ORDER BY created_at
+        "#,
+        exam_id,
+        user_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_anomaly_report(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+) -> ModelResult<ExamAnomalyReport> {
+    let events = get_by_exam_id(conn, exam_id).await?;
+    let mut events_by_user: BTreeMap<Uuid, Vec<ExamEnrollmentEvent>> = BTreeMap::new();
+    for event in events {
+        events_by_user.entry(event.user_id).or_default().push(event);
+    }
+    let enrollments_with_events = events_by_user.len() as i32;
+    let mut flagged_enrollments: Vec<ExamEnrollmentAnomalies> = events_by_user
+        .into_iter()
+        .filter_map(|(user_id, events)| {
+            let anomalies = detect_anomalies(&events);
+            if anomalies.is_empty() {
+                return None;
+            }
+            Some(ExamEnrollmentAnomalies {
+                user_id,
+                first_event_at: events.first()?.created_at,
+                last_event_at: events.last()?.created_at,
+                submissions: events
+                    .iter()
+                    .filter(|e| e.event_type == ExamEnrollmentEventType::Submission)
+                    .count() as i32,
+                anomalies,
+            })
+        })
+        .collect();
+    flagged_enrollments.sort_by_key(|e| Reverse(e.anomalies.len()));
+    Ok(ExamAnomalyReport {
+        exam_id,
+        enrollments_with_events,
+        flagged_enrollments,
+    })
+}
+
+/// Looks for suspicious patterns in the events of one enrollment. The events must be ordered by
+/// time.
+pub fn detect_anomalies(events: &[ExamEnrollmentEvent]) -> Vec<ExamAnomaly> {
+    let mut anomalies = vec![];
+
+    // Leaving and coming back are tracked per session, so that another open tab does not end an
+    // absence.
+    let mut left_at: HashMap<Uuid, DateTime<Utc>> = HashMap::new();
+    let mut focus_losses = 0;
+    let mut seconds_away = 0;
+    for event in events {
+        let Some(client_session_id) = event.client_session_id else {
+            continue;
+        };
+        if event.event_type.is_leaving() {
+            if !left_at.contains_key(&client_session_id) {
+                focus_losses += 1;
+                left_at.insert(client_session_id, event.created_at);
+            }
+        } else if event.event_type.is_returning()
+            && let Some(left) = left_at.remove(&client_session_id)
+        {
+            seconds_away += (event.created_at - left).num_seconds();
+        }
+    }
+    if focus_losses >= FREQUENT_FOCUS_LOSS_THRESHOLD {
+        anomalies.push(ExamAnomaly::FrequentFocusLoss {
+            times: focus_losses as i32,
+        });
+    }
+    if seconds_away >= LONG_TIME_AWAY_THRESHOLD_SECONDS {
+        anomalies.push(ExamAnomaly::LongTimeAway {
+            seconds: seconds_away,
+        });
+    }
+
+    let country_codes: BTreeSet<&str> = events
+        .iter()
+        .filter_map(|e| e.country_code.as_deref())
+        .collect();
+    if country_codes.len() > 1 {
+        anomalies.push(ExamAnomaly::CountryChanged {
+            country_codes: country_codes.into_iter().map(str::to_string).collect(),
+        });
+    }
+    let ip_addresses: BTreeSet<&str> = events
+        .iter()
+        .filter_map(|e| e.ip_address.as_deref())
+        .collect();
+    if ip_addresses.len() > 1 {
+        anomalies.push(ExamAnomaly::IpAddressChanged {
+            ip_addresses: ip_addresses.into_iter().map(str::to_string).collect(),
+        });
+    }
+
+    // A session is active from its first event to its last one. Overlapping sessions mean the exam
+    // was open in several places at once.
+    let mut session_spans: BTreeMap<Uuid, (DateTime<Utc>, DateTime<Utc>)> = BTreeMap::new();
+    for event in events {
+        if let Some(client_session_id) = event.client_session_id {
+            session_spans
+                .entry(client_session_id)
+                .and_modify(|(_, last)| *last = event.created_at)
+                .or_insert((event.created_at, event.created_at));
+        }
+    }
+    let parallel_sessions: BTreeSet<Uuid> = session_spans
+        .iter()
+        .flat_map(|(a_id, (a_first, a_last))| {
+            session_spans
+                .iter()
+                .filter(move |(b_id, (b_first, b_last))| {
+                    a_id != *b_id && a_first <= b_last && b_first <= a_last
+                })
+                .map(move |_| *a_id)
+        })
+        .collect();
+    if !parallel_sessions.is_empty() {
+        anomalies.push(ExamAnomaly::ParallelSessions {
+            client_session_ids: parallel_sessions.into_iter().collect(),
+        });
+    }
+
+    anomalies
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::test_helper::*;
+
+    fn event(
+        seconds: i64,
+        event_type: ExamEnrollmentEventType,
+        client_session_id: Option<Uuid>,
+        country_code: &str,
+        ip_address: &str,
+    ) -> ExamEnrollmentEvent {
+        let created_at =
+            Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap() + Duration::seconds(seconds);
+        ExamEnrollmentEvent {
+            id: Uuid::new_v4(),
+            created_at,
+            updated_at: created_at,
+            deleted_at: None,
+            exam_id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            event_type,
+            client_session_id,
+            ip_address: Some(ip_address.to_string()),
+            country_code: Some(country_code.to_string()),
+            exercise_slide_submission_id: None,
+        }
+    }
+
+    #[test]
+    fn ordinary_exam_has_no_anomalies() {
+        let session = Some(Uuid::new_v4());
+        let events = vec![
+            event(0, ExamEnrollmentEventType::Started, None, "fi", "10.0.0.1"),
+            event(
+                1,
+                ExamEnrollmentEventType::Heartbeat,
+                session,
+                "fi",
+                "10.0.0.1",
+            ),
+            event(
+                10,
+                ExamEnrollmentEventType::FocusLost,
+                session,
+                "fi",
+                "10.0.0.1",
+            ),
+            event(
+                20,
+                ExamEnrollmentEventType::FocusRegained,
+                session,
+                "fi",
+                "10.0.0.1",
+            ),
+            event(600, ExamEnrollmentEventType::Ended, None, "fi", "10.0.0.1"),
+        ];
+        assert_eq!(detect_anomalies(&events), vec![]);
+    }
+
+    #[test]
+    fn detects_suspicious_patterns() {
+        let first_session = Uuid::new_v4();
+        let second_session = Uuid::new_v4();
+        let mut events = vec![event(
+            0,
+            ExamEnrollmentEventType::Heartbeat,
+            Some(first_session),
+            "fi",
+            "10.0.0.1",
+        )];
+        for i in 0..5 {
+            events.push(event(
+                100 * i + 10,
+                ExamEnrollmentEventType::VisibilityHidden,
+                Some(first_session),
+                "fi",
+                "10.0.0.1",
+            ));
+            // Another hide while already away does not count as a new focus loss.
+            events.push(event(
+                100 * i + 20,
+                ExamEnrollmentEventType::FocusLost,
+                Some(first_session),
+                "fi",
+                "10.0.0.1",
+            ));
+            events.push(event(
+                100 * i + 40,
+                ExamEnrollmentEventType::VisibilityVisible,
+                Some(first_session),
+                "fi",
+                "10.0.0.1",
+            ));
+        }
+        events.push(event(
+            200,
+            ExamEnrollmentEventType::Heartbeat,
+            Some(second_session),
+            "se",
+            "10.0.0.2",
+        ));
+        events.sort_by_key(|e| e.created_at);
+
+        let anomalies = detect_anomalies(&events);
+        let mut parallel_sessions = vec![first_session, second_session];
+        parallel_sessions.sort();
+        assert_eq!(
+            anomalies,
+            vec![
+                ExamAnomaly::FrequentFocusLoss { times: 5 },
+                ExamAnomaly::LongTimeAway { seconds: 150 },
+                ExamAnomaly::CountryChanged {
+                    country_codes: vec!["fi".to_string(), "se".to_string()]
+                },
+                ExamAnomaly::IpAddressChanged {
+                    ip_addresses: vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()]
+                },
+                ExamAnomaly::ParallelSessions {
+                    client_session_ids: parallel_sessions
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn sessions_one_after_another_are_not_parallel() {
+        let first_session = Some(Uuid::new_v4());
+        let second_session = Some(Uuid::new_v4());
+        let events = vec![
+            event(
+                0,
+                ExamEnrollmentEventType::Heartbeat,
+                first_session,
+                "fi",
+                "10.0.0.1",
+            ),
+            event(
+                60,
+                ExamEnrollmentEventType::Heartbeat,
+                first_session,
+                "fi",
+                "10.0.0.1",
+            ),
+            event(
+                120,
+                ExamEnrollmentEventType::Heartbeat,
+                second_session,
+                "fi",
+                "10.0.0.1",
+            ),
+            event(
+                180,
+                ExamEnrollmentEventType::Heartbeat,
+                second_session,
+                "fi",
+                "10.0.0.1",
+            ),
+        ];
+        assert_eq!(detect_anomalies(&events), vec![]);
+    }
+
+    #[tokio::test]
+    async fn report_contains_only_flagged_enrollments() {
+        insert_data!(:tx, :user, :org);
+        let exam = crate::exams::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &crate::exams::NewExam {
+                name: "Exam".to_string(),
+                starts_at: None,
+                ends_at: None,
+                time_minutes: 60,
+                organization_id: org,
+                minimum_points_treshold: 0,
+                grade_manually: false,
+            },
+        )
+        .await
+        .unwrap();
+        let other_user = crate::users::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &format!("{}@example.com", Uuid::new_v4()),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        for (user_id, country_code) in [(user, "fi"), (user, "se"), (other_user, "fi")] {
+            insert(
+                tx.as_mut(),
+                &NewExamEnrollmentEvent {
+                    exam_id: exam,
+                    user_id,
+                    event_type: ExamEnrollmentEventType::Heartbeat,
+                    client_session_id: None,
+                    ip_address: None,
+                    country_code: Some(country_code.to_string()),
+                    exercise_slide_submission_id: None,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let report = get_anomaly_report(tx.as_mut(), exam).await.unwrap();
+        assert_eq!(report.enrollments_with_events, 2);
+        assert_eq!(report.flagged_enrollments.len(), 1);
+        assert_eq!(report.flagged_enrollments[0].user_id, user);
+        assert_eq!(
+            report.flagged_enrollments[0].anomalies,
+            vec![ExamAnomaly::CountryChanged {
+                country_codes: vec!["fi".to_string(), "se".to_string()]
+            }]
+        );
+        tx.rollback().await;
+    }
+}
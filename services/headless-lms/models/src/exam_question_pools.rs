@@ -0,0 +1,536 @@
+//! Question banks for exams. The exercises of an exam can be tagged into pools, and each enrolled
+//! student gets `draw_count` exercises from every pool. The draw is made with the seed stored on the
+//! student's [`crate::exams::ExamEnrollment`], so it can always be reproduced. Exercises that are not
+//! in any pool are given to everyone.
+
+use std::collections::{HashMap, HashSet};
+
+use utoipa::ToSchema;
+
+use crate::{exercises, prelude::*};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct ExamQuestionPool {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub exam_id: Uuid,
+    pub name: String,
+    pub draw_count: i32,
+    pub exercise_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewExamQuestionPool {
+    pub name: String,
+    pub draw_count: i32,
+    pub exercise_ids: Vec<Uuid>,
+}
+
+/// The exercises of an exam and its pools, everything needed to draw the exercises of a student.
+#[derive(Debug, Clone)]
+pub struct ExamQuestionBank {
+    pub score_maximums_by_exercise_id: HashMap<Uuid, i32>,
+    pub pools: Vec<ExamQuestionPool>,
+}
+
+impl ExamQuestionBank {
+    /// The exercises a student with the seed gets. Without a seed, e.g. for a student who never
+    /// enrolled, all the exercises of the exam are returned.
+    pub fn exercise_ids_for_seed(&self, seed: Option<i64>) -> HashSet<Uuid> {
+        let Some(seed) = seed else {
+            return self.score_maximums_by_exercise_id.keys().copied().collect();
+        };
+        let pooled: HashSet<Uuid> = self
+            .pools
+            .iter()
+            .flat_map(|p| p.exercise_ids.iter().copied())
+            .collect();
+        let mut res: HashSet<Uuid> = self
+            .score_maximums_by_exercise_id
+            .keys()
+            .filter(|id| !pooled.contains(id))
+            .copied()
+            .collect();
+        for pool in &self.pools {
+            // Exercises that have since been deleted from the exam are not drawn.
+            let mut candidates: Vec<Uuid> = pool
+                .exercise_ids
+                .iter()
+                .filter(|id| self.score_maximums_by_exercise_id.contains_key(id))
+                .copied()
+                .collect();
+            candidates.sort_by_key(|id| (draw_key(seed, *id), *id));
+            res.extend(candidates.into_iter().take(pool.draw_count.max(0) as usize));
+        }
+        res
+    }
+
+    /// The maximum points of a student with the seed, the sum of the maximum points of their
+    /// exercises.
+    pub fn maximum_points_for_seed(&self, seed: Option<i64>) -> i32 {
+        self.exercise_ids_for_seed(seed)
+            .iter()
+            .filter_map(|id| self.score_maximums_by_exercise_id.get(id))
+            .sum()
+    }
+}
+
+/// Picks the exercise slide a student with the seed gets, so that exercises with several slides are
+/// randomized per enrollment as well.
+pub fn pick_exercise_slide_id(seed: i64, exercise_slide_ids: &[Uuid]) -> Option<Uuid> {
+    exercise_slide_ids
+        .iter()
+        .min_by_key(|id| (draw_key(seed, **id), **id))
+        .copied()
+}
+
+/// Removes the exercise blocks of the exercises the student did not get from exam page content.
+pub fn remove_undrawn_exercise_blocks(
+    content: &mut serde_json::Value,
+    exercise_ids: &HashSet<Uuid>,
+) {
+    if let serde_json::Value::Array(blocks) = content {
+        blocks.retain(|block| {
+            if block["name"] != "moocfi/exercise" {
+                return true;
+            }
+            block["attributes"]["id"]
+                .as_str()
+                .and_then(|id| Uuid::parse_str(id).ok())
+                .is_none_or(|id| exercise_ids.contains(&id))
+        });
+    }
+}
+
+/// A stable pseudorandom sort key for the exercise. It must never change, otherwise the draws of
+/// past enrollments could no longer be reproduced. Based on the SplitMix64 finalizer.
+fn draw_key(seed: i64, id: Uuid) -> u64 {
+    fn mix(x: u64) -> u64 {
+        let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    let (high, low) = id.as_u64_pair();
+    mix(mix(seed as u64 ^ high) ^ low)
+}
+
+pub async fn get_by_exam_id(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+) -> ModelResult<Vec<ExamQuestionPool>> {
+    let res = sqlx::query_as!(
+        ExamQuestionPool,
+        r#"
+SELECT p.id,
+  p.created_at,
+  p.updated_at,
+  p.deleted_at,
+  p.exam_id,
+  p.name,
+  p.draw_count,
+  COALESCE(
+    array_agg(
+      pe.exercise_id
+      ORDER BY pe.exercise_id
+    ) FILTER (
+      WHERE pe.id IS NOT NULL
+    ),
+    '{}'
+  ) AS "exercise_ids!"
+FROM exam_question_pools p
+  LEFT JOIN exam_question_pool_exercises pe ON pe.exam_question_pool_id = p.id
+  AND pe.deleted_at IS NULL
+WHERE p.exam_id = $1
+  AND p.deleted_at IS NULL
+GROUP BY p.id
+ORDER BY p.created_at,
+  p.id
+        "#,
+        exam_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_id(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    id: Uuid,
+) -> ModelResult<ExamQuestionPool> {
+    get_by_exam_id(conn, exam_id)
+        .await?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| model_err!(NotFound, "Question pool not found.".to_string()))
+}
+
+pub async fn get_question_bank(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+) -> ModelResult<ExamQuestionBank> {
+    let score_maximums_by_exercise_id = exercises::get_exercises_by_exam_id(conn, exam_id)
+        .await?
+        .into_iter()
+        .map(|e| (e.id, e.score_maximum))
+        .collect();
+    let pools = get_by_exam_id(conn, exam_id).await?;
+    Ok(ExamQuestionBank {
+        score_maximums_by_exercise_id,
+        pools,
+    })
+}
+
+/// The exercises the student got in the exam. None if the student has not enrolled.
+pub async fn get_exercise_ids_for_user(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<Option<HashSet<Uuid>>> {
+    let Some(enrollment) = crate::exams::get_enrollment(conn, exam_id, user_id).await? else {
+        return Ok(None);
+    };
+    let question_bank = get_question_bank(conn, exam_id).await?;
+    Ok(Some(question_bank.exercise_ids_for_seed(Some(
+        enrollment.question_pool_seed,
+    ))))
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    new_pool: &NewExamQuestionPool,
+) -> ModelResult<ExamQuestionPool> {
+    let mut tx = conn.begin().await?;
+    let exercise_ids = validate(&mut tx, exam_id, None, new_pool).await?;
+    let id = sqlx::query!(
+        r#"
+INSERT INTO exam_question_pools (exam_id, name, draw_count)
+VALUES ($1, $2, $3)
+RETURNING id
+        "#,
+        exam_id,
+        new_pool.name.trim(),
+        new_pool.draw_count,
+    )
+    .fetch_one(&mut *tx)
+    .await?
+    .id;
+    insert_exercises(&mut tx, id, &exercise_ids).await?;
+    let res = get_by_id(&mut tx, exam_id, id).await?;
+    tx.commit().await?;
+    Ok(res)
+}
+
+pub async fn update(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    id: Uuid,
+    new_pool: &NewExamQuestionPool,
+) -> ModelResult<ExamQuestionPool> {
+    let mut tx = conn.begin().await?;
+    let exercise_ids = validate(&mut tx, exam_id, Some(id), new_pool).await?;
+    sqlx::query!(
+        r#"
+UPDATE exam_question_pools
+SET name = $3,
+  draw_count = $4
+WHERE id = $1
+  AND exam_id = $2
+  AND deleted_at IS NULL
+RETURNING id
+        "#,
+        id,
+        exam_id,
+        new_pool.name.trim(),
+        new_pool.draw_count,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    delete_exercises(&mut tx, id).await?;
+    insert_exercises(&mut tx, id, &exercise_ids).await?;
+    let res = get_by_id(&mut tx, exam_id, id).await?;
+    tx.commit().await?;
+    Ok(res)
+}
+
+pub async fn delete(conn: &mut PgConnection, exam_id: Uuid, id: Uuid) -> ModelResult<()> {
+    let mut tx = conn.begin().await?;
+    ensure_no_students_enrolled(&mut tx, exam_id).await?;
+    delete_exercises(&mut tx, id).await?;
+    sqlx::query!(
+        r#"
+UPDATE exam_question_pools
+SET deleted_at = now()
+WHERE id = $1
+  AND exam_id = $2
+  AND deleted_at IS NULL
+RETURNING id
+        "#,
+        id,
+        exam_id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn insert_exercises(
+    conn: &mut PgConnection,
+    exam_question_pool_id: Uuid,
+    exercise_ids: &[Uuid],
+) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+INSERT INTO exam_question_pool_exercises (exam_question_pool_id, exercise_id)
+SELECT $1,
+  UNNEST($2::uuid [])
+        "#,
+        exam_question_pool_id,
+        exercise_ids,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+async fn delete_exercises(conn: &mut PgConnection, exam_question_pool_id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+UPDATE exam_question_pool_exercises
+SET deleted_at = now()
+WHERE exam_question_pool_id = $1
+  AND deleted_at IS NULL
+        "#,
+        exam_question_pool_id,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Changing the pools would change the exercises of students who have already started, so the
+/// pools are locked once a student has enrolled. Teachers testing the exam do not count.
+async fn ensure_no_students_enrolled(conn: &mut PgConnection, exam_id: Uuid) -> ModelResult<()> {
+    let enrolled = sqlx::query!(
+        r#"
+SELECT EXISTS (
+    SELECT 1
+    FROM exam_enrollments
+    WHERE exam_id = $1
+      AND is_teacher_testing = FALSE
+      AND deleted_at IS NULL
+  ) AS "enrolled!"
+        "#,
+        exam_id,
+    )
+    .fetch_one(conn)
+    .await?
+    .enrolled;
+    if enrolled {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The question pools cannot be changed after students have enrolled in the exam."
+                .to_string()
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the exercise ids of the pool without duplicates.
+async fn validate(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    exam_question_pool_id: Option<Uuid>,
+    new_pool: &NewExamQuestionPool,
+) -> ModelResult<Vec<Uuid>> {
+    ensure_no_students_enrolled(conn, exam_id).await?;
+    if new_pool.name.trim().is_empty() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The question pool needs a name.".to_string()
+        ));
+    }
+    let mut exercise_ids = new_pool.exercise_ids.clone();
+    exercise_ids.sort();
+    exercise_ids.dedup();
+    if new_pool.draw_count < 0 || new_pool.draw_count as usize > exercise_ids.len() {
+        return Err(model_err!(
+            PreconditionFailed,
+            format!(
+                "The number of exercises to draw must be between 0 and {}.",
+                exercise_ids.len()
+            )
+        ));
+    }
+
+    let exam_exercise_ids: HashSet<Uuid> = exercises::get_exercises_by_exam_id(conn, exam_id)
+        .await?
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    if exercise_ids
+        .iter()
+        .any(|id| !exam_exercise_ids.contains(id))
+    {
+        return Err(model_err!(
+            PreconditionFailed,
+            "Only exercises of the exam can be added to its question pools.".to_string()
+        ));
+    }
+    let in_other_pools = get_by_exam_id(conn, exam_id)
+        .await?
+        .into_iter()
+        .filter(|p| Some(p.id) != exam_question_pool_id)
+        .flat_map(|p| p.exercise_ids)
+        .any(|id| exercise_ids.contains(&id));
+    if in_other_pools {
+        return Err(model_err!(
+            PreconditionFailed,
+            "An exercise can be in only one question pool.".to_string()
+        ));
+    }
+    Ok(exercise_ids)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helper::*;
+
+    fn pool(draw_count: i32, exercise_ids: Vec<Uuid>) -> ExamQuestionPool {
+        ExamQuestionPool {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            exam_id: Uuid::nil(),
+            name: "Pool".to_string(),
+            draw_count,
+            exercise_ids,
+        }
+    }
+
+    #[test]
+    fn draws_are_reproducible_and_respect_the_pools() {
+        let fixed = Uuid::new_v4();
+        let pool_a: Vec<Uuid> = (0..5).map(|_| Uuid::new_v4()).collect();
+        let pool_b: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        let mut score_maximums_by_exercise_id = HashMap::from([(fixed, 10)]);
+        for id in &pool_a {
+            score_maximums_by_exercise_id.insert(*id, 2);
+        }
+        for id in &pool_b {
+            score_maximums_by_exercise_id.insert(*id, 3);
+        }
+        let question_bank = ExamQuestionBank {
+            score_maximums_by_exercise_id,
+            pools: vec![pool(2, pool_a.clone()), pool(1, pool_b.clone())],
+        };
+
+        let mut draws = HashSet::new();
+        for seed in 0..50 {
+            let drawn = question_bank.exercise_ids_for_seed(Some(seed));
+            assert_eq!(drawn, question_bank.exercise_ids_for_seed(Some(seed)));
+            assert_eq!(drawn.len(), 4);
+            assert!(drawn.contains(&fixed));
+            assert_eq!(drawn.iter().filter(|id| pool_a.contains(id)).count(), 2);
+            assert_eq!(drawn.iter().filter(|id| pool_b.contains(id)).count(), 1);
+            assert_eq!(question_bank.maximum_points_for_seed(Some(seed)), 17);
+            let mut drawn: Vec<Uuid> = drawn.into_iter().collect();
+            drawn.sort();
+            draws.insert(drawn);
+        }
+        // Different seeds give different draws.
+        assert!(draws.len() > 1);
+        assert_eq!(question_bank.exercise_ids_for_seed(None).len(), 9);
+        assert_eq!(question_bank.maximum_points_for_seed(None), 29);
+    }
+
+    #[test]
+    fn draw_key_is_stable() {
+        // The draws of past enrollments depend on these values.
+        let id = Uuid::parse_str("8e8c3b1a-7f0a-4c3e-9b62-2a8f1f6c5d40").unwrap();
+        assert_eq!(draw_key(0, id), draw_key(0, id));
+        assert_ne!(draw_key(0, id), draw_key(1, id));
+        let slides = vec![id, Uuid::nil()];
+        assert_eq!(
+            pick_exercise_slide_id(42, &slides),
+            pick_exercise_slide_id(42, &slides)
+        );
+        assert_eq!(pick_exercise_slide_id(42, &[]), None);
+    }
+
+    #[test]
+    fn removes_undrawn_exercise_blocks() {
+        let drawn = Uuid::new_v4();
+        let undrawn = Uuid::new_v4();
+        let mut content = serde_json::json!([
+            { "name": "core/paragraph", "attributes": {} },
+            { "name": "moocfi/exercise", "attributes": { "id": drawn.to_string() } },
+            { "name": "moocfi/exercise", "attributes": { "id": undrawn.to_string() } },
+        ]);
+        remove_undrawn_exercise_blocks(&mut content, &HashSet::from([drawn]));
+        assert_eq!(
+            content,
+            serde_json::json!([
+                { "name": "core/paragraph", "attributes": {} },
+                { "name": "moocfi/exercise", "attributes": { "id": drawn.to_string() } },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn only_exam_exercises_can_be_pooled() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise);
+        let exam = crate::exams::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &crate::exams::NewExam {
+                name: "Exam".to_string(),
+                starts_at: None,
+                ends_at: None,
+                time_minutes: 60,
+                organization_id: org,
+                minimum_points_treshold: 0,
+                grade_manually: false,
+            },
+        )
+        .await
+        .unwrap();
+
+        let err = insert(
+            tx.as_mut(),
+            exam,
+            &NewExamQuestionPool {
+                name: "Pool".to_string(),
+                draw_count: 1,
+                exercise_ids: vec![exercise],
+            },
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(*err.error_type(), ModelErrorType::PreconditionFailed);
+
+        let empty = insert(
+            tx.as_mut(),
+            exam,
+            &NewExamQuestionPool {
+                name: "Empty".to_string(),
+                draw_count: 0,
+                exercise_ids: vec![],
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_by_exam_id(tx.as_mut(), exam).await.unwrap(),
+            vec![empty]
+        );
+        tx.rollback().await;
+    }
+}
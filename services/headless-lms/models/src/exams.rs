@@ -1,4 +1,3 @@
-use chrono::Duration;
 use std::collections::HashMap;
 use utoipa::ToSchema;
 
@@ -408,9 +407,10 @@ pub async fn verify_exam_submission_can_be_made(
                 "User has no enrollment for the exam".to_string()
             )
         })?;
-    let student_has_time =
-        Utc::now() <= enrollment.started_at + Duration::minutes(exam.time_minutes.into());
-    let exam_is_ongoing = exam.ends_at.map(|ea| Utc::now() < ea).unwrap_or_default();
+    // Accommodations and deadline exceptions change the times for this student only.
+    let timing = crate::exam_accommodations::get_exam_timing_for_user(conn, &exam, user_id).await?;
+    let student_has_time = Utc::now() <= timing.student_time_ends_at(enrollment.started_at);
+    let exam_is_ongoing = timing.ends_at.map(|ea| Utc::now() < ea).unwrap_or_default();
     Ok(student_has_time && exam_is_ongoing)
 }
 
@@ -426,6 +426,8 @@ pub struct ExamEnrollment {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    /// Seed for drawing the student's exercises, see [`crate::exam_question_pools`].
+    pub question_pool_seed: i64,
 }
 
 pub async fn get_enrollment(
@@ -539,12 +541,23 @@ WHERE exam_id = $1
     Ok(())
 }
 
+/// Marks the student's exam as ended. The end is clamped to when the student's time ran out, with
+/// their accommodation taken into account, so ending a timed out exam late does not record extra time.
 pub async fn update_exam_ended_at(
     conn: &mut PgConnection,
     exam_id: Uuid,
     user_id: Uuid,
     ended_at: DateTime<Utc>,
 ) -> ModelResult<()> {
+    let ended_at = match get_enrollment(conn, exam_id, user_id).await? {
+        Some(enrollment) if !enrollment.is_teacher_testing => {
+            let exam = get(conn, exam_id).await?;
+            let timing =
+                crate::exam_accommodations::get_exam_timing_for_user(conn, &exam, user_id).await?;
+            ended_at.min(timing.student_time_ends_at(enrollment.started_at))
+        }
+        _ => ended_at,
+    };
     sqlx::query!(
         "
 UPDATE exam_enrollments
@@ -562,25 +575,25 @@ WHERE exam_id = $1
     Ok(())
 }
 
+/// Ends the exam for the users, each at their own time. `ended_ats` is in the same order as `user_ids`.
 pub async fn update_exam_ended_at_for_users_with_exam_id(
     conn: &mut PgConnection,
     exam_id: Uuid,
     user_ids: &[Uuid],
-    ended_at: DateTime<Utc>,
+    ended_ats: &[DateTime<Utc>],
 ) -> ModelResult<()> {
     sqlx::query!(
         "
 UPDATE exam_enrollments
-SET ended_at = $3
-WHERE user_id IN (
-    SELECT UNNEST($1::uuid [])
-  )
-  AND exam_id = $2
-  AND deleted_at IS NULL
+SET ended_at = u.ended_at
+FROM UNNEST($1::uuid [], $3::timestamptz []) AS u(user_id, ended_at)
+WHERE exam_enrollments.user_id = u.user_id
+  AND exam_enrollments.exam_id = $2
+  AND exam_enrollments.deleted_at IS NULL
 ",
         user_ids,
         exam_id,
-        ended_at
+        ended_ats
     )
     .execute(conn)
     .await?;
@@ -727,6 +740,20 @@ WHERE user_exercise_state_id IN (
     .execute(&mut *tx)
     .await?;
 
+    sqlx::query!(
+        r#"
+UPDATE exam_enrollment_events
+SET deleted_at = NOW()
+WHERE exam_id = $1
+  AND user_id = $2
+  AND deleted_at IS NULL
+        "#,
+        exam_id,
+        user_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
     sqlx::query!(
         r#"
 UPDATE user_exercise_states
@@ -538,6 +538,48 @@ ORDER BY ess.created_at DESC, ess.id DESC, ets.created_at
     Ok(submissions)
 }
 
+/// The points of one graded submission to a slide, before any late submission penalty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExerciseSlideSubmissionAttemptScore {
+    pub exercise_slide_submission_id: Uuid,
+    pub score_given: f32,
+}
+
+/// The user's fully graded submissions to a slide, oldest first, with the points from all of
+/// their tasks summed up. Used to score the slide with the exercise's scoring strategy.
+pub async fn get_graded_attempt_scores_for_slide(
+    conn: &mut PgConnection,
+    user_exercise_state_id: Uuid,
+    exercise_slide_id: Uuid,
+) -> ModelResult<Vec<ExerciseSlideSubmissionAttemptScore>> {
+    let res = sqlx::query_as!(
+        ExerciseSlideSubmissionAttemptScore,
+        r#"
+SELECT ess.id AS exercise_slide_submission_id,
+  SUM(etg.score_given) AS "score_given!"
+FROM exercise_slide_submissions ess
+  JOIN user_exercise_states ues ON ues.id = $1
+  AND ues.user_id = ess.user_id
+  AND ues.exercise_id = ess.exercise_id
+  JOIN exercise_task_submissions ets ON ets.exercise_slide_submission_id = ess.id
+  AND ets.deleted_at IS NULL
+  LEFT JOIN exercise_task_gradings etg ON etg.id = ets.exercise_task_grading_id
+  AND etg.deleted_at IS NULL
+WHERE ess.exercise_slide_id = $2
+  AND ess.deleted_at IS NULL
+GROUP BY ess.id
+HAVING COUNT(etg.score_given) = COUNT(*)
+ORDER BY ess.created_at,
+  ess.id
+        "#,
+        user_exercise_state_id,
+        exercise_slide_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
 pub async fn get_users_latest_exercise_slide_submission(
     conn: &mut PgConnection,
     exercise_slide_id: Uuid,
@@ -1087,7 +1129,7 @@ AND deleted_at IS NULL
 mod tests {
     use super::*;
     use crate::exercise_tasks::CourseMaterialExerciseTask;
-    use crate::exercises::ActivityProgress;
+    use crate::exercises::{ActivityProgress, ExerciseScoringStrategy};
     use crate::user_exercise_states::ReviewingStage;
 
     fn dummy_exercise() -> Exercise {
@@ -1112,6 +1154,9 @@ mod tests {
             use_course_default_peer_or_self_review_config: false,
             exercise_language_group_id: None,
             teacher_reviews_answer_after_locking: false,
+            scoring_strategy: ExerciseScoringStrategy::BestAttempt,
+            scoring_strategy_attempt_count: None,
+            scoring_strategy_penalty_percent: None,
         }
     }
 
@@ -412,8 +412,10 @@ pub async fn get_for_student(
                     None,
                 )
             })?;
-        if Utc::now() > enrollment.started_at + chrono::Duration::minutes(exam.time_minutes.into())
-            || exam.ends_at.map(|ea| Utc::now() > ea).unwrap_or_default()
+        let timing =
+            crate::exam_accommodations::get_exam_timing_for_user(conn, &exam, user_id).await?;
+        if Utc::now() > timing.student_time_ends_at(enrollment.started_at)
+            || timing.ended_at_or(Utc::now(), false)
         {
             // exam over, return grading
             Ok(Some(grading))
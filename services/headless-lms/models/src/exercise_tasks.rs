@@ -379,9 +379,7 @@ pub async fn get_or_select_user_exercise_slide_for_course_or_exam(
         } else {
             info!("random");
             let exercise_slide_id =
-                exercise_slides::get_random_exercise_slide_for_exercise(conn, exercise_id)
-                    .await?
-                    .id;
+                select_exercise_slide_id(conn, user_id, exercise_id, exam_id).await?;
             user_exercise_states::upsert_selected_exercise_slide_id(
                 conn,
                 user_id,
@@ -416,6 +414,37 @@ pub async fn get_or_select_user_exercise_slide_for_course_or_exam(
     })
 }
 
+/// Exam students get the slide drawn with the seed of their enrollment so that the draw can be
+/// reproduced. Everyone else gets a random slide.
+async fn select_exercise_slide_id(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    exercise_id: Uuid,
+    exam_id: Option<Uuid>,
+) -> ModelResult<Uuid> {
+    if let Some(exam_id) = exam_id
+        && let Some(enrollment) = crate::exams::get_enrollment(conn, exam_id, user_id).await?
+    {
+        let exercise_slide_ids: Vec<Uuid> =
+            exercise_slides::get_exercise_slides_by_exercise_id(conn, exercise_id)
+                .await?
+                .into_iter()
+                .map(|s| s.id)
+                .collect();
+        if let Some(exercise_slide_id) = crate::exam_question_pools::pick_exercise_slide_id(
+            enrollment.question_pool_seed,
+            &exercise_slide_ids,
+        ) {
+            return Ok(exercise_slide_id);
+        }
+    }
+    Ok(
+        exercise_slides::get_random_exercise_slide_for_exercise(conn, exercise_id)
+            .await?
+            .id,
+    )
+}
+
 pub async fn get_exercise_tasks_by_exercise_id(
     conn: &mut PgConnection,
     exercise_id: Uuid,
@@ -13,6 +13,9 @@ use crate::{
     },
     exercise_slides::{self, CourseMaterialExerciseSlide},
     exercise_tasks,
+    grading_rubric_assessments::FilledGradingRubricCriterion,
+    late_submission_penalties::LateSubmissionPenalty,
+    late_submission_policies::LateSubmissionPolicy,
     peer_or_self_review_configs::CourseMaterialPeerOrSelfReviewConfig,
     peer_or_self_review_question_submissions::PeerOrSelfReviewQuestionSubmission,
     peer_or_self_review_questions::PeerOrSelfReviewQuestion,
@@ -50,6 +53,11 @@ pub struct Exercise {
     pub use_course_default_peer_or_self_review_config: bool,
     pub exercise_language_group_id: Option<Uuid>,
     pub teacher_reviews_answer_after_locking: bool,
+    pub scoring_strategy: ExerciseScoringStrategy,
+    /// Set when the scoring strategy is `AverageOfLastAttempts`.
+    pub scoring_strategy_attempt_count: Option<i32>,
+    /// Set when the scoring strategy is `PenaltyPerAttempt`.
+    pub scoring_strategy_penalty_percent: Option<f32>,
 }
 
 impl Exercise {
@@ -62,6 +70,51 @@ impl Exercise {
             )
         })
     }
+
+    /**
+    Combines the scores of the user's graded attempts, oldest first, into the score the user keeps according to the exercise's scoring strategy. None if there are no attempts.
+
+    Does not handle `BestAttempt`'s interaction with the points update strategy of the submission, so the grading code only uses this for the other strategies.
+    */
+    pub fn score_from_attempts(&self, attempt_scores: &[f32]) -> Option<f32> {
+        match self.scoring_strategy {
+            ExerciseScoringStrategy::BestAttempt => attempt_scores.iter().copied().reduce(f32::max),
+            ExerciseScoringStrategy::LatestAttempt => attempt_scores.last().copied(),
+            ExerciseScoringStrategy::AverageOfLastAttempts => {
+                let count = self.scoring_strategy_attempt_count.unwrap_or(1).max(1) as usize;
+                let last_attempts = &attempt_scores[attempt_scores.len().saturating_sub(count)..];
+                if last_attempts.is_empty() {
+                    return None;
+                }
+                Some(last_attempts.iter().sum::<f32>() / last_attempts.len() as f32)
+            }
+            ExerciseScoringStrategy::PenaltyPerAttempt => {
+                let penalty = self.scoring_strategy_penalty_percent.unwrap_or(0.0) / 100.0;
+                attempt_scores
+                    .iter()
+                    .enumerate()
+                    .map(|(retries, score)| score * (1.0 - penalty * retries as f32).max(0.0))
+                    .reduce(f32::max)
+            }
+        }
+    }
+}
+
+/// Decides which of the user's attempts at an exercise count towards the points they keep.
+#[derive(
+    Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, sqlx::Type, ToSchema,
+)]
+#[sqlx(type_name = "exercise_scoring_strategy", rename_all = "kebab-case")]
+pub enum ExerciseScoringStrategy {
+    /// The best attempt counts. Points are only removed when the exercise service asks for it.
+    #[default]
+    BestAttempt,
+    /// The latest attempt counts, even if it was worse than an earlier one.
+    LatestAttempt,
+    /// The average of the last `scoring_strategy_attempt_count` attempts counts.
+    AverageOfLastAttempts,
+    /// Every retry loses `scoring_strategy_penalty_percent` percent more of its points, and the best result counts.
+    PenaltyPerAttempt,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -105,11 +158,20 @@ pub struct CourseMaterialExercise {
     pub previous_exercise_slide_submission: Option<ExerciseSlideSubmission>,
     pub user_course_instance_exercise_service_variables: Vec<UserCourseExerciseServiceVariable>,
     pub should_show_reset_message: Option<String>,
+    /// The policy that decides whether submissions are still accepted after the deadline and how
+    /// their points are reduced. None if late submissions are not accepted.
+    pub late_submission_policy: Option<LateSubmissionPolicy>,
+    /// Set if the previous submission was made after the deadline.
+    pub previous_exercise_slide_submission_late_submission_penalty: Option<LateSubmissionPenalty>,
+    /// The rubric a teacher filled when grading the user's answer. None if the answer was not graded
+    /// with a rubric or the grading is hidden from the user.
+    pub filled_grading_rubric: Option<Vec<FilledGradingRubricCriterion>>,
 }
 
 impl CourseMaterialExercise {
     pub fn clear_grading_information(&mut self) {
         self.exercise_status = None;
+        self.filled_grading_rubric = None;
         self.current_exercise_slide
             .exercise_tasks
             .iter_mut()
@@ -475,12 +537,11 @@ pub async fn get_course_material_exercise(
     fetch_service_info: impl Fn(Url) -> BoxFuture<'static, ModelResult<ExerciseServiceInfoApi>>,
 ) -> ModelResult<CourseMaterialExercise> {
     let mut exercise = get_by_id(conn, exercise_id).await?;
-    if exercise.deadline.is_none()
-        && let Some(chapter_id) = exercise.chapter_id
-    {
-        let chapter = crate::chapters::get_chapter(conn, chapter_id).await?;
-        exercise.deadline = chapter.deadline;
-    }
+    // Shows the student the deadline that actually applies to them: their own exception if they
+    // have one, otherwise the exercise's or its chapter's deadline.
+    exercise.deadline =
+        crate::deadline_exceptions::get_effective_exercise_deadline(conn, &exercise, user_id)
+            .await?;
     let (current_exercise_slide, instance_or_exam_id) =
         get_or_select_exercise_slide(&mut *conn, user_id, &exercise, fetch_service_info).await?;
     info!(
@@ -516,6 +577,13 @@ pub async fn get_course_material_exercise(
         _ => None,
     };
 
+    let filled_grading_rubric = match &user_exercise_state {
+        Some(user_exercise_state) => {
+            get_filled_grading_rubric_visible_to_user(conn, user_exercise_state.id).await?
+        }
+        None => None,
+    };
+
     let exercise_status = user_exercise_state.map(|user_exercise_state| ExerciseStatus {
         score_given: user_exercise_state.score_given,
         activity_progress: user_exercise_state.activity_progress,
@@ -578,6 +646,20 @@ pub async fn get_course_material_exercise(
         None
     };
 
+    let late_submission_policy =
+        crate::late_submission_policies::get_for_exercise(conn, &exercise).await?;
+    let previous_exercise_slide_submission_late_submission_penalty =
+        match &previous_exercise_slide_submission {
+            Some(submission) => {
+                crate::late_submission_penalties::get_by_exercise_slide_submission_id(
+                    conn,
+                    submission.id,
+                )
+                .await?
+            }
+            None => None,
+        };
+
     Ok(CourseMaterialExercise {
         exercise,
         can_post_submission,
@@ -588,9 +670,39 @@ pub async fn get_course_material_exercise(
         user_course_instance_exercise_service_variables,
         previous_exercise_slide_submission,
         should_show_reset_message,
+        late_submission_policy,
+        previous_exercise_slide_submission_late_submission_penalty,
+        filled_grading_rubric,
     })
 }
 
+async fn get_filled_grading_rubric_visible_to_user(
+    conn: &mut PgConnection,
+    user_exercise_state_id: Uuid,
+) -> ModelResult<Option<Vec<FilledGradingRubricCriterion>>> {
+    let teacher_grading_decision =
+        crate::teacher_grading_decisions::try_to_get_latest_grading_decision_by_user_exercise_state_id(
+            conn,
+            user_exercise_state_id,
+        )
+        .await?;
+    match teacher_grading_decision {
+        Some(decision)
+            if decision.teacher_decision == TeacherDecisionType::Rubric
+                && decision.hidden != Some(true) =>
+        {
+            let filled_grading_rubric =
+                crate::grading_rubric_assessments::get_filled_rubric_by_teacher_grading_decision_id(
+                    conn,
+                    decision.id,
+                )
+                .await?;
+            Ok(Some(filled_grading_rubric))
+        }
+        _ => Ok(None),
+    }
+}
+
 async fn determine_can_post_submission(
     conn: &mut PgConnection,
     user_id: Option<Uuid>,
@@ -715,6 +827,16 @@ pub async fn get_or_select_exercise_slide(
         (Some(user_id), _, Some(exam_id)) => {
             info!("selecting exam task");
             // signed in, exam exercise
+            if !crate::exam_question_pools::get_exercise_ids_for_user(conn, exam_id, user_id)
+                .await?
+                .is_none_or(|ids| ids.contains(&exercise.id))
+            {
+                return Err(ModelError::new(
+                    ModelErrorType::NotFound,
+                    "The exercise was not drawn for you in this exam.".to_string(),
+                    None,
+                ));
+            }
             let tasks = exercise_tasks::get_or_select_user_exercise_slide_for_course_or_exam(
                 conn,
                 user_id,
@@ -1710,4 +1832,71 @@ mod test {
 
         assert_eq!(status, Some(ChapterLockingStatus::Unlocked));
     }
+
+    fn exercise_with_scoring_strategy(
+        scoring_strategy: ExerciseScoringStrategy,
+        scoring_strategy_attempt_count: Option<i32>,
+        scoring_strategy_penalty_percent: Option<f32>,
+    ) -> Exercise {
+        Exercise {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            name: "Exercise".to_string(),
+            course_id: Some(Uuid::new_v4()),
+            exam_id: None,
+            page_id: Uuid::new_v4(),
+            chapter_id: None,
+            deadline: None,
+            deleted_at: None,
+            score_maximum: 10,
+            order_number: 0,
+            copied_from: None,
+            max_tries_per_slide: None,
+            limit_number_of_tries: false,
+            needs_peer_review: false,
+            needs_self_review: false,
+            use_course_default_peer_or_self_review_config: false,
+            exercise_language_group_id: None,
+            teacher_reviews_answer_after_locking: true,
+            scoring_strategy,
+            scoring_strategy_attempt_count,
+            scoring_strategy_penalty_percent,
+        }
+    }
+
+    #[test]
+    fn scores_attempts_according_to_the_scoring_strategy() {
+        let attempts = [4.0, 10.0, 6.0];
+        let best = exercise_with_scoring_strategy(ExerciseScoringStrategy::BestAttempt, None, None);
+        assert_eq!(best.score_from_attempts(&attempts), Some(10.0));
+        let latest =
+            exercise_with_scoring_strategy(ExerciseScoringStrategy::LatestAttempt, None, None);
+        assert_eq!(latest.score_from_attempts(&attempts), Some(6.0));
+        let average = exercise_with_scoring_strategy(
+            ExerciseScoringStrategy::AverageOfLastAttempts,
+            Some(2),
+            None,
+        );
+        assert_eq!(average.score_from_attempts(&attempts), Some(8.0));
+        assert_eq!(average.score_from_attempts(&[3.0]), Some(3.0));
+        let penalty = exercise_with_scoring_strategy(
+            ExerciseScoringStrategy::PenaltyPerAttempt,
+            None,
+            Some(10.0),
+        );
+        assert_eq!(penalty.score_from_attempts(&attempts), Some(9.0));
+        assert_eq!(penalty.score_from_attempts(&[8.0, 8.0]), Some(8.0));
+        assert_eq!(latest.score_from_attempts(&[]), None);
+    }
+
+    #[test]
+    fn penalty_per_attempt_does_not_go_below_zero() {
+        let exercise = exercise_with_scoring_strategy(
+            ExerciseScoringStrategy::PenaltyPerAttempt,
+            None,
+            Some(60.0),
+        );
+        assert_eq!(exercise.score_from_attempts(&[0.0, 0.0, 10.0]), Some(0.0));
+    }
 }
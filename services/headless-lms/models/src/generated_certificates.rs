@@ -13,6 +13,36 @@ pub struct GeneratedCertificate {
     pub name_on_certificate: String,
     pub verification_id: String,
     pub certificate_configuration_id: Uuid,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Only shown to teachers.
+    pub revocation_reason: Option<String>,
+    /// `None` if the certificate was revoked automatically.
+    pub revoked_by_user_id: Option<Uuid>,
+    /// The certificate that was reissued in place of this one.
+    pub superseded_by_id: Option<Uuid>,
+    pub version: i32,
+}
+
+impl GeneratedCertificate {
+    pub fn status(&self) -> CertificateStatus {
+        if self.deleted_at.is_some() || self.revoked_at.is_some() {
+            CertificateStatus::Revoked
+        } else if self.superseded_by_id.is_some() {
+            CertificateStatus::Superseded
+        } else {
+            CertificateStatus::Valid
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CertificateStatus {
+    Valid,
+    /// The certificate no longer proves anything, for example because the completion was annulled.
+    Revoked,
+    /// A newer version of the certificate has been issued.
+    Superseded,
 }
 
 pub async fn get_certificate_for_user(
@@ -28,6 +58,8 @@ FROM generated_certificates
 WHERE user_id = $1
   AND certificate_configuration_id = $2
   AND deleted_at IS NULL
+  AND revoked_at IS NULL
+  AND superseded_by_id IS NULL
 ",
         user_id,
         certificate_configuration_id
@@ -65,6 +97,8 @@ FROM generated_certificates
 WHERE user_id = $1
     AND certificate_configuration_id = $2
     AND deleted_at IS NULL
+    AND revoked_at IS NULL
+    AND superseded_by_id IS NULL
 ",
         user_id,
         certificate_configuration_id,
@@ -89,9 +123,16 @@ INSERT INTO generated_certificates (
     user_id,
     certificate_configuration_id,
     name_on_certificate,
-    verification_id
+    verification_id,
+    version
   )
-VALUES ($1, $2, $3, $4)
+VALUES ($1, $2, $3, $4, (
+    SELECT COALESCE(MAX(version), 0) + 1
+    FROM generated_certificates
+    WHERE user_id = $1
+      AND certificate_configuration_id = $2
+      AND deleted_at IS NULL
+  ))
 RETURNING *
 ",
         user_id,
@@ -199,6 +240,309 @@ pub async fn get_by_id(
     Ok(res)
 }
 
+/// Like [`get_by_id`] but also returns deleted certificates, so that a verifier can tell a revoked
+/// certificate apart from one that never existed.
+pub async fn get_by_id_including_deleted(
+    conn: &mut PgConnection,
+    certificate_id: Uuid,
+) -> ModelResult<GeneratedCertificate> {
+    let res = sqlx::query_as!(
+        GeneratedCertificate,
+        r#"
+SELECT *
+FROM generated_certificates
+WHERE id = $1
+        "#,
+        certificate_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Revokes the certificate. Revoked certificates stay verifiable so that verification can tell
+/// that the certificate is no longer valid.
+pub async fn revoke(
+    conn: &mut PgConnection,
+    certificate_id: Uuid,
+    reason: &str,
+    revoked_by_user_id: Option<Uuid>,
+) -> ModelResult<GeneratedCertificate> {
+    if reason.trim().is_empty() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "A reason is required for revoking a certificate.".to_string()
+        ));
+    }
+    let certificate = get_by_id(&mut *conn, certificate_id).await?;
+    if certificate.revoked_at.is_some() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The certificate has already been revoked.".to_string()
+        ));
+    }
+    let res = sqlx::query_as!(
+        GeneratedCertificate,
+        r#"
+UPDATE generated_certificates
+SET revoked_at = NOW(),
+  revocation_reason = $2,
+  revoked_by_user_id = $3
+WHERE id = $1
+  AND deleted_at IS NULL
+  AND revoked_at IS NULL
+RETURNING *
+        "#,
+        certificate_id,
+        reason,
+        revoked_by_user_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Revokes the user's current certificates that require any of the given course modules. Used when
+/// the completions the certificates were issued for are annulled. Returns the revoked certificates.
+pub async fn revoke_all_by_user_and_course_module_ids(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    course_module_ids: &[Uuid],
+    reason: &str,
+) -> ModelResult<Vec<GeneratedCertificate>> {
+    let res = sqlx::query_as!(
+        GeneratedCertificate,
+        r#"
+UPDATE generated_certificates
+SET revoked_at = NOW(),
+  revocation_reason = $3
+WHERE user_id = $1
+  AND certificate_configuration_id IN (
+    SELECT certificate_configuration_id
+    FROM certificate_configuration_to_requirements
+    WHERE course_module_id = ANY($2)
+      AND deleted_at IS NULL
+  )
+  AND deleted_at IS NULL
+  AND revoked_at IS NULL
+  AND superseded_by_id IS NULL
+RETURNING *
+        "#,
+        user_id,
+        course_module_ids,
+        reason
+    )
+    .fetch_all(conn)
+    .await?;
+    if !res.is_empty() {
+        info!(
+            "Revoked {} certificates of user {}: {}",
+            res.len(),
+            user_id,
+            reason
+        );
+    }
+    Ok(res)
+}
+
+/// Issues a new version of the certificate, for example to correct the name on it. The old version
+/// is marked as superseded and verifying it points to the new one.
+pub async fn reissue(
+    conn: &mut PgConnection,
+    certificate_id: Uuid,
+    name_on_certificate: Option<&str>,
+) -> ModelResult<GeneratedCertificate> {
+    let mut tx = conn.begin().await?;
+    let old = sqlx::query_as!(
+        GeneratedCertificate,
+        r#"
+SELECT *
+FROM generated_certificates
+WHERE id = $1
+  AND deleted_at IS NULL
+FOR UPDATE
+        "#,
+        certificate_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    match old.status() {
+        CertificateStatus::Valid => {}
+        CertificateStatus::Revoked => {
+            return Err(model_err!(
+                PreconditionFailed,
+                "A revoked certificate cannot be reissued.".to_string()
+            ));
+        }
+        CertificateStatus::Superseded => {
+            return Err(model_err!(
+                PreconditionFailed,
+                "Only the latest version of a certificate can be reissued.".to_string()
+            ));
+        }
+    }
+    let new = sqlx::query_as!(
+        GeneratedCertificate,
+        r#"
+INSERT INTO generated_certificates (
+    user_id,
+    certificate_configuration_id,
+    name_on_certificate,
+    verification_id,
+    version
+  )
+VALUES ($1, $2, $3, $4, $5)
+RETURNING *
+        "#,
+        old.user_id,
+        old.certificate_configuration_id,
+        name_on_certificate.unwrap_or(&old.name_on_certificate),
+        generate_verification_id(),
+        old.version + 1
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+UPDATE generated_certificates
+SET superseded_by_id = $2
+WHERE id = $1
+        "#,
+        old.id,
+        new.id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(new)
+}
+
+/// All the certificates issued to the certificate's holder for the same configuration, including
+/// the revoked and superseded ones, oldest first.
+pub async fn get_version_history(
+    conn: &mut PgConnection,
+    certificate_id: Uuid,
+) -> ModelResult<Vec<GeneratedCertificate>> {
+    let res = sqlx::query_as!(
+        GeneratedCertificate,
+        r#"
+SELECT gc.*
+FROM generated_certificates gc
+  JOIN generated_certificates target ON target.user_id = gc.user_id
+  AND target.certificate_configuration_id = gc.certificate_configuration_id
+WHERE target.id = $1
+  AND gc.deleted_at IS NULL
+ORDER BY gc.version,
+  gc.created_at
+        "#,
+        certificate_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// What the public verification page shows about a certificate.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct CertificateVerificationStatus {
+    pub status: CertificateStatus,
+    pub verification_id: String,
+    pub name_on_certificate: String,
+    pub issued_at: DateTime<Utc>,
+    pub version: i32,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// Set for superseded certificates, so the verifier can look up the current version.
+    pub superseded_by_verification_id: Option<String>,
+}
+
+/// The reason for a revocation is left out because it may be sensitive, e.g. cheating.
+pub async fn get_verification_status(
+    conn: &mut PgConnection,
+    certificate_verification_id: &str,
+) -> ModelResult<CertificateVerificationStatus> {
+    let certificate =
+        get_certificate_by_verification_id(&mut *conn, certificate_verification_id).await?;
+    let superseded_by_verification_id = match certificate.superseded_by_id {
+        Some(superseded_by_id) => Some(
+            get_by_id_including_deleted(&mut *conn, superseded_by_id)
+                .await?
+                .verification_id,
+        ),
+        None => None,
+    };
+    Ok(CertificateVerificationStatus {
+        status: certificate.status(),
+        verification_id: certificate.verification_id,
+        name_on_certificate: certificate.name_on_certificate,
+        issued_at: certificate.created_at,
+        version: certificate.version,
+        revoked_at: certificate.revoked_at,
+        superseded_by_verification_id,
+    })
+}
+
+/// What a certificate's verifiable credential says about the completion it was issued for.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct CertificateCredentialDetails {
+    pub course_id: Uuid,
+    pub course_name: String,
+    pub course_module_id: Uuid,
+    /// `None` for a course's default module.
+    pub course_module_name: Option<String>,
+    pub organization_name: String,
+    /// `None` if the completion has since been removed.
+    pub completion_date: Option<DateTime<Utc>>,
+    pub grade: Option<i32>,
+    pub passed: Option<bool>,
+}
+
+/// Collects the completion behind the certificate. Like [`get_all_by_user_id`], a certificate whose
+/// configuration requires several modules is described by its first required module.
+pub async fn get_credential_details(
+    conn: &mut PgConnection,
+    certificate: &GeneratedCertificate,
+) -> ModelResult<CertificateCredentialDetails> {
+    let row = sqlx::query!(
+        r#"
+SELECT c.id AS course_id,
+  c.name AS course_name,
+  cm.id AS course_module_id,
+  cm.name AS course_module_name,
+  o.name AS organization_name
+FROM certificate_configuration_to_requirements cctr
+  JOIN course_modules cm ON cm.id = cctr.course_module_id
+  AND cm.deleted_at IS NULL
+  JOIN courses c ON c.id = cm.course_id
+  AND c.deleted_at IS NULL
+  JOIN organizations o ON o.id = c.organization_id
+WHERE cctr.certificate_configuration_id = $1
+  AND cctr.deleted_at IS NULL
+ORDER BY cm.order_number
+LIMIT 1
+        "#,
+        certificate.certificate_configuration_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let completions = crate::course_module_completions::get_all_by_user_id_and_course_module_id(
+        conn,
+        certificate.user_id,
+        row.course_module_id,
+    )
+    .await?;
+    let best_completion = crate::course_module_completions::select_best_completion(completions);
+    Ok(CertificateCredentialDetails {
+        course_id: row.course_id,
+        course_name: row.course_name,
+        course_module_id: row.course_module_id,
+        course_module_name: row.course_module_name,
+        organization_name: row.organization_name,
+        completion_date: best_completion.as_ref().map(|c| c.completion_date),
+        grade: best_completion.as_ref().and_then(|c| c.grade),
+        passed: best_completion.as_ref().map(|c| c.passed),
+    })
+}
+
 /// A certificate as its holder's own profile lists it: what it is for, and how to open it.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
 pub struct UserCertificate {
@@ -241,6 +585,8 @@ FROM generated_certificates gc
   AND c.deleted_at IS NULL
 WHERE gc.user_id = $1
   AND gc.deleted_at IS NULL
+  AND gc.revoked_at IS NULL
+  AND gc.superseded_by_id IS NULL
 ORDER BY gc.id,
   cm.order_number
         "#,
@@ -266,6 +612,8 @@ pub async fn find_existing(
         WHERE user_id = $1
           AND certificate_configuration_id = $2
           AND deleted_at IS NULL
+          AND revoked_at IS NULL
+          AND superseded_by_id IS NULL
         "#,
         user_id,
         config_id
@@ -289,9 +637,16 @@ pub async fn insert_raw(
             user_id,
             certificate_configuration_id,
             name_on_certificate,
-            verification_id
+            verification_id,
+            version
         )
-        VALUES ($1, $2, $3, $4)
+        VALUES ($1, $2, $3, $4, (
+            SELECT COALESCE(MAX(version), 0) + 1
+            FROM generated_certificates
+            WHERE user_id = $1
+              AND certificate_configuration_id = $2
+              AND deleted_at IS NULL
+        ))
         RETURNING *
         "#,
         user_id,
@@ -304,3 +659,172 @@ pub async fn insert_raw(
 
     Ok(row.id)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        course_module_completions::{CourseModuleCompletionGranter, NewCourseModuleCompletion},
+        test_helper::*,
+    };
+
+    #[tokio::test]
+    async fn reissue_supersedes_the_old_version() {
+        insert_data!(:tx, :user, :org, :course, instance: _instance, :course_module);
+        let configuration = insert_certificate_configuration(tx.as_mut(), course_module.id).await;
+        let original_id = insert_raw(
+            tx.as_mut(),
+            user,
+            configuration,
+            "Example Studnet",
+            "verification-original",
+        )
+        .await
+        .unwrap();
+
+        let reissued = reissue(tx.as_mut(), original_id, Some("Example Student"))
+            .await
+            .unwrap();
+        assert_eq!(reissued.version, 2);
+        assert_eq!(reissued.name_on_certificate, "Example Student");
+        assert_ne!(reissued.verification_id, "verification-original");
+        assert!(reissue(tx.as_mut(), original_id, None).await.is_err());
+
+        let status = get_verification_status(tx.as_mut(), "verification-original")
+            .await
+            .unwrap();
+        assert_eq!(status.status, CertificateStatus::Superseded);
+        assert_eq!(
+            status.superseded_by_verification_id,
+            Some(reissued.verification_id.clone())
+        );
+        assert_eq!(
+            get_certificate_for_user(tx.as_mut(), user, configuration)
+                .await
+                .unwrap()
+                .id,
+            reissued.id
+        );
+
+        let history = get_version_history(tx.as_mut(), reissued.id).await.unwrap();
+        assert_eq!(
+            history.iter().map(|c| c.id).collect::<Vec<_>>(),
+            vec![original_id, reissued.id]
+        );
+    }
+
+    #[tokio::test]
+    async fn revoked_certificates_stay_verifiable() {
+        insert_data!(:tx, :user, :org, :course, instance: _instance, :course_module);
+        let configuration = insert_certificate_configuration(tx.as_mut(), course_module.id).await;
+        let id = insert_raw(
+            tx.as_mut(),
+            user,
+            configuration,
+            "Example Student",
+            "verification-revoked",
+        )
+        .await
+        .unwrap();
+
+        assert!(revoke(tx.as_mut(), id, " ", Some(user)).await.is_err());
+        let revoked = revoke(tx.as_mut(), id, "Issued by mistake", Some(user))
+            .await
+            .unwrap();
+        assert_eq!(revoked.status(), CertificateStatus::Revoked);
+        assert_eq!(revoked.revoked_by_user_id, Some(user));
+        assert!(
+            revoke(tx.as_mut(), id, "Issued by mistake", Some(user))
+                .await
+                .is_err()
+        );
+        assert!(reissue(tx.as_mut(), id, None).await.is_err());
+
+        let status = get_verification_status(tx.as_mut(), "verification-revoked")
+            .await
+            .unwrap();
+        assert_eq!(status.status, CertificateStatus::Revoked);
+        assert!(
+            get_certificate_for_user(tx.as_mut(), user, configuration)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn removing_the_completion_revokes_the_certificate() {
+        insert_data!(:tx, :user, :org, :course, instance: _instance, :course_module);
+        let configuration = insert_certificate_configuration(tx.as_mut(), course_module.id).await;
+        let completion = crate::course_module_completions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &NewCourseModuleCompletion {
+                course_id: course,
+                course_module_id: course_module.id,
+                user_id: user,
+                completion_date: Utc::now(),
+                completion_registration_attempt_date: None,
+                completion_language: "en-US".to_string(),
+                eligible_for_ects: true,
+                email: "test@example.com".to_string(),
+                grade: Some(5),
+                passed: true,
+            },
+            CourseModuleCompletionGranter::User(user),
+        )
+        .await
+        .unwrap();
+        let certificate = generate_and_insert(tx.as_mut(), user, "Example Student", configuration)
+            .await
+            .unwrap();
+        assert_eq!(certificate.status(), CertificateStatus::Valid);
+
+        crate::course_module_completions::delete(tx.as_mut(), completion.id)
+            .await
+            .unwrap();
+        let certificate = get_by_id(tx.as_mut(), certificate.id).await.unwrap();
+        assert_eq!(certificate.status(), CertificateStatus::Revoked);
+        assert_eq!(certificate.revoked_by_user_id, None);
+        assert!(certificate.revocation_reason.is_some());
+    }
+
+    #[tokio::test]
+    async fn resetting_the_progress_revokes_the_certificate() {
+        insert_data!(:tx, :user, :org, :course, instance: _instance, :course_module);
+        let configuration = insert_certificate_configuration(tx.as_mut(), course_module.id).await;
+        crate::course_module_completions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &NewCourseModuleCompletion {
+                course_id: course,
+                course_module_id: course_module.id,
+                user_id: user,
+                completion_date: Utc::now(),
+                completion_registration_attempt_date: None,
+                completion_language: "en-US".to_string(),
+                eligible_for_ects: true,
+                email: "test@example.com".to_string(),
+                grade: Some(5),
+                passed: true,
+            },
+            CourseModuleCompletionGranter::User(user),
+        )
+        .await
+        .unwrap();
+        let certificate = generate_and_insert(tx.as_mut(), user, "Example Student", configuration)
+            .await
+            .unwrap();
+
+        crate::course_instances::reset_progress_on_course_instance_for_user(
+            tx.as_mut(),
+            user,
+            course,
+        )
+        .await
+        .unwrap();
+        let certificate = get_by_id(tx.as_mut(), certificate.id).await.unwrap();
+        assert_eq!(certificate.status(), CertificateStatus::Revoked);
+        assert_eq!(certificate.deleted_at, None);
+        assert!(certificate.revocation_reason.is_some());
+    }
+}
@@ -0,0 +1,275 @@
+//! The levels a teacher picked from a [`crate::grading_rubrics`] rubric when grading an answer.
+//! Together they make up a teacher grading decision of the type
+//! [`TeacherDecisionType::Rubric`](crate::teacher_grading_decisions::TeacherDecisionType::Rubric).
+
+use std::collections::HashSet;
+
+use utoipa::ToSchema;
+
+use crate::{grading_rubrics::GradingRubricWithCriteria, prelude::*};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct GradingRubricAssessment {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub teacher_grading_decision_id: Uuid,
+    pub grading_rubric_criterion_id: Uuid,
+    pub grading_rubric_criterion_level_id: Uuid,
+    pub feedback: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewGradingRubricAssessment {
+    pub grading_rubric_criterion_id: Uuid,
+    pub grading_rubric_criterion_level_id: Uuid,
+    pub feedback: Option<String>,
+}
+
+/// Grades an answer with the rubric of the exercise. Every criterion of the rubric must be assessed.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewRubricTeacherGradingDecision {
+    pub user_exercise_state_id: Uuid,
+    pub exercise_id: Uuid,
+    pub assessments: Vec<NewGradingRubricAssessment>,
+    pub justification: Option<String>,
+    pub hidden: bool,
+}
+
+/// A criterion of a rubric with the level the teacher picked for it, as shown to the student.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct FilledGradingRubricCriterion {
+    pub grading_rubric_criterion_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub level_title: String,
+    pub level_description: Option<String>,
+    pub points: f32,
+    pub maximum_points: f32,
+    pub feedback: Option<String>,
+}
+
+/// The points the assessments give, after checking that they assess every criterion of the rubric
+/// exactly once with one of the criterion's own levels.
+pub fn points_from_assessments(
+    rubric: &GradingRubricWithCriteria,
+    assessments: &[NewGradingRubricAssessment],
+) -> ModelResult<f32> {
+    if assessments.len() != rubric.criteria.len() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "Every criterion of the rubric must be assessed exactly once.".to_string()
+        ));
+    }
+    let mut assessed_criterion_ids = HashSet::new();
+    let mut points = 0.0;
+    for assessment in assessments {
+        let criterion = rubric
+            .criteria
+            .iter()
+            .find(|c| c.criterion.id == assessment.grading_rubric_criterion_id)
+            .ok_or_else(|| {
+                model_err!(
+                    PreconditionFailed,
+                    "The criterion does not belong to the rubric of the exercise.".to_string()
+                )
+            })?;
+        if !assessed_criterion_ids.insert(criterion.criterion.id) {
+            return Err(model_err!(
+                PreconditionFailed,
+                "Every criterion of the rubric must be assessed exactly once.".to_string()
+            ));
+        }
+        let level = criterion
+            .levels
+            .iter()
+            .find(|l| l.id == assessment.grading_rubric_criterion_level_id)
+            .ok_or_else(|| {
+                model_err!(
+                    PreconditionFailed,
+                    format!(
+                        "The level does not belong to criterion '{}'.",
+                        criterion.criterion.title
+                    )
+                )
+            })?;
+        points += level.points;
+    }
+    Ok(points)
+}
+
+pub async fn insert_for_teacher_grading_decision(
+    conn: &mut PgConnection,
+    teacher_grading_decision_id: Uuid,
+    assessments: &[NewGradingRubricAssessment],
+) -> ModelResult<Vec<GradingRubricAssessment>> {
+    let criterion_ids = assessments
+        .iter()
+        .map(|a| a.grading_rubric_criterion_id)
+        .collect::<Vec<_>>();
+    let level_ids = assessments
+        .iter()
+        .map(|a| a.grading_rubric_criterion_level_id)
+        .collect::<Vec<_>>();
+    let feedbacks = assessments
+        .iter()
+        .map(|a| a.feedback.clone())
+        .collect::<Vec<_>>();
+    let res = sqlx::query_as!(
+        GradingRubricAssessment,
+        r#"
+INSERT INTO grading_rubric_assessments (
+    teacher_grading_decision_id,
+    grading_rubric_criterion_id,
+    grading_rubric_criterion_level_id,
+    feedback
+  )
+SELECT $1,
+  criterion_id,
+  level_id,
+  feedback
+FROM UNNEST($2::uuid[], $3::uuid[], $4::text[]) AS a(criterion_id, level_id, feedback)
+RETURNING *
+        "#,
+        teacher_grading_decision_id,
+        &criterion_ids,
+        &level_ids,
+        &feedbacks,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The filled rubric of a grading decision in the order the criteria were shown. Empty if the
+/// decision was not made with a rubric.
+pub async fn get_filled_rubric_by_teacher_grading_decision_id(
+    conn: &mut PgConnection,
+    teacher_grading_decision_id: Uuid,
+) -> ModelResult<Vec<FilledGradingRubricCriterion>> {
+    // The levels of a criterion never change after it has been created, so the maximum is taken
+    // over all of them even if the rubric has since been edited.
+    let res = sqlx::query_as!(
+        FilledGradingRubricCriterion,
+        r#"
+SELECT c.id AS grading_rubric_criterion_id,
+  c.title,
+  c.description,
+  l.title AS level_title,
+  l.description AS level_description,
+  l.points,
+  (
+    SELECT MAX(all_levels.points)
+    FROM grading_rubric_criterion_levels all_levels
+    WHERE all_levels.grading_rubric_criterion_id = c.id
+  ) AS "maximum_points!",
+  a.feedback
+FROM grading_rubric_assessments a
+  JOIN grading_rubric_criteria c ON c.id = a.grading_rubric_criterion_id
+  JOIN grading_rubric_criterion_levels l ON l.id = a.grading_rubric_criterion_level_id
+WHERE a.teacher_grading_decision_id = $1
+  AND a.deleted_at IS NULL
+ORDER BY c.order_number
+        "#,
+        teacher_grading_decision_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::grading_rubrics::{
+        GradingRubric, GradingRubricCriterion, GradingRubricCriterionLevel,
+        GradingRubricCriterionWithLevels,
+    };
+
+    fn criterion_with_levels(points: &[f32]) -> GradingRubricCriterionWithLevels {
+        let criterion_id = Uuid::new_v4();
+        GradingRubricCriterionWithLevels {
+            criterion: GradingRubricCriterion {
+                id: criterion_id,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                deleted_at: None,
+                grading_rubric_id: Uuid::nil(),
+                order_number: 0,
+                title: "Criterion".to_string(),
+                description: None,
+            },
+            levels: points
+                .iter()
+                .enumerate()
+                .map(|(order_number, points)| GradingRubricCriterionLevel {
+                    id: Uuid::new_v4(),
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    deleted_at: None,
+                    grading_rubric_criterion_id: criterion_id,
+                    order_number: order_number as i32,
+                    title: "Level".to_string(),
+                    description: None,
+                    points: *points,
+                })
+                .collect(),
+        }
+    }
+
+    fn assess(
+        criterion: &GradingRubricCriterionWithLevels,
+        level: usize,
+    ) -> NewGradingRubricAssessment {
+        NewGradingRubricAssessment {
+            grading_rubric_criterion_id: criterion.criterion.id,
+            grading_rubric_criterion_level_id: criterion.levels[level].id,
+            feedback: None,
+        }
+    }
+
+    #[test]
+    fn sums_the_points_of_the_picked_levels() {
+        let structure = criterion_with_levels(&[0.0, 1.0, 2.0]);
+        let argumentation = criterion_with_levels(&[0.0, 3.0]);
+        let rubric = GradingRubricWithCriteria {
+            rubric: GradingRubric {
+                id: Uuid::nil(),
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                deleted_at: None,
+                exercise_id: Uuid::nil(),
+            },
+            criteria: vec![structure.clone(), argumentation.clone()],
+        };
+
+        assert_eq!(
+            points_from_assessments(&rubric, &[assess(&structure, 1), assess(&argumentation, 1)])
+                .unwrap(),
+            4.0
+        );
+        // A criterion left unassessed
+        assert!(points_from_assessments(&rubric, &[assess(&structure, 2)]).is_err());
+        // The same criterion assessed twice
+        assert!(
+            points_from_assessments(&rubric, &[assess(&structure, 0), assess(&structure, 2)])
+                .is_err()
+        );
+        // A level of another criterion
+        assert!(
+            points_from_assessments(
+                &rubric,
+                &[
+                    assess(&structure, 0),
+                    NewGradingRubricAssessment {
+                        grading_rubric_criterion_id: argumentation.criterion.id,
+                        grading_rubric_criterion_level_id: structure.levels[2].id,
+                        feedback: None,
+                    }
+                ]
+            )
+            .is_err()
+        );
+    }
+}
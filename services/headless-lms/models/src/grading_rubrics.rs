@@ -0,0 +1,418 @@
+//! Rubrics for grading the answers of an exercise manually. See [`crate::grading_rubric_assessments`]
+//! for the gradings made with them.
+
+use itertools::Itertools;
+use utoipa::ToSchema;
+
+use crate::{exercises::Exercise, prelude::*};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct GradingRubric {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub exercise_id: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct GradingRubricCriterion {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub grading_rubric_id: Uuid,
+    pub order_number: i32,
+    pub title: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct GradingRubricCriterionLevel {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub grading_rubric_criterion_id: Uuid,
+    pub order_number: i32,
+    pub title: String,
+    pub description: Option<String>,
+    pub points: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct GradingRubricWithCriteria {
+    pub rubric: GradingRubric,
+    pub criteria: Vec<GradingRubricCriterionWithLevels>,
+}
+
+impl GradingRubricWithCriteria {
+    /// The points an answer gets when it reaches the best level of every criterion.
+    pub fn maximum_points(&self) -> f32 {
+        self.criteria
+            .iter()
+            .map(GradingRubricCriterionWithLevels::maximum_points)
+            .sum()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct GradingRubricCriterionWithLevels {
+    pub criterion: GradingRubricCriterion,
+    pub levels: Vec<GradingRubricCriterionLevel>,
+}
+
+impl GradingRubricCriterionWithLevels {
+    pub fn maximum_points(&self) -> f32 {
+        self.levels
+            .iter()
+            .map(|level| level.points)
+            .fold(0.0, f32::max)
+    }
+}
+
+/// Replaces the whole rubric of an exercise. The criteria and levels are given in the order they
+/// are shown in.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewGradingRubric {
+    pub criteria: Vec<NewGradingRubricCriterion>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewGradingRubricCriterion {
+    pub title: String,
+    pub description: Option<String>,
+    pub levels: Vec<NewGradingRubricCriterionLevel>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewGradingRubricCriterionLevel {
+    pub title: String,
+    pub description: Option<String>,
+    pub points: f32,
+}
+
+fn validate(new_rubric: &NewGradingRubric, score_maximum: i32) -> ModelResult<()> {
+    if new_rubric.criteria.is_empty() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "A rubric must have at least one criterion.".to_string()
+        ));
+    }
+    let mut maximum_points = 0.0;
+    for criterion in &new_rubric.criteria {
+        if criterion.levels.is_empty() {
+            return Err(model_err!(
+                PreconditionFailed,
+                format!(
+                    "Criterion '{}' must have at least one level.",
+                    criterion.title
+                )
+            ));
+        }
+        if criterion
+            .levels
+            .iter()
+            .any(|level| !level.points.is_finite() || level.points < 0.0)
+        {
+            return Err(model_err!(
+                PreconditionFailed,
+                format!(
+                    "The levels of criterion '{}' cannot give negative points.",
+                    criterion.title
+                )
+            ));
+        }
+        maximum_points += criterion
+            .levels
+            .iter()
+            .map(|level| level.points)
+            .fold(0.0, f32::max);
+    }
+    if maximum_points > score_maximum as f32 {
+        return Err(model_err!(
+            PreconditionFailed,
+            format!(
+                "The rubric gives up to {} points, but the exercise is worth only {} points.",
+                maximum_points, score_maximum
+            )
+        ));
+    }
+    Ok(())
+}
+
+/// Saves the rubric of the exercise. If the exercise already has a rubric, its criteria and levels
+/// are replaced, and the gradings made with the old ones keep referring to them.
+pub async fn upsert_for_exercise(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    new_rubric: &NewGradingRubric,
+) -> ModelResult<GradingRubricWithCriteria> {
+    validate(new_rubric, exercise.score_maximum)?;
+    let mut tx = conn.begin().await?;
+    let rubric = sqlx::query_as!(
+        GradingRubric,
+        r#"
+INSERT INTO grading_rubrics (exercise_id)
+VALUES ($1) ON CONFLICT (exercise_id)
+WHERE deleted_at IS NULL DO
+UPDATE
+SET updated_at = now()
+RETURNING *
+        "#,
+        exercise.id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+UPDATE grading_rubric_criterion_levels
+SET deleted_at = now()
+WHERE grading_rubric_criterion_id IN (
+    SELECT id
+    FROM grading_rubric_criteria
+    WHERE grading_rubric_id = $1
+  )
+  AND deleted_at IS NULL
+        "#,
+        rubric.id,
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        r#"
+UPDATE grading_rubric_criteria
+SET deleted_at = now()
+WHERE grading_rubric_id = $1
+  AND deleted_at IS NULL
+        "#,
+        rubric.id,
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    for (criterion_order_number, new_criterion) in new_rubric.criteria.iter().enumerate() {
+        let criterion = sqlx::query_as!(
+            GradingRubricCriterion,
+            r#"
+INSERT INTO grading_rubric_criteria (
+    grading_rubric_id,
+    order_number,
+    title,
+    description
+  )
+VALUES ($1, $2, $3, $4)
+RETURNING *
+            "#,
+            rubric.id,
+            criterion_order_number as i32,
+            new_criterion.title,
+            new_criterion.description,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        for (level_order_number, new_level) in new_criterion.levels.iter().enumerate() {
+            sqlx::query!(
+                r#"
+INSERT INTO grading_rubric_criterion_levels (
+    grading_rubric_criterion_id,
+    order_number,
+    title,
+    description,
+    points
+  )
+VALUES ($1, $2, $3, $4, $5)
+                "#,
+                criterion.id,
+                level_order_number as i32,
+                new_level.title,
+                new_level.description,
+                new_level.points,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    let res = get_by_exercise_id(&mut tx, exercise.id)
+        .await?
+        .ok_or_else(|| model_err!(NotFound, "Grading rubric not found.".to_string()))?;
+    tx.commit().await?;
+    Ok(res)
+}
+
+/// None if the exercise is not graded with a rubric.
+pub async fn get_by_exercise_id(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+) -> ModelResult<Option<GradingRubricWithCriteria>> {
+    let rubric = sqlx::query_as!(
+        GradingRubric,
+        r#"
+SELECT *
+FROM grading_rubrics
+WHERE exercise_id = $1
+  AND deleted_at IS NULL
+        "#,
+        exercise_id,
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some(rubric) = rubric else {
+        return Ok(None);
+    };
+    let criteria = sqlx::query_as!(
+        GradingRubricCriterion,
+        r#"
+SELECT *
+FROM grading_rubric_criteria
+WHERE grading_rubric_id = $1
+  AND deleted_at IS NULL
+ORDER BY order_number
+        "#,
+        rubric.id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let criterion_ids = criteria.iter().map(|c| c.id).collect::<Vec<_>>();
+    let mut levels = sqlx::query_as!(
+        GradingRubricCriterionLevel,
+        r#"
+SELECT *
+FROM grading_rubric_criterion_levels
+WHERE grading_rubric_criterion_id = ANY($1)
+  AND deleted_at IS NULL
+ORDER BY order_number
+        "#,
+        &criterion_ids,
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .into_group_map_by(|level| level.grading_rubric_criterion_id);
+    let criteria = criteria
+        .into_iter()
+        .map(|criterion| GradingRubricCriterionWithLevels {
+            levels: levels.remove(&criterion.id).unwrap_or_default(),
+            criterion,
+        })
+        .collect();
+    Ok(Some(GradingRubricWithCriteria { rubric, criteria }))
+}
+
+/// Stops grading the exercise with a rubric. Gradings already made with it are kept.
+pub async fn delete_for_exercise(conn: &mut PgConnection, exercise_id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+UPDATE grading_rubrics
+SET deleted_at = now()
+WHERE exercise_id = $1
+  AND deleted_at IS NULL
+        "#,
+        exercise_id,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helper::*;
+
+    fn level(points: f32) -> NewGradingRubricCriterionLevel {
+        NewGradingRubricCriterionLevel {
+            title: format!("{points} points"),
+            description: None,
+            points,
+        }
+    }
+
+    fn criterion(
+        title: &str,
+        levels: Vec<NewGradingRubricCriterionLevel>,
+    ) -> NewGradingRubricCriterion {
+        NewGradingRubricCriterion {
+            title: title.to_string(),
+            description: None,
+            levels,
+        }
+    }
+
+    #[test]
+    fn validates_the_rubric() {
+        let rubric = NewGradingRubric {
+            criteria: vec![
+                criterion("Structure", vec![level(0.0), level(2.0)]),
+                criterion("Argumentation", vec![level(0.0), level(1.0), level(3.0)]),
+            ],
+        };
+        assert!(validate(&rubric, 5).is_ok());
+        assert!(validate(&rubric, 4).is_err());
+        assert!(validate(&NewGradingRubric { criteria: vec![] }, 5).is_err());
+        assert!(
+            validate(
+                &NewGradingRubric {
+                    criteria: vec![criterion("Structure", vec![])]
+                },
+                5
+            )
+            .is_err()
+        );
+        assert!(
+            validate(
+                &NewGradingRubric {
+                    criteria: vec![criterion("Structure", vec![level(-1.0)])]
+                },
+                5
+            )
+            .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn saving_a_rubric_replaces_its_criteria() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise);
+        let exercise = crate::exercises::get_by_id(tx.as_mut(), exercise)
+            .await
+            .unwrap();
+        let first = upsert_for_exercise(
+            tx.as_mut(),
+            &exercise,
+            &NewGradingRubric {
+                criteria: vec![
+                    criterion("Structure", vec![level(0.0), level(0.5)]),
+                    criterion("Argumentation", vec![level(0.0), level(0.5)]),
+                ],
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.criteria.len(), 2);
+        assert_eq!(first.criteria[1].criterion.title, "Argumentation");
+        assert_eq!(first.maximum_points(), 1.0);
+
+        let second = upsert_for_exercise(
+            tx.as_mut(),
+            &exercise,
+            &NewGradingRubric {
+                criteria: vec![criterion("Overall", vec![level(0.0), level(1.0)])],
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.rubric.id, second.rubric.id);
+        assert_eq!(second.criteria.len(), 1);
+        assert_eq!(second.criteria[0].levels.len(), 2);
+
+        delete_for_exercise(tx.as_mut(), exercise.id).await.unwrap();
+        assert!(
+            get_by_exercise_id(tx.as_mut(), exercise.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        tx.rollback().await;
+    }
+}
@@ -0,0 +1,199 @@
+//! Penalties applied to submissions made after the deadline. See [`crate::late_submission_policies`].
+
+use std::collections::HashMap;
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct LateSubmissionPenalty {
+    pub id: Uuid,
+    pub exercise_slide_submission_id: Uuid,
+    pub late_submission_policy_id: Uuid,
+    pub user_id: Uuid,
+    pub exercise_id: Uuid,
+    /// The deadline that applied to the student when the submission was made.
+    pub deadline: DateTime<Utc>,
+    /// The points of the submission are multiplied by this.
+    pub points_multiplier: f32,
+    /// The points of the submission are capped at this share of the exercise maximum points.
+    pub max_points_ratio: f32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl LateSubmissionPenalty {
+    /// The points left of `score_given` after the penalty.
+    pub fn apply(&self, score_given: f32, score_maximum: i32) -> f32 {
+        apply(
+            self.points_multiplier,
+            self.max_points_ratio,
+            score_given,
+            score_maximum,
+        )
+    }
+}
+
+/// The penalty for a late submission, resolved from the policy that applies to it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewLateSubmissionPenalty {
+    pub late_submission_policy_id: Uuid,
+    pub deadline: DateTime<Utc>,
+    pub points_multiplier: f32,
+    pub max_points_ratio: f32,
+}
+
+fn apply(
+    points_multiplier: f32,
+    max_points_ratio: f32,
+    score_given: f32,
+    score_maximum: i32,
+) -> f32 {
+    (score_given * points_multiplier).min(score_maximum as f32 * max_points_ratio)
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    exercise_slide_submission_id: Uuid,
+    user_id: Uuid,
+    exercise_id: Uuid,
+    new_penalty: &NewLateSubmissionPenalty,
+) -> ModelResult<LateSubmissionPenalty> {
+    let res = sqlx::query_as!(
+        LateSubmissionPenalty,
+        r#"
+INSERT INTO late_submission_penalties (
+    exercise_slide_submission_id,
+    late_submission_policy_id,
+    user_id,
+    exercise_id,
+    deadline,
+    points_multiplier,
+    max_points_ratio
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING *
+        "#,
+        exercise_slide_submission_id,
+        new_penalty.late_submission_policy_id,
+        user_id,
+        exercise_id,
+        new_penalty.deadline,
+        new_penalty.points_multiplier,
+        new_penalty.max_points_ratio,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// None if the submission was not late.
+pub async fn get_by_exercise_slide_submission_id(
+    conn: &mut PgConnection,
+    exercise_slide_submission_id: Uuid,
+) -> ModelResult<Option<LateSubmissionPenalty>> {
+    let res = sqlx::query_as!(
+        LateSubmissionPenalty,
+        r#"
+SELECT *
+FROM late_submission_penalties
+WHERE exercise_slide_submission_id = $1
+  AND deleted_at IS NULL
+        "#,
+        exercise_slide_submission_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The penalties of the given submissions, keyed by the submission id. Submissions that were not
+/// late are missing from the map.
+pub async fn get_by_exercise_slide_submission_ids(
+    conn: &mut PgConnection,
+    exercise_slide_submission_ids: &[Uuid],
+) -> ModelResult<HashMap<Uuid, LateSubmissionPenalty>> {
+    let res = sqlx::query_as!(
+        LateSubmissionPenalty,
+        r#"
+SELECT *
+FROM late_submission_penalties
+WHERE exercise_slide_submission_id = ANY($1)
+  AND deleted_at IS NULL
+        "#,
+        exercise_slide_submission_ids,
+    )
+    .fetch_all(conn)
+    .await?
+    .into_iter()
+    .map(|penalty| (penalty.exercise_slide_submission_id, penalty))
+    .collect();
+    Ok(res)
+}
+
+/// The penalty of the user's latest submission to the exercise. None if the latest submission was
+/// not late.
+pub async fn get_for_latest_exercise_slide_submission(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    exercise_id: Uuid,
+) -> ModelResult<Option<LateSubmissionPenalty>> {
+    let res = sqlx::query_as!(
+        LateSubmissionPenalty,
+        r#"
+SELECT lsp.*
+FROM late_submission_penalties lsp
+WHERE lsp.exercise_slide_submission_id = (
+    SELECT ess.id
+    FROM exercise_slide_submissions ess
+    WHERE ess.user_id = $1
+      AND ess.exercise_id = $2
+      AND ess.deleted_at IS NULL
+    ORDER BY ess.created_at DESC
+    LIMIT 1
+  )
+  AND lsp.deleted_at IS NULL
+        "#,
+        user_id,
+        exercise_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Every penalised submission to the exercise, newest first.
+pub async fn get_by_exercise_id(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+) -> ModelResult<Vec<LateSubmissionPenalty>> {
+    let res = sqlx::query_as!(
+        LateSubmissionPenalty,
+        r#"
+SELECT *
+FROM late_submission_penalties
+WHERE exercise_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at DESC
+        "#,
+        exercise_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn applies_both_the_multiplier_and_the_cap() {
+        assert_eq!(apply(0.5, 1.0, 4.0, 4), 2.0);
+        assert_eq!(apply(1.0, 0.25, 4.0, 4), 1.0);
+        assert_eq!(apply(1.0, 0.25, 0.5, 4), 0.5);
+        assert_eq!(apply(0.5, 0.25, 4.0, 4), 1.0);
+    }
+}
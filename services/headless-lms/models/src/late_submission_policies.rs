@@ -0,0 +1,383 @@
+//! Policies for accepting submissions after the deadline with reduced points.
+//!
+//! A course can have a default policy and each exercise can override it. The policy that applies
+//! to a submission is resolved when the submission is made, and the resulting penalty is stored in
+//! [`crate::late_submission_penalties`] so that it can be applied every time the submission is
+//! graded.
+
+use chrono::Duration;
+use utoipa::ToSchema;
+
+use crate::{
+    deadline_exceptions, exercises::Exercise, late_submission_penalties::NewLateSubmissionPenalty,
+    prelude::*,
+};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "late_submission_penalty_type", rename_all = "kebab-case")]
+pub enum LateSubmissionPenaltyType {
+    /// The penalty grows linearly from zero at the deadline to `penalty_percent` at the end of
+    /// the late window.
+    LinearDecay,
+    /// `penalty_percent` is deducted for every started day after the deadline.
+    PercentPerDay,
+    /// A late submission can get at most `100 - penalty_percent` percent of the maximum points.
+    HardCap,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct LateSubmissionPolicy {
+    pub id: Uuid,
+    pub course_id: Uuid,
+    /// None for the course default.
+    pub exercise_id: Option<Uuid>,
+    pub penalty_type: LateSubmissionPenaltyType,
+    /// Zero means that late submissions are not accepted.
+    pub late_window_hours: i32,
+    pub penalty_percent: f32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl LateSubmissionPolicy {
+    /// The penalty for a submission made at `submitted_at`, or `None` if the late window had
+    /// already closed by then.
+    pub fn penalty_for(
+        &self,
+        deadline: DateTime<Utc>,
+        submitted_at: DateTime<Utc>,
+    ) -> Option<NewLateSubmissionPenalty> {
+        let late_by = (submitted_at - deadline).max(Duration::zero());
+        let late_window = Duration::hours(i64::from(self.late_window_hours));
+        if late_by >= late_window {
+            return None;
+        }
+        let penalty = self.penalty_percent / 100.0;
+        let (points_multiplier, max_points_ratio) = match self.penalty_type {
+            LateSubmissionPenaltyType::LinearDecay => {
+                let share_of_window_used =
+                    late_by.num_seconds() as f32 / late_window.num_seconds() as f32;
+                (1.0 - penalty * share_of_window_used, 1.0)
+            }
+            LateSubmissionPenaltyType::PercentPerDay => {
+                let started_days = (late_by.num_seconds() / Duration::days(1).num_seconds()) + 1;
+                (1.0 - penalty * started_days as f32, 1.0)
+            }
+            LateSubmissionPenaltyType::HardCap => (1.0, 1.0 - penalty),
+        };
+        Some(NewLateSubmissionPenalty {
+            late_submission_policy_id: self.id,
+            deadline,
+            points_multiplier: points_multiplier.clamp(0.0, 1.0),
+            max_points_ratio: max_points_ratio.clamp(0.0, 1.0),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewLateSubmissionPolicy {
+    /// None to set the course default.
+    pub exercise_id: Option<Uuid>,
+    pub penalty_type: LateSubmissionPenaltyType,
+    pub late_window_hours: i32,
+    pub penalty_percent: f32,
+}
+
+/// Sets the course default policy, or the override for an exercise, replacing the previous one.
+///
+/// The caller is expected to have checked that the exercise belongs to the course.
+pub async fn upsert(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    new_policy: &NewLateSubmissionPolicy,
+) -> ModelResult<LateSubmissionPolicy> {
+    if new_policy.late_window_hours < 0 {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The late window cannot be negative.".to_string()
+        ));
+    }
+    if !(0.0..=100.0).contains(&new_policy.penalty_percent) {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The penalty must be between 0 and 100 percent.".to_string()
+        ));
+    }
+    let res = sqlx::query_as!(
+        LateSubmissionPolicy,
+        r#"
+INSERT INTO late_submission_policies (
+    course_id,
+    exercise_id,
+    penalty_type,
+    late_window_hours,
+    penalty_percent
+  )
+VALUES ($1, $2, $3, $4, $5) ON CONFLICT (course_id, exercise_id)
+WHERE deleted_at IS NULL DO
+UPDATE
+SET penalty_type = EXCLUDED.penalty_type,
+  late_window_hours = EXCLUDED.late_window_hours,
+  penalty_percent = EXCLUDED.penalty_percent
+RETURNING *
+        "#,
+        course_id,
+        new_policy.exercise_id,
+        new_policy.penalty_type as _,
+        new_policy.late_window_hours,
+        new_policy.penalty_percent,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<LateSubmissionPolicy> {
+    let res = sqlx::query_as!(
+        LateSubmissionPolicy,
+        r#"
+SELECT *
+FROM late_submission_policies
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The course default first, followed by the exercise overrides.
+pub async fn get_by_course_id(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+) -> ModelResult<Vec<LateSubmissionPolicy>> {
+    let res = sqlx::query_as!(
+        LateSubmissionPolicy,
+        r#"
+SELECT *
+FROM late_submission_policies
+WHERE course_id = $1
+  AND deleted_at IS NULL
+ORDER BY exercise_id IS NOT NULL,
+  created_at
+        "#,
+        course_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The override of the exercise if it has one, otherwise the default of its course. Exam exercises
+/// never have a policy.
+pub async fn get_for_exercise(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+) -> ModelResult<Option<LateSubmissionPolicy>> {
+    let Some(course_id) = exercise.course_id else {
+        return Ok(None);
+    };
+    let res = sqlx::query_as!(
+        LateSubmissionPolicy,
+        r#"
+SELECT *
+FROM late_submission_policies
+WHERE course_id = $1
+  AND (
+    exercise_id = $2
+    OR exercise_id IS NULL
+  )
+  AND deleted_at IS NULL
+ORDER BY exercise_id IS NOT NULL DESC
+LIMIT 1
+        "#,
+        course_id,
+        exercise.id,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn delete(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+UPDATE late_submission_policies
+SET deleted_at = now()
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Checks the deadline that applies to the user for the exercise, deadline exceptions included.
+///
+/// Returns `None` if the deadline has not passed, and the penalty to record for the submission if
+/// it has but the late window of the exercise's policy is still open. Otherwise the submission is
+/// rejected.
+pub async fn check_submission_deadline(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    user_id: Uuid,
+) -> ModelResult<Option<NewLateSubmissionPenalty>> {
+    let Some(deadline) =
+        deadline_exceptions::get_effective_exercise_deadline(conn, exercise, Some(user_id)).await?
+    else {
+        return Ok(None);
+    };
+    let now = Utc::now();
+    if !deadline_exceptions::deadline_has_passed(deadline, now) {
+        return Ok(None);
+    }
+    get_for_exercise(conn, exercise)
+        .await?
+        .and_then(|policy| policy.penalty_for(deadline, now))
+        .map(Some)
+        .ok_or_else(|| model_err!(PreconditionFailed, "Exercise deadline passed.".to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helper::*;
+
+    fn policy(
+        penalty_type: LateSubmissionPenaltyType,
+        late_window_hours: i32,
+        penalty_percent: f32,
+    ) -> LateSubmissionPolicy {
+        LateSubmissionPolicy {
+            id: Uuid::new_v4(),
+            course_id: Uuid::new_v4(),
+            exercise_id: None,
+            penalty_type,
+            late_window_hours,
+            penalty_percent,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn linear_decay_reaches_the_full_penalty_at_the_end_of_the_window() {
+        let deadline = Utc::now();
+        let policy = policy(LateSubmissionPenaltyType::LinearDecay, 10, 50.0);
+        let halfway = policy
+            .penalty_for(deadline, deadline + Duration::hours(5))
+            .unwrap();
+        assert_eq!(halfway.points_multiplier, 0.75);
+        assert_eq!(halfway.max_points_ratio, 1.0);
+        assert!(
+            policy
+                .penalty_for(deadline, deadline + Duration::hours(10))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn percent_per_day_counts_started_days() {
+        let deadline = Utc::now();
+        let policy = policy(LateSubmissionPenaltyType::PercentPerDay, 24 * 7, 30.0);
+        let first_day = policy
+            .penalty_for(deadline, deadline + Duration::minutes(1))
+            .unwrap();
+        assert!((first_day.points_multiplier - 0.7).abs() < 0.0001);
+        let fifth_day = policy
+            .penalty_for(deadline, deadline + Duration::days(4) + Duration::hours(1))
+            .unwrap();
+        assert_eq!(fifth_day.points_multiplier, 0.0);
+    }
+
+    #[test]
+    fn hard_cap_only_limits_the_maximum() {
+        let deadline = Utc::now();
+        let penalty = policy(LateSubmissionPenaltyType::HardCap, 48, 40.0)
+            .penalty_for(deadline, deadline + Duration::hours(47))
+            .unwrap();
+        assert_eq!(penalty.points_multiplier, 1.0);
+        assert!((penalty.max_points_ratio - 0.6).abs() < 0.0001);
+    }
+
+    #[test]
+    fn zero_window_rejects_late_submissions() {
+        let deadline = Utc::now();
+        assert!(
+            policy(LateSubmissionPenaltyType::HardCap, 0, 0.0)
+                .penalty_for(deadline, deadline)
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn exercise_override_beats_course_default() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise);
+        let exercise = crate::exercises::get_by_id(tx.as_mut(), exercise)
+            .await
+            .unwrap();
+        assert!(
+            get_for_exercise(tx.as_mut(), &exercise)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let default = upsert(
+            tx.as_mut(),
+            course,
+            &NewLateSubmissionPolicy {
+                exercise_id: None,
+                penalty_type: LateSubmissionPenaltyType::PercentPerDay,
+                late_window_hours: 72,
+                penalty_percent: 10.0,
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_for_exercise(tx.as_mut(), &exercise)
+                .await
+                .unwrap()
+                .unwrap()
+                .id,
+            default.id
+        );
+
+        let new_override = NewLateSubmissionPolicy {
+            exercise_id: Some(exercise.id),
+            penalty_type: LateSubmissionPenaltyType::HardCap,
+            late_window_hours: 24,
+            penalty_percent: 50.0,
+        };
+        let first = upsert(tx.as_mut(), course, &new_override).await.unwrap();
+        let second = upsert(
+            tx.as_mut(),
+            course,
+            &NewLateSubmissionPolicy {
+                late_window_hours: 0,
+                ..new_override
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.id, second.id, "upserting must update the override");
+        let found = get_for_exercise(tx.as_mut(), &exercise)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, first.id);
+        assert_eq!(found.late_window_hours, 0);
+        assert_eq!(
+            get_by_course_id(tx.as_mut(), course).await.unwrap().len(),
+            2
+        );
+        tx.rollback().await;
+    }
+}
@@ -6,11 +6,14 @@ Each submodule corresponds to a database table.
 // we always use --document-private-items, so this warning is moot
 #![allow(rustdoc::private_intra_doc_links)]
 pub mod application_task_default_language_models;
+pub mod certificate_bulk_exports;
 pub mod certificate_configuration_to_requirements;
 pub mod certificate_configurations;
 pub mod certificate_fonts;
+pub mod certificate_text_elements;
 pub mod chapter_lock_action_logs;
 pub mod chapters;
+pub mod chatbot_analytics;
 pub mod chatbot_configurations;
 pub mod chatbot_configurations_models;
 pub mod chatbot_conversation_message_messages;
@@ -21,7 +24,10 @@ pub mod chatbot_conversation_messages;
 pub mod chatbot_conversation_messages_citations;
 pub mod chatbot_conversation_suggested_messages;
 pub mod chatbot_conversations;
+pub mod chatbot_evaluations;
+pub mod chatbot_page_chunks;
 pub mod chatbot_page_sync_statuses;
+pub mod chatbot_tutoring_exercises;
 pub mod cheating_confirmation_grade_snapshots;
 pub mod cms_ai;
 pub mod code_giveaway_codes;
@@ -45,6 +51,7 @@ pub mod course_module_suotar_realisations;
 pub mod course_modules;
 pub mod course_page_markdown_content;
 pub mod course_prerequisites;
+pub mod course_xapi_opt_ins;
 pub mod courses;
 pub mod credit_registration_account_linking_emails;
 pub mod credit_registration_admin_actions;
@@ -52,12 +59,16 @@ pub mod credit_registration_daily_snapshots;
 pub mod credit_registration_events;
 pub mod credit_registration_phase_state;
 pub mod credit_registrations;
+pub mod deadline_exceptions;
 pub mod email_deliveries;
 pub mod email_templates;
 pub mod email_verification_tokens;
 pub mod ended_processed_exams;
 pub mod error;
 pub mod errors;
+pub mod exam_accommodations;
+pub mod exam_enrollment_events;
+pub mod exam_question_pools;
 pub mod exams;
 pub mod exercise_language_groups;
 pub mod exercise_repositories;
@@ -79,8 +90,18 @@ pub mod file_uploads;
 pub mod flagged_answers;
 pub mod generated_certificates;
 pub mod glossary;
+pub mod grading_rubric_assessments;
+pub mod grading_rubrics;
 pub mod join_code_uses;
+pub mod late_submission_penalties;
+pub mod late_submission_policies;
 pub mod library;
+pub mod lti_deep_linking_requests;
+pub mod lti_login_states;
+pub mod lti_platforms;
+pub mod lti_resource_link_users;
+pub mod lti_resource_links;
+pub mod lti_users;
 pub mod marketing_consents;
 pub mod material_references;
 pub mod oauth_access_token;
@@ -110,7 +131,11 @@ pub mod peer_or_self_review_configs;
 pub mod peer_or_self_review_question_submissions;
 pub mod peer_or_self_review_questions;
 pub mod peer_or_self_review_submissions;
+pub mod peer_review_calibration_answers;
+pub mod peer_review_calibration_submissions;
+pub mod peer_review_disputes;
 pub mod peer_review_queue_entries;
+pub mod peer_reviewer_reliabilities;
 pub mod pending_roles;
 pub mod playground_examples;
 pub mod privacy_link;
@@ -143,6 +168,9 @@ pub mod user_passwords;
 pub mod user_research_consents;
 pub mod users;
 pub mod verified_student_numbers;
+pub mod webhook_deliveries;
+pub mod webhook_subscriptions;
+pub mod xapi_statements;
 
 pub mod prelude;
 #[cfg(test)]
@@ -675,7 +675,10 @@ ins_exercises AS (
       needs_peer_review,
       use_course_default_peer_or_self_review_config,
       needs_self_review,
-      teacher_reviews_answer_after_locking
+      teacher_reviews_answer_after_locking,
+      scoring_strategy,
+      scoring_strategy_attempt_count,
+      scoring_strategy_penalty_percent
     )
   SELECT uuid_generate_v5($1, src.id::text),
     $1,
@@ -692,7 +695,10 @@ ins_exercises AS (
     src.needs_peer_review,
     src.use_course_default_peer_or_self_review_config,
     src.needs_self_review,
-    src.teacher_reviews_answer_after_locking
+    src.teacher_reviews_answer_after_locking,
+    src.scoring_strategy,
+    src.scoring_strategy_attempt_count,
+    src.scoring_strategy_penalty_percent
   FROM src
   RETURNING id,
     copied_from
@@ -747,7 +753,10 @@ INSERT INTO exercises (
     needs_peer_review,
     use_course_default_peer_or_self_review_config,
     needs_self_review,
-    teacher_reviews_answer_after_locking
+    teacher_reviews_answer_after_locking,
+    scoring_strategy,
+    scoring_strategy_attempt_count,
+    scoring_strategy_penalty_percent
   )
 SELECT uuid_generate_v5($1, id::text),
   $1,
@@ -763,7 +772,10 @@ SELECT uuid_generate_v5($1, id::text),
   needs_peer_review,
   use_course_default_peer_or_self_review_config,
   needs_self_review,
-  teacher_reviews_answer_after_locking
+  teacher_reviews_answer_after_locking,
+  scoring_strategy,
+  scoring_strategy_attempt_count,
+  scoring_strategy_penalty_percent
 FROM exercises
 WHERE exam_id = $2
   AND deleted_at IS NULL
@@ -921,7 +933,9 @@ INSERT INTO peer_or_self_review_configs (
     accepting_threshold,
     manual_review_cutoff_in_days,
     points_are_all_or_nothing,
-    review_instructions
+    review_instructions,
+    calibration_reviews_to_give,
+    manual_review_reliability_threshold
   )
 SELECT uuid_generate_v5($1, posrc.id::text),
   $1,
@@ -932,7 +946,9 @@ SELECT uuid_generate_v5($1, posrc.id::text),
   posrc.accepting_threshold,
   posrc.manual_review_cutoff_in_days,
   posrc.points_are_all_or_nothing,
-  posrc.review_instructions
+  posrc.review_instructions,
+  posrc.calibration_reviews_to_give,
+  posrc.manual_review_reliability_threshold
 FROM peer_or_self_review_configs posrc
   LEFT JOIN exercises e ON (e.id = posrc.exercise_id)
 WHERE posrc.course_id = $2
@@ -1085,7 +1101,9 @@ INSERT INTO certificate_configurations (
     overlay_svg_file_upload_id,
     overlay_svg_path,
     paper_size,
-    render_certificate_grade
+    render_certificate_grade,
+    pdf_enabled,
+    pdf_transcript_pages_enabled
   )
 SELECT uuid_generate_v5($1, id::text),
   background_svg_file_upload_id,
@@ -1114,7 +1132,9 @@ SELECT uuid_generate_v5($1, id::text),
   overlay_svg_file_upload_id,
   overlay_svg_path,
   paper_size,
-  render_certificate_grade
+  render_certificate_grade,
+  pdf_enabled,
+  pdf_transcript_pages_enabled
 FROM certificate_configurations
 WHERE id IN (
     SELECT certificate_configuration_id
@@ -1156,6 +1176,47 @@ WHERE cm.course_id = $2
     .execute(&mut *tx)
     .await?;
 
+    sqlx::query!(
+        "
+INSERT INTO certificate_text_elements (
+    id,
+    certificate_configuration_id,
+    order_number,
+    template,
+    x_pos,
+    y_pos,
+    font_size,
+    text_color,
+    text_anchor,
+    font_family
+  )
+SELECT uuid_generate_v5($1, cte.id::text),
+  uuid_generate_v5($1, cte.certificate_configuration_id::text),
+  cte.order_number,
+  cte.template,
+  cte.x_pos,
+  cte.y_pos,
+  cte.font_size,
+  cte.text_color,
+  cte.text_anchor,
+  cte.font_family
+FROM certificate_text_elements cte
+WHERE cte.certificate_configuration_id IN (
+    SELECT certificate_configuration_id
+    FROM certificate_configuration_to_requirements cctr
+      JOIN course_modules cm ON cctr.course_module_id = cm.id
+    WHERE cm.course_id = $2
+      AND cctr.deleted_at IS NULL
+      AND cm.deleted_at IS NULL
+  )
+  AND cte.deleted_at IS NULL;
+        ",
+        new_course_id,
+        old_course_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
     Ok(())
 }
 
@@ -1186,7 +1247,8 @@ INSERT INTO chatbot_configurations (
     default_chatbot,
     enabled_to_students,
     model_id,
-    use_tools
+    use_tools,
+    use_builtin_search
   )
 SELECT
   uuid_generate_v5($1, id::text),
@@ -1208,7 +1270,8 @@ SELECT
   default_chatbot,
   enabled_to_students,
   model_id,
-  use_tools
+  use_tools,
+  use_builtin_search
 FROM chatbot_configurations
 WHERE course_id = $2
   AND deleted_at IS NULL;
@@ -1631,6 +1694,8 @@ mod tests {
             certificate_grade_font_size: None,
             certificate_grade_text_color: None,
             certificate_grade_text_anchor: None,
+            pdf_enabled: false,
+            pdf_transcript_pages_enabled: false,
         };
         crate::certificate_configurations::insert(conn, &configuration)
             .await
@@ -1660,6 +1725,23 @@ mod tests {
         )
         .await
         .unwrap();
+        crate::certificate_text_elements::replace_all(
+            tx.as_mut(),
+            configuration,
+            &[
+                crate::certificate_text_elements::NewCertificateTextElement {
+                    template: "{course_name}".to_string(),
+                    x_pos: "50%".to_string(),
+                    y_pos: "30%".to_string(),
+                    font_size: "100px".to_string(),
+                    text_color: "black".to_string(),
+                    text_anchor: crate::certificate_configurations::CertificateTextAnchor::Middle,
+                    font_family: None,
+                },
+            ],
+        )
+        .await
+        .unwrap();
 
         let new_course = create_new_course(org, "en-NZ".into());
         let copied_course = copy_course(tx.as_mut(), course, &new_course, true, user)
@@ -1678,6 +1760,8 @@ mod tests {
             .unwrap();
         assert_eq!(required_module.course_id, copied_course.id);
         assert_eq!(required_module.copied_from, Some(course_module.id));
+        assert_eq!(copied[0].text_elements.len(), 1);
+        assert_eq!(copied[0].text_elements[0].template, "{course_name}");
     }
 
     /// Older data can hold requirements pointing at a deleted configuration.
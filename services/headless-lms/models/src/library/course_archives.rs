@@ -0,0 +1,1381 @@
+/*!
+Portable course archives for moving a course between deployments.
+
+[`copying`](super::copying) copies a course inside one database. An archive is a self-contained
+snapshot of the course content instead: the rows of the content tables in the shape of the tables,
+without the timestamps. Importing creates a new course from it. The ids are remapped with
+`uuid_generate_v5(new_course_id, old_id::text)` like when copying, so every reference inside the
+archive, including the ones in the page contents, still points to the right place after the import.
+An archive whose rows refer to rows outside of it is rejected, as the archive could otherwise be
+used to attach content to other courses.
+
+Files referenced by the content are listed in [`CourseArchive::files`]. Storing their contents is
+up to the caller, since the database does not have them.
+*/
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+use crate::course_instances::{self, NewCourseInstance};
+use crate::course_language_groups;
+use crate::courses::{Course, NewCourse};
+use crate::prelude::*;
+
+/// Bumped whenever the archive contents change in a way older importers cannot read.
+pub const COURSE_ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// File store paths of course, exam and organization files, optionally preceded by the download url
+/// prefix. For example `https://example.com/api/v0/files/course/{id}/images/{random}.png`.
+static FILE_PATH_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?P<url>https?://[^\s"'<>()]+?/api/v0/files/)?(?P<path>(?:course|exam|organization)/[0-9a-fA-F-]{36}/(?:images|files|audios)/[A-Za-z0-9_-]+(?:\.[A-Za-z0-9]+)?)"#,
+    )
+    .expect("Invalid file path regex.")
+});
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CourseArchive {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// The `courses` row. Only the content settings are used when importing.
+    pub course: Value,
+    pub course_modules: Vec<Value>,
+    pub chapters: Vec<Value>,
+    pub pages: Vec<Value>,
+    pub exercises: Vec<Value>,
+    pub exercise_slides: Vec<Value>,
+    pub exercise_tasks: Vec<Value>,
+    pub peer_or_self_review_configs: Vec<Value>,
+    pub peer_or_self_review_questions: Vec<Value>,
+    pub glossary: Vec<Value>,
+    pub material_references: Vec<Value>,
+    pub certificate_configurations: Vec<Value>,
+    pub certificate_configuration_requirements: Vec<Value>,
+    pub certificate_text_elements: Vec<Value>,
+    pub files: Vec<ArchivedFile>,
+}
+
+/// A file referenced by the course content. `path` is the path in the file store of the exporting
+/// deployment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedFile {
+    pub path: String,
+    pub name: String,
+    pub mime: String,
+}
+
+/// Where an archived file was stored in the importing deployment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedFile {
+    pub path: String,
+    pub url: String,
+    pub file_upload_id: Uuid,
+}
+
+impl CourseArchive {
+    /// The exercise services the exercise tasks need, deduplicated and sorted.
+    pub fn exercise_types(&self) -> Vec<String> {
+        self.exercise_tasks
+            .iter()
+            .filter_map(|task| task["exercise_type"].as_str())
+            .map(str::to_string)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    fn rows_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        std::iter::once(&mut self.course)
+            .chain(self.course_modules.iter_mut())
+            .chain(self.chapters.iter_mut())
+            .chain(self.pages.iter_mut())
+            .chain(self.exercises.iter_mut())
+            .chain(self.exercise_slides.iter_mut())
+            .chain(self.exercise_tasks.iter_mut())
+            .chain(self.peer_or_self_review_configs.iter_mut())
+            .chain(self.peer_or_self_review_questions.iter_mut())
+            .chain(self.glossary.iter_mut())
+            .chain(self.material_references.iter_mut())
+            .chain(self.certificate_configurations.iter_mut())
+            .chain(self.certificate_configuration_requirements.iter_mut())
+            .chain(self.certificate_text_elements.iter_mut())
+    }
+}
+
+pub async fn export_course(conn: &mut PgConnection, course_id: Uuid) -> ModelResult<CourseArchive> {
+    let res = sqlx::query!(
+        r#"
+SELECT to_jsonb(c) - ARRAY ['created_at', 'updated_at', 'deleted_at', 'copied_from', 'join_code'] AS "course!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(cm) - ARRAY ['created_at', 'updated_at', 'deleted_at', 'copied_from']
+          ORDER BY cm.order_number
+        ),
+        '[]'
+      )
+    FROM course_modules cm
+    WHERE cm.course_id = c.id
+      AND cm.deleted_at IS NULL
+  ) AS "course_modules!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(ch) - ARRAY ['created_at', 'updated_at', 'deleted_at', 'copied_from']
+          ORDER BY ch.chapter_number
+        ),
+        '[]'
+      )
+    FROM chapters ch
+    WHERE ch.course_id = c.id
+      AND ch.deleted_at IS NULL
+  ) AS "chapters!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(p) - ARRAY ['created_at', 'updated_at', 'deleted_at', 'copied_from', 'content_search', 'content_search_original_text']
+          ORDER BY p.order_number
+        ),
+        '[]'
+      )
+    FROM pages p
+    WHERE p.course_id = c.id
+      AND p.deleted_at IS NULL
+  ) AS "pages!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(e) - ARRAY ['created_at', 'updated_at', 'deleted_at', 'copied_from']
+          ORDER BY e.order_number
+        ),
+        '[]'
+      )
+    FROM exercises e
+    WHERE e.course_id = c.id
+      AND e.deleted_at IS NULL
+  ) AS "exercises!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(s) - ARRAY ['created_at', 'updated_at', 'deleted_at']
+          ORDER BY s.order_number
+        ),
+        '[]'
+      )
+    FROM exercise_slides s
+      JOIN exercises e ON (e.id = s.exercise_id)
+    WHERE e.course_id = c.id
+      AND e.deleted_at IS NULL
+      AND s.deleted_at IS NULL
+  ) AS "exercise_slides!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(t) - ARRAY ['created_at', 'updated_at', 'deleted_at', 'copied_from']
+          ORDER BY t.order_number
+        ),
+        '[]'
+      )
+    FROM exercise_tasks t
+      JOIN exercise_slides s ON (s.id = t.exercise_slide_id)
+      JOIN exercises e ON (e.id = s.exercise_id)
+    WHERE e.course_id = c.id
+      AND e.deleted_at IS NULL
+      AND s.deleted_at IS NULL
+      AND t.deleted_at IS NULL
+  ) AS "exercise_tasks!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(posrc) - ARRAY ['created_at', 'updated_at', 'deleted_at']
+        ),
+        '[]'
+      )
+    FROM peer_or_self_review_configs posrc
+      LEFT JOIN exercises e ON (e.id = posrc.exercise_id)
+    WHERE posrc.course_id = c.id
+      AND posrc.deleted_at IS NULL
+      AND e.deleted_at IS NULL
+  ) AS "peer_or_self_review_configs!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(q) - ARRAY ['created_at', 'updated_at', 'deleted_at']
+          ORDER BY q.order_number
+        ),
+        '[]'
+      )
+    FROM peer_or_self_review_questions q
+      JOIN peer_or_self_review_configs posrc ON (posrc.id = q.peer_or_self_review_config_id)
+      LEFT JOIN exercises e ON (e.id = posrc.exercise_id)
+    WHERE posrc.course_id = c.id
+      AND q.deleted_at IS NULL
+      AND posrc.deleted_at IS NULL
+      AND e.deleted_at IS NULL
+  ) AS "peer_or_self_review_questions!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(g) - ARRAY ['created_at', 'updated_at', 'deleted_at']
+          ORDER BY g.term
+        ),
+        '[]'
+      )
+    FROM glossary g
+    WHERE g.course_id = c.id
+      AND g.deleted_at IS NULL
+  ) AS "glossary!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(mr) - ARRAY ['created_at', 'updated_at', 'deleted_at']
+          ORDER BY mr.citation_key
+        ),
+        '[]'
+      )
+    FROM material_references mr
+    WHERE mr.course_id = c.id
+      AND mr.deleted_at IS NULL
+  ) AS "material_references!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(cc) - ARRAY ['created_at', 'updated_at', 'deleted_at']
+        ),
+        '[]'
+      )
+    FROM certificate_configurations cc
+    WHERE cc.id IN (
+        SELECT cctr.certificate_configuration_id
+        FROM certificate_configuration_to_requirements cctr
+          JOIN course_modules cm ON (cm.id = cctr.course_module_id)
+        WHERE cm.course_id = c.id
+          AND cctr.deleted_at IS NULL
+          AND cm.deleted_at IS NULL
+      )
+      AND cc.deleted_at IS NULL
+  ) AS "certificate_configurations!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(cctr) - ARRAY ['created_at', 'updated_at', 'deleted_at']
+        ),
+        '[]'
+      )
+    FROM certificate_configuration_to_requirements cctr
+      JOIN course_modules cm ON (cm.id = cctr.course_module_id)
+      JOIN certificate_configurations cc ON (cc.id = cctr.certificate_configuration_id)
+    WHERE cm.course_id = c.id
+      AND cctr.deleted_at IS NULL
+      AND cm.deleted_at IS NULL
+      AND cc.deleted_at IS NULL
+  ) AS "certificate_configuration_requirements!",
+  (
+    SELECT COALESCE(
+        jsonb_agg(
+          to_jsonb(cte) - ARRAY ['created_at', 'updated_at', 'deleted_at']
+          ORDER BY cte.order_number
+        ),
+        '[]'
+      )
+    FROM certificate_text_elements cte
+    WHERE cte.certificate_configuration_id IN (
+        SELECT cctr.certificate_configuration_id
+        FROM certificate_configuration_to_requirements cctr
+          JOIN course_modules cm ON (cm.id = cctr.course_module_id)
+        WHERE cm.course_id = c.id
+          AND cctr.deleted_at IS NULL
+          AND cm.deleted_at IS NULL
+      )
+      AND cte.deleted_at IS NULL
+  ) AS "certificate_text_elements!"
+FROM courses c
+WHERE c.id = $1
+  AND c.deleted_at IS NULL
+"#,
+        course_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let mut archive = CourseArchive {
+        format_version: COURSE_ARCHIVE_FORMAT_VERSION,
+        exported_at: Utc::now(),
+        course: res.course,
+        course_modules: from_json_array(res.course_modules)?,
+        chapters: from_json_array(res.chapters)?,
+        pages: from_json_array(res.pages)?,
+        exercises: from_json_array(res.exercises)?,
+        exercise_slides: from_json_array(res.exercise_slides)?,
+        exercise_tasks: from_json_array(res.exercise_tasks)?,
+        peer_or_self_review_configs: from_json_array(res.peer_or_self_review_configs)?,
+        peer_or_self_review_questions: from_json_array(res.peer_or_self_review_questions)?,
+        glossary: from_json_array(res.glossary)?,
+        material_references: from_json_array(res.material_references)?,
+        certificate_configurations: from_json_array(res.certificate_configurations)?,
+        certificate_configuration_requirements: from_json_array(
+            res.certificate_configuration_requirements,
+        )?,
+        certificate_text_elements: from_json_array(res.certificate_text_elements)?,
+        files: vec![],
+    };
+
+    let mut referenced_paths = BTreeSet::new();
+    for row in archive.rows_mut() {
+        collect_file_paths(row, &mut referenced_paths);
+    }
+    let referenced_paths = referenced_paths.into_iter().collect::<Vec<_>>();
+    // Paths that are not in file_uploads are left as they are, there is nothing to archive for them.
+    archive.files = sqlx::query_as!(
+        ArchivedFile,
+        "
+SELECT path,
+  name,
+  mime
+FROM file_uploads
+WHERE path = ANY($1)
+  AND deleted_at IS NULL
+ORDER BY path
+",
+        &referenced_paths
+    )
+    .fetch_all(conn)
+    .await?;
+
+    Ok(archive)
+}
+
+/// Returns the exercise types of the archive that have no exercise service in this deployment.
+pub async fn get_missing_exercise_services(
+    conn: &mut PgConnection,
+    archive: &CourseArchive,
+) -> ModelResult<Vec<String>> {
+    let res = sqlx::query_scalar!(
+        r#"
+SELECT exercise_type AS "exercise_type!"
+FROM unnest($1::text []) AS exercise_type
+WHERE NOT EXISTS (
+    SELECT 1
+    FROM exercise_services
+    WHERE slug = exercise_type
+      AND deleted_at IS NULL
+  )
+ORDER BY exercise_type
+"#,
+        &archive.exercise_types()
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/**
+Creates a new course with the id `new_course_id` from the archive.
+
+`files` maps the paths in [`CourseArchive::files`] to where the files were stored in this deployment.
+References to files that are not in the map are left as they are.
+*/
+pub async fn import_course(
+    conn: &mut PgConnection,
+    archive: &CourseArchive,
+    new_course_id: Uuid,
+    new_course: &NewCourse,
+    files: &HashMap<String, ImportedFile>,
+) -> ModelResult<Course> {
+    if archive.format_version != COURSE_ARCHIVE_FORMAT_VERSION {
+        return Err(model_err!(
+            PreconditionFailed,
+            format!(
+                "Unsupported course archive format version {}, expected {}.",
+                archive.format_version, COURSE_ARCHIVE_FORMAT_VERSION
+            )
+        ));
+    }
+    check_references(archive)?;
+    let missing_exercise_services = get_missing_exercise_services(conn, archive).await?;
+    if !missing_exercise_services.is_empty() {
+        return Err(model_err!(
+            PreconditionFailed,
+            format!(
+                "No exercise service for exercise types: {}.",
+                missing_exercise_services.join(", ")
+            )
+        ));
+    }
+
+    let mut archive = archive.clone();
+    for configuration in archive.certificate_configurations.iter_mut() {
+        for (path_key, file_upload_id_key) in [
+            ("background_svg_path", "background_svg_file_upload_id"),
+            ("overlay_svg_path", "overlay_svg_file_upload_id"),
+        ] {
+            let Some(path) = configuration[path_key].as_str() else {
+                if !configuration[file_upload_id_key].is_null() {
+                    return Err(model_err!(
+                        PreconditionFailed,
+                        format!(
+                            "The certificate configuration has a {file_upload_id_key} but no {path_key}."
+                        )
+                    ));
+                }
+                continue;
+            };
+            let file = files.get(path).ok_or_else(|| {
+                model_err!(
+                    PreconditionFailed,
+                    format!("The archive is missing the certificate file '{path}'.")
+                )
+            })?;
+            configuration[file_upload_id_key] = Value::String(file.file_upload_id.to_string());
+        }
+    }
+
+    let mut ids = HashMap::new();
+    for row in archive.rows_mut() {
+        if let Some(id) = row["id"].as_str() {
+            let old_id = Uuid::parse_str(id)
+                .map_err(|err| model_err!(InvalidRequest, format!("Invalid id '{id}'."), err))?;
+            ids.insert(
+                id.to_string(),
+                Uuid::new_v5(&new_course_id, old_id.to_string().as_bytes()).to_string(),
+            );
+        }
+    }
+    // The course itself gets the id chosen by the caller.
+    if let Some(old_course_id) = archive.course["id"].as_str() {
+        ids.insert(old_course_id.to_string(), new_course_id.to_string());
+    }
+    let old_slug = archive.course["slug"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let remapper = Remapper {
+        ids: &ids,
+        files,
+        old_course_path: format!("/courses/{old_slug}"),
+        new_course_path: format!("/courses/{}", new_course.slug),
+    };
+    for row in archive.rows_mut() {
+        remapper.remap(row);
+    }
+
+    let mut tx = conn.begin().await?;
+    let course_language_group_id =
+        course_language_groups::insert(&mut tx, PKeyPolicy::Generate, &new_course.slug).await?;
+    let course = sqlx::query_as!(
+        Course,
+        r#"
+INSERT INTO courses (
+    id,
+    name,
+    organization_id,
+    slug,
+    content_search_language,
+    language_code,
+    course_language_group_id,
+    is_draft,
+    is_test_mode,
+    base_module_completion_requires_n_submodule_completions,
+    can_add_chatbot,
+    is_unlisted,
+    is_joinable_by_code_only,
+    join_code,
+    ask_marketing_consent,
+    description,
+    flagged_answers_threshold,
+    flagged_answers_skip_manual_review_and_allow_retry,
+    cheater_detection_enabled,
+    chapter_locking_enabled,
+    ai_policy,
+    course_material_ai_instructions
+  )
+SELECT $1,
+  $2,
+  $3,
+  $4,
+  a.content_search_language,
+  $5,
+  $6,
+  $7,
+  $8,
+  a.base_module_completion_requires_n_submodule_completions,
+  a.can_add_chatbot,
+  $9,
+  $10,
+  $11,
+  $12,
+  $13,
+  a.flagged_answers_threshold,
+  a.flagged_answers_skip_manual_review_and_allow_retry,
+  a.cheater_detection_enabled,
+  a.chapter_locking_enabled,
+  a.ai_policy,
+  a.course_material_ai_instructions
+FROM jsonb_populate_record(NULL::courses, $14) a
+RETURNING id,
+  name,
+  created_at,
+  updated_at,
+  organization_id,
+  deleted_at,
+  slug,
+  content_search_language::text,
+  language_code,
+  copied_from,
+  course_language_group_id,
+  description,
+  is_draft,
+  is_test_mode,
+  base_module_completion_requires_n_submodule_completions,
+  can_add_chatbot,
+  is_unlisted,
+  is_joinable_by_code_only,
+  join_code,
+  ask_marketing_consent,
+  flagged_answers_threshold,
+  flagged_answers_skip_manual_review_and_allow_retry,
+  closed_at,
+  closed_additional_message,
+  closed_course_successor_id,
+  chapter_locking_enabled,
+  cheater_detection_enabled,
+  ai_policy,
+  course_material_ai_instructions
+"#,
+        new_course_id,
+        new_course.name,
+        new_course.organization_id,
+        new_course.slug,
+        new_course.language_code,
+        course_language_group_id,
+        new_course.is_draft,
+        new_course.is_test_mode,
+        new_course.is_unlisted,
+        new_course.is_joinable_by_code_only,
+        new_course.join_code,
+        new_course.ask_marketing_consent,
+        new_course.description,
+        archive.course
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO course_modules (
+    id,
+    course_id,
+    name,
+    order_number,
+    automatic_completion,
+    automatic_completion_number_of_exercises_attempted_treshold,
+    automatic_completion_number_of_points_treshold,
+    automatic_completion_requires_exam,
+    certification_enabled,
+    completion_registration_link_override,
+    ects_credits,
+    enable_registering_completion_to_uh_open_university,
+    uh_course_code
+  )
+SELECT a.id,
+  $1,
+  a.name,
+  a.order_number,
+  a.automatic_completion,
+  a.automatic_completion_number_of_exercises_attempted_treshold,
+  a.automatic_completion_number_of_points_treshold,
+  a.automatic_completion_requires_exam,
+  a.certification_enabled,
+  a.completion_registration_link_override,
+  a.ects_credits,
+  a.enable_registering_completion_to_uh_open_university,
+  a.uh_course_code
+FROM jsonb_populate_recordset(NULL::course_modules, $2) a
+",
+        course.id,
+        Value::Array(archive.course_modules.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // The front pages are set after the pages have been inserted.
+    sqlx::query!(
+        "
+INSERT INTO chapters (
+    id,
+    name,
+    course_id,
+    chapter_number,
+    opens_at,
+    chapter_image_path,
+    course_module_id,
+    color,
+    deadline
+  )
+SELECT a.id,
+  a.name,
+  $1,
+  a.chapter_number,
+  a.opens_at,
+  a.chapter_image_path,
+  a.course_module_id,
+  a.color,
+  a.deadline
+FROM jsonb_populate_recordset(NULL::chapters, $2) a
+",
+        course.id,
+        Value::Array(archive.chapters.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+WITH archived AS (
+  SELECT *
+  FROM jsonb_populate_recordset(NULL::pages, $3)
+),
+inserted_page_language_groups AS (
+  INSERT INTO page_language_groups (id, course_language_group_id)
+  SELECT uuid_generate_v5($2, archived.id::text),
+    $2
+  FROM archived
+)
+INSERT INTO pages (
+    id,
+    course_id,
+    content,
+    url_path,
+    title,
+    chapter_id,
+    order_number,
+    content_search_language,
+    page_language_group_id,
+    hidden
+  )
+SELECT archived.id,
+  $1,
+  archived.content,
+  archived.url_path,
+  archived.title,
+  archived.chapter_id,
+  archived.order_number,
+  archived.content_search_language,
+  uuid_generate_v5($2, archived.id::text),
+  archived.hidden
+FROM archived
+",
+        course.id,
+        course_language_group_id,
+        Value::Array(archive.pages.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+UPDATE chapters
+SET front_page_id = a.front_page_id
+FROM jsonb_populate_recordset(NULL::chapters, $2) a
+WHERE chapters.id = a.id
+  AND chapters.course_id = $1
+",
+        course.id,
+        Value::Array(archive.chapters.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+WITH archived AS (
+  SELECT *
+  FROM jsonb_populate_recordset(NULL::exercises, $3)
+),
+inserted_exercise_language_groups AS (
+  INSERT INTO exercise_language_groups (id, course_language_group_id)
+  SELECT uuid_generate_v5($2, archived.id::text),
+    $2
+  FROM archived
+)
+INSERT INTO exercises (
+    id,
+    course_id,
+    name,
+    deadline,
+    page_id,
+    score_maximum,
+    order_number,
+    chapter_id,
+    exercise_language_group_id,
+    max_tries_per_slide,
+    limit_number_of_tries,
+    needs_peer_review,
+    use_course_default_peer_or_self_review_config,
+    needs_self_review,
+    teacher_reviews_answer_after_locking,
+    scoring_strategy,
+    scoring_strategy_attempt_count,
+    scoring_strategy_penalty_percent
+  )
+SELECT archived.id,
+  $1,
+  archived.name,
+  archived.deadline,
+  archived.page_id,
+  archived.score_maximum,
+  archived.order_number,
+  archived.chapter_id,
+  uuid_generate_v5($2, archived.id::text),
+  archived.max_tries_per_slide,
+  archived.limit_number_of_tries,
+  archived.needs_peer_review,
+  archived.use_course_default_peer_or_self_review_config,
+  archived.needs_self_review,
+  archived.teacher_reviews_answer_after_locking,
+  archived.scoring_strategy,
+  archived.scoring_strategy_attempt_count,
+  archived.scoring_strategy_penalty_percent
+FROM archived
+",
+        course.id,
+        course_language_group_id,
+        Value::Array(archive.exercises.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO exercise_slides (id, exercise_id, order_number)
+SELECT a.id,
+  a.exercise_id,
+  a.order_number
+FROM jsonb_populate_recordset(NULL::exercise_slides, $1) a
+",
+        Value::Array(archive.exercise_slides.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO exercise_tasks (
+    id,
+    exercise_slide_id,
+    exercise_type,
+    assignment,
+    private_spec,
+    public_spec,
+    model_solution_spec,
+    order_number
+  )
+SELECT a.id,
+  a.exercise_slide_id,
+  a.exercise_type,
+  a.assignment,
+  a.private_spec,
+  a.public_spec,
+  a.model_solution_spec,
+  a.order_number
+FROM jsonb_populate_recordset(NULL::exercise_tasks, $1) a
+",
+        Value::Array(archive.exercise_tasks.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO peer_or_self_review_configs (
+    id,
+    course_id,
+    exercise_id,
+    peer_reviews_to_give,
+    peer_reviews_to_receive,
+    processing_strategy,
+    accepting_threshold,
+    manual_review_cutoff_in_days,
+    points_are_all_or_nothing,
+    review_instructions,
+    calibration_reviews_to_give,
+    manual_review_reliability_threshold
+  )
+SELECT a.id,
+  $1,
+  a.exercise_id,
+  a.peer_reviews_to_give,
+  a.peer_reviews_to_receive,
+  a.processing_strategy,
+  a.accepting_threshold,
+  a.manual_review_cutoff_in_days,
+  a.points_are_all_or_nothing,
+  a.review_instructions,
+  a.calibration_reviews_to_give,
+  a.manual_review_reliability_threshold
+FROM jsonb_populate_recordset(NULL::peer_or_self_review_configs, $2) a
+",
+        course.id,
+        Value::Array(archive.peer_or_self_review_configs.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO peer_or_self_review_questions (
+    id,
+    peer_or_self_review_config_id,
+    order_number,
+    question,
+    question_type,
+    answer_required,
+    weight
+  )
+SELECT a.id,
+  a.peer_or_self_review_config_id,
+  a.order_number,
+  a.question,
+  a.question_type,
+  a.answer_required,
+  a.weight
+FROM jsonb_populate_recordset(NULL::peer_or_self_review_questions, $1) a
+",
+        Value::Array(archive.peer_or_self_review_questions.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO glossary (id, course_id, term, definition)
+SELECT a.id,
+  $1,
+  a.term,
+  a.definition
+FROM jsonb_populate_recordset(NULL::glossary, $2) a
+",
+        course.id,
+        Value::Array(archive.glossary.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO material_references (id, course_id, citation_key, reference)
+SELECT a.id,
+  $1,
+  a.citation_key,
+  a.reference
+FROM jsonb_populate_recordset(NULL::material_references, $2) a
+",
+        course.id,
+        Value::Array(archive.material_references.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO certificate_configurations (
+    id,
+    background_svg_file_upload_id,
+    background_svg_path,
+    certificate_date_font_size,
+    certificate_date_text_anchor,
+    certificate_date_text_color,
+    certificate_date_x_pos,
+    certificate_date_y_pos,
+    certificate_grade_font_size,
+    certificate_grade_text_anchor,
+    certificate_grade_text_color,
+    certificate_grade_x_pos,
+    certificate_grade_y_pos,
+    certificate_locale,
+    certificate_owner_name_font_size,
+    certificate_owner_name_text_anchor,
+    certificate_owner_name_text_color,
+    certificate_owner_name_x_pos,
+    certificate_owner_name_y_pos,
+    certificate_validate_url_font_size,
+    certificate_validate_url_text_anchor,
+    certificate_validate_url_text_color,
+    certificate_validate_url_x_pos,
+    certificate_validate_url_y_pos,
+    overlay_svg_file_upload_id,
+    overlay_svg_path,
+    paper_size,
+    render_certificate_grade,
+    pdf_enabled,
+    pdf_transcript_pages_enabled
+  )
+SELECT a.id,
+  a.background_svg_file_upload_id,
+  a.background_svg_path,
+  a.certificate_date_font_size,
+  a.certificate_date_text_anchor,
+  a.certificate_date_text_color,
+  a.certificate_date_x_pos,
+  a.certificate_date_y_pos,
+  a.certificate_grade_font_size,
+  a.certificate_grade_text_anchor,
+  a.certificate_grade_text_color,
+  a.certificate_grade_x_pos,
+  a.certificate_grade_y_pos,
+  a.certificate_locale,
+  a.certificate_owner_name_font_size,
+  a.certificate_owner_name_text_anchor,
+  a.certificate_owner_name_text_color,
+  a.certificate_owner_name_x_pos,
+  a.certificate_owner_name_y_pos,
+  a.certificate_validate_url_font_size,
+  a.certificate_validate_url_text_anchor,
+  a.certificate_validate_url_text_color,
+  a.certificate_validate_url_x_pos,
+  a.certificate_validate_url_y_pos,
+  a.overlay_svg_file_upload_id,
+  a.overlay_svg_path,
+  a.paper_size,
+  a.render_certificate_grade,
+  a.pdf_enabled,
+  a.pdf_transcript_pages_enabled
+FROM jsonb_populate_recordset(NULL::certificate_configurations, $1) a
+",
+        Value::Array(archive.certificate_configurations.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO certificate_configuration_to_requirements (
+    id,
+    certificate_configuration_id,
+    course_module_id
+  )
+SELECT a.id,
+  a.certificate_configuration_id,
+  a.course_module_id
+FROM jsonb_populate_recordset(
+    NULL::certificate_configuration_to_requirements,
+    $1
+  ) a
+",
+        Value::Array(archive.certificate_configuration_requirements.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        "
+INSERT INTO certificate_text_elements (
+    id,
+    certificate_configuration_id,
+    order_number,
+    template,
+    x_pos,
+    y_pos,
+    font_size,
+    text_color,
+    text_anchor,
+    font_family
+  )
+SELECT a.id,
+  a.certificate_configuration_id,
+  a.order_number,
+  a.template,
+  a.x_pos,
+  a.y_pos,
+  a.font_size,
+  a.text_color,
+  a.text_anchor,
+  a.font_family
+FROM jsonb_populate_recordset(NULL::certificate_text_elements, $1) a
+",
+        Value::Array(archive.certificate_text_elements.clone())
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Like when copying, the course instances are not part of the content.
+    course_instances::insert(
+        &mut tx,
+        PKeyPolicy::Generate,
+        NewCourseInstance {
+            course_id: course.id,
+            name: None,
+            description: None,
+            support_email: None,
+            teacher_in_charge_name: &new_course.teacher_in_charge_name,
+            teacher_in_charge_email: &new_course.teacher_in_charge_email,
+            opening_time: None,
+            closing_time: None,
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+    Ok(course)
+}
+
+/// Checks that the references between the rows of the archive point to rows in the archive. The
+/// references to the course itself are not checked, as they are replaced with the new course.
+fn check_references(archive: &CourseArchive) -> ModelResult<()> {
+    fn ids(rows: &[Value]) -> HashSet<&str> {
+        rows.iter().filter_map(|row| row["id"].as_str()).collect()
+    }
+    let course_modules = ids(&archive.course_modules);
+    let chapters = ids(&archive.chapters);
+    let pages = ids(&archive.pages);
+    let exercises = ids(&archive.exercises);
+    let exercise_slides = ids(&archive.exercise_slides);
+    let peer_or_self_review_configs = ids(&archive.peer_or_self_review_configs);
+    let certificate_configurations = ids(&archive.certificate_configurations);
+    let references: [(&str, &[Value], &str, &HashSet<&str>); 12] = [
+        (
+            "chapters",
+            &archive.chapters,
+            "course_module_id",
+            &course_modules,
+        ),
+        ("chapters", &archive.chapters, "front_page_id", &pages),
+        ("pages", &archive.pages, "chapter_id", &chapters),
+        ("exercises", &archive.exercises, "page_id", &pages),
+        ("exercises", &archive.exercises, "chapter_id", &chapters),
+        (
+            "exercise_slides",
+            &archive.exercise_slides,
+            "exercise_id",
+            &exercises,
+        ),
+        (
+            "exercise_tasks",
+            &archive.exercise_tasks,
+            "exercise_slide_id",
+            &exercise_slides,
+        ),
+        (
+            "peer_or_self_review_configs",
+            &archive.peer_or_self_review_configs,
+            "exercise_id",
+            &exercises,
+        ),
+        (
+            "peer_or_self_review_questions",
+            &archive.peer_or_self_review_questions,
+            "peer_or_self_review_config_id",
+            &peer_or_self_review_configs,
+        ),
+        (
+            "certificate_configuration_requirements",
+            &archive.certificate_configuration_requirements,
+            "certificate_configuration_id",
+            &certificate_configurations,
+        ),
+        (
+            "certificate_configuration_requirements",
+            &archive.certificate_configuration_requirements,
+            "course_module_id",
+            &course_modules,
+        ),
+        (
+            "certificate_text_elements",
+            &archive.certificate_text_elements,
+            "certificate_configuration_id",
+            &certificate_configurations,
+        ),
+    ];
+    for (rows_name, rows, column, targets) in references {
+        for row in rows {
+            match &row[column] {
+                Value::Null => {}
+                Value::String(id) if targets.contains(id.as_str()) => {}
+                other => {
+                    return Err(model_err!(
+                        PreconditionFailed,
+                        format!(
+                            "The {column} {other} in the {rows_name} of the archive does not refer to a row in the archive."
+                        )
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn from_json_array(value: Value) -> ModelResult<Vec<Value>> {
+    match value {
+        Value::Array(rows) => Ok(rows),
+        _ => Err(model_err!(
+            Generic,
+            "Expected the rows to be aggregated to an array.".to_string()
+        )),
+    }
+}
+
+fn collect_file_paths(value: &Value, paths: &mut BTreeSet<String>) {
+    match value {
+        Value::String(s) => {
+            for captures in FILE_PATH_RE.captures_iter(s) {
+                paths.insert(captures["path"].to_string());
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_file_paths(value, paths);
+            }
+        }
+        Value::Object(map) => {
+            for value in map.values() {
+                collect_file_paths(value, paths);
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) => {}
+    }
+}
+
+/// Rewrites the references in the archived rows to point to the imported course.
+struct Remapper<'a> {
+    ids: &'a HashMap<String, String>,
+    files: &'a HashMap<String, ImportedFile>,
+    old_course_path: String,
+    new_course_path: String,
+}
+
+impl Remapper<'_> {
+    fn remap(&self, value: &mut Value) {
+        match value {
+            Value::String(s) => {
+                if let Some(new_id) = self.ids.get(s.as_str()) {
+                    *s = new_id.clone();
+                    return;
+                }
+                let remapped = FILE_PATH_RE.replace_all(s, |captures: &Captures| {
+                    match self.files.get(&captures["path"]) {
+                        Some(file) if captures.name("url").is_some() => file.url.clone(),
+                        Some(file) => file.path.clone(),
+                        None => captures[0].to_string(),
+                    }
+                });
+                let mut remapped = remapped.into_owned();
+                // Links between the pages of the course contain the course slug.
+                if remapped.contains("<a href=") {
+                    remapped = remapped.replace(&self.old_course_path, &self.new_course_path);
+                }
+                *s = remapped;
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.remap(value);
+                }
+            }
+            Value::Object(map) => {
+                for value in map.values_mut() {
+                    self.remap(value);
+                }
+            }
+            Value::Null | Value::Bool(_) | Value::Number(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        exercise_services::{self, ExerciseServiceNewOrUpdate},
+        test_helper::*,
+    };
+
+    fn new_course(organization_id: Uuid) -> NewCourse {
+        NewCourse {
+            name: "Imported course".to_string(),
+            slug: "imported-course".to_string(),
+            organization_id,
+            language_code: "en".to_string(),
+            teacher_in_charge_name: "teacher".to_string(),
+            teacher_in_charge_email: "teacher@example.com".to_string(),
+            description: "description".to_string(),
+            is_draft: true,
+            is_test_mode: false,
+            is_unlisted: false,
+            copy_user_permissions: false,
+            is_joinable_by_code_only: false,
+            join_code: None,
+            ask_marketing_consent: false,
+            flagged_answers_threshold: Some(3),
+            can_add_chatbot: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn exports_and_imports_course() {
+        insert_data!(:tx, :user, :org, :course, instance: _instance, :course_module, :chapter, :page, :exercise, slide: _slide, task: _task);
+        let page_content = serde_json::json!([{
+            "name": "moocfi/exercise",
+            "isValid": true,
+            "clientId": "b2ecb473-38cc-4df1-84f7-06709cc63e95",
+            "attributes": { "id": exercise, "name": "exercise" },
+            "innerBlocks": []
+        }]);
+        sqlx::query!(
+            "UPDATE pages SET content = $1 WHERE id = $2",
+            page_content,
+            page
+        )
+        .execute(tx.as_mut())
+        .await
+        .unwrap();
+
+        let archive = export_course(tx.as_mut(), course).await.unwrap();
+        assert_eq!(archive.chapters.len(), 1);
+        assert_eq!(archive.pages.len(), 1);
+        assert_eq!(archive.exercises.len(), 1);
+        assert_eq!(archive.exercise_slides.len(), 1);
+        assert_eq!(archive.exercise_tasks.len(), 1);
+        assert_eq!(
+            archive.exercise_types(),
+            vec![TEST_HELPER_EXERCISE_SERVICE_NAME.to_string()]
+        );
+        // survives the trip through the archive file
+        let archive: CourseArchive =
+            serde_json::from_str(&serde_json::to_string(&archive).unwrap()).unwrap();
+
+        if !get_missing_exercise_services(tx.as_mut(), &archive)
+            .await
+            .unwrap()
+            .is_empty()
+        {
+            exercise_services::insert_exercise_service(
+                tx.as_mut(),
+                &ExerciseServiceNewOrUpdate {
+                    name: "Test exercise".to_string(),
+                    slug: TEST_HELPER_EXERCISE_SERVICE_NAME.to_string(),
+                    public_url: "http://example.com".to_string(),
+                    internal_url: None,
+                    max_reprocessing_submissions_at_once: 1,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let new_course_id = Uuid::new_v4();
+        let imported = import_course(
+            tx.as_mut(),
+            &archive,
+            new_course_id,
+            &new_course(org),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(imported.id, new_course_id);
+
+        let chapters = crate::chapters::get_course_chapters(tx.as_mut(), imported.id)
+            .await
+            .unwrap();
+        assert_eq!(chapters.len(), 1);
+        let expected_chapter_id = Uuid::new_v5(&new_course_id, chapter.to_string().as_bytes());
+        assert_eq!(chapters[0].id, expected_chapter_id);
+        assert_eq!(
+            chapters[0].course_module_id,
+            Uuid::new_v5(&new_course_id, course_module.id.to_string().as_bytes())
+        );
+
+        let exercises = crate::exercises::get_exercises_by_course_id(tx.as_mut(), imported.id)
+            .await
+            .unwrap();
+        assert_eq!(exercises.len(), 1);
+        assert_ne!(exercises[0].id, exercise);
+        let imported_page = crate::pages::get_page(tx.as_mut(), exercises[0].page_id)
+            .await
+            .unwrap();
+        assert_eq!(
+            imported_page.content[0]["attributes"]["id"],
+            Value::String(exercises[0].id.to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_format_version() {
+        insert_data!(:tx, :user, :org, :course);
+        let mut archive = export_course(tx.as_mut(), course).await.unwrap();
+        archive.format_version = COURSE_ARCHIVE_FORMAT_VERSION + 1;
+        let res = import_course(
+            tx.as_mut(),
+            &archive,
+            Uuid::new_v4(),
+            &new_course(org),
+            &HashMap::new(),
+        )
+        .await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_references_outside_of_the_archive() {
+        insert_data!(:tx, :user, :org, :course, instance: _instance, course_module: _course_module, chapter: _chapter, page: _page, exercise: _exercise, slide: _slide, task: _task);
+        let mut archive = export_course(tx.as_mut(), course).await.unwrap();
+        // An exercise of some other course.
+        archive.exercise_slides[0]["exercise_id"] = Value::String(Uuid::new_v4().to_string());
+        let err = import_course(
+            tx.as_mut(),
+            &archive,
+            Uuid::new_v4(),
+            &new_course(org),
+            &HashMap::new(),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err.error_type(),
+            ModelErrorType::PreconditionFailed
+        ));
+    }
+
+    #[test]
+    fn remaps_ids_and_files() {
+        let old_id = "8c6a8a9e-6bd9-4a6b-9f5e-4a1f6f0c6d5a".to_string();
+        let new_id = "f6b3cd2a-1b0e-4ed4-a3a1-0b8e9b0a6f39".to_string();
+        let ids = HashMap::from([(old_id.clone(), new_id.clone())]);
+        let old_path = format!("course/{old_id}/images/abc123.png");
+        let files = HashMap::from([(
+            old_path.clone(),
+            ImportedFile {
+                path: format!("course/{new_id}/images/xyz789.png"),
+                url: format!("https://example.org/api/v0/files/course/{new_id}/images/xyz789.png"),
+                file_upload_id: Uuid::new_v4(),
+            },
+        )]);
+        let remapper = Remapper {
+            ids: &ids,
+            files: &files,
+            old_course_path: "/courses/old".to_string(),
+            new_course_path: "/courses/new".to_string(),
+        };
+        let mut value = serde_json::json!({
+            "id": old_id,
+            "chapter_image_path": old_path,
+            "content": format!(
+                "<img src=\"https://staging.example.com/api/v0/files/{old_path}\"> <a href=\"/org/uh/courses/old/chapter-1\">link</a>"
+            ),
+        });
+        remapper.remap(&mut value);
+        assert_eq!(value["id"], Value::String(new_id.clone()));
+        assert_eq!(
+            value["chapter_image_path"],
+            Value::String(format!("course/{new_id}/images/xyz789.png"))
+        );
+        assert_eq!(
+            value["content"],
+            Value::String(format!(
+                "<img src=\"https://example.org/api/v0/files/course/{new_id}/images/xyz789.png\"> <a href=\"/org/uh/courses/new/chapter-1\">link</a>"
+            ))
+        );
+    }
+}
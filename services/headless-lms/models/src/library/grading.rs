@@ -14,8 +14,11 @@ use crate::{
     exercise_task_regrading_submissions::ExerciseTaskRegradingSubmission,
     exercise_task_submissions::{self, ExerciseTaskSubmission},
     exercise_tasks::{self, CourseMaterialExerciseTask, ExerciseTask},
-    exercises::{self, Exercise, ExerciseStatus, GradingProgress},
+    exercises::{self, Exercise, ExerciseScoringStrategy, ExerciseStatus, GradingProgress},
     flagged_answers::{self, FlaggedAnswer},
+    grading_rubrics::{self, GradingRubricWithCriteria},
+    late_submission_penalties::{self, LateSubmissionPenalty},
+    late_submission_policies,
     peer_or_self_review_configs::PeerReviewProcessingStrategy,
     peer_or_self_review_question_submissions::{
         self, PeerOrSelfReviewQuestionSubmission, PeerReviewWithQuestionsAndAnswers,
@@ -44,6 +47,8 @@ pub struct StudentExerciseSlideSubmissionResult {
     pub exercise_status: Option<ExerciseStatus>,
     pub exercise_task_submission_results: Vec<StudentExerciseTaskSubmissionResult>,
     pub user_course_instance_exercise_service_variables: Vec<UserCourseExerciseServiceVariable>,
+    /// Set if the submission was made after the deadline and its points were reduced.
+    pub late_submission_penalty: Option<LateSubmissionPenalty>,
 }
 
 impl StudentExerciseSlideSubmissionResult {
@@ -86,6 +91,7 @@ pub struct StudentExerciseTaskSubmissionResult {
 pub struct ExerciseSlideSubmissionWithTasks {
     pub exercise_slide_submission: ExerciseSlideSubmission,
     pub exercise_slide_submission_tasks: Vec<ExerciseTaskSubmission>,
+    pub late_submission_penalty: Option<LateSubmissionPenalty>,
 }
 
 /// If passed to to an exercise state update, it will update the peer review status with the given information
@@ -100,12 +106,20 @@ pub struct ExerciseStateUpdateNeedToUpdatePeerReviewStatusWithThis {
 }
 
 /// Inserts user submission to database. Tasks within submission are validated to make sure that
-/// they belong to the correct exercise slide.
+/// they belong to the correct exercise slide. If the deadline that applies to the student, deadline
+/// exceptions included, has passed, the submission is accepted only inside the late window of the
+/// exercise's late submission policy, and the penalty is recorded with the submission.
 pub async fn create_user_exercise_slide_submission(
     conn: &mut PgConnection,
     exercise_with_user_state: &ExerciseWithUserState,
     user_exercise_slide_submission: &StudentExerciseSlideSubmission,
 ) -> ModelResult<ExerciseSlideSubmissionWithTasks> {
+    let new_late_submission_penalty = late_submission_policies::check_submission_deadline(
+        conn,
+        exercise_with_user_state.exercise(),
+        exercise_with_user_state.user_exercise_state().user_id,
+    )
+    .await?;
     let selected_exercise_slide_id = exercise_with_user_state
         .user_exercise_state()
         .selected_exercise_slide_id
@@ -139,6 +153,19 @@ pub async fn create_user_exercise_slide_submission(
         },
     )
     .await?;
+    let late_submission_penalty = match new_late_submission_penalty {
+        Some(new_late_submission_penalty) => Some(
+            late_submission_penalties::insert(
+                &mut tx,
+                exercise_slide_submission.id,
+                exercise_slide_submission.user_id,
+                exercise_slide_submission.exercise_id,
+                &new_late_submission_penalty,
+            )
+            .await?,
+        ),
+        None => None,
+    };
     let user_exercise_task_submissions = &user_exercise_slide_submission.exercise_task_submissions;
     let mut exercise_slide_submission_tasks =
         Vec::with_capacity(user_exercise_task_submissions.len());
@@ -169,6 +196,7 @@ pub async fn create_user_exercise_slide_submission(
     Ok(ExerciseSlideSubmissionWithTasks {
         exercise_slide_submission,
         exercise_slide_submission_tasks,
+        late_submission_penalty,
     })
 }
 
@@ -242,6 +270,7 @@ pub async fn grade_user_submission(
     let ExerciseSlideSubmissionWithTasks {
         exercise_slide_submission,
         exercise_slide_submission_tasks,
+        late_submission_penalty,
     } = create_user_exercise_slide_submission(
         &mut tx,
         exercise_with_user_state,
@@ -346,8 +375,10 @@ pub async fn grade_user_submission(
     };
     let user_exercise_state = update_user_exercise_slide_state_and_user_exercise_state(
         &mut tx,
+        exercise_with_user_state.exercise(),
         user_exercise_slide_state,
         exercise_slide_submission.user_points_update_strategy,
+        late_submission_penalty.as_ref(),
     )
     .await?;
 
@@ -367,6 +398,7 @@ pub async fn grade_user_submission(
         }),
         exercise_task_submission_results: results,
         user_course_instance_exercise_service_variables,
+        late_submission_penalty,
     };
     exercise_with_user_state.set_user_exercise_state(user_exercise_state)?;
     tx.commit().await?;
@@ -452,13 +484,17 @@ async fn create_fixed_grading_for_submission_task(
 /// whole user exercise state.
 async fn update_user_exercise_slide_state_and_user_exercise_state(
     conn: &mut PgConnection,
+    exercise: &Exercise,
     user_exercise_slide_state: UserExerciseSlideState,
     user_points_update_strategy: UserPointsUpdateStrategy,
+    late_submission_penalty: Option<&LateSubmissionPenalty>,
 ) -> ModelResult<UserExerciseState> {
     update_user_exercise_slide_state(
         conn,
+        exercise,
         &user_exercise_slide_state,
         user_points_update_strategy,
+        late_submission_penalty,
     )
     .await?;
     let user_exercise_state = user_exercise_state_updater::update_user_exercise_state(
@@ -470,10 +506,18 @@ async fn update_user_exercise_slide_state_and_user_exercise_state(
     Ok(user_exercise_state)
 }
 
+/// The late submission penalty of the submission being graded is applied to the points from the
+/// tasks before they are compared with the points the slide already has, so an earlier submission
+/// made in time keeps its points.
+///
+/// Course exercises that use some other scoring strategy than the best attempt are scored from all
+/// of the user's graded submissions to the slide instead, each with its own late submission penalty.
 async fn update_user_exercise_slide_state(
     conn: &mut PgConnection,
+    exercise: &Exercise,
     user_exercise_slide_state: &UserExerciseSlideState,
     user_points_update_strategy: UserPointsUpdateStrategy,
+    late_submission_penalty: Option<&LateSubmissionPenalty>,
 ) -> ModelResult<()> {
     let (points_from_tasks, grading_progress) =
         user_exercise_task_states::get_grading_summary_by_user_exercise_slide_state_id(
@@ -481,11 +525,23 @@ async fn update_user_exercise_slide_state(
             user_exercise_slide_state.id,
         )
         .await?;
-    let new_score_given = user_exercise_task_states::figure_out_new_score_given(
-        user_exercise_slide_state.score_given,
-        points_from_tasks,
-        user_points_update_strategy,
-    );
+    let new_score_given = if exercise.exam_id.is_none()
+        && exercise.scoring_strategy != ExerciseScoringStrategy::BestAttempt
+    {
+        score_slide_from_attempts(conn, exercise, user_exercise_slide_state)
+            .await?
+            .or(user_exercise_slide_state.score_given)
+    } else {
+        let points_from_tasks = match (points_from_tasks, late_submission_penalty) {
+            (Some(points), Some(penalty)) => Some(penalty.apply(points, exercise.score_maximum)),
+            (points, _) => points,
+        };
+        user_exercise_task_states::figure_out_new_score_given(
+            user_exercise_slide_state.score_given,
+            points_from_tasks,
+            user_points_update_strategy,
+        )
+    };
     let changes = user_exercise_slide_states::update(
         conn,
         user_exercise_slide_state.id,
@@ -500,6 +556,36 @@ async fn update_user_exercise_slide_state(
     Ok(())
 }
 
+/// None if the user has no fully graded submissions to the slide yet.
+async fn score_slide_from_attempts(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    user_exercise_slide_state: &UserExerciseSlideState,
+) -> ModelResult<Option<f32>> {
+    let attempts = exercise_slide_submissions::get_graded_attempt_scores_for_slide(
+        conn,
+        user_exercise_slide_state.user_exercise_state_id,
+        user_exercise_slide_state.exercise_slide_id,
+    )
+    .await?;
+    let attempt_ids = attempts
+        .iter()
+        .map(|attempt| attempt.exercise_slide_submission_id)
+        .collect::<Vec<_>>();
+    let penalties =
+        late_submission_penalties::get_by_exercise_slide_submission_ids(conn, &attempt_ids).await?;
+    let attempt_scores = attempts
+        .iter()
+        .map(
+            |attempt| match penalties.get(&attempt.exercise_slide_submission_id) {
+                Some(penalty) => penalty.apply(attempt.score_given, exercise.score_maximum),
+                None => attempt.score_given,
+            },
+        )
+        .collect::<Vec<_>>();
+    Ok(exercise.score_from_attempts(&attempt_scores))
+}
+
 /// Updates the user exercise state starting from a single task, and propagates the update up to the
 /// whole user exercise state.
 pub async fn propagate_user_exercise_state_update_from_exercise_task_grading_result(
@@ -534,10 +620,22 @@ pub async fn propagate_user_exercise_state_update_from_exercise_task_grading_res
         user_exercise_task_state.user_exercise_slide_state_id,
     )
     .await?;
+    let exercise_task_submission = exercise_task_submissions::get_by_id(
+        conn,
+        updated_exercise_task_grading.exercise_task_submission_id,
+    )
+    .await?;
+    let late_submission_penalty = late_submission_penalties::get_by_exercise_slide_submission_id(
+        conn,
+        exercise_task_submission.exercise_slide_submission_id,
+    )
+    .await?;
     let user_exercise_state = update_user_exercise_slide_state_and_user_exercise_state(
         conn,
+        exercise,
         user_exercise_slide_state,
         user_points_update_strategy,
+        late_submission_penalty.as_ref(),
     )
     .await?;
     Ok(user_exercise_state)
@@ -549,6 +647,8 @@ pub struct AnswersRequiringAttention {
     pub exercise_max_points: i32,
     pub data: Vec<AnswerRequiringAttentionWithTasks>,
     pub total_pages: u32,
+    /// Set if the answers are graded with a rubric.
+    pub grading_rubric: Option<GradingRubricWithCriteria>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
@@ -568,6 +668,9 @@ pub struct AnswerRequiringAttentionWithTasks {
     pub given_peer_reviews: Vec<PeerReviewWithQuestionsAndAnswers>,
     pub received_peer_or_self_reviews: Vec<PeerReviewWithQuestionsAndAnswers>,
     pub received_peer_review_flagging_reports: Vec<FlaggedAnswer>,
+    /// Set if the answer was submitted after the deadline. Full points given to it are reduced by
+    /// the penalty.
+    pub late_submission_penalty: Option<LateSubmissionPenalty>,
 }
 
 /// Gets submissions that require input from the teacher to continue processing.
@@ -613,6 +716,12 @@ pub async fn get_paginated_answers_requiring_attention_for_exercise(
         let received_peer_review_flagging_reports: Vec<FlaggedAnswer> =
             flagged_answers::get_flagged_answers_by_submission_id(conn, answer.submission_id)
                 .await?;
+        let late_submission_penalty =
+            late_submission_penalties::get_by_exercise_slide_submission_id(
+                conn,
+                answer.submission_id,
+            )
+            .await?;
         let new_answer = AnswerRequiringAttentionWithTasks {
             id: answer.id,
             user_id: answer.user_id,
@@ -628,12 +737,15 @@ pub async fn get_paginated_answers_requiring_attention_for_exercise(
             given_peer_reviews,
             received_peer_or_self_reviews,
             received_peer_review_flagging_reports,
+            late_submission_penalty,
         };
         answers.push(new_answer);
     }
+    let grading_rubric = grading_rubrics::get_by_exercise_id(conn, exercise.id).await?;
     Ok(AnswersRequiringAttention {
         exercise_max_points: exercise.score_maximum,
         data: answers,
         total_pages: pagination.total_pages(answer_requiring_attention_count),
+        grading_rubric,
     })
 }
@@ -3,6 +3,7 @@ use utoipa::ToSchema;
 
 pub mod content_management;
 pub mod copying;
+pub mod course_archives;
 pub mod course_instances;
 pub mod course_stats;
 pub mod credit_registration;
@@ -13,6 +14,7 @@ pub mod migration;
 pub mod oauth;
 pub mod page_visit_stats;
 pub mod peer_or_self_reviewing;
+pub mod peer_review_disputes;
 pub mod progressing;
 pub mod regrading;
 pub mod students_view;
@@ -15,8 +15,10 @@ use crate::{
     peer_or_self_review_configs::{self, PeerOrSelfReviewConfig, PeerReviewProcessingStrategy},
     peer_or_self_review_question_submissions,
     peer_or_self_review_questions::{self, PeerOrSelfReviewQuestion},
-    peer_or_self_review_submissions,
+    peer_or_self_review_submissions, peer_review_calibration_answers,
+    peer_review_calibration_submissions,
     peer_review_queue_entries::{self, PeerReviewQueueEntry},
+    peer_reviewer_reliabilities::{self, PeerReviewerReliability},
     prelude::*,
     user_exercise_states::{self, ReviewingStage, UserExerciseState},
 };
@@ -116,6 +118,15 @@ pub async fn create_peer_or_self_review_submission_for_user(
         exercise.get_course_id()?,
     )
     .await?;
+    if !is_self_review {
+        ensure_peer_review_calibration_is_done(
+            conn,
+            &peer_or_self_review_config,
+            giver_exercise_state.user_id,
+            exercise.id,
+        )
+        .await?;
+    }
     let sanitized_answers = validate_and_sanitize_peer_review_submission_answers(
         peer_or_self_review_questions::get_all_by_peer_or_self_review_config_id_as_map(
             conn,
@@ -469,6 +480,13 @@ pub async fn try_to_select_exercise_slide_submission_for_peer_review(
         exercise.get_course_id()?,
     )
     .await?;
+    ensure_peer_review_calibration_is_done(
+        conn,
+        &peer_or_self_review_config,
+        reviewer_user_exercise_state.user_id,
+        exercise.id,
+    )
+    .await?;
 
     let course_id = exercise.get_course_id()?;
 
@@ -503,11 +521,14 @@ pub async fn try_to_select_exercise_slide_submission_for_peer_review(
         .await?;
     excluded_exercise_slide_submission_ids.extend(reported_submissions);
 
+    let starved_before =
+        Utc::now() - peer_review_queue_entries::starvation_threshold(&peer_or_self_review_config);
     let candidate_submission_id = try_to_select_peer_review_candidate_from_queue(
         conn,
         reviewer_user_exercise_state.exercise_id,
         reviewer_user_exercise_state.user_id,
         &excluded_exercise_slide_submission_ids,
+        starved_before,
     )
     .await?;
     let exercise_slide_submission_to_review = match candidate_submission_id {
@@ -585,6 +606,7 @@ async fn try_to_select_peer_review_candidate_from_queue(
     exercise_id: Uuid,
     excluded_user_id: Uuid,
     excluded_exercise_slide_submission_ids: &[Uuid],
+    starved_before: DateTime<Utc>,
 ) -> ModelResult<Option<ExerciseSlideSubmission>> {
     const MAX_ATTEMPTS: u32 = 10;
     let mut attempts = 0;
@@ -597,6 +619,7 @@ async fn try_to_select_peer_review_candidate_from_queue(
             exercise_id,
             excluded_user_id,
             excluded_exercise_slide_submission_ids,
+            starved_before,
         )
         .await?;
 
@@ -655,21 +678,28 @@ async fn try_to_select_peer_review_candidate_from_queue(
 }
 
 /// Returns a tuple of the exercise slide submission id and a boolean indicating if the submission needs peer review.
+///
+/// Answers that need reviews are allocated fairly: starved answers first, then the answers with the
+/// fewest reviews, avoiding authors the reviewer has already reviewed. See
+/// [`peer_review_queue_entries::get_many_that_need_peer_reviews_in_allocation_order`].
 async fn try_to_select_peer_review_candidate_from_queue_impl(
     conn: &mut PgConnection,
     exercise_id: Uuid,
     excluded_user_id: Uuid,
     excluded_exercise_slide_submission_ids: &[Uuid],
+    starved_before: DateTime<Utc>,
 ) -> ModelResult<Option<(Uuid, bool)>> {
-    let mut rng = rng();
     // Try to get a candidate that needs reviews from queue.
-    let mut candidates = peer_review_queue_entries::get_many_that_need_peer_reviews_by_exercise_id_and_review_priority(conn,
-        exercise_id,
-        excluded_user_id,
-        excluded_exercise_slide_submission_ids,
-        MAX_PEER_REVIEW_CANDIDATES,
-    ).await?;
-    candidates.shuffle(&mut rng);
+    let candidates =
+        peer_review_queue_entries::get_many_that_need_peer_reviews_in_allocation_order(
+            conn,
+            exercise_id,
+            excluded_user_id,
+            excluded_exercise_slide_submission_ids,
+            starved_before,
+            1,
+        )
+        .await?;
     match candidates.into_iter().next() {
         Some(candidate) => Ok(Some((
             candidate.receiving_peer_reviews_exercise_slide_submission_id,
@@ -685,7 +715,7 @@ async fn try_to_select_peer_review_candidate_from_queue_impl(
                 MAX_PEER_REVIEW_CANDIDATES,
             )
             .await?;
-            candidates.shuffle(&mut rng);
+            candidates.shuffle(&mut rng());
             Ok(candidates.into_iter().next().map(|entry| {
                 (
                     entry.receiving_peer_reviews_exercise_slide_submission_id,
@@ -745,6 +775,237 @@ async fn get_course_material_peer_or_self_review_data(
     })
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+
+pub struct CourseMaterialPeerReviewCalibrationData {
+    /// If none, the user has reviewed all the calibration answers they need to and can review their peers.
+    pub answer_to_review: Option<CourseMaterialPeerReviewCalibrationAnswerToReview>,
+    pub peer_or_self_review_questions: Vec<PeerOrSelfReviewQuestion>,
+    pub num_calibration_reviews_given: i64,
+    pub num_calibration_reviews_required: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+
+pub struct CourseMaterialPeerReviewCalibrationAnswerToReview {
+    pub peer_review_calibration_answer_id: Uuid,
+    pub exercise_slide_submission_id: Uuid,
+    pub course_material_exercise_tasks: Vec<CourseMaterialExerciseTask>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+
+pub struct CourseMaterialPeerReviewCalibrationSubmission {
+    pub peer_review_calibration_answer_id: Uuid,
+    pub peer_review_question_answers: Vec<CourseMaterialPeerOrSelfReviewQuestionAnswer>,
+}
+
+/// How many calibration answers the user has to review before reviewing their peers. If the teacher has picked fewer calibration answers than the config asks for, all of them are reviewed.
+async fn get_num_calibration_reviews_required(
+    conn: &mut PgConnection,
+    peer_or_self_review_config: &PeerOrSelfReviewConfig,
+    exercise_id: Uuid,
+) -> ModelResult<i64> {
+    if peer_or_self_review_config.calibration_reviews_to_give == 0 {
+        return Ok(0);
+    }
+    let num_calibration_answers =
+        peer_review_calibration_answers::count_by_exercise_id(conn, exercise_id).await?;
+    Ok(num_calibration_answers.min(peer_or_self_review_config.calibration_reviews_to_give as i64))
+}
+
+async fn ensure_peer_review_calibration_is_done(
+    conn: &mut PgConnection,
+    peer_or_self_review_config: &PeerOrSelfReviewConfig,
+    user_id: Uuid,
+    exercise_id: Uuid,
+) -> ModelResult<()> {
+    let num_calibration_reviews_required =
+        get_num_calibration_reviews_required(conn, peer_or_self_review_config, exercise_id).await?;
+    if num_calibration_reviews_required == 0 {
+        return Ok(());
+    }
+    let num_calibration_reviews_given =
+        peer_review_calibration_submissions::get_num_given_by_user_and_exercise(
+            conn,
+            user_id,
+            exercise_id,
+        )
+        .await?;
+    if num_calibration_reviews_given < num_calibration_reviews_required {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            "Review the calibration answers before reviewing your peers.".to_string(),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+fn ensure_user_can_calibrate(user_exercise_state: &UserExerciseState) -> ModelResult<()> {
+    // Same as with peer reviews, the calibration answers often expose the correct solution.
+    if !matches!(
+        user_exercise_state.reviewing_stage,
+        ReviewingStage::PeerReview | ReviewingStage::WaitingForPeerReviews
+    ) {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            "You cannot review calibration answers yet".to_string(),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Selects the next calibration answer for the user to review before they review their peers.
+pub async fn get_peer_review_calibration_data_for_user(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    user_exercise_state: &UserExerciseState,
+    fetch_service_info: impl Fn(Url) -> BoxFuture<'static, ModelResult<ExerciseServiceInfoApi>>,
+) -> ModelResult<CourseMaterialPeerReviewCalibrationData> {
+    ensure_user_can_calibrate(user_exercise_state)?;
+    let peer_or_self_review_config = peer_or_self_review_configs::get_by_exercise_or_course_id(
+        conn,
+        exercise,
+        exercise.get_course_id()?,
+    )
+    .await?;
+    let peer_or_self_review_questions =
+        peer_or_self_review_questions::get_all_by_peer_or_self_review_config_id(
+            conn,
+            peer_or_self_review_config.id,
+        )
+        .await?;
+    let num_calibration_reviews_required =
+        get_num_calibration_reviews_required(conn, &peer_or_self_review_config, exercise.id)
+            .await?;
+    let num_calibration_reviews_given =
+        peer_review_calibration_submissions::get_num_given_by_user_and_exercise(
+            conn,
+            user_exercise_state.user_id,
+            exercise.id,
+        )
+        .await?;
+
+    let calibration_answer = if num_calibration_reviews_given < num_calibration_reviews_required {
+        peer_review_calibration_answers::get_next_to_review_by_user(
+            conn,
+            exercise.id,
+            user_exercise_state.user_id,
+        )
+        .await?
+    } else {
+        None
+    };
+    let answer_to_review = match calibration_answer {
+        Some(calibration_answer) => {
+            let course_material_exercise_tasks = exercise_task_submissions::get_exercise_task_submission_info_by_exercise_slide_submission_id(
+                conn,
+                calibration_answer.exercise_slide_submission_id,
+                user_exercise_state.user_id,
+                fetch_service_info,
+                false,
+            )
+            .await?;
+            Some(CourseMaterialPeerReviewCalibrationAnswerToReview {
+                peer_review_calibration_answer_id: calibration_answer.id,
+                exercise_slide_submission_id: calibration_answer.exercise_slide_submission_id,
+                course_material_exercise_tasks,
+            })
+        }
+        None => None,
+    };
+
+    Ok(CourseMaterialPeerReviewCalibrationData {
+        answer_to_review,
+        peer_or_self_review_questions,
+        num_calibration_reviews_given,
+        num_calibration_reviews_required,
+    })
+}
+
+/// Saves the user's review of a calibration answer and updates their reliability as a reviewer on the course.
+pub async fn create_peer_review_calibration_submission_for_user(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    user_exercise_state: &UserExerciseState,
+    calibration_submission: CourseMaterialPeerReviewCalibrationSubmission,
+) -> ModelResult<PeerReviewerReliability> {
+    ensure_user_can_calibrate(user_exercise_state)?;
+    let course_id = user_exercise_state.get_course_id()?;
+    let peer_or_self_review_config =
+        peer_or_self_review_configs::get_by_exercise_or_course_id(conn, exercise, course_id)
+            .await?;
+    let num_calibration_reviews_required =
+        get_num_calibration_reviews_required(conn, &peer_or_self_review_config, exercise.id)
+            .await?;
+    let num_calibration_reviews_given =
+        peer_review_calibration_submissions::get_num_given_by_user_and_exercise(
+            conn,
+            user_exercise_state.user_id,
+            exercise.id,
+        )
+        .await?;
+    // Reviewing more calibration answers than required would let students retry until their reliability is high.
+    if num_calibration_reviews_given >= num_calibration_reviews_required {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            "You have already reviewed enough calibration answers.".to_string(),
+            None,
+        ));
+    }
+    let calibration_answer = peer_review_calibration_answers::get_by_id(
+        conn,
+        calibration_submission.peer_review_calibration_answer_id,
+    )
+    .await?;
+    if calibration_answer.exercise_id != exercise.id {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            "The calibration answer does not belong to the exercise.".to_string(),
+            None,
+        ));
+    }
+    let sanitized_answers = validate_and_sanitize_peer_review_submission_answers(
+        peer_or_self_review_questions::get_all_by_peer_or_self_review_config_id_as_map(
+            conn,
+            peer_or_self_review_config.id,
+        )
+        .await?,
+        calibration_submission.peer_review_question_answers,
+    )?;
+    let reference_scores =
+        peer_review_calibration_answers::get_reference_scores_by_calibration_answer_id(
+            conn,
+            calibration_answer.id,
+        )
+        .await?;
+    let agreement = peer_review_calibration_submissions::calculate_agreement(
+        &reference_scores,
+        &sanitized_answers,
+    );
+
+    let mut tx = conn.begin().await?;
+    peer_review_calibration_submissions::insert(
+        &mut tx,
+        user_exercise_state.user_id,
+        course_id,
+        exercise.id,
+        calibration_answer.id,
+        agreement,
+    )
+    .await?;
+    let reliability = peer_reviewer_reliabilities::recalculate_for_user_and_course(
+        &mut tx,
+        user_exercise_state.user_id,
+        course_id,
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(reliability)
+}
+
 #[instrument(skip(conn))]
 pub async fn update_peer_review_queue_reviews_received(
     conn: &mut PgConnection,
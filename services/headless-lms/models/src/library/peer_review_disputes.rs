@@ -0,0 +1,416 @@
+//! Lets students contest the peer reviews they have received, and teachers resolve the disputes.
+
+use serde_json::json;
+
+use crate::{
+    courses, email_deliveries,
+    email_templates::{self, EmailTemplateType},
+    exercise_slide_submissions,
+    exercises::Exercise,
+    peer_or_self_review_submissions,
+    peer_review_disputes::{
+        self, NewPeerReviewDispute, PeerReviewDispute, PeerReviewDisputeResolution,
+        PeerReviewDisputeStatus,
+    },
+    peer_review_queue_entries,
+    prelude::*,
+    teacher_grading_decisions::{self, TeacherDecisionType},
+    user_exercise_states,
+};
+
+use super::user_exercise_state_updater;
+
+const MAX_REASON_LENGTH: usize = 10_000;
+
+/// Files a dispute against a peer review the user has received on the exercise. Each review can
+/// be disputed once.
+pub async fn create_peer_review_dispute_for_user(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    exercise: &Exercise,
+    new_dispute: &NewPeerReviewDispute,
+) -> ModelResult<PeerReviewDispute> {
+    let reason = new_dispute.reason.trim();
+    if reason.is_empty() {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            "Tell why you think the review is unfair.".to_string(),
+            None,
+        ));
+    }
+    if reason.chars().count() > MAX_REASON_LENGTH {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            format!("The reason can be at most {MAX_REASON_LENGTH} characters long."),
+            None,
+        ));
+    }
+
+    let review = peer_or_self_review_submissions::get_by_id(
+        conn,
+        new_dispute.peer_or_self_review_submission_id,
+    )
+    .await?;
+    let reviewed_submission =
+        exercise_slide_submissions::get_by_id(conn, review.exercise_slide_submission_id).await?;
+    // Students only see the reviews given to their own answers.
+    if review.exercise_id != exercise.id || reviewed_submission.user_id != user_id {
+        return Err(ModelError::new(
+            ModelErrorType::NotFound,
+            "Review not found.".to_string(),
+            None,
+        ));
+    }
+    if review.user_id == user_id {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            "Self reviews cannot be disputed.".to_string(),
+            None,
+        ));
+    }
+    if peer_review_disputes::try_to_get_by_peer_or_self_review_submission_id(conn, review.id)
+        .await?
+        .is_some()
+    {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            "The review has already been disputed.".to_string(),
+            None,
+        ));
+    }
+
+    peer_review_disputes::insert(
+        conn,
+        user_id,
+        exercise.id,
+        review.course_id,
+        reviewed_submission.id,
+        review.id,
+        reason,
+    )
+    .await
+}
+
+/// Resolves a pending dispute, updates the exercise state of the student who filed it and queues
+/// an email telling them about the resolution.
+pub async fn resolve_peer_review_dispute(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    peer_review_dispute_id: Uuid,
+    resolver_user_id: Uuid,
+    resolution: &PeerReviewDisputeResolution,
+) -> ModelResult<PeerReviewDispute> {
+    let mut tx = conn.begin().await?;
+    let dispute = peer_review_disputes::get_by_id(&mut tx, peer_review_dispute_id).await?;
+    if dispute.exercise_id != exercise.id {
+        return Err(ModelError::new(
+            ModelErrorType::NotFound,
+            "Dispute not found.".to_string(),
+            None,
+        ));
+    }
+    if dispute.status != PeerReviewDisputeStatus::Pending {
+        return Err(ModelError::new(
+            ModelErrorType::PreconditionFailed,
+            "The dispute has already been resolved.".to_string(),
+            None,
+        ));
+    }
+    let teacher_response = resolution
+        .teacher_response
+        .as_deref()
+        .map(str::trim)
+        .filter(|r| !r.is_empty());
+    let user_exercise_state = user_exercise_states::get_user_exercise_state_if_exists(
+        &mut tx,
+        dispute.user_id,
+        exercise.id,
+        CourseOrExamId::Course(dispute.course_id),
+    )
+    .await?
+    .ok_or_else(|| {
+        ModelError::new(
+            ModelErrorType::NotFound,
+            "The student has no state for the exercise.".to_string(),
+            None,
+        )
+    })?;
+
+    let mut teacher_grading_decision_id = None;
+    match resolution.status {
+        PeerReviewDisputeStatus::Pending => {
+            return Err(ModelError::new(
+                ModelErrorType::PreconditionFailed,
+                "A dispute cannot be resolved as pending.".to_string(),
+                None,
+            ));
+        }
+        PeerReviewDisputeStatus::ReviewDiscarded => {
+            peer_or_self_review_submissions::delete(
+                &mut tx,
+                dispute.peer_or_self_review_submission_id,
+            )
+            .await?;
+            user_exercise_state_updater::update_user_exercise_state(
+                &mut tx,
+                user_exercise_state.id,
+            )
+            .await?;
+        }
+        PeerReviewDisputeStatus::GradeOverridden => {
+            let points = resolution.manual_points.ok_or_else(|| {
+                ModelError::new(
+                    ModelErrorType::PreconditionFailed,
+                    "Give the points when overriding the grade.".to_string(),
+                    None,
+                )
+            })?;
+            if !(0.0..=exercise.score_maximum as f32).contains(&points) {
+                return Err(ModelError::new(
+                    ModelErrorType::PreconditionFailed,
+                    format!(
+                        "The points must be between 0 and {}.",
+                        exercise.score_maximum
+                    ),
+                    None,
+                ));
+            }
+            let decision = teacher_grading_decisions::upsert_by_state_id_and_exercise_id(
+                &mut tx,
+                user_exercise_state.id,
+                exercise.id,
+                TeacherDecisionType::CustomPoints,
+                points,
+                Some(resolver_user_id),
+                teacher_response.map(str::to_string),
+                false,
+            )
+            .await?;
+            teacher_grading_decision_id = Some(decision.id);
+            user_exercise_state_updater::update_user_exercise_state(
+                &mut tx,
+                user_exercise_state.id,
+            )
+            .await?;
+            // Like any teacher grading decision, this ends the peer reviewing of the answer.
+            peer_review_queue_entries::remove_queue_entries_for_unusual_reason(
+                &mut tx,
+                dispute.user_id,
+                exercise.id,
+                dispute.course_id,
+            )
+            .await?;
+        }
+        PeerReviewDisputeStatus::Rejected => {}
+    }
+
+    let dispute = peer_review_disputes::resolve(
+        &mut tx,
+        dispute.id,
+        resolution.status,
+        resolver_user_id,
+        teacher_response,
+        teacher_grading_decision_id,
+    )
+    .await?;
+    let dispute = queue_resolution_email(&mut tx, exercise, dispute).await?;
+    tx.commit().await?;
+    Ok(dispute)
+}
+
+/// Nothing is queued if there is no template for the resolution email, so that disputes can be
+/// resolved before the templates have been written.
+async fn queue_resolution_email(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    mut dispute: PeerReviewDispute,
+) -> ModelResult<PeerReviewDispute> {
+    let course = courses::get_course(conn, dispute.course_id).await?;
+    let language = course
+        .language_code
+        .split(['-', '_'])
+        .next()
+        .unwrap_or(&course.language_code)
+        .to_lowercase();
+    let template = email_templates::get_generic_email_template_by_type_and_language(
+        conn,
+        EmailTemplateType::PeerReviewDisputeResolved,
+        &language,
+    )
+    .await
+    .optional()?;
+    let Some(template) = template else {
+        warn!(
+            peer_review_dispute_id = %dispute.id,
+            "No email template for resolved peer review disputes, the student is not notified by email."
+        );
+        return Ok(dispute);
+    };
+    let placeholders = json!({
+        "COURSE_NAME": course.name,
+        "EXERCISE_NAME": exercise.name,
+        "TEACHER_RESPONSE": dispute.teacher_response.clone().unwrap_or_default(),
+    });
+    let email_delivery_id = email_deliveries::insert_email_delivery_with_placeholders(
+        conn,
+        dispute.user_id,
+        template.id,
+        &placeholders,
+    )
+    .await?;
+    peer_review_disputes::set_email_delivery_id(conn, dispute.id, email_delivery_id).await?;
+    dispute.email_delivery_id = Some(email_delivery_id);
+    Ok(dispute)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        exercise_slide_submissions::NewExerciseSlideSubmission,
+        exercise_task_gradings::UserPointsUpdateStrategy, exercises, peer_or_self_review_configs,
+        test_helper::*, users,
+    };
+
+    #[tokio::test]
+    async fn disputes_can_be_filed_and_resolved() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise, :slide);
+        let exercise = exercises::get_by_id(tx.as_mut(), exercise).await.unwrap();
+        let peer_or_self_review_config = peer_or_self_review_configs::get_by_exercise_or_course_id(
+            tx.as_mut(),
+            &exercise,
+            course,
+        )
+        .await
+        .unwrap();
+        let reviewer = users::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &format!("{}@example.com", Uuid::new_v4()),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        let answer = exercise_slide_submissions::insert_exercise_slide_submission(
+            tx.as_mut(),
+            NewExerciseSlideSubmission {
+                exercise_slide_id: slide,
+                course_id: Some(course),
+                exam_id: None,
+                user_id: user,
+                exercise_id: exercise.id,
+                user_points_update_strategy:
+                    UserPointsUpdateStrategy::CanAddPointsAndCanRemovePoints,
+            },
+        )
+        .await
+        .unwrap();
+        user_exercise_states::get_or_create_user_exercise_state(
+            tx.as_mut(),
+            user,
+            exercise.id,
+            Some(course),
+            None,
+        )
+        .await
+        .unwrap();
+        let review_id = peer_or_self_review_submissions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            reviewer,
+            exercise.id,
+            course,
+            peer_or_self_review_config.id,
+            answer.id,
+        )
+        .await
+        .unwrap();
+
+        // Only the author of the reviewed answer can dispute the review
+        let not_own_review = create_peer_review_dispute_for_user(
+            tx.as_mut(),
+            reviewer,
+            &exercise,
+            &NewPeerReviewDispute {
+                peer_or_self_review_submission_id: review_id,
+                reason: "Unfair".to_string(),
+            },
+        )
+        .await;
+        assert!(not_own_review.is_err());
+        let empty_reason = create_peer_review_dispute_for_user(
+            tx.as_mut(),
+            user,
+            &exercise,
+            &NewPeerReviewDispute {
+                peer_or_self_review_submission_id: review_id,
+                reason: "  ".to_string(),
+            },
+        )
+        .await;
+        assert!(empty_reason.is_err());
+
+        let dispute = create_peer_review_dispute_for_user(
+            tx.as_mut(),
+            user,
+            &exercise,
+            &NewPeerReviewDispute {
+                peer_or_self_review_submission_id: review_id,
+                reason: " The reviewer did not read my answer. ".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(dispute.status, PeerReviewDisputeStatus::Pending);
+        assert_eq!(dispute.reason, "The reviewer did not read my answer.");
+        assert_eq!(
+            peer_review_disputes::get_by_exercise_id(tx.as_mut(), exercise.id, true)
+                .await
+                .unwrap(),
+            vec![dispute.clone()]
+        );
+
+        let resolved = resolve_peer_review_dispute(
+            tx.as_mut(),
+            &exercise,
+            dispute.id,
+            reviewer,
+            &PeerReviewDisputeResolution {
+                status: PeerReviewDisputeStatus::ReviewDiscarded,
+                manual_points: None,
+                teacher_response: Some("You are right.".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(resolved.status, PeerReviewDisputeStatus::ReviewDiscarded);
+        assert_eq!(resolved.resolved_by_user_id, Some(reviewer));
+        assert!(
+            peer_or_self_review_submissions::get_by_id(tx.as_mut(), review_id)
+                .await
+                .is_err()
+        );
+        assert!(
+            peer_review_disputes::get_by_exercise_id(tx.as_mut(), exercise.id, true)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        let resolved_again = resolve_peer_review_dispute(
+            tx.as_mut(),
+            &exercise,
+            dispute.id,
+            reviewer,
+            &PeerReviewDisputeResolution {
+                status: PeerReviewDisputeStatus::Rejected,
+                manual_points: None,
+                teacher_response: None,
+            },
+        )
+        .await;
+        assert!(resolved_again.is_err());
+        tx.rollback().await;
+    }
+}
@@ -937,6 +937,130 @@ mod test {
         assert_eq!(regrading.total_grading_progress, GradingProgress::Failed);
     }
 
+    #[tokio::test]
+    async fn regrading_an_earlier_attempt_follows_the_scoring_strategy() {
+        insert_data!(:tx, :user, :org, :course, instance: _instance, :course_module, :chapter, :page, :exercise, :slide);
+        sqlx::query("UPDATE exercises SET scoring_strategy = 'latest-attempt' WHERE id = $1")
+            .bind(exercise)
+            .execute(tx.as_mut())
+            .await
+            .unwrap();
+        let exercise = exercises::get_by_id(tx.as_mut(), exercise).await.unwrap();
+        let task = models::exercise_tasks::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            NewExerciseTask {
+                exercise_slide_id: slide,
+                exercise_type: "test-exercise".to_string(),
+                assignment: vec![],
+                public_spec: Some(Value::Null),
+                private_spec: Some(Value::Null),
+                model_solution_spec: Some(Value::Null),
+                order_number: 0,
+            },
+        )
+        .await
+        .unwrap();
+        let graded = |score_given| ExerciseTaskGradingResult {
+            grading_progress: models::exercises::GradingProgress::FullyGraded,
+            score_given,
+            score_maximum: 100,
+            feedback_text: None,
+            feedback_json: None,
+            set_user_variables: Some(HashMap::new()),
+        };
+        let submission = StudentExerciseSlideSubmission {
+            exercise_slide_id: slide,
+            exercise_task_submissions: vec![StudentExerciseTaskSubmission {
+                exercise_task_id: task,
+                data_json: Value::Null,
+            }],
+        };
+        let first_attempt = create_initial_submission(
+            tx.as_mut(),
+            user,
+            &exercise,
+            course,
+            slide,
+            submission.clone(),
+            HashMap::from([(task, graded(50.0))]),
+        )
+        .await
+        .unwrap();
+        create_initial_submission(
+            tx.as_mut(),
+            user,
+            &exercise,
+            course,
+            slide,
+            submission,
+            HashMap::from([(task, graded(0.0))]),
+        )
+        .await
+        .unwrap();
+        let mut server = mockito::Server::new_async().await;
+        let _m = server
+            .mock("POST", Matcher::Any)
+            .with_body(serde_json::to_string(&graded(100.0)).unwrap())
+            .create();
+        let service = create_mock_service(tx.as_mut(), "test-exercise".to_string(), 1, &server)
+            .await
+            .unwrap();
+        let services = HashMap::from([("test-exercise".to_string(), service)]);
+
+        let regrading = models::regradings::insert(
+            tx.as_mut(),
+            UserPointsUpdateStrategy::CanAddPointsButCannotRemovePoints,
+        )
+        .await
+        .unwrap();
+        let first_attempt = first_attempt
+            .exercise_task_submission_results
+            .first()
+            .unwrap();
+        models::exercise_task_regrading_submissions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            regrading,
+            first_attempt.submission.id,
+            first_attempt.grading.as_ref().unwrap().id,
+        )
+        .await
+        .unwrap();
+        regrade(tx.as_mut(), &services, |_, _, _| {
+            async {
+                Ok(ExerciseTaskGradingResult {
+                    grading_progress: GradingProgress::FullyGraded,
+                    score_given: 1.0,
+                    score_maximum: 1,
+                    feedback_text: None,
+                    feedback_json: None,
+                    set_user_variables: None,
+                })
+            }
+            .boxed()
+        })
+        .await
+        .unwrap();
+
+        // The latest attempt still counts, even though the regraded earlier one is now better.
+        let user_exercise_state = user_exercise_states::get_or_create_user_exercise_state(
+            tx.as_mut(),
+            user,
+            exercise.id,
+            Some(course),
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(
+            f32_approx_eq(user_exercise_state.score_given.unwrap(), 0.0),
+            "{} != {}",
+            user_exercise_state.score_given.unwrap(),
+            0.0
+        );
+    }
+
     async fn create_initial_submission(
         conn: &mut PgConnection,
         user_id: Uuid,
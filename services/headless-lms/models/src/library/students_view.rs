@@ -283,6 +283,8 @@ user_certs AS (
    AND cm.deleted_at IS NULL
   WHERE cm.course_id = $1
     AND gc.deleted_at IS NULL
+    AND gc.revoked_at IS NULL
+    AND gc.superseded_by_id IS NULL
     AND gc.user_id = ANY($2::uuid[])
   ORDER BY gc.user_id, gc.created_at DESC
 )
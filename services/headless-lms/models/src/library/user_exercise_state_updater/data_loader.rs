@@ -1,13 +1,19 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
 use crate::{
     chapters::{self, DatabaseChapter},
     courses::{self, Course},
     exercise_slide_submissions::ExerciseSlideSubmission,
     exercises::Exercise,
+    late_submission_penalties::{self, LateSubmissionPenalty},
     peer_or_self_review_configs::{self, PeerOrSelfReviewConfig},
     peer_or_self_review_question_submissions::PeerOrSelfReviewQuestionSubmission,
     peer_or_self_review_questions::{self, PeerOrSelfReviewQuestion, PeerOrSelfReviewQuestionType},
     peer_or_self_review_submissions::{self, PeerOrSelfReviewSubmission},
     peer_review_queue_entries::PeerReviewQueueEntry,
+    peer_reviewer_reliabilities,
     prelude::*,
     teacher_grading_decisions::{self, TeacherGradingDecision},
     user_exercise_slide_states::{self, UserExerciseSlideStateGradingSummary},
@@ -35,6 +41,7 @@ pub(super) async fn load_required_data(
         user_exercise_slide_state_grading_summary,
         chapter,
         course,
+        latest_late_submission_penalty,
     } = already_loaded_internal_dependencies;
 
     let loaded_user_exercise_state =
@@ -67,12 +74,37 @@ pub(super) async fn load_required_data(
             &loaded_user_exercise_state,
         )
         .await?,
+        latest_late_submission_penalty: load_latest_late_submission_penalty(
+            conn,
+            latest_late_submission_penalty,
+            &loaded_user_exercise_state,
+        )
+        .await?,
         current_user_exercise_state: loaded_user_exercise_state,
         chapter: loaded_chapter,
         course: loaded_course,
     })
 }
 
+async fn load_latest_late_submission_penalty(
+    conn: &mut PgConnection,
+    latest_late_submission_penalty: Option<Option<LateSubmissionPenalty>>,
+    loaded_user_exercise_state: &UserExerciseState,
+) -> ModelResult<Option<LateSubmissionPenalty>> {
+    if let Some(latest_late_submission_penalty) = latest_late_submission_penalty {
+        info!("Using already loaded latest late submission penalty");
+        Ok(latest_late_submission_penalty)
+    } else {
+        info!("Loading latest late submission penalty");
+        late_submission_penalties::get_for_latest_exercise_slide_submission(
+            conn,
+            loaded_user_exercise_state.user_id,
+            loaded_user_exercise_state.exercise_id,
+        )
+        .await
+    }
+}
+
 async fn load_user_exercise_slide_state_grading_summary(
     conn: &mut PgConnection,
     user_exercise_slide_state_grading_summary: Option<UserExerciseSlideStateGradingSummary>,
@@ -197,6 +229,7 @@ async fn load_peer_or_self_review_information(
             peer_review_queue_entry,
             peer_or_self_review_config,
             peer_or_self_review_questions,
+            received_peer_review_reviewer_reliabilities,
         } = already_loaded_peer_or_self_review_information.unwrap_or_default();
 
         let loaded_latest_exercise_slide_submission = load_latest_exercise_slide_submission(
@@ -210,6 +243,14 @@ async fn load_peer_or_self_review_information(
             load_peer_or_self_review_config(conn, peer_or_self_review_config, loaded_exercise)
                 .await?;
 
+        let loaded_received_peer_or_self_review_question_submissions =
+            load_latest_exercise_slide_submission_received_peer_or_self_review_question_submissions(
+                conn,
+                latest_exercise_slide_submission_received_peer_or_self_review_question_submissions,
+                loaded_latest_exercise_slide_submission.id,
+            )
+            .await?;
+
         Ok(Some(
             UserExerciseStateUpdateRequiredDataPeerReviewInformation {
                 given_peer_or_self_review_submissions: load_given_peer_or_self_review_submissions(
@@ -224,13 +265,15 @@ async fn load_peer_or_self_review_information(
                     loaded_user_exercise_state,
                 )
                 .await?,
-                latest_exercise_slide_submission_received_peer_or_self_review_question_submissions:
-                    load_latest_exercise_slide_submission_received_peer_or_self_review_question_submissions(
+                received_peer_review_reviewer_reliabilities:
+                    load_received_peer_review_reviewer_reliabilities(
                         conn,
-                        latest_exercise_slide_submission_received_peer_or_self_review_question_submissions,
-                        loaded_latest_exercise_slide_submission.id,
+                        received_peer_review_reviewer_reliabilities,
+                        &loaded_received_peer_or_self_review_question_submissions,
                     )
                     .await?,
+                latest_exercise_slide_submission_received_peer_or_self_review_question_submissions:
+                    loaded_received_peer_or_self_review_question_submissions,
                 peer_review_queue_entry: load_peer_review_queue_entry(
                     conn,
                     peer_review_queue_entry,
@@ -382,6 +425,32 @@ async fn load_latest_exercise_slide_submission_received_peer_or_self_review_ques
     }
 }
 
+async fn load_received_peer_review_reviewer_reliabilities(
+    conn: &mut PgConnection,
+    already_loaded_received_peer_review_reviewer_reliabilities: Option<HashMap<Uuid, f32>>,
+    loaded_received_peer_or_self_review_question_submissions: &[PeerOrSelfReviewQuestionSubmission],
+) -> ModelResult<HashMap<Uuid, f32>> {
+    if let Some(reliabilities) = already_loaded_received_peer_review_reviewer_reliabilities {
+        info!("Using already loaded received peer review reviewer reliabilities");
+        Ok(reliabilities)
+    } else {
+        info!("Loading received peer review reviewer reliabilities");
+        let peer_or_self_review_submission_ids =
+            loaded_received_peer_or_self_review_question_submissions
+                .iter()
+                .map(|prqs| prqs.peer_or_self_review_submission_id)
+                .unique()
+                .collect::<Vec<_>>();
+        Ok(
+            peer_reviewer_reliabilities::get_by_peer_or_self_review_submission_ids(
+                conn,
+                &peer_or_self_review_submission_ids,
+            )
+            .await?,
+        )
+    }
+}
+
 async fn load_latest_exercise_slide_submission(
     conn: &mut PgConnection,
     already_loaded_latest_exercise_slide_submission: Option<ExerciseSlideSubmission>,
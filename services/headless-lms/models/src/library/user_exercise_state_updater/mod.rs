@@ -12,6 +12,7 @@ use crate::{
     courses::Course,
     exercise_slide_submissions::ExerciseSlideSubmission,
     exercises::Exercise,
+    late_submission_penalties::LateSubmissionPenalty,
     peer_or_self_review_configs::PeerOrSelfReviewConfig,
     peer_or_self_review_question_submissions::PeerOrSelfReviewQuestionSubmission,
     peer_or_self_review_questions::PeerOrSelfReviewQuestion,
@@ -23,7 +24,7 @@ use crate::{
     user_exercise_states::{self, UserExerciseState, UserExerciseStateUpdate},
 };
 
-use std::default::Default;
+use std::{collections::HashMap, default::Default};
 
 /// Visible only in the current module (and submodules) to prevent misuse.
 #[derive(Debug)]
@@ -41,6 +42,8 @@ pub struct UserExerciseStateUpdateRequiredData {
     pub chapter: Option<DatabaseChapter>,
     /// Course information. Used to check if chapter_locking_enabled is enabled.
     pub course: Option<Course>,
+    /// None if the latest submission was made in time. The slide states already have the penalty applied, so this is only used for points that come from elsewhere, such as peer reviews.
+    pub latest_late_submission_penalty: Option<LateSubmissionPenalty>,
 }
 
 /// Visible only in the current module (and submodules) to prevent misuse.
@@ -53,6 +56,8 @@ pub struct UserExerciseStateUpdateRequiredDataPeerReviewInformation {
     pub peer_review_queue_entry: Option<PeerReviewQueueEntry>,
     pub peer_or_self_review_config: PeerOrSelfReviewConfig,
     pub peer_or_self_review_questions: Vec<PeerOrSelfReviewQuestion>,
+    /// Reliabilities of the reviewers of the latest submission, keyed by peer review submission id. Reviewers without a reliability are missing.
+    pub received_peer_review_reviewer_reliabilities: HashMap<Uuid, f32>,
 }
 
 /**
@@ -69,6 +74,8 @@ pub struct UserExerciseStateUpdateAlreadyLoadedRequiredData {
     pub user_exercise_slide_state_grading_summary: Option<UserExerciseSlideStateGradingSummary>,
     pub chapter: Option<Option<DatabaseChapter>>,
     pub course: Option<Course>,
+    /// The outer option is to indicate whether this cached value is provided or not, and the inner option is to tell whether the latest submission was late or not.
+    pub latest_late_submission_penalty: Option<Option<LateSubmissionPenalty>>,
 }
 
 /**
@@ -85,6 +92,7 @@ pub struct UserExerciseStateUpdateAlreadyLoadedRequiredDataPeerReviewInformation
     pub peer_review_queue_entry: Option<Option<PeerReviewQueueEntry>>,
     pub peer_or_self_review_config: Option<PeerOrSelfReviewConfig>,
     pub peer_or_self_review_questions: Option<Vec<PeerOrSelfReviewQuestion>>,
+    pub received_peer_review_reviewer_reliabilities: Option<HashMap<Uuid, f32>>,
 }
 
 /// Loads all required data and updates user_exercise_state. Also creates completions if needed.
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use headless_lms_utils::numbers::f32_to_two_decimals;
 use itertools::Itertools;
 
@@ -8,6 +10,7 @@ use crate::{
     peer_or_self_review_question_submissions::PeerOrSelfReviewQuestionSubmission,
     peer_or_self_review_questions::{PeerOrSelfReviewQuestion, PeerOrSelfReviewQuestionType},
     prelude::*,
+    teacher_grading_decisions::TeacherDecisionType,
     user_exercise_states::{ReviewingStage, UserExerciseStateUpdate},
 };
 
@@ -130,8 +133,14 @@ fn derive_new_score_given(
     new_reviewing_stage: &ReviewingStage,
     peer_or_self_review_opinion: &Option<PeerOrSelfReviewOpinion>,
 ) -> Option<f32> {
-    // Teacher grading decisions always override everything else
+    // Teacher grading decisions always override everything else. Accepting a late answer with full points still gives only what the late submission penalty leaves, while other decisions are taken as they are.
     if let Some(teacher_grading_decision) = &input_data.latest_teacher_grading_decision {
+        if teacher_grading_decision.teacher_decision == TeacherDecisionType::FullPoints {
+            return Some(apply_late_submission_penalty(
+                input_data,
+                teacher_grading_decision.score_given,
+            ));
+        }
         return Some(teacher_grading_decision.score_given);
     };
 
@@ -160,15 +169,28 @@ fn derive_new_score_given(
     if let Some(peer_or_self_review_opinion) = peer_or_self_review_opinion
         && (input_data.exercise.needs_peer_review || input_data.exercise.needs_self_review)
     {
-        return peer_or_self_review_opinion.score_given;
+        return peer_or_self_review_opinion
+            .score_given
+            .map(|score_given| apply_late_submission_penalty(input_data, score_given));
     }
     // Peer reviews are not enabled, we'll just give the points according to the automated grading
-    // No need to consider the UserPointsUpdateStrategy here because it's already used when updating the user_exercise_slide_state. The user_exercise_state is just taking its data from there (and other sources).
+    // No need to consider the UserPointsUpdateStrategy or the late submission penalty here because they are already used when updating the user_exercise_slide_state. The user_exercise_state is just taking its data from there (and other sources).
     input_data
         .user_exercise_slide_state_grading_summary
         .score_given
 }
 
+/// Reduces points that don't come from the slide states if the latest submission was late.
+fn apply_late_submission_penalty(
+    input_data: &UserExerciseStateUpdateRequiredData,
+    score_given: f32,
+) -> f32 {
+    match &input_data.latest_late_submission_penalty {
+        Some(penalty) => penalty.apply(score_given, input_data.exercise.score_maximum),
+        None => score_given,
+    }
+}
+
 fn derive_new_reviewing_stage(
     input_data: &UserExerciseStateUpdateRequiredData,
     peer_or_self_review_opinion: &Option<PeerOrSelfReviewOpinion>,
@@ -291,17 +313,37 @@ fn get_peer_or_self_review_opinion(
         }
 
         // Users have given and received enough peer reviews, time to consider how we're doing the grading
+        if let Some(reliability_threshold) = info
+            .peer_or_self_review_config
+            .manual_review_reliability_threshold
+            && info.peer_or_self_review_config.processing_strategy
+                != PeerReviewProcessingStrategy::ManualReviewEverything
+        {
+            let average_reliability = calculate_average_reviewer_reliability(
+                &info.latest_exercise_slide_submission_received_peer_or_self_review_question_submissions,
+                &info.received_peer_review_reviewer_reliabilities,
+            );
+            if average_reliability < reliability_threshold {
+                info!(average_reliability = ?average_reliability, reliability_threshold = ?reliability_threshold, peer_review_processing_strategy = ?info.peer_or_self_review_config.processing_strategy, "Not giving points because the reviewers of the answer are not reliable enough. The answer should be moved to manual review.");
+                return Some(PeerOrSelfReviewOpinion {
+                    score_given: None,
+                    reviewing_stage: ReviewingStage::WaitingForManualGrading,
+                });
+            }
+        }
         match info.peer_or_self_review_config.processing_strategy {
             PeerReviewProcessingStrategy::AutomaticallyGradeByAverage => {
                 let avg = calculate_average_received_peer_review_score(
                     &info
                         .latest_exercise_slide_submission_received_peer_or_self_review_question_submissions,
+                    &info.received_peer_review_reviewer_reliabilities,
                 );
                 if !info.peer_or_self_review_config.points_are_all_or_nothing {
                     let score_given = calculate_peer_review_weighted_points(
                         &info.peer_or_self_review_questions,
                         &info
                             .latest_exercise_slide_submission_received_peer_or_self_review_question_submissions,
+                        &info.received_peer_review_reviewer_reliabilities,
                         score_maximum,
                     );
                     Some(PeerOrSelfReviewOpinion {
@@ -326,6 +368,7 @@ fn get_peer_or_self_review_opinion(
                 let avg = calculate_average_received_peer_review_score(
                     &info
                         .latest_exercise_slide_submission_received_peer_or_self_review_question_submissions,
+                    &info.received_peer_review_reviewer_reliabilities,
                 );
                 if avg < info.peer_or_self_review_config.accepting_threshold {
                     info!(avg = ?avg, threshold = ?info.peer_or_self_review_config.accepting_threshold, peer_review_processing_strategy = ?info.peer_or_self_review_config.processing_strategy, "Not giving points because average is below the threshold. The answer should be moved to manual review.");
@@ -338,6 +381,7 @@ fn get_peer_or_self_review_opinion(
                         &info.peer_or_self_review_questions,
                         &info
                             .latest_exercise_slide_submission_received_peer_or_self_review_question_submissions,
+                        &info.received_peer_review_reviewer_reliabilities,
                         score_maximum,
                     );
                     Some(PeerOrSelfReviewOpinion {
@@ -367,8 +411,47 @@ fn get_peer_or_self_review_opinion(
     }
 }
 
+/// The weight of a received peer review is the reliability of its giver. Reviewers who have not been calibrated weigh as much as fully reliable ones.
+fn reviewer_weight(
+    reviewer_reliabilities: &HashMap<Uuid, f32>,
+    peer_or_self_review_submission_id: Uuid,
+) -> f32 {
+    reviewer_reliabilities
+        .get(&peer_or_self_review_submission_id)
+        .copied()
+        .unwrap_or(1.0)
+}
+
+/// Averages (value, weight) pairs. Falls back to a plain average if all the weights are zero.
+fn weighted_average(values_and_weights: &[(f32, f32)]) -> f32 {
+    let total_weight = values_and_weights.iter().map(|(_, w)| w).sum::<f32>();
+    if total_weight < 0.000001 {
+        return values_and_weights.iter().map(|(v, _)| v).sum::<f32>()
+            / values_and_weights.len() as f32;
+    }
+    values_and_weights.iter().map(|(v, w)| v * w).sum::<f32>() / total_weight
+}
+
+fn calculate_average_reviewer_reliability(
+    peer_or_self_review_question_submissions: &[PeerOrSelfReviewQuestionSubmission],
+    reviewer_reliabilities: &HashMap<Uuid, f32>,
+) -> f32 {
+    let weights = peer_or_self_review_question_submissions
+        .iter()
+        .filter(|prqs| prqs.deleted_at.is_none())
+        .map(|prqs| prqs.peer_or_self_review_submission_id)
+        .unique()
+        .map(|id| reviewer_weight(reviewer_reliabilities, id))
+        .collect::<Vec<_>>();
+    if weights.is_empty() {
+        return 1.0;
+    }
+    weights.iter().sum::<f32>() / weights.len() as f32
+}
+
 fn calculate_average_received_peer_review_score(
     peer_or_self_review_question_submissions: &[PeerOrSelfReviewQuestionSubmission],
+    reviewer_reliabilities: &HashMap<Uuid, f32>,
 ) -> f32 {
     let answers_considered = peer_or_self_review_question_submissions
         .iter()
@@ -376,7 +459,15 @@ fn calculate_average_received_peer_review_score(
             if prqs.deleted_at.is_some() {
                 return None;
             }
-            prqs.number_data
+            prqs.number_data.map(|number_data| {
+                (
+                    number_data,
+                    reviewer_weight(
+                        reviewer_reliabilities,
+                        prqs.peer_or_self_review_submission_id,
+                    ),
+                )
+            })
         })
         .collect::<Vec<_>>();
     if answers_considered.is_empty() {
@@ -385,12 +476,13 @@ fn calculate_average_received_peer_review_score(
         );
         return 0.0;
     }
-    answers_considered.iter().sum::<f32>() / answers_considered.len() as f32
+    weighted_average(&answers_considered)
 }
 
 fn calculate_peer_review_weighted_points(
     peer_or_self_review_questions: &[PeerOrSelfReviewQuestion],
     received_peer_or_self_review_question_submissions: &[PeerOrSelfReviewQuestionSubmission],
+    reviewer_reliabilities: &HashMap<Uuid, f32>,
     score_maximum: i32,
 ) -> f32 {
     // Weights should be sum to 1. This should be guranteed by the data loader.
@@ -407,11 +499,6 @@ fn calculate_peer_review_weighted_points(
                     .any(|prq| prq.id == prqs.peer_or_self_review_question_id)
             })
             .collect::<Vec<_>>();
-    let number_of_submissions = question_submissions_considered_for_weighted_points
-        .iter()
-        .map(|prqs| prqs.peer_or_self_review_submission_id)
-        .unique()
-        .count();
     let grouped = question_submissions_considered_for_weighted_points
         .iter()
         .chunk_by(|prqs| prqs.peer_or_self_review_submission_id);
@@ -419,20 +506,23 @@ fn calculate_peer_review_weighted_points(
     let weighted_score_by_submission = grouped
         .into_iter()
         .map(
-            |(_peer_or_self_review_submission_id, peer_review_question_answers)| {
-                peer_review_question_answers
+            |(peer_or_self_review_submission_id, peer_review_question_answers)| {
+                let weighted_score = peer_review_question_answers
                     .filter_map(|prqs| {
                         questions_considered_for_weighted_points
                             .iter()
                             .find(|prq| prq.id == prqs.peer_or_self_review_question_id)
                             .map(|question| question.weight * prqs.number_data.unwrap_or_default())
                     })
-                    .sum::<f32>()
+                    .sum::<f32>();
+                (
+                    weighted_score,
+                    reviewer_weight(reviewer_reliabilities, peer_or_self_review_submission_id),
+                )
             },
         )
         .collect::<Vec<_>>();
-    let average_weighted_score =
-        weighted_score_by_submission.iter().sum::<f32>() / number_of_submissions as f32;
+    let average_weighted_score = weighted_average(&weighted_score_by_submission);
     info!(
         "Average weighted score is {} ({:?})",
         average_weighted_score, weighted_score_by_submission
@@ -453,7 +543,8 @@ mod tests {
         use chrono::TimeZone;
 
         use crate::{
-            exercises::Exercise,
+            exercises::{Exercise, ExerciseScoringStrategy},
+            late_submission_penalties::LateSubmissionPenalty,
             library::user_exercise_state_updater::UserExerciseStateUpdateRequiredDataPeerReviewInformation,
             peer_or_self_review_configs::PeerOrSelfReviewConfig,
             peer_or_self_review_submissions::PeerOrSelfReviewSubmission,
@@ -480,6 +571,7 @@ mod tests {
                     current_user_exercise_state: user_exercise_state,
                     peer_or_self_review_information: None,
                     latest_teacher_grading_decision: None,
+                    latest_late_submission_penalty: None,
                     user_exercise_slide_state_grading_summary:
                         UserExerciseSlideStateGradingSummary {
                             score_given: Some(1.0),
@@ -520,6 +612,7 @@ mod tests {
                     current_user_exercise_state: user_exercise_state,
                     peer_or_self_review_information: None,
                     latest_teacher_grading_decision: None,
+                    latest_late_submission_penalty: None,
                     user_exercise_slide_state_grading_summary:
                         UserExerciseSlideStateGradingSummary {
                             score_given: Some(1.0),
@@ -556,6 +649,7 @@ mod tests {
                     current_user_exercise_state: user_exercise_state,
                     peer_or_self_review_information: None,
                     latest_teacher_grading_decision: None,
+                    latest_late_submission_penalty: None,
                     user_exercise_slide_state_grading_summary:
                         UserExerciseSlideStateGradingSummary {
                             score_given: Some(1.0),
@@ -595,9 +689,11 @@ mod tests {
                             peer_review_queue_entry: None,
                             peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
                             peer_or_self_review_questions: Vec::new(),
+                            received_peer_review_reviewer_reliabilities: HashMap::new(),
                         },
                     ),
                     latest_teacher_grading_decision: None,
+                    latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -640,9 +736,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -661,6 +759,53 @@ mod tests {
                 );
             }
 
+            #[test]
+            fn late_submission_penalty_reduces_the_points_given_by_peer_review() {
+                let id = Uuid::parse_str("5f464818-1e68-4839-ae86-850b310f508c").unwrap();
+                let exercise = create_exercise(CourseOrExamId::Course(id), true, false, true);
+                let user_exercise_state = create_user_exercise_state(
+                    &exercise,
+                    None,
+                    ActivityProgress::Initialized,
+                    ReviewingStage::PeerReview,
+                );
+                let new_user_exercise_state =
+                    derive_new_user_exercise_state(UserExerciseStateUpdateRequiredData {
+                        exercise: exercise.clone(),
+                        current_user_exercise_state: user_exercise_state,
+                        peer_or_self_review_information: Some(
+                            UserExerciseStateUpdateRequiredDataPeerReviewInformation {
+                                given_peer_or_self_review_submissions: vec![create_peer_review_submission(), create_peer_review_submission(), create_peer_review_submission()],
+                                given_self_review_submission: None,
+                                latest_exercise_slide_submission_received_peer_or_self_review_question_submissions: vec![create_peer_review_question_submission(4.0), create_peer_review_question_submission(3.0), create_peer_review_question_submission(4.0)],
+                                peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
+                                peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
+                                peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
+                            },
+                        ),
+                        latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: Some(create_late_submission_penalty(
+                            0.5, 1.0,
+                        )),
+                        user_exercise_slide_state_grading_summary:
+                            UserExerciseSlideStateGradingSummary {
+                                score_given: Some(1.0),
+                                grading_progress: GradingProgress::FullyGraded,
+                            },
+                        chapter: None,
+                        course: exercise.course_id.map(create_course).or_else(|| Some(create_course(id))),
+                    })
+                    .unwrap();
+                assert_results(
+                    &new_user_exercise_state,
+                    // Passed peer review, but the answer was submitted late
+                    Some(4500.0),
+                    ActivityProgress::Completed,
+                    ReviewingStage::ReviewedAndLocked,
+                );
+            }
+
             #[test]
             fn peer_review_automatically_accept_or_reject_by_average_works_gives_zero_points() {
                 let id = Uuid::parse_str("5f464818-1e68-4839-ae86-850b310f508c").unwrap();
@@ -684,9 +829,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -732,9 +879,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeOrManualReviewByAverage),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -777,9 +926,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeOrManualReviewByAverage),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -825,9 +976,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::ManualReviewEverything),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -870,9 +1023,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::ManualReviewEverything),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -893,6 +1048,8 @@ mod tests {
         }
 
         mod calculate_peer_review_weighted_points {
+            use std::collections::HashMap;
+
             use uuid::Uuid;
 
             use crate::library::user_exercise_state_updater::state_deriver::{
@@ -943,10 +1100,135 @@ mod tests {
                         // Extra one to check that ignoring questions works
                         create_peer_review_question_submission_with_ids(3.0, e1_id, s3_id),
                     ],
+                    &HashMap::new(),
                     4,
                 );
                 assert_eq!(res, 3.0);
             }
+
+            #[test]
+            fn weights_reviews_by_reviewer_reliability() {
+                let q1_id = Uuid::parse_str("d42ecbc9-34ff-4549-aacf-1b8ac6e672c2").unwrap();
+                let s1_id = Uuid::parse_str("2795b352-d5ef-41c7-92f7-a60d90c62c91").unwrap();
+                let s2_id = Uuid::parse_str("e5c16a89-2a3f-4910-9b00-dd981cedcbcc").unwrap();
+
+                let res = calculate_peer_review_weighted_points(
+                    &[create_peer_review_question_scale(q1_id, 1.0)],
+                    &[
+                        create_peer_review_question_submission_with_ids(5.0, q1_id, s1_id),
+                        create_peer_review_question_submission_with_ids(1.0, q1_id, s2_id),
+                    ],
+                    // The first reviewer counts three times as much as the second one
+                    &HashMap::from([(s1_id, 0.75), (s2_id, 0.25)]),
+                    5,
+                );
+                assert_eq!(res, 4.0);
+            }
+        }
+
+        mod reviewer_reliability {
+            use super::*;
+
+            fn derive_with_reviewer_reliabilities(
+                manual_review_reliability_threshold: Option<f32>,
+                received_peer_review_reviewer_reliabilities: HashMap<Uuid, f32>,
+            ) -> UserExerciseStateUpdate {
+                let id = Uuid::parse_str("5f464818-1e68-4839-ae86-850b310f508c").unwrap();
+                let q1_id = Uuid::parse_str("d42ecbc9-34ff-4549-aacf-1b8ac6e672c2").unwrap();
+                let s1_id = Uuid::parse_str("2795b352-d5ef-41c7-92f7-a60d90c62c91").unwrap();
+                let s2_id = Uuid::parse_str("e5c16a89-2a3f-4910-9b00-dd981cedcbcc").unwrap();
+                let s3_id = Uuid::parse_str("462a6493-a506-42e6-869d-10220b2885b8").unwrap();
+                let exercise = create_exercise(CourseOrExamId::Course(id), true, false, true);
+                let user_exercise_state = create_user_exercise_state(
+                    &exercise,
+                    None,
+                    ActivityProgress::Initialized,
+                    ReviewingStage::PeerReview,
+                );
+                let mut peer_or_self_review_config = create_peer_or_self_review_config(
+                    PeerReviewProcessingStrategy::AutomaticallyGradeByAverage,
+                );
+                peer_or_self_review_config.manual_review_reliability_threshold =
+                    manual_review_reliability_threshold;
+                derive_new_user_exercise_state(UserExerciseStateUpdateRequiredData {
+                    exercise: exercise.clone(),
+                    current_user_exercise_state: user_exercise_state,
+                    peer_or_self_review_information: Some(
+                        UserExerciseStateUpdateRequiredDataPeerReviewInformation {
+                            given_peer_or_self_review_submissions: vec![create_peer_review_submission(), create_peer_review_submission(), create_peer_review_submission()],
+                            given_self_review_submission: None,
+                            // Unweighted average below 2.1
+                            latest_exercise_slide_submission_received_peer_or_self_review_question_submissions: vec![
+                                create_peer_review_question_submission_with_ids(4.0, q1_id, s1_id),
+                                create_peer_review_question_submission_with_ids(1.0, q1_id, s2_id),
+                                create_peer_review_question_submission_with_ids(1.0, q1_id, s3_id),
+                            ],
+                            peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
+                            peer_or_self_review_config,
+                            peer_or_self_review_questions: Vec::new(),
+                            received_peer_review_reviewer_reliabilities,
+                        },
+                    ),
+                    latest_teacher_grading_decision: None,
+                    latest_late_submission_penalty: None,
+                    user_exercise_slide_state_grading_summary:
+                        UserExerciseSlideStateGradingSummary {
+                            score_given: Some(1.0),
+                            grading_progress: GradingProgress::FullyGraded,
+                        },
+                    chapter: None,
+                    course: exercise.course_id.map(create_course).or_else(|| Some(create_course(id))),
+                })
+                .unwrap()
+            }
+
+            #[test]
+            fn uncalibrated_reviewers_are_weighted_equally() {
+                let new_user_exercise_state =
+                    derive_with_reviewer_reliabilities(None, HashMap::new());
+                assert_results(
+                    &new_user_exercise_state,
+                    Some(0.0),
+                    ActivityProgress::Completed,
+                    ReviewingStage::ReviewedAndLocked,
+                );
+            }
+
+            #[test]
+            fn unreliable_reviewers_weigh_less() {
+                let s1_id = Uuid::parse_str("2795b352-d5ef-41c7-92f7-a60d90c62c91").unwrap();
+                let s2_id = Uuid::parse_str("e5c16a89-2a3f-4910-9b00-dd981cedcbcc").unwrap();
+                let s3_id = Uuid::parse_str("462a6493-a506-42e6-869d-10220b2885b8").unwrap();
+                // Weighted average (0.9 * 4 + 0.1 * 1 + 0.1 * 1) / 1.1 is above 2.1
+                let new_user_exercise_state = derive_with_reviewer_reliabilities(
+                    None,
+                    HashMap::from([(s1_id, 0.9), (s2_id, 0.1), (s3_id, 0.1)]),
+                );
+                assert_results(
+                    &new_user_exercise_state,
+                    Some(9000.0),
+                    ActivityProgress::Completed,
+                    ReviewingStage::ReviewedAndLocked,
+                );
+            }
+
+            #[test]
+            fn answers_reviewed_by_unreliable_reviewers_are_put_to_manual_review() {
+                let s1_id = Uuid::parse_str("2795b352-d5ef-41c7-92f7-a60d90c62c91").unwrap();
+                let s2_id = Uuid::parse_str("e5c16a89-2a3f-4910-9b00-dd981cedcbcc").unwrap();
+                let s3_id = Uuid::parse_str("462a6493-a506-42e6-869d-10220b2885b8").unwrap();
+                // Average reliability 0.4
+                let new_user_exercise_state = derive_with_reviewer_reliabilities(
+                    Some(0.5),
+                    HashMap::from([(s1_id, 0.9), (s2_id, 0.1), (s3_id, 0.2)]),
+                );
+                assert_results(
+                    &new_user_exercise_state,
+                    None,
+                    ActivityProgress::Completed,
+                    ReviewingStage::WaitingForManualGrading,
+                );
+            }
         }
 
         mod self_review {
@@ -974,9 +1256,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -1016,9 +1300,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(false)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -1058,9 +1344,11 @@ mod tests {
                                 peer_review_queue_entry: Some(create_peer_review_queue_entry(true)),
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -1101,9 +1389,11 @@ mod tests {
                                 peer_review_queue_entry: None,
                                 peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
                                 peer_or_self_review_questions: Vec::new(),
+                                received_peer_review_reviewer_reliabilities: HashMap::new(),
                             },
                         ),
                         latest_teacher_grading_decision: None,
+                        latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -1144,9 +1434,11 @@ mod tests {
                             peer_review_queue_entry: None,
                             peer_or_self_review_config: create_peer_or_self_review_config(PeerReviewProcessingStrategy::AutomaticallyGradeByAverage),
                             peer_or_self_review_questions: Vec::new(),
+                            received_peer_review_reviewer_reliabilities: HashMap::new(),
                         },
                     ),
                     latest_teacher_grading_decision: None,
+                    latest_late_submission_penalty: None,
                         user_exercise_slide_state_grading_summary:
                             UserExerciseSlideStateGradingSummary {
                                 score_given: Some(1.0),
@@ -1204,6 +1496,9 @@ mod tests {
                 exercise_language_group_id: None,
                 needs_self_review,
                 teacher_reviews_answer_after_locking: true,
+                scoring_strategy: ExerciseScoringStrategy::BestAttempt,
+                scoring_strategy_attempt_count: None,
+                scoring_strategy_penalty_percent: None,
             }
         }
 
@@ -1242,6 +1537,26 @@ mod tests {
             }
         }
 
+        fn create_late_submission_penalty(
+            points_multiplier: f32,
+            max_points_ratio: f32,
+        ) -> LateSubmissionPenalty {
+            let id = Uuid::parse_str("5f464818-1e68-4839-ae86-850b310f508c").unwrap();
+            LateSubmissionPenalty {
+                id,
+                exercise_slide_submission_id: id,
+                late_submission_policy_id: id,
+                user_id: id,
+                exercise_id: id,
+                deadline: Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap(),
+                points_multiplier,
+                max_points_ratio,
+                created_at: Utc.with_ymd_and_hms(2022, 1, 2, 0, 0, 0).unwrap(),
+                updated_at: Utc.with_ymd_and_hms(2022, 1, 2, 0, 0, 0).unwrap(),
+                deleted_at: None,
+            }
+        }
+
         fn create_peer_or_self_review_config(
             processing_strategy: PeerReviewProcessingStrategy,
         ) -> PeerOrSelfReviewConfig {
@@ -1261,6 +1576,8 @@ mod tests {
                 manual_review_cutoff_in_days: 21,
                 points_are_all_or_nothing: true,
                 review_instructions: None,
+                calibration_reviews_to_give: 0,
+                manual_review_reliability_threshold: None,
             }
         }
 
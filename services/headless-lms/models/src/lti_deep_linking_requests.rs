@@ -0,0 +1,111 @@
+//! LTI 1.3 Deep Linking requests, where a teacher on a platform picks which of our chapters or
+//! exercises to add to their course.
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct LtiDeepLinkingRequest {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub lti_platform_id: Uuid,
+    pub deployment_id: String,
+    pub user_id: Uuid,
+    pub deep_link_return_url: String,
+    /// Returned to the platform unchanged.
+    pub data: Option<String>,
+    pub accept_multiple: bool,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewLtiDeepLinkingRequest {
+    pub lti_platform_id: Uuid,
+    pub deployment_id: String,
+    pub user_id: Uuid,
+    pub deep_link_return_url: String,
+    pub data: Option<String>,
+    pub accept_multiple: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    request: &NewLtiDeepLinkingRequest,
+) -> ModelResult<LtiDeepLinkingRequest> {
+    let res = sqlx::query_as!(
+        LtiDeepLinkingRequest,
+        r#"
+INSERT INTO lti_deep_linking_requests (
+    lti_platform_id,
+    deployment_id,
+    user_id,
+    deep_link_return_url,
+    data,
+    accept_multiple,
+    expires_at
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING *
+        "#,
+        request.lti_platform_id,
+        request.deployment_id,
+        request.user_id,
+        request.deep_link_return_url,
+        request.data,
+        request.accept_multiple,
+        request.expires_at
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Returns the request if the user started it and it can still be completed.
+pub async fn get_open_by_id(
+    conn: &mut PgConnection,
+    id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<LtiDeepLinkingRequest> {
+    let res = sqlx::query_as!(
+        LtiDeepLinkingRequest,
+        r#"
+SELECT *
+FROM lti_deep_linking_requests
+WHERE id = $1
+  AND user_id = $2
+  AND used_at IS NULL
+  AND expires_at > now()
+  AND deleted_at IS NULL
+        "#,
+        id,
+        user_id
+    )
+    .fetch_optional(conn)
+    .await?;
+    res.ok_or_else(|| {
+        model_err!(
+            NotFound,
+            "The content selection has expired or has already been completed. Start it again from the platform."
+                .to_string()
+        )
+    })
+}
+
+pub async fn mark_used(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE lti_deep_linking_requests
+SET used_at = now()
+WHERE id = $1
+",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
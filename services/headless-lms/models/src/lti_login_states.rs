@@ -0,0 +1,127 @@
+//! The state and nonce of LTI 1.3 third-party-initiated logins that are waiting for the launch.
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct LtiLoginState {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub lti_platform_id: Uuid,
+    pub state: String,
+    pub nonce: String,
+    pub target_link_uri: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    lti_platform_id: Uuid,
+    state: &str,
+    nonce: &str,
+    target_link_uri: &str,
+    expires_at: DateTime<Utc>,
+) -> ModelResult<LtiLoginState> {
+    let res = sqlx::query_as!(
+        LtiLoginState,
+        r#"
+INSERT INTO lti_login_states (
+    lti_platform_id,
+    state,
+    nonce,
+    target_link_uri,
+    expires_at
+  )
+VALUES ($1, $2, $3, $4, $5)
+RETURNING *
+        "#,
+        lti_platform_id,
+        state,
+        nonce,
+        target_link_uri,
+        expires_at
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Marks the state as used and returns it. Fails if the state does not exist, has expired or has
+/// already been used, so a launch cannot be replayed.
+pub async fn consume(conn: &mut PgConnection, state: &str) -> ModelResult<LtiLoginState> {
+    let res = sqlx::query_as!(
+        LtiLoginState,
+        r#"
+UPDATE lti_login_states
+SET used_at = now()
+WHERE state = $1
+  AND used_at IS NULL
+  AND expires_at > now()
+  AND deleted_at IS NULL
+RETURNING *
+        "#,
+        state
+    )
+    .fetch_optional(conn)
+    .await?;
+    res.ok_or_else(|| {
+        model_err!(
+            PreconditionFailed,
+            "The login state is unknown, expired or already used. Try launching again from the platform."
+                .to_string()
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{lti_platforms::NewLtiPlatform, test_helper::*};
+
+    #[tokio::test]
+    async fn state_can_only_be_consumed_once() {
+        insert_data!(:tx, :user, :org);
+        let platform = crate::lti_platforms::insert(
+            tx.as_mut(),
+            org,
+            &NewLtiPlatform {
+                name: "Platform".to_string(),
+                issuer: "https://lms.example.com".to_string(),
+                client_id: "client".to_string(),
+                deployment_ids: vec![],
+                auth_login_url: "https://lms.example.com/auth".to_string(),
+                auth_token_url: "https://lms.example.com/token".to_string(),
+                jwks_url: "https://lms.example.com/jwks".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        insert(
+            tx.as_mut(),
+            platform.id,
+            "state",
+            "nonce",
+            "https://courses.mooc.fi",
+            Utc::now() + chrono::Duration::minutes(5),
+        )
+        .await
+        .unwrap();
+        insert(
+            tx.as_mut(),
+            platform.id,
+            "expired",
+            "nonce",
+            "https://courses.mooc.fi",
+            Utc::now() - chrono::Duration::minutes(5),
+        )
+        .await
+        .unwrap();
+
+        let consumed = consume(tx.as_mut(), "state").await.unwrap();
+        assert_eq!(consumed.nonce, "nonce");
+        assert!(consume(tx.as_mut(), "state").await.is_err());
+        assert!(consume(tx.as_mut(), "expired").await.is_err());
+    }
+}
@@ -0,0 +1,273 @@
+//! LTI 1.3 platforms, such as the Moodle or Canvas of a partner university, that may launch the
+//! course material of an organization.
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct LtiPlatform {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub organization_id: Uuid,
+    pub name: String,
+    /// The `iss` claim of the tokens the platform signs.
+    pub issuer: String,
+    /// The client id the platform assigned to our tool.
+    pub client_id: String,
+    pub deployment_ids: Vec<String>,
+    pub auth_login_url: String,
+    pub auth_token_url: String,
+    pub jwks_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct NewLtiPlatform {
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub deployment_ids: Vec<String>,
+    pub auth_login_url: String,
+    pub auth_token_url: String,
+    pub jwks_url: String,
+}
+
+impl NewLtiPlatform {
+    fn validate(&self) -> ModelResult<()> {
+        if self.name.trim().is_empty()
+            || self.issuer.trim().is_empty()
+            || self.client_id.trim().is_empty()
+        {
+            return Err(model_err!(
+                PreconditionFailed,
+                "The name, issuer and client id of the platform are required.".to_string()
+            ));
+        }
+        if self.deployment_ids.iter().any(|id| id.trim().is_empty()) {
+            return Err(model_err!(
+                PreconditionFailed,
+                "Deployment ids cannot be empty.".to_string()
+            ));
+        }
+        for url in [&self.auth_login_url, &self.auth_token_url, &self.jwks_url] {
+            if !(url.starts_with("https://") || url.starts_with("http://")) {
+                return Err(model_err!(
+                    PreconditionFailed,
+                    format!("'{url}' is not an absolute URL.")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    organization_id: Uuid,
+    platform: &NewLtiPlatform,
+) -> ModelResult<LtiPlatform> {
+    platform.validate()?;
+    let res = sqlx::query_as!(
+        LtiPlatform,
+        r#"
+INSERT INTO lti_platforms (
+    organization_id,
+    name,
+    issuer,
+    client_id,
+    deployment_ids,
+    auth_login_url,
+    auth_token_url,
+    jwks_url
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+RETURNING *
+        "#,
+        organization_id,
+        platform.name.trim(),
+        platform.issuer.trim(),
+        platform.client_id.trim(),
+        &platform.deployment_ids,
+        platform.auth_login_url,
+        platform.auth_token_url,
+        platform.jwks_url,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<LtiPlatform> {
+    let res = sqlx::query_as!(
+        LtiPlatform,
+        r#"
+SELECT *
+FROM lti_platforms
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_organization_id(
+    conn: &mut PgConnection,
+    organization_id: Uuid,
+) -> ModelResult<Vec<LtiPlatform>> {
+    let res = sqlx::query_as!(
+        LtiPlatform,
+        r#"
+SELECT *
+FROM lti_platforms
+WHERE organization_id = $1
+  AND deleted_at IS NULL
+ORDER BY name
+        "#,
+        organization_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Finds the platform a login initiation or launch comes from. The client id is optional in the
+/// login initiation, in which case the issuer alone has to identify the platform.
+pub async fn find_by_issuer_and_client_id(
+    conn: &mut PgConnection,
+    issuer: &str,
+    client_id: Option<&str>,
+) -> ModelResult<Option<LtiPlatform>> {
+    let mut res = sqlx::query_as!(
+        LtiPlatform,
+        r#"
+SELECT *
+FROM lti_platforms
+WHERE issuer = $1
+  AND (
+    $2::VARCHAR IS NULL
+    OR client_id = $2
+  )
+  AND deleted_at IS NULL
+LIMIT 2
+        "#,
+        issuer,
+        client_id
+    )
+    .fetch_all(conn)
+    .await?;
+    if res.len() > 1 {
+        return Err(model_err!(
+            PreconditionFailed,
+            "Several tools are registered for the platform, so the client id is required."
+                .to_string()
+        ));
+    }
+    Ok(res.pop())
+}
+
+pub async fn update(
+    conn: &mut PgConnection,
+    id: Uuid,
+    platform: &NewLtiPlatform,
+) -> ModelResult<LtiPlatform> {
+    platform.validate()?;
+    let res = sqlx::query_as!(
+        LtiPlatform,
+        r#"
+UPDATE lti_platforms
+SET name = $2,
+  issuer = $3,
+  client_id = $4,
+  deployment_ids = $5,
+  auth_login_url = $6,
+  auth_token_url = $7,
+  jwks_url = $8
+WHERE id = $1
+  AND deleted_at IS NULL
+RETURNING *
+        "#,
+        id,
+        platform.name.trim(),
+        platform.issuer.trim(),
+        platform.client_id.trim(),
+        &platform.deployment_ids,
+        platform.auth_login_url,
+        platform.auth_token_url,
+        platform.jwks_url,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn delete(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE lti_platforms
+SET deleted_at = now()
+WHERE id = $1
+  AND deleted_at IS NULL
+",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helper::*;
+
+    fn new_platform(client_id: &str) -> NewLtiPlatform {
+        NewLtiPlatform {
+            name: "Partner university".to_string(),
+            issuer: "https://moodle.example.com".to_string(),
+            client_id: client_id.to_string(),
+            deployment_ids: vec!["1".to_string()],
+            auth_login_url: "https://moodle.example.com/mod/lti/auth.php".to_string(),
+            auth_token_url: "https://moodle.example.com/mod/lti/token.php".to_string(),
+            jwks_url: "https://moodle.example.com/mod/lti/certs.php".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn client_id_is_required_only_when_the_issuer_is_ambiguous() {
+        insert_data!(:tx, :user, :org);
+        let first = insert(tx.as_mut(), org, &new_platform("first"))
+            .await
+            .unwrap();
+        let found = find_by_issuer_and_client_id(tx.as_mut(), "https://moodle.example.com", None)
+            .await
+            .unwrap();
+        assert_eq!(found.map(|p| p.id), Some(first.id));
+
+        let second = insert(tx.as_mut(), org, &new_platform("second"))
+            .await
+            .unwrap();
+        assert!(
+            find_by_issuer_and_client_id(tx.as_mut(), "https://moodle.example.com", None)
+                .await
+                .is_err()
+        );
+        let found =
+            find_by_issuer_and_client_id(tx.as_mut(), "https://moodle.example.com", Some("second"))
+                .await
+                .unwrap();
+        assert_eq!(found.map(|p| p.id), Some(second.id));
+    }
+
+    #[tokio::test]
+    async fn urls_must_be_absolute() {
+        insert_data!(:tx, :user, :org);
+        let mut platform = new_platform("first");
+        platform.jwks_url = "/certs.php".to_string();
+        assert!(insert(tx.as_mut(), org, &platform).await.is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! The users who have launched an LTI resource link, and the state of passing their scores back
+//! to the platform.
+
+use crate::{
+    exercises::{ActivityProgress, GradingProgress},
+    prelude::*,
+};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct LtiResourceLinkUser {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub lti_resource_link_id: Uuid,
+    pub user_id: Uuid,
+    pub lti_user_id: Uuid,
+    pub last_launched_at: DateTime<Utc>,
+    pub score_synced_at: Option<DateTime<Utc>>,
+    pub score_sync_failed_at: Option<DateTime<Utc>>,
+    pub score_sync_error: Option<String>,
+}
+
+/// The score of a user in the content a resource link points to, summed over its exercises.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PendingScoreSync {
+    pub lti_resource_link_user_id: Uuid,
+    pub lti_platform_id: Uuid,
+    pub ags_lineitem_url: String,
+    /// The platform user the score is for.
+    pub subject: String,
+    pub score_given: f32,
+    pub score_maximum: i64,
+    pub exercise_count: i64,
+    /// How many of the exercises the user has a state in.
+    pub started_exercise_count: i64,
+    /// The least advanced activity progress among the exercises the user has a state in.
+    pub least_activity_progress: Option<ActivityProgress>,
+    /// The least finished grading progress among the exercises the user has a state in.
+    pub least_grading_progress: Option<GradingProgress>,
+    /// When the state of the user in any of the exercises last changed.
+    pub latest_state_change: DateTime<Utc>,
+}
+
+/// Records that the user has launched the link.
+pub async fn upsert(
+    conn: &mut PgConnection,
+    lti_resource_link_id: Uuid,
+    user_id: Uuid,
+    lti_user_id: Uuid,
+) -> ModelResult<LtiResourceLinkUser> {
+    let res = sqlx::query_as!(
+        LtiResourceLinkUser,
+        r#"
+INSERT INTO lti_resource_link_users (lti_resource_link_id, user_id, lti_user_id)
+VALUES ($1, $2, $3) ON CONFLICT (lti_resource_link_id, user_id)
+WHERE deleted_at IS NULL DO
+UPDATE
+SET last_launched_at = now(),
+  lti_user_id = excluded.lti_user_id
+RETURNING *
+        "#,
+        lti_resource_link_id,
+        user_id,
+        lti_user_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Finds the scores that have changed since they were last passed back. Scores whose sync failed
+/// after `retry_failed_before` are left for a later run.
+///
+/// A link to an exercise counts that exercise, a link to a chapter the exercises of the chapter,
+/// and a link to a course every exercise of the course.
+pub async fn get_pending_score_syncs(
+    conn: &mut PgConnection,
+    retry_failed_before: DateTime<Utc>,
+    limit: i64,
+) -> ModelResult<Vec<PendingScoreSync>> {
+    let res = sqlx::query_as!(
+        PendingScoreSync,
+        r#"
+SELECT lu.id AS lti_resource_link_user_id,
+  rl.lti_platform_id,
+  rl.ags_lineitem_url AS "ags_lineitem_url!",
+  u.subject,
+  COALESCE(SUM(s.score_given), 0)::REAL AS "score_given!",
+  COALESCE(SUM(e.score_maximum), 0)::BIGINT AS "score_maximum!",
+  COUNT(e.id) AS "exercise_count!",
+  COUNT(s.id) AS "started_exercise_count!",
+  MIN(s.activity_progress) AS least_activity_progress,
+  MAX(s.grading_progress) AS least_grading_progress,
+  MAX(s.updated_at) AS "latest_state_change!"
+FROM lti_resource_link_users lu
+  JOIN lti_resource_links rl ON rl.id = lu.lti_resource_link_id
+  JOIN lti_users u ON u.id = lu.lti_user_id
+  JOIN lti_platforms p ON p.id = rl.lti_platform_id
+  JOIN exercises e ON e.deleted_at IS NULL
+  AND (
+    e.id = rl.exercise_id
+    OR (
+      rl.exercise_id IS NULL
+      AND e.chapter_id = rl.chapter_id
+    )
+    OR (
+      rl.exercise_id IS NULL
+      AND rl.chapter_id IS NULL
+      AND e.course_id = rl.course_id
+    )
+  )
+  LEFT JOIN user_exercise_states s ON s.exercise_id = e.id
+  AND s.user_id = lu.user_id
+  AND s.deleted_at IS NULL
+WHERE lu.deleted_at IS NULL
+  AND rl.deleted_at IS NULL
+  AND u.deleted_at IS NULL
+  AND p.deleted_at IS NULL
+  AND rl.ags_lineitem_url IS NOT NULL
+  AND (
+    lu.score_sync_failed_at IS NULL
+    OR lu.score_sync_failed_at < $1
+  )
+GROUP BY lu.id,
+  rl.id,
+  u.subject
+HAVING MAX(s.updated_at) > COALESCE(lu.score_synced_at, '-infinity')
+ORDER BY MAX(s.updated_at)
+LIMIT $2
+        "#,
+        retry_failed_before,
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// `synced_state_change` is the [`PendingScoreSync::latest_state_change`] of the score that was
+/// sent, so changes made while the score was being sent are picked up by the next run.
+pub async fn mark_score_synced(
+    conn: &mut PgConnection,
+    id: Uuid,
+    synced_state_change: DateTime<Utc>,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE lti_resource_link_users
+SET score_synced_at = $2,
+  score_sync_failed_at = NULL,
+  score_sync_error = NULL
+WHERE id = $1
+",
+        id,
+        synced_state_change
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_score_sync_failed(
+    conn: &mut PgConnection,
+    id: Uuid,
+    error: &str,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE lti_resource_link_users
+SET score_sync_failed_at = now(),
+  score_sync_error = $2
+WHERE id = $1
+",
+        id,
+        error
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        lti_platforms::NewLtiPlatform, lti_resource_links::NewLtiResourceLink, test_helper::*,
+    };
+
+    #[tokio::test]
+    async fn changed_scores_are_pending_until_synced() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise);
+        let platform = crate::lti_platforms::insert(
+            tx.as_mut(),
+            org,
+            &NewLtiPlatform {
+                name: "Platform".to_string(),
+                issuer: "https://lms.example.com".to_string(),
+                client_id: "client".to_string(),
+                deployment_ids: vec!["1".to_string()],
+                auth_login_url: "https://lms.example.com/auth".to_string(),
+                auth_token_url: "https://lms.example.com/token".to_string(),
+                jwks_url: "https://lms.example.com/jwks".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let link = crate::lti_resource_links::upsert(
+            tx.as_mut(),
+            &NewLtiResourceLink {
+                lti_platform_id: platform.id,
+                deployment_id: "1".to_string(),
+                resource_link_id: "link-1".to_string(),
+                context_id: None,
+                context_title: None,
+                course_id: course,
+                chapter_id: None,
+                exercise_id: Some(exercise),
+                ags_lineitem_url: Some("https://lms.example.com/lineitems/1".to_string()),
+                nrps_context_memberships_url: None,
+            },
+        )
+        .await
+        .unwrap();
+        let lti_user = crate::lti_users::get_or_create(tx.as_mut(), platform.id, "sub", None, None)
+            .await
+            .unwrap();
+        let link_user = upsert(tx.as_mut(), link.id, lti_user.user_id, lti_user.id)
+            .await
+            .unwrap();
+
+        // Nothing to send before the user has done anything.
+        let pending = get_pending_score_syncs(tx.as_mut(), Utc::now(), 10)
+            .await
+            .unwrap();
+        assert!(pending.is_empty());
+
+        crate::user_exercise_states::get_or_create_user_exercise_state(
+            tx.as_mut(),
+            lti_user.user_id,
+            exercise,
+            Some(course),
+            None,
+        )
+        .await
+        .unwrap();
+        let pending = get_pending_score_syncs(tx.as_mut(), Utc::now(), 10)
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].lti_resource_link_user_id, link_user.id);
+        assert_eq!(pending[0].subject, "sub");
+        assert_eq!(pending[0].exercise_count, 1);
+        assert_eq!(pending[0].started_exercise_count, 1);
+
+        mark_score_synced(tx.as_mut(), link_user.id, pending[0].latest_state_change)
+            .await
+            .unwrap();
+        let pending = get_pending_score_syncs(tx.as_mut(), Utc::now(), 10)
+            .await
+            .unwrap();
+        assert!(pending.is_empty());
+    }
+}
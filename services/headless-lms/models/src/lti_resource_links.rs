@@ -0,0 +1,158 @@
+//! Links in the courses of LTI 1.3 platforms that launch our course material.
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct LtiResourceLink {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub lti_platform_id: Uuid,
+    pub deployment_id: String,
+    /// The id of the link on the platform.
+    pub resource_link_id: String,
+    pub context_id: Option<String>,
+    pub context_title: Option<String>,
+    pub course_id: Uuid,
+    pub chapter_id: Option<Uuid>,
+    pub exercise_id: Option<Uuid>,
+    /// The gradebook column scores are passed back to. If `None`, scores are not passed back.
+    pub ags_lineitem_url: Option<String>,
+    pub nrps_context_memberships_url: Option<String>,
+}
+
+/// What the platform told about the link in a launch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewLtiResourceLink {
+    pub lti_platform_id: Uuid,
+    pub deployment_id: String,
+    pub resource_link_id: String,
+    pub context_id: Option<String>,
+    pub context_title: Option<String>,
+    pub course_id: Uuid,
+    pub chapter_id: Option<Uuid>,
+    pub exercise_id: Option<Uuid>,
+    pub ags_lineitem_url: Option<String>,
+    pub nrps_context_memberships_url: Option<String>,
+}
+
+/// A resource link together with how many users have launched it, for listing the links of a course.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct LtiResourceLinkSummary {
+    pub id: Uuid,
+    pub lti_platform_id: Uuid,
+    pub lti_platform_name: String,
+    pub resource_link_id: String,
+    pub context_title: Option<String>,
+    pub chapter_id: Option<Uuid>,
+    pub exercise_id: Option<Uuid>,
+    pub grade_passback_enabled: bool,
+    pub roster_available: bool,
+    pub user_count: i64,
+    pub last_launched_at: Option<DateTime<Utc>>,
+}
+
+/// Creates the link on its first launch and updates it on the following ones, since the platform
+/// may change for example the title of the course or the gradebook column.
+pub async fn upsert(
+    conn: &mut PgConnection,
+    link: &NewLtiResourceLink,
+) -> ModelResult<LtiResourceLink> {
+    let res = sqlx::query_as!(
+        LtiResourceLink,
+        r#"
+INSERT INTO lti_resource_links (
+    lti_platform_id,
+    deployment_id,
+    resource_link_id,
+    context_id,
+    context_title,
+    course_id,
+    chapter_id,
+    exercise_id,
+    ags_lineitem_url,
+    nrps_context_memberships_url
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) ON CONFLICT (lti_platform_id, resource_link_id)
+WHERE deleted_at IS NULL DO
+UPDATE
+SET deployment_id = excluded.deployment_id,
+  context_id = excluded.context_id,
+  context_title = excluded.context_title,
+  course_id = excluded.course_id,
+  chapter_id = excluded.chapter_id,
+  exercise_id = excluded.exercise_id,
+  ags_lineitem_url = excluded.ags_lineitem_url,
+  nrps_context_memberships_url = excluded.nrps_context_memberships_url
+RETURNING *
+        "#,
+        link.lti_platform_id,
+        link.deployment_id,
+        link.resource_link_id,
+        link.context_id,
+        link.context_title,
+        link.course_id,
+        link.chapter_id,
+        link.exercise_id,
+        link.ags_lineitem_url,
+        link.nrps_context_memberships_url
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<LtiResourceLink> {
+    let res = sqlx::query_as!(
+        LtiResourceLink,
+        r#"
+SELECT *
+FROM lti_resource_links
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_summaries_by_course_id(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+) -> ModelResult<Vec<LtiResourceLinkSummary>> {
+    let res = sqlx::query_as!(
+        LtiResourceLinkSummary,
+        r#"
+SELECT rl.id,
+  rl.lti_platform_id,
+  p.name AS lti_platform_name,
+  rl.resource_link_id,
+  rl.context_title,
+  rl.chapter_id,
+  rl.exercise_id,
+  rl.ags_lineitem_url IS NOT NULL AS "grade_passback_enabled!",
+  rl.nrps_context_memberships_url IS NOT NULL AS "roster_available!",
+  COUNT(lu.id) AS "user_count!",
+  MAX(lu.last_launched_at) AS last_launched_at
+FROM lti_resource_links rl
+  JOIN lti_platforms p ON p.id = rl.lti_platform_id
+  LEFT JOIN lti_resource_link_users lu ON lu.lti_resource_link_id = rl.id
+  AND lu.deleted_at IS NULL
+WHERE rl.course_id = $1
+  AND rl.deleted_at IS NULL
+  AND p.deleted_at IS NULL
+GROUP BY rl.id,
+  p.name
+ORDER BY rl.created_at
+        "#,
+        course_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
@@ -0,0 +1,118 @@
+//! Links the users of LTI 1.3 platforms to our users.
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct LtiUser {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub lti_platform_id: Uuid,
+    pub subject: String,
+    pub user_id: Uuid,
+}
+
+pub async fn find_by_subject(
+    conn: &mut PgConnection,
+    lti_platform_id: Uuid,
+    subject: &str,
+) -> ModelResult<Option<LtiUser>> {
+    let res = sqlx::query_as!(
+        LtiUser,
+        r#"
+SELECT *
+FROM lti_users
+WHERE lti_platform_id = $1
+  AND subject = $2
+  AND deleted_at IS NULL
+        "#,
+        lti_platform_id,
+        subject
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Returns the user the platform user is linked to, creating a new user on the first launch.
+///
+/// The new user is never matched to an existing one by email, since the platform does not prove
+/// that the user owns the address. The user gets a placeholder email address that cannot receive
+/// mail, and the name the platform sent.
+pub async fn get_or_create(
+    conn: &mut PgConnection,
+    lti_platform_id: Uuid,
+    subject: &str,
+    first_name: Option<&str>,
+    last_name: Option<&str>,
+) -> ModelResult<LtiUser> {
+    let mut tx = conn.begin().await?;
+    if let Some(existing) = find_by_subject(&mut tx, lti_platform_id, subject).await? {
+        tx.commit().await?;
+        return Ok(existing);
+    }
+    let user_id = Uuid::new_v4();
+    let email = format!("lti-{user_id}@lti.invalid");
+    crate::users::insert(
+        &mut tx,
+        PKeyPolicy::Fixed(user_id),
+        &email,
+        first_name,
+        last_name,
+    )
+    .await?;
+    let res = sqlx::query_as!(
+        LtiUser,
+        r#"
+INSERT INTO lti_users (lti_platform_id, subject, user_id)
+VALUES ($1, $2, $3)
+RETURNING *
+        "#,
+        lti_platform_id,
+        subject,
+        user_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{lti_platforms::NewLtiPlatform, test_helper::*};
+
+    #[tokio::test]
+    async fn the_same_subject_is_the_same_user() {
+        insert_data!(:tx, :user, :org);
+        let platform = crate::lti_platforms::insert(
+            tx.as_mut(),
+            org,
+            &NewLtiPlatform {
+                name: "Platform".to_string(),
+                issuer: "https://lms.example.com".to_string(),
+                client_id: "client".to_string(),
+                deployment_ids: vec![],
+                auth_login_url: "https://lms.example.com/auth".to_string(),
+                auth_token_url: "https://lms.example.com/token".to_string(),
+                jwks_url: "https://lms.example.com/jwks".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+        let first = get_or_create(tx.as_mut(), platform.id, "sub-1", Some("Ada"), None)
+            .await
+            .unwrap();
+        let again = get_or_create(tx.as_mut(), platform.id, "sub-1", None, None)
+            .await
+            .unwrap();
+        let other = get_or_create(tx.as_mut(), platform.id, "sub-2", None, None)
+            .await
+            .unwrap();
+        assert_eq!(first.user_id, again.user_id);
+        assert_ne!(first.user_id, other.user_id);
+        assert_ne!(first.user_id, user);
+    }
+}
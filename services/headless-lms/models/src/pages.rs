@@ -23,7 +23,7 @@ use crate::{
     exercise_services::{get_internal_public_spec_url, get_model_solution_url},
     exercise_slides::ExerciseSlide,
     exercise_tasks::ExerciseTask,
-    exercises::Exercise,
+    exercises::{Exercise, ExerciseScoringStrategy},
     organizations::Organization,
     page_history::{self, HistoryChangeReason, PageHistoryContent},
     peer_or_self_review_configs::CmsPeerOrSelfReviewConfig,
@@ -1407,6 +1407,14 @@ pub struct CmsPageExercise {
     pub peer_or_self_review_questions: Option<Vec<CmsPeerOrSelfReviewQuestion>>,
     pub use_course_default_peer_or_self_review_config: bool,
     pub teacher_reviews_answer_after_locking: bool,
+    #[serde(default)]
+    pub scoring_strategy: ExerciseScoringStrategy,
+    /// Required when the scoring strategy is `AverageOfLastAttempts`.
+    #[serde(default)]
+    pub scoring_strategy_attempt_count: Option<i32>,
+    /// Required when the scoring strategy is `PenaltyPerAttempt`.
+    #[serde(default)]
+    pub scoring_strategy_penalty_percent: Option<f32>,
 }
 
 impl CmsPageExercise {
@@ -1428,6 +1436,9 @@ impl CmsPageExercise {
             use_course_default_peer_or_self_review_config: exercise
                 .use_course_default_peer_or_self_review_config,
             teacher_reviews_answer_after_locking: exercise.teacher_reviews_answer_after_locking,
+            scoring_strategy: exercise.scoring_strategy,
+            scoring_strategy_attempt_count: exercise.scoring_strategy_attempt_count,
+            scoring_strategy_penalty_percent: exercise.scoring_strategy_penalty_percent,
             peer_or_self_review_config,
             peer_or_self_review_questions,
         }
@@ -1490,8 +1501,37 @@ pub struct CmsPageUpdate {
 }
 
 impl CmsPageUpdate {
-    /// Checks that each exercise has at least one slide and each slide has at least one task.
+    /// Checks that each exercise has at least one slide and each slide has at least one task, and
+    /// that the scoring strategies of the exercises have the settings they need.
     pub fn validate_exercise_data(&self) -> ModelResult<()> {
+        for exercise in self.exercises.iter() {
+            let description = match exercise.scoring_strategy {
+                ExerciseScoringStrategy::AverageOfLastAttempts
+                    if !exercise
+                        .scoring_strategy_attempt_count
+                        .is_some_and(|count| count > 0) =>
+                {
+                    Some("The scoring strategy must average at least one attempt.")
+                }
+                ExerciseScoringStrategy::PenaltyPerAttempt
+                    if !exercise
+                        .scoring_strategy_penalty_percent
+                        .is_some_and(|percent| (0.0..=100.0).contains(&percent)) =>
+                {
+                    Some("The penalty per attempt must be between 0 and 100 percent.")
+                }
+                _ => None,
+            };
+            if let Some(description) = description {
+                return Err(model_err!(
+                    PreconditionFailedWithCMSAnchorBlockId {
+                        id: exercise.id,
+                        description,
+                    },
+                    description.to_string()
+                ));
+            }
+        }
         let mut exercise_ids: HashMap<Uuid, bool> =
             self.exercises.iter().map(|x| (x.id, false)).collect();
         let mut slide_ids = self
@@ -2203,7 +2243,10 @@ INSERT INTO exercises(
     needs_self_review,
     use_course_default_peer_or_self_review_config,
     exercise_language_group_id,
-    teacher_reviews_answer_after_locking
+    teacher_reviews_answer_after_locking,
+    scoring_strategy,
+    scoring_strategy_attempt_count,
+    scoring_strategy_penalty_percent
   )
 VALUES (
     $1,
@@ -2221,7 +2264,10 @@ VALUES (
     $13,
     $14,
     $15,
-    $16
+    $16,
+    $17,
+    $18,
+    $19
   ) ON CONFLICT (id) DO
 UPDATE
 SET course_id = $2,
@@ -2239,6 +2285,9 @@ SET course_id = $2,
   use_course_default_peer_or_self_review_config = $14,
   exercise_language_group_id = $15,
   teacher_reviews_answer_after_locking = $16,
+  scoring_strategy = $17,
+  scoring_strategy_attempt_count = $18,
+  scoring_strategy_penalty_percent = $19,
   deleted_at = NULL
 RETURNING *;
             ",
@@ -2258,6 +2307,9 @@ RETURNING *;
             exercise_update.use_course_default_peer_or_self_review_config,
             exercise_language_group_id,
             exercise_update.teacher_reviews_answer_after_locking,
+            exercise_update.scoring_strategy as _,
+            exercise_update.scoring_strategy_attempt_count,
+            exercise_update.scoring_strategy_penalty_percent,
         )
         .fetch_one(&mut *conn)
         .await?;
@@ -2471,6 +2523,8 @@ pub async fn upsert_peer_or_self_review_configs(
         points_are_all_or_nothing,
         reset_answer_if_zero_points_from_review,
         review_instructions,
+        calibration_reviews_to_give,
+        manual_review_reliability_threshold,
         deleted_at
       ) ",
         );
@@ -2507,6 +2561,8 @@ pub async fn upsert_peer_or_self_review_configs(
                 .push_bind(pr.points_are_all_or_nothing)
                 .push_bind(pr.reset_answer_if_zero_points_from_review)
                 .push_bind(pr.review_instructions.clone())
+                .push_bind(pr.calibration_reviews_to_give)
+                .push_bind(pr.manual_review_reliability_threshold)
                 .push("NULL");
         });
 
@@ -2528,6 +2584,8 @@ SET course_id = excluded.course_id,
   accepting_threshold = excluded.accepting_threshold,
   points_are_all_or_nothing = excluded.points_are_all_or_nothing,
   review_instructions = excluded.review_instructions,
+  calibration_reviews_to_give = excluded.calibration_reviews_to_give,
+  manual_review_reliability_threshold = excluded.manual_review_reliability_threshold,
   deleted_at = NULL
 RETURNING id;
 ",
@@ -2553,7 +2611,9 @@ SELECT id as "id!",
   accepting_threshold "accepting_threshold!",
   points_are_all_or_nothing "points_are_all_or_nothing!",
   reset_answer_if_zero_points_from_review,
-  review_instructions
+  review_instructions,
+  calibration_reviews_to_give,
+  manual_review_reliability_threshold
 FROM peer_or_self_review_configs
 WHERE id IN (
     SELECT UNNEST($1::uuid [])
@@ -4773,6 +4833,9 @@ mod test {
             peer_or_self_review_questions: None,
             use_course_default_peer_or_self_review_config: false,
             teacher_reviews_answer_after_locking: true,
+            scoring_strategy: ExerciseScoringStrategy::BestAttempt,
+            scoring_strategy_attempt_count: None,
+            scoring_strategy_penalty_percent: None,
         };
         let e1_s1 = CmsPageExerciseSlide {
             id: Uuid::parse_str("43380e81-6ff2-4f46-9f38-af0ac6a8421a").unwrap(),
@@ -4806,6 +4869,33 @@ mod test {
             .is_ok()
         );
 
+        // Fails with a scoring strategy that is missing its settings
+        assert!(
+            create_update(
+                vec![CmsPageExercise {
+                    scoring_strategy: ExerciseScoringStrategy::AverageOfLastAttempts,
+                    ..e1.clone()
+                }],
+                vec![e1_s1.clone()],
+                vec![e1_s1_t1.clone()],
+            )
+            .validate_exercise_data()
+            .is_err()
+        );
+        assert!(
+            create_update(
+                vec![CmsPageExercise {
+                    scoring_strategy: ExerciseScoringStrategy::PenaltyPerAttempt,
+                    scoring_strategy_penalty_percent: Some(110.0),
+                    ..e1.clone()
+                }],
+                vec![e1_s1.clone()],
+                vec![e1_s1_t1.clone()],
+            )
+            .validate_exercise_data()
+            .is_err()
+        );
+
         // Fails with missing slide
         assert!(
             create_update(vec![e1.clone()], vec![], vec![e1_s1_t1],)
@@ -5132,6 +5222,8 @@ mod test {
             points_are_all_or_nothing: false,
             reset_answer_if_zero_points_from_review: false,
             review_instructions: None,
+            calibration_reviews_to_give: 0,
+            manual_review_reliability_threshold: None,
         };
         let prq = CmsPeerOrSelfReviewQuestion {
             id: prq_id,
@@ -5185,6 +5277,8 @@ mod test {
             points_are_all_or_nothing: true,
             reset_answer_if_zero_points_from_review: false,
             review_instructions: None,
+            calibration_reviews_to_give: 0,
+            manual_review_reliability_threshold: None,
         };
         let prq = CmsPeerOrSelfReviewQuestion {
             id: prq_id,
@@ -32,6 +32,10 @@ pub struct PeerOrSelfReviewConfig {
     pub points_are_all_or_nothing: bool,
     pub reset_answer_if_zero_points_from_review: bool,
     pub review_instructions: Option<serde_json::Value>,
+    /// How many teacher-graded calibration answers a student reviews before reviewing their peers. Zero disables calibration.
+    pub calibration_reviews_to_give: i32,
+    /// Answers whose reviewers have a lower average reliability than this are sent to manual review.
+    pub manual_review_reliability_threshold: Option<f32>,
 }
 
 /// Like `PeerOrSelfReviewConfig` but only the fields it's fine to show to all users.
@@ -58,6 +62,10 @@ pub struct CmsPeerOrSelfReviewConfig {
     pub points_are_all_or_nothing: bool,
     pub reset_answer_if_zero_points_from_review: bool,
     pub review_instructions: Option<serde_json::Value>,
+    #[serde(default)]
+    pub calibration_reviews_to_give: i32,
+    #[serde(default)]
+    pub manual_review_reliability_threshold: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
@@ -125,9 +133,11 @@ pub async fn upsert_with_id(
     processing_strategy,
     points_are_all_or_nothing,
     review_instructions,
-    reset_answer_if_zero_points_from_review
+    reset_answer_if_zero_points_from_review,
+    calibration_reviews_to_give,
+    manual_review_reliability_threshold
   )
-VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) ON CONFLICT (id) DO
+VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) ON CONFLICT (id) DO
 UPDATE
 SET course_id = excluded.course_id,
   exercise_id = excluded.exercise_id,
@@ -137,7 +147,9 @@ SET course_id = excluded.course_id,
   processing_strategy = excluded.processing_strategy,
   points_are_all_or_nothing = excluded.points_are_all_or_nothing,
   reset_answer_if_zero_points_from_review = excluded.reset_answer_if_zero_points_from_review,
-  review_instructions = excluded.review_instructions
+  review_instructions = excluded.review_instructions,
+  calibration_reviews_to_give = excluded.calibration_reviews_to_give,
+  manual_review_reliability_threshold = excluded.manual_review_reliability_threshold
 RETURNING id,
   course_id,
   exercise_id,
@@ -147,7 +159,9 @@ RETURNING id,
   processing_strategy,
   points_are_all_or_nothing,
   review_instructions,
-  reset_answer_if_zero_points_from_review
+  reset_answer_if_zero_points_from_review,
+  calibration_reviews_to_give,
+  manual_review_reliability_threshold
 "#,
         pkey_policy.into_uuid(),
         cms_peer_review.course_id,
@@ -159,6 +173,8 @@ RETURNING id,
         cms_peer_review.points_are_all_or_nothing,
         cms_peer_review.review_instructions,
         cms_peer_review.reset_answer_if_zero_points_from_review,
+        cms_peer_review.calibration_reviews_to_give,
+        cms_peer_review.manual_review_reliability_threshold,
     )
     .fetch_one(conn)
     .await?;
@@ -335,7 +351,9 @@ SELECT pr.id as id,
   pr.processing_strategy,
   points_are_all_or_nothing,
   pr.reset_answer_if_zero_points_from_review,
-  pr.review_instructions
+  pr.review_instructions,
+  pr.calibration_reviews_to_give,
+  pr.manual_review_reliability_threshold
 from pages p
   join exercises e on p.id = e.page_id
   join peer_or_self_review_configs pr on e.id = pr.exercise_id
@@ -390,7 +408,9 @@ SELECT id,
   processing_strategy,
   points_are_all_or_nothing,
   reset_answer_if_zero_points_from_review,
-  review_instructions
+  review_instructions,
+  calibration_reviews_to_give,
+  manual_review_reliability_threshold
 FROM peer_or_self_review_configs
 WHERE course_id = $1
   AND exercise_id IS NULL
@@ -419,7 +439,9 @@ SELECT id,
   processing_strategy,
   points_are_all_or_nothing,
   reset_answer_if_zero_points_from_review,
-  review_instructions
+  review_instructions,
+  calibration_reviews_to_give,
+  manual_review_reliability_threshold
 FROM peer_or_self_review_configs
 WHERE id = $1;
     "#,
@@ -513,9 +535,11 @@ INSERT INTO peer_or_self_review_configs (
     processing_strategy,
     points_are_all_or_nothing,
     review_instructions,
-    reset_answer_if_zero_points_from_review
+    reset_answer_if_zero_points_from_review,
+    calibration_reviews_to_give,
+    manual_review_reliability_threshold
 )
-SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10
+SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12
 WHERE (
     $3::uuid IS NULL
     OR EXISTS (
@@ -536,6 +560,8 @@ SET course_id = excluded.course_id,
   points_are_all_or_nothing = excluded.points_are_all_or_nothing,
   reset_answer_if_zero_points_from_review = excluded.reset_answer_if_zero_points_from_review,
   review_instructions = excluded.review_instructions,
+  calibration_reviews_to_give = excluded.calibration_reviews_to_give,
+  manual_review_reliability_threshold = excluded.manual_review_reliability_threshold,
   deleted_at = NULL
 WHERE peer_or_self_review_configs.course_id = $2
 RETURNING id,
@@ -547,7 +573,9 @@ RETURNING id,
   processing_strategy,
   points_are_all_or_nothing,
   review_instructions,
-  reset_answer_if_zero_points_from_review
+  reset_answer_if_zero_points_from_review,
+  calibration_reviews_to_give,
+  manual_review_reliability_threshold
         "#,
         input.id,
         course_id,
@@ -559,6 +587,8 @@ RETURNING id,
         input.points_are_all_or_nothing,
         input.review_instructions,
         input.reset_answer_if_zero_points_from_review,
+        input.calibration_reviews_to_give,
+        input.manual_review_reliability_threshold,
     )
     .fetch_optional(&mut *tx)
     .await?;
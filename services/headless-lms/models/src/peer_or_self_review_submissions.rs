@@ -154,6 +154,36 @@ WHERE id = $1
     Ok(res)
 }
 
+/// Deletes the submission together with its answers to the questions, so that it no longer counts
+/// towards the grade of the reviewed answer.
+pub async fn delete(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    let mut tx = conn.begin().await?;
+    sqlx::query!(
+        "
+UPDATE peer_or_self_review_question_submissions
+SET deleted_at = now()
+WHERE peer_or_self_review_submission_id = $1
+  AND deleted_at IS NULL
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query!(
+        "
+UPDATE peer_or_self_review_submissions
+SET deleted_at = now()
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
 pub async fn get_by_ids(
     conn: &mut PgConnection,
     ids: &[Uuid],
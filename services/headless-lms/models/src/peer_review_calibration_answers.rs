@@ -0,0 +1,398 @@
+//! Answers a teacher has scored with the peer review questions of an exercise. Students review
+//! them before reviewing their peers, and their agreement with the teacher decides how much their
+//! peer reviews weigh. See [`crate::peer_reviewer_reliabilities`].
+
+use std::collections::HashSet;
+
+use itertools::Itertools;
+use utoipa::ToSchema;
+
+use crate::{
+    exercise_slide_submissions,
+    exercises::Exercise,
+    peer_or_self_review_configs,
+    peer_or_self_review_questions::{self, PeerOrSelfReviewQuestion, PeerOrSelfReviewQuestionType},
+    prelude::*,
+};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct PeerReviewCalibrationAnswer {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub exercise_id: Uuid,
+    pub exercise_slide_submission_id: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct PeerReviewCalibrationReferenceScore {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub peer_review_calibration_answer_id: Uuid,
+    pub peer_or_self_review_question_id: Uuid,
+    pub number_data: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct PeerReviewCalibrationAnswerWithReferenceScores {
+    pub calibration_answer: PeerReviewCalibrationAnswer,
+    pub reference_scores: Vec<PeerReviewCalibrationReferenceScore>,
+}
+
+/// Marks an answer to the exercise as a calibration answer. The teacher scores the answer with
+/// the scale questions of the exercise's peer review.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewPeerReviewCalibrationAnswer {
+    pub exercise_slide_submission_id: Uuid,
+    pub reference_scores: Vec<NewPeerReviewCalibrationReferenceScore>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewPeerReviewCalibrationReferenceScore {
+    pub peer_or_self_review_question_id: Uuid,
+    pub number_data: f32,
+}
+
+fn validate(
+    new_calibration_answer: &NewPeerReviewCalibrationAnswer,
+    peer_or_self_review_questions: &[PeerOrSelfReviewQuestion],
+) -> ModelResult<()> {
+    if new_calibration_answer.reference_scores.is_empty() {
+        return Err(model_err!(
+            PreconditionFailed,
+            "A calibration answer must be scored with at least one scale question.".to_string()
+        ));
+    }
+    let mut scored_question_ids = HashSet::new();
+    for reference_score in &new_calibration_answer.reference_scores {
+        let question = peer_or_self_review_questions
+            .iter()
+            .find(|q| q.id == reference_score.peer_or_self_review_question_id)
+            .ok_or_else(|| {
+                model_err!(
+                    PreconditionFailed,
+                    "The question does not belong to the peer review of the exercise.".to_string()
+                )
+            })?;
+        if question.question_type != PeerOrSelfReviewQuestionType::Scale {
+            return Err(model_err!(
+                PreconditionFailed,
+                format!("Question '{}' is not a scale question.", question.question)
+            ));
+        }
+        if !(1.0..=5.0).contains(&reference_score.number_data) {
+            return Err(model_err!(
+                PreconditionFailed,
+                format!(
+                    "The score for question '{}' must be between 1 and 5.",
+                    question.question
+                )
+            ));
+        }
+        if !scored_question_ids.insert(question.id) {
+            return Err(model_err!(
+                PreconditionFailed,
+                format!("Question '{}' is scored more than once.", question.question)
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    exercise: &Exercise,
+    new_calibration_answer: &NewPeerReviewCalibrationAnswer,
+) -> ModelResult<PeerReviewCalibrationAnswerWithReferenceScores> {
+    let exercise_slide_submission = exercise_slide_submissions::get_by_id(
+        conn,
+        new_calibration_answer.exercise_slide_submission_id,
+    )
+    .await?;
+    if exercise_slide_submission.exercise_id != exercise.id {
+        return Err(model_err!(
+            PreconditionFailed,
+            "The answer does not belong to the exercise.".to_string()
+        ));
+    }
+    let peer_or_self_review_config = peer_or_self_review_configs::get_by_exercise_or_course_id(
+        conn,
+        exercise,
+        exercise.get_course_id()?,
+    )
+    .await?;
+    let peer_or_self_review_questions =
+        peer_or_self_review_questions::get_all_by_peer_or_self_review_config_id(
+            conn,
+            peer_or_self_review_config.id,
+        )
+        .await?;
+    validate(new_calibration_answer, &peer_or_self_review_questions)?;
+
+    let mut tx = conn.begin().await?;
+    let calibration_answer = sqlx::query_as!(
+        PeerReviewCalibrationAnswer,
+        r#"
+INSERT INTO peer_review_calibration_answers (exercise_id, exercise_slide_submission_id)
+VALUES ($1, $2)
+RETURNING *
+        "#,
+        exercise.id,
+        exercise_slide_submission.id,
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    let question_ids = new_calibration_answer
+        .reference_scores
+        .iter()
+        .map(|s| s.peer_or_self_review_question_id)
+        .collect::<Vec<_>>();
+    let number_data = new_calibration_answer
+        .reference_scores
+        .iter()
+        .map(|s| s.number_data)
+        .collect::<Vec<_>>();
+    let reference_scores = sqlx::query_as!(
+        PeerReviewCalibrationReferenceScore,
+        r#"
+INSERT INTO peer_review_calibration_reference_scores (
+    peer_review_calibration_answer_id,
+    peer_or_self_review_question_id,
+    number_data
+  )
+SELECT $1,
+  question_id,
+  number_data
+FROM UNNEST($2::uuid[], $3::real[]) AS s(question_id, number_data)
+RETURNING *
+        "#,
+        calibration_answer.id,
+        &question_ids,
+        &number_data,
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(PeerReviewCalibrationAnswerWithReferenceScores {
+        calibration_answer,
+        reference_scores,
+    })
+}
+
+pub async fn get_by_id(
+    conn: &mut PgConnection,
+    id: Uuid,
+) -> ModelResult<PeerReviewCalibrationAnswer> {
+    let res = sqlx::query_as!(
+        PeerReviewCalibrationAnswer,
+        r#"
+SELECT *
+FROM peer_review_calibration_answers
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_reference_scores_by_calibration_answer_id(
+    conn: &mut PgConnection,
+    peer_review_calibration_answer_id: Uuid,
+) -> ModelResult<Vec<PeerReviewCalibrationReferenceScore>> {
+    let res = sqlx::query_as!(
+        PeerReviewCalibrationReferenceScore,
+        r#"
+SELECT *
+FROM peer_review_calibration_reference_scores
+WHERE peer_review_calibration_answer_id = $1
+  AND deleted_at IS NULL
+        "#,
+        peer_review_calibration_answer_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_exercise_id(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+) -> ModelResult<Vec<PeerReviewCalibrationAnswerWithReferenceScores>> {
+    let calibration_answers = sqlx::query_as!(
+        PeerReviewCalibrationAnswer,
+        r#"
+SELECT *
+FROM peer_review_calibration_answers
+WHERE exercise_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at,
+  id
+        "#,
+        exercise_id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+    let calibration_answer_ids = calibration_answers.iter().map(|a| a.id).collect::<Vec<_>>();
+    let mut reference_scores = sqlx::query_as!(
+        PeerReviewCalibrationReferenceScore,
+        r#"
+SELECT *
+FROM peer_review_calibration_reference_scores
+WHERE peer_review_calibration_answer_id = ANY($1)
+  AND deleted_at IS NULL
+        "#,
+        &calibration_answer_ids,
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .into_group_map_by(|s| s.peer_review_calibration_answer_id);
+    let res = calibration_answers
+        .into_iter()
+        .map(
+            |calibration_answer| PeerReviewCalibrationAnswerWithReferenceScores {
+                reference_scores: reference_scores
+                    .remove(&calibration_answer.id)
+                    .unwrap_or_default(),
+                calibration_answer,
+            },
+        )
+        .collect();
+    Ok(res)
+}
+
+pub async fn count_by_exercise_id(conn: &mut PgConnection, exercise_id: Uuid) -> ModelResult<i64> {
+    let res = sqlx::query!(
+        r#"
+SELECT COUNT(*) AS "count!"
+FROM peer_review_calibration_answers
+WHERE exercise_id = $1
+  AND deleted_at IS NULL
+        "#,
+        exercise_id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res.count)
+}
+
+/// The oldest calibration answer of the exercise the user has not reviewed yet.
+pub async fn get_next_to_review_by_user(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+    user_id: Uuid,
+) -> ModelResult<Option<PeerReviewCalibrationAnswer>> {
+    let res = sqlx::query_as!(
+        PeerReviewCalibrationAnswer,
+        r#"
+SELECT *
+FROM peer_review_calibration_answers a
+WHERE a.exercise_id = $1
+  AND a.deleted_at IS NULL
+  AND NOT EXISTS (
+    SELECT 1
+    FROM peer_review_calibration_submissions s
+    WHERE s.peer_review_calibration_answer_id = a.id
+      AND s.user_id = $2
+      AND s.deleted_at IS NULL
+  )
+ORDER BY a.created_at,
+  a.id
+LIMIT 1
+        "#,
+        exercise_id,
+        user_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Stops using the answer for calibration. Reliabilities already calculated from it are kept.
+pub async fn delete(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+    peer_review_calibration_answer_id: Uuid,
+) -> ModelResult<()> {
+    let res = sqlx::query!(
+        r#"
+UPDATE peer_review_calibration_answers
+SET deleted_at = now()
+WHERE id = $1
+  AND exercise_id = $2
+  AND deleted_at IS NULL
+        "#,
+        peer_review_calibration_answer_id,
+        exercise_id,
+    )
+    .execute(conn)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(model_err!(
+            NotFound,
+            "Calibration answer not found.".to_string()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn question(question_type: PeerOrSelfReviewQuestionType) -> PeerOrSelfReviewQuestion {
+        PeerOrSelfReviewQuestion {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            peer_or_self_review_config_id: Uuid::nil(),
+            order_number: 0,
+            question: "Question".to_string(),
+            question_type,
+            answer_required: true,
+            weight: 0.0,
+        }
+    }
+
+    fn calibration_answer(scores: &[(Uuid, f32)]) -> NewPeerReviewCalibrationAnswer {
+        NewPeerReviewCalibrationAnswer {
+            exercise_slide_submission_id: Uuid::nil(),
+            reference_scores: scores
+                .iter()
+                .map(|(peer_or_self_review_question_id, number_data)| {
+                    NewPeerReviewCalibrationReferenceScore {
+                        peer_or_self_review_question_id: *peer_or_self_review_question_id,
+                        number_data: *number_data,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn validates_reference_scores() {
+        let scale = question(PeerOrSelfReviewQuestionType::Scale);
+        let essay = question(PeerOrSelfReviewQuestionType::Essay);
+        let questions = vec![scale.clone(), essay.clone()];
+
+        assert!(validate(&calibration_answer(&[(scale.id, 4.0)]), &questions).is_ok());
+        assert!(validate(&calibration_answer(&[]), &questions).is_err());
+        assert!(validate(&calibration_answer(&[(essay.id, 4.0)]), &questions).is_err());
+        assert!(validate(&calibration_answer(&[(scale.id, 6.0)]), &questions).is_err());
+        assert!(validate(&calibration_answer(&[(Uuid::new_v4(), 3.0)]), &questions).is_err());
+        assert!(
+            validate(
+                &calibration_answer(&[(scale.id, 3.0), (scale.id, 4.0)]),
+                &questions
+            )
+            .is_err()
+        );
+    }
+}
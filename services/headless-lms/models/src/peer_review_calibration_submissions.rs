@@ -0,0 +1,168 @@
+//! Reviews students give to [`crate::peer_review_calibration_answers`]. Only the agreement with the
+//! teacher's scores is stored because the reviews are never shown to anyone.
+
+use crate::{
+    library::peer_or_self_reviewing::CourseMaterialPeerOrSelfReviewQuestionAnswer,
+    peer_review_calibration_answers::PeerReviewCalibrationReferenceScore, prelude::*,
+};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PeerReviewCalibrationSubmission {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub user_id: Uuid,
+    pub course_id: Uuid,
+    pub exercise_id: Uuid,
+    pub peer_review_calibration_answer_id: Uuid,
+    pub agreement: f32,
+}
+
+/// How well the answers agree with the teacher's scores, from 0 to 1. Each scored question counts
+/// equally, and a question the student left unanswered counts as the largest possible difference.
+pub fn calculate_agreement(
+    reference_scores: &[PeerReviewCalibrationReferenceScore],
+    answers: &[CourseMaterialPeerOrSelfReviewQuestionAnswer],
+) -> f32 {
+    if reference_scores.is_empty() {
+        return 0.0;
+    }
+    // The answers are given on a scale from 1 to 5.
+    let largest_possible_difference = 4.0;
+    let total_difference = reference_scores
+        .iter()
+        .map(|reference_score| {
+            answers
+                .iter()
+                .find(|a| {
+                    a.peer_or_self_review_question_id
+                        == reference_score.peer_or_self_review_question_id
+                })
+                .and_then(|a| a.number_data)
+                .map(|number_data| {
+                    (number_data - reference_score.number_data)
+                        .abs()
+                        .min(largest_possible_difference)
+                })
+                .unwrap_or(largest_possible_difference)
+        })
+        .sum::<f32>();
+    let average_difference = total_difference / reference_scores.len() as f32;
+    (1.0 - average_difference / largest_possible_difference).clamp(0.0, 1.0)
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    course_id: Uuid,
+    exercise_id: Uuid,
+    peer_review_calibration_answer_id: Uuid,
+    agreement: f32,
+) -> ModelResult<PeerReviewCalibrationSubmission> {
+    let res = sqlx::query_as!(
+        PeerReviewCalibrationSubmission,
+        r#"
+INSERT INTO peer_review_calibration_submissions (
+    user_id,
+    course_id,
+    exercise_id,
+    peer_review_calibration_answer_id,
+    agreement
+  )
+VALUES ($1, $2, $3, $4, $5)
+RETURNING *
+        "#,
+        user_id,
+        course_id,
+        exercise_id,
+        peer_review_calibration_answer_id,
+        agreement,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_num_given_by_user_and_exercise(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    exercise_id: Uuid,
+) -> ModelResult<i64> {
+    let res = sqlx::query!(
+        r#"
+SELECT COUNT(*) AS "count!"
+FROM peer_review_calibration_submissions s
+  JOIN peer_review_calibration_answers a ON a.id = s.peer_review_calibration_answer_id
+WHERE s.user_id = $1
+  AND s.exercise_id = $2
+  AND s.deleted_at IS NULL
+  AND a.deleted_at IS NULL
+        "#,
+        user_id,
+        exercise_id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res.count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn reference_score(
+        peer_or_self_review_question_id: Uuid,
+        number_data: f32,
+    ) -> PeerReviewCalibrationReferenceScore {
+        PeerReviewCalibrationReferenceScore {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            peer_review_calibration_answer_id: Uuid::nil(),
+            peer_or_self_review_question_id,
+            number_data,
+        }
+    }
+
+    fn answer(
+        peer_or_self_review_question_id: Uuid,
+        number_data: f32,
+    ) -> CourseMaterialPeerOrSelfReviewQuestionAnswer {
+        CourseMaterialPeerOrSelfReviewQuestionAnswer {
+            peer_or_self_review_question_id,
+            text_data: None,
+            number_data: Some(number_data),
+        }
+    }
+
+    #[test]
+    fn agreement_decreases_with_the_difference_to_the_teacher() {
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let reference_scores = vec![reference_score(first, 4.0), reference_score(second, 2.0)];
+
+        assert_eq!(
+            calculate_agreement(
+                &reference_scores,
+                &[answer(first, 4.0), answer(second, 2.0)]
+            ),
+            1.0
+        );
+        // Off by 2 in one question out of two: average difference 1 out of 4
+        assert_eq!(
+            calculate_agreement(
+                &reference_scores,
+                &[answer(first, 4.0), answer(second, 4.0)]
+            ),
+            0.75
+        );
+        // An unanswered question counts as the largest difference
+        assert_eq!(
+            calculate_agreement(&reference_scores, &[answer(first, 4.0)]),
+            0.5
+        );
+        assert_eq!(calculate_agreement(&[], &[answer(first, 4.0)]), 0.0);
+    }
+}
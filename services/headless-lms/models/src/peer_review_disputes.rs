@@ -0,0 +1,234 @@
+//! Objections students file against peer reviews they have received. The resolution flow is in
+//! [`crate::library::peer_review_disputes`].
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "peer_review_dispute_status", rename_all = "kebab-case")]
+pub enum PeerReviewDisputeStatus {
+    /// Waiting for a teacher.
+    Pending,
+    /// The review no longer counts towards the grade of the answer.
+    ReviewDiscarded,
+    /// The teacher graded the answer themselves.
+    GradeOverridden,
+    /// The review stands.
+    Rejected,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct PeerReviewDispute {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub user_id: Uuid,
+    pub exercise_id: Uuid,
+    pub course_id: Uuid,
+    pub exercise_slide_submission_id: Uuid,
+    pub peer_or_self_review_submission_id: Uuid,
+    pub reason: String,
+    pub status: PeerReviewDisputeStatus,
+    pub resolved_by_user_id: Option<Uuid>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub teacher_response: Option<String>,
+    pub teacher_grading_decision_id: Option<Uuid>,
+    pub email_delivery_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct NewPeerReviewDispute {
+    pub peer_or_self_review_submission_id: Uuid,
+    pub reason: String,
+}
+
+/// How a teacher resolves a pending dispute.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct PeerReviewDisputeResolution {
+    /// Anything but [`PeerReviewDisputeStatus::Pending`].
+    pub status: PeerReviewDisputeStatus,
+    /// The points the answer gets. Required when overriding the grade.
+    pub manual_points: Option<f32>,
+    pub teacher_response: Option<String>,
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    exercise_id: Uuid,
+    course_id: Uuid,
+    exercise_slide_submission_id: Uuid,
+    peer_or_self_review_submission_id: Uuid,
+    reason: &str,
+) -> ModelResult<PeerReviewDispute> {
+    let res = sqlx::query_as!(
+        PeerReviewDispute,
+        r#"
+INSERT INTO peer_review_disputes (
+    user_id,
+    exercise_id,
+    course_id,
+    exercise_slide_submission_id,
+    peer_or_self_review_submission_id,
+    reason
+  )
+VALUES ($1, $2, $3, $4, $5, $6)
+RETURNING *
+        "#,
+        user_id,
+        exercise_id,
+        course_id,
+        exercise_slide_submission_id,
+        peer_or_self_review_submission_id,
+        reason,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<PeerReviewDispute> {
+    let res = sqlx::query_as!(
+        PeerReviewDispute,
+        r#"
+SELECT *
+FROM peer_review_disputes
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn try_to_get_by_peer_or_self_review_submission_id(
+    conn: &mut PgConnection,
+    peer_or_self_review_submission_id: Uuid,
+) -> ModelResult<Option<PeerReviewDispute>> {
+    let res = sqlx::query_as!(
+        PeerReviewDispute,
+        r#"
+SELECT *
+FROM peer_review_disputes
+WHERE peer_or_self_review_submission_id = $1
+  AND deleted_at IS NULL
+        "#,
+        peer_or_self_review_submission_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The disputes the user has filed on the exercise, newest first.
+pub async fn get_by_user_and_exercise_id(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    exercise_id: Uuid,
+) -> ModelResult<Vec<PeerReviewDispute>> {
+    let res = sqlx::query_as!(
+        PeerReviewDispute,
+        r#"
+SELECT *
+FROM peer_review_disputes
+WHERE user_id = $1
+  AND exercise_id = $2
+  AND deleted_at IS NULL
+ORDER BY created_at DESC
+        "#,
+        user_id,
+        exercise_id,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The teacher queue of the exercise: pending disputes oldest first when `pending_only`, otherwise
+/// all disputes newest first.
+pub async fn get_by_exercise_id(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+    pending_only: bool,
+) -> ModelResult<Vec<PeerReviewDispute>> {
+    let res = sqlx::query_as!(
+        PeerReviewDispute,
+        r#"
+SELECT *
+FROM peer_review_disputes
+WHERE exercise_id = $1
+  AND (
+    status = 'pending'
+    OR NOT $2
+  )
+  AND deleted_at IS NULL
+ORDER BY CASE
+    WHEN $2 THEN created_at
+  END ASC,
+  created_at DESC
+        "#,
+        exercise_id,
+        pending_only,
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Marks a pending dispute resolved. Fails with `NotFound` if the dispute has already been resolved.
+pub async fn resolve(
+    conn: &mut PgConnection,
+    id: Uuid,
+    status: PeerReviewDisputeStatus,
+    resolved_by_user_id: Uuid,
+    teacher_response: Option<&str>,
+    teacher_grading_decision_id: Option<Uuid>,
+) -> ModelResult<PeerReviewDispute> {
+    let res = sqlx::query_as!(
+        PeerReviewDispute,
+        r#"
+UPDATE peer_review_disputes
+SET status = $2,
+  resolved_by_user_id = $3,
+  resolved_at = now(),
+  teacher_response = $4,
+  teacher_grading_decision_id = $5
+WHERE id = $1
+  AND status = 'pending'
+  AND deleted_at IS NULL
+RETURNING *
+        "#,
+        id,
+        status as PeerReviewDisputeStatus,
+        resolved_by_user_id,
+        teacher_response,
+        teacher_grading_decision_id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn set_email_delivery_id(
+    conn: &mut PgConnection,
+    id: Uuid,
+    email_delivery_id: Uuid,
+) -> ModelResult<()> {
+    sqlx::query!(
+        r#"
+UPDATE peer_review_disputes
+SET email_delivery_id = $2
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id,
+        email_delivery_id,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
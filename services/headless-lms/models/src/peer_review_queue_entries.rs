@@ -1,6 +1,9 @@
+use chrono::Duration;
+
 use crate::{
     exercises,
     library::user_exercise_state_updater,
+    peer_or_self_review_configs::PeerOrSelfReviewConfig,
     prelude::*,
     teacher_grading_decisions,
     user_exercise_states::{self, ReviewingStage},
@@ -23,6 +26,62 @@ pub struct PeerReviewQueueEntry {
     pub removed_from_queue_for_unusual_reason: bool,
 }
 
+/// Upper bounds of the wait time buckets in [`PeerReviewQueueWaitTimeDistribution`]: a day, three
+/// days, a week, two weeks and three weeks. The last bucket has no upper bound.
+const WAIT_TIME_BUCKET_UPPER_BOUNDS_IN_HOURS: [i64; 5] = [24, 72, 168, 336, 504];
+
+/// How the queue of an exercise is doing. Shown to teachers so that they can see if answers are
+/// waiting for peer reviews for too long.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+
+pub struct PeerReviewQueueHealth {
+    pub exercise_id: Uuid,
+    pub entries_waiting_for_peer_reviews: i64,
+    pub entries_with_enough_peer_reviews: i64,
+    /// Entries removed from the queue, for example because they were sent to manual review.
+    pub entries_removed_from_queue: i64,
+    /// Entries that have waited longer than this are starved and are offered to reviewers first.
+    pub starvation_threshold_hours: i64,
+    /// How long the entries still waiting for peer reviews have waited.
+    pub wait_time_distribution: PeerReviewQueueWaitTimeDistribution,
+    /// The longest waiting entries first.
+    pub starved_entries: Vec<StarvedPeerReviewQueueEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+
+pub struct PeerReviewQueueWaitTimeDistribution {
+    pub median_hours: Option<i64>,
+    pub p90_hours: Option<i64>,
+    pub max_hours: Option<i64>,
+    pub buckets: Vec<PeerReviewQueueWaitTimeBucket>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+
+pub struct PeerReviewQueueWaitTimeBucket {
+    pub min_hours: i64,
+    /// None for the last bucket.
+    pub max_hours: Option<i64>,
+    pub entries: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+
+pub struct StarvedPeerReviewQueueEntry {
+    pub peer_review_queue_entry_id: Uuid,
+    pub user_id: Uuid,
+    pub receiving_peer_reviews_exercise_slide_submission_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub peer_reviews_received: i64,
+}
+
+/// How long an entry can wait for peer reviews before it is considered starved: a third of the time
+/// after which the entry is sent to manual review.
+pub fn starvation_threshold(peer_or_self_review_config: &PeerOrSelfReviewConfig) -> Duration {
+    Duration::hours(i64::from(peer_or_self_review_config.manual_review_cutoff_in_days) * 24 / 3)
+}
+
 pub async fn insert(
     conn: &mut PgConnection,
     pkey_policy: PKeyPolicy<Uuid>,
@@ -266,34 +325,65 @@ LIMIT $4
     Ok(res)
 }
 
-/// Gets multiple records of `PeerReviewQueueEntry` that still require more peer reviews, ordered by
-/// peer review priority.
+/// Gets the entries that still require more peer reviews in the order they should be offered to
+/// the reviewer:
 ///
-/// Doesn't differentiate between different course instances.
-pub async fn get_many_that_need_peer_reviews_by_exercise_id_and_review_priority(
+/// 1. Entries created before `starved_before` first, so that late answers are not left waiting until
+///    they are sent to manual review.
+/// 2. Entries that have received or have been offered the fewest peer reviews, so that the reviews
+///    are spread evenly across the answers.
+/// 3. Entries whose author the reviewer has reviewed the least on the course, so that the same
+///    reviewer and author are not paired repeatedly.
+/// 4. Entries with the highest peer review priority, and then the oldest entries.
+pub async fn get_many_that_need_peer_reviews_in_allocation_order(
     conn: &mut PgConnection,
     exercise_id: Uuid,
-    excluded_user_id: Uuid,
+    reviewer_user_id: Uuid,
     excluded_submissions_ids: &[Uuid],
+    starved_before: DateTime<Utc>,
     count: i64,
 ) -> ModelResult<Vec<PeerReviewQueueEntry>> {
     let res = sqlx::query_as!(
         PeerReviewQueueEntry,
         "
-SELECT *
-FROM peer_review_queue_entries
-WHERE exercise_id = $1
-  AND user_id <> $2
-  AND receiving_peer_reviews_exercise_slide_submission_id <> ALL($3)
-  AND received_enough_peer_reviews = 'false'
-  AND removed_from_queue_for_unusual_reason = 'false'
-  AND deleted_at IS NULL
-ORDER BY peer_review_priority DESC
-LIMIT $4
+SELECT prqe.*
+FROM peer_review_queue_entries prqe
+WHERE prqe.exercise_id = $1
+  AND prqe.user_id <> $2
+  AND prqe.receiving_peer_reviews_exercise_slide_submission_id <> ALL($3)
+  AND prqe.received_enough_peer_reviews = 'false'
+  AND prqe.removed_from_queue_for_unusual_reason = 'false'
+  AND prqe.deleted_at IS NULL
+ORDER BY prqe.created_at < $4 DESC,
+  (
+    SELECT COUNT(*)
+    FROM peer_or_self_review_submissions prs
+    WHERE prs.exercise_slide_submission_id = prqe.receiving_peer_reviews_exercise_slide_submission_id
+      AND prs.user_id <> prqe.user_id
+      AND prs.deleted_at IS NULL
+  ) + (
+    SELECT COUNT(*)
+    FROM offered_answers_to_peer_review_temporary oa
+    WHERE oa.exercise_slide_submission_id = prqe.receiving_peer_reviews_exercise_slide_submission_id
+      AND oa.created_at > now() - '1 hour'::interval
+  ) ASC,
+  (
+    SELECT COUNT(*)
+    FROM peer_or_self_review_submissions prs
+      JOIN exercise_slide_submissions reviewed ON reviewed.id = prs.exercise_slide_submission_id
+    WHERE prs.user_id = $2
+      AND prs.course_id = prqe.course_id
+      AND reviewed.user_id = prqe.user_id
+      AND prs.deleted_at IS NULL
+  ) ASC,
+  prqe.peer_review_priority DESC,
+  prqe.created_at ASC
+LIMIT $5
         ",
         exercise_id,
-        excluded_user_id,
+        reviewer_user_id,
         excluded_submissions_ids,
+        starved_before,
         count,
     )
     .fetch_all(conn)
@@ -597,3 +687,320 @@ pub async fn try_to_get_all_by_user_and_course_id(
         .await
         .optional()
 }
+
+pub async fn get_queue_health_by_exercise_id(
+    conn: &mut PgConnection,
+    exercise_id: Uuid,
+    peer_or_self_review_config: &PeerOrSelfReviewConfig,
+    now: DateTime<Utc>,
+) -> ModelResult<PeerReviewQueueHealth> {
+    let counts = sqlx::query!(
+        "
+SELECT COUNT(*) FILTER (
+    WHERE received_enough_peer_reviews
+      AND NOT removed_from_queue_for_unusual_reason
+  ) AS \"entries_with_enough_peer_reviews!\",
+  COUNT(*) FILTER (
+    WHERE removed_from_queue_for_unusual_reason
+  ) AS \"entries_removed_from_queue!\"
+FROM peer_review_queue_entries
+WHERE exercise_id = $1
+  AND deleted_at IS NULL
+        ",
+        exercise_id,
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+    let waiting_entries = sqlx::query!(
+        "
+SELECT prqe.id,
+  prqe.user_id,
+  prqe.receiving_peer_reviews_exercise_slide_submission_id,
+  prqe.created_at,
+  (
+    SELECT COUNT(*)
+    FROM peer_or_self_review_submissions prs
+    WHERE prs.exercise_slide_submission_id = prqe.receiving_peer_reviews_exercise_slide_submission_id
+      AND prs.user_id <> prqe.user_id
+      AND prs.deleted_at IS NULL
+  ) AS \"peer_reviews_received!\"
+FROM peer_review_queue_entries prqe
+WHERE prqe.exercise_id = $1
+  AND prqe.received_enough_peer_reviews = FALSE
+  AND prqe.removed_from_queue_for_unusual_reason = FALSE
+  AND prqe.deleted_at IS NULL
+ORDER BY prqe.created_at ASC
+        ",
+        exercise_id,
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let starvation_threshold = starvation_threshold(peer_or_self_review_config);
+    let wait_times = waiting_entries
+        .iter()
+        .map(|e| now - e.created_at)
+        .collect::<Vec<_>>();
+    let starved_entries = waiting_entries
+        .iter()
+        .filter(|e| now - e.created_at > starvation_threshold)
+        .map(|e| StarvedPeerReviewQueueEntry {
+            peer_review_queue_entry_id: e.id,
+            user_id: e.user_id,
+            receiving_peer_reviews_exercise_slide_submission_id: e
+                .receiving_peer_reviews_exercise_slide_submission_id,
+            created_at: e.created_at,
+            peer_reviews_received: e.peer_reviews_received,
+        })
+        .collect();
+    Ok(PeerReviewQueueHealth {
+        exercise_id,
+        entries_waiting_for_peer_reviews: waiting_entries.len() as i64,
+        entries_with_enough_peer_reviews: counts.entries_with_enough_peer_reviews,
+        entries_removed_from_queue: counts.entries_removed_from_queue,
+        starvation_threshold_hours: starvation_threshold.num_hours(),
+        wait_time_distribution: calculate_wait_time_distribution(&wait_times),
+        starved_entries,
+    })
+}
+
+fn calculate_wait_time_distribution(
+    wait_times: &[Duration],
+) -> PeerReviewQueueWaitTimeDistribution {
+    let mut hours = wait_times
+        .iter()
+        .map(|w| w.num_hours().max(0))
+        .collect::<Vec<_>>();
+    hours.sort_unstable();
+    // Nearest-rank percentile
+    let percentile = |p: usize| -> Option<i64> {
+        if hours.is_empty() {
+            return None;
+        }
+        let rank = (p * hours.len()).div_ceil(100).max(1);
+        hours.get(rank - 1).copied()
+    };
+
+    let mut buckets = Vec::with_capacity(WAIT_TIME_BUCKET_UPPER_BOUNDS_IN_HOURS.len() + 1);
+    let mut min_hours = 0;
+    for max_hours in WAIT_TIME_BUCKET_UPPER_BOUNDS_IN_HOURS
+        .iter()
+        .copied()
+        .map(Some)
+        .chain(std::iter::once(None))
+    {
+        let entries = hours
+            .iter()
+            .filter(|h| **h >= min_hours && max_hours.is_none_or(|max| **h < max))
+            .count() as i64;
+        buckets.push(PeerReviewQueueWaitTimeBucket {
+            min_hours,
+            max_hours,
+            entries,
+        });
+        min_hours = max_hours.unwrap_or(min_hours);
+    }
+
+    PeerReviewQueueWaitTimeDistribution {
+        median_hours: percentile(50),
+        p90_hours: percentile(90),
+        max_hours: hours.last().copied(),
+        buckets,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        exercise_slide_submissions::{self, NewExerciseSlideSubmission},
+        exercise_task_gradings::UserPointsUpdateStrategy,
+        peer_or_self_review_configs, peer_or_self_review_submissions,
+        test_helper::*,
+        users,
+    };
+
+    #[test]
+    fn calculates_wait_time_distribution() {
+        let wait_times = [1, 30, 30, 100, 600]
+            .into_iter()
+            .map(Duration::hours)
+            .collect::<Vec<_>>();
+        let distribution = calculate_wait_time_distribution(&wait_times);
+        assert_eq!(distribution.median_hours, Some(30));
+        assert_eq!(distribution.p90_hours, Some(600));
+        assert_eq!(distribution.max_hours, Some(600));
+        assert_eq!(
+            distribution
+                .buckets
+                .iter()
+                .map(|b| b.entries)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 1, 0, 0, 1]
+        );
+        assert_eq!(distribution.buckets.last().unwrap().max_hours, None);
+
+        let empty = calculate_wait_time_distribution(&[]);
+        assert_eq!(empty.median_hours, None);
+        assert!(empty.buckets.iter().all(|b| b.entries == 0));
+    }
+
+    #[tokio::test]
+    async fn allocates_fairly() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise, :slide);
+        let exercise = exercises::get_by_id(tx.as_mut(), exercise).await.unwrap();
+        let peer_or_self_review_config = peer_or_self_review_configs::get_by_exercise_or_course_id(
+            tx.as_mut(),
+            &exercise,
+            course,
+        )
+        .await
+        .unwrap();
+        let reviewer = user;
+        let mut users_and_submissions = vec![];
+        for _ in 0..3 {
+            let user_id = users::insert(
+                tx.as_mut(),
+                PKeyPolicy::Generate,
+                &format!("{}@example.com", Uuid::new_v4()),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+            let exercise_slide_submission =
+                exercise_slide_submissions::insert_exercise_slide_submission(
+                    tx.as_mut(),
+                    NewExerciseSlideSubmission {
+                        exercise_slide_id: slide,
+                        course_id: Some(course),
+                        exam_id: None,
+                        user_id,
+                        exercise_id: exercise.id,
+                        user_points_update_strategy:
+                            UserPointsUpdateStrategy::CanAddPointsAndCanRemovePoints,
+                    },
+                )
+                .await
+                .unwrap();
+            users_and_submissions.push((user_id, exercise_slide_submission.id));
+        }
+        let [
+            (author, submission),
+            (other_author, other_submission),
+            (third_user, _),
+        ] = users_and_submissions[..]
+        else {
+            unreachable!()
+        };
+        for (user_id, submission_id) in [(author, submission), (other_author, other_submission)] {
+            insert(
+                tx.as_mut(),
+                PKeyPolicy::Generate,
+                user_id,
+                exercise.id,
+                course,
+                submission_id,
+                0,
+            )
+            .await
+            .unwrap();
+        }
+        let allocation_order = |entries: Vec<PeerReviewQueueEntry>| {
+            entries
+                .into_iter()
+                .map(|e| e.receiving_peer_reviews_exercise_slide_submission_id)
+                .collect::<Vec<_>>()
+        };
+        let starved_before = Utc::now() - Duration::days(7);
+
+        // The reviewer has already reviewed an earlier answer by the first author
+        let earlier_submission = exercise_slide_submissions::insert_exercise_slide_submission(
+            tx.as_mut(),
+            NewExerciseSlideSubmission {
+                exercise_slide_id: slide,
+                course_id: Some(course),
+                exam_id: None,
+                user_id: author,
+                exercise_id: exercise.id,
+                user_points_update_strategy:
+                    UserPointsUpdateStrategy::CanAddPointsAndCanRemovePoints,
+            },
+        )
+        .await
+        .unwrap();
+        peer_or_self_review_submissions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            reviewer,
+            exercise.id,
+            course,
+            peer_or_self_review_config.id,
+            earlier_submission.id,
+        )
+        .await
+        .unwrap();
+        let entries = get_many_that_need_peer_reviews_in_allocation_order(
+            tx.as_mut(),
+            exercise.id,
+            reviewer,
+            &[],
+            starved_before,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            allocation_order(entries),
+            vec![other_submission, submission]
+        );
+
+        // Balancing the received reviews goes before avoiding repeated pairs
+        peer_or_self_review_submissions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            third_user,
+            exercise.id,
+            course,
+            peer_or_self_review_config.id,
+            other_submission,
+        )
+        .await
+        .unwrap();
+        let entries = get_many_that_need_peer_reviews_in_allocation_order(
+            tx.as_mut(),
+            exercise.id,
+            reviewer,
+            &[],
+            starved_before,
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            allocation_order(entries),
+            vec![submission, other_submission]
+        );
+
+        let health = get_queue_health_by_exercise_id(
+            tx.as_mut(),
+            exercise.id,
+            &peer_or_self_review_config,
+            Utc::now(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(health.entries_waiting_for_peer_reviews, 2);
+        assert!(health.starved_entries.is_empty());
+        let health = get_queue_health_by_exercise_id(
+            tx.as_mut(),
+            exercise.id,
+            &peer_or_self_review_config,
+            Utc::now() + Duration::days(30),
+        )
+        .await
+        .unwrap();
+        assert_eq!(health.starved_entries.len(), 2);
+        tx.rollback().await;
+    }
+}
@@ -0,0 +1,222 @@
+//! How reliable students are as peer reviewers on a course, based on their
+//! [`crate::peer_review_calibration_submissions`]. The reliability is used as the weight of the
+//! student's peer reviews when the received reviews are averaged.
+
+use std::collections::HashMap;
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct PeerReviewerReliability {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub user_id: Uuid,
+    pub course_id: Uuid,
+    pub reliability: f32,
+    pub calibration_reviews_given: i32,
+}
+
+/// Sets the reliability of the user to the average agreement of their calibration reviews on the
+/// course. The user must have given at least one calibration review on the course.
+pub async fn recalculate_for_user_and_course(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    course_id: Uuid,
+) -> ModelResult<PeerReviewerReliability> {
+    let res = sqlx::query_as!(
+        PeerReviewerReliability,
+        r#"
+INSERT INTO peer_reviewer_reliabilities (
+    user_id,
+    course_id,
+    reliability,
+    calibration_reviews_given
+  )
+SELECT $1,
+  $2,
+  AVG(agreement)::real,
+  COUNT(*)::integer
+FROM peer_review_calibration_submissions
+WHERE user_id = $1
+  AND course_id = $2
+  AND deleted_at IS NULL ON CONFLICT (user_id, course_id)
+WHERE deleted_at IS NULL DO
+UPDATE
+SET reliability = excluded.reliability,
+  calibration_reviews_given = excluded.calibration_reviews_given
+RETURNING *
+        "#,
+        user_id,
+        course_id,
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// None if the user has not given any calibration reviews on the course.
+pub async fn get_by_user_and_course(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    course_id: Uuid,
+) -> ModelResult<Option<PeerReviewerReliability>> {
+    let res = sqlx::query_as!(
+        PeerReviewerReliability,
+        r#"
+SELECT *
+FROM peer_reviewer_reliabilities
+WHERE user_id = $1
+  AND course_id = $2
+  AND deleted_at IS NULL
+        "#,
+        user_id,
+        course_id,
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The reliabilities of the givers of the peer review submissions, keyed by the peer review
+/// submission id. Submissions whose giver has no reliability are left out.
+pub async fn get_by_peer_or_self_review_submission_ids(
+    conn: &mut PgConnection,
+    peer_or_self_review_submission_ids: &[Uuid],
+) -> ModelResult<HashMap<Uuid, f32>> {
+    let res = sqlx::query!(
+        r#"
+SELECT prs.id AS peer_or_self_review_submission_id,
+  r.reliability
+FROM peer_or_self_review_submissions prs
+  JOIN peer_reviewer_reliabilities r ON r.user_id = prs.user_id
+  AND r.course_id = prs.course_id
+WHERE prs.id = ANY($1)
+  AND r.deleted_at IS NULL
+        "#,
+        peer_or_self_review_submission_ids,
+    )
+    .fetch_all(conn)
+    .await?
+    .into_iter()
+    .map(|r| (r.peer_or_self_review_submission_id, r.reliability))
+    .collect();
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        exercise_slide_submissions::{self, NewExerciseSlideSubmission},
+        exercise_task_gradings::UserPointsUpdateStrategy,
+        peer_or_self_review_configs,
+        peer_or_self_review_questions::{self, PeerOrSelfReviewQuestionType},
+        peer_or_self_review_submissions,
+        peer_review_calibration_answers::{
+            self, NewPeerReviewCalibrationAnswer, NewPeerReviewCalibrationReferenceScore,
+        },
+        peer_review_calibration_submissions,
+        test_helper::*,
+    };
+
+    #[tokio::test]
+    async fn reliability_is_the_average_agreement_of_calibration_reviews() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise, :slide);
+        let exercise = crate::exercises::get_by_id(tx.as_mut(), exercise)
+            .await
+            .unwrap();
+        let peer_or_self_review_config = peer_or_self_review_configs::get_by_exercise_or_course_id(
+            tx.as_mut(),
+            &exercise,
+            course,
+        )
+        .await
+        .unwrap();
+        let scale_question =
+            peer_or_self_review_questions::get_all_by_peer_or_self_review_config_id(
+                tx.as_mut(),
+                peer_or_self_review_config.id,
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|q| q.question_type == PeerOrSelfReviewQuestionType::Scale)
+            .unwrap();
+
+        let mut exercise_slide_submission_id = None;
+        for agreement in [1.0, 0.5] {
+            let exercise_slide_submission =
+                exercise_slide_submissions::insert_exercise_slide_submission(
+                    tx.as_mut(),
+                    NewExerciseSlideSubmission {
+                        exercise_slide_id: slide,
+                        course_id: Some(course),
+                        exam_id: None,
+                        user_id: user,
+                        exercise_id: exercise.id,
+                        user_points_update_strategy:
+                            UserPointsUpdateStrategy::CanAddPointsAndCanRemovePoints,
+                    },
+                )
+                .await
+                .unwrap();
+            exercise_slide_submission_id = Some(exercise_slide_submission.id);
+            let calibration_answer = peer_review_calibration_answers::insert(
+                tx.as_mut(),
+                &exercise,
+                &NewPeerReviewCalibrationAnswer {
+                    exercise_slide_submission_id: exercise_slide_submission.id,
+                    reference_scores: vec![NewPeerReviewCalibrationReferenceScore {
+                        peer_or_self_review_question_id: scale_question.id,
+                        number_data: 5.0,
+                    }],
+                },
+            )
+            .await
+            .unwrap();
+            peer_review_calibration_submissions::insert(
+                tx.as_mut(),
+                user,
+                course,
+                exercise.id,
+                calibration_answer.calibration_answer.id,
+                agreement,
+            )
+            .await
+            .unwrap();
+        }
+
+        let reliability = recalculate_for_user_and_course(tx.as_mut(), user, course)
+            .await
+            .unwrap();
+        assert_eq!(reliability.reliability, 0.75);
+        assert_eq!(reliability.calibration_reviews_given, 2);
+
+        let peer_or_self_review_submission_id = peer_or_self_review_submissions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            user,
+            exercise.id,
+            course,
+            peer_or_self_review_config.id,
+            exercise_slide_submission_id.unwrap(),
+        )
+        .await
+        .unwrap();
+        let reliabilities = get_by_peer_or_self_review_submission_ids(
+            tx.as_mut(),
+            &[peer_or_self_review_submission_id, Uuid::new_v4()],
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            reliabilities,
+            HashMap::from([(peer_or_self_review_submission_id, 0.75)])
+        );
+        tx.rollback().await;
+    }
+}
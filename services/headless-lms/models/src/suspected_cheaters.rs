@@ -292,6 +292,18 @@ RETURNING *
         &mut tx, course_id, user_id,
     )
     .await?;
+    let course_module_ids: Vec<Uuid> = course_modules::get_by_course_id(&mut tx, course_id)
+        .await?
+        .into_iter()
+        .map(|module| module.id)
+        .collect();
+    crate::generated_certificates::revoke_all_by_user_and_course_module_ids(
+        &mut tx,
+        user_id,
+        &course_module_ids,
+        "Cheating was confirmed on the course.",
+    )
+    .await?;
     tx.commit().await?;
     Ok(cheater)
 }
@@ -26,6 +26,8 @@ pub enum TeacherDecisionType {
     CustomPoints,
     SuspectedPlagiarism,
     RejectAndReset,
+    /// The points come from the levels the teacher picked from the rubric of the exercise. See [`crate::grading_rubric_assessments`].
+    Rubric,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
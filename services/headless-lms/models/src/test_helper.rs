@@ -2,6 +2,7 @@ use headless_lms_base::config::ApplicationConfiguration;
 use sqlx::{Connection, PgConnection, Postgres, Transaction};
 use std::env;
 use tokio::sync::Mutex;
+use uuid::Uuid;
 
 // tried storing PgPool here but that caused strange errors
 static DB_URL: Mutex<Option<String>> = Mutex::const_new(None);
@@ -292,6 +293,67 @@ macro_rules! insert_data {
 use crate::ModelResult;
 pub use crate::insert_data;
 
+/// Inserts a certificate configuration that requires the given course module.
+pub async fn insert_certificate_configuration(
+    conn: &mut PgConnection,
+    course_module_id: Uuid,
+) -> Uuid {
+    let background_svg_file_upload_id = crate::file_uploads::insert(
+        &mut *conn,
+        "background.svg",
+        "certificates/background.svg",
+        "image/svg+xml",
+        None,
+    )
+    .await
+    .unwrap();
+    let configuration = crate::certificate_configurations::insert(
+        &mut *conn,
+        &crate::certificate_configurations::DatabaseCertificateConfiguration {
+            id: Uuid::new_v4(),
+            certificate_owner_name_y_pos: None,
+            certificate_owner_name_x_pos: None,
+            certificate_owner_name_font_size: None,
+            certificate_owner_name_text_color: None,
+            certificate_owner_name_text_anchor: None,
+            certificate_validate_url_y_pos: None,
+            certificate_validate_url_x_pos: None,
+            certificate_validate_url_font_size: None,
+            certificate_validate_url_text_color: None,
+            certificate_validate_url_text_anchor: None,
+            certificate_date_y_pos: None,
+            certificate_date_x_pos: None,
+            certificate_date_font_size: None,
+            certificate_date_text_color: None,
+            certificate_date_text_anchor: None,
+            certificate_locale: None,
+            paper_size: None,
+            background_svg_path: "certificates/background.svg".to_string(),
+            background_svg_file_upload_id,
+            overlay_svg_path: None,
+            overlay_svg_file_upload_id: None,
+            render_certificate_grade: false,
+            certificate_grade_y_pos: None,
+            certificate_grade_x_pos: None,
+            certificate_grade_font_size: None,
+            certificate_grade_text_color: None,
+            certificate_grade_text_anchor: None,
+            pdf_enabled: false,
+            pdf_transcript_pages_enabled: false,
+        },
+    )
+    .await
+    .unwrap();
+    crate::certificate_configuration_to_requirements::insert(
+        &mut *conn,
+        configuration.id,
+        Some(course_module_id),
+    )
+    .await
+    .unwrap();
+    configuration.id
+}
+
 // checks that correct usage of the macro compiles
 #[allow(unused)]
 async fn _test() {
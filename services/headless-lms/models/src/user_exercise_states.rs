@@ -1,5 +1,5 @@
 use derive_more::Display;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use futures::Stream;
 use headless_lms_utils::numbers::option_f32_to_f32_two_decimals_with_none_as_zero;
@@ -9,6 +9,7 @@ use utoipa::ToSchema;
 use crate::{
     course_modules::{self, CourseModule},
     courses,
+    exam_question_pools::ExamQuestionBank,
     exercises::{ActivityProgress, Exercise, GradingProgress},
     prelude::*,
 };
@@ -400,7 +401,8 @@ pub async fn get_user_course_progress(
     merge_modules_with_metrics(course_modules, &course_metrics, &user_metrics, &course_name)
 }
 
-/// Gets the total amount of points that the user has received from an exam.
+/// Gets the total amount of points that the user has received from an exam. Only the exercises the
+/// user drew from the exam's question pools count.
 ///
 /// The caller should take into consideration that for an ongoing exam the result will be volatile.
 pub async fn get_user_total_exam_points(
@@ -408,16 +410,25 @@ pub async fn get_user_total_exam_points(
     user_id: Uuid,
     exam_id: Uuid,
 ) -> ModelResult<Option<f32>> {
+    let exercise_ids: Option<Vec<Uuid>> =
+        crate::exam_question_pools::get_exercise_ids_for_user(conn, exam_id, user_id)
+            .await?
+            .map(|ids| ids.into_iter().collect());
     let res = sqlx::query!(
         r#"
 SELECT SUM(score_given) AS "points"
 FROM user_exercise_states
 WHERE user_id = $2
   AND exam_id = $1
+  AND (
+    $3::uuid [] IS NULL
+    OR exercise_id = ANY($3)
+  )
   AND deleted_at IS NULL
         "#,
         exam_id,
         user_id,
+        exercise_ids.as_deref(),
     )
     .map(|x| x.points)
     .fetch_one(conn)
@@ -1138,7 +1149,12 @@ pub struct CourseUserPointsInner {
 pub struct ExamUserPoints {
     pub user_id: Uuid,
     pub email: String,
+    /// Only the exercises the user drew from the exam's question pools.
     pub points_for_exercise: Vec<ExamUserPointsInner>,
+    /// The exercises the user drew, see [`crate::exam_question_pools`].
+    pub exercise_ids: HashSet<Uuid>,
+    /// The sum of the maximum points of the exercises the user drew.
+    pub maximum_points: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -1192,15 +1208,25 @@ GROUP BY user_id
     .fetch(conn)
 }
 
+/// The question bank is used to filter each user's points to the exercises they drew, see
+/// [`crate::exam_question_pools::get_question_bank`].
 pub fn stream_exam_points(
     conn: &mut PgConnection,
     exam_id: Uuid,
+    question_bank: ExamQuestionBank,
 ) -> impl Stream<Item = sqlx::Result<ExamUserPoints>> + '_ {
     sqlx::query!(
         "
 SELECT user_id,
   email,
-  to_jsonb(array_agg(to_jsonb(uue) - 'email' - 'user_id')) AS points_for_exercises
+  to_jsonb(array_agg(to_jsonb(uue) - 'email' - 'user_id')) AS points_for_exercises,
+  (
+    SELECT ee.question_pool_seed
+    FROM exam_enrollments ee
+    WHERE ee.exam_id = $1
+      AND ee.user_id = uue.user_id
+      AND ee.deleted_at IS NULL
+  ) AS question_pool_seed
 FROM (
     SELECT u.id AS user_id,
       ud.email,
@@ -1220,15 +1246,24 @@ GROUP BY user_id,
 ",
         exam_id
     )
-    .try_map(|i| {
+    .try_map(move |i| {
         let user_id = i.user_id;
         let points_for_exercises = i.points_for_exercises.unwrap_or(Value::Null);
+        let exercise_ids = question_bank.exercise_ids_for_seed(i.question_pool_seed);
+        let maximum_points = question_bank.maximum_points_for_seed(i.question_pool_seed);
         serde_json::from_value(points_for_exercises)
-            .map(|points_for_exercise| ExamUserPoints {
-                user_id,
-                points_for_exercise,
-                email: i.email,
-            })
+            .map(
+                |points_for_exercise: Vec<ExamUserPointsInner>| ExamUserPoints {
+                    user_id,
+                    points_for_exercise: points_for_exercise
+                        .into_iter()
+                        .filter(|p| exercise_ids.contains(&p.exercise_id))
+                        .collect(),
+                    exercise_ids,
+                    maximum_points,
+                    email: i.email,
+                },
+            )
             .map_err(|e| sqlx::Error::Decode(Box::new(e)))
     })
     .fetch(conn)
@@ -0,0 +1,836 @@
+//! The outbox of the webhook deliveries.
+//!
+//! The events are read in order from the source table of each [`WebhookEventType`] with a cursor,
+//! and added once for every subscription in [`crate::webhook_subscriptions`] they match. The
+//! payloads only carry ids and the facts of the event, never emails or names.
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+use crate::webhook_subscriptions::WebhookEventType;
+
+/// How many deliveries are claimed for sending at a time.
+pub const FETCH_LIMIT: i64 = 100;
+
+/// How long a delivery keeps being retried after its first failure before it becomes a dead letter.
+pub const RETRY_WINDOW_SECS: i64 = 3 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Hash, sqlx::Type, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(type_name = "webhook_delivery_status", rename_all = "kebab-case")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    /// Not attempted again unless redelivered with [`redeliver`].
+    Dead,
+}
+
+/// A row of a source table that an event is made from.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct WebhookSourceEvent {
+    pub source_id: Uuid,
+    /// When the event happened. Also the position of the row in the source.
+    pub cursor_at: DateTime<Utc>,
+    pub organization_id: Uuid,
+    pub course_id: Option<Uuid>,
+    /// The facts of the event, sent as the `data` of the payload.
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct NewWebhookDelivery {
+    pub subscription_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: WebhookEventType,
+    pub source_id: Uuid,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub subscription_id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: WebhookEventType,
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempt_count: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub first_failed_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    /// The HTTP status of the last response, if the receiver responded.
+    pub last_response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// A delivery claimed for sending, with the endpoint and the secret of its subscription.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClaimedWebhookDelivery {
+    pub id: Uuid,
+    pub event_id: Uuid,
+    pub event_type: WebhookEventType,
+    pub payload: serde_json::Value,
+    pub url: String,
+    pub secret: String,
+    pub attempt_count: i32,
+    pub first_failed_at: Option<DateTime<Utc>>,
+}
+
+/// The position the source has been read to. Defaults to the beginning.
+pub async fn get_cursor(
+    conn: &mut PgConnection,
+    event_type: WebhookEventType,
+) -> ModelResult<(DateTime<Utc>, Uuid)> {
+    let res = sqlx::query!(
+        "
+SELECT cursor_at,
+  cursor_id
+FROM webhook_source_cursors
+WHERE event_type = $1
+  AND deleted_at IS NULL
+        ",
+        event_type as WebhookEventType
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res
+        .map(|row| (row.cursor_at, row.cursor_id))
+        .unwrap_or((DateTime::UNIX_EPOCH, Uuid::nil())))
+}
+
+pub async fn set_cursor(
+    conn: &mut PgConnection,
+    event_type: WebhookEventType,
+    cursor_at: DateTime<Utc>,
+    cursor_id: Uuid,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+INSERT INTO webhook_source_cursors (event_type, cursor_at, cursor_id)
+VALUES ($1, $2, $3)
+ON CONFLICT (event_type)
+WHERE deleted_at IS NULL DO UPDATE
+SET cursor_at = $2,
+  cursor_id = $3
+        ",
+        event_type as WebhookEventType,
+        cursor_at,
+        cursor_id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The events of the type after the cursor and before `until`, in order.
+pub async fn get_source_events(
+    conn: &mut PgConnection,
+    event_type: WebhookEventType,
+    after: (DateTime<Utc>, Uuid),
+    until: DateTime<Utc>,
+    limit: i64,
+) -> ModelResult<Vec<WebhookSourceEvent>> {
+    let (after_at, after_id) = after;
+    let res = match event_type {
+        WebhookEventType::CourseModuleCompletionCreated => {
+            sqlx::query_as!(
+                WebhookSourceEvent,
+                r#"
+SELECT cmc.id AS source_id,
+  cmc.created_at AS cursor_at,
+  c.organization_id,
+  cmc.course_id AS "course_id?",
+  jsonb_build_object(
+    'course_module_completion_id',
+    cmc.id,
+    'user_id',
+    cmc.user_id,
+    'course_id',
+    cmc.course_id,
+    'course_module_id',
+    cmc.course_module_id,
+    'completion_date',
+    cmc.completion_date,
+    'completion_language',
+    cmc.completion_language,
+    'passed',
+    cmc.passed,
+    'grade',
+    cmc.grade,
+    'eligible_for_ects',
+    cmc.eligible_for_ects
+  ) AS "data!"
+FROM course_module_completions cmc
+  JOIN courses c ON c.id = cmc.course_id
+WHERE cmc.deleted_at IS NULL
+  AND (cmc.created_at, cmc.id) > ($1, $2)
+  AND cmc.created_at < $3
+ORDER BY cmc.created_at,
+  cmc.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        WebhookEventType::CertificateGenerated => {
+            sqlx::query_as!(
+                WebhookSourceEvent,
+                r#"
+SELECT gc.id AS source_id,
+  gc.created_at AS cursor_at,
+  c.organization_id,
+  c.id AS "course_id?",
+  jsonb_build_object(
+    'certificate_id',
+    gc.id,
+    'user_id',
+    gc.user_id,
+    'course_id',
+    c.id,
+    'certificate_configuration_id',
+    gc.certificate_configuration_id,
+    'verification_id',
+    gc.verification_id,
+    'version',
+    gc.version
+  ) AS "data!"
+FROM generated_certificates gc
+  JOIN LATERAL (
+    SELECT cm.course_id
+    FROM certificate_configuration_to_requirements cctr
+      JOIN course_modules cm ON cm.id = cctr.course_module_id
+    WHERE cctr.certificate_configuration_id = gc.certificate_configuration_id
+      AND cctr.deleted_at IS NULL
+    ORDER BY cctr.created_at
+    LIMIT 1
+  ) requirement ON TRUE
+  JOIN courses c ON c.id = requirement.course_id
+WHERE gc.deleted_at IS NULL
+  AND (gc.created_at, gc.id) > ($1, $2)
+  AND gc.created_at < $3
+ORDER BY gc.created_at,
+  gc.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        WebhookEventType::CourseInstanceEnrollmentCreated => {
+            // Enrollments have no id of their own, so one is derived from the user and the
+            // course instance.
+            sqlx::query_as!(
+                WebhookSourceEvent,
+                r#"
+SELECT e.source_id AS "source_id!",
+  e.created_at AS "cursor_at!",
+  c.organization_id,
+  e.course_id AS "course_id?",
+  jsonb_build_object(
+    'user_id',
+    e.user_id,
+    'course_id',
+    e.course_id,
+    'course_instance_id',
+    e.course_instance_id
+  ) AS "data!"
+FROM (
+    SELECT md5(cie.user_id::text || cie.course_instance_id::text)::uuid AS source_id,
+      cie.created_at,
+      cie.user_id,
+      cie.course_id,
+      cie.course_instance_id
+    FROM course_instance_enrollments cie
+    WHERE cie.deleted_at IS NULL
+      AND cie.created_at >= $1
+      AND cie.created_at < $3
+  ) e
+  JOIN courses c ON c.id = e.course_id
+WHERE (e.created_at, e.source_id) > ($1, $2)
+ORDER BY e.created_at,
+  e.source_id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        WebhookEventType::ExamEnded => {
+            // The exams are read once the ended exams processor has handled them.
+            sqlx::query_as!(
+                WebhookSourceEvent,
+                r#"
+SELECT ex.id AS source_id,
+  epe.created_at AS cursor_at,
+  ex.organization_id,
+  NULL::UUID AS "course_id?",
+  jsonb_build_object(
+    'exam_id',
+    ex.id,
+    'name',
+    ex.name,
+    'starts_at',
+    ex.starts_at,
+    'ends_at',
+    ex.ends_at
+  ) AS "data!"
+FROM ended_processed_exams epe
+  JOIN exams ex ON ex.id = epe.exam_id
+WHERE epe.deleted_at IS NULL
+  AND (epe.created_at, ex.id) > ($1, $2)
+  AND epe.created_at < $3
+ORDER BY epe.created_at,
+  ex.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        WebhookEventType::ExerciseTaskGradingUpdated => {
+            // Regradings update the same row, so the gradings are read by when they were last
+            // updated and a grading can be sent more than once.
+            sqlx::query_as!(
+                WebhookSourceEvent,
+                r#"
+SELECT etg.id AS source_id,
+  etg.updated_at AS cursor_at,
+  COALESCE(c.organization_id, ex.organization_id) AS "organization_id!",
+  etg.course_id AS "course_id?",
+  jsonb_build_object(
+    'exercise_task_grading_id',
+    etg.id,
+    'user_id',
+    ess.user_id,
+    'course_id',
+    etg.course_id,
+    'exam_id',
+    etg.exam_id,
+    'exercise_id',
+    etg.exercise_id,
+    'exercise_task_id',
+    etg.exercise_task_id,
+    'score_given',
+    etg.score_given,
+    'unscaled_score_given',
+    etg.unscaled_score_given,
+    'unscaled_score_maximum',
+    etg.unscaled_score_maximum,
+    'grading_completed_at',
+    etg.grading_completed_at
+  ) AS "data!"
+FROM exercise_task_gradings etg
+  JOIN exercise_task_submissions ets ON ets.id = etg.exercise_task_submission_id
+  JOIN exercise_slide_submissions ess ON ess.id = ets.exercise_slide_submission_id
+  LEFT JOIN courses c ON c.id = etg.course_id
+  LEFT JOIN exams ex ON ex.id = etg.exam_id
+WHERE etg.deleted_at IS NULL
+  AND etg.grading_progress = 'fully-graded'
+  AND (c.id IS NOT NULL OR ex.id IS NOT NULL)
+  AND (etg.updated_at, etg.id) > ($1, $2)
+  AND etg.updated_at < $3
+ORDER BY etg.updated_at,
+  etg.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        WebhookEventType::CreditRegistrationStateChanged => {
+            sqlx::query_as!(
+                WebhookSourceEvent,
+                r#"
+SELECT cre.id AS source_id,
+  cre.created_at AS cursor_at,
+  c.organization_id,
+  cr.course_id AS "course_id?",
+  jsonb_build_object(
+    'credit_registration_id',
+    cr.id,
+    'user_id',
+    cr.user_id,
+    'course_id',
+    cr.course_id,
+    'course_module_id',
+    cr.course_module_id,
+    'course_module_completion_id',
+    cr.course_module_completion_id,
+    'from_state',
+    cre.from_state,
+    'to_state',
+    cre.to_state,
+    'error_code',
+    cre.error_code
+  ) AS "data!"
+FROM credit_registration_events cre
+  JOIN credit_registrations cr ON cr.id = cre.credit_registration_id
+  JOIN courses c ON c.id = cr.course_id
+WHERE cre.deleted_at IS NULL
+  AND cre.to_state IS NOT NULL
+  AND cre.from_state IS DISTINCT FROM cre.to_state
+  AND (cre.created_at, cre.id) > ($1, $2)
+  AND cre.created_at < $3
+ORDER BY cre.created_at,
+  cre.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+    };
+    Ok(res)
+}
+
+/// Adds the delivery to the outbox. Does nothing if the event has already been added for the
+/// subscription.
+pub async fn insert(conn: &mut PgConnection, new: &NewWebhookDelivery) -> ModelResult<()> {
+    sqlx::query!(
+        "
+INSERT INTO webhook_deliveries (
+    subscription_id,
+    event_id,
+    event_type,
+    source_id,
+    payload
+  )
+VALUES ($1, $2, $3, $4, $5)
+ON CONFLICT (subscription_id, event_id)
+WHERE deleted_at IS NULL DO NOTHING
+        ",
+        new.subscription_id,
+        new.event_id,
+        new.event_type as WebhookEventType,
+        new.source_id,
+        new.payload
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<WebhookDelivery> {
+    let res = sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+SELECT id,
+  created_at,
+  updated_at,
+  subscription_id,
+  event_id,
+  event_type AS "event_type: WebhookEventType",
+  payload,
+  status AS "status: WebhookDeliveryStatus",
+  attempt_count,
+  next_attempt_at,
+  first_failed_at,
+  last_attempt_at,
+  last_response_status,
+  last_error,
+  delivered_at
+FROM webhook_deliveries
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The delivery log of the subscription, newest first.
+pub async fn get_by_subscription_id(
+    conn: &mut PgConnection,
+    subscription_id: Uuid,
+    status: Option<WebhookDeliveryStatus>,
+    pagination: Pagination,
+) -> ModelResult<Vec<WebhookDelivery>> {
+    let res = sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+SELECT id,
+  created_at,
+  updated_at,
+  subscription_id,
+  event_id,
+  event_type AS "event_type: WebhookEventType",
+  payload,
+  status AS "status: WebhookDeliveryStatus",
+  attempt_count,
+  next_attempt_at,
+  first_failed_at,
+  last_attempt_at,
+  last_response_status,
+  last_error,
+  delivered_at
+FROM webhook_deliveries
+WHERE subscription_id = $1
+  AND (
+    $2::webhook_delivery_status IS NULL
+    OR status = $2
+  )
+  AND deleted_at IS NULL
+ORDER BY created_at DESC,
+  id
+LIMIT $3 OFFSET $4
+        "#,
+        subscription_id,
+        status as Option<WebhookDeliveryStatus>,
+        pagination.limit(),
+        pagination.offset()
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The dead letters of all the subscriptions of the organization, newest first.
+pub async fn get_dead_by_organization_id(
+    conn: &mut PgConnection,
+    organization_id: Uuid,
+    pagination: Pagination,
+) -> ModelResult<Vec<WebhookDelivery>> {
+    let res = sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+SELECT wd.id,
+  wd.created_at,
+  wd.updated_at,
+  wd.subscription_id,
+  wd.event_id,
+  wd.event_type AS "event_type: WebhookEventType",
+  wd.payload,
+  wd.status AS "status: WebhookDeliveryStatus",
+  wd.attempt_count,
+  wd.next_attempt_at,
+  wd.first_failed_at,
+  wd.last_attempt_at,
+  wd.last_response_status,
+  wd.last_error,
+  wd.delivered_at
+FROM webhook_deliveries wd
+  JOIN webhook_subscriptions ws ON ws.id = wd.subscription_id
+WHERE ws.organization_id = $1
+  AND ws.deleted_at IS NULL
+  AND wd.status = 'dead'
+  AND wd.deleted_at IS NULL
+ORDER BY wd.updated_at DESC,
+  wd.id
+LIMIT $2 OFFSET $3
+        "#,
+        organization_id,
+        pagination.limit(),
+        pagination.offset()
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Claims the pending deliveries that are due, leasing them for five minutes so that another
+/// deliverer does not send them at the same time. Only the deliveries of enabled subscriptions are
+/// claimed.
+pub async fn claim_due(
+    conn: &mut PgConnection,
+    limit: i64,
+) -> ModelResult<Vec<ClaimedWebhookDelivery>> {
+    let res = sqlx::query_as!(
+        ClaimedWebhookDelivery,
+        r#"
+WITH due AS (
+  SELECT wd.id
+  FROM webhook_deliveries wd
+    JOIN webhook_subscriptions ws ON ws.id = wd.subscription_id
+  WHERE wd.status = 'pending'
+    AND wd.next_attempt_at <= now()
+    AND wd.deleted_at IS NULL
+    AND ws.enabled
+    AND ws.deleted_at IS NULL
+  ORDER BY wd.next_attempt_at,
+    wd.created_at
+  FOR UPDATE OF wd SKIP LOCKED
+  LIMIT $1
+)
+UPDATE webhook_deliveries wd
+SET last_attempt_at = now(),
+  next_attempt_at = now() + interval '5 minutes',
+  attempt_count = wd.attempt_count + 1
+FROM due,
+  webhook_subscriptions ws
+WHERE wd.id = due.id
+  AND ws.id = wd.subscription_id
+RETURNING wd.id,
+  wd.event_id,
+  wd.event_type AS "event_type: WebhookEventType",
+  wd.payload,
+  ws.url,
+  ws.secret,
+  wd.attempt_count,
+  wd.first_failed_at
+        "#,
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn mark_delivered(
+    conn: &mut PgConnection,
+    id: Uuid,
+    response_status: i32,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE webhook_deliveries
+SET status = 'delivered',
+  delivered_at = now(),
+  last_response_status = $2,
+  last_error = NULL
+WHERE id = $1
+        ",
+        id,
+        response_status
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn schedule_retry(
+    conn: &mut PgConnection,
+    id: Uuid,
+    next_attempt_at: DateTime<Utc>,
+    response_status: Option<i32>,
+    error: &str,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE webhook_deliveries
+SET next_attempt_at = $2,
+  first_failed_at = COALESCE(first_failed_at, now()),
+  last_response_status = $3,
+  last_error = $4
+WHERE id = $1
+        ",
+        id,
+        next_attempt_at,
+        response_status,
+        error
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_dead(
+    conn: &mut PgConnection,
+    id: Uuid,
+    response_status: Option<i32>,
+    error: &str,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE webhook_deliveries
+SET status = 'dead',
+  first_failed_at = COALESCE(first_failed_at, now()),
+  last_response_status = $2,
+  last_error = $3
+WHERE id = $1
+        ",
+        id,
+        response_status,
+        error
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Moves a dead letter back to the outbox with a fresh retry window. The payload is sent as it was,
+/// so the receiver sees the same event id.
+pub async fn redeliver(conn: &mut PgConnection, id: Uuid) -> ModelResult<WebhookDelivery> {
+    let res = sqlx::query_as!(
+        WebhookDelivery,
+        r#"
+UPDATE webhook_deliveries
+SET status = 'pending',
+  next_attempt_at = now(),
+  first_failed_at = NULL
+WHERE id = $1
+  AND status = 'dead'
+  AND deleted_at IS NULL
+RETURNING id,
+  created_at,
+  updated_at,
+  subscription_id,
+  event_id,
+  event_type AS "event_type: WebhookEventType",
+  payload,
+  status AS "status: WebhookDeliveryStatus",
+  attempt_count,
+  next_attempt_at,
+  first_failed_at,
+  last_attempt_at,
+  last_response_status,
+  last_error,
+  delivered_at
+        "#,
+        id
+    )
+    .fetch_optional(conn)
+    .await?;
+    res.ok_or_else(|| {
+        model_err!(
+            PreconditionFailed,
+            "Only dead deliveries can be redelivered.".to_string()
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helper::*;
+    use crate::{
+        course_module_completions::{
+            self, CourseModuleCompletionGranter, NewCourseModuleCompletion,
+        },
+        webhook_subscriptions::{self, NewWebhookSubscription},
+    };
+
+    #[tokio::test]
+    async fn completions_are_read_from_the_cursor() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module);
+
+        let completion = course_module_completions::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            &NewCourseModuleCompletion {
+                course_id: course,
+                course_module_id: course_module.id,
+                user_id: user,
+                completion_date: Utc::now(),
+                completion_registration_attempt_date: None,
+                completion_language: "en-US".to_string(),
+                eligible_for_ects: true,
+                email: "student@example.com".to_string(),
+                grade: Some(4),
+                passed: true,
+            },
+            CourseModuleCompletionGranter::Automatic,
+        )
+        .await
+        .unwrap();
+
+        let until = Utc::now() + chrono::Duration::seconds(1);
+        let events = get_source_events(
+            tx.as_mut(),
+            WebhookEventType::CourseModuleCompletionCreated,
+            (DateTime::UNIX_EPOCH, Uuid::nil()),
+            until,
+            10,
+        )
+        .await
+        .unwrap();
+        let event = events
+            .iter()
+            .find(|event| event.source_id == completion.id)
+            .unwrap();
+        assert_eq!(event.organization_id, org);
+        assert_eq!(event.course_id, Some(course));
+        assert_eq!(event.data["grade"], 4);
+        assert!(event.data.get("email").is_none());
+
+        let after = get_source_events(
+            tx.as_mut(),
+            WebhookEventType::CourseModuleCompletionCreated,
+            (event.cursor_at, event.source_id),
+            until,
+            10,
+        )
+        .await
+        .unwrap();
+        assert!(after.iter().all(|e| e.source_id != completion.id));
+    }
+
+    #[tokio::test]
+    async fn dead_deliveries_can_be_redelivered() {
+        insert_data!(:tx, :user, :org);
+
+        let subscription = webhook_subscriptions::insert(
+            tx.as_mut(),
+            org,
+            &NewWebhookSubscription {
+                course_id: None,
+                url: "https://example.com/hooks".to_string(),
+                event_types: vec![WebhookEventType::ExamEnded],
+                enabled: true,
+            },
+            "secret",
+            user,
+        )
+        .await
+        .unwrap();
+        let new = NewWebhookDelivery {
+            subscription_id: subscription.id,
+            event_id: Uuid::new_v4(),
+            event_type: WebhookEventType::ExamEnded,
+            source_id: Uuid::new_v4(),
+            payload: serde_json::json!({ "type": "exam-ended" }),
+        };
+        insert(tx.as_mut(), &new).await.unwrap();
+        // The same event is added only once.
+        insert(tx.as_mut(), &new).await.unwrap();
+
+        let claimed = claim_due(tx.as_mut(), 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].secret, "secret");
+        assert_eq!(claimed[0].attempt_count, 1);
+        assert!(claim_due(tx.as_mut(), 10).await.unwrap().is_empty());
+
+        mark_dead(tx.as_mut(), claimed[0].id, Some(410), "Gone")
+            .await
+            .unwrap();
+        let dead = get_dead_by_organization_id(tx.as_mut(), org, Pagination::default())
+            .await
+            .unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].last_response_status, Some(410));
+
+        let redelivered = redeliver(tx.as_mut(), claimed[0].id).await.unwrap();
+        assert_eq!(redelivered.status, WebhookDeliveryStatus::Pending);
+        assert!(redeliver(tx.as_mut(), claimed[0].id).await.is_err());
+        assert_eq!(claim_due(tx.as_mut(), 10).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,324 @@
+//! Endpoints that are sent the events of an organization or a course, see [`crate::webhook_deliveries`].
+//!
+//! The secret the deliveries are signed with is never returned with the subscription. It is shown
+//! to the subscriber only when it is created, with [`insert`] or [`rotate_secret`].
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Hash, sqlx::Type, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(type_name = "webhook_event_type", rename_all = "kebab-case")]
+pub enum WebhookEventType {
+    CourseModuleCompletionCreated,
+    CertificateGenerated,
+    CourseInstanceEnrollmentCreated,
+    /// Exams belong to organizations, so only subscriptions without a course get these.
+    ExamEnded,
+    ExerciseTaskGradingUpdated,
+    CreditRegistrationStateChanged,
+}
+
+impl WebhookEventType {
+    pub const ALL: [WebhookEventType; 6] = [
+        WebhookEventType::CourseModuleCompletionCreated,
+        WebhookEventType::CertificateGenerated,
+        WebhookEventType::CourseInstanceEnrollmentCreated,
+        WebhookEventType::ExamEnded,
+        WebhookEventType::ExerciseTaskGradingUpdated,
+        WebhookEventType::CreditRegistrationStateChanged,
+    ];
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct WebhookSubscription {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub organization_id: Uuid,
+    /// If set, only the events of this course are sent.
+    pub course_id: Option<Uuid>,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+    pub enabled: bool,
+    pub created_by_user_id: Uuid,
+}
+
+impl WebhookSubscription {
+    /// Whether an event of the organization, and of the course if it has one, is sent to this
+    /// subscription.
+    pub fn matches(&self, organization_id: Uuid, course_id: Option<Uuid>) -> bool {
+        self.organization_id == organization_id
+            && (self.course_id.is_none() || self.course_id == course_id)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct NewWebhookSubscription {
+    pub course_id: Option<Uuid>,
+    pub url: String,
+    pub event_types: Vec<WebhookEventType>,
+    pub enabled: bool,
+}
+
+pub async fn insert(
+    conn: &mut PgConnection,
+    organization_id: Uuid,
+    new: &NewWebhookSubscription,
+    secret: &str,
+    created_by_user_id: Uuid,
+) -> ModelResult<WebhookSubscription> {
+    let res = sqlx::query_as!(
+        WebhookSubscription,
+        r#"
+INSERT INTO webhook_subscriptions (
+    organization_id,
+    course_id,
+    url,
+    secret,
+    event_types,
+    enabled,
+    created_by_user_id
+  )
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING id,
+  created_at,
+  updated_at,
+  organization_id,
+  course_id,
+  url,
+  event_types AS "event_types: Vec<WebhookEventType>",
+  enabled,
+  created_by_user_id
+        "#,
+        organization_id,
+        new.course_id,
+        new.url,
+        secret,
+        &new.event_types as &[WebhookEventType],
+        new.enabled,
+        created_by_user_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_id(conn: &mut PgConnection, id: Uuid) -> ModelResult<WebhookSubscription> {
+    let res = sqlx::query_as!(
+        WebhookSubscription,
+        r#"
+SELECT id,
+  created_at,
+  updated_at,
+  organization_id,
+  course_id,
+  url,
+  event_types AS "event_types: Vec<WebhookEventType>",
+  enabled,
+  created_by_user_id
+FROM webhook_subscriptions
+WHERE id = $1
+  AND deleted_at IS NULL
+        "#,
+        id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn get_by_organization_id(
+    conn: &mut PgConnection,
+    organization_id: Uuid,
+) -> ModelResult<Vec<WebhookSubscription>> {
+    let res = sqlx::query_as!(
+        WebhookSubscription,
+        r#"
+SELECT id,
+  created_at,
+  updated_at,
+  organization_id,
+  course_id,
+  url,
+  event_types AS "event_types: Vec<WebhookEventType>",
+  enabled,
+  created_by_user_id
+FROM webhook_subscriptions
+WHERE organization_id = $1
+  AND deleted_at IS NULL
+ORDER BY created_at
+        "#,
+        organization_id
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+/// The enabled subscriptions of all organizations that want the events of the type.
+pub async fn get_enabled_by_event_type(
+    conn: &mut PgConnection,
+    event_type: WebhookEventType,
+) -> ModelResult<Vec<WebhookSubscription>> {
+    let res = sqlx::query_as!(
+        WebhookSubscription,
+        r#"
+SELECT id,
+  created_at,
+  updated_at,
+  organization_id,
+  course_id,
+  url,
+  event_types AS "event_types: Vec<WebhookEventType>",
+  enabled,
+  created_by_user_id
+FROM webhook_subscriptions
+WHERE $1 = ANY(event_types)
+  AND enabled
+  AND deleted_at IS NULL
+        "#,
+        event_type as WebhookEventType
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn update(
+    conn: &mut PgConnection,
+    id: Uuid,
+    new: &NewWebhookSubscription,
+) -> ModelResult<WebhookSubscription> {
+    let res = sqlx::query_as!(
+        WebhookSubscription,
+        r#"
+UPDATE webhook_subscriptions
+SET course_id = $2,
+  url = $3,
+  event_types = $4,
+  enabled = $5
+WHERE id = $1
+  AND deleted_at IS NULL
+RETURNING id,
+  created_at,
+  updated_at,
+  organization_id,
+  course_id,
+  url,
+  event_types AS "event_types: Vec<WebhookEventType>",
+  enabled,
+  created_by_user_id
+        "#,
+        id,
+        new.course_id,
+        new.url,
+        &new.event_types as &[WebhookEventType],
+        new.enabled
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+/// Replaces the secret. The deliveries that are retried after this are signed with the new one.
+pub async fn rotate_secret(conn: &mut PgConnection, id: Uuid, secret: &str) -> ModelResult<()> {
+    let res = sqlx::query!(
+        "
+UPDATE webhook_subscriptions
+SET secret = $2
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id,
+        secret
+    )
+    .execute(conn)
+    .await?;
+    if res.rows_affected() == 0 {
+        return Err(model_err!(
+            NotFound,
+            "Webhook subscription not found.".to_string()
+        ));
+    }
+    Ok(())
+}
+
+/// Deletes the subscription. Its pending deliveries are no longer attempted.
+pub async fn delete(conn: &mut PgConnection, id: Uuid) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE webhook_subscriptions
+SET deleted_at = now()
+WHERE id = $1
+  AND deleted_at IS NULL
+        ",
+        id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_helper::*;
+
+    #[tokio::test]
+    async fn only_enabled_subscriptions_of_the_event_type_are_returned() {
+        insert_data!(:tx, :user, :org, :course);
+
+        let course_subscription = insert(
+            tx.as_mut(),
+            org,
+            &NewWebhookSubscription {
+                course_id: Some(course),
+                url: "https://example.com/hooks".to_string(),
+                event_types: vec![
+                    WebhookEventType::CourseModuleCompletionCreated,
+                    WebhookEventType::CertificateGenerated,
+                ],
+                enabled: true,
+            },
+            "secret",
+            user,
+        )
+        .await
+        .unwrap();
+        let disabled = insert(
+            tx.as_mut(),
+            org,
+            &NewWebhookSubscription {
+                course_id: None,
+                url: "https://example.com/other".to_string(),
+                event_types: vec![WebhookEventType::CourseModuleCompletionCreated],
+                enabled: false,
+            },
+            "secret",
+            user,
+        )
+        .await
+        .unwrap();
+
+        let enabled =
+            get_enabled_by_event_type(tx.as_mut(), WebhookEventType::CourseModuleCompletionCreated)
+                .await
+                .unwrap();
+        assert_eq!(enabled, vec![course_subscription.clone()]);
+        assert!(
+            get_enabled_by_event_type(tx.as_mut(), WebhookEventType::ExamEnded)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        assert!(course_subscription.matches(org, Some(course)));
+        assert!(!course_subscription.matches(org, Some(Uuid::new_v4())));
+        assert!(!course_subscription.matches(org, None));
+        assert!(disabled.matches(org, None));
+        assert!(disabled.matches(org, Some(course)));
+        assert!(!disabled.matches(Uuid::new_v4(), Some(course)));
+    }
+}
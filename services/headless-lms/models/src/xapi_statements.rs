@@ -0,0 +1,647 @@
+//! The outbox of the xAPI statements sent to the learning record store.
+//!
+//! The statements are made from the rows of the source tables in [`XapiStatementSource`]. Each
+//! source is read in order from a cursor, and only the events of courses that have opted in with
+//! [`crate::course_xapi_opt_ins`] and of users who have given their research consent are returned.
+//! Page visits are not tied to users, so they are returned with the daily anonymous visitor
+//! identifier instead.
+
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+/// How many statements are claimed for sending at a time.
+pub const FETCH_LIMIT: i64 = 200;
+
+/// How long a statement keeps being retried after its first failure.
+pub const RETRY_WINDOW_SECS: i64 = 3 * 24 * 60 * 60;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Hash, sqlx::Type, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+#[sqlx(type_name = "xapi_statement_source", rename_all = "kebab-case")]
+pub enum XapiStatementSource {
+    ExerciseSlideSubmission,
+    ExerciseTaskGrading,
+    PageVisit,
+    PeerOrSelfReviewSubmission,
+    CourseModuleCompletion,
+}
+
+impl XapiStatementSource {
+    pub const ALL: [XapiStatementSource; 5] = [
+        XapiStatementSource::ExerciseSlideSubmission,
+        XapiStatementSource::ExerciseTaskGrading,
+        XapiStatementSource::PageVisit,
+        XapiStatementSource::PeerOrSelfReviewSubmission,
+        XapiStatementSource::CourseModuleCompletion,
+    ];
+}
+
+/// A row of a source table that a statement is made from.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct XapiSourceEvent {
+    pub source_id: Uuid,
+    /// When the event happened. Also the position of the row in the source.
+    pub cursor_at: DateTime<Utc>,
+    pub course_id: Uuid,
+    pub user_id: Option<Uuid>,
+    /// Only for page visits.
+    pub anonymous_identifier: Option<String>,
+    /// The exercise, page or course module of the event.
+    pub object_id: Uuid,
+    pub object_name: Option<String>,
+    pub score_given: Option<f32>,
+    pub score_maximum: Option<i32>,
+    pub passed: Option<bool>,
+    pub grade: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct NewXapiStatement {
+    /// Also the id of the statement.
+    pub id: Uuid,
+    pub source: XapiStatementSource,
+    pub source_id: Uuid,
+    pub course_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub statement: serde_json::Value,
+}
+
+/// A statement claimed for sending.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct XapiStatement {
+    pub id: Uuid,
+    pub statement: serde_json::Value,
+    pub retry_count: i32,
+    pub first_failed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct XapiStatementCounts {
+    /// Waiting to be sent, including the ones being retried.
+    pub pending: i64,
+    pub sent: i64,
+    /// Rejected by the learning record store or not sent within the retry window.
+    pub failed: i64,
+}
+
+/// The position the source has been read to. Defaults to the beginning.
+pub async fn get_cursor(
+    conn: &mut PgConnection,
+    source: XapiStatementSource,
+) -> ModelResult<(DateTime<Utc>, Uuid)> {
+    let res = sqlx::query!(
+        "
+SELECT cursor_at,
+  cursor_id
+FROM xapi_source_cursors
+WHERE source = $1
+  AND deleted_at IS NULL
+        ",
+        source as XapiStatementSource
+    )
+    .fetch_optional(conn)
+    .await?;
+    Ok(res
+        .map(|row| (row.cursor_at, row.cursor_id))
+        .unwrap_or((DateTime::UNIX_EPOCH, Uuid::nil())))
+}
+
+pub async fn set_cursor(
+    conn: &mut PgConnection,
+    source: XapiStatementSource,
+    cursor_at: DateTime<Utc>,
+    cursor_id: Uuid,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+INSERT INTO xapi_source_cursors (source, cursor_at, cursor_id)
+VALUES ($1, $2, $3)
+ON CONFLICT (source)
+WHERE deleted_at IS NULL DO UPDATE
+SET cursor_at = $2,
+  cursor_id = $3
+        ",
+        source as XapiStatementSource,
+        cursor_at,
+        cursor_id
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// The events of the source after the cursor and before `until`, in order.
+pub async fn get_source_events(
+    conn: &mut PgConnection,
+    source: XapiStatementSource,
+    after: (DateTime<Utc>, Uuid),
+    until: DateTime<Utc>,
+    limit: i64,
+) -> ModelResult<Vec<XapiSourceEvent>> {
+    let (after_at, after_id) = after;
+    let res = match source {
+        XapiStatementSource::ExerciseSlideSubmission => {
+            sqlx::query_as!(
+                XapiSourceEvent,
+                r#"
+SELECT ess.id AS source_id,
+  ess.created_at AS cursor_at,
+  o.course_id,
+  ess.user_id AS "user_id?",
+  NULL::VARCHAR AS anonymous_identifier,
+  e.id AS object_id,
+  e.name AS "object_name?",
+  NULL::REAL AS score_given,
+  NULL::INTEGER AS score_maximum,
+  NULL::BOOLEAN AS passed,
+  NULL::INTEGER AS grade
+FROM exercise_slide_submissions ess
+  JOIN exercises e ON e.id = ess.exercise_id
+  JOIN course_xapi_opt_ins o ON o.course_id = ess.course_id
+  AND o.deleted_at IS NULL
+  AND o.created_at <= ess.created_at
+  JOIN user_research_consents urc ON urc.user_id = ess.user_id
+  AND urc.research_consent
+  AND urc.deleted_at IS NULL
+WHERE ess.deleted_at IS NULL
+  AND (ess.created_at, ess.id) > ($1, $2)
+  AND ess.created_at < $3
+ORDER BY ess.created_at,
+  ess.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        XapiStatementSource::ExerciseTaskGrading => {
+            sqlx::query_as!(
+                XapiSourceEvent,
+                r#"
+SELECT etg.id AS source_id,
+  etg.grading_completed_at AS "cursor_at!",
+  o.course_id,
+  ess.user_id AS "user_id?",
+  NULL::VARCHAR AS anonymous_identifier,
+  e.id AS object_id,
+  e.name AS "object_name?",
+  etg.unscaled_score_given AS score_given,
+  etg.unscaled_score_maximum AS score_maximum,
+  NULL::BOOLEAN AS passed,
+  NULL::INTEGER AS grade
+FROM exercise_task_gradings etg
+  JOIN exercise_task_submissions ets ON ets.id = etg.exercise_task_submission_id
+  JOIN exercise_slide_submissions ess ON ess.id = ets.exercise_slide_submission_id
+  JOIN exercises e ON e.id = etg.exercise_id
+  JOIN course_xapi_opt_ins o ON o.course_id = etg.course_id
+  AND o.deleted_at IS NULL
+  AND o.created_at <= etg.grading_completed_at
+  JOIN user_research_consents urc ON urc.user_id = ess.user_id
+  AND urc.research_consent
+  AND urc.deleted_at IS NULL
+WHERE etg.deleted_at IS NULL
+  AND etg.grading_progress = 'fully-graded'
+  AND (etg.grading_completed_at, etg.id) > ($1, $2)
+  AND etg.grading_completed_at < $3
+ORDER BY etg.grading_completed_at,
+  etg.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        XapiStatementSource::PageVisit => {
+            sqlx::query_as!(
+                XapiSourceEvent,
+                r#"
+SELECT pvd.id AS source_id,
+  pvd.created_at AS cursor_at,
+  o.course_id,
+  NULL::UUID AS user_id,
+  pvd.anonymous_identifier,
+  p.id AS object_id,
+  p.title AS "object_name?",
+  NULL::REAL AS score_given,
+  NULL::INTEGER AS score_maximum,
+  NULL::BOOLEAN AS passed,
+  NULL::INTEGER AS grade
+FROM page_visit_datum pvd
+  JOIN pages p ON p.id = pvd.page_id
+  JOIN course_xapi_opt_ins o ON o.course_id = pvd.course_id
+  AND o.deleted_at IS NULL
+  AND o.created_at <= pvd.created_at
+WHERE pvd.deleted_at IS NULL
+  AND NOT pvd.is_bot
+  AND pvd.anonymous_identifier IS NOT NULL
+  AND (pvd.created_at, pvd.id) > ($1, $2)
+  AND pvd.created_at < $3
+ORDER BY pvd.created_at,
+  pvd.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        XapiStatementSource::PeerOrSelfReviewSubmission => {
+            sqlx::query_as!(
+                XapiSourceEvent,
+                r#"
+SELECT prs.id AS source_id,
+  prs.created_at AS cursor_at,
+  o.course_id,
+  prs.user_id AS "user_id?",
+  NULL::VARCHAR AS anonymous_identifier,
+  e.id AS object_id,
+  e.name AS "object_name?",
+  NULL::REAL AS score_given,
+  NULL::INTEGER AS score_maximum,
+  NULL::BOOLEAN AS passed,
+  NULL::INTEGER AS grade
+FROM peer_or_self_review_submissions prs
+  JOIN exercises e ON e.id = prs.exercise_id
+  JOIN course_xapi_opt_ins o ON o.course_id = prs.course_id
+  AND o.deleted_at IS NULL
+  AND o.created_at <= prs.created_at
+  JOIN user_research_consents urc ON urc.user_id = prs.user_id
+  AND urc.research_consent
+  AND urc.deleted_at IS NULL
+WHERE prs.deleted_at IS NULL
+  AND (prs.created_at, prs.id) > ($1, $2)
+  AND prs.created_at < $3
+ORDER BY prs.created_at,
+  prs.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+        XapiStatementSource::CourseModuleCompletion => {
+            sqlx::query_as!(
+                XapiSourceEvent,
+                r#"
+SELECT cmc.id AS source_id,
+  cmc.created_at AS cursor_at,
+  o.course_id,
+  cmc.user_id AS "user_id?",
+  NULL::VARCHAR AS anonymous_identifier,
+  cm.id AS object_id,
+  cm.name AS "object_name?",
+  NULL::REAL AS score_given,
+  NULL::INTEGER AS score_maximum,
+  cmc.passed AS "passed?",
+  cmc.grade
+FROM course_module_completions cmc
+  JOIN course_modules cm ON cm.id = cmc.course_module_id
+  JOIN course_xapi_opt_ins o ON o.course_id = cmc.course_id
+  AND o.deleted_at IS NULL
+  AND o.created_at <= cmc.created_at
+  JOIN user_research_consents urc ON urc.user_id = cmc.user_id
+  AND urc.research_consent
+  AND urc.deleted_at IS NULL
+WHERE cmc.deleted_at IS NULL
+  AND (cmc.created_at, cmc.id) > ($1, $2)
+  AND cmc.created_at < $3
+ORDER BY cmc.created_at,
+  cmc.id
+LIMIT $4
+                "#,
+                after_at,
+                after_id,
+                until,
+                limit
+            )
+            .fetch_all(conn)
+            .await?
+        }
+    };
+    Ok(res)
+}
+
+/// Adds the statement to the outbox. Does nothing if a statement has already been made from the
+/// source row.
+pub async fn insert(conn: &mut PgConnection, new: &NewXapiStatement) -> ModelResult<()> {
+    sqlx::query!(
+        "
+INSERT INTO xapi_statements (
+    id,
+    source,
+    source_id,
+    course_id,
+    user_id,
+    statement
+  )
+VALUES ($1, $2, $3, $4, $5, $6)
+ON CONFLICT (source, source_id)
+WHERE deleted_at IS NULL DO NOTHING
+        ",
+        new.id,
+        new.source as XapiStatementSource,
+        new.source_id,
+        new.course_id,
+        new.user_id,
+        new.statement
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Claims the statements that are due for sending. The claimed statements are leased for five
+/// minutes, so that they are picked up again if the sender stops before recording the result.
+pub async fn claim_due(conn: &mut PgConnection, limit: i64) -> ModelResult<Vec<XapiStatement>> {
+    let res = sqlx::query_as!(
+        XapiStatement,
+        r#"
+WITH due AS (
+  SELECT id
+  FROM xapi_statements
+  WHERE sent_at IS NULL
+    AND retryable
+    AND deleted_at IS NULL
+    AND (
+      next_retry_at IS NULL
+      OR next_retry_at <= now()
+    )
+  ORDER BY coalesce(next_retry_at, '-infinity'::timestamptz),
+    created_at
+  FOR UPDATE SKIP LOCKED
+  LIMIT $1
+)
+UPDATE xapi_statements xs
+SET last_attempt_at = now(),
+  next_retry_at = now() + interval '5 minutes'
+FROM due
+WHERE xs.id = due.id
+RETURNING xs.id,
+  xs.statement,
+  xs.retry_count,
+  xs.first_failed_at
+        "#,
+        limit
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(res)
+}
+
+pub async fn mark_sent(conn: &mut PgConnection, ids: &[Uuid]) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE xapi_statements
+SET sent_at = now(),
+  next_retry_at = NULL,
+  last_error = NULL
+WHERE id = ANY($1)
+        ",
+        ids
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn schedule_retry(
+    conn: &mut PgConnection,
+    id: Uuid,
+    next_retry_at: DateTime<Utc>,
+    error: &str,
+) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE xapi_statements
+SET retry_count = retry_count + 1,
+  next_retry_at = $2,
+  first_failed_at = COALESCE(first_failed_at, now()),
+  last_error = $3
+WHERE id = $1
+        ",
+        id,
+        next_retry_at,
+        error
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_non_retryable(conn: &mut PgConnection, id: Uuid, error: &str) -> ModelResult<()> {
+    sqlx::query!(
+        "
+UPDATE xapi_statements
+SET retry_count = retry_count + 1,
+  next_retry_at = NULL,
+  retryable = FALSE,
+  first_failed_at = COALESCE(first_failed_at, now()),
+  last_error = $2
+WHERE id = $1
+        ",
+        id,
+        error
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Deletes the unsent statements of the users who no longer have a research consent. Returns how
+/// many were deleted.
+pub async fn delete_unsent_without_consent(conn: &mut PgConnection) -> ModelResult<u64> {
+    let res = sqlx::query!(
+        "
+UPDATE xapi_statements xs
+SET deleted_at = now()
+WHERE xs.sent_at IS NULL
+  AND xs.deleted_at IS NULL
+  AND xs.user_id IS NOT NULL
+  AND NOT EXISTS (
+    SELECT 1
+    FROM user_research_consents urc
+    WHERE urc.user_id = xs.user_id
+      AND urc.research_consent
+      AND urc.deleted_at IS NULL
+  )
+        "
+    )
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected())
+}
+
+pub async fn get_counts_by_course_id(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+) -> ModelResult<XapiStatementCounts> {
+    let res = sqlx::query_as!(
+        XapiStatementCounts,
+        r#"
+SELECT COUNT(*) FILTER (
+    WHERE sent_at IS NULL
+      AND retryable
+  ) AS "pending!",
+  COUNT(*) FILTER (
+    WHERE sent_at IS NOT NULL
+  ) AS "sent!",
+  COUNT(*) FILTER (
+    WHERE sent_at IS NULL
+      AND NOT retryable
+  ) AS "failed!"
+FROM xapi_statements
+WHERE course_id = $1
+  AND deleted_at IS NULL
+        "#,
+        course_id
+    )
+    .fetch_one(conn)
+    .await?;
+    Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{course_xapi_opt_ins, test_helper::*, user_research_consents};
+
+    #[tokio::test]
+    async fn only_events_of_consenting_users_on_opted_in_courses_are_returned() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise, :slide);
+        let other_user = crate::users::insert(
+            tx.as_mut(),
+            PKeyPolicy::Generate,
+            "xapi-other@example.com",
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        course_xapi_opt_ins::opt_in(tx.as_mut(), course, user)
+            .await
+            .unwrap();
+        user_research_consents::upsert(tx.as_mut(), PKeyPolicy::Generate, user, true)
+            .await
+            .unwrap();
+        user_research_consents::upsert(tx.as_mut(), PKeyPolicy::Generate, other_user, false)
+            .await
+            .unwrap();
+        for user_id in [user, other_user] {
+            crate::exercise_slide_submissions::insert_exercise_slide_submission(
+                tx.as_mut(),
+                crate::exercise_slide_submissions::NewExerciseSlideSubmission {
+                    exercise_slide_id: slide,
+                    course_id: Some(course),
+                    exam_id: None,
+                    user_id,
+                    exercise_id: exercise,
+                    user_points_update_strategy:
+                        crate::exercise_task_gradings::UserPointsUpdateStrategy::CanAddPointsButCannotRemovePoints,
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let events = get_source_events(
+            tx.as_mut(),
+            XapiStatementSource::ExerciseSlideSubmission,
+            get_cursor(tx.as_mut(), XapiStatementSource::ExerciseSlideSubmission)
+                .await
+                .unwrap(),
+            Utc::now() + chrono::Duration::minutes(1),
+            10,
+        )
+        .await
+        .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].user_id, Some(user));
+        assert_eq!(events[0].object_id, exercise);
+    }
+
+    #[tokio::test]
+    async fn claimed_statements_are_not_claimed_again() {
+        insert_data!(:tx, :user, :org, :course);
+        user_research_consents::upsert(tx.as_mut(), PKeyPolicy::Generate, user, true)
+            .await
+            .unwrap();
+        let new = NewXapiStatement {
+            id: Uuid::new_v4(),
+            source: XapiStatementSource::CourseModuleCompletion,
+            source_id: Uuid::new_v4(),
+            course_id: course,
+            user_id: Some(user),
+            statement: serde_json::json!({}),
+        };
+        insert(tx.as_mut(), &new).await.unwrap();
+        // A second statement from the same row is ignored.
+        insert(
+            tx.as_mut(),
+            &NewXapiStatement {
+                id: Uuid::new_v4(),
+                ..new.clone()
+            },
+        )
+        .await
+        .unwrap();
+
+        let claimed = claim_due(tx.as_mut(), 10).await.unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, new.id);
+        assert!(claim_due(tx.as_mut(), 10).await.unwrap().is_empty());
+
+        mark_sent(tx.as_mut(), &[new.id]).await.unwrap();
+        let counts = get_counts_by_course_id(tx.as_mut(), course).await.unwrap();
+        assert_eq!(
+            counts,
+            XapiStatementCounts {
+                pending: 0,
+                sent: 1,
+                failed: 0
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn withdrawing_consent_deletes_unsent_statements() {
+        insert_data!(:tx, :user, :org, :course);
+        user_research_consents::upsert(tx.as_mut(), PKeyPolicy::Generate, user, true)
+            .await
+            .unwrap();
+        insert(
+            tx.as_mut(),
+            &NewXapiStatement {
+                id: Uuid::new_v4(),
+                source: XapiStatementSource::ExerciseSlideSubmission,
+                source_id: Uuid::new_v4(),
+                course_id: course,
+                user_id: Some(user),
+                statement: serde_json::json!({}),
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(delete_unsent_without_consent(tx.as_mut()).await.unwrap(), 0);
+
+        user_research_consents::upsert(tx.as_mut(), PKeyPolicy::Generate, user, false)
+            .await
+            .unwrap();
+        assert_eq!(delete_unsent_without_consent(tx.as_mut()).await.unwrap(), 1);
+    }
+}
@@ -116,10 +116,19 @@ async fn get_chapters_exercises(
         .into_iter()
         .filter(|page_with_exercises| can_view_hidden_pages || !page_with_exercises.page.hidden)
         .collect();
-    let chapter_pages_with_exercises = models::pages::filter_course_material_pages_with_exercises(
+    let mut chapter_pages_with_exercises =
+        models::pages::filter_course_material_pages_with_exercises(
+            &mut conn,
+            user_id,
+            chapter_pages_with_exercises,
+        )
+        .await?;
+    models::deadline_exceptions::apply_effective_exercise_deadlines(
         &mut conn,
+        chapter_pages_with_exercises
+            .iter_mut()
+            .flat_map(|page_with_exercises| page_with_exercises.exercises.iter_mut()),
         user_id,
-        chapter_pages_with_exercises,
     )
     .await?;
     token.authorized_ok(web::Json(chapter_pages_with_exercises))
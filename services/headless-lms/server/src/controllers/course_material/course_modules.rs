@@ -124,12 +124,18 @@ async fn get_user_course_module_exercises_by_exercise_type(
     course_id)
         .await?;
 
-    let exercises = models::exercises::get_exercises_by_module_containing_exercise_type(
+    let mut exercises = models::exercises::get_exercises_by_module_containing_exercise_type(
         &mut conn,
         &exercise_type,
         course_module_id,
     )
     .await?;
+    models::deadline_exceptions::apply_effective_exercise_deadlines(
+        &mut conn,
+        &mut exercises,
+        Some(user.id),
+    )
+    .await?;
     let user_variables =
     user_course_exercise_service_variables::get_all_user_variables_for_user_and_course_and_exercise_type(&mut conn, user.id, course_id, &exercise_type).await?;
     let res = CustomViewExerciseSubmissions {
@@ -552,12 +552,23 @@ async fn get_chapters(
     let token = authorize_access_to_course_material(&mut conn, user_id, *course_id).await?;
     let course_modules = models::course_modules::get_by_course_id(&mut conn, *course_id).await?;
     let exercise_deadline_overrides =
-        models::chapters::exercise_deadline_overrides_by_chapter_for_course(&mut conn, *course_id)
-            .await?;
+        models::chapters::exercise_deadline_overrides_by_chapter_for_course(
+            &mut conn, *course_id, user_id,
+        )
+        .await?;
+    let chapter_deadlines = if let Some(user_id) = user_id {
+        models::deadline_exceptions::get_chapter_deadlines_for_user(&mut conn, *course_id, user_id)
+            .await?
+    } else {
+        HashMap::new()
+    };
     let chapters = models::chapters::get_course_chapters(&mut conn, *course_id)
         .await?
         .into_iter()
-        .map(|chapter| {
+        .map(|mut chapter| {
+            if let Some(deadline) = chapter_deadlines.get(&chapter.id) {
+                chapter.deadline = Some(*deadline);
+            }
             let chapter_image_url = chapter
                 .chapter_image_path
                 .as_ref()
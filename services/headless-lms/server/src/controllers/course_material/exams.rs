@@ -1,6 +1,14 @@
-use chrono::{DateTime, Duration, Utc};
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
 use headless_lms_models::{CourseOrExamId, ModelError, ModelErrorType, exercises::Exercise};
+use headless_lms_utils::ip_to_country::IpToCountryMapper;
 use models::{
+    exam_accommodations,
+    exam_enrollment_events::{
+        self, ClientExamEnrollmentEvent, ExamEnrollmentEventType, NewExamEnrollmentEvent,
+    },
+    exam_question_pools,
     exams::{self, ExamEnrollment},
     exercises,
     pages::{self, Page},
@@ -19,7 +27,8 @@ use crate::prelude::*;
     fetch_exam_for_testing,
     update_show_exercise_answers,
     reset_exam_progress,
-    end_exam_time
+    end_exam_time,
+    post_exam_enrollment_event
 ))]
 pub(crate) struct CourseMaterialExamsApiDoc;
 
@@ -71,12 +80,14 @@ POST /api/v0/course-material/exams/:id/enroll
         (status = 200, description = "Enrollment created", body = ())
     )
 )]
-#[instrument(skip(pool))]
+#[instrument(skip(pool, req, ip_to_country_mapper))]
 pub async fn enroll(
     pool: web::Data<PgPool>,
     exam_id: web::Path<Uuid>,
     user: AuthUser,
     payload: web::Json<IsTeacherTesting>,
+    req: HttpRequest,
+    ip_to_country_mapper: web::Data<IpToCountryMapper>,
 ) -> ControllerResult<web::Json<()>> {
     let mut conn = pool.acquire().await?;
     let exam = exams::get(&mut conn, *exam_id).await?;
@@ -88,9 +99,12 @@ pub async fn enroll(
         return token.authorized_ok(web::Json(()));
     }
 
+    // students with an accommodation may have their own window
+    let timing = exam_accommodations::get_exam_timing_for_user(&mut conn, &exam, user.id).await?;
+
     // check that the exam is not over
     let now = Utc::now();
-    if exam.ended_at_or(now, false) {
+    if timing.ended_at_or(now, false) {
         return Err(ControllerError::new(
             ControllerErrorType::Forbidden,
             "Exam is over".to_string(),
@@ -98,7 +112,7 @@ pub async fn enroll(
         ));
     }
 
-    if exam.started_at_or(now, false) {
+    if timing.started_at_or(now, false) {
         // This check should probably be handled in the authorize function but I'm not sure of
         // the proper action type.
         let can_start =
@@ -111,6 +125,16 @@ pub async fn enroll(
             ));
         }
         exams::enroll(&mut conn, *exam_id, user.id, payload.is_teacher_testing).await?;
+        record_exam_enrollment_event(
+            &mut conn,
+            &req,
+            &ip_to_country_mapper,
+            *exam_id,
+            user.id,
+            ExamEnrollmentEventType::Started,
+            None,
+        )
+        .await?;
         let token = skip_authorize();
         return token.authorized_ok(web::Json(()));
     }
@@ -182,8 +206,10 @@ pub async fn fetch_exam_for_user(
 ) -> ControllerResult<web::Json<ExamData>> {
     let mut conn = pool.acquire().await?;
     let exam = exams::get(&mut conn, *exam_id).await?;
+    // The student's own window and time limit, if they have an accommodation.
+    let timing = exam_accommodations::get_exam_timing_for_user(&mut conn, &exam, user.id).await?;
 
-    let starts_at = if let Some(starts_at) = exam.starts_at {
+    let starts_at = if let Some(starts_at) = timing.starts_at {
         starts_at
     } else {
         return Err(ControllerError::new(
@@ -192,7 +218,7 @@ pub async fn fetch_exam_for_user(
             None,
         ));
     };
-    let ends_at = if let Some(ends_at) = exam.ends_at {
+    let ends_at = if let Some(ends_at) = timing.ends_at {
         ends_at
     } else {
         return Err(ControllerError::new(
@@ -214,7 +240,7 @@ pub async fn fetch_exam_for_user(
             starts_at,
             ends_at,
             ended,
-            time_minutes: exam.time_minutes,
+            time_minutes: timing.time_minutes,
             enrollment_data: ExamEnrollmentData::NotYetStarted,
             language: exam.language,
         }));
@@ -289,7 +315,7 @@ pub async fn fetch_exam_for_user(
                             starts_at,
                             ends_at,
                             ended,
-                            time_minutes: exam.time_minutes,
+                            time_minutes: timing.time_minutes,
                             enrollment_data: ExamEnrollmentData::StudentCanViewGrading {
                                 gradings: grading_decision_and_exercise_list,
                                 enrollment,
@@ -309,7 +335,7 @@ pub async fn fetch_exam_for_user(
                         starts_at,
                         ends_at,
                         ended,
-                        time_minutes: exam.time_minutes,
+                        time_minutes: timing.time_minutes,
                         enrollment_data: ExamEnrollmentData::StudentTimeUp,
                         language: exam.language,
                     }));
@@ -318,8 +344,7 @@ pub async fn fetch_exam_for_user(
 
             // user has started the exam
             if Utc::now() < ends_at
-                && (Utc::now()
-                    > enrollment.started_at + Duration::minutes(exam.time_minutes.into())
+                && (Utc::now() > timing.student_time_ends_at(enrollment.started_at)
                     || enrollment.ended_at.is_some())
             {
                 // exam is still open but the student's time has expired or student has ended their exam
@@ -335,7 +360,7 @@ pub async fn fetch_exam_for_user(
                     starts_at,
                     ends_at,
                     ended,
-                    time_minutes: exam.time_minutes,
+                    time_minutes: timing.time_minutes,
                     enrollment_data: ExamEnrollmentData::StudentTimeUp,
                     language: exam.language,
                 }));
@@ -355,14 +380,19 @@ pub async fn fetch_exam_for_user(
                 starts_at,
                 ends_at,
                 ended,
-                time_minutes: exam.time_minutes,
+                time_minutes: timing.time_minutes,
                 enrollment_data: ExamEnrollmentData::NotEnrolled { can_enroll },
                 language: exam.language,
             }));
         }
     };
 
-    let page = pages::get_page(&mut conn, exam.page_id).await?;
+    let mut page = pages::get_page(&mut conn, exam.page_id).await?;
+    if let Some(exercise_ids) =
+        exam_question_pools::get_exercise_ids_for_user(&mut conn, *exam_id, user.id).await?
+    {
+        exam_question_pools::remove_undrawn_exercise_blocks(&mut page.content, &exercise_ids);
+    }
 
     let token = authorize(&mut conn, Act::View, Some(user.id), Res::Exam(*exam_id)).await?;
     token.authorized_ok(web::Json(ExamData {
@@ -372,7 +402,7 @@ pub async fn fetch_exam_for_user(
         starts_at,
         ends_at,
         ended,
-        time_minutes: exam.time_minutes,
+        time_minutes: timing.time_minutes,
         enrollment_data: ExamEnrollmentData::EnrolledAndStarted {
             page_id: exam.page_id,
             page: Box::new(page),
@@ -442,7 +472,12 @@ pub async fn fetch_exam_for_testing(
         }
     };
 
-    let page = pages::get_page(&mut conn, exam.page_id).await?;
+    let mut page = pages::get_page(&mut conn, exam.page_id).await?;
+    if let Some(exercise_ids) =
+        exam_question_pools::get_exercise_ids_for_user(&mut conn, *exam_id, user.id).await?
+    {
+        exam_question_pools::remove_undrawn_exercise_blocks(&mut page.content, &exercise_ids);
+    }
 
     let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Exam(*exam_id)).await?;
     token.authorized_ok(web::Json(ExamData {
@@ -551,21 +586,137 @@ Used for marking the students exam as ended in the exam enrollment
         (status = 200, description = "Exam end time updated", body = ())
     )
 )]
-#[instrument(skip(pool))]
+#[instrument(skip(pool, req, ip_to_country_mapper))]
 pub async fn end_exam_time(
     pool: web::Data<PgPool>,
     exam_id: web::Path<Uuid>,
     user: AuthUser,
+    req: HttpRequest,
+    ip_to_country_mapper: web::Data<IpToCountryMapper>,
 ) -> ControllerResult<web::Json<()>> {
     let mut conn = pool.acquire().await?;
 
     let ended_at = Utc::now();
     models::exams::update_exam_ended_at(&mut conn, *exam_id, user.id, ended_at).await?;
+    record_exam_enrollment_event(
+        &mut conn,
+        &req,
+        &ip_to_country_mapper,
+        *exam_id,
+        user.id,
+        ExamEnrollmentEventType::Ended,
+        None,
+    )
+    .await?;
+
+    let token = authorize(&mut conn, Act::View, Some(user.id), Res::Exam(*exam_id)).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/**
+POST /api/v0/course-material/exams/:id/events
 
+Records an event reported by the exam page, such as the page being hidden or losing focus. Only
+accepted while the student's exam is ongoing.
+*/
+#[utoipa::path(
+    post,
+    path = "/{id}/events",
+    operation_id = "postExamEnrollmentEvent",
+    tag = "course-material-exams",
+    params(
+        ("id" = Uuid, Path, description = "Exam id")
+    ),
+    request_body = ClientExamEnrollmentEvent,
+    responses(
+        (status = 200, description = "Event recorded", body = ())
+    )
+)]
+#[instrument(skip(pool, req, ip_to_country_mapper))]
+pub async fn post_exam_enrollment_event(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+    payload: web::Json<ClientExamEnrollmentEvent>,
+    req: HttpRequest,
+    ip_to_country_mapper: web::Data<IpToCountryMapper>,
+) -> ControllerResult<web::Json<()>> {
+    let mut conn = pool.acquire().await?;
     let token = authorize(&mut conn, Act::View, Some(user.id), Res::Exam(*exam_id)).await?;
+    if !payload.event_type.can_be_reported_by_client() {
+        return Err(ControllerError::new(
+            ControllerErrorType::BadRequest,
+            "This event cannot be reported by the client.".to_string(),
+            None,
+        ));
+    }
+    let enrollment = exams::get_enrollment(&mut conn, *exam_id, user.id).await?;
+    if enrollment.is_none_or(|e| e.ended_at.is_some()) {
+        return Err(ControllerError::new(
+            ControllerErrorType::BadRequest,
+            "The exam is not ongoing.".to_string(),
+            None,
+        ));
+    }
+    let (ip_address, country_code) = requester_ip_and_country(&req, &ip_to_country_mapper);
+    exam_enrollment_events::insert(
+        &mut conn,
+        &NewExamEnrollmentEvent {
+            exam_id: *exam_id,
+            user_id: user.id,
+            event_type: payload.event_type,
+            client_session_id: Some(payload.client_session_id),
+            ip_address,
+            country_code,
+            exercise_slide_submission_id: None,
+        },
+    )
+    .await?;
     token.authorized_ok(web::Json(()))
 }
 
+/// The IP address the request came from and the country it maps to, if known.
+pub(crate) fn requester_ip_and_country(
+    req: &HttpRequest,
+    ip_to_country_mapper: &IpToCountryMapper,
+) -> (Option<String>, Option<String>) {
+    let ip: Option<IpAddr> = req
+        .connection_info()
+        .realip_remote_addr()
+        .and_then(|ip| ip.parse::<IpAddr>().ok());
+    let country_code = ip
+        .and_then(|ip| ip_to_country_mapper.map_ip_to_country(&ip))
+        .map(|c| c.to_string());
+    (ip.map(|ip| ip.to_string()), country_code)
+}
+
+/// Records an event of the student's exam that the server has observed itself.
+pub(crate) async fn record_exam_enrollment_event(
+    conn: &mut PgConnection,
+    req: &HttpRequest,
+    ip_to_country_mapper: &IpToCountryMapper,
+    exam_id: Uuid,
+    user_id: Uuid,
+    event_type: ExamEnrollmentEventType,
+    exercise_slide_submission_id: Option<Uuid>,
+) -> ControllerResult<()> {
+    let (ip_address, country_code) = requester_ip_and_country(req, ip_to_country_mapper);
+    exam_enrollment_events::insert(
+        conn,
+        &NewExamEnrollmentEvent {
+            exam_id,
+            user_id,
+            event_type,
+            client_session_id: None,
+            ip_address,
+            country_code,
+            exercise_slide_submission_id,
+        },
+    )
+    .await?;
+    Ok(())
+}
+
 /**
 Add a route for each controller in this module.
 
@@ -589,5 +740,6 @@ pub fn _add_routes(cfg: &mut ServiceConfig) {
             "/testexam/{id}/reset-exam-progress",
             web::post().to(reset_exam_progress),
         )
-        .route("/{id}/end-exam-time", web::post().to(end_exam_time));
+        .route("/{id}/end-exam-time", web::post().to(end_exam_time))
+        .route("/{id}/events", web::post().to(post_exam_enrollment_event));
 }
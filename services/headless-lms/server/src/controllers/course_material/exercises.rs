@@ -1,6 +1,7 @@
 //! Controllers for requests starting with `/api/v0/course-material/exercises`.
 
 use crate::{
+    controllers::course_material::exams::record_exam_enrollment_event,
     domain::{
         authorization::skip_authorize,
         exercise_services::submission_files,
@@ -11,7 +12,9 @@ use crate::{
 use headless_lms_models::{
     ModelError, ModelErrorType, flagged_answers::FlaggedAnswer, peer_or_self_review_configs,
 };
+use headless_lms_utils::ip_to_country::IpToCountryMapper;
 use models::{
+    exam_enrollment_events::ExamEnrollmentEventType,
     exercise_task_submissions::PeerOrSelfReviewsReceived,
     exercises::CourseMaterialExercise,
     flagged_answers::NewFlaggedAnswerWithToken,
@@ -19,8 +22,10 @@ use models::{
         grading::{StudentExerciseSlideSubmission, StudentExerciseSlideSubmissionResult},
         peer_or_self_reviewing::{
             CourseMaterialPeerOrSelfReviewData, CourseMaterialPeerOrSelfReviewSubmission,
+            CourseMaterialPeerReviewCalibrationData, CourseMaterialPeerReviewCalibrationSubmission,
         },
     },
+    peer_review_disputes::{NewPeerReviewDispute, PeerReviewDispute},
     user_chapter_locking_statuses, user_exercise_states,
 };
 use utoipa::OpenApi;
@@ -33,7 +38,11 @@ use utoipa::OpenApi;
     post_submission,
     start_peer_or_self_review,
     submit_peer_or_self_review,
-    post_flag_answer_in_peer_review
+    post_flag_answer_in_peer_review,
+    get_peer_review_calibration,
+    submit_peer_review_calibration,
+    get_peer_review_disputes,
+    post_peer_review_dispute
 ))]
 pub(crate) struct CourseMaterialExercisesApiDoc;
 
@@ -279,7 +288,7 @@ Content-Type: application/json
         )
     )
 )]
-#[instrument(skip(pool, file_store, jwt_key))]
+#[instrument(skip(pool, file_store, jwt_key, req, ip_to_country_mapper))]
 async fn post_submission(
     pool: web::Data<PgPool>,
     file_store: web::Data<dyn FileStore>,
@@ -287,6 +296,8 @@ async fn post_submission(
     exercise_id: web::Path<Uuid>,
     payload: web::Json<StudentExerciseSlideSubmission>,
     user: AuthUser,
+    req: HttpRequest,
+    ip_to_country_mapper: web::Data<IpToCountryMapper>,
 ) -> ControllerResult<web::Json<StudentExerciseSlideSubmissionResult>> {
     let submission = payload.0;
     let mut conn = pool.acquire().await?;
@@ -327,6 +338,7 @@ async fn post_submission(
         Res::Exercise(exercise.id),
     )
     .await?;
+    let exam_id = exercise.exam_id;
     let result = submission_files::submit_recording_answer_files(
         &mut conn,
         user.id,
@@ -336,6 +348,24 @@ async fn post_submission(
         file_store.as_ref(),
     )
     .await?;
+    if let Some(exam_id) = exam_id {
+        let exercise_slide_submission_id = result
+            .exercise_task_submission_results
+            .first()
+            .map(|r| r.submission.exercise_slide_submission_id);
+        if exercise_slide_submission_id.is_some() {
+            record_exam_enrollment_event(
+                &mut conn,
+                &req,
+                &ip_to_country_mapper,
+                exam_id,
+                user.id,
+                ExamEnrollmentEventType::Submission,
+                exercise_slide_submission_id,
+            )
+            .await?;
+        }
+    }
     token.authorized_ok(web::Json(result))
 }
 
@@ -633,6 +663,173 @@ async fn post_flag_answer_in_peer_review(
     token.authorized_ok(web::Json(insert_result))
 }
 
+/**
+GET `/api/v0/course-material/exercises/:exercise_id/peer-review-calibration` - Get the next teacher-scored answer the user should review before they can peer review their peers.
+
+Like the peer review, this is only available in the peer review stage because the answer may expose the correct solution to the exercise.
+*/
+#[utoipa::path(
+    get,
+    path = "/{exercise_id}/peer-review-calibration",
+    operation_id = "getPeerReviewCalibration",
+    tag = "course-material-exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (
+            status = 200,
+            description = "Peer review calibration data",
+            body = CourseMaterialPeerReviewCalibrationData
+        )
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_peer_review_calibration(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<CourseMaterialPeerReviewCalibrationData>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::View,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    let exercise = models::exercises::get_non_deleted_by_id(&mut conn, *exercise_id).await?;
+    let user_exercise_state =
+        user_exercise_states::get_users_current_by_exercise(&mut conn, user.id, &exercise).await?;
+    let calibration_data =
+        models::library::peer_or_self_reviewing::get_peer_review_calibration_data_for_user(
+            &mut conn,
+            &exercise,
+            &user_exercise_state,
+            models_requests::fetch_service_info,
+        )
+        .await?;
+    token.authorized_ok(web::Json(calibration_data))
+}
+
+/**
+POST `/api/v0/course-material/exercises/:exercise_id/peer-review-calibration` - Post a review of a teacher-scored answer. The agreement with the teacher's scores updates the user's reliability as a peer reviewer.
+*/
+#[utoipa::path(
+    post,
+    path = "/{exercise_id}/peer-review-calibration",
+    operation_id = "postPeerReviewCalibrationSubmission",
+    tag = "course-material-exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    request_body = CourseMaterialPeerReviewCalibrationSubmission,
+    responses(
+        (status = 200, description = "Peer review calibration submitted", body = bool)
+    )
+)]
+#[instrument(skip(pool))]
+async fn submit_peer_review_calibration(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    payload: web::Json<CourseMaterialPeerReviewCalibrationSubmission>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<bool>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::View,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    let exercise = models::exercises::get_non_deleted_by_id(&mut conn, *exercise_id).await?;
+    let user_exercise_state =
+        user_exercise_states::get_users_current_by_exercise(&mut conn, user.id, &exercise).await?;
+    models::library::peer_or_self_reviewing::create_peer_review_calibration_submission_for_user(
+        &mut conn,
+        &exercise,
+        &user_exercise_state,
+        payload.into_inner(),
+    )
+    .await?;
+    token.authorized_ok(web::Json(true))
+}
+
+/**
+GET `/api/v0/course-material/exercises/:exercise_id/peer-review-disputes` - Get the disputes the user has filed against the peer reviews they have received on the exercise, newest first.
+*/
+#[utoipa::path(
+    get,
+    path = "/{exercise_id}/peer-review-disputes",
+    operation_id = "getPeerReviewDisputes",
+    tag = "course-material-exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Peer review disputes", body = [PeerReviewDispute])
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_peer_review_disputes(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<PeerReviewDispute>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::View,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    let res =
+        models::peer_review_disputes::get_by_user_and_exercise_id(&mut conn, user.id, *exercise_id)
+            .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+POST `/api/v0/course-material/exercises/:exercise_id/peer-review-disputes` - Dispute a peer review the user has received. The teachers of the course are asked to resolve the dispute.
+*/
+#[utoipa::path(
+    post,
+    path = "/{exercise_id}/peer-review-disputes",
+    operation_id = "postPeerReviewDispute",
+    tag = "course-material-exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    request_body = NewPeerReviewDispute,
+    responses(
+        (status = 200, description = "Peer review disputed", body = PeerReviewDispute)
+    )
+)]
+#[instrument(skip(pool))]
+async fn post_peer_review_dispute(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    payload: web::Json<NewPeerReviewDispute>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<PeerReviewDispute>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::View,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    let exercise = models::exercises::get_non_deleted_by_id(&mut conn, *exercise_id).await?;
+    let res = models::library::peer_review_disputes::create_peer_review_dispute_for_user(
+        &mut conn, user.id, &exercise, &payload,
+    )
+    .await?;
+    token.authorized_ok(web::Json(res))
+}
+
 /**
 Add a route for each controller in this module.
 
@@ -664,5 +861,21 @@ pub fn _add_routes(cfg: &mut ServiceConfig) {
         ).route(
             "/{exercise_id}/flag-peer-review-answer",
             web::post().to(post_flag_answer_in_peer_review),
+        )
+        .route(
+            "/{exercise_id}/peer-review-calibration",
+            web::get().to(get_peer_review_calibration),
+        )
+        .route(
+            "/{exercise_id}/peer-review-calibration",
+            web::post().to(submit_peer_review_calibration),
+        )
+        .route(
+            "/{exercise_id}/peer-review-disputes",
+            web::get().to(get_peer_review_disputes),
+        )
+        .route(
+            "/{exercise_id}/peer-review-disputes",
+            web::post().to(post_peer_review_dispute),
         );
 }
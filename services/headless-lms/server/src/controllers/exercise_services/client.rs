@@ -318,7 +318,7 @@ async fn get_course_exercises(
     let open_chapter_ids = open_chapter_ids(&mut conn, *course).await?;
 
     let course = models::courses::get_course(&mut conn, *course).await?;
-    let open_chapter_exercises =
+    let open_chapter_exercises: Vec<_> =
         models::exercises::get_exercises_by_course_id(&mut conn, course.id)
             .await?
             .into_iter()
@@ -326,7 +326,15 @@ async fn get_course_exercises(
                 e.chapter_id
                     .map(|ci| open_chapter_ids.contains(&ci))
                     .unwrap_or_default()
-            });
+            })
+            .collect();
+    let open_exercise_ids: Vec<Uuid> = open_chapter_exercises.iter().map(|e| e.id).collect();
+    let deadlines = models::deadline_exceptions::get_effective_exercise_deadlines(
+        &mut conn,
+        &open_exercise_ids,
+        Some(user.id),
+    )
+    .await?;
     for open_exercise in open_chapter_exercises {
         let (slide, _) = models::exercises::get_or_select_exercise_slide(
             &mut conn,
@@ -345,7 +353,7 @@ async fn get_course_exercises(
                 course_id: course.id,
                 exercise_name: open_exercise.name,
                 exercise_order_number: open_exercise.order_number,
-                deadline: open_exercise.deadline,
+                deadline: deadlines.get(&open_exercise.id).copied().flatten(),
                 tasks,
             });
         }
@@ -546,6 +554,12 @@ async fn get_exercise(
         .unwrap_or(0);
     let reveal_model_solution =
         model_solution_should_be_revealed(&exercise, score_given, slide_submission_count);
+    let deadline = models::deadline_exceptions::get_effective_exercise_deadline(
+        &mut conn,
+        &exercise,
+        Some(user.id),
+    )
+    .await?;
 
     // Without the capability filter this endpoint would hand out task ids belonging to services
     // that cannot serve a native client, which submit would then have to reject.
@@ -568,7 +582,7 @@ async fn get_exercise(
         course_id,
         exercise_name: exercise.name,
         exercise_order_number: exercise.order_number,
-        deadline: exercise.deadline,
+        deadline,
         tasks,
     }))
 }
@@ -1538,6 +1552,9 @@ mod tests {
             use_course_default_peer_or_self_review_config: false,
             exercise_language_group_id: None,
             teacher_reviews_answer_after_locking: false,
+            scoring_strategy: models::exercises::ExerciseScoringStrategy::BestAttempt,
+            scoring_strategy_attempt_count: None,
+            scoring_strategy_penalty_percent: None,
         }
     }
 
@@ -1860,7 +1877,8 @@ mod upload_tests {
     use crate::test_helper::*;
     use actix_web::http::header::{CONTENT_TYPE, HeaderMap};
     use headless_lms_base::config::{
-        ApplicationConfiguration, OAuthServerConfiguration, SuotarConfiguration,
+        ApplicationConfiguration, CertificateCredentialConfiguration, OAuthServerConfiguration,
+        SuotarConfiguration,
     };
     use headless_lms_utils::prelude::UtilResult;
     use models::exercise_slide_submissions::NewExerciseSlideSubmission;
@@ -1876,9 +1894,12 @@ mod upload_tests {
             test_chatbot: false,
             test_sisu: false,
             test_suotar: false,
+            test_lti: false,
+            test_lrs: false,
             disable_embedding_vector_creation_when_seeding: false,
             suotar_configuration: SuotarConfiguration::mock_conf("http://project-331.local")
                 .expect("Failed to build the mock Suotar configuration"),
+            xapi_configuration: None,
             development_uuid_login: false,
             enable_admin_email_verification: false,
             enable_email_ownership_verification: false,
@@ -1893,6 +1914,10 @@ mod upload_tests {
                     "unused".into(),
                 ))),
             },
+            certificate_credential_configuration: CertificateCredentialConfiguration {
+                rsa_public_key: "unused".into(),
+                rsa_private_key: SecretString::new("unused".into()),
+            },
         }
     }
 
@@ -569,6 +569,32 @@ pub async fn upload_certificate_svg(
     Ok((id, safe_path))
 }
 
+/// Uploads a file extracted from an imported package to the files of the course. The caller is
+/// responsible for deleting the file from storage if the transaction it was recorded in is rolled
+/// back.
+pub async fn upload_file_from_package(
+    conn: &mut PgConnection,
+    file_name: &str,
+    mime_type: &str,
+    contents: Vec<u8>,
+    file_store: &dyn FileStore,
+    course_id: Uuid,
+    uploader: AuthUser,
+) -> Result<(Uuid, PathBuf), ControllerError> {
+    let file_type = if mime_type.starts_with("image/") {
+        FileType::Image
+    } else {
+        FileType::File
+    };
+    let path = make_filename_safe(&path(file_name, file_type, StoreKind::Course(course_id)));
+    let path_string = path.to_str().context("invalid path")?.to_string();
+    let id =
+        models::file_uploads::insert(conn, file_name, &path_string, mime_type, Some(uploader.id))
+            .await?;
+    file_store.upload(&path, contents, mime_type).await?;
+    Ok((id, path))
+}
+
 async fn upload_file_to_storage(
     conn: &mut PgConnection,
     path: &Path,
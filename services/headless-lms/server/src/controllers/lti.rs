@@ -0,0 +1,396 @@
+/*!
+Handlers for HTTP requests to `/api/v0/lti`.
+
+These are the LTI 1.3 endpoints the platforms call, see [`crate::domain::lti`]. The endpoints our own frontend
+uses for Deep Linking are in [`crate::controllers::main_frontend::lti`].
+*/
+
+use actix_session::Session;
+use actix_web::{
+    HttpRequest,
+    cookie::{Cookie, SameSite},
+    http::header,
+};
+use headless_lms_utils::strings::generate_random_string;
+use models::{
+    lti_deep_linking_requests::NewLtiDeepLinkingRequest, lti_platforms::LtiPlatform,
+    lti_resource_links::NewLtiResourceLink,
+};
+use url::Url;
+
+use crate::{
+    domain::{
+        authorization,
+        lti::{
+            self, DEEP_LINKING_REQUEST, RESOURCE_LINK_REQUEST,
+            claims::{LaunchClaims, LaunchTarget},
+        },
+    },
+    prelude::*,
+};
+
+/// How long the platform has to complete the login with a launch.
+const LOGIN_STATE_LIFETIME_MINUTES: i64 = 10;
+
+/// Binds the login to the browser that started it, so that a launch started elsewhere can't log the
+/// browser in as another user.
+const LOGIN_STATE_COOKIE: &str = "lti_login_state";
+
+/// How long a teacher has to pick the content in Deep Linking.
+const DEEP_LINKING_LIFETIME_MINUTES: i64 = 60;
+
+/// The parameters of a third-party-initiated login.
+///
+/// See <https://www.imsglobal.org/spec/security/v1p0#step-1-third-party-initiated-login>.
+#[derive(Debug, Deserialize)]
+pub struct LoginInitiation {
+    iss: String,
+    login_hint: String,
+    target_link_uri: String,
+    lti_message_hint: Option<String>,
+    client_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LaunchForm {
+    id_token: String,
+    state: String,
+}
+
+/**
+GET or POST `/api/v0/lti/login` - Starts an LTI launch. Redirects the browser to the authorization endpoint
+of the platform with a fresh state and nonce, and stores the state in a cookie the launch must present.
+*/
+#[instrument(skip(pool, app_conf))]
+async fn login_get(
+    pool: web::Data<PgPool>,
+    query: web::Query<LoginInitiation>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    initiate_login(pool, query.into_inner(), &app_conf).await
+}
+
+#[instrument(skip(pool, app_conf))]
+async fn login_post(
+    pool: web::Data<PgPool>,
+    form: web::Form<LoginInitiation>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    initiate_login(pool, form.into_inner(), &app_conf).await
+}
+
+async fn initiate_login(
+    pool: web::Data<PgPool>,
+    login: LoginInitiation,
+    app_conf: &ApplicationConfiguration,
+) -> ControllerResult<HttpResponse> {
+    let mut conn = pool.acquire().await?;
+    let platform = models::lti_platforms::find_by_issuer_and_client_id(
+        &mut conn,
+        &login.iss,
+        login.client_id.as_deref(),
+    )
+    .await?
+    .ok_or_else(|| {
+        controller_err!(
+            NotFound,
+            format!("The platform '{}' is not registered.", login.iss)
+        )
+    })?;
+    if !login
+        .target_link_uri
+        .starts_with(app_conf.base_url.trim_end_matches('/'))
+    {
+        return Err(controller_err!(
+            BadRequest,
+            "The target link does not point to this service.".to_string()
+        ));
+    }
+    let state = generate_random_string(32);
+    let nonce = generate_random_string(32);
+    models::lti_login_states::insert(
+        &mut conn,
+        platform.id,
+        &state,
+        &nonce,
+        &login.target_link_uri,
+        Utc::now() + chrono::Duration::minutes(LOGIN_STATE_LIFETIME_MINUTES),
+    )
+    .await?;
+
+    let mut redirect = Url::parse(&platform.auth_login_url).map_err(|err| {
+        controller_err!(
+            InternalServerError,
+            "The authorization endpoint of the platform is not a valid URL.".to_string(),
+            anyhow::Error::from(err)
+        )
+    })?;
+    {
+        let mut query = redirect.query_pairs_mut();
+        query
+            .append_pair("scope", "openid")
+            .append_pair("response_type", "id_token")
+            .append_pair("response_mode", "form_post")
+            .append_pair("prompt", "none")
+            .append_pair("client_id", &platform.client_id)
+            .append_pair("redirect_uri", &lti::launch_url(&app_conf.base_url))
+            .append_pair("login_hint", &login.login_hint)
+            .append_pair("state", &state)
+            .append_pair("nonce", &nonce);
+        if let Some(hint) = &login.lti_message_hint {
+            query.append_pair("lti_message_hint", hint);
+        }
+    }
+    let token = skip_authorize();
+    token.authorized_ok(
+        HttpResponse::Found()
+            .insert_header((header::LOCATION, redirect.to_string()))
+            .cookie(login_state_cookie(state))
+            .finish(),
+    )
+}
+
+/// The launch is a cross-site POST from the platform, so the cookie must be `SameSite=None`.
+fn login_state_cookie(state: String) -> Cookie<'static> {
+    Cookie::build(LOGIN_STATE_COOKIE, state)
+        .path("/api/v0/lti")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::None)
+        .max_age(actix_web::cookie::time::Duration::minutes(
+            LOGIN_STATE_LIFETIME_MINUTES,
+        ))
+        .finish()
+}
+
+/**
+POST `/api/v0/lti/launch` - Completes an LTI launch. Validates the ID token the platform posted, logs the user
+in and takes them to the linked content, or to the content selection in a Deep Linking launch.
+*/
+#[instrument(skip(pool, req, session, form, app_conf))]
+async fn launch(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    session: Session,
+    form: web::Form<LaunchForm>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    if req
+        .cookie(LOGIN_STATE_COOKIE)
+        .is_none_or(|cookie| cookie.value() != form.state)
+    {
+        return Err(controller_err!(
+            Forbidden,
+            "The launch was not started from this browser.".to_string()
+        ));
+    }
+    let mut conn = pool.acquire().await?;
+    // Consumed before validating so that a rejected token cannot be retried with the same state.
+    let login_state = models::lti_login_states::consume(&mut conn, &form.state).await?;
+    let platform = models::lti_platforms::get_by_id(&mut conn, login_state.lti_platform_id).await?;
+    let claims = lti::launch::validate_id_token(&form.id_token, &platform, &login_state).await?;
+
+    let mut tx = conn.begin().await?;
+    let lti_user = models::lti_users::get_or_create(
+        &mut tx,
+        platform.id,
+        &claims.sub,
+        claims.given_name.as_deref(),
+        claims.family_name.as_deref(),
+    )
+    .await?;
+    let redirect_to = match claims.message_type.as_str() {
+        RESOURCE_LINK_REQUEST => {
+            let target = claims.launch_target()?;
+            let path = content_path(&mut tx, &platform, &target).await?;
+            let link = models::lti_resource_links::upsert(
+                &mut tx,
+                &resource_link_from_claims(&platform, &claims, &target)?,
+            )
+            .await?;
+            models::lti_resource_link_users::upsert(
+                &mut tx,
+                link.id,
+                lti_user.user_id,
+                lti_user.id,
+            )
+            .await?;
+            format!("{}{}", app_conf.base_url.trim_end_matches('/'), path)
+        }
+        DEEP_LINKING_REQUEST => {
+            if !claims.is_instructor() {
+                return Err(controller_err!(
+                    Forbidden,
+                    "Only teachers can pick content to add to a course.".to_string()
+                ));
+            }
+            let settings = claims.deep_linking_settings.as_ref().ok_or_else(|| {
+                controller_err!(
+                    BadRequest,
+                    "The deep linking settings are missing.".to_string()
+                )
+            })?;
+            let request = models::lti_deep_linking_requests::insert(
+                &mut tx,
+                &NewLtiDeepLinkingRequest {
+                    lti_platform_id: platform.id,
+                    deployment_id: claims.deployment_id.clone(),
+                    user_id: lti_user.user_id,
+                    deep_link_return_url: settings.deep_link_return_url.clone(),
+                    data: settings.data.clone(),
+                    accept_multiple: settings.accept_multiple,
+                    expires_at: Utc::now()
+                        + chrono::Duration::minutes(DEEP_LINKING_LIFETIME_MINUTES),
+                },
+            )
+            .await?;
+            format!(
+                "{}/lti/deep-linking/{}",
+                app_conf.base_url.trim_end_matches('/'),
+                request.id
+            )
+        }
+        other => {
+            return Err(controller_err!(
+                BadRequest,
+                format!("The message type '{other}' is not supported.")
+            ));
+        }
+    };
+    let user = models::users::get_by_id(&mut tx, lti_user.user_id).await?;
+    tx.commit().await?;
+    authorization::remember(&session, user)?;
+
+    let mut response = redirect_page(&redirect_to);
+    response
+        .add_removal_cookie(&login_state_cookie(String::new()))
+        .map_err(anyhow::Error::from)?;
+    let token = skip_authorize();
+    token.authorized_ok(response)
+}
+
+/// The path of the course material page the target is on. Fails if the target is not a published
+/// course of the organization the platform belongs to.
+async fn content_path(
+    conn: &mut PgConnection,
+    platform: &LtiPlatform,
+    target: &LaunchTarget,
+) -> ControllerResult<String> {
+    let course = models::courses::get_course(conn, target.course_id).await?;
+    if course.organization_id != platform.organization_id || course.is_draft {
+        return Err(controller_err!(
+            Forbidden,
+            "The platform is not allowed to open this course.".to_string()
+        ));
+    }
+    let organization =
+        models::organizations::get_organization(conn, course.organization_id).await?;
+    let course_path = format!("/org/{}/courses/{}", organization.slug, course.slug);
+
+    let page_id = if let Some(exercise_id) = target.exercise_id {
+        let exercise = models::exercises::get_by_id(conn, exercise_id).await?;
+        if exercise.course_id != Some(course.id) {
+            return Err(controller_err!(
+                BadRequest,
+                "The exercise is not in the course.".to_string()
+            ));
+        }
+        Some(exercise.page_id)
+    } else if let Some(chapter_id) = target.chapter_id {
+        let chapter = models::chapters::get_chapter(conn, chapter_id).await?;
+        if chapter.course_id != course.id {
+            return Err(controller_err!(
+                BadRequest,
+                "The chapter is not in the course.".to_string()
+            ));
+        }
+        chapter.front_page_id
+    } else {
+        None
+    };
+    match page_id {
+        Some(page_id) => {
+            let page = models::pages::get_page(conn, page_id).await?;
+            Ok(format!("{}{}", course_path, page.url_path))
+        }
+        None => Ok(course_path),
+    }
+}
+
+fn resource_link_from_claims(
+    platform: &LtiPlatform,
+    claims: &LaunchClaims,
+    target: &LaunchTarget,
+) -> ControllerResult<NewLtiResourceLink> {
+    let resource_link = claims
+        .resource_link
+        .as_ref()
+        .ok_or_else(|| controller_err!(BadRequest, "The resource link is missing.".to_string()))?;
+    Ok(NewLtiResourceLink {
+        lti_platform_id: platform.id,
+        deployment_id: claims.deployment_id.clone(),
+        resource_link_id: resource_link.id.clone(),
+        context_id: claims.context.as_ref().map(|c| c.id.clone()),
+        context_title: claims.context.as_ref().and_then(|c| c.title.clone()),
+        course_id: target.course_id,
+        chapter_id: target.chapter_id,
+        exercise_id: target.exercise_id,
+        ags_lineitem_url: claims
+            .ags_endpoint
+            .as_ref()
+            .filter(|ags| ags.scope.iter().any(|scope| scope == lti::AGS_SCOPE_SCORE))
+            .and_then(|ags| ags.lineitem.clone()),
+        nrps_context_memberships_url: claims
+            .nrps
+            .as_ref()
+            .map(|nrps| nrps.context_memberships_url.clone()),
+    })
+}
+
+/// The launch is a cross-site POST, so the browser would not send our `SameSite=Strict` session cookie
+/// if we redirected right away. Navigating from a page of our own makes the next request same-site.
+fn redirect_page(url: &str) -> HttpResponse {
+    let url = escape_html(url);
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <meta http-equiv="refresh" content="0; url={url}">
+  </head>
+  <body>
+    <a href="{url}">Continue</a>
+  </body>
+</html>
+"#
+        ))
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+pub fn _add_routes(cfg: &mut ServiceConfig) {
+    cfg.route("/login", web::get().to(login_get))
+        .route("/login", web::post().to(login_post))
+        .route("/launch", web::post().to(launch));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn redirect_page_url_is_escaped() {
+        assert_eq!(
+            escape_html("https://example.com/a?b=1&c=\"><script>"),
+            "https://example.com/a?b=1&amp;c=&quot;&gt;&lt;script&gt;"
+        );
+    }
+}
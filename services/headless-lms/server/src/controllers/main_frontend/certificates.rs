@@ -1,4 +1,14 @@
-use crate::{controllers::helpers::file_uploading, prelude::*};
+use crate::{
+    controllers::helpers::file_uploading,
+    domain::{
+        authorization::AuthorizationToken,
+        certificate_credentials::{
+            self, CertificateCredentialVerification, CertificateCredentialVerificationRequest,
+            SignedCertificateCredential,
+        },
+    },
+    prelude::*,
+};
 use actix_multipart::form::{MultipartForm, tempfile::TempFile};
 use chrono::Utc;
 use headless_lms_certificates as certificates;
@@ -10,7 +20,10 @@ use models::{
     certificate_configurations::{
         CertificateTextAnchor, DatabaseCertificateConfiguration, PaperSize,
     },
-    generated_certificates::GeneratedCertificate,
+    certificate_text_elements::NewCertificateTextElement,
+    generated_certificates::{
+        CertificateStatus, CertificateVerificationStatus, GeneratedCertificate,
+    },
 };
 
 #[derive(OpenApi)]
@@ -20,7 +33,15 @@ use models::{
     get_generated_certificate,
     update_generated_certificate,
     delete_certificate_configuration,
-    get_cerficate_by_verification_id
+    get_cerficate_by_verification_id,
+    get_certificate_pdf_by_verification_id,
+    revoke_generated_certificate,
+    reissue_generated_certificate,
+    get_generated_certificate_history,
+    get_certificate_verification_status,
+    get_generated_certificate_credential,
+    verify_certificate_credential,
+    get_certificate_credential_jwks
 ))]
 pub(crate) struct MainFrontendCertificatesApiDoc;
 
@@ -55,6 +76,15 @@ pub struct CertificateConfigurationUpdate {
     pub certificate_grade_font_size: Option<String>,
     pub certificate_grade_text_color: Option<String>,
     pub certificate_grade_text_anchor: Option<CertificateTextAnchor>,
+    #[serde(default)]
+    pub pdf_enabled: bool,
+    #[serde(default)]
+    pub pdf_transcript_pages_enabled: bool,
+    /// Replaces the text elements of the configuration if given. Placeholders like `{name}` in the
+    /// texts are filled in when rendering, see `headless_lms_certificates::templates::PLACEHOLDERS`.
+    /// If the configuration has no text elements, the fixed text fields above are rendered instead.
+    #[serde(default)]
+    pub text_elements: Option<Vec<NewCertificateTextElement>>,
 }
 
 #[derive(Debug, MultipartForm)]
@@ -322,11 +352,14 @@ async fn update_certificate_configuration_inner(
         certificate_grade_font_size: metadata.certificate_grade_font_size,
         certificate_grade_text_color: metadata.certificate_grade_text_color,
         certificate_grade_text_anchor: metadata.certificate_grade_text_anchor,
+        pdf_enabled: metadata.pdf_enabled,
+        pdf_transcript_pages_enabled: metadata.pdf_transcript_pages_enabled,
     };
-    if let Some(existing_configuration) = existing_configuration {
+    let configuration_id = if let Some(existing_configuration) = existing_configuration {
         // update existing config
         models::certificate_configurations::update(&mut tx, existing_configuration.id, &conf)
             .await?;
+        existing_configuration.id
     } else {
         let inserted_configuration =
             models::certificate_configurations::insert(&mut tx, &conf).await?;
@@ -336,6 +369,11 @@ async fn update_certificate_configuration_inner(
             Some(metadata.course_module_id),
         )
         .await?;
+        inserted_configuration.id
+    };
+    if let Some(text_elements) = &metadata.text_elements {
+        models::certificate_text_elements::replace_all(&mut tx, configuration_id, text_elements)
+            .await?;
     }
     tx.commit().await?;
     Ok(files_to_delete)
@@ -496,6 +534,11 @@ pub async fn get_cerficate_by_verification_id(
                 certificate_configuration_id: test_certificate_configuration_id,
                 name_on_certificate: "Example user".to_string(),
                 verification_id: "test".to_string(),
+                revoked_at: None,
+                revocation_reason: None,
+                revoked_by_user_id: None,
+                superseded_by_id: None,
+                version: 1,
             }
         } else {
             models::generated_certificates::get_certificate_by_verification_id(
@@ -523,6 +566,77 @@ pub async fn get_cerficate_by_verification_id(
     )
 }
 
+/**
+GET `/api/v0/main-frontend/certificates/{certificate_verification_id}/pdf`
+
+Fetches the user's certificate as a pdf using the verification id. Only available if the certificate configuration has pdf output enabled.
+
+Response: the certificate as a pdf.
+*/
+#[utoipa::path(
+    get,
+    path = "/{certificate_verification_id}/pdf",
+    operation_id = "getCertificatePdfByVerificationId",
+    tag = "certificates",
+    params(
+        ("certificate_verification_id" = String, Path, description = "Certificate verification id")
+    ),
+    responses(
+        (status = 200, description = "Certificate pdf", content_type = "application/pdf", body = serde_json::Value)
+    )
+)]
+#[instrument(skip(pool, file_store))]
+pub async fn get_certificate_pdf_by_verification_id(
+    certificate_verification_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+    file_store: web::Data<dyn FileStore>,
+    icu4x_blob: web::Data<Icu4xBlob>,
+) -> ControllerResult<HttpResponse> {
+    let mut conn = pool.acquire().await?;
+
+    // everyone needs to be able to view the certificate in order to verify its validity
+    let token = skip_authorize();
+
+    let certificate = models::generated_certificates::get_certificate_by_verification_id(
+        &mut conn,
+        &certificate_verification_id,
+    )
+    .await?;
+    let config = models::certificate_configurations::get_by_id(
+        &mut conn,
+        certificate.certificate_configuration_id,
+    )
+    .await?;
+    if !config.pdf_enabled {
+        return Err(controller_err!(
+            NotFound,
+            "This certificate is not available as a pdf".to_string()
+        ));
+    }
+
+    let data = certificates::generate_certificate_pdf(
+        &mut conn,
+        file_store.as_ref(),
+        &certificate,
+        **icu4x_blob,
+    )
+    .await?;
+
+    token.authorized_ok(
+        HttpResponse::Ok()
+            .content_type("application/pdf")
+            .insert_header(("Cache-Control", "max-age=300"))
+            .insert_header((
+                "Content-Disposition",
+                format!(
+                    "inline; filename=\"certificate-{}.pdf\"",
+                    certificate.verification_id
+                ),
+            ))
+            .body(data),
+    )
+}
+
 /**
 DELETE `/api/v0/main-frontend/certificates/configuration/{configuration_id}`
 
@@ -579,43 +693,32 @@ pub async fn delete_certificate_configuration(
     token.authorized_ok(web::Json(true))
 }
 
-#[utoipa::path(
-    put,
-    path = "/generated/{certificate_id}",
-    operation_id = "updateGeneratedCertificate",
-    tag = "certificates",
-    params(
-        ("certificate_id" = Uuid, Path, description = "Generated certificate id")
-    ),
-    request_body = CertificateUpdateRequest,
-    responses(
-        (status = 200, description = "Generated certificate updated", body = GeneratedCertificate)
-    )
-)]
-#[instrument(skip(pool))]
-pub async fn update_generated_certificate(
-    certificate_id: web::Path<Uuid>,
-    payload: web::Json<CertificateUpdateRequest>,
-    pool: web::Data<PgPool>,
-    user: AuthUser,
-) -> ControllerResult<web::Json<GeneratedCertificate>> {
-    let mut conn = pool.acquire().await?;
-
-    let cert = models::generated_certificates::get_by_id(&mut conn, *certificate_id).await?;
-
+/// Teachers of the courses the certificate's configuration requires can manage the certificate.
+async fn authorize_certificate_management(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    certificate: &GeneratedCertificate,
+) -> Result<AuthorizationToken, ControllerError> {
     // find course_id for authorization
     let req = models::certificate_configuration_to_requirements::get_all_requirements_for_certificate_configuration(
-        &mut conn,
-        cert.certificate_configuration_id,
+        &mut *conn,
+        certificate.certificate_configuration_id,
     ).await?;
 
     let mut token = None;
     if req.course_module_ids.is_empty() {
-        token =
-            Some(authorize(&mut conn, Act::Teach, Some(user.id), Res::GlobalPermissions).await?);
+        token = Some(
+            authorize(
+                &mut *conn,
+                Act::Teach,
+                Some(user_id),
+                Res::GlobalPermissions,
+            )
+            .await?,
+        );
     } else {
         let course_modules =
-            models::course_modules::get_by_ids(&mut conn, &req.course_module_ids).await?;
+            models::course_modules::get_by_ids(&mut *conn, &req.course_module_ids).await?;
         if course_modules.len() != req.course_module_ids.len() {
             return Err(controller_err!(
                 BadRequest,
@@ -625,17 +728,49 @@ pub async fn update_generated_certificate(
 
         for course_id in course_modules.iter().map(|module| module.course_id) {
             token = Some(
-                authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(course_id)).await?,
+                authorize(
+                    &mut *conn,
+                    Act::Teach,
+                    Some(user_id),
+                    Res::Course(course_id),
+                )
+                .await?,
             );
         }
     }
 
-    let token = token.ok_or_else(|| {
+    token.ok_or_else(|| {
         controller_err!(
             InternalServerError,
             "Authorization token was not set".to_string()
         )
-    })?;
+    })
+}
+
+#[utoipa::path(
+    put,
+    path = "/generated/{certificate_id}",
+    operation_id = "updateGeneratedCertificate",
+    tag = "certificates",
+    params(
+        ("certificate_id" = Uuid, Path, description = "Generated certificate id")
+    ),
+    request_body = CertificateUpdateRequest,
+    responses(
+        (status = 200, description = "Generated certificate updated", body = GeneratedCertificate)
+    )
+)]
+#[instrument(skip(pool))]
+pub async fn update_generated_certificate(
+    certificate_id: web::Path<Uuid>,
+    payload: web::Json<CertificateUpdateRequest>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<GeneratedCertificate>> {
+    let mut conn = pool.acquire().await?;
+
+    let cert = models::generated_certificates::get_by_id(&mut conn, *certificate_id).await?;
+    let token = authorize_certificate_management(&mut conn, user.id, &cert).await?;
 
     let updated = models::generated_certificates::update_certificate(
         &mut conn,
@@ -648,6 +783,263 @@ pub async fn update_generated_certificate(
     token.authorized_ok(web::Json(updated))
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CertificateRevocationRequest {
+    pub reason: String,
+}
+
+/**
+POST `/api/v0/main-frontend/certificates/generated/{certificate_id}/revoke`
+
+Revokes the certificate, for example when the completion it was issued for has been annulled. The certificate stays verifiable but is shown as revoked.
+*/
+#[utoipa::path(
+    post,
+    path = "/generated/{certificate_id}/revoke",
+    operation_id = "revokeGeneratedCertificate",
+    tag = "certificates",
+    params(
+        ("certificate_id" = Uuid, Path, description = "Generated certificate id")
+    ),
+    request_body = CertificateRevocationRequest,
+    responses(
+        (status = 200, description = "Revoked certificate", body = GeneratedCertificate)
+    )
+)]
+#[instrument(skip(pool))]
+pub async fn revoke_generated_certificate(
+    certificate_id: web::Path<Uuid>,
+    payload: web::Json<CertificateRevocationRequest>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<GeneratedCertificate>> {
+    let mut conn = pool.acquire().await?;
+    let certificate = models::generated_certificates::get_by_id(&mut conn, *certificate_id).await?;
+    let token = authorize_certificate_management(&mut conn, user.id, &certificate).await?;
+    let revoked = models::generated_certificates::revoke(
+        &mut conn,
+        *certificate_id,
+        &payload.reason,
+        Some(user.id),
+    )
+    .await?;
+    token.authorized_ok(web::Json(revoked))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CertificateReissueRequest {
+    /// Keeps the current name if not given.
+    pub name_on_certificate: Option<String>,
+}
+
+/**
+POST `/api/v0/main-frontend/certificates/generated/{certificate_id}/reissue`
+
+Issues a new version of the certificate with a new verification id. The old version is shown as superseded when verified.
+*/
+#[utoipa::path(
+    post,
+    path = "/generated/{certificate_id}/reissue",
+    operation_id = "reissueGeneratedCertificate",
+    tag = "certificates",
+    params(
+        ("certificate_id" = Uuid, Path, description = "Generated certificate id")
+    ),
+    request_body = CertificateReissueRequest,
+    responses(
+        (status = 200, description = "The new version of the certificate", body = GeneratedCertificate)
+    )
+)]
+#[instrument(skip(pool))]
+pub async fn reissue_generated_certificate(
+    certificate_id: web::Path<Uuid>,
+    payload: web::Json<CertificateReissueRequest>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<GeneratedCertificate>> {
+    let mut conn = pool.acquire().await?;
+    let certificate = models::generated_certificates::get_by_id(&mut conn, *certificate_id).await?;
+    let token = authorize_certificate_management(&mut conn, user.id, &certificate).await?;
+    let reissued = models::generated_certificates::reissue(
+        &mut conn,
+        *certificate_id,
+        payload.name_on_certificate.as_deref(),
+    )
+    .await?;
+    token.authorized_ok(web::Json(reissued))
+}
+
+/**
+GET `/api/v0/main-frontend/certificates/generated/{certificate_id}/history`
+
+Lists every version of the certificate, including the revoked and superseded ones, oldest first.
+*/
+#[utoipa::path(
+    get,
+    path = "/generated/{certificate_id}/history",
+    operation_id = "getGeneratedCertificateHistory",
+    tag = "certificates",
+    params(
+        ("certificate_id" = Uuid, Path, description = "Generated certificate id")
+    ),
+    responses(
+        (status = 200, description = "Certificate versions", body = [GeneratedCertificate])
+    )
+)]
+#[instrument(skip(pool))]
+pub async fn get_generated_certificate_history(
+    certificate_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<GeneratedCertificate>>> {
+    let mut conn = pool.acquire().await?;
+    let certificate = models::generated_certificates::get_by_id(&mut conn, *certificate_id).await?;
+    let token = authorize_certificate_management(&mut conn, user.id, &certificate).await?;
+    let history =
+        models::generated_certificates::get_version_history(&mut conn, *certificate_id).await?;
+    token.authorized_ok(web::Json(history))
+}
+
+/**
+GET `/api/v0/main-frontend/certificates/{certificate_verification_id}/status`
+
+Tells whether the certificate is valid, revoked or superseded by a newer version. Public, so that anyone can verify a certificate.
+*/
+#[utoipa::path(
+    get,
+    path = "/{certificate_verification_id}/status",
+    operation_id = "getCertificateVerificationStatus",
+    tag = "certificates",
+    params(
+        ("certificate_verification_id" = String, Path, description = "Certificate verification id")
+    ),
+    responses(
+        (status = 200, description = "Certificate status", body = CertificateVerificationStatus)
+    )
+)]
+#[instrument(skip(pool))]
+pub async fn get_certificate_verification_status(
+    certificate_verification_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> ControllerResult<web::Json<CertificateVerificationStatus>> {
+    let mut conn = pool.acquire().await?;
+    // everyone needs to be able to verify a certificate
+    let token = skip_authorize();
+    let status = models::generated_certificates::get_verification_status(
+        &mut conn,
+        &certificate_verification_id,
+    )
+    .await?;
+    token.authorized_ok(web::Json(status))
+}
+
+/**
+GET `/api/v0/main-frontend/certificates/generated/{certificate_id}/credential`
+
+Issues the holder's certificate as a signed Open Badges 3.0 credential that can be imported into a digital wallet.
+*/
+#[utoipa::path(
+    get,
+    path = "/generated/{certificate_id}/credential",
+    operation_id = "getGeneratedCertificateCredential",
+    tag = "certificates",
+    params(
+        ("certificate_id" = Uuid, Path, description = "Generated certificate id")
+    ),
+    responses(
+        (status = 200, description = "Signed certificate credential", body = SignedCertificateCredential)
+    )
+)]
+#[instrument(skip(pool, app_conf))]
+pub async fn get_generated_certificate_credential(
+    certificate_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    app_conf: web::Data<ApplicationConfiguration>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<SignedCertificateCredential>> {
+    let mut conn = pool.acquire().await?;
+    let certificate = models::generated_certificates::get_by_id(&mut conn, *certificate_id).await?;
+    if certificate.user_id != user.id {
+        return Err(controller_err!(
+            Forbidden,
+            "Only the holder can download the certificate credential.".to_string()
+        ));
+    }
+    if certificate.status() != CertificateStatus::Valid {
+        return Err(controller_err!(
+            BadRequest,
+            "A revoked or superseded certificate cannot be downloaded as a credential.".to_string()
+        ));
+    }
+    // The holder was checked above.
+    let token = skip_authorize();
+    let details =
+        models::generated_certificates::get_credential_details(&mut conn, &certificate).await?;
+    let credential =
+        certificate_credentials::build_credential(&app_conf.base_url, &certificate, &details);
+    let jwt = certificate_credentials::sign_credential(
+        &credential,
+        &app_conf.certificate_credential_configuration,
+    )?;
+    token.authorized_ok(web::Json(SignedCertificateCredential { credential, jwt }))
+}
+
+/**
+POST `/api/v0/main-frontend/certificates/credentials/verify`
+
+Checks that a certificate credential was signed by us and that the certificate has not been revoked. Public, so that anyone the holder shares the credential with can verify it.
+*/
+#[utoipa::path(
+    post,
+    path = "/credentials/verify",
+    operation_id = "verifyCertificateCredential",
+    tag = "certificates",
+    request_body = CertificateCredentialVerificationRequest,
+    responses(
+        (status = 200, description = "Verification result", body = CertificateCredentialVerification)
+    )
+)]
+#[instrument(skip(pool, app_conf, payload))]
+pub async fn verify_certificate_credential(
+    payload: web::Json<CertificateCredentialVerificationRequest>,
+    pool: web::Data<PgPool>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<web::Json<CertificateCredentialVerification>> {
+    let mut conn = pool.acquire().await?;
+    // everyone needs to be able to verify credentials
+    let token = skip_authorize();
+    let res = certificate_credentials::verify(
+        &mut conn,
+        &payload.jwt,
+        &app_conf.certificate_credential_configuration,
+    )
+    .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+GET `/api/v0/main-frontend/certificates/credentials/jwks.json`
+
+The public key that certificate credentials are signed with, as a JSON Web Key Set.
+*/
+#[utoipa::path(
+    get,
+    path = "/credentials/jwks.json",
+    operation_id = "getCertificateCredentialJwks",
+    tag = "certificates",
+    responses(
+        (status = 200, description = "Certificate credential JSON Web Key Set", body = serde_json::Value)
+    )
+)]
+#[instrument(skip(app_conf))]
+pub async fn get_certificate_credential_jwks(
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    let token = skip_authorize();
+    let jwks = certificate_credentials::jwks(&app_conf.certificate_credential_configuration)?;
+    token.authorized_ok(HttpResponse::Ok().json(jwks))
+}
+
 /**
 Add a route for each controller in this module.
 
@@ -666,10 +1058,42 @@ pub fn _add_routes(cfg: &mut ServiceConfig) {
             "/generated/{certificate_id}",
             web::put().to(update_generated_certificate),
         )
+        .route(
+            "/generated/{certificate_id}/revoke",
+            web::post().to(revoke_generated_certificate),
+        )
+        .route(
+            "/generated/{certificate_id}/reissue",
+            web::post().to(reissue_generated_certificate),
+        )
+        .route(
+            "/generated/{certificate_id}/history",
+            web::get().to(get_generated_certificate_history),
+        )
+        .route(
+            "/{certificate_verification_id}/status",
+            web::get().to(get_certificate_verification_status),
+        )
+        .route(
+            "/generated/{certificate_id}/credential",
+            web::get().to(get_generated_certificate_credential),
+        )
+        .route(
+            "/credentials/verify",
+            web::post().to(verify_certificate_credential),
+        )
+        .route(
+            "/credentials/jwks.json",
+            web::get().to(get_certificate_credential_jwks),
+        )
         .route(
             "/{certificate_verification_id}",
             web::get().to(get_cerficate_by_verification_id),
         )
+        .route(
+            "/{certificate_verification_id}/pdf",
+            web::get().to(get_certificate_pdf_by_verification_id),
+        )
         .route(
             "/configuration/{certificate_configuration_id}",
             web::delete().to(delete_certificate_configuration),
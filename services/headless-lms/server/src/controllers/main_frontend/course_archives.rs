@@ -0,0 +1,289 @@
+//! Controllers for requests starting with `/api/v0/main-frontend/course-archives`.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use actix_multipart::form::{MultipartForm, json::Json as MpJson, tempfile::TempFile};
+use headless_lms_utils::strings::is_ietf_language_code_like;
+use models::{
+    courses::NewCourse,
+    library::course_archives::{self, ImportedFile},
+};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    controllers::helpers::file_uploading,
+    domain::course_archives::{
+        self as archive_files, CourseArchiveFile, CourseArchiveImportReport,
+    },
+    prelude::*,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(export_course_archive, import_course_archive))]
+pub(crate) struct MainFrontendCourseArchivesApiDoc;
+
+#[derive(Debug, MultipartForm)]
+pub struct CourseArchiveImportForm {
+    course: MpJson<NewCourse>,
+    file: TempFile,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct CourseArchiveImportMultipartPayload {
+    #[schema(content_media_type = "application/json")]
+    course: NewCourse,
+    #[schema(content_media_type = "application/zip", value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CourseArchiveImportQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/**
+GET `/api/v0/main-frontend/course-archives/{course_id}` - Exports the course as a course archive
+that can be imported to another deployment.
+*/
+#[utoipa::path(
+    get,
+    path = "/{course_id}",
+    operation_id = "exportCourseArchive",
+    tag = "course-archives",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    responses(
+        (status = 200, description = "ZIP file of the course", content_type = "application/zip", body = serde_json::Value)
+    )
+)]
+#[instrument(skip(pool, file_store))]
+async fn export_course_archive(
+    course_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    file_store: web::Data<dyn FileStore>,
+    user: AuthUser,
+) -> ControllerResult<HttpResponse> {
+    let course_id = course_id.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Duplicate,
+        Some(user.id),
+        Res::Course(course_id),
+    )
+    .await?;
+    let course = models::courses::get_course(&mut conn, course_id).await?;
+    let contents = archive_files::export(&mut conn, file_store.as_ref(), course_id).await?;
+    token.authorized_ok(
+        HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                "Content-Disposition",
+                format!(
+                    "attachment; filename=\"{} {}.zip\"",
+                    course.slug,
+                    Utc::now().format("%Y-%m-%d")
+                ),
+            ))
+            .body(contents),
+    )
+}
+
+/**
+POST `/api/v0/main-frontend/course-archives/import?dry_run=true` - Creates a new course from a
+course archive.
+
+The exercise types of the archive must have exercise services in this deployment. With `dry_run`
+the import is done in a transaction that is rolled back and no files are stored, so the report
+tells whether the import would succeed.
+*/
+#[utoipa::path(
+    post,
+    path = "/import",
+    operation_id = "importCourseArchive",
+    tag = "course-archives",
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Only check that the archive can be imported")
+    ),
+    request_body(
+        content = inline(CourseArchiveImportMultipartPayload),
+        content_type = "multipart/form-data"
+    ),
+    responses(
+        (status = 200, description = "What was or would be imported", body = CourseArchiveImportReport)
+    )
+)]
+#[instrument(skip(pool, payload, file_store, app_conf))]
+async fn import_course_archive(
+    query: web::Query<CourseArchiveImportQuery>,
+    payload: MultipartForm<CourseArchiveImportForm>,
+    pool: web::Data<PgPool>,
+    file_store: web::Data<dyn FileStore>,
+    app_conf: web::Data<ApplicationConfiguration>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<CourseArchiveImportReport>> {
+    let CourseArchiveImportForm { course, file } = payload.into_inner();
+    let new_course = course.into_inner();
+    if !is_ietf_language_code_like(&new_course.language_code) {
+        return Err(controller_err!(
+            BadRequest,
+            "Malformed language code.".to_string()
+        ));
+    }
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::CreateCoursesOrExams,
+        Some(user.id),
+        Res::Organization(new_course.organization_id),
+    )
+    .await?;
+
+    let file = file.file.reopen().map_err(anyhow::Error::from)?;
+    let mut archive_file = CourseArchiveFile::open(file)
+        .map_err(|err| controller_err!(BadRequest, format!("{err:#}"), err))?;
+    let missing_exercise_services =
+        course_archives::get_missing_exercise_services(&mut conn, &archive_file.archive).await?;
+    let report = archive_file.report(missing_exercise_services);
+    if !report.missing_exercise_services.is_empty() {
+        if query.dry_run {
+            return token.authorized_ok(web::Json(report));
+        }
+        return Err(controller_err!(
+            BadRequest,
+            format!(
+                "No exercise service for exercise types: {}.",
+                report.missing_exercise_services.join(", ")
+            )
+        ));
+    }
+
+    let mut uploaded_files = Vec::new();
+    let result = import_course_archive_inner(
+        &mut conn,
+        &mut uploaded_files,
+        &mut archive_file,
+        &new_course,
+        query.dry_run,
+        file_store.as_ref(),
+        app_conf.as_ref(),
+        user,
+    )
+    .await;
+    match result {
+        Ok(course_id) => token.authorized_ok(web::Json(CourseArchiveImportReport {
+            course_id,
+            ..report
+        })),
+        Err(err) => {
+            // The uploads were recorded in the transaction that was rolled back, so only the stored
+            // files are left to clean up.
+            for path in uploaded_files {
+                if let Err(err) = file_store.delete(&path).await {
+                    error!(
+                        "Failed to delete file '{}' during cleanup: {err}",
+                        path.display()
+                    );
+                }
+            }
+            Err(err)
+        }
+    }
+}
+
+// wrapper so that the parent function can do cleanup if anything goes wrong
+#[allow(clippy::too_many_arguments)]
+async fn import_course_archive_inner(
+    conn: &mut PgConnection,
+    uploaded_files: &mut Vec<PathBuf>,
+    archive_file: &mut CourseArchiveFile<std::fs::File>,
+    new_course: &NewCourse,
+    dry_run: bool,
+    file_store: &dyn FileStore,
+    app_conf: &ApplicationConfiguration,
+    user: AuthUser,
+) -> ControllerResult<Option<Uuid>> {
+    let mut tx = conn.begin().await?;
+    let new_course_id = Uuid::new_v4();
+
+    let mut files = HashMap::new();
+    for archived in archive_file.archive.files.clone() {
+        if !archive_file.has_file(&archived.path) {
+            continue;
+        }
+        let (file_upload_id, path) = if dry_run {
+            // The rows are rolled back, but the certificate configurations need them to exist.
+            let id = models::file_uploads::insert(
+                &mut tx,
+                &archived.name,
+                &archived.path,
+                &archived.mime,
+                Some(user.id),
+            )
+            .await?;
+            (id, PathBuf::from(&archived.path))
+        } else {
+            let contents = archive_file
+                .read_file(&archived.path)
+                .map_err(|err| controller_err!(BadRequest, format!("{err:#}"), err))?;
+            let (id, path) = file_uploading::upload_file_from_package(
+                &mut tx,
+                &archived.name,
+                &archived.mime,
+                contents,
+                file_store,
+                new_course_id,
+                user,
+            )
+            .await?;
+            uploaded_files.push(path.clone());
+            (id, path)
+        };
+        files.insert(
+            archived.path.clone(),
+            ImportedFile {
+                path: path.to_string_lossy().to_string(),
+                url: file_store.get_download_url(&path, app_conf),
+                file_upload_id,
+            },
+        );
+    }
+
+    let course = course_archives::import_course(
+        &mut tx,
+        &archive_file.archive,
+        new_course_id,
+        new_course,
+        &files,
+    )
+    .await?;
+    models::roles::insert(
+        &mut tx,
+        user.id,
+        models::roles::UserRole::Teacher,
+        models::roles::RoleDomain::Course(course.id),
+    )
+    .await?;
+
+    if dry_run {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+    tx.commit().await?;
+    Ok(Some(course.id))
+}
+
+/**
+Add a route for each controller in this module.
+
+The name starts with an underline in order to appear before other functions in the module documentation.
+
+We add the routes by calling the route method instead of using the route annotations because this method preserves the function signatures for documentation.
+*/
+pub fn _add_routes(cfg: &mut ServiceConfig) {
+    cfg.route("/import", web::post().to(import_course_archive))
+        .route("/{course_id}", web::get().to(export_course_archive));
+}
@@ -1,7 +1,12 @@
 //! Controllers for requests starting with `/api/v0/main-frontend/course-instances`.
 
+use std::path::Path;
+
 use chrono::Utc;
 use models::{
+    certificate_bulk_exports::{
+        CertificateBulkExport, CertificateBulkExportFormat, CertificateBulkExportStatus,
+    },
     certificate_configurations::CertificateConfigurationAndRequirements,
     course_instances::{self, CourseInstance, CourseInstanceForm, Points},
     course_module_completions::CourseModuleCompletion,
@@ -17,7 +22,7 @@ use models::{
     },
     user_exercise_states::UserCourseProgress,
 };
-use utoipa::OpenApi;
+use utoipa::{OpenApi, ToSchema};
 
 use crate::{
     domain::csv_export::{
@@ -41,6 +46,10 @@ use crate::{
     delete,
     completions_export,
     certificate_configurations,
+    post_certificate_bulk_export,
+    get_certificate_bulk_exports,
+    get_certificate_bulk_export,
+    download_certificate_bulk_export,
     get_all_exercise_statuses_by_course_instance_id,
     get_all_get_all_course_module_completions_for_user_by_course_instance_id,
     get_user_progress_for_course_instance
@@ -510,6 +519,202 @@ pub async fn certificate_configurations(
     token.authorized_ok(web::Json(certificate_configurations))
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewCertificateBulkExport {
+    pub certificate_configuration_id: Uuid,
+    pub format: CertificateBulkExportFormat,
+}
+
+/**
+POST /course-instances/:id/certificate-bulk-exports - queues an export of the certificates of every student in the course instance who is eligible for the given certificate configuration. The export is processed in the background and can be downloaded as a ZIP file once it has completed.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    post,
+    path = "/{course_instance_id}/certificate-bulk-exports",
+    operation_id = "createCourseInstanceCertificateBulkExport",
+    tag = "course-instances",
+    params(
+        ("course_instance_id" = Uuid, Path, description = "Course instance id")
+    ),
+    request_body = NewCertificateBulkExport,
+    responses(
+        (status = 200, description = "Queued certificate bulk export", body = CertificateBulkExport)
+    )
+)]
+async fn post_certificate_bulk_export(
+    course_instance_id: web::Path<Uuid>,
+    payload: web::Json<NewCertificateBulkExport>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<CertificateBulkExport>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::CourseInstance(*course_instance_id),
+    )
+    .await?;
+    let export = models::certificate_bulk_exports::insert(
+        &mut conn,
+        *course_instance_id,
+        payload.certificate_configuration_id,
+        payload.format,
+        user.id,
+    )
+    .await?;
+    token.authorized_ok(web::Json(export))
+}
+
+/**
+GET /course-instances/:id/certificate-bulk-exports - gets the certificate bulk exports of the course instance with their progress, newest first.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/{course_instance_id}/certificate-bulk-exports",
+    operation_id = "getCourseInstanceCertificateBulkExports",
+    tag = "course-instances",
+    params(
+        ("course_instance_id" = Uuid, Path, description = "Course instance id")
+    ),
+    responses(
+        (status = 200, description = "Certificate bulk exports", body = [CertificateBulkExport])
+    )
+)]
+async fn get_certificate_bulk_exports(
+    course_instance_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<CertificateBulkExport>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::CourseInstance(*course_instance_id),
+    )
+    .await?;
+    let exports =
+        models::certificate_bulk_exports::get_by_course_instance_id(&mut conn, *course_instance_id)
+            .await?;
+    token.authorized_ok(web::Json(exports))
+}
+
+/**
+GET /course-instances/:id/certificate-bulk-exports/:export_id - gets a certificate bulk export with its progress.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/{course_instance_id}/certificate-bulk-exports/{export_id}",
+    operation_id = "getCourseInstanceCertificateBulkExport",
+    tag = "course-instances",
+    params(
+        ("course_instance_id" = Uuid, Path, description = "Course instance id"),
+        ("export_id" = Uuid, Path, description = "Certificate bulk export id")
+    ),
+    responses(
+        (status = 200, description = "Certificate bulk export", body = CertificateBulkExport)
+    )
+)]
+async fn get_certificate_bulk_export(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<CertificateBulkExport>> {
+    let (course_instance_id, export_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::CourseInstance(course_instance_id),
+    )
+    .await?;
+    let export =
+        get_certificate_bulk_export_of_course_instance(&mut conn, course_instance_id, export_id)
+            .await?;
+    token.authorized_ok(web::Json(export))
+}
+
+/**
+GET /course-instances/:id/certificate-bulk-exports/:export_id/download - downloads the ZIP file of a completed certificate bulk export. The ZIP file contains the certificates and a `manifest.csv` listing every student in the export.
+*/
+#[instrument(skip(pool, file_store))]
+#[utoipa::path(
+    get,
+    path = "/{course_instance_id}/certificate-bulk-exports/{export_id}/download",
+    operation_id = "downloadCourseInstanceCertificateBulkExport",
+    tag = "course-instances",
+    params(
+        ("course_instance_id" = Uuid, Path, description = "Course instance id"),
+        ("export_id" = Uuid, Path, description = "Certificate bulk export id")
+    ),
+    responses(
+        (status = 200, description = "ZIP file of the certificates", content_type = "application/zip", body = serde_json::Value)
+    )
+)]
+async fn download_certificate_bulk_export(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    file_store: web::Data<dyn FileStore>,
+    user: AuthUser,
+) -> ControllerResult<HttpResponse> {
+    let (course_instance_id, export_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::CourseInstance(course_instance_id),
+    )
+    .await?;
+    let export =
+        get_certificate_bulk_export_of_course_instance(&mut conn, course_instance_id, export_id)
+            .await?;
+    let zip_file_path = match (export.status, export.zip_file_path) {
+        (CertificateBulkExportStatus::Completed, Some(zip_file_path)) => zip_file_path,
+        _ => {
+            return Err(controller_err!(
+                BadRequest,
+                "The certificate bulk export has not completed yet.".to_string()
+            ));
+        }
+    };
+    let contents = file_store.download(Path::new(&zip_file_path)).await?;
+    token.authorized_ok(
+        HttpResponse::Ok()
+            .content_type("application/zip")
+            .insert_header((
+                "Content-Disposition",
+                format!(
+                    "attachment; filename=\"Certificates {}.zip\"",
+                    export.created_at.format("%Y-%m-%d")
+                ),
+            ))
+            .body(contents),
+    )
+}
+
+/// The export ids are only authorized through the course instance, so an export of another
+/// course instance is treated as missing.
+async fn get_certificate_bulk_export_of_course_instance(
+    conn: &mut PgConnection,
+    course_instance_id: Uuid,
+    export_id: Uuid,
+) -> ControllerResult<CertificateBulkExport> {
+    let export = models::certificate_bulk_exports::get_by_id(conn, export_id).await?;
+    if export.course_instance_id != course_instance_id {
+        return Err(controller_err!(
+            NotFound,
+            "Certificate bulk export not found.".to_string()
+        ));
+    }
+    Ok(export)
+}
+
 /**
 GET /course-instances/:id/status-for-all-exercises/:user_id - Returns a status for all exercises in a course instance for a given user.
 */
@@ -742,5 +947,21 @@ pub fn _add_routes(cfg: &mut ServiceConfig) {
         .route(
             "/{course_instance_id}/default-certificate-configurations",
             web::get().to(certificate_configurations),
+        )
+        .route(
+            "/{course_instance_id}/certificate-bulk-exports",
+            web::post().to(post_certificate_bulk_export),
+        )
+        .route(
+            "/{course_instance_id}/certificate-bulk-exports",
+            web::get().to(get_certificate_bulk_exports),
+        )
+        .route(
+            "/{course_instance_id}/certificate-bulk-exports/{export_id}",
+            web::get().to(get_certificate_bulk_export),
+        )
+        .route(
+            "/{course_instance_id}/certificate-bulk-exports/{export_id}/download",
+            web::get().to(download_certificate_bulk_export),
         );
 }
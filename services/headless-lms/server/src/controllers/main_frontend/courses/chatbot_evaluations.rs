@@ -0,0 +1,508 @@
+//! Controllers for requests starting with `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations`.
+use std::collections::HashSet;
+
+use crate::prelude::*;
+
+use models::chatbot_evaluations::{
+    ChatbotEvaluationQuestion, ChatbotEvaluationResult, ChatbotEvaluationRun,
+    ChatbotEvaluationRunComparison, ChatbotEvaluationRunSummary, ChatbotEvaluationSet,
+    NewChatbotEvaluationQuestion,
+};
+use utoipa::{OpenApi, ToSchema};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_evaluation_sets,
+    create_evaluation_set,
+    delete_evaluation_set,
+    get_questions,
+    create_question,
+    update_question,
+    delete_question,
+    get_runs,
+    start_run,
+    get_run_results,
+    compare_runs
+))]
+pub(crate) struct MainFrontendCourseChatbotEvaluationsApiDoc;
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StartChatbotEvaluationRun {
+    pub chatbot_configuration_id: Uuid,
+}
+
+/// GET `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations`
+#[utoipa::path(
+    get,
+    path = "",
+    operation_id = "getCourseChatbotEvaluationSets",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    responses(
+        (status = 200, description = "Chatbot evaluation sets of the course", body = Vec<ChatbotEvaluationSet>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_evaluation_sets(
+    course_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<ChatbotEvaluationSet>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(*course_id)).await?;
+    let res = models::chatbot_evaluations::get_sets_by_course_id(&mut conn, *course_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// POST `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations`
+#[utoipa::path(
+    post,
+    path = "",
+    operation_id = "createCourseChatbotEvaluationSet",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    request_body(
+        content = String,
+        description = "JSON string literal name of the set, e.g. \"Course practicalities\".",
+        content_type = "application/json"
+    ),
+    responses(
+        (status = 200, description = "Created chatbot evaluation set", body = ChatbotEvaluationSet)
+    )
+)]
+#[instrument(skip(pool, payload))]
+async fn create_evaluation_set(
+    course_id: web::Path<Uuid>,
+    payload: web::Json<String>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<ChatbotEvaluationSet>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(*course_id)).await?;
+    let name = payload.into_inner();
+    if name.trim().is_empty() {
+        return Err(controller_err!(
+            BadRequest,
+            "The name of the set cannot be empty.".to_string()
+        ));
+    }
+    let res = models::chatbot_evaluations::insert_set(&mut conn, *course_id, name.trim()).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// DELETE `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}`
+#[utoipa::path(
+    delete,
+    path = "/{evaluation_set_id}",
+    operation_id = "deleteCourseChatbotEvaluationSet",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id")
+    ),
+    responses(
+        (status = 200, description = "Chatbot evaluation set deleted")
+    )
+)]
+#[instrument(skip(pool))]
+async fn delete_evaluation_set(
+    ids: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let (course_id, evaluation_set_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_set_of_course(&mut conn, course_id, evaluation_set_id).await?;
+    models::chatbot_evaluations::delete_set(&mut conn, evaluation_set_id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/// GET `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}/questions`
+#[utoipa::path(
+    get,
+    path = "/{evaluation_set_id}/questions",
+    operation_id = "getCourseChatbotEvaluationQuestions",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id")
+    ),
+    responses(
+        (status = 200, description = "Questions of the set", body = Vec<ChatbotEvaluationQuestion>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_questions(
+    ids: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<ChatbotEvaluationQuestion>>> {
+    let (course_id, evaluation_set_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_set_of_course(&mut conn, course_id, evaluation_set_id).await?;
+    let res =
+        models::chatbot_evaluations::get_questions_by_set_id(&mut conn, evaluation_set_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// POST `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}/questions`
+#[utoipa::path(
+    post,
+    path = "/{evaluation_set_id}/questions",
+    operation_id = "createCourseChatbotEvaluationQuestion",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id")
+    ),
+    request_body = NewChatbotEvaluationQuestion,
+    responses(
+        (status = 200, description = "Created question", body = ChatbotEvaluationQuestion)
+    )
+)]
+#[instrument(skip(pool, payload))]
+async fn create_question(
+    ids: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<NewChatbotEvaluationQuestion>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<ChatbotEvaluationQuestion>> {
+    let (course_id, evaluation_set_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_set_of_course(&mut conn, course_id, evaluation_set_id).await?;
+    validate_question(&mut conn, course_id, &payload).await?;
+    let res = models::chatbot_evaluations::insert_question(&mut conn, evaluation_set_id, &payload)
+        .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// PUT `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}/questions/{question_id}`
+#[utoipa::path(
+    put,
+    path = "/{evaluation_set_id}/questions/{question_id}",
+    operation_id = "updateCourseChatbotEvaluationQuestion",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id"),
+        ("question_id" = Uuid, Path, description = "Chatbot evaluation question id")
+    ),
+    request_body = NewChatbotEvaluationQuestion,
+    responses(
+        (status = 200, description = "Updated question", body = ChatbotEvaluationQuestion)
+    )
+)]
+#[instrument(skip(pool, payload))]
+async fn update_question(
+    ids: web::Path<(Uuid, Uuid, Uuid)>,
+    payload: web::Json<NewChatbotEvaluationQuestion>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<ChatbotEvaluationQuestion>> {
+    let (course_id, evaluation_set_id, question_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_question_of_set(&mut conn, course_id, evaluation_set_id, question_id).await?;
+    validate_question(&mut conn, course_id, &payload).await?;
+    let res =
+        models::chatbot_evaluations::update_question(&mut conn, question_id, &payload).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// DELETE `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}/questions/{question_id}`
+#[utoipa::path(
+    delete,
+    path = "/{evaluation_set_id}/questions/{question_id}",
+    operation_id = "deleteCourseChatbotEvaluationQuestion",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id"),
+        ("question_id" = Uuid, Path, description = "Chatbot evaluation question id")
+    ),
+    responses(
+        (status = 200, description = "Question deleted")
+    )
+)]
+#[instrument(skip(pool))]
+async fn delete_question(
+    ids: web::Path<(Uuid, Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let (course_id, evaluation_set_id, question_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_question_of_set(&mut conn, course_id, evaluation_set_id, question_id).await?;
+    models::chatbot_evaluations::delete_question(&mut conn, question_id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/// GET `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}/runs` -
+/// The runs of the set with the averages of their scores, newest first.
+#[utoipa::path(
+    get,
+    path = "/{evaluation_set_id}/runs",
+    operation_id = "getCourseChatbotEvaluationRuns",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id")
+    ),
+    responses(
+        (status = 200, description = "Runs of the set", body = Vec<ChatbotEvaluationRunSummary>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_runs(
+    ids: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<ChatbotEvaluationRunSummary>>> {
+    let (course_id, evaluation_set_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_set_of_course(&mut conn, course_id, evaluation_set_id).await?;
+    let res =
+        models::chatbot_evaluations::get_run_summaries_by_set_id(&mut conn, evaluation_set_id)
+            .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// POST `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}/runs` -
+/// Queues a run of the set against a chatbot of the course. The run is answered in the background
+/// by the chatbot evaluation runner.
+#[utoipa::path(
+    post,
+    path = "/{evaluation_set_id}/runs",
+    operation_id = "startCourseChatbotEvaluationRun",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id")
+    ),
+    request_body = StartChatbotEvaluationRun,
+    responses(
+        (status = 200, description = "The queued run", body = ChatbotEvaluationRun)
+    )
+)]
+#[instrument(skip(pool))]
+async fn start_run(
+    ids: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<StartChatbotEvaluationRun>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<ChatbotEvaluationRun>> {
+    let (course_id, evaluation_set_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_set_of_course(&mut conn, course_id, evaluation_set_id).await?;
+    let configuration =
+        models::chatbot_configurations::get_by_id(&mut conn, payload.chatbot_configuration_id)
+            .await?;
+    if configuration.course_id != Some(course_id) {
+        return Err(controller_err!(
+            BadRequest,
+            "The chatbot does not belong to the course.".to_string()
+        ));
+    }
+    let model =
+        models::chatbot_configurations_models::get_by_id(&mut conn, configuration.model_id).await?;
+    let snapshot = serde_json::json!({
+        "configuration": configuration,
+        "model": model,
+    });
+    let res = models::chatbot_evaluations::insert_run(
+        &mut conn,
+        evaluation_set_id,
+        configuration.id,
+        user.id,
+        snapshot,
+    )
+    .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// GET `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}/runs/{run_id}/results`
+#[utoipa::path(
+    get,
+    path = "/{evaluation_set_id}/runs/{run_id}/results",
+    operation_id = "getCourseChatbotEvaluationRunResults",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id"),
+        ("run_id" = Uuid, Path, description = "Chatbot evaluation run id")
+    ),
+    responses(
+        (status = 200, description = "Scored answers of the run", body = Vec<ChatbotEvaluationResult>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_run_results(
+    ids: web::Path<(Uuid, Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<ChatbotEvaluationResult>>> {
+    let (course_id, evaluation_set_id, run_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_run_of_set(&mut conn, course_id, evaluation_set_id, run_id).await?;
+    let res = models::chatbot_evaluations::get_results_by_run_id(&mut conn, run_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// GET `/api/v0/main-frontend/courses/{course_id}/chatbot-evaluations/{evaluation_set_id}/runs/{run_id}/compare/{compared_run_id}` -
+/// Compares two runs of the set question by question, for example to check another chatbot
+/// configuration against the current default chatbot.
+#[utoipa::path(
+    get,
+    path = "/{evaluation_set_id}/runs/{run_id}/compare/{compared_run_id}",
+    operation_id = "compareCourseChatbotEvaluationRuns",
+    tag = "course-chatbot-evaluations",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("evaluation_set_id" = Uuid, Path, description = "Chatbot evaluation set id"),
+        ("run_id" = Uuid, Path, description = "The run compared against"),
+        ("compared_run_id" = Uuid, Path, description = "The run compared to the first one")
+    ),
+    responses(
+        (status = 200, description = "The runs side by side", body = ChatbotEvaluationRunComparison)
+    )
+)]
+#[instrument(skip(pool))]
+async fn compare_runs(
+    ids: web::Path<(Uuid, Uuid, Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<ChatbotEvaluationRunComparison>> {
+    let (course_id, evaluation_set_id, run_id, compared_run_id) = *ids;
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    get_run_of_set(&mut conn, course_id, evaluation_set_id, run_id).await?;
+    get_run_of_set(&mut conn, course_id, evaluation_set_id, compared_run_id).await?;
+    let res = models::chatbot_evaluations::compare_runs(&mut conn, run_id, compared_run_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+async fn get_set_of_course(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    evaluation_set_id: Uuid,
+) -> ControllerResult<ChatbotEvaluationSet> {
+    let set = models::chatbot_evaluations::get_set_by_id(conn, evaluation_set_id).await?;
+    if set.course_id != course_id {
+        return Err(controller_err!(
+            NotFound,
+            "The evaluation set does not belong to the course.".to_string()
+        ));
+    }
+    Ok(set)
+}
+
+async fn get_question_of_set(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    evaluation_set_id: Uuid,
+    question_id: Uuid,
+) -> ControllerResult<ChatbotEvaluationQuestion> {
+    get_set_of_course(conn, course_id, evaluation_set_id).await?;
+    let question = models::chatbot_evaluations::get_question_by_id(conn, question_id).await?;
+    if question.evaluation_set_id != evaluation_set_id {
+        return Err(controller_err!(
+            NotFound,
+            "The question does not belong to the evaluation set.".to_string()
+        ));
+    }
+    Ok(question)
+}
+
+async fn get_run_of_set(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    evaluation_set_id: Uuid,
+    run_id: Uuid,
+) -> ControllerResult<ChatbotEvaluationRun> {
+    get_set_of_course(conn, course_id, evaluation_set_id).await?;
+    let run = models::chatbot_evaluations::get_run_by_id(conn, run_id).await?;
+    if run.evaluation_set_id != evaluation_set_id {
+        return Err(controller_err!(
+            NotFound,
+            "The run does not belong to the evaluation set.".to_string()
+        ));
+    }
+    Ok(run)
+}
+
+async fn validate_question(
+    conn: &mut PgConnection,
+    course_id: Uuid,
+    question: &NewChatbotEvaluationQuestion,
+) -> ControllerResult<()> {
+    if question.question.trim().is_empty() {
+        return Err(controller_err!(
+            BadRequest,
+            "The question cannot be empty.".to_string()
+        ));
+    }
+    if !question.expected_page_ids.is_empty() {
+        let course_page_ids: HashSet<Uuid> = models::pages::get_pages_by_course_id(conn, course_id)
+            .await?
+            .into_iter()
+            .map(|p| p.id)
+            .collect();
+        if question
+            .expected_page_ids
+            .iter()
+            .any(|id| !course_page_ids.contains(id))
+        {
+            return Err(controller_err!(
+                BadRequest,
+                "The expected pages must be pages of the course.".to_string()
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn _add_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(get_evaluation_sets))
+        .route("", web::post().to(create_evaluation_set))
+        .route(
+            "/{evaluation_set_id}",
+            web::delete().to(delete_evaluation_set),
+        )
+        .route(
+            "/{evaluation_set_id}/questions",
+            web::get().to(get_questions),
+        )
+        .route(
+            "/{evaluation_set_id}/questions",
+            web::post().to(create_question),
+        )
+        .route(
+            "/{evaluation_set_id}/questions/{question_id}",
+            web::put().to(update_question),
+        )
+        .route(
+            "/{evaluation_set_id}/questions/{question_id}",
+            web::delete().to(delete_question),
+        )
+        .route("/{evaluation_set_id}/runs", web::get().to(get_runs))
+        .route("/{evaluation_set_id}/runs", web::post().to(start_run))
+        .route(
+            "/{evaluation_set_id}/runs/{run_id}/results",
+            web::get().to(get_run_results),
+        )
+        .route(
+            "/{evaluation_set_id}/runs/{run_id}/compare/{compared_run_id}",
+            web::get().to(compare_runs),
+        );
+}
@@ -1,6 +1,11 @@
 //! Controllers for requests starting with `/api/v0/main-frontend/{course_id}/chatbots`.
 use crate::prelude::*;
 
+use chrono::Duration;
+use headless_lms_chatbot::{
+    builtin_search::AzureEmbedder,
+    chatbot_analytics::{ChatbotAnalytics, get_chatbot_analytics},
+};
 use headless_lms_models::chatbot_configurations::NewChatbotConf;
 use models::chatbot_configurations::ChatbotConfiguration;
 use utoipa::OpenApi;
@@ -10,7 +15,8 @@ use utoipa::OpenApi;
     get_chatbots,
     create_chatbot,
     set_default_chatbot,
-    set_non_default_chatbot
+    set_non_default_chatbot,
+    get_chatbot_analytics_for_course
 ))]
 pub(crate) struct MainFrontendCourseChatbotsApiDoc;
 
@@ -214,6 +220,78 @@ async fn set_non_default_chatbot(
     token.authorized_ok(web::Json(configuration))
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ChatbotAnalyticsQuery {
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
+/**
+GET `/api/v0/main-frontend/courses/{course_id}/chatbots/{chatbot_configuration_id}/analytics?start=...&end=...`
+
+Returns aggregated analytics of the chatbot's conversations: the common question topics, the cited
+pages and the token usage. Defaults to the last 30 days, and the time range is moved to whole
+weeks. Figures concerning fewer than `min_group_size` users are left out.
+*/
+#[utoipa::path(
+    get,
+    path = "/{chatbot_configuration_id}/analytics",
+    operation_id = "getCourseChatbotAnalytics",
+    tag = "courses",
+    params(
+        ("course_id" = String, Path, description = "Course id"),
+        ("chatbot_configuration_id" = String, Path, description = "Chatbot configuration id"),
+        ("start" = Option<DateTime<Utc>>, Query, description = "Start of the time range"),
+        ("end" = Option<DateTime<Utc>>, Query, description = "End of the time range")
+    ),
+    responses(
+        (status = 200, description = "Chatbot analytics", body = ChatbotAnalytics)
+    )
+)]
+#[instrument(skip(pool, app_conf))]
+async fn get_chatbot_analytics_for_course(
+    ids: web::Path<(Uuid, Uuid)>,
+    query: web::Query<ChatbotAnalyticsQuery>,
+    pool: web::Data<PgPool>,
+    app_conf: web::Data<ApplicationConfiguration>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<ChatbotAnalytics>> {
+    let mut conn = pool.acquire().await?;
+    let (course_id, chatbot_configuration_id) = *ids;
+
+    let token = authorize(
+        &mut conn,
+        Act::ViewStats,
+        Some(user.id),
+        Res::Course(course_id),
+    )
+    .await?;
+
+    let configuration =
+        models::chatbot_configurations::get_by_id(&mut conn, chatbot_configuration_id).await?;
+    if configuration.course_id != Some(course_id) {
+        return Err(ControllerError::new(
+            ControllerErrorType::NotFound,
+            "Chatbot not found in the course.".to_string(),
+            None,
+        ));
+    }
+    let end = query.end.unwrap_or_else(Utc::now);
+    let start = query.start.unwrap_or(end - Duration::days(30));
+    if start >= end {
+        return Err(ControllerError::new(
+            ControllerErrorType::BadRequest,
+            "The start of the time range must be before its end.".to_string(),
+            None,
+        ));
+    }
+
+    let embedder = AzureEmbedder::new(&app_conf);
+    let analytics = get_chatbot_analytics(&mut conn, &embedder, &configuration, start, end).await?;
+
+    token.authorized_ok(web::Json(analytics))
+}
+
 pub fn _add_routes(cfg: &mut web::ServiceConfig) {
     cfg.route("", web::get().to(get_chatbots))
         .route("", web::post().to(create_chatbot))
@@ -224,5 +302,9 @@ pub fn _add_routes(cfg: &mut web::ServiceConfig) {
         .route(
             "/{chatbot_configuration_id}/set-as-non-default",
             web::post().to(set_non_default_chatbot),
+        )
+        .route(
+            "/{chatbot_configuration_id}/analytics",
+            web::get().to(get_chatbot_analytics_for_course),
         );
 }
@@ -0,0 +1,238 @@
+//! Controllers for requests starting with `/api/v0/main-frontend/courses/{course_id}/common-cartridge-import`.
+
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
+
+use actix_multipart::form::{MultipartForm, tempfile::TempFile};
+use models::chapters::NewChapter;
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    controllers::helpers::file_uploading,
+    domain::{
+        common_cartridge::package::{self, CommonCartridgeImportReport, ImportPlan, Package},
+        models_requests::{self, JwtKey},
+    },
+    prelude::*,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(import_common_cartridge))]
+pub(crate) struct MainFrontendCourseCommonCartridgeImportApiDoc;
+
+#[derive(Debug, MultipartForm)]
+pub struct CommonCartridgeImportForm {
+    file: TempFile,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct CommonCartridgeImportMultipartPayload {
+    #[schema(content_media_type = "application/zip", value_type = String, format = Binary)]
+    file: Vec<u8>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommonCartridgeImportQuery {
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/**
+POST `/api/v0/main-frontend/courses/{course_id}/common-cartridge-import?dry_run=true` - Imports an
+IMS Common Cartridge package (`.imscc`) to the course.
+
+The modules of the cartridge are added as new chapters after the existing ones. With `dry_run` nothing
+is created, and the report only tells what would be imported and what is not supported.
+*/
+#[utoipa::path(
+    post,
+    path = "",
+    operation_id = "importCommonCartridge",
+    tag = "courses",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("dry_run" = Option<bool>, Query, description = "Only report what would be imported")
+    ),
+    request_body(
+        content = inline(CommonCartridgeImportMultipartPayload),
+        content_type = "multipart/form-data"
+    ),
+    responses(
+        (status = 200, description = "What was imported and what was not supported", body = CommonCartridgeImportReport)
+    )
+)]
+#[instrument(skip(pool, payload, file_store, jwt_key, app_conf))]
+#[allow(clippy::too_many_arguments)]
+async fn import_common_cartridge(
+    request_id: RequestId,
+    course_id: web::Path<Uuid>,
+    query: web::Query<CommonCartridgeImportQuery>,
+    payload: MultipartForm<CommonCartridgeImportForm>,
+    pool: web::Data<PgPool>,
+    file_store: web::Data<dyn FileStore>,
+    jwt_key: web::Data<JwtKey>,
+    app_conf: web::Data<ApplicationConfiguration>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<CommonCartridgeImportReport>> {
+    let course_id = course_id.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+
+    let file = payload
+        .into_inner()
+        .file
+        .file
+        .reopen()
+        .map_err(anyhow::Error::from)?;
+    let mut package =
+        Package::open(file).map_err(|err| controller_err!(BadRequest, format!("{err:#}"), err))?;
+    let plan = package
+        .plan(&HashMap::new())
+        .map_err(|err| controller_err!(BadRequest, format!("{err:#}"), err))?;
+    if query.dry_run {
+        return token.authorized_ok(web::Json(plan.report()));
+    }
+
+    let mut uploaded_files = Vec::new();
+    let result = import_common_cartridge_inner(
+        &mut conn,
+        &mut uploaded_files,
+        course_id,
+        &mut package,
+        plan,
+        &ImportContext {
+            request_id,
+            file_store: file_store.as_ref(),
+            jwt_key: Arc::clone(&jwt_key),
+            app_conf: app_conf.as_ref(),
+            user,
+        },
+    )
+    .await;
+    match result {
+        Ok(report) => token.authorized_ok(web::Json(report)),
+        Err(err) => {
+            // The uploads were recorded in the transaction that was rolled back, so only the stored
+            // files are left to clean up.
+            for path in uploaded_files {
+                if let Err(err) = file_store.delete(&path).await {
+                    error!(
+                        "Failed to delete file '{}' during cleanup: {err}",
+                        path.display()
+                    );
+                }
+            }
+            Err(err)
+        }
+    }
+}
+
+struct ImportContext<'a> {
+    request_id: RequestId,
+    file_store: &'a dyn FileStore,
+    jwt_key: Arc<JwtKey>,
+    app_conf: &'a ApplicationConfiguration,
+    user: AuthUser,
+}
+
+// wrapper so that the parent function can do cleanup if anything goes wrong
+async fn import_common_cartridge_inner(
+    conn: &mut PgConnection,
+    uploaded_files: &mut Vec<PathBuf>,
+    course_id: Uuid,
+    package: &mut Package<std::fs::File>,
+    dry_run_plan: ImportPlan,
+    ctx: &ImportContext<'_>,
+) -> ControllerResult<CommonCartridgeImportReport> {
+    let mut tx = conn.begin().await?;
+
+    let mut file_urls = HashMap::new();
+    for path in &dry_run_plan.files {
+        let contents = package
+            .read_file(path)
+            .map_err(|err| controller_err!(BadRequest, format!("{err:#}"), err))?;
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        let (_, stored_path) = file_uploading::upload_file_from_package(
+            &mut tx,
+            file_name,
+            package::mime_type(path),
+            contents,
+            ctx.file_store,
+            course_id,
+            ctx.user,
+        )
+        .await?;
+        file_urls.insert(
+            path.clone(),
+            ctx.file_store.get_download_url(&stored_path, ctx.app_conf),
+        );
+        uploaded_files.push(stored_path);
+    }
+
+    let plan = package
+        .plan(&file_urls)
+        .map_err(|err| controller_err!(BadRequest, format!("{err:#}"), err))?;
+    let mut report = plan.report();
+    let mut chapter_number = models::chapters::get_course_chapters(&mut tx, course_id)
+        .await?
+        .iter()
+        .map(|chapter| chapter.chapter_number)
+        .max()
+        .unwrap_or(0);
+    for (chapter, reported_chapter) in plan.chapters.into_iter().zip(report.chapters.iter_mut()) {
+        chapter_number += 1;
+        let (database_chapter, _) = models::library::content_management::create_new_chapter(
+            &mut tx,
+            PKeyPolicy::Generate,
+            &NewChapter {
+                name: chapter.name,
+                color: None,
+                course_id,
+                chapter_number,
+                front_page_id: None,
+                opens_at: None,
+                deadline: None,
+                course_module_id: None,
+            },
+            ctx.user.id,
+            models_requests::make_spec_fetcher(
+                ctx.app_conf.base_url.clone(),
+                ctx.request_id.0,
+                Arc::clone(&ctx.jwt_key),
+            ),
+            models_requests::fetch_service_info,
+        )
+        .await?;
+        reported_chapter.chapter_id = Some(database_chapter.id);
+
+        for (page, reported_page) in chapter
+            .pages
+            .into_iter()
+            .zip(reported_chapter.pages.iter_mut())
+        {
+            let page_id = models::library::migration::create_page(
+                &mut tx,
+                course_id,
+                models::pages::CmsPageUpdate {
+                    chapter_id: Some(database_chapter.id),
+                    ..page.page
+                },
+                ctx.user.id,
+                models_requests::make_spec_fetcher(
+                    ctx.app_conf.base_url.clone(),
+                    ctx.request_id.0,
+                    Arc::clone(&ctx.jwt_key),
+                ),
+                models_requests::fetch_service_info,
+            )
+            .await?;
+            reported_page.page_id = Some(page_id);
+        }
+    }
+    tx.commit().await?;
+    Ok(report)
+}
+
+pub fn _add_routes(cfg: &mut ServiceConfig) {
+    cfg.route("", web::post().to(import_common_cartridge));
+}
@@ -0,0 +1,172 @@
+//! Controllers for requests starting with `/api/v0/main-frontend/courses/{course_id}/deadline-exceptions`.
+use crate::prelude::*;
+
+use models::deadline_exceptions::{
+    DeadlineException, DeadlineExceptionScope, DeadlineExceptionTarget, NewDeadlineException,
+};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_deadline_exceptions,
+    grant_deadline_exception,
+    revoke_deadline_exception
+))]
+pub(crate) struct MainFrontendCourseDeadlineExceptionsApiDoc;
+
+/// GET `/api/v0/main-frontend/courses/{course_id}/deadline-exceptions` - Every deadline exception
+/// granted on the course, revoked and replaced ones included, newest first.
+#[utoipa::path(
+    get,
+    path = "",
+    operation_id = "getCourseDeadlineExceptions",
+    tag = "course-deadline-exceptions",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    responses(
+        (status = 200, description = "Deadline exceptions of the course", body = Vec<DeadlineException>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_deadline_exceptions(
+    course_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<DeadlineException>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::Course(*course_id),
+    )
+    .await?;
+    let res = models::deadline_exceptions::get_history_for_course(&mut conn, *course_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// POST `/api/v0/main-frontend/courses/{course_id}/deadline-exceptions` - Grants a deadline
+/// exception for an exercise or chapter of the course, replacing the live exception for the same
+/// student or course instance and target if there is one.
+#[utoipa::path(
+    post,
+    path = "",
+    operation_id = "grantCourseDeadlineException",
+    tag = "course-deadline-exceptions",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    request_body = NewDeadlineException,
+    responses(
+        (status = 200, description = "The granted deadline exception", body = DeadlineException)
+    )
+)]
+#[instrument(skip(pool))]
+async fn grant_deadline_exception(
+    course_id: web::Path<Uuid>,
+    payload: web::Json<NewDeadlineException>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<DeadlineException>> {
+    let course_id = course_id.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(course_id)).await?;
+
+    let target_course_id = match payload.target {
+        DeadlineExceptionTarget::Exercise(exercise_id) => {
+            models::exercises::get_by_id(&mut conn, exercise_id)
+                .await?
+                .course_id
+        }
+        DeadlineExceptionTarget::Chapter(chapter_id) => Some(
+            models::chapters::get_chapter(&mut conn, chapter_id)
+                .await?
+                .course_id,
+        ),
+        DeadlineExceptionTarget::Exam(_) => {
+            return Err(controller_err!(
+                BadRequest,
+                "Exam deadline exceptions are granted through the exam.".to_string()
+            ));
+        }
+    };
+    if target_course_id != Some(course_id) {
+        return Err(controller_err!(
+            BadRequest,
+            "The exercise or chapter does not belong to the course.".to_string()
+        ));
+    }
+    match payload.scope {
+        DeadlineExceptionScope::User(target_user_id) => {
+            models::user_details::get_user_details_by_user_id_for_course(
+                &mut conn,
+                target_user_id,
+                course_id,
+            )
+            .await?;
+        }
+        DeadlineExceptionScope::CourseInstance(course_instance_id) => {
+            let instance =
+                models::course_instances::get_course_instance(&mut conn, course_instance_id)
+                    .await?;
+            if instance.course_id != course_id {
+                return Err(controller_err!(
+                    BadRequest,
+                    "Course instance does not belong to the course.".to_string()
+                ));
+            }
+        }
+    }
+
+    let exception =
+        models::deadline_exceptions::grant(&mut conn, Some(course_id), &payload, user.id).await?;
+    info!(
+        deadline_exception_id = %exception.id,
+        granted_by = %user.id,
+        "Deadline exception granted"
+    );
+    token.authorized_ok(web::Json(exception))
+}
+
+/// DELETE `/api/v0/main-frontend/courses/{course_id}/deadline-exceptions/{id}` - Revokes a deadline
+/// exception, after which the shared deadline applies again. The row is kept in the history.
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    operation_id = "revokeCourseDeadlineException",
+    tag = "course-deadline-exceptions",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("id" = Uuid, Path, description = "Deadline exception id")
+    ),
+    responses(
+        (status = 200, description = "Whether a live exception was revoked", body = bool)
+    )
+)]
+#[instrument(skip(pool))]
+async fn revoke_deadline_exception(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<bool>> {
+    let (course_id, id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(course_id)).await?;
+
+    let exception = models::deadline_exceptions::get_by_id(&mut conn, id).await?;
+    if exception.course_id != Some(course_id) {
+        return Err(controller_err!(
+            NotFound,
+            "Deadline exception not found on this course.".to_string()
+        ));
+    }
+    let revoked = models::deadline_exceptions::revoke(&mut conn, id, user.id).await?;
+    token.authorized_ok(web::Json(revoked))
+}
+
+pub fn _add_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(get_deadline_exceptions))
+        .route("", web::post().to(grant_deadline_exception))
+        .route("/{id}", web::delete().to(revoke_deadline_exception));
+}
@@ -0,0 +1,128 @@
+//! Controllers for requests starting with `/api/v0/main-frontend/courses/{course_id}/late-submission-policies`.
+use crate::prelude::*;
+
+use models::late_submission_policies::{LateSubmissionPolicy, NewLateSubmissionPolicy};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_late_submission_policies,
+    upsert_late_submission_policy,
+    delete_late_submission_policy
+))]
+pub(crate) struct MainFrontendCourseLateSubmissionPoliciesApiDoc;
+
+/// GET `/api/v0/main-frontend/courses/{course_id}/late-submission-policies` - The course default
+/// late submission policy, if set, followed by the exercise overrides.
+#[utoipa::path(
+    get,
+    path = "",
+    operation_id = "getCourseLateSubmissionPolicies",
+    tag = "course-late-submission-policies",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    responses(
+        (status = 200, description = "Late submission policies of the course", body = Vec<LateSubmissionPolicy>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_late_submission_policies(
+    course_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<LateSubmissionPolicy>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::Course(*course_id),
+    )
+    .await?;
+    let res = models::late_submission_policies::get_by_course_id(&mut conn, *course_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// PUT `/api/v0/main-frontend/courses/{course_id}/late-submission-policies` - Sets the course
+/// default policy, or the override for an exercise of the course. Submissions that were already
+/// made keep the penalty they got.
+#[utoipa::path(
+    put,
+    path = "",
+    operation_id = "upsertCourseLateSubmissionPolicy",
+    tag = "course-late-submission-policies",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    request_body = NewLateSubmissionPolicy,
+    responses(
+        (status = 200, description = "The saved late submission policy", body = LateSubmissionPolicy)
+    )
+)]
+#[instrument(skip(pool))]
+async fn upsert_late_submission_policy(
+    course_id: web::Path<Uuid>,
+    payload: web::Json<NewLateSubmissionPolicy>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<LateSubmissionPolicy>> {
+    let course_id = course_id.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(course_id)).await?;
+
+    if let Some(exercise_id) = payload.exercise_id {
+        let exercise = models::exercises::get_by_id(&mut conn, exercise_id).await?;
+        if exercise.course_id != Some(course_id) {
+            return Err(controller_err!(
+                BadRequest,
+                "The exercise does not belong to the course.".to_string()
+            ));
+        }
+    }
+    let policy = models::late_submission_policies::upsert(&mut conn, course_id, &payload).await?;
+    token.authorized_ok(web::Json(policy))
+}
+
+/// DELETE `/api/v0/main-frontend/courses/{course_id}/late-submission-policies/{id}` - Removes a
+/// policy. Removing an exercise override makes the course default apply again, and removing the
+/// course default stops late submissions from being accepted.
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    operation_id = "deleteCourseLateSubmissionPolicy",
+    tag = "course-late-submission-policies",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id"),
+        ("id" = Uuid, Path, description = "Late submission policy id")
+    ),
+    responses(
+        (status = 200, description = "Late submission policy deleted")
+    )
+)]
+#[instrument(skip(pool))]
+async fn delete_late_submission_policy(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let (course_id, id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(course_id)).await?;
+
+    let policy = models::late_submission_policies::get_by_id(&mut conn, id).await?;
+    if policy.course_id != course_id {
+        return Err(controller_err!(
+            NotFound,
+            "Late submission policy not found on this course.".to_string()
+        ));
+    }
+    models::late_submission_policies::delete(&mut conn, id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+pub fn _add_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(get_late_submission_policies))
+        .route("", web::put().to(upsert_late_submission_policy))
+        .route("/{id}", web::delete().to(delete_late_submission_policy));
+}
@@ -1,8 +1,13 @@
 //! Controllers for requests starting with `/api/v0/main-frontend/courses`.
 
+pub mod chatbot_evaluations;
 pub mod chatbots;
+pub mod common_cartridge_import;
+pub mod deadline_exceptions;
+pub mod late_submission_policies;
 pub mod stats;
 pub mod students;
+pub mod xapi;
 
 use chrono::Utc;
 use domain::csv_export::user_exercise_states_export::UserExerciseStatesExportOperation;
@@ -132,9 +137,14 @@ use crate::domain::csv_export::users_export::UsersExportOperation;
         get_all_courses
     ),
     nest(
+        (path = "/{course_id}/chatbot-evaluations", api = chatbot_evaluations::MainFrontendCourseChatbotEvaluationsApiDoc),
         (path = "/{course_id}/chatbots", api = chatbots::MainFrontendCourseChatbotsApiDoc),
+        (path = "/{course_id}/common-cartridge-import", api = common_cartridge_import::MainFrontendCourseCommonCartridgeImportApiDoc),
+        (path = "/{course_id}/deadline-exceptions", api = deadline_exceptions::MainFrontendCourseDeadlineExceptionsApiDoc),
+        (path = "/{course_id}/late-submission-policies", api = late_submission_policies::MainFrontendCourseLateSubmissionPoliciesApiDoc),
         (path = "/{course_id}/stats", api = stats::MainFrontendCourseStatsApiDoc),
-        (path = "/{course_id}/students", api = students::MainFrontendCourseStudentsApiDoc)
+        (path = "/{course_id}/students", api = students::MainFrontendCourseStudentsApiDoc),
+        (path = "/{course_id}/xapi", api = xapi::MainFrontendCourseXapiApiDoc)
     )
 )]
 pub(crate) struct MainFrontendCoursesApiDoc;
@@ -2856,8 +2866,25 @@ We add the routes by calling the route method instead of using the route annotat
 */
 pub fn _add_routes(cfg: &mut ServiceConfig) {
     cfg.service(web::scope("/{course_id}/stats").configure(stats::_add_routes))
+        .service(
+            web::scope("/{course_id}/chatbot-evaluations")
+                .configure(chatbot_evaluations::_add_routes),
+        )
         .service(web::scope("/{course_id}/chatbots").configure(chatbots::_add_routes))
+        .service(
+            web::scope("/{course_id}/common-cartridge-import")
+                .configure(common_cartridge_import::_add_routes),
+        )
+        .service(
+            web::scope("/{course_id}/deadline-exceptions")
+                .configure(deadline_exceptions::_add_routes),
+        )
+        .service(
+            web::scope("/{course_id}/late-submission-policies")
+                .configure(late_submission_policies::_add_routes),
+        )
         .service(web::scope("/{course_id}/students").configure(students::_add_routes))
+        .service(web::scope("/{course_id}/xapi").configure(xapi::_add_routes))
         .route("/{course_id}", web::get().to(get_course))
         .route("", web::post().to(post_new_course))
         .route("/{course_id}", web::put().to(update_course))
@@ -0,0 +1,94 @@
+//! Controllers for requests starting with `/api/v0/main-frontend/courses/{course_id}/xapi`.
+use crate::prelude::*;
+
+use models::{course_xapi_opt_ins::CourseXapiOptIn, xapi_statements::XapiStatementCounts};
+use utoipa::{OpenApi, ToSchema};
+
+#[derive(OpenApi)]
+#[openapi(paths(get_xapi_status, set_xapi_opt_in))]
+pub(crate) struct MainFrontendCourseXapiApiDoc;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CourseXapiStatus {
+    /// Set if the learning events of the course are sent to the learning record store.
+    pub opt_in: Option<CourseXapiOptIn>,
+    pub counts: XapiStatementCounts,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CourseXapiOptInUpdate {
+    pub enabled: bool,
+}
+
+/// GET `/api/v0/main-frontend/courses/{course_id}/xapi` - Whether the course sends its learning
+/// events to the learning record store, and how many statements have been sent.
+#[utoipa::path(
+    get,
+    path = "",
+    operation_id = "getCourseXapiStatus",
+    tag = "course-xapi",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    responses(
+        (status = 200, description = "The xAPI status of the course", body = CourseXapiStatus)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_xapi_status(
+    course_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<CourseXapiStatus>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::Course(*course_id),
+    )
+    .await?;
+    let opt_in = models::course_xapi_opt_ins::get_by_course_id(&mut conn, *course_id).await?;
+    let counts = models::xapi_statements::get_counts_by_course_id(&mut conn, *course_id).await?;
+    token.authorized_ok(web::Json(CourseXapiStatus { opt_in, counts }))
+}
+
+/// PUT `/api/v0/main-frontend/courses/{course_id}/xapi` - Opts the course in to or out of sending its
+/// learning events. Only the events that happen while the course is opted in are sent, and opting out
+/// does not remove the statements that were already sent.
+#[utoipa::path(
+    put,
+    path = "",
+    operation_id = "setCourseXapiOptIn",
+    tag = "course-xapi",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    request_body = CourseXapiOptInUpdate,
+    responses(
+        (status = 200, description = "The opt-in, if the course is opted in", body = Option<CourseXapiOptIn>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn set_xapi_opt_in(
+    course_id: web::Path<Uuid>,
+    payload: web::Json<CourseXapiOptInUpdate>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Option<CourseXapiOptIn>>> {
+    let course_id = course_id.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Course(course_id)).await?;
+    let res = if payload.enabled {
+        Some(models::course_xapi_opt_ins::opt_in(&mut conn, course_id, user.id).await?)
+    } else {
+        models::course_xapi_opt_ins::opt_out(&mut conn, course_id).await?;
+        None
+    };
+    token.authorized_ok(web::Json(res))
+}
+
+pub fn _add_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(get_xapi_status))
+        .route("", web::put().to(set_xapi_opt_in));
+}
@@ -6,6 +6,12 @@ use chrono::Utc;
 use headless_lms_models::user_exercise_states::UserExerciseState;
 use models::{
     course_exams,
+    deadline_exceptions::{
+        DeadlineException, DeadlineExceptionScope, DeadlineExceptionTarget, NewDeadlineException,
+    },
+    exam_accommodations::{ExamAccommodation, NewExamAccommodation},
+    exam_enrollment_events::{ExamAnomalyReport, ExamEnrollmentEvent},
+    exam_question_pools::{ExamQuestionPool, NewExamQuestionPool},
     exams::{self, Exam, NewExam},
     exercise_slide_submissions::{
         ExerciseSlideSubmissionAndUserExerciseState,
@@ -18,9 +24,12 @@ use models::{
 use utoipa::{OpenApi, ToSchema};
 
 use crate::{
-    domain::csv_export::{
-        general_export, points::ExamPointExportOperation,
-        submissions::ExamSubmissionExportOperation,
+    domain::{
+        csv_export::{
+            general_export, points::ExamPointExportOperation,
+            submissions::ExamSubmissionExportOperation,
+        },
+        exam_accommodations::{ExamAccommodationImport, ExamAccommodationImportResult},
     },
     prelude::*,
 };
@@ -37,7 +46,20 @@ use crate::{
     get_exercise_slide_submissions_and_user_exercise_states_with_exam_id,
     get_exercise_slide_submissions_and_user_exercise_states_with_exercise_id,
     release_grades,
-    get_exercises_with_exam_id
+    get_exercises_with_exam_id,
+    get_exam_deadline_exceptions,
+    grant_exam_deadline_exception,
+    revoke_exam_deadline_exception,
+    get_exam_anomaly_report,
+    get_exam_enrollment_events,
+    get_exam_question_pools,
+    create_exam_question_pool,
+    update_exam_question_pool,
+    delete_exam_question_pool,
+    get_exam_accommodations,
+    upsert_exam_accommodation,
+    delete_exam_accommodation,
+    import_exam_accommodations
 ))]
 pub(crate) struct MainFrontendExamsApiDoc;
 
@@ -539,6 +561,415 @@ async fn release_grades(
     token.authorized_ok(web::Json(()))
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct NewExamDeadlineException {
+    pub user_id: Uuid,
+    /// Replaces the exam's end time for this student.
+    pub deadline: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+/**
+GET `/api/v0/main-frontend/exams/:exam_id/deadline-exceptions` - Every deadline exception granted on the exam, revoked and replaced ones included, newest first.
+*/
+#[utoipa::path(
+    get,
+    path = "/{exam_id}/deadline-exceptions",
+    operation_id = "getExamDeadlineExceptions",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id")
+    ),
+    responses(
+        (status = 200, description = "Deadline exceptions of the exam", body = Vec<DeadlineException>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_exam_deadline_exceptions(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<DeadlineException>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(*exam_id)).await?;
+    let res = models::deadline_exceptions::get_history_for_exam(&mut conn, *exam_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+POST `/api/v0/main-frontend/exams/:exam_id/deadline-exceptions` - Gives an enrolled student an individual end time for the exam.
+*/
+#[utoipa::path(
+    post,
+    path = "/{exam_id}/deadline-exceptions",
+    operation_id = "grantExamDeadlineException",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id")
+    ),
+    request_body = NewExamDeadlineException,
+    responses(
+        (status = 200, description = "The granted deadline exception", body = DeadlineException)
+    )
+)]
+#[instrument(skip(pool))]
+async fn grant_exam_deadline_exception(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+    payload: web::Json<NewExamDeadlineException>,
+) -> ControllerResult<web::Json<DeadlineException>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(*exam_id)).await?;
+
+    if exams::get_enrollment(&mut conn, *exam_id, payload.user_id)
+        .await?
+        .is_none()
+    {
+        return Err(controller_err!(
+            BadRequest,
+            "The student is not enrolled in the exam.".to_string()
+        ));
+    }
+    let exception = models::deadline_exceptions::grant(
+        &mut conn,
+        None,
+        &NewDeadlineException {
+            target: DeadlineExceptionTarget::Exam(*exam_id),
+            scope: DeadlineExceptionScope::User(payload.user_id),
+            deadline: payload.deadline,
+            reason: payload.reason.clone(),
+        },
+        user.id,
+    )
+    .await?;
+    token.authorized_ok(web::Json(exception))
+}
+
+/**
+DELETE `/api/v0/main-frontend/exams/:exam_id/deadline-exceptions/:id` - Revokes a deadline exception, after which the exam's own end time applies to the student again.
+*/
+#[utoipa::path(
+    delete,
+    path = "/{exam_id}/deadline-exceptions/{id}",
+    operation_id = "revokeExamDeadlineException",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id"),
+        ("id" = Uuid, Path, description = "Deadline exception id")
+    ),
+    responses(
+        (status = 200, description = "Whether a live exception was revoked", body = bool)
+    )
+)]
+#[instrument(skip(pool))]
+async fn revoke_exam_deadline_exception(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<bool>> {
+    let (exam_id, id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(exam_id)).await?;
+
+    let exception = models::deadline_exceptions::get_by_id(&mut conn, id).await?;
+    if exception.exam_id != Some(exam_id) {
+        return Err(controller_err!(
+            NotFound,
+            "Deadline exception not found on this exam.".to_string()
+        ));
+    }
+    let revoked = models::deadline_exceptions::revoke(&mut conn, id, user.id).await?;
+    token.authorized_ok(web::Json(revoked))
+}
+
+/**
+GET `/api/v0/main-frontend/exams/:exam_id/anomaly-report` - The enrollments whose event logs show suspicious patterns, such as leaving the exam page often or taking the exam from several places at once. The report is for teachers to review; it has no effect on grading.
+*/
+#[utoipa::path(
+    get,
+    path = "/{exam_id}/anomaly-report",
+    operation_id = "getExamAnomalyReport",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id")
+    ),
+    responses(
+        (status = 200, description = "Anomaly report of the exam", body = ExamAnomalyReport)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_exam_anomaly_report(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<ExamAnomalyReport>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(*exam_id)).await?;
+    let res = models::exam_enrollment_events::get_anomaly_report(&mut conn, *exam_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+GET `/api/v0/main-frontend/exams/:exam_id/users/:user_id/events` - The event log of a student's exam, oldest first.
+*/
+#[utoipa::path(
+    get,
+    path = "/{exam_id}/users/{user_id}/events",
+    operation_id = "getExamEnrollmentEvents",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id"),
+        ("user_id" = Uuid, Path, description = "User id")
+    ),
+    responses(
+        (status = 200, description = "Events of the student's exam", body = Vec<ExamEnrollmentEvent>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_exam_enrollment_events(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<ExamEnrollmentEvent>>> {
+    let (exam_id, user_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(exam_id)).await?;
+    let res = models::exam_enrollment_events::get_by_exam_and_user_id(&mut conn, exam_id, user_id)
+        .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+GET `/api/v0/main-frontend/exams/:exam_id/question-pools` - The question pools of the exam.
+*/
+#[utoipa::path(
+    get,
+    path = "/{exam_id}/question-pools",
+    operation_id = "getExamQuestionPools",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id")
+    ),
+    responses(
+        (status = 200, description = "Question pools of the exam", body = Vec<ExamQuestionPool>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_exam_question_pools(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<ExamQuestionPool>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(*exam_id)).await?;
+    let res = models::exam_question_pools::get_by_exam_id(&mut conn, *exam_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+POST `/api/v0/main-frontend/exams/:exam_id/question-pools` - Creates a question pool. Pools can only be changed before students have enrolled in the exam.
+*/
+#[utoipa::path(
+    post,
+    path = "/{exam_id}/question-pools",
+    operation_id = "createExamQuestionPool",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id")
+    ),
+    request_body = NewExamQuestionPool,
+    responses(
+        (status = 200, description = "The created question pool", body = ExamQuestionPool)
+    )
+)]
+#[instrument(skip(pool))]
+async fn create_exam_question_pool(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+    payload: web::Json<NewExamQuestionPool>,
+) -> ControllerResult<web::Json<ExamQuestionPool>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Exam(*exam_id)).await?;
+    let res = models::exam_question_pools::insert(&mut conn, *exam_id, &payload).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+PUT `/api/v0/main-frontend/exams/:exam_id/question-pools/:id` - Replaces the name, draw count and exercises of a question pool.
+*/
+#[utoipa::path(
+    put,
+    path = "/{exam_id}/question-pools/{id}",
+    operation_id = "updateExamQuestionPool",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id"),
+        ("id" = Uuid, Path, description = "Question pool id")
+    ),
+    request_body = NewExamQuestionPool,
+    responses(
+        (status = 200, description = "The updated question pool", body = ExamQuestionPool)
+    )
+)]
+#[instrument(skip(pool))]
+async fn update_exam_question_pool(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user: AuthUser,
+    payload: web::Json<NewExamQuestionPool>,
+) -> ControllerResult<web::Json<ExamQuestionPool>> {
+    let (exam_id, id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Exam(exam_id)).await?;
+    let res = models::exam_question_pools::update(&mut conn, exam_id, id, &payload).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+DELETE `/api/v0/main-frontend/exams/:exam_id/question-pools/:id` - Deletes a question pool. Its exercises are then given to every student.
+*/
+#[utoipa::path(
+    delete,
+    path = "/{exam_id}/question-pools/{id}",
+    operation_id = "deleteExamQuestionPool",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id"),
+        ("id" = Uuid, Path, description = "Question pool id")
+    ),
+    responses(
+        (status = 200, description = "Question pool deleted", body = ())
+    )
+)]
+#[instrument(skip(pool))]
+async fn delete_exam_question_pool(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let (exam_id, id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Edit, Some(user.id), Res::Exam(exam_id)).await?;
+    models::exam_question_pools::delete(&mut conn, exam_id, id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/**
+GET `/api/v0/main-frontend/exams/:exam_id/accommodations` - The extra time and personal windows granted to students in the exam.
+*/
+#[utoipa::path(
+    get,
+    path = "/{exam_id}/accommodations",
+    operation_id = "getExamAccommodations",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id")
+    ),
+    responses(
+        (status = 200, description = "Accommodations of the exam", body = Vec<ExamAccommodation>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_exam_accommodations(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<ExamAccommodation>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(*exam_id)).await?;
+    let res = models::exam_accommodations::get_by_exam_id(&mut conn, *exam_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+POST `/api/v0/main-frontend/exams/:exam_id/accommodations` - Grants an accommodation to a student, replacing their current one.
+*/
+#[utoipa::path(
+    post,
+    path = "/{exam_id}/accommodations",
+    operation_id = "upsertExamAccommodation",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id")
+    ),
+    request_body = NewExamAccommodation,
+    responses(
+        (status = 200, description = "The granted accommodation", body = ExamAccommodation)
+    )
+)]
+#[instrument(skip(pool))]
+async fn upsert_exam_accommodation(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+    payload: web::Json<NewExamAccommodation>,
+) -> ControllerResult<web::Json<ExamAccommodation>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(*exam_id)).await?;
+    let res = models::exam_accommodations::upsert(&mut conn, *exam_id, &payload, user.id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+DELETE `/api/v0/main-frontend/exams/:exam_id/accommodations/:id` - Removes an accommodation. The student then has the exam's normal times.
+*/
+#[utoipa::path(
+    delete,
+    path = "/{exam_id}/accommodations/{id}",
+    operation_id = "deleteExamAccommodation",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id"),
+        ("id" = Uuid, Path, description = "Accommodation id")
+    ),
+    responses(
+        (status = 200, description = "Accommodation removed", body = ())
+    )
+)]
+#[instrument(skip(pool))]
+async fn delete_exam_accommodation(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let (exam_id, id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(exam_id)).await?;
+    models::exam_accommodations::delete(&mut conn, exam_id, id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/**
+POST `/api/v0/main-frontend/exams/:exam_id/accommodations/import` - Grants accommodations from a CSV file. Nothing is saved if any row is invalid; the invalid rows are returned instead.
+*/
+#[utoipa::path(
+    post,
+    path = "/{exam_id}/accommodations/import",
+    operation_id = "importExamAccommodations",
+    tag = "exams",
+    params(
+        ("exam_id" = Uuid, Path, description = "Exam id")
+    ),
+    request_body = ExamAccommodationImport,
+    responses(
+        (status = 200, description = "The imported accommodations or the row errors", body = ExamAccommodationImportResult)
+    )
+)]
+#[instrument(skip(pool, payload))]
+async fn import_exam_accommodations(
+    pool: web::Data<PgPool>,
+    exam_id: web::Path<Uuid>,
+    user: AuthUser,
+    payload: web::Json<ExamAccommodationImport>,
+) -> ControllerResult<web::Json<ExamAccommodationImportResult>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(*exam_id)).await?;
+    let res = domain::exam_accommodations::import(&mut conn, *exam_id, &payload, user.id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
 /**
 Add a route for each controller in this module.
 
@@ -569,5 +1000,57 @@ pub fn _add_routes(cfg: &mut ServiceConfig) {
         .route(
             "/{exam_id}/exam-exercises",
             web::get().to(get_exercises_with_exam_id),
+        )
+        .route(
+            "/{exam_id}/deadline-exceptions",
+            web::get().to(get_exam_deadline_exceptions),
+        )
+        .route(
+            "/{exam_id}/deadline-exceptions",
+            web::post().to(grant_exam_deadline_exception),
+        )
+        .route(
+            "/{exam_id}/deadline-exceptions/{id}",
+            web::delete().to(revoke_exam_deadline_exception),
+        )
+        .route(
+            "/{exam_id}/anomaly-report",
+            web::get().to(get_exam_anomaly_report),
+        )
+        .route(
+            "/{exam_id}/users/{user_id}/events",
+            web::get().to(get_exam_enrollment_events),
+        )
+        .route(
+            "/{exam_id}/question-pools",
+            web::get().to(get_exam_question_pools),
+        )
+        .route(
+            "/{exam_id}/question-pools",
+            web::post().to(create_exam_question_pool),
+        )
+        .route(
+            "/{exam_id}/question-pools/{id}",
+            web::put().to(update_exam_question_pool),
+        )
+        .route(
+            "/{exam_id}/question-pools/{id}",
+            web::delete().to(delete_exam_question_pool),
+        )
+        .route(
+            "/{exam_id}/accommodations",
+            web::get().to(get_exam_accommodations),
+        )
+        .route(
+            "/{exam_id}/accommodations",
+            web::post().to(upsert_exam_accommodation),
+        )
+        .route(
+            "/{exam_id}/accommodations/import",
+            web::post().to(import_exam_accommodations),
+        )
+        .route(
+            "/{exam_id}/accommodations/{id}",
+            web::delete().to(delete_exam_accommodation),
         );
 }
@@ -8,10 +8,19 @@ use url::Url;
 
 use headless_lms_models::exercises::Exercise;
 use models::{
-    exercise_service_info::ExerciseServiceInfoApi, exercise_services::ExerciseService,
+    exercise_service_info::ExerciseServiceInfoApi,
+    exercise_services::ExerciseService,
     exercise_slide_submissions::ExerciseSlideSubmission,
-    exercise_task_gradings::ExerciseTaskGrading, exercise_tasks::ExerciseTask,
+    exercise_task_gradings::ExerciseTaskGrading,
+    exercise_tasks::ExerciseTask,
+    grading_rubrics::{GradingRubricWithCriteria, NewGradingRubric},
+    late_submission_penalties::LateSubmissionPenalty,
     library::grading::AnswersRequiringAttention,
+    peer_review_calibration_answers::{
+        NewPeerReviewCalibrationAnswer, PeerReviewCalibrationAnswerWithReferenceScores,
+    },
+    peer_review_disputes::{PeerReviewDispute, PeerReviewDisputeResolution},
+    peer_review_queue_entries::PeerReviewQueueHealth,
 };
 use utoipa::{OpenApi, ToSchema};
 
@@ -28,6 +37,19 @@ const EXERCISE_SERVICE_CSV_EXPORT_BATCH_SIZE: usize = 1000;
     export_exercise_task_definitions_csv,
     export_exercise_task_answers_csv,
     get_exercise_answers_requiring_attention,
+    get_exercise_late_submission_penalties,
+    get_exercise_grading_rubric,
+    upsert_exercise_grading_rubric,
+    delete_exercise_grading_rubric,
+    get_exercise_chatbot_tutoring_enabled,
+    enable_exercise_chatbot_tutoring,
+    disable_exercise_chatbot_tutoring,
+    get_exercise_peer_review_calibration_answers,
+    insert_exercise_peer_review_calibration_answer,
+    delete_exercise_peer_review_calibration_answer,
+    get_exercise_peer_review_queue_health,
+    get_exercise_peer_review_disputes,
+    resolve_exercise_peer_review_dispute,
     get_exercises_by_course_id,
     reset_exercises_for_selected_users
 ))]
@@ -940,6 +962,495 @@ async fn get_exercise_answers_requiring_attention(
     token.authorized_ok(web::Json(res))
 }
 
+/**
+GET `/api/v0/main-frontend/exercises/:exercise_id/late-submission-penalties` - Returns the penalties of the submissions that were made to the exercise after the deadline, newest first.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/{exercise_id}/late-submission-penalties",
+    operation_id = "getExerciseLateSubmissionPenalties",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Late submission penalties", body = [LateSubmissionPenalty])
+    )
+)]
+async fn get_exercise_late_submission_penalties(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<LateSubmissionPenalty>>> {
+    let mut conn = pool.acquire().await?;
+    let token = match models::exercises::get_course_or_exam_id(&mut conn, *exercise_id).await? {
+        CourseOrExamId::Course(id) => {
+            authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(id)).await?
+        }
+        CourseOrExamId::Exam(id) => {
+            authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(id)).await?
+        }
+    };
+    let res =
+        models::late_submission_penalties::get_by_exercise_id(&mut conn, *exercise_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+GET `/api/v0/main-frontend/exercises/:exercise_id/grading-rubric` - Returns the rubric the answers of the exercise are graded with, if any.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/{exercise_id}/grading-rubric",
+    operation_id = "getExerciseGradingRubric",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Grading rubric of the exercise", body = Option<GradingRubricWithCriteria>)
+    )
+)]
+async fn get_exercise_grading_rubric(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Option<GradingRubricWithCriteria>>> {
+    let mut conn = pool.acquire().await?;
+    let token = match models::exercises::get_course_or_exam_id(&mut conn, *exercise_id).await? {
+        CourseOrExamId::Course(id) => {
+            authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(id)).await?
+        }
+        CourseOrExamId::Exam(id) => {
+            authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(id)).await?
+        }
+    };
+    let res = models::grading_rubrics::get_by_exercise_id(&mut conn, *exercise_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+PUT `/api/v0/main-frontend/exercises/:exercise_id/grading-rubric` - Sets the rubric the answers of the exercise are graded with. Replaces the criteria of an existing rubric; answers already graded keep the rubric they were graded with.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    put,
+    path = "/{exercise_id}/grading-rubric",
+    operation_id = "upsertExerciseGradingRubric",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    request_body = NewGradingRubric,
+    responses(
+        (status = 200, description = "The saved grading rubric", body = GradingRubricWithCriteria)
+    )
+)]
+async fn upsert_exercise_grading_rubric(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    payload: web::Json<NewGradingRubric>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<GradingRubricWithCriteria>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    let exercise = models::exercises::get_non_deleted_by_id(&mut conn, *exercise_id).await?;
+    let res = models::grading_rubrics::upsert_for_exercise(&mut conn, &exercise, &payload).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+DELETE `/api/v0/main-frontend/exercises/:exercise_id/grading-rubric` - Stops grading the exercise with a rubric.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    delete,
+    path = "/{exercise_id}/grading-rubric",
+    operation_id = "deleteExerciseGradingRubric",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Grading rubric deleted")
+    )
+)]
+async fn delete_exercise_grading_rubric(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    models::grading_rubrics::delete_for_exercise(&mut conn, *exercise_id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/**
+GET `/api/v0/main-frontend/exercises/:exercise_id/chatbot-tutoring` - Returns whether the course chatbot may tutor students on the exercise.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/{exercise_id}/chatbot-tutoring",
+    operation_id = "getExerciseChatbotTutoringEnabled",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Whether chatbot tutoring is enabled for the exercise", body = bool)
+    )
+)]
+async fn get_exercise_chatbot_tutoring_enabled(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<bool>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    let res = models::chatbot_tutoring_exercises::is_enabled_for_exercise(&mut conn, *exercise_id)
+        .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+PUT `/api/v0/main-frontend/exercises/:exercise_id/chatbot-tutoring` - Allows the course chatbot to tutor students on the exercise. The chatbot sees the assignment, the public spec and the student's own answers and feedback, but never the model solution.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    put,
+    path = "/{exercise_id}/chatbot-tutoring",
+    operation_id = "enableExerciseChatbotTutoring",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Chatbot tutoring enabled")
+    )
+)]
+async fn enable_exercise_chatbot_tutoring(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    let exercise = models::exercises::get_non_deleted_by_id(&mut conn, *exercise_id).await?;
+    if exercise.course_id.is_none() {
+        return Err(controller_err!(
+            BadRequest,
+            "Chatbot tutoring can only be enabled for course exercises.".to_string()
+        ));
+    }
+    models::chatbot_tutoring_exercises::enable_for_exercise(&mut conn, exercise.id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/**
+DELETE `/api/v0/main-frontend/exercises/:exercise_id/chatbot-tutoring` - Stops the course chatbot from tutoring students on the exercise.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    delete,
+    path = "/{exercise_id}/chatbot-tutoring",
+    operation_id = "disableExerciseChatbotTutoring",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Chatbot tutoring disabled")
+    )
+)]
+async fn disable_exercise_chatbot_tutoring(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    models::chatbot_tutoring_exercises::disable_for_exercise(&mut conn, *exercise_id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/**
+GET `/api/v0/main-frontend/exercises/:exercise_id/peer-review-calibration-answers` - Returns the teacher-scored answers students review before they can peer review their peers.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/{exercise_id}/peer-review-calibration-answers",
+    operation_id = "getExercisePeerReviewCalibrationAnswers",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Peer review calibration answers of the exercise", body = [PeerReviewCalibrationAnswerWithReferenceScores])
+    )
+)]
+async fn get_exercise_peer_review_calibration_answers(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<PeerReviewCalibrationAnswerWithReferenceScores>>> {
+    let mut conn = pool.acquire().await?;
+    let token = match models::exercises::get_course_or_exam_id(&mut conn, *exercise_id).await? {
+        CourseOrExamId::Course(id) => {
+            authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(id)).await?
+        }
+        CourseOrExamId::Exam(id) => {
+            authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(id)).await?
+        }
+    };
+    let res = models::peer_review_calibration_answers::get_by_exercise_id(&mut conn, *exercise_id)
+        .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+POST `/api/v0/main-frontend/exercises/:exercise_id/peer-review-calibration-answers` - Marks an answer to the exercise as a calibration answer and saves the teacher's scores for it.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    post,
+    path = "/{exercise_id}/peer-review-calibration-answers",
+    operation_id = "insertExercisePeerReviewCalibrationAnswer",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    request_body = NewPeerReviewCalibrationAnswer,
+    responses(
+        (status = 200, description = "The saved calibration answer", body = PeerReviewCalibrationAnswerWithReferenceScores)
+    )
+)]
+async fn insert_exercise_peer_review_calibration_answer(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    payload: web::Json<NewPeerReviewCalibrationAnswer>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<PeerReviewCalibrationAnswerWithReferenceScores>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Exercise(*exercise_id),
+    )
+    .await?;
+    let exercise = models::exercises::get_non_deleted_by_id(&mut conn, *exercise_id).await?;
+    let res =
+        models::peer_review_calibration_answers::insert(&mut conn, &exercise, &payload).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+DELETE `/api/v0/main-frontend/exercises/:exercise_id/peer-review-calibration-answers/:peer_review_calibration_answer_id` - Stops using the answer for calibration. Reliabilities already calculated from it are kept.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    delete,
+    path = "/{exercise_id}/peer-review-calibration-answers/{peer_review_calibration_answer_id}",
+    operation_id = "deleteExercisePeerReviewCalibrationAnswer",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id"),
+        ("peer_review_calibration_answer_id" = Uuid, Path, description = "Peer review calibration answer id")
+    ),
+    responses(
+        (status = 200, description = "Peer review calibration answer deleted")
+    )
+)]
+async fn delete_exercise_peer_review_calibration_answer(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let (exercise_id, peer_review_calibration_answer_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Exercise(exercise_id),
+    )
+    .await?;
+    models::peer_review_calibration_answers::delete(
+        &mut conn,
+        exercise_id,
+        peer_review_calibration_answer_id,
+    )
+    .await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/**
+GET `/api/v0/main-frontend/exercises/:exercise_id/peer-review-queue-health` - Returns how long answers have been waiting for peer reviews and which answers are starved.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/{exercise_id}/peer-review-queue-health",
+    operation_id = "getExercisePeerReviewQueueHealth",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id")
+    ),
+    responses(
+        (status = 200, description = "Peer review queue health of the exercise", body = PeerReviewQueueHealth)
+    )
+)]
+async fn get_exercise_peer_review_queue_health(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<PeerReviewQueueHealth>> {
+    let mut conn = pool.acquire().await?;
+    let exercise = models::exercises::get_non_deleted_by_id(&mut conn, *exercise_id).await?;
+    let course_id = exercise.get_course_id()?;
+    let token = authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(course_id)).await?;
+    let peer_or_self_review_config =
+        models::peer_or_self_review_configs::get_by_exercise_or_course_id(
+            &mut conn, &exercise, course_id,
+        )
+        .await?;
+    let res = models::peer_review_queue_entries::get_queue_health_by_exercise_id(
+        &mut conn,
+        exercise.id,
+        &peer_or_self_review_config,
+        Utc::now(),
+    )
+    .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PeerReviewDisputesQuery {
+    #[serde(default)]
+    pending_only: bool,
+}
+
+/**
+GET `/api/v0/main-frontend/exercises/:exercise_id/peer-review-disputes` - Returns the disputes students have filed against the peer reviews they have received. With `pending_only`, returns the disputes waiting for a teacher, oldest first.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/{exercise_id}/peer-review-disputes",
+    operation_id = "getExercisePeerReviewDisputes",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id"),
+        ("pending_only" = bool, Query, description = "Whether to return only the disputes waiting for a teacher")
+    ),
+    responses(
+        (status = 200, description = "Peer review disputes of the exercise", body = [PeerReviewDispute])
+    )
+)]
+async fn get_exercise_peer_review_disputes(
+    pool: web::Data<PgPool>,
+    exercise_id: web::Path<Uuid>,
+    query: web::Query<PeerReviewDisputesQuery>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<PeerReviewDispute>>> {
+    let mut conn = pool.acquire().await?;
+    let token = match models::exercises::get_course_or_exam_id(&mut conn, *exercise_id).await? {
+        CourseOrExamId::Course(id) => {
+            authorize(&mut conn, Act::Teach, Some(user.id), Res::Course(id)).await?
+        }
+        CourseOrExamId::Exam(id) => {
+            authorize(&mut conn, Act::Teach, Some(user.id), Res::Exam(id)).await?
+        }
+    };
+    let res = models::peer_review_disputes::get_by_exercise_id(
+        &mut conn,
+        *exercise_id,
+        query.pending_only,
+    )
+    .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+POST `/api/v0/main-frontend/exercises/:exercise_id/peer-review-disputes/:peer_review_dispute_id/resolve` - Resolves a dispute by discarding the review, overriding the grade of the answer or rejecting the dispute. The student is notified by email.
+ */
+#[instrument(skip(pool))]
+#[utoipa::path(
+    post,
+    path = "/{exercise_id}/peer-review-disputes/{peer_review_dispute_id}/resolve",
+    operation_id = "resolveExercisePeerReviewDispute",
+    tag = "exercises",
+    params(
+        ("exercise_id" = Uuid, Path, description = "Exercise id"),
+        ("peer_review_dispute_id" = Uuid, Path, description = "Peer review dispute id")
+    ),
+    request_body = PeerReviewDisputeResolution,
+    responses(
+        (status = 200, description = "The resolved dispute", body = PeerReviewDispute)
+    )
+)]
+async fn resolve_exercise_peer_review_dispute(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<PeerReviewDisputeResolution>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<PeerReviewDispute>> {
+    let (exercise_id, peer_review_dispute_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Exercise(exercise_id),
+    )
+    .await?;
+    let exercise = models::exercises::get_non_deleted_by_id(&mut conn, exercise_id).await?;
+    let res = models::library::peer_review_disputes::resolve_peer_review_dispute(
+        &mut conn,
+        &exercise,
+        peer_review_dispute_id,
+        user.id,
+        &payload,
+    )
+    .await?;
+    token.authorized_ok(web::Json(res))
+}
+
 /**
 GET `/api/v0/main-frontend/exercises/:course_id/exercises-by-course-id` - Returns all exercises for a course with course_id
  */
@@ -1087,6 +1598,58 @@ pub fn _add_routes(cfg: &mut ServiceConfig) {
         "/{exercise_id}/answers-requiring-attention",
         web::get().to(get_exercise_answers_requiring_attention),
     )
+    .route(
+        "/{exercise_id}/late-submission-penalties",
+        web::get().to(get_exercise_late_submission_penalties),
+    )
+    .route(
+        "/{exercise_id}/grading-rubric",
+        web::get().to(get_exercise_grading_rubric),
+    )
+    .route(
+        "/{exercise_id}/grading-rubric",
+        web::put().to(upsert_exercise_grading_rubric),
+    )
+    .route(
+        "/{exercise_id}/grading-rubric",
+        web::delete().to(delete_exercise_grading_rubric),
+    )
+    .route(
+        "/{exercise_id}/chatbot-tutoring",
+        web::get().to(get_exercise_chatbot_tutoring_enabled),
+    )
+    .route(
+        "/{exercise_id}/chatbot-tutoring",
+        web::put().to(enable_exercise_chatbot_tutoring),
+    )
+    .route(
+        "/{exercise_id}/chatbot-tutoring",
+        web::delete().to(disable_exercise_chatbot_tutoring),
+    )
+    .route(
+        "/{exercise_id}/peer-review-calibration-answers",
+        web::get().to(get_exercise_peer_review_calibration_answers),
+    )
+    .route(
+        "/{exercise_id}/peer-review-calibration-answers",
+        web::post().to(insert_exercise_peer_review_calibration_answer),
+    )
+    .route(
+        "/{exercise_id}/peer-review-calibration-answers/{peer_review_calibration_answer_id}",
+        web::delete().to(delete_exercise_peer_review_calibration_answer),
+    )
+    .route(
+        "/{exercise_id}/peer-review-queue-health",
+        web::get().to(get_exercise_peer_review_queue_health),
+    )
+    .route(
+        "/{exercise_id}/peer-review-disputes",
+        web::get().to(get_exercise_peer_review_disputes),
+    )
+    .route(
+        "/{exercise_id}/peer-review-disputes/{peer_review_dispute_id}/resolve",
+        web::post().to(resolve_exercise_peer_review_dispute),
+    )
     .route(
         "/{course_id}/exercises-by-course-id",
         web::get().to(get_exercises_by_course_id),
@@ -0,0 +1,569 @@
+//! Controllers for requests starting with `/api/v0/main-frontend/lti`.
+//!
+//! Registering LTI 1.3 platforms, the content selection of Deep Linking, and the resource links of a
+//! course. See [`crate::domain::lti`].
+
+use models::{
+    lti_deep_linking_requests::LtiDeepLinkingRequest,
+    lti_platforms::{LtiPlatform, NewLtiPlatform},
+    lti_resource_links::LtiResourceLinkSummary,
+};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    domain::lti::{
+        self, NRPS_SCOPE_MEMBERSHIP_READONLY,
+        deep_linking::{self, DeepLinkingResponse, SelectedContent},
+        services::{self, MembershipContainer},
+    },
+    prelude::*,
+};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_tool_configuration,
+    get_organization_platforms,
+    post_organization_platform,
+    put_platform,
+    delete_platform,
+    get_course_resource_links,
+    get_resource_link_members,
+    get_deep_linking_request,
+    get_deep_linking_course_content,
+    post_deep_linking_response
+))]
+pub(crate) struct MainFrontendLtiApiDoc;
+
+/// The values an admin enters on the platform when adding our tool to it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct LtiToolConfiguration {
+    pub login_url: String,
+    pub launch_url: String,
+    pub deep_linking_url: String,
+    pub jwks_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct DeepLinkingSelection {
+    pub request: LtiDeepLinkingRequest,
+    pub courses: Vec<DeepLinkingCourse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct DeepLinkingCourse {
+    pub id: Uuid,
+    pub name: String,
+    pub language_code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct DeepLinkingChapter {
+    pub id: Uuid,
+    pub name: String,
+    pub chapter_number: i32,
+    pub exercises: Vec<DeepLinkingExercise>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct DeepLinkingExercise {
+    pub id: Uuid,
+    pub name: String,
+    pub score_maximum: i32,
+}
+
+/// One picked piece of content. A whole course if neither a chapter nor an exercise is given.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct DeepLinkingItem {
+    pub course_id: Uuid,
+    pub chapter_id: Option<Uuid>,
+    pub exercise_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub struct DeepLinkingSelectionRequest {
+    pub items: Vec<DeepLinkingItem>,
+}
+
+/**
+GET `/api/v0/main-frontend/lti/tool-configuration` - The URLs of our tool to enter on a platform.
+*/
+#[instrument(skip(app_conf))]
+#[utoipa::path(
+    get,
+    path = "/tool-configuration",
+    operation_id = "getLtiToolConfiguration",
+    tag = "lti",
+    responses(
+        (status = 200, description = "LTI tool configuration", body = LtiToolConfiguration)
+    )
+)]
+async fn get_tool_configuration(
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<web::Json<LtiToolConfiguration>> {
+    let base_url = app_conf.base_url.trim_end_matches('/');
+    let token = skip_authorize();
+    token.authorized_ok(web::Json(LtiToolConfiguration {
+        login_url: format!("{base_url}/api/v0/lti/login"),
+        launch_url: lti::launch_url(base_url),
+        // Deep Linking launches come to the same endpoint, the message type tells them apart.
+        deep_linking_url: lti::launch_url(base_url),
+        jwks_url: format!("{base_url}/api/v0/main-frontend/oauth/jwks.json"),
+    }))
+}
+
+/**
+GET `/api/v0/main-frontend/lti/organizations/{organization_id}/platforms` - The platforms registered for the organization.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/organizations/{organization_id}/platforms",
+    operation_id = "getOrganizationLtiPlatforms",
+    tag = "lti",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id")
+    ),
+    responses(
+        (status = 200, description = "LTI platforms", body = [LtiPlatform])
+    )
+)]
+async fn get_organization_platforms(
+    organization_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<LtiPlatform>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(*organization_id),
+    )
+    .await?;
+    let platforms =
+        models::lti_platforms::get_by_organization_id(&mut conn, *organization_id).await?;
+    token.authorized_ok(web::Json(platforms))
+}
+
+/**
+POST `/api/v0/main-frontend/lti/organizations/{organization_id}/platforms` - Registers a platform for the organization.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    post,
+    path = "/organizations/{organization_id}/platforms",
+    operation_id = "createOrganizationLtiPlatform",
+    tag = "lti",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id")
+    ),
+    request_body = NewLtiPlatform,
+    responses(
+        (status = 200, description = "Registered LTI platform", body = LtiPlatform)
+    )
+)]
+async fn post_organization_platform(
+    organization_id: web::Path<Uuid>,
+    payload: web::Json<NewLtiPlatform>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<LtiPlatform>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(*organization_id),
+    )
+    .await?;
+    let platform = models::lti_platforms::insert(&mut conn, *organization_id, &payload).await?;
+    token.authorized_ok(web::Json(platform))
+}
+
+/**
+PUT `/api/v0/main-frontend/lti/platforms/{platform_id}` - Updates a platform.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    put,
+    path = "/platforms/{platform_id}",
+    operation_id = "updateLtiPlatform",
+    tag = "lti",
+    params(
+        ("platform_id" = Uuid, Path, description = "LTI platform id")
+    ),
+    request_body = NewLtiPlatform,
+    responses(
+        (status = 200, description = "Updated LTI platform", body = LtiPlatform)
+    )
+)]
+async fn put_platform(
+    platform_id: web::Path<Uuid>,
+    payload: web::Json<NewLtiPlatform>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<LtiPlatform>> {
+    let mut conn = pool.acquire().await?;
+    let platform = models::lti_platforms::get_by_id(&mut conn, *platform_id).await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(platform.organization_id),
+    )
+    .await?;
+    let platform = models::lti_platforms::update(&mut conn, platform.id, &payload).await?;
+    token.authorized_ok(web::Json(platform))
+}
+
+/**
+DELETE `/api/v0/main-frontend/lti/platforms/{platform_id}` - Deletes a platform. Its links stop working.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    delete,
+    path = "/platforms/{platform_id}",
+    operation_id = "deleteLtiPlatform",
+    tag = "lti",
+    params(
+        ("platform_id" = Uuid, Path, description = "LTI platform id")
+    ),
+    responses(
+        (status = 200, description = "LTI platform deleted")
+    )
+)]
+async fn delete_platform(
+    platform_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let mut conn = pool.acquire().await?;
+    let platform = models::lti_platforms::get_by_id(&mut conn, *platform_id).await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(platform.organization_id),
+    )
+    .await?;
+    models::lti_platforms::delete(&mut conn, platform.id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/**
+GET `/api/v0/main-frontend/lti/courses/{course_id}/resource-links` - The links on platforms that launch the course.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/courses/{course_id}/resource-links",
+    operation_id = "getCourseLtiResourceLinks",
+    tag = "lti",
+    params(
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    responses(
+        (status = 200, description = "LTI resource links", body = [LtiResourceLinkSummary])
+    )
+)]
+async fn get_course_resource_links(
+    course_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<LtiResourceLinkSummary>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::Course(*course_id),
+    )
+    .await?;
+    let links =
+        models::lti_resource_links::get_summaries_by_course_id(&mut conn, *course_id).await?;
+    token.authorized_ok(web::Json(links))
+}
+
+/**
+GET `/api/v0/main-frontend/lti/resource-links/{resource_link_id}/members` - The roster of the platform course the
+link is in, fetched from the platform with the Names and Role Provisioning Services.
+*/
+#[instrument(skip(pool, app_conf))]
+#[utoipa::path(
+    get,
+    path = "/resource-links/{resource_link_id}/members",
+    operation_id = "getLtiResourceLinkMembers",
+    tag = "lti",
+    params(
+        ("resource_link_id" = Uuid, Path, description = "LTI resource link id")
+    ),
+    responses(
+        (status = 200, description = "Members of the platform course", body = MembershipContainer)
+    )
+)]
+async fn get_resource_link_members(
+    resource_link_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<web::Json<MembershipContainer>> {
+    let mut conn = pool.acquire().await?;
+    let link = models::lti_resource_links::get_by_id(&mut conn, *resource_link_id).await?;
+    let token = authorize(
+        &mut conn,
+        Act::Teach,
+        Some(user.id),
+        Res::Course(link.course_id),
+    )
+    .await?;
+    let memberships_url = link.nrps_context_memberships_url.ok_or_else(|| {
+        controller_err!(
+            NotFound,
+            "The platform does not share the roster of this course.".to_string()
+        )
+    })?;
+    let platform = models::lti_platforms::get_by_id(&mut conn, link.lti_platform_id).await?;
+    let access_token =
+        services::get_access_token(&platform, &[NRPS_SCOPE_MEMBERSHIP_READONLY], &app_conf).await?;
+    let memberships = services::get_memberships(&memberships_url, &access_token).await?;
+    token.authorized_ok(web::Json(memberships))
+}
+
+/**
+GET `/api/v0/main-frontend/lti/deep-linking/{request_id}` - A content selection started on a platform, with the
+courses the teacher can pick content from.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/deep-linking/{request_id}",
+    operation_id = "getLtiDeepLinkingRequest",
+    tag = "lti",
+    params(
+        ("request_id" = Uuid, Path, description = "Deep linking request id")
+    ),
+    responses(
+        (status = 200, description = "Deep linking request and the selectable courses", body = DeepLinkingSelection)
+    )
+)]
+async fn get_deep_linking_request(
+    request_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<DeepLinkingSelection>> {
+    let mut conn = pool.acquire().await?;
+    // Only the teacher who started the selection on the platform can see it.
+    let request =
+        models::lti_deep_linking_requests::get_open_by_id(&mut conn, *request_id, user.id).await?;
+    let platform = models::lti_platforms::get_by_id(&mut conn, request.lti_platform_id).await?;
+    let courses = models::courses::get_by_organization_id(&mut conn, platform.organization_id)
+        .await?
+        .into_iter()
+        .filter(|course| !course.is_draft)
+        .map(|course| DeepLinkingCourse {
+            id: course.id,
+            name: course.name,
+            language_code: course.language_code,
+        })
+        .collect();
+    let token = skip_authorize();
+    token.authorized_ok(web::Json(DeepLinkingSelection { request, courses }))
+}
+
+/**
+GET `/api/v0/main-frontend/lti/deep-linking/{request_id}/courses/{course_id}` - The chapters and exercises of a
+course that can be picked.
+*/
+#[instrument(skip(pool))]
+#[utoipa::path(
+    get,
+    path = "/deep-linking/{request_id}/courses/{course_id}",
+    operation_id = "getLtiDeepLinkingCourseContent",
+    tag = "lti",
+    params(
+        ("request_id" = Uuid, Path, description = "Deep linking request id"),
+        ("course_id" = Uuid, Path, description = "Course id")
+    ),
+    responses(
+        (status = 200, description = "Chapters and exercises of the course", body = [DeepLinkingChapter])
+    )
+)]
+async fn get_deep_linking_course_content(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<DeepLinkingChapter>>> {
+    let (request_id, course_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let request =
+        models::lti_deep_linking_requests::get_open_by_id(&mut conn, request_id, user.id).await?;
+    let platform = models::lti_platforms::get_by_id(&mut conn, request.lti_platform_id).await?;
+    let course = get_selectable_course(&mut conn, &platform, course_id).await?;
+
+    let mut chapters = models::chapters::get_course_chapters(&mut conn, course.id).await?;
+    chapters.sort_by_key(|chapter| chapter.chapter_number);
+    let mut exercises = models::exercises::get_exercises_by_course_id(&mut conn, course.id).await?;
+    exercises.sort_by_key(|exercise| exercise.order_number);
+    let res = chapters
+        .into_iter()
+        .map(|chapter| DeepLinkingChapter {
+            exercises: exercises
+                .iter()
+                .filter(|exercise| exercise.chapter_id == Some(chapter.id))
+                .map(|exercise| DeepLinkingExercise {
+                    id: exercise.id,
+                    name: exercise.name.clone(),
+                    score_maximum: exercise.score_maximum,
+                })
+                .collect(),
+            id: chapter.id,
+            name: chapter.name,
+            chapter_number: chapter.chapter_number,
+        })
+        .collect();
+    let token = skip_authorize();
+    token.authorized_ok(web::Json(res))
+}
+
+/**
+POST `/api/v0/main-frontend/lti/deep-linking/{request_id}` - Completes a content selection. Returns the signed
+response, which the frontend posts to the platform in a form field named `JWT`.
+*/
+#[instrument(skip(pool, app_conf))]
+#[utoipa::path(
+    post,
+    path = "/deep-linking/{request_id}",
+    operation_id = "postLtiDeepLinkingResponse",
+    tag = "lti",
+    params(
+        ("request_id" = Uuid, Path, description = "Deep linking request id")
+    ),
+    request_body = DeepLinkingSelectionRequest,
+    responses(
+        (status = 200, description = "Signed deep linking response", body = DeepLinkingResponse)
+    )
+)]
+async fn post_deep_linking_response(
+    request_id: web::Path<Uuid>,
+    payload: web::Json<DeepLinkingSelectionRequest>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<web::Json<DeepLinkingResponse>> {
+    let mut conn = pool.acquire().await?;
+    let mut tx = conn.begin().await?;
+    let request =
+        models::lti_deep_linking_requests::get_open_by_id(&mut tx, *request_id, user.id).await?;
+    let platform = models::lti_platforms::get_by_id(&mut tx, request.lti_platform_id).await?;
+    let mut selected = Vec::with_capacity(payload.items.len());
+    for item in &payload.items {
+        selected.push(selected_content(&mut tx, &platform, item).await?);
+    }
+    let response = deep_linking::build_response(&platform, &request, &selected, &app_conf)?;
+    models::lti_deep_linking_requests::mark_used(&mut tx, request.id).await?;
+    tx.commit().await?;
+    let token = skip_authorize();
+    token.authorized_ok(web::Json(response))
+}
+
+/// Platforms can only link to the published courses of their organization.
+async fn get_selectable_course(
+    conn: &mut PgConnection,
+    platform: &LtiPlatform,
+    course_id: Uuid,
+) -> ControllerResult<models::courses::Course> {
+    let course = models::courses::get_course(conn, course_id).await?;
+    if course.organization_id != platform.organization_id || course.is_draft {
+        return Err(controller_err!(
+            NotFound,
+            "The course cannot be linked from this platform.".to_string()
+        ));
+    }
+    Ok(course)
+}
+
+async fn selected_content(
+    conn: &mut PgConnection,
+    platform: &LtiPlatform,
+    item: &DeepLinkingItem,
+) -> ControllerResult<SelectedContent> {
+    let course = get_selectable_course(conn, platform, item.course_id).await?;
+    if let Some(exercise_id) = item.exercise_id {
+        let exercise = models::exercises::get_by_id(conn, exercise_id).await?;
+        if exercise.course_id != Some(course.id) {
+            return Err(controller_err!(
+                BadRequest,
+                "The exercise is not in the course.".to_string()
+            ));
+        }
+        return Ok(SelectedContent {
+            title: exercise.name,
+            course_id: course.id,
+            chapter_id: exercise.chapter_id,
+            exercise_id: Some(exercise.id),
+            score_maximum: exercise.score_maximum,
+        });
+    }
+    if let Some(chapter_id) = item.chapter_id {
+        let chapter = models::chapters::get_chapter(conn, chapter_id).await?;
+        if chapter.course_id != course.id {
+            return Err(controller_err!(
+                BadRequest,
+                "The chapter is not in the course.".to_string()
+            ));
+        }
+        let exercises = models::exercises::get_exercises_by_chapter_id(conn, chapter.id).await?;
+        return Ok(SelectedContent {
+            title: format!("{}: {}", course.name, chapter.name),
+            course_id: course.id,
+            chapter_id: Some(chapter.id),
+            exercise_id: None,
+            score_maximum: exercises.iter().map(|e| e.score_maximum).sum(),
+        });
+    }
+    let exercises = models::exercises::get_exercises_by_course_id(conn, course.id).await?;
+    Ok(SelectedContent {
+        title: course.name,
+        course_id: course.id,
+        chapter_id: None,
+        exercise_id: None,
+        score_maximum: exercises.iter().map(|e| e.score_maximum).sum(),
+    })
+}
+
+pub fn _add_routes(cfg: &mut ServiceConfig) {
+    cfg.route("/tool-configuration", web::get().to(get_tool_configuration))
+        .route(
+            "/organizations/{organization_id}/platforms",
+            web::get().to(get_organization_platforms),
+        )
+        .route(
+            "/organizations/{organization_id}/platforms",
+            web::post().to(post_organization_platform),
+        )
+        .route("/platforms/{platform_id}", web::put().to(put_platform))
+        .route(
+            "/platforms/{platform_id}",
+            web::delete().to(delete_platform),
+        )
+        .route(
+            "/courses/{course_id}/resource-links",
+            web::get().to(get_course_resource_links),
+        )
+        .route(
+            "/resource-links/{resource_link_id}/members",
+            web::get().to(get_resource_link_members),
+        )
+        .route(
+            "/deep-linking/{request_id}",
+            web::get().to(get_deep_linking_request),
+        )
+        .route(
+            "/deep-linking/{request_id}",
+            web::post().to(post_deep_linking_response),
+        )
+        .route(
+            "/deep-linking/{request_id}/courses/{course_id}",
+            web::get().to(get_deep_linking_course_content),
+        );
+}
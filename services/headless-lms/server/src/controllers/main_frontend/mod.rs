@@ -10,6 +10,7 @@ pub mod chapters;
 pub mod chatbot_models;
 pub mod chatbots;
 pub mod code_giveaways;
+pub mod course_archives;
 pub mod course_credit_registrations;
 pub mod course_designer;
 pub mod course_instances;
@@ -27,6 +28,7 @@ pub mod exercises;
 pub mod feedback;
 pub mod global_stats;
 pub mod glossary;
+pub mod lti;
 pub mod oauth;
 pub mod org;
 pub mod organizations;
@@ -55,6 +57,7 @@ use utoipa::OpenApi;
         (path = "/chatbot-models", api = chatbot_models::MainFrontendChatbotModelsApiDoc),
         (path = "/chatbots", api = chatbots::MainFrontendChatbotsApiDoc),
         (path = "/code-giveaways", api = code_giveaways::MainFrontendCodeGiveawaysApiDoc),
+        (path = "/course-archives", api = course_archives::MainFrontendCourseArchivesApiDoc),
         (path = "/course-credit-registrations", api = course_credit_registrations::MainFrontendCourseCreditRegistrationsApiDoc),
         (path = "/course-plans", api = course_designer::MainFrontendCourseDesignerApiDoc),
         (path = "/course-instances", api = course_instances::MainFrontendCourseInstancesApiDoc),
@@ -72,6 +75,7 @@ use utoipa::OpenApi;
         (path = "/feedback", api = feedback::MainFrontendFeedbackApiDoc),
         (path = "/global-stats", api = global_stats::MainFrontendGlobalStatsApiDoc),
         (path = "/glossary", api = glossary::MainFrontendGlossaryApiDoc),
+        (path = "/lti", api = lti::MainFrontendLtiApiDoc),
         (path = "/oauth", api = oauth::MainFrontendOauthApiDoc),
         (path = "/org", api = org::MainFrontendOrgApiDoc),
         (path = "/organizations", api = organizations::MainFrontendOrganizationsApiDoc),
@@ -95,6 +99,7 @@ pub struct MainFrontendRoutesApiDoc;
 /// Add controllers from all the submodules.
 pub fn _add_routes(cfg: &mut ServiceConfig) {
     cfg.service(web::scope("/chapters").configure(chapters::_add_routes))
+        .service(web::scope("/course-archives").configure(course_archives::_add_routes))
         .service(
             web::scope("/course-credit-registrations")
                 .configure(course_credit_registrations::_add_routes),
@@ -138,6 +143,7 @@ pub fn _add_routes(cfg: &mut ServiceConfig) {
                 .configure(teacher_grading_decisions::_add_routes),
         )
         .service(web::scope("/code-giveaways").configure(code_giveaways::_add_routes))
+        .service(web::scope("/lti").configure(lti::_add_routes))
         .service(web::scope("/oauth").configure(oauth::_add_routes))
         .service(web::scope("/chatbots").configure(chatbots::_add_routes))
         .service(web::scope("/chatbot-models").configure(chatbot_models::_add_routes))
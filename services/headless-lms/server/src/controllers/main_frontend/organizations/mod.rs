@@ -1,5 +1,7 @@
 //! Controllers for requests starting with `/api/v0/main-frontend/organizations`.
 
+pub mod webhooks;
+
 use std::{path::PathBuf, str::FromStr};
 
 use models::{
@@ -23,24 +25,29 @@ use actix_web::web::{self, Json};
 use utoipa::{OpenApi, ToSchema};
 
 #[derive(OpenApi)]
-#[openapi(paths(
-    get_all_organizations,
-    create_organization,
-    get_organization,
-    update_organization,
-    soft_delete_organization,
-    get_organization_courses,
-    get_organization_duplicatable_courses,
-    get_organization_course_count,
-    get_organization_active_courses,
-    get_organization_active_courses_count,
-    set_organization_image,
-    remove_organization_image,
-    get_course_exams,
-    get_org_exams,
-    get_org_exam_with_exam_id,
-    create_exam
-))]
+#[openapi(
+    paths(
+        get_all_organizations,
+        create_organization,
+        get_organization,
+        update_organization,
+        soft_delete_organization,
+        get_organization_courses,
+        get_organization_duplicatable_courses,
+        get_organization_course_count,
+        get_organization_active_courses,
+        get_organization_active_courses_count,
+        set_organization_image,
+        remove_organization_image,
+        get_course_exams,
+        get_org_exams,
+        get_org_exam_with_exam_id,
+        create_exam
+    ),
+    nest(
+        (path = "/{organization_id}/webhooks", api = webhooks::MainFrontendOrganizationWebhooksApiDoc)
+    )
+)]
 pub(crate) struct MainFrontendOrganizationsApiDoc;
 
 #[allow(dead_code)]
@@ -806,7 +813,8 @@ The name starts with an underline in order to appear before other functions in t
 We add the routes by calling the route method instead of using the route annotations because this method preserves the function signatures for documentation.
 */
 pub fn _add_routes(cfg: &mut ServiceConfig) {
-    cfg.route("", web::get().to(get_all_organizations))
+    cfg.service(web::scope("/{organization_id}/webhooks").configure(webhooks::_add_routes))
+        .route("", web::get().to(get_all_organizations))
         .route("", web::post().to(create_organization))
         .route("/{organization_id}", web::get().to(get_organization))
         .route("/{organization_id}", web::put().to(update_organization))
@@ -0,0 +1,463 @@
+//! Controllers for requests starting with `/api/v0/main-frontend/organizations/{organization_id}/webhooks`.
+use crate::{
+    domain::webhooks::{address, signing},
+    prelude::*,
+};
+
+use models::{
+    webhook_deliveries::{WebhookDelivery, WebhookDeliveryStatus},
+    webhook_subscriptions::{NewWebhookSubscription, WebhookSubscription},
+};
+use url::Url;
+use utoipa::{OpenApi, ToSchema};
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_webhook_subscriptions,
+    create_webhook_subscription,
+    update_webhook_subscription,
+    delete_webhook_subscription,
+    rotate_webhook_subscription_secret,
+    get_webhook_deliveries,
+    get_dead_webhook_deliveries,
+    redeliver_webhook_delivery
+))]
+pub(crate) struct MainFrontendOrganizationWebhooksApiDoc;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookSubscriptionWithSecret {
+    pub subscription: WebhookSubscription,
+    /// The key the deliveries are signed with. Shown only once.
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookSecret {
+    /// The key the deliveries are signed with. Shown only once.
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebhookDeliveriesQuery {
+    pub status: Option<WebhookDeliveryStatus>,
+}
+
+/// GET `/api/v0/main-frontend/organizations/{organization_id}/webhooks` - The webhook subscriptions of
+/// the organization.
+#[utoipa::path(
+    get,
+    path = "",
+    operation_id = "getOrganizationWebhookSubscriptions",
+    tag = "organization-webhooks",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id")
+    ),
+    responses(
+        (status = 200, description = "Webhook subscriptions", body = Vec<WebhookSubscription>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_webhook_subscriptions(
+    organization_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<WebhookSubscription>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(*organization_id),
+    )
+    .await?;
+    let res =
+        models::webhook_subscriptions::get_by_organization_id(&mut conn, *organization_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// POST `/api/v0/main-frontend/organizations/{organization_id}/webhooks` - Subscribes an endpoint to
+/// events of the organization, or of one of its courses. The response contains the signing secret,
+/// which is not shown again.
+#[utoipa::path(
+    post,
+    path = "",
+    operation_id = "createOrganizationWebhookSubscription",
+    tag = "organization-webhooks",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id")
+    ),
+    request_body = NewWebhookSubscription,
+    responses(
+        (status = 200, description = "The created subscription and its secret", body = WebhookSubscriptionWithSecret)
+    )
+)]
+#[instrument(skip(pool, app_conf, payload))]
+async fn create_webhook_subscription(
+    organization_id: web::Path<Uuid>,
+    payload: web::Json<NewWebhookSubscription>,
+    pool: web::Data<PgPool>,
+    app_conf: web::Data<ApplicationConfiguration>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<WebhookSubscriptionWithSecret>> {
+    let organization_id = organization_id.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(organization_id),
+    )
+    .await?;
+
+    let new =
+        validate_subscription(&mut conn, organization_id, payload.into_inner(), &app_conf).await?;
+    let secret = signing::generate_secret();
+    let subscription =
+        models::webhook_subscriptions::insert(&mut conn, organization_id, &new, &secret, user.id)
+            .await?;
+    token.authorized_ok(web::Json(WebhookSubscriptionWithSecret {
+        subscription,
+        secret,
+    }))
+}
+
+/// PUT `/api/v0/main-frontend/organizations/{organization_id}/webhooks/{subscription_id}` - Updates
+/// the subscription. The deliveries already in the outbox are still sent to the new URL.
+#[utoipa::path(
+    put,
+    path = "/{subscription_id}",
+    operation_id = "updateOrganizationWebhookSubscription",
+    tag = "organization-webhooks",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id"),
+        ("subscription_id" = Uuid, Path, description = "Webhook subscription id")
+    ),
+    request_body = NewWebhookSubscription,
+    responses(
+        (status = 200, description = "The updated subscription", body = WebhookSubscription)
+    )
+)]
+#[instrument(skip(pool, app_conf, payload))]
+async fn update_webhook_subscription(
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<NewWebhookSubscription>,
+    pool: web::Data<PgPool>,
+    app_conf: web::Data<ApplicationConfiguration>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<WebhookSubscription>> {
+    let (organization_id, subscription_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(organization_id),
+    )
+    .await?;
+
+    get_subscription_of_organization(&mut conn, organization_id, subscription_id).await?;
+    let new =
+        validate_subscription(&mut conn, organization_id, payload.into_inner(), &app_conf).await?;
+    let subscription =
+        models::webhook_subscriptions::update(&mut conn, subscription_id, &new).await?;
+    token.authorized_ok(web::Json(subscription))
+}
+
+/// DELETE `/api/v0/main-frontend/organizations/{organization_id}/webhooks/{subscription_id}` - Deletes
+/// the subscription. Its pending deliveries are not sent.
+#[utoipa::path(
+    delete,
+    path = "/{subscription_id}",
+    operation_id = "deleteOrganizationWebhookSubscription",
+    tag = "organization-webhooks",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id"),
+        ("subscription_id" = Uuid, Path, description = "Webhook subscription id")
+    ),
+    responses(
+        (status = 200, description = "Webhook subscription deleted")
+    )
+)]
+#[instrument(skip(pool))]
+async fn delete_webhook_subscription(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<()>> {
+    let (organization_id, subscription_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(organization_id),
+    )
+    .await?;
+
+    get_subscription_of_organization(&mut conn, organization_id, subscription_id).await?;
+    models::webhook_subscriptions::delete(&mut conn, subscription_id).await?;
+    token.authorized_ok(web::Json(()))
+}
+
+/// POST `/api/v0/main-frontend/organizations/{organization_id}/webhooks/{subscription_id}/rotate-secret` -
+/// Replaces the signing secret, for example if it has leaked. The response contains the new secret,
+/// which is not shown again.
+#[utoipa::path(
+    post,
+    path = "/{subscription_id}/rotate-secret",
+    operation_id = "rotateOrganizationWebhookSubscriptionSecret",
+    tag = "organization-webhooks",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id"),
+        ("subscription_id" = Uuid, Path, description = "Webhook subscription id")
+    ),
+    responses(
+        (status = 200, description = "The new secret", body = WebhookSecret)
+    )
+)]
+#[instrument(skip(pool))]
+async fn rotate_webhook_subscription_secret(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<WebhookSecret>> {
+    let (organization_id, subscription_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(organization_id),
+    )
+    .await?;
+
+    get_subscription_of_organization(&mut conn, organization_id, subscription_id).await?;
+    let secret = signing::generate_secret();
+    models::webhook_subscriptions::rotate_secret(&mut conn, subscription_id, &secret).await?;
+    token.authorized_ok(web::Json(WebhookSecret { secret }))
+}
+
+/// GET `/api/v0/main-frontend/organizations/{organization_id}/webhooks/{subscription_id}/deliveries` -
+/// The delivery log of the subscription, newest first.
+#[utoipa::path(
+    get,
+    path = "/{subscription_id}/deliveries",
+    operation_id = "getOrganizationWebhookDeliveries",
+    tag = "organization-webhooks",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id"),
+        ("subscription_id" = Uuid, Path, description = "Webhook subscription id"),
+        ("status" = Option<WebhookDeliveryStatus>, Query, description = "Only the deliveries with this status"),
+        ("page" = Option<i64>, Query, description = "Page number"),
+        ("limit" = Option<i64>, Query, description = "Page size")
+    ),
+    responses(
+        (status = 200, description = "Webhook deliveries", body = Vec<WebhookDelivery>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_webhook_deliveries(
+    path: web::Path<(Uuid, Uuid)>,
+    query: web::Query<WebhookDeliveriesQuery>,
+    pagination: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<WebhookDelivery>>> {
+    let (organization_id, subscription_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(organization_id),
+    )
+    .await?;
+
+    get_subscription_of_organization(&mut conn, organization_id, subscription_id).await?;
+    let res = models::webhook_deliveries::get_by_subscription_id(
+        &mut conn,
+        subscription_id,
+        query.status,
+        *pagination,
+    )
+    .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// GET `/api/v0/main-frontend/organizations/{organization_id}/webhooks/dead-letters` - The deliveries
+/// of all the subscriptions of the organization that are no longer attempted, newest first.
+#[utoipa::path(
+    get,
+    path = "/dead-letters",
+    operation_id = "getOrganizationDeadWebhookDeliveries",
+    tag = "organization-webhooks",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id"),
+        ("page" = Option<i64>, Query, description = "Page number"),
+        ("limit" = Option<i64>, Query, description = "Page size")
+    ),
+    responses(
+        (status = 200, description = "Dead webhook deliveries", body = Vec<WebhookDelivery>)
+    )
+)]
+#[instrument(skip(pool))]
+async fn get_dead_webhook_deliveries(
+    organization_id: web::Path<Uuid>,
+    pagination: web::Query<Pagination>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<Vec<WebhookDelivery>>> {
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(*organization_id),
+    )
+    .await?;
+
+    let res = models::webhook_deliveries::get_dead_by_organization_id(
+        &mut conn,
+        *organization_id,
+        *pagination,
+    )
+    .await?;
+    token.authorized_ok(web::Json(res))
+}
+
+/// POST `/api/v0/main-frontend/organizations/{organization_id}/webhooks/deliveries/{delivery_id}/redeliver` -
+/// Moves a dead delivery back to the outbox. It is sent again with the same event id.
+#[utoipa::path(
+    post,
+    path = "/deliveries/{delivery_id}/redeliver",
+    operation_id = "redeliverOrganizationWebhookDelivery",
+    tag = "organization-webhooks",
+    params(
+        ("organization_id" = Uuid, Path, description = "Organization id"),
+        ("delivery_id" = Uuid, Path, description = "Webhook delivery id")
+    ),
+    responses(
+        (status = 200, description = "The delivery back in the outbox", body = WebhookDelivery)
+    )
+)]
+#[instrument(skip(pool))]
+async fn redeliver_webhook_delivery(
+    path: web::Path<(Uuid, Uuid)>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<WebhookDelivery>> {
+    let (organization_id, delivery_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Organization(organization_id),
+    )
+    .await?;
+
+    let delivery = models::webhook_deliveries::get_by_id(&mut conn, delivery_id).await?;
+    get_subscription_of_organization(&mut conn, organization_id, delivery.subscription_id).await?;
+    let res = models::webhook_deliveries::redeliver(&mut conn, delivery_id).await?;
+    token.authorized_ok(web::Json(res))
+}
+
+async fn get_subscription_of_organization(
+    conn: &mut PgConnection,
+    organization_id: Uuid,
+    subscription_id: Uuid,
+) -> ControllerResult<WebhookSubscription> {
+    let subscription = models::webhook_subscriptions::get_by_id(conn, subscription_id).await?;
+    if subscription.organization_id != organization_id {
+        return Err(controller_err!(
+            NotFound,
+            "Webhook subscription not found on this organization.".to_string()
+        ));
+    }
+    Ok(subscription)
+}
+
+/// Checks that the course belongs to the organization and the URL can be posted to, and removes
+/// duplicate event types. Outside the test mode the URL must resolve to public addresses only.
+async fn validate_subscription(
+    conn: &mut PgConnection,
+    organization_id: Uuid,
+    mut new: NewWebhookSubscription,
+    app_conf: &ApplicationConfiguration,
+) -> ControllerResult<NewWebhookSubscription> {
+    if let Some(course_id) = new.course_id {
+        let course = models::courses::get_course(conn, course_id).await?;
+        if course.organization_id != organization_id {
+            return Err(controller_err!(
+                BadRequest,
+                "The course does not belong to the organization.".to_string()
+            ));
+        }
+    }
+    let mut event_types = Vec::with_capacity(new.event_types.len());
+    for event_type in new.event_types {
+        if !event_types.contains(&event_type) {
+            event_types.push(event_type);
+        }
+    }
+    new.event_types = event_types;
+    if new.event_types.is_empty() {
+        return Err(controller_err!(
+            BadRequest,
+            "At least one event type is required.".to_string()
+        ));
+    }
+    new.url = new.url.trim().to_string();
+    let url = Url::parse(&new.url).map_err(|err| {
+        controller_err!(
+            BadRequest,
+            format!("Invalid webhook URL: {err}"),
+            anyhow::Error::from(err)
+        )
+    })?;
+    // Plain HTTP is only allowed for the receivers the tests run locally.
+    let allowed_scheme = url.scheme() == "https" || (app_conf.test_mode && url.scheme() == "http");
+    if !allowed_scheme || url.host_str().is_none() {
+        return Err(controller_err!(
+            BadRequest,
+            "The webhook URL must be an https URL.".to_string()
+        ));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(controller_err!(
+            BadRequest,
+            "The webhook URL must not contain credentials.".to_string()
+        ));
+    }
+    // The test receivers run locally. The address is checked again before every delivery.
+    address::resolve(&url, app_conf.test_mode)
+        .await
+        .map_err(|err| controller_err!(BadRequest, err.to_string()))?;
+    Ok(new)
+}
+
+pub fn _add_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("", web::get().to(get_webhook_subscriptions))
+        .route("", web::post().to(create_webhook_subscription))
+        .route("/dead-letters", web::get().to(get_dead_webhook_deliveries))
+        .route(
+            "/deliveries/{delivery_id}/redeliver",
+            web::post().to(redeliver_webhook_delivery),
+        )
+        .route(
+            "/{subscription_id}",
+            web::put().to(update_webhook_subscription),
+        )
+        .route(
+            "/{subscription_id}",
+            web::delete().to(delete_webhook_subscription),
+        )
+        .route(
+            "/{subscription_id}/rotate-secret",
+            web::post().to(rotate_webhook_subscription_secret),
+        )
+        .route(
+            "/{subscription_id}/deliveries",
+            web::get().to(get_webhook_deliveries),
+        );
+}
@@ -1,12 +1,16 @@
 use crate::prelude::*;
 use headless_lms_models::{
+    grading_rubric_assessments::NewRubricTeacherGradingDecision,
     teacher_grading_decisions::{NewTeacherGradingDecision, TeacherDecisionType},
     user_exercise_states::UserExerciseState,
 };
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
-#[openapi(paths(create_teacher_grading_decision))]
+#[openapi(paths(
+    create_teacher_grading_decision,
+    create_rubric_teacher_grading_decision
+))]
 pub(crate) struct MainFrontendTeacherGradingDecisionsApiDoc;
 
 /**
@@ -151,6 +155,119 @@ async fn create_teacher_grading_decision(
     token.authorized_ok(web::Json(Some(new_user_exercise_state)))
 }
 
+/**
+POST `/api/v0/main-frontend/teacher-grading-decisions/rubric` - Grades an answer with the rubric of the exercise. The points of the picked levels override the points the user has received from the exercise.
+*/
+#[utoipa::path(
+    post,
+    path = "/rubric",
+    operation_id = "createRubricTeacherGradingDecision",
+    tag = "teacher_grading_decisions",
+    request_body = NewRubricTeacherGradingDecision,
+    responses(
+        (status = 200, description = "Rubric grading decision created", body = UserExerciseState)
+    )
+)]
+#[instrument(skip(pool))]
+async fn create_rubric_teacher_grading_decision(
+    payload: web::Json<NewRubricTeacherGradingDecision>,
+    pool: web::Data<PgPool>,
+    user: AuthUser,
+) -> ControllerResult<web::Json<UserExerciseState>> {
+    let mut conn = pool.acquire().await?;
+
+    let student_state =
+        models::user_exercise_states::get_by_id(&mut conn, payload.user_exercise_state_id).await?;
+    if student_state.exercise_id != payload.exercise_id {
+        return Err(controller_err!(
+            Forbidden,
+            "User exercise state does not belong to the requested exercise".to_string()
+        ));
+    }
+    let exercise =
+        models::exercises::get_non_deleted_by_id(&mut conn, student_state.exercise_id).await?;
+    if exercise.course_id != student_state.course_id || exercise.exam_id != student_state.exam_id {
+        return Err(controller_err!(
+            Forbidden,
+            "User exercise state does not match the requested exercise context".to_string()
+        ));
+    }
+
+    let token = authorize(
+        &mut conn,
+        Act::Edit,
+        Some(user.id),
+        Res::Exercise(student_state.exercise_id),
+    )
+    .await?;
+
+    let rubric = models::grading_rubrics::get_by_exercise_id(&mut conn, exercise.id)
+        .await?
+        .ok_or_else(|| {
+            controller_err!(
+                BadRequest,
+                "The exercise is not graded with a rubric.".to_string()
+            )
+        })?;
+    let points_given =
+        models::grading_rubric_assessments::points_from_assessments(&rubric, &payload.assessments)?;
+    if points_given > exercise.score_maximum as f32 {
+        return Err(controller_err!(
+            BadRequest,
+            "Cannot give more points than maximum score".to_string()
+        ));
+    }
+
+    info!(
+        "Teacher graded the answer with a rubric. Points given: {:?}.",
+        points_given
+    );
+
+    let mut tx = conn.begin().await?;
+    let decision = models::teacher_grading_decisions::upsert_by_state_id_and_exercise_id(
+        &mut tx,
+        student_state.id,
+        student_state.exercise_id,
+        TeacherDecisionType::Rubric,
+        points_given,
+        Some(user.id),
+        payload.justification.clone(),
+        payload.hidden,
+    )
+    .await?;
+    models::grading_rubric_assessments::insert_for_teacher_grading_decision(
+        &mut tx,
+        decision.id,
+        &payload.assessments,
+    )
+    .await?;
+
+    let new_user_exercise_state = models::user_exercise_states::recalculate_by_id_and_exercise_id(
+        &mut tx,
+        student_state.id,
+        student_state.exercise_id,
+    )
+    .await?;
+
+    if let Some(course_id) = new_user_exercise_state.course_id {
+        // Same as with the other grading decisions, the answer should not be given to others to review anymore.
+        models::peer_review_queue_entries::remove_queue_entries_for_unusual_reason(
+            &mut tx,
+            new_user_exercise_state.user_id,
+            new_user_exercise_state.exercise_id,
+            course_id,
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    token.authorized_ok(web::Json(new_user_exercise_state))
+}
+
 pub fn _add_routes(cfg: &mut ServiceConfig) {
-    cfg.route("", web::post().to(create_teacher_grading_decision));
+    cfg.route("", web::post().to(create_teacher_grading_decision))
+        .route(
+            "/rubric",
+            web::post().to(create_rubric_teacher_grading_decision),
+        );
 }
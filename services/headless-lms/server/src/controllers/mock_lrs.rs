@@ -0,0 +1,161 @@
+//! A mock learning record store for the system tests, mounted at `/api/v0/mock-lrs` when `USE_MOCK_LRS`
+//! is set. The `xapi-sender` is then pointed at it, see [`headless_lms_base::config::XapiConfiguration::mock_conf`].
+//!
+//! Tests read the stored statements from `GET /xapi/statements`, and make the next requests fail with
+//! `POST /faults` to exercise the retries. The state is kept in memory, so the tests must run against
+//! a single server.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex, MutexGuard, PoisonError},
+};
+
+use actix_web::{HttpRequest, http::header};
+use base64::Engine;
+use headless_lms_base::config::{MOCK_LRS_PASSWORD, MOCK_LRS_USERNAME};
+use serde_json::Value;
+
+use crate::prelude::*;
+
+#[derive(Default)]
+struct MockState {
+    /// The stored statements by id, in the order they were stored.
+    statements: Vec<Value>,
+    ids: HashMap<String, usize>,
+    /// Status codes the next requests are answered with.
+    faults: Vec<u16>,
+}
+
+static STATE: LazyLock<Mutex<MockState>> = LazyLock::new(|| Mutex::new(MockState::default()));
+
+#[derive(Debug, Deserialize)]
+pub struct Faults {
+    status: u16,
+    count: usize,
+}
+
+/// The mock is only served in the test mode when `USE_MOCK_LRS` is set.
+fn ensure_enabled(app_conf: &ApplicationConfiguration) -> ControllerResult<()> {
+    if app_conf.test_mode && app_conf.test_lrs {
+        Ok(())
+    } else {
+        Err(controller_err!(
+            Forbidden,
+            "The mock LRS is not enabled.".to_string()
+        ))
+    }
+}
+
+/// A panicking handler can't leave the mock state half-updated, so a poisoned lock is still usable.
+fn state() -> MutexGuard<'static, MockState> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn authorized(req: &HttpRequest) -> bool {
+    let expected = base64::engine::general_purpose::STANDARD
+        .encode(format!("{MOCK_LRS_USERNAME}:{MOCK_LRS_PASSWORD}"));
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value == format!("Basic {expected}"))
+}
+
+/// Stores the statements like a real store would: all or nothing, ignoring statements it already has
+/// and rejecting ones that reuse an id with different content.
+async fn post_statements(
+    req: HttpRequest,
+    payload: web::Json<Vec<Value>>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    let token = skip_authorize();
+    if !authorized(&req) {
+        return token.authorized_ok(HttpResponse::Unauthorized().finish());
+    }
+    let mut state = state();
+    if !state.faults.is_empty() {
+        let status = state.faults.remove(0);
+        return token.authorized_ok(
+            HttpResponse::build(
+                actix_web::http::StatusCode::from_u16(status)
+                    .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR),
+            )
+            .finish(),
+        );
+    }
+    let mut new = Vec::new();
+    for statement in payload.iter() {
+        let Some(id) = statement.get("id").and_then(Value::as_str) else {
+            return token.authorized_ok(
+                HttpResponse::BadRequest().body("Every statement must have an id."),
+            );
+        };
+        if ["actor", "verb", "object"]
+            .iter()
+            .any(|key| statement.get(key).is_none())
+        {
+            return token.authorized_ok(
+                HttpResponse::BadRequest()
+                    .body("Every statement must have an actor, verb and object."),
+            );
+        }
+        match state.ids.get(id) {
+            Some(index) if &state.statements[*index] != statement => {
+                return token.authorized_ok(
+                    HttpResponse::Conflict().body(format!("The statement {id} is already stored.")),
+                );
+            }
+            Some(_) => {}
+            None => new.push((id.to_string(), statement.clone())),
+        }
+    }
+    let ids = payload
+        .iter()
+        .filter_map(|statement| statement.get("id").cloned())
+        .collect::<Vec<_>>();
+    for (id, statement) in new {
+        let index = state.statements.len();
+        state.statements.push(statement);
+        state.ids.insert(id, index);
+    }
+    token.authorized_ok(HttpResponse::Ok().json(ids))
+}
+
+async fn get_statements(
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    let statements = state().statements.clone();
+    let token = skip_authorize();
+    token.authorized_ok(HttpResponse::Ok().json(serde_json::json!({
+        "statements": statements,
+        "more": ""
+    })))
+}
+
+async fn post_faults(
+    payload: web::Json<Faults>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    state()
+        .faults
+        .extend(std::iter::repeat_n(payload.status, payload.count));
+    let token = skip_authorize();
+    token.authorized_ok(HttpResponse::Ok().finish())
+}
+
+/// Forgets the stored statements and the faults.
+async fn reset(app_conf: web::Data<ApplicationConfiguration>) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    *state() = MockState::default();
+    let token = skip_authorize();
+    token.authorized_ok(HttpResponse::Ok().finish())
+}
+
+pub fn _add_routes(cfg: &mut ServiceConfig) {
+    cfg.route("/xapi/statements", web::post().to(post_statements))
+        .route("/xapi/statements", web::get().to(get_statements))
+        .route("/faults", web::post().to(post_faults))
+        .route("/reset", web::post().to(reset));
+}
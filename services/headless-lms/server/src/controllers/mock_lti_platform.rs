@@ -0,0 +1,328 @@
+//! A mock LTI 1.3 platform for the system tests, mounted at `/api/v0/mock-lti-platform` when
+//! `USE_MOCK_LTI_PLATFORM` is set.
+//!
+//! Register it as a platform with the issuer `{base_url}/api/v0/mock-lti-platform`, the client id
+//! [`MOCK_CLIENT_ID`], the deployment id [`MOCK_DEPLOYMENT_ID`] and the `/auth`, `/token` endpoints of this
+//! module. The mock signs its ID tokens with the key of our own OAuth server, so its keyset URL is
+//! `/api/v0/main-frontend/oauth/jwks.json`.
+//!
+//! A test starts a launch by opening `/launch`, and reads what the tool sent back from `/scores` and
+//! `/deep-link-responses`. The state is kept in memory, so the tests must run against a single server.
+
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex, MutexGuard, PoisonError},
+};
+
+use actix_web::http::header;
+use url::Url;
+
+use crate::{
+    domain::lti::{
+        AGS_SCOPE_SCORE, DEEP_LINKING_REQUEST, LTI_VERSION, RESOURCE_LINK_REQUEST,
+        claims::{
+            AgsEndpointClaim, Audience, ContextClaim, DeepLinkingSettingsClaim, LaunchClaims,
+            NrpsClaim, ResourceLinkClaim,
+        },
+        services::{AgsScore, Member, MembershipContainer, sign_tool_jwt},
+    },
+    prelude::*,
+};
+
+pub const MOCK_CLIENT_ID: &str = "mock-client";
+pub const MOCK_DEPLOYMENT_ID: &str = "mock-deployment";
+const MOCK_CONTEXT_ID: &str = "mock-context";
+const ROLE_LEARNER: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Learner";
+const ROLE_INSTRUCTOR: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Instructor";
+
+#[derive(Default)]
+struct MockState {
+    /// The launches started with `/launch`, by login hint.
+    pending_launches: HashMap<String, LaunchRequest>,
+    scores: Vec<RecordedScore>,
+    deep_link_responses: Vec<String>,
+}
+
+static STATE: LazyLock<Mutex<MockState>> = LazyLock::new(|| Mutex::new(MockState::default()));
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LaunchRequest {
+    /// The platform user. Each subject becomes a separate user in our service.
+    subject: String,
+    #[serde(default)]
+    deep_linking: bool,
+    #[serde(default)]
+    resource_link_id: Option<String>,
+    #[serde(default)]
+    course_id: Option<Uuid>,
+    #[serde(default)]
+    chapter_id: Option<Uuid>,
+    #[serde(default)]
+    exercise_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthRequest {
+    redirect_uri: String,
+    login_hint: String,
+    state: String,
+    nonce: String,
+    client_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedScore {
+    lineitem: String,
+    score: AgsScore,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeepLinkReturn {
+    #[serde(rename = "JWT")]
+    jwt: String,
+}
+
+/// The mock is only served in the test mode when `USE_MOCK_LTI_PLATFORM` is set.
+fn ensure_enabled(app_conf: &ApplicationConfiguration) -> ControllerResult<()> {
+    if app_conf.test_mode && app_conf.test_lti {
+        Ok(())
+    } else {
+        Err(controller_err!(
+            Forbidden,
+            "The mock LTI platform is not enabled.".to_string()
+        ))
+    }
+}
+
+/// A panicking handler can't leave the mock state half-updated, so a poisoned lock is still usable.
+fn state() -> MutexGuard<'static, MockState> {
+    STATE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn issuer(app_conf: &ApplicationConfiguration) -> String {
+    format!(
+        "{}/api/v0/mock-lti-platform",
+        app_conf.base_url.trim_end_matches('/')
+    )
+}
+
+/// Starts a launch as the platform would: redirects the browser to the login initiation of the tool.
+async fn start_launch(
+    query: web::Query<LaunchRequest>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    let login_hint = Uuid::new_v4().to_string();
+    state()
+        .pending_launches
+        .insert(login_hint.clone(), query.into_inner());
+    let base_url = app_conf.base_url.trim_end_matches('/');
+    let mut login =
+        Url::parse(&format!("{base_url}/api/v0/lti/login")).map_err(anyhow::Error::from)?;
+    login
+        .query_pairs_mut()
+        .append_pair("iss", &issuer(&app_conf))
+        .append_pair("login_hint", &login_hint)
+        .append_pair("target_link_uri", &format!("{base_url}/api/v0/lti/launch"))
+        .append_pair("client_id", MOCK_CLIENT_ID);
+    let token = skip_authorize();
+    token.authorized_ok(
+        HttpResponse::Found()
+            .insert_header((header::LOCATION, login.to_string()))
+            .finish(),
+    )
+}
+
+/// The authorization endpoint. Answers with a page that posts the signed ID token to the tool.
+async fn auth(
+    query: web::Query<AuthRequest>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    let launch = state()
+        .pending_launches
+        .remove(&query.login_hint)
+        .ok_or_else(|| controller_err!(NotFound, "Unknown login hint.".to_string()))?;
+    let issuer = issuer(&app_conf);
+    let now = Utc::now().timestamp();
+    let custom = [
+        ("course_id", launch.course_id),
+        ("chapter_id", launch.chapter_id),
+        ("exercise_id", launch.exercise_id),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| value.map(|value| (key.to_string(), value.to_string())))
+    .collect();
+    let resource_link_id = launch
+        .resource_link_id
+        .clone()
+        .unwrap_or_else(|| "mock-resource-link".to_string());
+    let claims = LaunchClaims {
+        iss: issuer.clone(),
+        sub: launch.subject.clone(),
+        aud: Audience::Single(query.client_id.clone()),
+        exp: now + 300,
+        iat: now,
+        nonce: query.nonce.clone(),
+        azp: None,
+        given_name: Some("Mock".to_string()),
+        family_name: Some(launch.subject.clone()),
+        name: None,
+        message_type: if launch.deep_linking {
+            DEEP_LINKING_REQUEST
+        } else {
+            RESOURCE_LINK_REQUEST
+        }
+        .to_string(),
+        version: LTI_VERSION.to_string(),
+        deployment_id: MOCK_DEPLOYMENT_ID.to_string(),
+        target_link_uri: Some(query.redirect_uri.clone()),
+        roles: vec![
+            if launch.deep_linking {
+                ROLE_INSTRUCTOR
+            } else {
+                ROLE_LEARNER
+            }
+            .to_string(),
+        ],
+        resource_link: (!launch.deep_linking).then(|| ResourceLinkClaim {
+            id: resource_link_id.clone(),
+            title: None,
+        }),
+        context: Some(ContextClaim {
+            id: MOCK_CONTEXT_ID.to_string(),
+            title: Some("Mock course".to_string()),
+        }),
+        custom,
+        ags_endpoint: (!launch.deep_linking).then(|| AgsEndpointClaim {
+            scope: vec![AGS_SCOPE_SCORE.to_string()],
+            lineitems: None,
+            lineitem: Some(format!("{issuer}/lineitems/{resource_link_id}")),
+        }),
+        nrps: Some(NrpsClaim {
+            context_memberships_url: format!("{issuer}/memberships"),
+        }),
+        deep_linking_settings: launch.deep_linking.then(|| DeepLinkingSettingsClaim {
+            deep_link_return_url: format!("{issuer}/deep-link-return"),
+            accept_types: vec!["ltiResourceLink".to_string()],
+            accept_multiple: true,
+            data: Some("mock-data".to_string()),
+        }),
+    };
+    let id_token = sign_tool_jwt(&claims, &app_conf)?;
+    let token = skip_authorize();
+    token.authorized_ok(
+        HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(format!(
+                r#"<!DOCTYPE html>
+<html>
+  <body onload="document.forms[0].submit()">
+    <form method="post" action="{}">
+      <input type="hidden" name="id_token" value="{}">
+      <input type="hidden" name="state" value="{}">
+    </form>
+  </body>
+</html>
+"#,
+                query.redirect_uri, id_token, query.state
+            )),
+    )
+}
+
+/// Accepts any client assertion.
+async fn access_token(
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    let token = skip_authorize();
+    token.authorized_ok(HttpResponse::Ok().json(serde_json::json!({
+        "access_token": "mock-access-token",
+        "token_type": "Bearer",
+        "expires_in": 3600,
+    })))
+}
+
+async fn post_score(
+    lineitem: web::Path<String>,
+    body: web::Bytes,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    let score: AgsScore = serde_json::from_slice(&body)?;
+    state().scores.push(RecordedScore {
+        lineitem: lineitem.into_inner(),
+        score,
+    });
+    let token = skip_authorize();
+    token.authorized_ok(HttpResponse::Ok().finish())
+}
+
+async fn get_scores(
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<web::Json<Vec<RecordedScore>>> {
+    ensure_enabled(&app_conf)?;
+    let scores = state().scores.clone();
+    let token = skip_authorize();
+    token.authorized_ok(web::Json(scores))
+}
+
+async fn memberships(
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<web::Json<MembershipContainer>> {
+    ensure_enabled(&app_conf)?;
+    let member = |user_id: &str, role: &str| Member {
+        user_id: user_id.to_string(),
+        roles: vec![role.to_string()],
+        status: Some("Active".to_string()),
+        name: Some(format!("Mock {user_id}")),
+        given_name: Some("Mock".to_string()),
+        family_name: Some(user_id.to_string()),
+        email: None,
+    };
+    let token = skip_authorize();
+    token.authorized_ok(web::Json(MembershipContainer {
+        id: format!("{}/memberships", issuer(&app_conf)),
+        members: vec![
+            member("mock-teacher", ROLE_INSTRUCTOR),
+            member("mock-student", ROLE_LEARNER),
+        ],
+    }))
+}
+
+async fn deep_link_return(
+    form: web::Form<DeepLinkReturn>,
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<HttpResponse> {
+    ensure_enabled(&app_conf)?;
+    state().deep_link_responses.push(form.into_inner().jwt);
+    let token = skip_authorize();
+    token.authorized_ok(
+        HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body("<!DOCTYPE html><html><body>Content added</body></html>"),
+    )
+}
+
+async fn get_deep_link_responses(
+    app_conf: web::Data<ApplicationConfiguration>,
+) -> ControllerResult<web::Json<Vec<String>>> {
+    ensure_enabled(&app_conf)?;
+    let responses = state().deep_link_responses.clone();
+    let token = skip_authorize();
+    token.authorized_ok(web::Json(responses))
+}
+
+pub fn _add_routes(cfg: &mut ServiceConfig) {
+    cfg.route("/launch", web::get().to(start_launch))
+        .route("/auth", web::get().to(auth))
+        .route("/token", web::post().to(access_token))
+        .route("/lineitems/{lineitem}/scores", web::post().to(post_score))
+        .route("/scores", web::get().to(get_scores))
+        .route("/memberships", web::get().to(memberships))
+        .route("/deep-link-return", web::post().to(deep_link_return))
+        .route(
+            "/deep-link-responses",
+            web::get().to(get_deep_link_responses),
+        );
+}
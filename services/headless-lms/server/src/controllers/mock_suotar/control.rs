@@ -422,7 +422,9 @@ pub fn _add_routes(cfg: &mut ServiceConfig) {
 #[cfg(test)]
 mod tests {
     use actix_web::{App, http::StatusCode, test, web::Data};
-    use headless_lms_base::config::{OAuthServerConfiguration, SuotarConfiguration};
+    use headless_lms_base::config::{
+        CertificateCredentialConfiguration, OAuthServerConfiguration, SuotarConfiguration,
+    };
     use secrecy::{SecretBox, SecretString};
     use std::sync::Arc;
 
@@ -437,6 +439,8 @@ mod tests {
             test_chatbot: false,
             test_sisu: false,
             test_suotar,
+            test_lti: false,
+            test_lrs: false,
             disable_embedding_vector_creation_when_seeding: false,
             development_uuid_login: false,
             enable_admin_email_verification: false,
@@ -444,6 +448,7 @@ mod tests {
             azure_configuration: None,
             suotar_configuration: SuotarConfiguration::mock_conf("http://project-331.local")
                 .expect("the mock configuration is built from a constant base url"),
+            xapi_configuration: None,
             tmc_account_creation_origin: None,
             tmc_admin_access_token: SecretString::new("mock-access-token".to_string().into()),
             oauth_server_configuration: OAuthServerConfiguration {
@@ -452,6 +457,10 @@ mod tests {
                 oauth_token_hmac_key: SecretString::new("test".into()),
                 dpop_nonce_key: Arc::new(SecretBox::new(Box::new("test".into()))),
             },
+            certificate_credential_configuration: CertificateCredentialConfiguration {
+                rsa_public_key: "test".into(),
+                rsa_private_key: SecretString::new("test".into()),
+            },
         }
     }
 
@@ -16,9 +16,12 @@ pub mod exercise_services;
 pub mod files;
 pub mod health;
 pub mod helpers;
+pub mod lti;
 pub mod main_frontend;
 pub mod mock_azure;
 pub mod mock_document_storage;
+pub mod mock_lrs;
+pub mod mock_lti_platform;
 pub mod mock_sisu;
 pub mod mock_suotar;
 pub mod other_domain_redirects;
@@ -58,6 +61,7 @@ pub fn configure_controllers(
         )
         .service(web::scope("/health").configure(health::_add_routes))
         .service(web::scope("/tmc-server").configure(tmc_server::_add_routes))
+        .service(web::scope("/lti").configure(lti::_add_routes))
         .default_service(web::to(not_found));
     if app_conf.test_chatbot && app_conf.test_mode {
         cfg.service(web::scope("/mock-azure").configure(mock_azure::_add_routes))
@@ -71,6 +75,12 @@ pub fn configure_controllers(
     if app_conf.test_suotar && app_conf.test_mode {
         cfg.service(web::scope("/mock-suotar").configure(mock_suotar::_add_routes));
     }
+    if app_conf.test_lti && app_conf.test_mode {
+        cfg.service(web::scope("/mock-lti-platform").configure(mock_lti_platform::_add_routes));
+    }
+    if app_conf.test_lrs && app_conf.test_mode {
+        cfg.service(web::scope("/mock-lrs").configure(mock_lrs::_add_routes));
+    }
 }
 
 async fn not_found(req: HttpRequest) -> HttpResponse {
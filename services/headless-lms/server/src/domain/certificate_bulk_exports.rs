@@ -0,0 +1,290 @@
+//! Runs certificate bulk exports, see [`models::certificate_bulk_exports`].
+//!
+//! Each student's certificate is rendered and uploaded to the file store on its own, and the item
+//! is marked as processed right after, so a job that is interrupted continues where it left off.
+//! Once every item is processed, the rendered files are packed into a ZIP file together with a
+//! `manifest.csv` that lists every student, including the ones whose certificate could not be
+//! exported and why. The ZIP file is written to a temporary file and streamed to the file store
+//! from there, as it can be too large to keep in memory.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use bytes::Bytes;
+use headless_lms_certificates as certificates;
+use headless_lms_utils::icu4x::Icu4xBlob;
+use models::certificate_bulk_exports::{
+    self, CertificateBulkExport, CertificateBulkExportFormat, CertificateBulkExportItem,
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::prelude::*;
+
+/// A running job whose worker has not reported progress for this long is picked up again.
+pub const STALE_LOCK_TIMEOUT_MINUTES: i64 = 15;
+
+/// The columns `error_message` fits in.
+const MAX_ERROR_MESSAGE_LENGTH: usize = 1000;
+
+/// How much of the ZIP file is read into memory at a time when uploading it.
+const ZIP_UPLOAD_CHUNK_BYTES: usize = 1024 * 1024;
+
+/// Picks up the next job, if there is one, and runs it to the end. Returns whether a job was
+/// found.
+///
+/// An error fails the job only once it has been attempted [`certificate_bulk_exports::MAX_ATTEMPTS`]
+/// times. Until then the lock is released so that the job is retried on a later run.
+pub async fn process_next(
+    conn: &mut PgConnection,
+    file_store: &dyn FileStore,
+    icu4x_blob: Icu4xBlob,
+) -> anyhow::Result<bool> {
+    let stale_lock_cutoff = Utc::now() - chrono::Duration::minutes(STALE_LOCK_TIMEOUT_MINUTES);
+    let Some(id) = certificate_bulk_exports::claim_next(conn, stale_lock_cutoff).await? else {
+        return Ok(false);
+    };
+    let export = certificate_bulk_exports::get_by_id(conn, id).await?;
+    info!(
+        "Processing certificate bulk export {} (attempt {}).",
+        export.id, export.attempts
+    );
+    if let Err(err) = run(conn, file_store, icu4x_blob, &export).await {
+        if export.attempts >= certificate_bulk_exports::MAX_ATTEMPTS {
+            certificate_bulk_exports::mark_failed(conn, export.id, &truncate(&err.to_string()))
+                .await?;
+        } else {
+            certificate_bulk_exports::release_lock(conn, export.id).await?;
+        }
+        return Err(err);
+    }
+    Ok(true)
+}
+
+async fn run(
+    conn: &mut PgConnection,
+    file_store: &dyn FileStore,
+    icu4x_blob: Icu4xBlob,
+    export: &CertificateBulkExport,
+) -> anyhow::Result<()> {
+    let items = certificate_bulk_exports::get_unprocessed_items(conn, export.id).await?;
+    for item in items {
+        export_item(conn, file_store, icu4x_blob, export, &item).await?;
+        certificate_bulk_exports::refresh_lock(conn, export.id).await?;
+    }
+
+    let mut zip = tokio::fs::File::from_std(build_zip(conn, file_store, export).await?);
+    zip.rewind().await?;
+    let zip = futures::stream::try_unfold(zip, |mut zip| async move {
+        let mut chunk = vec![0; ZIP_UPLOAD_CHUNK_BYTES];
+        let read = zip.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok::<_, anyhow::Error>(None);
+        }
+        chunk.truncate(read);
+        Ok(Some((Bytes::from(chunk), zip)))
+    });
+    let zip_file_path = format!("certificate-bulk-exports/{}/certificates.zip", export.id);
+    file_store
+        .upload_stream(Path::new(&zip_file_path), Box::pin(zip), "application/zip")
+        .await?;
+    certificate_bulk_exports::mark_completed(conn, export.id, &zip_file_path).await?;
+    info!("Certificate bulk export {} completed.", export.id);
+    Ok(())
+}
+
+/// Renders and uploads the certificate of one student. The student's current certificate is
+/// reused if there is one, otherwise a new one is generated with the name in their user details.
+async fn export_item(
+    conn: &mut PgConnection,
+    file_store: &dyn FileStore,
+    icu4x_blob: Icu4xBlob,
+    export: &CertificateBulkExport,
+    item: &CertificateBulkExportItem,
+) -> anyhow::Result<()> {
+    let existing_certificate_id = match item.generated_certificate_id {
+        Some(id) => Some(id),
+        None => {
+            models::generated_certificates::find_existing(
+                conn,
+                item.user_id,
+                export.certificate_configuration_id,
+            )
+            .await?
+        }
+    };
+    let certificate = match existing_certificate_id {
+        Some(id) => models::generated_certificates::get_by_id(conn, id).await?,
+        None => {
+            let user_details =
+                models::user_details::get_user_details_by_user_id(conn, item.user_id).await?;
+            let name = [user_details.first_name, user_details.last_name]
+                .into_iter()
+                .flatten()
+                .map(|part| part.trim().to_string())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            if name.is_empty() {
+                certificate_bulk_exports::mark_item_failed(
+                    conn,
+                    item.id,
+                    None,
+                    "The student has not entered their name.",
+                )
+                .await?;
+                return Ok(());
+            }
+            match models::generated_certificates::generate_and_insert(
+                conn,
+                item.user_id,
+                &name,
+                export.certificate_configuration_id,
+            )
+            .await
+            {
+                Ok(certificate) => certificate,
+                // The student is no longer eligible, for example because the completion was
+                // removed after the export was requested.
+                Err(err) if matches!(err.error_type(), ModelErrorType::PreconditionFailed) => {
+                    certificate_bulk_exports::mark_item_failed(
+                        conn,
+                        item.id,
+                        None,
+                        &truncate(err.message()),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    };
+
+    let contents = match export.format {
+        CertificateBulkExportFormat::Png => {
+            certificates::generate_certificate(conn, file_store, &certificate, false, icu4x_blob)
+                .await?
+        }
+        CertificateBulkExportFormat::Pdf => {
+            certificates::generate_certificate_pdf(conn, file_store, &certificate, icu4x_blob)
+                .await?
+        }
+    };
+    let file_path = format!(
+        "certificate-bulk-exports/{}/{}.{}",
+        export.id,
+        certificate.verification_id,
+        export.format.file_extension()
+    );
+    file_store
+        .upload(Path::new(&file_path), contents, export.format.mime_type())
+        .await?;
+    certificate_bulk_exports::mark_item_exported(conn, item.id, certificate.id, &file_path).await?;
+    Ok(())
+}
+
+/// Writes the ZIP file to a temporary file, which is deleted once the returned handle is dropped.
+async fn build_zip(
+    conn: &mut PgConnection,
+    file_store: &dyn FileStore,
+    export: &CertificateBulkExport,
+) -> anyhow::Result<File> {
+    let items = certificate_bulk_exports::get_items(conn, export.id).await?;
+    let mut zip = ZipWriter::new(tempfile::tempfile()?);
+    // The certificates are already compressed images and documents.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let mut manifest = csv::Writer::from_writer(vec![]);
+    manifest.write_record([
+        "user_id",
+        "name_on_certificate",
+        "verification_id",
+        "file_name",
+        "error",
+    ])?;
+    for item in items {
+        let certificate = match item.generated_certificate_id {
+            Some(id) => {
+                Some(models::generated_certificates::get_by_id_including_deleted(conn, id).await?)
+            }
+            None => None,
+        };
+        let file_name = match (&certificate, &item.file_path) {
+            (Some(certificate), Some(file_path)) => {
+                let file_name = zip_file_name(
+                    &certificate.name_on_certificate,
+                    &certificate.verification_id,
+                    export.format,
+                );
+                let contents = file_store
+                    .fetch_file_content_or_use_filesystem_cache(Path::new(file_path))
+                    .await?;
+                zip.start_file(file_name.as_str(), stored)?;
+                zip.write_all(&contents)?;
+                Some(file_name)
+            }
+            _ => None,
+        };
+        manifest.write_record([
+            item.user_id.to_string(),
+            certificate
+                .as_ref()
+                .map(|c| c.name_on_certificate.clone())
+                .unwrap_or_default(),
+            certificate
+                .as_ref()
+                .map(|c| c.verification_id.clone())
+                .unwrap_or_default(),
+            file_name.unwrap_or_default(),
+            item.error_message.unwrap_or_default(),
+        ])?;
+    }
+    zip.start_file(
+        "manifest.csv",
+        SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+    )?;
+    zip.write_all(&manifest.into_inner()?)?;
+    Ok(zip.finish()?)
+}
+
+/// A file name that is unique within the export and tells the organizers whose certificate it is.
+fn zip_file_name(
+    name_on_certificate: &str,
+    verification_id: &str,
+    format: CertificateBulkExportFormat,
+) -> String {
+    let name = name_on_certificate
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{}-{}.{}", name, verification_id, format.file_extension())
+}
+
+fn truncate(message: &str) -> String {
+    message.chars().take(MAX_ERROR_MESSAGE_LENGTH).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zip_file_names_are_safe() {
+        assert_eq!(
+            zip_file_name(
+                " Äijä O'Brien/../x ",
+                "abc123",
+                CertificateBulkExportFormat::Pdf
+            ),
+            "Äijä_O_Brien____x-abc123.pdf"
+        );
+    }
+}
@@ -0,0 +1,408 @@
+//! Open Badges 3.0 credentials for generated certificates.
+//!
+//! Each certificate can be downloaded as an `OpenBadgeCredential`, a W3C Verifiable Credential that
+//! digital wallets understand. The credential is secured as a JWT signed with RS256 using the key in
+//! [`CertificateCredentialConfiguration`], and the matching public key is published as a JWKS.
+//! A credential is valid while its signature checks out and the certificate has not been deleted.
+
+use headless_lms_models::generated_certificates::{
+    CertificateCredentialDetails, CertificateStatus, GeneratedCertificate,
+};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use secrecy::ExposeSecret;
+use utoipa::ToSchema;
+
+use crate::{
+    domain::oauth::{
+        jwks::{Jwk, Jwks},
+        oidc::rsa_n_e_and_kid_from_pem,
+    },
+    prelude::*,
+};
+
+const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+const OPEN_BADGES_CONTEXT: &str = "https://purl.imsglobal.org/spec/ob/v3p0/context-3.0.3.json";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenBadgeCredential {
+    #[serde(rename = "@context")]
+    pub context: Vec<String>,
+    /// `urn:uuid:` followed by the generated certificate id.
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+    pub issuer: CredentialIssuer,
+    pub valid_from: DateTime<Utc>,
+    pub name: String,
+    pub image: CredentialImage,
+    pub credential_subject: AchievementSubject,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct CredentialIssuer {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct CredentialImage {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementSubject {
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+    pub identifier: Vec<IdentityObject>,
+    pub achievement: Achievement,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub activity_end_date: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub result: Vec<AchievementResult>,
+}
+
+/// Names the holder the way the certificate image does.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityObject {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub identity_hash: String,
+    pub identity_type: String,
+    pub hashed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Achievement {
+    /// `urn:uuid:` followed by the course module id.
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+    pub achievement_type: String,
+    pub name: String,
+    pub description: String,
+    pub criteria: AchievementCriteria,
+    pub result_description: Vec<ResultDescription>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct AchievementCriteria {
+    pub narrative: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultDescription {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+    pub name: String,
+    pub result_type: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementResult {
+    #[serde(rename = "type")]
+    pub type_: Vec<String>,
+    pub result_description: String,
+    pub value: String,
+}
+
+/// The JWT claims: the credential itself plus the registered claims the JWT encoding requires.
+#[derive(Debug, Serialize, Deserialize)]
+struct CredentialClaims {
+    iss: String,
+    jti: String,
+    nbf: i64,
+    #[serde(flatten)]
+    credential: OpenBadgeCredential,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SignedCertificateCredential {
+    pub credential: OpenBadgeCredential,
+    /// The credential as a compact JWS, which is what wallets import.
+    pub jwt: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CertificateCredentialVerificationRequest {
+    pub jwt: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CertificateCredentialVerification {
+    /// True when the signature is valid and the certificate has not been revoked or superseded.
+    pub valid: bool,
+    pub signature_valid: bool,
+    pub revoked: bool,
+    /// A newer version of the certificate has been issued, see `superseded_by_verification_id`.
+    pub superseded: bool,
+    pub superseded_by_verification_id: Option<String>,
+    /// The verified credential. `None` if the signature did not check out.
+    pub credential: Option<OpenBadgeCredential>,
+}
+
+pub fn build_credential(
+    base_url: &str,
+    certificate: &GeneratedCertificate,
+    details: &CertificateCredentialDetails,
+) -> OpenBadgeCredential {
+    let base_url = base_url.trim_end_matches('/');
+    let achievement_id = format!("urn:uuid:{}", details.course_module_id);
+    let achievement_name = match &details.course_module_name {
+        Some(module_name) => format!("{}: {}", details.course_name, module_name),
+        None => details.course_name.clone(),
+    };
+    let grade_id = format!("{achievement_id}#grade");
+    let grade = match (details.grade, details.passed) {
+        (Some(grade), _) => Some(grade.to_string()),
+        (None, Some(true)) => Some("passed".to_string()),
+        (None, Some(false)) => Some("failed".to_string()),
+        (None, None) => None,
+    };
+    OpenBadgeCredential {
+        context: vec![
+            CREDENTIALS_CONTEXT.to_string(),
+            OPEN_BADGES_CONTEXT.to_string(),
+        ],
+        id: format!("urn:uuid:{}", certificate.id),
+        type_: vec![
+            "VerifiableCredential".to_string(),
+            "OpenBadgeCredential".to_string(),
+        ],
+        issuer: CredentialIssuer {
+            id: base_url.to_string(),
+            type_: vec!["Profile".to_string()],
+            name: details.organization_name.clone(),
+            url: base_url.to_string(),
+        },
+        valid_from: certificate.created_at,
+        name: achievement_name.clone(),
+        image: CredentialImage {
+            id: format!(
+                "{base_url}/api/v0/main-frontend/certificates/{}",
+                certificate.verification_id
+            ),
+            type_: "Image".to_string(),
+        },
+        credential_subject: AchievementSubject {
+            type_: vec!["AchievementSubject".to_string()],
+            identifier: vec![IdentityObject {
+                type_: "IdentityObject".to_string(),
+                identity_hash: certificate.name_on_certificate.clone(),
+                identity_type: "name".to_string(),
+                hashed: false,
+            }],
+            achievement: Achievement {
+                id: achievement_id,
+                type_: vec!["Achievement".to_string()],
+                achievement_type: "Course".to_string(),
+                name: achievement_name,
+                description: format!("Completion of {}", details.course_name),
+                criteria: AchievementCriteria {
+                    narrative: "Completed all the requirements of the course module.".to_string(),
+                },
+                result_description: vec![ResultDescription {
+                    id: grade_id.clone(),
+                    type_: vec!["ResultDescription".to_string()],
+                    name: "Grade".to_string(),
+                    result_type: "LetterGrade".to_string(),
+                }],
+            },
+            activity_end_date: details.completion_date,
+            result: grade
+                .map(|value| AchievementResult {
+                    type_: vec!["Result".to_string()],
+                    result_description: grade_id,
+                    value,
+                })
+                .into_iter()
+                .collect(),
+        },
+    }
+}
+
+pub fn sign_credential(
+    credential: &OpenBadgeCredential,
+    conf: &CertificateCredentialConfiguration,
+) -> ControllerResult<String> {
+    let (_, _, kid) = rsa_n_e_and_kid_from_pem(&conf.rsa_public_key)?;
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(kid);
+    header.typ = Some("vc+jwt".to_string());
+    let key = EncodingKey::from_rsa_pem(conf.rsa_private_key.expose_secret().as_bytes())?;
+    let claims = CredentialClaims {
+        iss: credential.issuer.id.clone(),
+        jti: credential.id.clone(),
+        nbf: credential.valid_from.timestamp(),
+        credential: credential.clone(),
+    };
+    Ok(encode(&header, &claims, &key)?)
+}
+
+/// Returns the credential if the JWT was signed with our key, `None` otherwise.
+pub fn verify_signature(
+    jwt: &str,
+    conf: &CertificateCredentialConfiguration,
+) -> ControllerResult<Option<OpenBadgeCredential>> {
+    let key = DecodingKey::from_rsa_pem(conf.rsa_public_key.as_bytes())?;
+    let mut validation = Validation::new(Algorithm::RS256);
+    // Certificates do not expire.
+    validation.validate_exp = false;
+    validation.required_spec_claims.clear();
+    match decode::<CredentialClaims>(jwt, &key, &validation) {
+        Ok(data) => Ok(Some(data.claims.credential)),
+        Err(err) => {
+            info!("Certificate credential failed verification: {}", err);
+            Ok(None)
+        }
+    }
+}
+
+/// Checks the signature and whether the certificate has been revoked since the credential was issued.
+pub async fn verify(
+    conn: &mut PgConnection,
+    jwt: &str,
+    conf: &CertificateCredentialConfiguration,
+) -> ControllerResult<CertificateCredentialVerification> {
+    let Some(credential) = verify_signature(jwt, conf)? else {
+        return Ok(CertificateCredentialVerification {
+            valid: false,
+            signature_valid: false,
+            revoked: false,
+            superseded: false,
+            superseded_by_verification_id: None,
+            credential: None,
+        });
+    };
+    let certificate_id = credential
+        .id
+        .strip_prefix("urn:uuid:")
+        .and_then(|id| Uuid::parse_str(id).ok());
+    let certificate = match certificate_id {
+        Some(id) => models::generated_certificates::get_by_id_including_deleted(conn, id)
+            .await
+            .optional()?,
+        None => None,
+    };
+    // We signed it, so a certificate that can no longer be found has been removed.
+    let status = certificate
+        .as_ref()
+        .map(|c| c.status())
+        .unwrap_or(CertificateStatus::Revoked);
+    let superseded_by_verification_id = match certificate.and_then(|c| c.superseded_by_id) {
+        Some(superseded_by_id) => Some(
+            models::generated_certificates::get_by_id_including_deleted(conn, superseded_by_id)
+                .await?
+                .verification_id,
+        ),
+        None => None,
+    };
+    Ok(CertificateCredentialVerification {
+        valid: status == CertificateStatus::Valid,
+        signature_valid: true,
+        revoked: status == CertificateStatus::Revoked,
+        superseded: status == CertificateStatus::Superseded,
+        superseded_by_verification_id,
+        credential: Some(credential),
+    })
+}
+
+pub fn jwks(conf: &CertificateCredentialConfiguration) -> ControllerResult<Jwks> {
+    let (n, e, kid) = rsa_n_e_and_kid_from_pem(&conf.rsa_public_key)?;
+    Ok(Jwks {
+        keys: vec![Jwk {
+            kty: "RSA".into(),
+            use_: "sig".into(),
+            alg: "RS256".into(),
+            kid,
+            n,
+            e,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn certificate() -> GeneratedCertificate {
+        GeneratedCertificate {
+            id: Uuid::parse_str("8c4b1a1e-6a2b-4c5d-9e0f-1a2b3c4d5e6f").unwrap(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            user_id: Uuid::new_v4(),
+            name_on_certificate: "Example Student".to_string(),
+            verification_id: "abcdefghijklmno".to_string(),
+            certificate_configuration_id: Uuid::new_v4(),
+            revoked_at: None,
+            revocation_reason: None,
+            revoked_by_user_id: None,
+            superseded_by_id: None,
+            version: 1,
+        }
+    }
+
+    fn details(grade: Option<i32>, passed: Option<bool>) -> CertificateCredentialDetails {
+        CertificateCredentialDetails {
+            course_id: Uuid::new_v4(),
+            course_name: "Introduction to Programming".to_string(),
+            course_module_id: Uuid::parse_str("1f0e2d3c-4b5a-6978-8796-a5b4c3d2e1f0").unwrap(),
+            course_module_name: Some("Part 2".to_string()),
+            organization_name: "University of Helsinki".to_string(),
+            completion_date: Some(Utc::now()),
+            grade,
+            passed,
+        }
+    }
+
+    #[test]
+    fn credential_carries_the_completion() {
+        let credential = build_credential(
+            "http://project-331.local/",
+            &certificate(),
+            &details(Some(4), Some(true)),
+        );
+        let json = serde_json::to_value(&credential).unwrap();
+        assert_eq!(json["@context"][0], CREDENTIALS_CONTEXT);
+        assert_eq!(json["id"], "urn:uuid:8c4b1a1e-6a2b-4c5d-9e0f-1a2b3c4d5e6f");
+        assert_eq!(json["issuer"]["id"], "http://project-331.local");
+        assert_eq!(
+            json["credentialSubject"]["achievement"]["name"],
+            "Introduction to Programming: Part 2"
+        );
+        assert_eq!(json["credentialSubject"]["result"][0]["value"], "4");
+        assert_eq!(
+            json["credentialSubject"]["result"][0]["resultDescription"],
+            "urn:uuid:1f0e2d3c-4b5a-6978-8796-a5b4c3d2e1f0#grade"
+        );
+    }
+
+    #[test]
+    fn pass_fail_completion_is_reported_as_status() {
+        let credential = build_credential(
+            "http://project-331.local",
+            &certificate(),
+            &details(None, Some(true)),
+        );
+        assert_eq!(credential.credential_subject.result[0].value, "passed");
+
+        let credential = build_credential(
+            "http://project-331.local",
+            &certificate(),
+            &details(None, None),
+        );
+        assert!(credential.credential_subject.result.is_empty());
+    }
+}
@@ -0,0 +1,372 @@
+//! Converting the HTML pages of a cartridge to Gutenberg blocks.
+//!
+//! The top level paragraphs, headings and images become the corresponding core blocks so that they
+//! can be edited in the CMS. Other elements, like lists and tables, are kept as they are in
+//! `core/html` blocks. Scripts, styles and comments are dropped.
+
+use headless_lms_utils::{attributes, document_schema_processor::GutenbergBlock};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+
+static BODY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<body[^>]*>(.*)</body>").expect("Invalid regex."));
+static TITLE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?is)<title[^>]*>(.*?)</title>").expect("Invalid regex."));
+static DROPPED_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?is)<script\b.*?</script\s*>|<style\b.*?</style\s*>|<!--.*?-->")
+        .expect("Invalid regex.")
+});
+static REFERENCE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\b(src|href)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("Invalid regex.")
+});
+static ATTRIBUTE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)\b([a-z-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("Invalid regex.")
+});
+static TAG_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").expect("Invalid regex."));
+static LINE_BREAK_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)<br\s*/?>|</(p|div|li|h[1-6])\s*>").expect("Invalid regex."));
+static ENTITY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"&(#[0-9]+|#[xX][0-9a-fA-F]+|[a-zA-Z]+);").expect("Invalid regex."));
+
+/// Elements whose content is split into blocks like the top level of the page.
+const CONTAINER_ELEMENTS: &[&str] = &[
+    "div", "section", "article", "main", "header", "footer", "center",
+];
+/// Elements that are kept as they are in a `core/html` block.
+const HTML_ELEMENTS: &[&str] = &[
+    "ul",
+    "ol",
+    "dl",
+    "table",
+    "pre",
+    "blockquote",
+    "figure",
+    "iframe",
+    "video",
+    "audio",
+    "hr",
+    "object",
+    "embed",
+    "form",
+];
+const VOID_ELEMENTS: &[&str] = &["img", "hr", "br", "embed", "input", "source", "wbr"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlPage {
+    pub title: Option<String>,
+    pub blocks: Vec<GutenbergBlock>,
+}
+
+/// Converts an HTML document or fragment. The `src` and `href` attributes are passed to
+/// `rewrite_reference`, which can replace them, for example with the address of an uploaded file.
+pub fn to_blocks(
+    html: &str,
+    mut rewrite_reference: impl FnMut(&str) -> Option<String>,
+) -> HtmlPage {
+    let title = TITLE_REGEX
+        .captures(html)
+        .map(|captures| html_to_text(&captures[1]))
+        .filter(|title| !title.is_empty());
+    let body = BODY_REGEX
+        .captures(html)
+        .map(|captures| captures.get(1).map_or("", |m| m.as_str()))
+        .unwrap_or(html);
+    let body = DROPPED_REGEX.replace_all(body, "");
+    let body = REFERENCE_REGEX.replace_all(&body, |captures: &Captures| {
+        let value = captures
+            .get(2)
+            .or_else(|| captures.get(3))
+            .map_or("", |m| m.as_str());
+        match rewrite_reference(&decode_entities(value)) {
+            Some(rewritten) => format!(
+                "{}=\"{}\"",
+                &captures[1],
+                rewritten.replace('&', "&amp;").replace('"', "&quot;")
+            ),
+            None => captures[0].to_string(),
+        }
+    });
+    let mut blocks = Vec::new();
+    split_into_blocks(&body, &mut blocks);
+    HtmlPage { title, blocks }
+}
+
+/// Plain text for the places that do not support markup, like the texts of quiz items.
+pub fn html_to_text(html: &str) -> String {
+    let html = DROPPED_REGEX.replace_all(html, "");
+    let html = LINE_BREAK_REGEX.replace_all(&html, "\n");
+    let text = decode_entities(&TAG_REGEX.replace_all(&html, ""));
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn decode_entities(text: &str) -> String {
+    ENTITY_REGEX
+        .replace_all(text, |captures: &Captures| {
+            let entity = &captures[1];
+            let decoded = if let Some(hex) = entity
+                .strip_prefix("#x")
+                .or_else(|| entity.strip_prefix("#X"))
+            {
+                u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+            } else if let Some(decimal) = entity.strip_prefix('#') {
+                decimal.parse().ok().and_then(char::from_u32)
+            } else {
+                match entity {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some('\u{a0}'),
+                    _ => None,
+                }
+            };
+            decoded.map_or_else(|| captures[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+fn split_into_blocks(html: &str, blocks: &mut Vec<GutenbergBlock>) {
+    // Lowercasing ASCII keeps the byte offsets the same.
+    let lowercase = html.to_ascii_lowercase();
+    let mut inline = String::new();
+    let mut position = 0;
+    while position < html.len() {
+        let Some(offset) = html[position..].find('<') else {
+            inline.push_str(&html[position..]);
+            break;
+        };
+        inline.push_str(&html[position..position + offset]);
+        position += offset;
+        let name = tag_name(&lowercase[position + 1..]);
+        let is_block = name == "p"
+            || name == "img"
+            || heading_level(name).is_some()
+            || CONTAINER_ELEMENTS.contains(&name)
+            || HTML_ELEMENTS.contains(&name);
+        if !is_block {
+            // An inline tag or a closing tag, kept in the current paragraph.
+            let end = html[position..]
+                .find('>')
+                .map_or(html.len(), |end| position + end + 1);
+            inline.push_str(&html[position..end]);
+            position = end;
+            continue;
+        }
+        push_paragraph(&inline, blocks);
+        inline.clear();
+        let element = find_element(html, &lowercase, position, name);
+        let outer = &html[position..element.end];
+        let inner = &html[element.inner_start..element.inner_end];
+        if name == "p" {
+            push_paragraph(inner, blocks);
+        } else if name == "img" {
+            let attributes = element_attributes(outer);
+            let url = attribute(&attributes, "src").unwrap_or_default();
+            let alt = attribute(&attributes, "alt").unwrap_or_default();
+            blocks.push(GutenbergBlock::block_with_name_and_attributes(
+                "core/image",
+                attributes! {
+                    "url": url,
+                    "alt": alt
+                },
+            ));
+        } else if let Some(level) = heading_level(name) {
+            blocks.push(GutenbergBlock::block_with_name_and_attributes(
+                "core/heading",
+                attributes! {
+                    "content": inner.trim(),
+                    "level": level
+                },
+            ));
+        } else if CONTAINER_ELEMENTS.contains(&name) {
+            split_into_blocks(inner, blocks);
+        } else {
+            blocks.push(GutenbergBlock::block_with_name_and_attributes(
+                "core/html",
+                attributes! {
+                    "content": outer.trim()
+                },
+            ));
+        }
+        position = element.end;
+    }
+    push_paragraph(&inline, blocks);
+}
+
+fn push_paragraph(content: &str, blocks: &mut Vec<GutenbergBlock>) {
+    let content = content.trim();
+    if html_to_text(content).trim().is_empty() && !content.contains("<img") {
+        return;
+    }
+    blocks.push(GutenbergBlock::paragraph(content));
+}
+
+/// The lowercase name of the tag at the start of `input`, which starts after the `<`.
+fn tag_name(input: &str) -> &str {
+    let end = input
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(input.len());
+    &input[..end]
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    match name.as_bytes() {
+        [b'h', level @ b'1'..=b'6'] => Some(level - b'0'),
+        _ => None,
+    }
+}
+
+struct ElementSpan {
+    inner_start: usize,
+    inner_end: usize,
+    end: usize,
+}
+
+/// Finds the end of the element that starts at `start`, counting the nested elements with the
+/// same name. An element that is never closed extends to the end of the input.
+fn find_element(html: &str, lowercase: &str, start: usize, name: &str) -> ElementSpan {
+    let inner_start = lowercase[start..]
+        .find('>')
+        .map_or(html.len(), |end| start + end + 1);
+    if VOID_ELEMENTS.contains(&name) || html[start..inner_start].ends_with("/>") {
+        return ElementSpan {
+            inner_start,
+            inner_end: inner_start,
+            end: inner_start,
+        };
+    }
+    let open = format!("<{name}");
+    let close = format!("</{name}");
+    let mut depth = 1;
+    let mut position = inner_start;
+    while let Some(offset) = lowercase[position..].find('<') {
+        let tag_start = position + offset;
+        let rest = &lowercase[tag_start..];
+        let tag_end = rest.find('>').map_or(html.len(), |end| tag_start + end + 1);
+        if rest.starts_with(&close) && tag_name(&rest[2..]) == name {
+            depth -= 1;
+            if depth == 0 {
+                return ElementSpan {
+                    inner_start,
+                    inner_end: tag_start,
+                    end: tag_end,
+                };
+            }
+        } else if rest.starts_with(&open) && tag_name(&rest[1..]) == name {
+            depth += 1;
+        }
+        position = tag_end;
+    }
+    ElementSpan {
+        inner_start,
+        inner_end: html.len(),
+        end: html.len(),
+    }
+}
+
+fn element_attributes(tag: &str) -> Vec<(String, String)> {
+    let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+    ATTRIBUTE_REGEX
+        .captures_iter(tag)
+        .map(|captures| {
+            let value = captures
+                .get(2)
+                .or_else(|| captures.get(3))
+                .map_or("", |m| m.as_str());
+            (captures[1].to_ascii_lowercase(), decode_entities(value))
+        })
+        .collect()
+}
+
+fn attribute(attributes: &[(String, String)], name: &str) -> Option<String> {
+    attributes
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names_and_contents(page: &HtmlPage) -> Vec<(String, String)> {
+        page.blocks
+            .iter()
+            .map(|block| {
+                let content = block
+                    .attributes
+                    .get("content")
+                    .or_else(|| block.attributes.get("url"))
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                (block.name.clone(), content)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn converts_the_top_level_elements_to_blocks() {
+        let page = to_blocks(
+            r#"<html><head><title>Welcome &amp; hello</title><style>p { color: red; }</style></head>
+<body>
+  <div class="wrapper">
+    <h2>Getting <em>started</em></h2>
+    <p>First paragraph with a <a href="syllabus.pdf">link</a>.</p>
+    <!-- a comment -->
+    Loose <strong>text</strong>
+    <ul><li>One</li><li>Two <ul><li>Nested</li></ul></li></ul>
+    <img src='images/fish.png' alt="A fish">
+    <p>&nbsp;</p>
+  </div>
+  <script>alert("hi")</script>
+</body></html>"#,
+            |reference| {
+                (!reference.starts_with("http"))
+                    .then(|| format!("https://cdn.example.com/{reference}"))
+            },
+        );
+        assert_eq!(page.title.as_deref(), Some("Welcome & hello"));
+        assert_eq!(
+            names_and_contents(&page),
+            vec![
+                (
+                    "core/heading".to_string(),
+                    "Getting <em>started</em>".to_string()
+                ),
+                (
+                    "core/paragraph".to_string(),
+                    r#"First paragraph with a <a href="https://cdn.example.com/syllabus.pdf">link</a>."#
+                        .to_string()
+                ),
+                (
+                    "core/paragraph".to_string(),
+                    "Loose <strong>text</strong>".to_string()
+                ),
+                (
+                    "core/html".to_string(),
+                    "<ul><li>One</li><li>Two <ul><li>Nested</li></ul></li></ul>".to_string()
+                ),
+                (
+                    "core/image".to_string(),
+                    "https://cdn.example.com/images/fish.png".to_string()
+                ),
+            ]
+        );
+        assert_eq!(page.blocks[0].attributes["level"], 2);
+        assert_eq!(page.blocks[4].attributes["alt"], "A fish");
+    }
+
+    #[test]
+    fn converts_html_to_text() {
+        assert_eq!(
+            html_to_text("<p>What is <b>2 &gt; 1</b>?</p><p>Pick&#160;one.</p>"),
+            "What is 2 > 1?\nPick one."
+        );
+    }
+}
@@ -0,0 +1,197 @@
+//! Reading `imsmanifest.xml`, the table of contents of a cartridge.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+
+use super::xml::{self, XmlElement};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub title: Option<String>,
+    /// The items of the organization below its root item.
+    pub items: Vec<OrganizationItem>,
+    pub resources: HashMap<String, Resource>,
+}
+
+/// A node in the organization. Modules have children, the items that show content refer to a
+/// resource.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrganizationItem {
+    pub title: Option<String>,
+    pub identifierref: Option<String>,
+    pub children: Vec<OrganizationItem>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    pub identifier: String,
+    pub resource_type: String,
+    /// The file that is shown, relative to the root of the package.
+    pub href: Option<String>,
+    /// All the files of the resource, relative to the root of the package.
+    pub files: Vec<String>,
+}
+
+pub fn parse(input: &str) -> anyhow::Result<Manifest> {
+    let root = xml::parse(input)?;
+    if root.name != "manifest" {
+        anyhow::bail!("imsmanifest.xml is not a manifest");
+    }
+    let title = root
+        .child("metadata")
+        .and_then(|metadata| metadata.descendants("general").into_iter().next())
+        .and_then(|general| general.child("title"))
+        .map(|title| title.text().trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let items = root
+        .child("organizations")
+        .and_then(|organizations| organizations.child("organization"))
+        .map(|organization| {
+            let top_level = organization
+                .children_named("item")
+                .map(organization_item)
+                .collect::<Vec<_>>();
+            // Rooted hierarchies have a single untitled item that contains everything else.
+            match <[OrganizationItem; 1]>::try_from(top_level) {
+                Ok([root_item]) if root_item.identifierref.is_none() => root_item.children,
+                Ok(single) => Vec::from(single),
+                Err(top_level) => top_level,
+            }
+        })
+        .unwrap_or_default();
+
+    let mut resources = HashMap::new();
+    if let Some(resource_elements) = root.child("resources") {
+        for element in resource_elements.children_named("resource") {
+            let resource = resource(element)?;
+            resources.insert(resource.identifier.clone(), resource);
+        }
+    }
+
+    Ok(Manifest {
+        title,
+        items,
+        resources,
+    })
+}
+
+fn organization_item(element: &XmlElement) -> OrganizationItem {
+    OrganizationItem {
+        title: element
+            .child("title")
+            .map(|title| title.text().trim().to_string())
+            .filter(|title| !title.is_empty()),
+        identifierref: element.attribute("identifierref").map(str::to_string),
+        children: element
+            .children_named("item")
+            .map(organization_item)
+            .collect(),
+    }
+}
+
+fn resource(element: &XmlElement) -> anyhow::Result<Resource> {
+    let identifier = element
+        .attribute("identifier")
+        .context("A resource has no identifier")?
+        .to_string();
+    // `xml:base` is stored without its prefix.
+    let base = element.attribute("base").unwrap_or_default();
+    let href = element
+        .attribute("href")
+        .map(|href| normalize_path(&format!("{base}{href}")));
+    let files = element
+        .children_named("file")
+        .filter_map(|file| file.attribute("href"))
+        .map(|href| normalize_path(&format!("{base}{href}")))
+        .collect();
+    Ok(Resource {
+        identifier,
+        resource_type: element.attribute("type").unwrap_or_default().to_string(),
+        href,
+        files,
+    })
+}
+
+/// Resolves `.` and `..` segments and drops leading slashes so that the path matches the names of
+/// the entries in the zip file.
+pub fn normalize_path(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_the_organization_and_resources() {
+        let manifest = parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest identifier="cctd0001" xmlns="http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1" xmlns:lomimscc="http://ltsc.ieee.org/xsd/imsccv1p1/LOM/manifest">
+  <metadata>
+    <schema>IMS Common Cartridge</schema>
+    <schemaversion>1.1.0</schemaversion>
+    <lomimscc:lom>
+      <lomimscc:general>
+        <lomimscc:title><lomimscc:string language="en">Introduction to Fish</lomimscc:string></lomimscc:title>
+      </lomimscc:general>
+    </lomimscc:lom>
+  </metadata>
+  <organizations>
+    <organization identifier="org" structure="rooted-hierarchy">
+      <item identifier="root">
+        <item identifier="m1">
+          <title>Week 1</title>
+          <item identifier="i1" identifierref="r1"><title>Welcome</title></item>
+          <item identifier="i2" identifierref="r2"><title>Quiz</title></item>
+        </item>
+      </item>
+    </organization>
+  </organizations>
+  <resources>
+    <resource identifier="r1" type="webcontent" href="web_resources/week1/./welcome.html">
+      <file href="web_resources/week1/welcome.html"/>
+    </resource>
+    <resource identifier="r2" type="imsqti_xmlv1p2/imscc_xmlv1p1/assessment" xml:base="r2/">
+      <file href="assessment.xml"/>
+    </resource>
+  </resources>
+</manifest>"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.title.as_deref(), Some("Introduction to Fish"));
+        assert_eq!(manifest.items.len(), 1);
+        let week = &manifest.items[0];
+        assert_eq!(week.title.as_deref(), Some("Week 1"));
+        assert_eq!(week.identifierref, None);
+        assert_eq!(
+            week.children
+                .iter()
+                .map(|item| item.identifierref.as_deref())
+                .collect::<Vec<_>>(),
+            vec![Some("r1"), Some("r2")]
+        );
+        assert_eq!(
+            manifest.resources["r1"].href.as_deref(),
+            Some("web_resources/week1/welcome.html")
+        );
+        assert_eq!(manifest.resources["r2"].files, vec!["r2/assessment.xml"]);
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        assert_eq!(normalize_path("/a/./b/../c.html"), "a/c.html");
+        assert_eq!(normalize_path("../../c.html"), "c.html");
+    }
+}
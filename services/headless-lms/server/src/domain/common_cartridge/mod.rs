@@ -0,0 +1,15 @@
+/*!
+Importing IMS Common Cartridge packages to a course.
+
+The organization of the cartridge becomes the structure of the course: its modules become chapters
+and the items in them pages. Web content is converted to Gutenberg blocks, web links become a page
+with the link and QTI assessments a quizzes exercise on a page of its own. The files the pages link
+to are uploaded to the course. Everything else, like discussion topics and the questions that have
+no counterpart in quizzes, is listed as unsupported in the report, which is all a dry run returns.
+*/
+
+pub mod html;
+pub mod manifest;
+pub mod package;
+pub mod qti;
+pub mod xml;
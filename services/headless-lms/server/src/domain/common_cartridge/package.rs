@@ -0,0 +1,690 @@
+//! Planning the import of a cartridge: what chapters and pages it becomes and what is left out.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    io::{Read, Seek},
+};
+
+use anyhow::Context;
+use headless_lms_utils::document_schema_processor::GutenbergBlock;
+use models::{
+    exercises::ExerciseScoringStrategy,
+    pages::{CmsPageExercise, CmsPageExerciseSlide, CmsPageExerciseTask, CmsPageUpdate},
+};
+use utoipa::ToSchema;
+use zip::ZipArchive;
+
+use super::{
+    html,
+    manifest::{self, Manifest, OrganizationItem, Resource},
+    qti, xml,
+};
+use crate::prelude::*;
+
+pub const MANIFEST_PATH: &str = "imsmanifest.xml";
+/// Files larger than this are not uploaded to the course, and documents larger than this are not read.
+pub const MAX_FILE_BYTES: u64 = 25 * 1024 * 1024;
+/// Prefix for the files of the resource in the XML files of a cartridge.
+const FILE_BASE: &str = "$IMS-CC-FILEBASE$";
+
+/// What a cartridge was or would be imported as.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct CommonCartridgeImportReport {
+    pub title: Option<String>,
+    pub chapters: Vec<ImportedChapter>,
+    /// The files of the package that the pages link to, uploaded to the course.
+    pub files: Vec<String>,
+    pub unsupported: Vec<UnsupportedContent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ImportedChapter {
+    pub name: String,
+    /// Not set on a dry run.
+    pub chapter_id: Option<Uuid>,
+    pub pages: Vec<ImportedPage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct ImportedPage {
+    pub title: String,
+    pub kind: ImportedPageKind,
+    /// The number of questions that were mapped to quiz items.
+    pub quiz_item_count: usize,
+    /// Not set on a dry run.
+    pub page_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportedPageKind {
+    WebContent,
+    WebLink,
+    Assessment,
+}
+
+/// Something in the cartridge that has no counterpart in a course and is not imported.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct UnsupportedContent {
+    pub title: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportPlan {
+    pub title: Option<String>,
+    pub chapters: Vec<PlannedChapter>,
+    /// Paths of the package files the pages link to.
+    pub files: BTreeSet<String>,
+    pub unsupported: Vec<UnsupportedContent>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedChapter {
+    pub name: String,
+    pub pages: Vec<PlannedPage>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedPage {
+    pub kind: ImportedPageKind,
+    pub quiz_item_count: usize,
+    /// The page without its chapter, which is only known once the chapter has been created.
+    pub page: CmsPageUpdate,
+}
+
+impl ImportPlan {
+    pub fn report(&self) -> CommonCartridgeImportReport {
+        CommonCartridgeImportReport {
+            title: self.title.clone(),
+            chapters: self
+                .chapters
+                .iter()
+                .map(|chapter| ImportedChapter {
+                    name: chapter.name.clone(),
+                    chapter_id: None,
+                    pages: chapter
+                        .pages
+                        .iter()
+                        .map(|page| ImportedPage {
+                            title: page.page.title.clone(),
+                            kind: page.kind,
+                            quiz_item_count: page.quiz_item_count,
+                            page_id: None,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            files: self.files.iter().cloned().collect(),
+            unsupported: self.unsupported.clone(),
+        }
+    }
+}
+
+pub struct Package<R> {
+    archive: ZipArchive<R>,
+    manifest: Manifest,
+}
+
+impl<R: Read + Seek> Package<R> {
+    pub fn open(reader: R) -> anyhow::Result<Self> {
+        let mut archive = ZipArchive::new(reader).context("The file is not a zip file")?;
+        let manifest = read_text(&mut archive, MANIFEST_PATH)
+            .context("The package has no imsmanifest.xml, so it is not a Common Cartridge")?;
+        let manifest = manifest::parse(&manifest).context("Failed to read imsmanifest.xml")?;
+        Ok(Self { archive, manifest })
+    }
+
+    pub fn read_file(&mut self, path: &str) -> anyhow::Result<Vec<u8>> {
+        let file = self.archive.by_name(path)?;
+        read_limited(file, path)
+    }
+
+    /// Plans the import. The links to the files of the package are replaced with the addresses in
+    /// `file_urls`, so a dry run passes an empty map and an import the addresses of the files in
+    /// [`ImportPlan::files`] after uploading them.
+    pub fn plan(&mut self, file_urls: &HashMap<String, String>) -> anyhow::Result<ImportPlan> {
+        let mut plan = ImportPlan {
+            title: self.manifest.title.clone(),
+            chapters: Vec::new(),
+            files: BTreeSet::new(),
+            unsupported: Vec::new(),
+        };
+        let items = self.manifest.items.clone();
+        // Pages outside the modules are gathered to a chapter of their own, since top level pages
+        // cannot have exercises.
+        let mut loose_pages = Vec::new();
+        for item in &items {
+            if item.identifierref.is_some() {
+                if let Some(page) = self.plan_page(item, file_urls, &mut plan)? {
+                    loose_pages.push(page);
+                }
+                continue;
+            }
+            let mut pages = Vec::new();
+            for leaf in leaves(item) {
+                if let Some(page) = self.plan_page(leaf, file_urls, &mut plan)? {
+                    pages.push(page);
+                }
+            }
+            if pages.is_empty() {
+                continue;
+            }
+            plan.chapters.push(PlannedChapter {
+                name: item
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| "Untitled chapter".to_string()),
+                pages,
+            });
+        }
+        if !loose_pages.is_empty() {
+            plan.chapters.push(PlannedChapter {
+                name: plan
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| "Imported content".to_string()),
+                pages: loose_pages,
+            });
+        }
+        Ok(plan)
+    }
+
+    fn plan_page(
+        &mut self,
+        item: &OrganizationItem,
+        file_urls: &HashMap<String, String>,
+        plan: &mut ImportPlan,
+    ) -> anyhow::Result<Option<PlannedPage>> {
+        let item_title = item.title.clone().unwrap_or_default();
+        let Some(resource) = item
+            .identifierref
+            .as_ref()
+            .and_then(|identifier| self.manifest.resources.get(identifier))
+            .cloned()
+        else {
+            plan.unsupported.push(UnsupportedContent {
+                title: item_title,
+                reason: "The item refers to a resource that is not in the package.".to_string(),
+            });
+            return Ok(None);
+        };
+        let resource_type = resource.resource_type.as_str();
+        let page = if resource_type == "webcontent" {
+            self.plan_web_content(&item_title, &resource, file_urls, plan)?
+        } else if resource_type.starts_with("imswl_xmlv1p") {
+            self.plan_web_link(&item_title, &resource)?
+        } else if resource_type.starts_with("imsqti_xmlv1p2/")
+            && resource_type.ends_with("/assessment")
+        {
+            self.plan_assessment(&item_title, &resource, plan)?
+        } else {
+            let reason = if resource_type.starts_with("imsdt_xmlv1p") {
+                "Discussion topics are not supported.".to_string()
+            } else if resource_type.starts_with("imsbasiclti_xmlv1p") {
+                "LTI links are not supported.".to_string()
+            } else {
+                format!("Resources of the type {resource_type} are not supported.")
+            };
+            plan.unsupported.push(UnsupportedContent {
+                title: title_or_identifier(&item_title, &resource),
+                reason,
+            });
+            None
+        };
+        Ok(page)
+    }
+
+    fn plan_web_content(
+        &mut self,
+        item_title: &str,
+        resource: &Resource,
+        file_urls: &HashMap<String, String>,
+        plan: &mut ImportPlan,
+    ) -> anyhow::Result<Option<PlannedPage>> {
+        let Some(href) = resource
+            .href
+            .clone()
+            .or_else(|| resource.files.first().cloned())
+        else {
+            plan.unsupported.push(UnsupportedContent {
+                title: title_or_identifier(item_title, resource),
+                reason: "The web content has no files.".to_string(),
+            });
+            return Ok(None);
+        };
+        let lowercase = href.to_ascii_lowercase();
+        let (title, content) = if lowercase.ends_with(".html") || lowercase.ends_with(".htm") {
+            let document = read_text(&mut self.archive, &href)?;
+            let directory = href.rsplit_once('/').map_or("", |(directory, _)| directory);
+            let page = html::to_blocks(&document, |reference| {
+                let path = package_path(directory, reference)?;
+                self.archive.index_for_name(&path)?;
+                plan.files.insert(path.clone());
+                file_urls.get(&path).cloned()
+            });
+            (non_empty(item_title).or(page.title), page.blocks)
+        } else {
+            // Other files, like PDFs, are linked from a page of their own.
+            plan.files.insert(href.clone());
+            let file_name = href.rsplit('/').next().unwrap_or(&href).to_string();
+            let url = file_urls
+                .get(&href)
+                .cloned()
+                .unwrap_or_else(|| href.clone());
+            (
+                non_empty(item_title).or(Some(file_name.clone())),
+                vec![GutenbergBlock::paragraph(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape_html(&url),
+                    escape_html(&file_name)
+                ))],
+            )
+        };
+        Ok(Some(PlannedPage {
+            kind: ImportedPageKind::WebContent,
+            quiz_item_count: 0,
+            page: page_update(
+                title.unwrap_or_else(|| resource.identifier.clone()),
+                content,
+            ),
+        }))
+    }
+
+    fn plan_web_link(
+        &mut self,
+        item_title: &str,
+        resource: &Resource,
+    ) -> anyhow::Result<Option<PlannedPage>> {
+        let root = self.read_xml(resource)?;
+        let link_title = root
+            .child("title")
+            .map(|title| title.text().trim().to_string())
+            .filter(|title| !title.is_empty());
+        let url = root
+            .child("url")
+            .and_then(|url| url.attribute("href"))
+            .context("The web link has no address")?
+            .to_string();
+        let title = non_empty(item_title)
+            .or(link_title)
+            .unwrap_or_else(|| url.clone());
+        let content = vec![GutenbergBlock::paragraph(&format!(
+            "<a href=\"{}\">{}</a>",
+            escape_html(&url),
+            escape_html(&title)
+        ))];
+        Ok(Some(PlannedPage {
+            kind: ImportedPageKind::WebLink,
+            quiz_item_count: 0,
+            page: page_update(title, content),
+        }))
+    }
+
+    fn plan_assessment(
+        &mut self,
+        item_title: &str,
+        resource: &Resource,
+        plan: &mut ImportPlan,
+    ) -> anyhow::Result<Option<PlannedPage>> {
+        let assessment = qti::parse(&self.read_xml(resource)?)?;
+        let title = non_empty(item_title)
+            .or(assessment.title.clone())
+            .unwrap_or_else(|| resource.identifier.clone());
+        plan.unsupported.extend(
+            assessment
+                .unsupported
+                .iter()
+                .map(|question| UnsupportedContent {
+                    title: format!("{title}: {}", question.title),
+                    reason: question.reason.clone(),
+                }),
+        );
+        if assessment.items.is_empty() {
+            plan.unsupported.push(UnsupportedContent {
+                title,
+                reason: "None of the questions of the assessment are supported.".to_string(),
+            });
+            return Ok(None);
+        }
+
+        let exercise_id = Uuid::new_v4();
+        let exercise_slide_id = Uuid::new_v4();
+        let block = GutenbergBlock::block_with_name_and_attributes(
+            "moocfi/exercise",
+            headless_lms_utils::attributes! {
+                "id": exercise_id,
+                "name": title,
+                "dropCap": false,
+            },
+        );
+        let mut page = page_update(title.clone(), vec![block]);
+        page.exercises.push(CmsPageExercise {
+            id: exercise_id,
+            name: title,
+            order_number: 1,
+            score_maximum: assessment.items.len() as i32,
+            max_tries_per_slide: None,
+            limit_number_of_tries: false,
+            deadline: None,
+            needs_peer_review: false,
+            needs_self_review: false,
+            peer_or_self_review_config: None,
+            peer_or_self_review_questions: None,
+            use_course_default_peer_or_self_review_config: true,
+            teacher_reviews_answer_after_locking: true,
+            scoring_strategy: ExerciseScoringStrategy::BestAttempt,
+            scoring_strategy_attempt_count: None,
+            scoring_strategy_penalty_percent: None,
+        });
+        page.exercise_slides.push(CmsPageExerciseSlide {
+            id: exercise_slide_id,
+            exercise_id,
+            order_number: 1,
+        });
+        page.exercise_tasks.push(CmsPageExerciseTask {
+            id: Uuid::new_v4(),
+            exercise_slide_id,
+            assignment: serde_json::json!([]),
+            exercise_type: "quizzes".to_string(),
+            private_spec: Some(assessment.private_spec()),
+            order_number: 0,
+        });
+        Ok(Some(PlannedPage {
+            kind: ImportedPageKind::Assessment,
+            quiz_item_count: assessment.items.len(),
+            page,
+        }))
+    }
+
+    /// The XML file of a resource that is described by a file instead of a web page.
+    fn read_xml(&mut self, resource: &Resource) -> anyhow::Result<xml::XmlElement> {
+        let path = resource
+            .files
+            .iter()
+            .chain(resource.href.iter())
+            .find(|path| path.to_ascii_lowercase().ends_with(".xml"))
+            .with_context(|| format!("The resource {} has no XML file", resource.identifier))?;
+        let text = read_text(&mut self.archive, path)?;
+        xml::parse(&text).with_context(|| format!("Failed to read {path}"))
+    }
+}
+
+fn read_text<R: Read + Seek>(archive: &mut ZipArchive<R>, path: &str) -> anyhow::Result<String> {
+    let file = archive
+        .by_name(path)
+        .with_context(|| format!("{path} is missing from the package"))?;
+    let bytes = read_limited(file, path)?;
+    String::from_utf8(bytes).with_context(|| format!("{path} is not UTF-8 text"))
+}
+
+/// Reads at most [`MAX_FILE_BYTES`] of an entry. The size in the zip header is not trusted, as a
+/// crafted package can declare a small size for an entry that decompresses to much more.
+fn read_limited(file: impl Read, path: &str) -> anyhow::Result<Vec<u8>> {
+    let mut contents = Vec::new();
+    file.take(MAX_FILE_BYTES + 1).read_to_end(&mut contents)?;
+    if contents.len() as u64 > MAX_FILE_BYTES {
+        anyhow::bail!("{path} is larger than {MAX_FILE_BYTES} bytes");
+    }
+    Ok(contents)
+}
+
+/// The items that show content, below the item in the organization.
+fn leaves(item: &OrganizationItem) -> Vec<&OrganizationItem> {
+    let mut res = Vec::new();
+    for child in &item.children {
+        if child.identifierref.is_some() {
+            res.push(child);
+        }
+        res.extend(leaves(child));
+    }
+    res
+}
+
+/// The path in the package a link on a page in the directory points to, if it is not an absolute
+/// address.
+fn package_path(directory: &str, reference: &str) -> Option<String> {
+    if reference.is_empty()
+        || reference.starts_with('#')
+        || reference.starts_with("//")
+        || url::Url::parse(reference).is_ok()
+    {
+        return None;
+    }
+    let reference = reference.split(['#', '?']).next().unwrap_or(reference);
+    let reference = percent_decode(reference);
+    let path = match reference.strip_prefix(FILE_BASE) {
+        Some(rest) => format!("{directory}/{rest}"),
+        None => format!("{directory}/{reference}"),
+    };
+    Some(manifest::normalize_path(&path))
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut res = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%'
+            && let Some(value) = input
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            res.push(value);
+            index += 3;
+        } else {
+            res.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8_lossy(&res).into_owned()
+}
+
+fn page_update(title: String, content: Vec<GutenbergBlock>) -> CmsPageUpdate {
+    CmsPageUpdate {
+        content,
+        exercises: Vec::new(),
+        exercise_slides: Vec::new(),
+        exercise_tasks: Vec::new(),
+        url_path: String::new(),
+        title,
+        chapter_id: None,
+        hidden: false,
+    }
+}
+
+fn title_or_identifier(item_title: &str, resource: &Resource) -> String {
+    non_empty(item_title).unwrap_or_else(|| resource.identifier.clone())
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The content type of a file, from its extension.
+pub fn mime_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    fn package(files: &[(&str, &str)]) -> Package<Cursor<Vec<u8>>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, contents) in files {
+            writer
+                .start_file(*path, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        Package::open(writer.finish().unwrap()).unwrap()
+    }
+
+    const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest identifier="m" xmlns="http://www.imsglobal.org/xsd/imsccv1p1/imscp_v1p1">
+  <organizations>
+    <organization identifier="org" structure="rooted-hierarchy">
+      <item identifier="root">
+        <item identifier="m1">
+          <title>Week 1</title>
+          <item identifier="i1" identifierref="r1"><title>Welcome</title></item>
+          <item identifier="folder">
+            <title>Extra</title>
+            <item identifier="i2" identifierref="r2"><title>Course site</title></item>
+          </item>
+          <item identifier="i3" identifierref="r3"><title>Quiz</title></item>
+          <item identifier="i4" identifierref="r4"><title>Discuss</title></item>
+        </item>
+        <item identifier="i5" identifierref="r1"><title>Loose page</title></item>
+      </item>
+    </organization>
+  </organizations>
+  <resources>
+    <resource identifier="r1" type="webcontent" href="web_resources/welcome.html">
+      <file href="web_resources/welcome.html"/>
+    </resource>
+    <resource identifier="r2" type="imswl_xmlv1p1">
+      <file href="r2/link.xml"/>
+    </resource>
+    <resource identifier="r3" type="imsqti_xmlv1p2/imscc_xmlv1p1/assessment">
+      <file href="r3/assessment.xml"/>
+    </resource>
+    <resource identifier="r4" type="imsdt_xmlv1p1">
+      <file href="r4/topic.xml"/>
+    </resource>
+    <resource identifier="r5" type="webcontent" href="web_resources/images/fish%20one.png">
+      <file href="web_resources/images/fish one.png"/>
+    </resource>
+  </resources>
+</manifest>"#;
+
+    const WELCOME: &str = r#"<html><body><h1>Welcome</h1><p>Hello</p><img src="images/fish%20one.png" alt=""><p><a href="https://example.com/">Elsewhere</a></p></body></html>"#;
+
+    const LINK: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<webLink xmlns="http://www.imsglobal.org/xsd/imsccv1p1/imswl_v1p1"><title>Site</title><url href="https://example.com/course"/></webLink>"#;
+
+    const ASSESSMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<questestinterop><assessment ident="a" title="Quiz"><section ident="s">
+  <item ident="q1" title="Essay">
+    <itemmetadata><qtimetadata><qtimetadatafield><fieldlabel>cc_profile</fieldlabel><fieldentry>cc.essay.v0p1</fieldentry></qtimetadatafield></qtimetadata></itemmetadata>
+    <presentation><material><mattext>Why?</mattext></material><response_str ident="r"><render_fib/></response_str></presentation>
+  </item>
+</section></assessment></questestinterop>"#;
+
+    #[test]
+    fn plans_chapters_pages_and_quizzes() {
+        let mut package = package(&[
+            (MANIFEST_PATH, MANIFEST),
+            ("web_resources/welcome.html", WELCOME),
+            ("web_resources/images/fish one.png", "png"),
+            ("r2/link.xml", LINK),
+            ("r3/assessment.xml", ASSESSMENT),
+            ("r4/topic.xml", "<topic/>"),
+        ]);
+
+        let dry_run = package.plan(&HashMap::new()).unwrap();
+        let report = dry_run.report();
+        assert_eq!(
+            report
+                .chapters
+                .iter()
+                .map(|chapter| (
+                    chapter.name.as_str(),
+                    chapter
+                        .pages
+                        .iter()
+                        .map(|page| (page.title.as_str(), page.kind, page.quiz_item_count))
+                        .collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "Week 1",
+                    vec![
+                        ("Welcome", ImportedPageKind::WebContent, 0),
+                        ("Course site", ImportedPageKind::WebLink, 0),
+                        ("Quiz", ImportedPageKind::Assessment, 1),
+                    ]
+                ),
+                (
+                    "Imported content",
+                    vec![("Loose page", ImportedPageKind::WebContent, 0)]
+                ),
+            ]
+        );
+        assert_eq!(report.files, vec!["web_resources/images/fish one.png"]);
+        assert_eq!(
+            report.unsupported,
+            vec![UnsupportedContent {
+                title: "Discuss".to_string(),
+                reason: "Discussion topics are not supported.".to_string(),
+            }]
+        );
+        let quiz = &dry_run.chapters[0].pages[2].page;
+        assert_eq!(quiz.exercises.len(), 1);
+        assert_eq!(quiz.exercise_tasks[0].exercise_type, "quizzes");
+
+        let uploaded = HashMap::from([(
+            "web_resources/images/fish one.png".to_string(),
+            "https://cdn.example.com/fish.png".to_string(),
+        )]);
+        let import = package.plan(&uploaded).unwrap();
+        let welcome = &import.chapters[0].pages[0].page.content;
+        assert_eq!(welcome[2].name, "core/image");
+        assert_eq!(
+            welcome[2].attributes["url"],
+            "https://cdn.example.com/fish.png"
+        );
+        assert_eq!(
+            welcome[3].attributes["content"],
+            r#"<a href="https://example.com/">Elsewhere</a>"#
+        );
+    }
+
+    #[test]
+    fn rejects_packages_without_a_manifest() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .start_file("index.html", SimpleFileOptions::default())
+            .unwrap();
+        assert!(Package::open(writer.finish().unwrap()).is_err());
+    }
+
+    #[test]
+    fn reading_stops_at_the_size_limit() {
+        assert_eq!(
+            read_limited(std::io::repeat(0).take(10), "small.bin").unwrap(),
+            vec![0; 10]
+        );
+        // An entry that decompresses to more than it declares is cut off instead of read whole.
+        assert!(read_limited(std::io::repeat(0), "bomb.bin").is_err());
+    }
+}
@@ -0,0 +1,408 @@
+//! Mapping QTI 1.2 assessments, in the profile used by Common Cartridge, to quizzes.
+//!
+//! Every assessment becomes one quiz with an item per question. Multiple choice, true/false and
+//! multiple response questions become multiple choice items, fill in the blank questions closed
+//! ended questions with the accepted answers, and essays essay items. The other questions are
+//! returned as unsupported.
+
+use serde_json::{Value, json};
+use uuid::Uuid;
+
+use super::{html::html_to_text, xml::XmlElement};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assessment {
+    pub title: Option<String>,
+    /// The quiz items of the questions that could be mapped.
+    pub items: Vec<Value>,
+    pub unsupported: Vec<UnsupportedQuestion>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsupportedQuestion {
+    pub title: String,
+    pub reason: String,
+}
+
+impl Assessment {
+    /// The private spec of the quizzes exercise service.
+    pub fn private_spec(&self) -> Value {
+        json!({
+            "version": "4",
+            "awardPointsEvenIfWrong": false,
+            "grantPointsPolicy": "grant_whenever_possible",
+            "items": self.items,
+            "title": null,
+            "body": null,
+            "quizItemDisplayDirection": "vertical",
+            "feedbackMessages": [],
+        })
+    }
+}
+
+enum QuestionType {
+    Choice { multiple: bool },
+    FillInTheBlank,
+    Essay,
+}
+
+pub fn parse(root: &XmlElement) -> anyhow::Result<Assessment> {
+    if root.name != "questestinterop" {
+        anyhow::bail!("The assessment is not a QTI document");
+    }
+    let assessment = root.child("assessment");
+    let title = assessment
+        .and_then(|assessment| assessment.attribute("title"))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty());
+
+    let mut items = Vec::new();
+    let mut unsupported = Vec::new();
+    for (index, item) in root.descendants("item").into_iter().enumerate() {
+        let item_title = item
+            .attribute("title")
+            .map(str::to_string)
+            .or_else(|| item.attribute("ident").map(str::to_string))
+            .unwrap_or_else(|| format!("Question {}", index + 1));
+        match quiz_item(item, items.len()) {
+            Ok(quiz_item) => items.push(quiz_item),
+            Err(reason) => unsupported.push(UnsupportedQuestion {
+                title: item_title,
+                reason,
+            }),
+        }
+    }
+    Ok(Assessment {
+        title,
+        items,
+        unsupported,
+    })
+}
+
+/// The quiz item for the question, or the reason it could not be mapped.
+fn quiz_item(item: &XmlElement, order: usize) -> Result<Value, String> {
+    let body = item
+        .child("presentation")
+        .and_then(|presentation| presentation.child("material"))
+        .map(material_text)
+        .filter(|body| !body.is_empty());
+    let id = Uuid::new_v4();
+    match question_type(item)? {
+        QuestionType::Choice { multiple } => {
+            let correct = correct_values(item, "varequal");
+            let options = item
+                .descendants("response_label")
+                .into_iter()
+                .enumerate()
+                .map(|(index, label)| {
+                    let ident = label.attribute("ident").unwrap_or_default();
+                    json!({
+                        "id": Uuid::new_v4(),
+                        "order": index,
+                        "correct": correct.iter().any(|value| value == ident),
+                        "title": label.child("material").map(material_text).unwrap_or_default(),
+                        "body": null,
+                        "feedbackMessages": [],
+                    })
+                })
+                .collect::<Vec<_>>();
+            if options.is_empty() {
+                return Err("The question has no options.".to_string());
+            }
+            if !options.iter().any(|option| option["correct"] == true) {
+                return Err("The question has no correct option.".to_string());
+            }
+            Ok(json!({
+                "type": "multiple-choice",
+                "id": id,
+                "order": order,
+                "shuffleOptions": false,
+                "allowSelectingMultipleOptions": multiple,
+                "fogOfWar": false,
+                "options": options,
+                "title": null,
+                "body": body,
+                "feedbackMessages": [],
+                "optionDisplayDirection": "vertical",
+                "multipleChoiceMultipleOptionsGradingPolicy": "default",
+            }))
+        }
+        QuestionType::FillInTheBlank => {
+            if !correct_values(item, "varsubstring").is_empty() {
+                return Err("Answers matched by a substring are not supported.".to_string());
+            }
+            let accepted_answers = correct_values(item, "varequal");
+            if accepted_answers.is_empty() {
+                return Err("The question has no accepted answers.".to_string());
+            }
+            let case_sensitive = item
+                .descendants("varequal")
+                .iter()
+                .any(|varequal| varequal.attribute("case") == Some("Yes"));
+            Ok(json!({
+                "type": "closed-ended-question",
+                "id": id,
+                "order": order,
+                "gradingStrategy": {
+                    "strategy": "exact-match",
+                    "acceptedAnswers": accepted_answers,
+                    "caseSensitive": case_sensitive,
+                    "trimWhitespace": true,
+                },
+                "formatRegex": null,
+                "title": null,
+                "body": body,
+                "feedbackMessages": [],
+            }))
+        }
+        QuestionType::Essay => Ok(json!({
+            "type": "essay",
+            "id": id,
+            "order": order,
+            "minWords": null,
+            "maxWords": null,
+            "title": null,
+            "body": body,
+            "feedbackMessages": [],
+        })),
+    }
+}
+
+/// The type from the `cc_profile` metadata field, or from the `question_type` field that some
+/// systems use instead. Without either the type is inferred from the response element.
+fn question_type(item: &XmlElement) -> Result<QuestionType, String> {
+    let profile = item
+        .descendants("qtimetadatafield")
+        .into_iter()
+        .find(|field| {
+            field
+                .child("fieldlabel")
+                .is_some_and(|label| matches!(label.text().trim(), "cc_profile" | "question_type"))
+        })
+        .and_then(|field| field.child("fieldentry"))
+        .map(|entry| entry.text().trim().to_string());
+    match profile.as_deref() {
+        Some("cc.multiple_choice.v0p1" | "multiple_choice_question")
+        | Some("cc.true_false.v0p1" | "true_false_question") => {
+            Ok(QuestionType::Choice { multiple: false })
+        }
+        Some("cc.multiple_response.v0p1" | "multiple_answers_question") => {
+            Ok(QuestionType::Choice { multiple: true })
+        }
+        Some("cc.fib.v0p1" | "cc.pattern_match.v0p1" | "short_answer_question") => {
+            Ok(QuestionType::FillInTheBlank)
+        }
+        Some("cc.essay.v0p1" | "essay_question") => Ok(QuestionType::Essay),
+        Some(other) => Err(format!("Questions of the type {other} are not supported.")),
+        None => {
+            if let Some(response) = item.descendants("response_lid").first() {
+                let multiple = response.attribute("rcardinality") == Some("Multiple");
+                Ok(QuestionType::Choice { multiple })
+            } else if !item.descendants("response_str").is_empty() {
+                if item.descendants("varequal").is_empty() {
+                    Ok(QuestionType::Essay)
+                } else {
+                    Ok(QuestionType::FillInTheBlank)
+                }
+            } else {
+                Err("The type of the question could not be recognized.".to_string())
+            }
+        }
+    }
+}
+
+/// The values of the conditions that give points, excluding the ones inside a `<not>`.
+fn correct_values(item: &XmlElement, condition: &str) -> Vec<String> {
+    fn collect(element: &XmlElement, condition: &str, values: &mut Vec<String>) {
+        for child in element.elements() {
+            if child.name == condition {
+                values.push(child.text().trim().to_string());
+            } else if child.name != "not" {
+                collect(child, condition, values);
+            }
+        }
+    }
+    let mut values = Vec::new();
+    for respcondition in item.descendants("respcondition") {
+        let gives_points = respcondition.children_named("setvar").any(|setvar| {
+            setvar
+                .text()
+                .trim()
+                .parse::<f64>()
+                .is_ok_and(|value| value > 0.0)
+        });
+        if let Some(conditionvar) = respcondition.child("conditionvar")
+            && gives_points
+        {
+            collect(conditionvar, condition, &mut values);
+        }
+    }
+    values.dedup();
+    values
+}
+
+fn material_text(material: &XmlElement) -> String {
+    material
+        .descendants("mattext")
+        .iter()
+        .map(|mattext| {
+            if mattext.attribute("texttype") == Some("text/html") {
+                html_to_text(&mattext.text())
+            } else {
+                mattext.text().trim().to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::common_cartridge::xml;
+
+    const ASSESSMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<questestinterop xmlns="http://www.imsglobal.org/xsd/ims_qtiasiv1p2">
+  <assessment ident="a1" title="Week 1 quiz">
+    <section ident="root_section">
+      <item ident="q1" title="Capital">
+        <itemmetadata><qtimetadata>
+          <qtimetadatafield><fieldlabel>cc_profile</fieldlabel><fieldentry>cc.multiple_choice.v0p1</fieldentry></qtimetadatafield>
+        </qtimetadata></itemmetadata>
+        <presentation>
+          <material><mattext texttype="text/html">&lt;p&gt;What is the capital of &lt;b&gt;Finland&lt;/b&gt;?&lt;/p&gt;</mattext></material>
+          <response_lid ident="response1" rcardinality="Single">
+            <render_choice>
+              <response_label ident="a"><material><mattext>Turku</mattext></material></response_label>
+              <response_label ident="b"><material><mattext>Helsinki</mattext></material></response_label>
+            </render_choice>
+          </response_lid>
+        </presentation>
+        <resprocessing>
+          <outcomes><decvar maxvalue="100" minvalue="0" varname="SCORE" vartype="Decimal"/></outcomes>
+          <respcondition continue="No">
+            <conditionvar><varequal respident="response1">b</varequal></conditionvar>
+            <setvar action="Set" varname="SCORE">100</setvar>
+          </respcondition>
+        </resprocessing>
+      </item>
+      <item ident="q2" title="Fish">
+        <itemmetadata><qtimetadata>
+          <qtimetadatafield><fieldlabel>cc_profile</fieldlabel><fieldentry>cc.multiple_response.v0p1</fieldentry></qtimetadatafield>
+        </qtimetadata></itemmetadata>
+        <presentation>
+          <material><mattext>Which of these are fish?</mattext></material>
+          <response_lid ident="response2" rcardinality="Multiple">
+            <render_choice>
+              <response_label ident="c"><material><mattext>Salmon</mattext></material></response_label>
+              <response_label ident="d"><material><mattext>Whale</mattext></material></response_label>
+              <response_label ident="e"><material><mattext>Pike</mattext></material></response_label>
+            </render_choice>
+          </response_lid>
+        </presentation>
+        <resprocessing>
+          <respcondition continue="No">
+            <conditionvar><and>
+              <varequal respident="response2">c</varequal>
+              <not><varequal respident="response2">d</varequal></not>
+              <varequal respident="response2">e</varequal>
+            </and></conditionvar>
+            <setvar action="Set" varname="SCORE">100</setvar>
+          </respcondition>
+        </resprocessing>
+      </item>
+      <item ident="q3" title="Blank">
+        <itemmetadata><qtimetadata>
+          <qtimetadatafield><fieldlabel>cc_profile</fieldlabel><fieldentry>cc.fib.v0p1</fieldentry></qtimetadatafield>
+        </qtimetadata></itemmetadata>
+        <presentation>
+          <material><mattext>The largest lake in Finland is ...</mattext></material>
+          <response_str ident="response3" rcardinality="Single"><render_fib/></response_str>
+        </presentation>
+        <resprocessing>
+          <respcondition continue="No">
+            <conditionvar>
+              <varequal respident="response3">Saimaa</varequal>
+              <varequal respident="response3">Greater Saimaa</varequal>
+            </conditionvar>
+            <setvar action="Set" varname="SCORE">100</setvar>
+          </respcondition>
+        </resprocessing>
+      </item>
+      <item ident="q4" title="Essay">
+        <itemmetadata><qtimetadata>
+          <qtimetadatafield><fieldlabel>cc_profile</fieldlabel><fieldentry>cc.essay.v0p1</fieldentry></qtimetadatafield>
+        </qtimetadata></itemmetadata>
+        <presentation>
+          <material><mattext>Describe your favourite fish.</mattext></material>
+          <response_str ident="response4"><render_fib/></response_str>
+        </presentation>
+      </item>
+      <item ident="q5" title="Matching">
+        <itemmetadata><qtimetadata>
+          <qtimetadatafield><fieldlabel>question_type</fieldlabel><fieldentry>matching_question</fieldentry></qtimetadatafield>
+        </qtimetadata></itemmetadata>
+      </item>
+    </section>
+  </assessment>
+</questestinterop>"#;
+
+    #[test]
+    fn maps_the_supported_questions_to_quiz_items() {
+        let assessment = parse(&xml::parse(ASSESSMENT).unwrap()).unwrap();
+        assert_eq!(assessment.title.as_deref(), Some("Week 1 quiz"));
+        assert_eq!(assessment.items.len(), 4);
+
+        let choice = &assessment.items[0];
+        assert_eq!(choice["type"], "multiple-choice");
+        assert_eq!(choice["body"], "What is the capital of Finland?");
+        assert_eq!(choice["allowSelectingMultipleOptions"], false);
+        assert_eq!(
+            choice["options"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|option| (option["title"].clone(), option["correct"].clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (json!("Turku"), json!(false)),
+                (json!("Helsinki"), json!(true))
+            ]
+        );
+
+        let multiple = &assessment.items[1];
+        assert_eq!(multiple["allowSelectingMultipleOptions"], true);
+        assert_eq!(
+            multiple["options"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|option| option["correct"].clone())
+                .collect::<Vec<_>>(),
+            vec![json!(true), json!(false), json!(true)]
+        );
+
+        let blank = &assessment.items[2];
+        assert_eq!(blank["type"], "closed-ended-question");
+        assert_eq!(
+            blank["gradingStrategy"]["acceptedAnswers"],
+            json!(["Saimaa", "Greater Saimaa"])
+        );
+        assert_eq!(blank["gradingStrategy"]["caseSensitive"], false);
+
+        assert_eq!(assessment.items[3]["type"], "essay");
+        assert_eq!(assessment.items[3]["order"], 3);
+
+        assert_eq!(
+            assessment.unsupported,
+            vec![UnsupportedQuestion {
+                title: "Matching".to_string(),
+                reason: "Questions of the type matching_question are not supported.".to_string(),
+            }]
+        );
+        assert_eq!(
+            assessment.private_spec()["items"].as_array().unwrap().len(),
+            4
+        );
+    }
+}
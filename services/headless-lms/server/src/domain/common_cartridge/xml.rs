@@ -0,0 +1,174 @@
+//! A small owned XML tree for reading the manifest and the QTI files of a cartridge.
+//!
+//! The cartridges use several namespaces with whatever prefixes the exporting system chose, so the
+//! element and attribute names are stored without their prefixes.
+
+use anyhow::Context;
+use quick_xml::{Reader, escape::resolve_predefined_entity, events::BytesStart, events::Event};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+}
+
+impl XmlElement {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The child elements, skipping the text between them.
+    pub fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|child| match child {
+            XmlNode::Element(element) => Some(element),
+            XmlNode::Text(_) => None,
+        })
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> {
+        self.elements().filter(move |element| element.name == name)
+    }
+
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children_named(name).next()
+    }
+
+    /// All the elements with the name below this one, in document order.
+    pub fn descendants<'a>(&'a self, name: &str) -> Vec<&'a XmlElement> {
+        let mut res = Vec::new();
+        for element in self.elements() {
+            if element.name == name {
+                res.push(element);
+            }
+            res.extend(element.descendants(name));
+        }
+        res
+    }
+
+    /// The text of the element and its descendants.
+    pub fn text(&self) -> String {
+        let mut res = String::new();
+        for child in &self.children {
+            match child {
+                XmlNode::Element(element) => res.push_str(&element.text()),
+                XmlNode::Text(text) => res.push_str(text),
+            }
+        }
+        res
+    }
+}
+
+pub fn parse(input: &str) -> anyhow::Result<XmlElement> {
+    let mut reader = Reader::from_str(input);
+    let mut open: Vec<XmlElement> = Vec::new();
+    let mut root = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => open.push(element(&start)?),
+            Event::Empty(start) => {
+                let element = element(&start)?;
+                close(&mut open, &mut root, element);
+            }
+            Event::End(_) => {
+                let element = open.pop().context("Unexpected end tag")?;
+                close(&mut open, &mut root, element);
+            }
+            Event::Text(text) => push_text(&mut open, &text.decode()?),
+            Event::CData(cdata) => push_text(&mut open, &cdata.decode()?),
+            Event::GeneralRef(reference) => {
+                if let Some(character) = reference.resolve_char_ref()? {
+                    push_text(&mut open, character.encode_utf8(&mut [0; 4]));
+                } else {
+                    let name = reference.decode()?;
+                    let resolved = resolve_predefined_entity(&name)
+                        .with_context(|| format!("Unknown entity &{name};"))?;
+                    push_text(&mut open, resolved);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    if !open.is_empty() {
+        anyhow::bail!("Unclosed element <{}>", open[open.len() - 1].name);
+    }
+    root.context("The document has no root element")
+}
+
+fn element(start: &BytesStart) -> anyhow::Result<XmlElement> {
+    let mut attributes = Vec::new();
+    for attribute in start.attributes() {
+        let attribute = attribute?;
+        attributes.push((
+            String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned(),
+            attribute.unescape_value()?.into_owned(),
+        ));
+    }
+    Ok(XmlElement {
+        name: String::from_utf8_lossy(start.local_name().as_ref()).into_owned(),
+        attributes,
+        children: Vec::new(),
+    })
+}
+
+fn close(open: &mut [XmlElement], root: &mut Option<XmlElement>, element: XmlElement) {
+    match open.last_mut() {
+        Some(parent) => parent.children.push(XmlNode::Element(element)),
+        None => *root = Some(element),
+    }
+}
+
+fn push_text(open: &mut [XmlElement], text: &str) {
+    let Some(parent) = open.last_mut() else {
+        return;
+    };
+    if let Some(XmlNode::Text(previous)) = parent.children.last_mut() {
+        previous.push_str(text);
+    } else {
+        parent.children.push(XmlNode::Text(text.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_prefixed_names_entities_and_cdata() {
+        let root = parse(
+            r#"<?xml version="1.0"?>
+<manifest xmlns:lom="http://ltsc.ieee.org/xsd/imsccv1p1/LOM/resource" identifier="m">
+  <lom:title><lom:string>Fish &amp; chips &#x2013; <![CDATA[<b>raw</b>]]></lom:string></lom:title>
+  <resource identifier="r1" href="a.html"/>
+</manifest>"#,
+        )
+        .unwrap();
+        assert_eq!(root.name, "manifest");
+        assert_eq!(root.attribute("identifier"), Some("m"));
+        assert_eq!(
+            root.child("title").unwrap().text(),
+            "Fish & chips \u{2013} <b>raw</b>"
+        );
+        assert_eq!(root.descendants("string").len(), 1);
+        assert_eq!(
+            root.child("resource").unwrap().attribute("href"),
+            Some("a.html")
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_tags() {
+        assert!(parse("<a><b></a>").is_err());
+        assert!(parse("<a>").is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Reads and writes course archive files, see [`models::library::course_archives`].
+//!
+//! A course archive file is a ZIP file with the course content in `course.json` and the files the
+//! content refers to under `files/`, named after their paths in the file store of the exporting
+//! deployment.
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use anyhow::Context;
+use models::library::course_archives::{self, COURSE_ARCHIVE_FORMAT_VERSION, CourseArchive};
+use utoipa::ToSchema;
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use crate::prelude::*;
+
+pub const COURSE_JSON_PATH: &str = "course.json";
+const FILES_DIR: &str = "files/";
+/// Neither `course.json` nor the files may be larger than this.
+pub const MAX_ENTRY_BYTES: u64 = 100 * 1024 * 1024;
+
+/// What an archive was or would be imported as.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct CourseArchiveImportReport {
+    /// The created course. `None` for a dry run.
+    pub course_id: Option<Uuid>,
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub chapters: usize,
+    pub pages: usize,
+    pub exercises: usize,
+    pub files: usize,
+    /// Exercise types in the archive that have no exercise service in this deployment. The archive
+    /// cannot be imported before they are added.
+    pub missing_exercise_services: Vec<String>,
+    /// Files listed in the archive but not included in it. References to them are left as they
+    /// are.
+    pub missing_files: Vec<String>,
+}
+
+/// Exports the course to a course archive file.
+pub async fn export(
+    conn: &mut PgConnection,
+    file_store: &dyn FileStore,
+    course_id: Uuid,
+) -> anyhow::Result<Vec<u8>> {
+    let archive = course_archives::export_course(conn, course_id).await?;
+    let mut files = HashMap::new();
+    for file in &archive.files {
+        let contents = file_store
+            .download(Path::new(&file.path))
+            .await
+            .with_context(|| format!("Failed to download {}", file.path))?;
+        files.insert(file.path.clone(), contents);
+    }
+    write(&archive, &files)
+}
+
+pub fn write(archive: &CourseArchive, files: &HashMap<String, Vec<u8>>) -> anyhow::Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(COURSE_JSON_PATH, deflated)?;
+    zip.write_all(&serde_json::to_vec_pretty(archive)?)?;
+    for file in &archive.files {
+        if let Some(contents) = files.get(&file.path) {
+            zip.start_file(format!("{FILES_DIR}{}", file.path), deflated)?;
+            zip.write_all(contents)?;
+        }
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+pub struct CourseArchiveFile<R> {
+    pub archive: CourseArchive,
+    zip: ZipArchive<R>,
+}
+
+impl<R: Read + Seek> CourseArchiveFile<R> {
+    pub fn open(reader: R) -> anyhow::Result<Self> {
+        let mut zip = ZipArchive::new(reader).context("The file is not a zip file")?;
+        let course_json = read_entry(&mut zip, COURSE_JSON_PATH)
+            .context("The file has no course.json, so it is not a course archive")?;
+        // The version is checked first so that archives from newer deployments get a useful error
+        // instead of a deserialization error.
+        let format_version = serde_json::from_slice::<serde_json::Value>(&course_json)
+            .context("Failed to read course.json")?["format_version"]
+            .as_u64();
+        if format_version != Some(u64::from(COURSE_ARCHIVE_FORMAT_VERSION)) {
+            anyhow::bail!(
+                "Unsupported course archive format version {}, expected {COURSE_ARCHIVE_FORMAT_VERSION}",
+                format_version.map_or_else(|| "(none)".to_string(), |v| v.to_string())
+            );
+        }
+        let archive = serde_json::from_slice(&course_json).context("Failed to read course.json")?;
+        Ok(Self { archive, zip })
+    }
+
+    pub fn has_file(&self, path: &str) -> bool {
+        self.zip
+            .index_for_name(&format!("{FILES_DIR}{path}"))
+            .is_some()
+    }
+
+    pub fn read_file(&mut self, path: &str) -> anyhow::Result<Vec<u8>> {
+        read_entry(&mut self.zip, &format!("{FILES_DIR}{path}"))
+    }
+
+    pub fn report(&self, missing_exercise_services: Vec<String>) -> CourseArchiveImportReport {
+        CourseArchiveImportReport {
+            course_id: None,
+            format_version: self.archive.format_version,
+            exported_at: self.archive.exported_at,
+            chapters: self.archive.chapters.len(),
+            pages: self.archive.pages.len(),
+            exercises: self.archive.exercises.len(),
+            files: self.archive.files.len(),
+            missing_exercise_services,
+            missing_files: self
+                .archive
+                .files
+                .iter()
+                .filter(|file| !self.has_file(&file.path))
+                .map(|file| file.path.clone())
+                .collect(),
+        }
+    }
+}
+
+/// The size in the zip header is not trusted, as a crafted archive can declare a small size for an
+/// entry that decompresses to much more.
+fn read_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> anyhow::Result<Vec<u8>> {
+    let entry = zip.by_name(name)?;
+    let mut contents = Vec::new();
+    entry.take(MAX_ENTRY_BYTES + 1).read_to_end(&mut contents)?;
+    if contents.len() as u64 > MAX_ENTRY_BYTES {
+        anyhow::bail!("{name} is larger than {MAX_ENTRY_BYTES} bytes");
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod test {
+    use models::library::course_archives::ArchivedFile;
+
+    use super::*;
+
+    fn archive(files: Vec<ArchivedFile>) -> CourseArchive {
+        CourseArchive {
+            format_version: COURSE_ARCHIVE_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            course: serde_json::json!({ "name": "Course" }),
+            course_modules: vec![],
+            chapters: vec![serde_json::json!({ "name": "Chapter" })],
+            pages: vec![],
+            exercises: vec![],
+            exercise_slides: vec![],
+            exercise_tasks: vec![],
+            peer_or_self_review_configs: vec![],
+            peer_or_self_review_questions: vec![],
+            glossary: vec![],
+            material_references: vec![],
+            certificate_configurations: vec![],
+            certificate_configuration_requirements: vec![],
+            certificate_text_elements: vec![],
+            files,
+        }
+    }
+
+    #[test]
+    fn reads_written_archive() {
+        let image = ArchivedFile {
+            path: "course/6c8f1c8e-4a3c-4d2f-9c5b-1d2e3f4a5b6c/images/abc.png".to_string(),
+            name: "abc.png".to_string(),
+            mime: "image/png".to_string(),
+        };
+        let missing = ArchivedFile {
+            path: "course/6c8f1c8e-4a3c-4d2f-9c5b-1d2e3f4a5b6c/files/def.pdf".to_string(),
+            name: "def.pdf".to_string(),
+            mime: "application/pdf".to_string(),
+        };
+        let archive = archive(vec![image.clone(), missing.clone()]);
+        let files = HashMap::from([(image.path.clone(), vec![1, 2, 3])]);
+        let bytes = write(&archive, &files).unwrap();
+
+        let mut file = CourseArchiveFile::open(Cursor::new(bytes)).unwrap();
+        assert_eq!(file.archive, archive);
+        assert_eq!(file.read_file(&image.path).unwrap(), vec![1, 2, 3]);
+        let report = file.report(vec![]);
+        assert_eq!(report.chapters, 1);
+        assert_eq!(report.files, 2);
+        assert_eq!(report.missing_files, vec![missing.path]);
+    }
+
+    #[test]
+    fn rejects_other_format_versions() {
+        let mut archive = archive(vec![]);
+        archive.format_version = COURSE_ARCHIVE_FORMAT_VERSION + 1;
+        let bytes = write(&archive, &HashMap::new()).unwrap();
+        let err = CourseArchiveFile::open(Cursor::new(bytes))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("format version"), "{err}");
+    }
+}
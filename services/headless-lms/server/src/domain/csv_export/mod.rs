@@ -391,6 +391,31 @@ mod test {
         assert_eq!(count, 2)
     }
 
+    #[actix_web::test]
+    async fn exports_points_by_the_scoring_strategy() {
+        insert_data!(:tx, :user, :org, :course, :instance, :course_module, :chapter, :page, :exercise, :slide, :task);
+        course_instance_enrollments::insert(tx.as_mut(), user, course, instance.id)
+            .await
+            .unwrap();
+        sqlx::query("UPDATE exercises SET scoring_strategy = 'latest-attempt' WHERE id = $1")
+            .bind(exercise)
+            .execute(tx.as_mut())
+            .await
+            .unwrap();
+        submit_and_grade(tx.as_mut(), exercise, slide, task, user, course, 80.0).await;
+        submit_and_grade(tx.as_mut(), exercise, slide, task, user, course, 20.0).await;
+
+        let buf = export_course_instance_points(tx.as_mut(), instance.id, vec![])
+            .await
+            .unwrap();
+        let mut reader = csv::Reader::from_reader(Cursor::new(buf));
+        let records = reader.records().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(records.len(), 1);
+        // The latest attempt counts, not the better earlier one.
+        let points = records[0][1].parse::<f32>().unwrap();
+        assert!((points - 0.2).abs() < 0.01, "{points} != 0.2");
+    }
+
     async fn submit_and_grade(
         tx: &mut PgConnection,
         ex: Uuid,
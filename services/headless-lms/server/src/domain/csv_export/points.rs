@@ -2,7 +2,9 @@ use anyhow::{Context, Result};
 use bytes::Bytes;
 
 use futures::TryStreamExt;
-use headless_lms_models::{chapters, course_instances, exercises, user_exercise_states, users};
+use headless_lms_models::{
+    chapters, course_instances, exam_question_pools, exercises, user_exercise_states, users,
+};
 
 use async_trait::async_trait;
 
@@ -135,6 +137,7 @@ where
 {
     let csv_fields_before_headers = 2;
 
+    let question_bank = exam_question_pools::get_question_bank(conn, exam_id).await?;
     let mut exercises = exercises::get_exercises_by_exam_id(conn, exam_id).await?;
     // I's fine to sort just by order number because exams have no chapters
     exercises.sort_by_key(|a| a.order_number);
@@ -144,21 +147,34 @@ where
         exercise_id_to_header_idx.insert(exercise.id, csv_fields_before_headers + idx);
     }
 
-    let header_count = csv_fields_before_headers + exercises.len();
+    // The maximum points come last, after the exercises.
+    let maximum_points_idx = csv_fields_before_headers + exercises.len();
+    let header_count = maximum_points_idx + 1;
     // remember to update csv_fields_before_headers if this changes!
-    let headers = IntoIterator::into_iter(["user_id".to_string(), "email".to_string()]).chain(
-        exercises
-            .into_iter()
-            .map(|e| format!("{}: {}", e.order_number, e.name)),
-    );
+    let headers = IntoIterator::into_iter(["user_id".to_string(), "email".to_string()])
+        .chain(
+            exercises
+                .into_iter()
+                .map(|e| format!("{}: {}", e.order_number, e.name)),
+        )
+        .chain(["maximum_points".to_string()]);
 
-    let mut stream = user_exercise_states::stream_exam_points(conn, exam_id);
+    let mut stream = user_exercise_states::stream_exam_points(conn, exam_id, question_bank);
 
     let writer = CsvWriter::new_with_initialized_headers(writer, headers).await?;
     while let Some(next) = stream.try_next().await? {
-        let mut csv_row = vec!["0".to_string(); header_count];
+        // Exercises the user did not draw from the question pools are left empty.
+        let mut csv_row = vec![String::new(); header_count];
         csv_row[0] = next.user_id.to_string();
         csv_row[1] = next.email;
+        csv_row[maximum_points_idx] = next.maximum_points.to_string();
+        for exercise_id in &next.exercise_ids {
+            if let Some(idx) = exercise_id_to_header_idx.get(exercise_id)
+                && let Some(item) = csv_row.get_mut(*idx)
+            {
+                *item = "0".to_string();
+            }
+        }
         for points in next.points_for_exercise {
             let idx = exercise_id_to_header_idx
                 .get(&points.exercise_id)
@@ -0,0 +1,219 @@
+//! Bulk import of exam accommodations from a CSV file.
+//!
+//! The file has a header row with the columns `email`, `time_multiplier`, `starts_at`, `ends_at`
+//! and `reason`. Only `email` is required to have a value. Times are in RFC 3339, e.g.
+//! `2026-05-01T18:00:00Z`. The import is all or nothing: if any row has a problem, nothing is saved
+//! and every problem is reported back with its row number.
+
+use models::exam_accommodations::{ExamAccommodation, NewExamAccommodation};
+use utoipa::ToSchema;
+
+use crate::prelude::*;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExamAccommodationImport {
+    /// The contents of the CSV file.
+    pub csv: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone, ToSchema)]
+pub struct ExamAccommodationImportError {
+    /// Row number in the file, the header being row 1.
+    pub row: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExamAccommodationImportResult {
+    /// Empty if there were any errors.
+    pub imported: Vec<ExamAccommodation>,
+    pub errors: Vec<ExamAccommodationImportError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CsvRow {
+    email: String,
+    time_multiplier: Option<f32>,
+    starts_at: Option<String>,
+    ends_at: Option<String>,
+    reason: Option<String>,
+}
+
+/// A parsed row whose email has not been resolved to a user yet.
+#[derive(Debug, PartialEq)]
+pub struct ParsedAccommodation {
+    pub row: usize,
+    pub email: String,
+    pub time_multiplier: f32,
+    pub starts_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+}
+
+pub fn parse_csv(csv: &str) -> Result<Vec<ParsedAccommodation>, Vec<ExamAccommodationImportError>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv.as_bytes());
+    let mut parsed = vec![];
+    let mut errors = vec![];
+    for (idx, record) in reader.deserialize::<CsvRow>().enumerate() {
+        let row = idx + 2;
+        let mut error = |message: String| {
+            errors.push(ExamAccommodationImportError { row, message });
+        };
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                error(format!("Could not read the row: {err}"));
+                continue;
+            }
+        };
+        if record.email.is_empty() {
+            error("The email is missing.".to_string());
+            continue;
+        }
+        let parse_time = |value: Option<String>| {
+            value
+                .filter(|v| !v.is_empty())
+                .map(|v| {
+                    DateTime::parse_from_rfc3339(&v)
+                        .map(|t| t.with_timezone(&Utc))
+                        .map_err(|_| {
+                            format!("Invalid time '{v}', expected e.g. 2026-05-01T18:00:00Z.")
+                        })
+                })
+                .transpose()
+        };
+        let (starts_at, ends_at) = match (parse_time(record.starts_at), parse_time(record.ends_at))
+        {
+            (Ok(starts_at), Ok(ends_at)) => (starts_at, ends_at),
+            (Err(message), _) | (_, Err(message)) => {
+                error(message);
+                continue;
+            }
+        };
+        parsed.push(ParsedAccommodation {
+            row,
+            email: record.email,
+            time_multiplier: record.time_multiplier.unwrap_or(1.0),
+            starts_at,
+            ends_at,
+            reason: record.reason.filter(|r| !r.is_empty()),
+        });
+    }
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses the file, resolves the emails to users and saves the accommodations if every row is valid.
+pub async fn import(
+    conn: &mut PgConnection,
+    exam_id: Uuid,
+    import: &ExamAccommodationImport,
+    granted_by: Uuid,
+) -> ControllerResult<ExamAccommodationImportResult> {
+    let parsed = match parse_csv(&import.csv) {
+        Ok(parsed) => parsed,
+        Err(errors) => {
+            return Ok(ExamAccommodationImportResult {
+                imported: vec![],
+                errors,
+            });
+        }
+    };
+    let mut new_accommodations = vec![];
+    let mut errors = vec![];
+    for p in parsed {
+        let user = models::users::get_by_email(conn, &p.email)
+            .await
+            .optional()?;
+        let Some(user) = user else {
+            errors.push(ExamAccommodationImportError {
+                row: p.row,
+                message: format!("No user with the email {}.", p.email),
+            });
+            continue;
+        };
+        if new_accommodations
+            .iter()
+            .any(|a: &NewExamAccommodation| a.user_id == user.id)
+        {
+            errors.push(ExamAccommodationImportError {
+                row: p.row,
+                message: format!("{} is on more than one row.", p.email),
+            });
+            continue;
+        }
+        let new_accommodation = NewExamAccommodation {
+            user_id: user.id,
+            time_multiplier: p.time_multiplier,
+            starts_at: p.starts_at,
+            ends_at: p.ends_at,
+            reason: p.reason,
+        };
+        if let Err(err) = models::exam_accommodations::validate(&new_accommodation) {
+            errors.push(ExamAccommodationImportError {
+                row: p.row,
+                message: err.message().to_string(),
+            });
+            continue;
+        }
+        new_accommodations.push(new_accommodation);
+    }
+    if !errors.is_empty() {
+        return Ok(ExamAccommodationImportResult {
+            imported: vec![],
+            errors,
+        });
+    }
+    let imported =
+        models::exam_accommodations::upsert_many(conn, exam_id, &new_accommodations, granted_by)
+            .await?;
+    Ok(ExamAccommodationImportResult {
+        imported,
+        errors: vec![],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parses_csv() {
+        let csv = "email,time_multiplier,starts_at,ends_at,reason
+student@example.com,1.5,,,
+other@example.com, ,2026-05-01T18:00:00Z,2026-05-01T21:00:00+00:00,Time zone
+";
+        let parsed = parse_csv(csv).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].time_multiplier, 1.5);
+        assert_eq!(parsed[0].starts_at, None);
+        assert_eq!(parsed[1].row, 3);
+        assert_eq!(parsed[1].time_multiplier, 1.0);
+        assert_eq!(
+            parsed[1].ends_at,
+            Some(Utc.with_ymd_and_hms(2026, 5, 1, 21, 0, 0).unwrap())
+        );
+        assert_eq!(parsed[1].reason.as_deref(), Some("Time zone"));
+    }
+
+    #[test]
+    fn reports_every_invalid_row() {
+        let csv = "email,time_multiplier,starts_at,ends_at,reason
+,1.5,,,
+student@example.com,lots,,,
+other@example.com,1,tomorrow,,
+";
+        let errors = parse_csv(csv).unwrap_err();
+        assert_eq!(
+            errors.iter().map(|e| e.row).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+    }
+}
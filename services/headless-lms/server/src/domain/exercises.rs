@@ -4,8 +4,6 @@ use crate::{
     domain::models_requests::{self, JwtKey},
     prelude::*,
 };
-use chrono::{Duration, Utc};
-use futures_util::future::OptionFuture;
 use models::{
     exercises::Exercise,
     library::grading::{
@@ -21,8 +19,6 @@ pub async fn process_submission(
     submission: &StudentExerciseSlideSubmission,
     jwt_key: Arc<JwtKey>,
 ) -> Result<StudentExerciseSlideSubmissionResult, ControllerError> {
-    enforce_deadline(conn, &exercise).await?;
-
     let (course_or_exam_id, last_try) = resolve_course_or_exam_id_and_verify_that_user_can_submit(
         conn,
         user_id,
@@ -80,33 +76,6 @@ pub async fn process_submission(
     Ok(result)
 }
 
-/// Returns an error if the chapter's or exercise's deadline has passed.
-async fn enforce_deadline(
-    conn: &mut PgConnection,
-    exercise: &Exercise,
-) -> Result<(), ControllerError> {
-    let chapter_option_future: OptionFuture<_> = exercise
-        .chapter_id
-        .map(|id| models::chapters::get_chapter(conn, id))
-        .into();
-    let chapter = chapter_option_future.await.transpose()?;
-
-    // Exercise deadlines takes precedence to chapter deadlines
-    if let Some(deadline) = exercise
-        .deadline
-        .or_else(|| chapter.and_then(|c| c.deadline))
-        && Utc::now() + Duration::seconds(1) >= deadline
-    {
-        return Err(ControllerError::new(
-            ControllerErrorType::BadRequest,
-            "Exercise deadline passed.".to_string(),
-            None,
-        ));
-    }
-
-    Ok(())
-}
-
 /// Submissions for exams are posted from course instances or from exams. Make respective validations
 /// while figuring out which.
 async fn resolve_course_or_exam_id_and_verify_that_user_can_submit(
@@ -135,6 +104,17 @@ async fn resolve_course_or_exam_id_and_verify_that_user_can_submit(
         }
     } else if let Some(exam_id) = exercise.exam_id {
         // If submitting for an exam, make sure that user's time is not up.
+        let exercise_was_drawn =
+            models::exam_question_pools::get_exercise_ids_for_user(conn, exam_id, user_id)
+                .await?
+                .is_none_or(|ids| ids.contains(&exercise.id));
+        if !exercise_was_drawn {
+            return Err(ControllerError::new(
+                ControllerErrorType::Forbidden,
+                "The exercise was not drawn for you in this exam.".to_string(),
+                None,
+            ));
+        }
         if models::exams::verify_exam_submission_can_be_made(conn, exam_id, user_id).await? {
             let token = authorize(conn, Act::View, Some(user_id), Res::Exam(exam_id)).await?;
             token.authorized_ok(CourseOrExamId::Exam(exam_id))
@@ -0,0 +1,236 @@
+//! The claims of the ID token a platform sends in a launch.
+//!
+//! See <https://www.imsglobal.org/spec/lti/v1p3#required-message-claims>.
+
+use std::collections::HashMap;
+
+use crate::prelude::*;
+
+use super::{CUSTOM_CHAPTER_ID, CUSTOM_COURSE_ID, CUSTOM_EXERCISE_ID};
+
+const ROLE_INSTRUCTOR: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Instructor";
+const ROLE_CONTENT_DEVELOPER: &str =
+    "http://purl.imsglobal.org/vocab/lis/v2/membership#ContentDeveloper";
+const ROLE_ADMINISTRATOR: &str = "http://purl.imsglobal.org/vocab/lis/v2/membership#Administrator";
+const ROLE_INSTITUTION_ADMINISTRATOR: &str =
+    "http://purl.imsglobal.org/vocab/lis/v2/institution/person#Administrator";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct LaunchClaims {
+    pub iss: String,
+    pub sub: String,
+    pub aud: Audience,
+    pub exp: i64,
+    pub iat: i64,
+    pub nonce: String,
+    /// Required when there are several audiences.
+    pub azp: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub name: Option<String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/message_type")]
+    pub message_type: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/version")]
+    pub version: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/deployment_id")]
+    pub deployment_id: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/target_link_uri")]
+    pub target_link_uri: Option<String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/roles", default)]
+    pub roles: Vec<String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/resource_link")]
+    pub resource_link: Option<ResourceLinkClaim>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/context")]
+    pub context: Option<ContextClaim>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/custom", default)]
+    pub custom: HashMap<String, String>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti-ags/claim/endpoint")]
+    pub ags_endpoint: Option<AgsEndpointClaim>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti-nrps/claim/namesroleservice")]
+    pub nrps: Option<NrpsClaim>,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti-dl/claim/deep_linking_settings")]
+    pub deep_linking_settings: Option<DeepLinkingSettingsClaim>,
+}
+
+/// The `aud` claim is either a single client id or a list of them.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum Audience {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Audience {
+    pub fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::Single(aud) => aud == client_id,
+            Audience::Multiple(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+
+    pub fn is_multiple(&self) -> bool {
+        matches!(self, Audience::Multiple(auds) if auds.len() > 1)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct ResourceLinkClaim {
+    pub id: String,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct ContextClaim {
+    pub id: String,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct AgsEndpointClaim {
+    #[serde(default)]
+    pub scope: Vec<String>,
+    pub lineitems: Option<String>,
+    /// The gradebook column of the link. Only present if the link has exactly one.
+    pub lineitem: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct NrpsClaim {
+    pub context_memberships_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct DeepLinkingSettingsClaim {
+    pub deep_link_return_url: String,
+    #[serde(default)]
+    pub accept_types: Vec<String>,
+    #[serde(default)]
+    pub accept_multiple: bool,
+    pub data: Option<String>,
+}
+
+/// What a resource link launches, read from its custom parameters.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct LaunchTarget {
+    pub course_id: Uuid,
+    pub chapter_id: Option<Uuid>,
+    pub exercise_id: Option<Uuid>,
+}
+
+impl LaunchClaims {
+    /// Whether the user may pick content with Deep Linking.
+    pub fn is_instructor(&self) -> bool {
+        self.roles.iter().any(|role| {
+            matches!(
+                role.as_str(),
+                ROLE_INSTRUCTOR
+                    | ROLE_CONTENT_DEVELOPER
+                    | ROLE_ADMINISTRATOR
+                    | ROLE_INSTITUTION_ADMINISTRATOR
+            )
+        })
+    }
+
+    /// Links created with Deep Linking carry the ids in custom parameters. Links added by hand on the
+    /// platform need at least the `course_id` custom parameter.
+    pub fn launch_target(&self) -> ControllerResult<LaunchTarget> {
+        let parse = |key: &str| -> ControllerResult<Option<Uuid>> {
+            self.custom
+                .get(key)
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .map(|value| {
+                    Uuid::parse_str(value).map_err(|_| {
+                        controller_err!(
+                            BadRequest,
+                            format!("The custom parameter '{key}' of the link is not a valid id.")
+                        )
+                    })
+                })
+                .transpose()
+        };
+        let course_id = parse(CUSTOM_COURSE_ID)?.ok_or_else(|| {
+            controller_err!(
+                BadRequest,
+                format!(
+                    "The link does not tell which course to open. Add the link with the content selection or set the custom parameter '{CUSTOM_COURSE_ID}'."
+                )
+            )
+        })?;
+        Ok(LaunchTarget {
+            course_id,
+            chapter_id: parse(CUSTOM_CHAPTER_ID)?,
+            exercise_id: parse(CUSTOM_EXERCISE_ID)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn claims(custom: serde_json::Value) -> LaunchClaims {
+        serde_json::from_value(serde_json::json!({
+            "iss": "https://lms.example.com",
+            "sub": "user-1",
+            "aud": ["client", "other"],
+            "azp": "client",
+            "exp": 2000000000,
+            "iat": 1000000000,
+            "nonce": "nonce",
+            "https://purl.imsglobal.org/spec/lti/claim/message_type": "LtiResourceLinkRequest",
+            "https://purl.imsglobal.org/spec/lti/claim/version": "1.3.0",
+            "https://purl.imsglobal.org/spec/lti/claim/deployment_id": "1",
+            "https://purl.imsglobal.org/spec/lti/claim/roles": [
+                "http://purl.imsglobal.org/vocab/lis/v2/membership#Learner"
+            ],
+            "https://purl.imsglobal.org/spec/lti/claim/resource_link": { "id": "link-1" },
+            "https://purl.imsglobal.org/spec/lti/claim/custom": custom,
+            "https://purl.imsglobal.org/spec/lti-ags/claim/endpoint": {
+                "scope": ["https://purl.imsglobal.org/spec/lti-ags/scope/score"],
+                "lineitem": "https://lms.example.com/lineitems/1"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_platform_claims() {
+        let claims = claims(serde_json::json!({}));
+        assert!(claims.aud.contains("client"));
+        assert!(claims.aud.is_multiple());
+        assert!(!claims.is_instructor());
+        assert_eq!(claims.resource_link.unwrap().id, "link-1");
+        assert_eq!(
+            claims.ags_endpoint.unwrap().lineitem.as_deref(),
+            Some("https://lms.example.com/lineitems/1")
+        );
+    }
+
+    #[test]
+    fn launch_target_comes_from_custom_parameters() {
+        let course_id = Uuid::new_v4();
+        let chapter_id = Uuid::new_v4();
+        let target = claims(serde_json::json!({
+            "course_id": course_id.to_string(),
+            "chapter_id": chapter_id.to_string(),
+            "exercise_id": ""
+        }))
+        .launch_target()
+        .unwrap();
+        assert_eq!(
+            target,
+            LaunchTarget {
+                course_id,
+                chapter_id: Some(chapter_id),
+                exercise_id: None
+            }
+        );
+        assert!(claims(serde_json::json!({})).launch_target().is_err());
+        assert!(
+            claims(serde_json::json!({ "course_id": "not-an-id" }))
+                .launch_target()
+                .is_err()
+        );
+    }
+}
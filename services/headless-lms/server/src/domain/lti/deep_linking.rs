@@ -0,0 +1,182 @@
+//! Deep Linking responses, which return the chapters and exercises a teacher picked to the platform.
+//!
+//! See <https://www.imsglobal.org/spec/lti-dl/v2p0#deep-linking-response-message>.
+
+use std::collections::HashMap;
+
+use models::{lti_deep_linking_requests::LtiDeepLinkingRequest, lti_platforms::LtiPlatform};
+use utoipa::ToSchema;
+
+use super::{
+    CUSTOM_CHAPTER_ID, CUSTOM_COURSE_ID, CUSTOM_EXERCISE_ID, DEEP_LINKING_RESPONSE, LTI_VERSION,
+    launch_url, services::sign_tool_jwt,
+};
+use crate::prelude::*;
+
+/// How long the platform accepts the response.
+const RESPONSE_LIFETIME_SECONDS: i64 = 600;
+
+/// A piece of content the teacher picked, with the details the platform shows for it.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct SelectedContent {
+    pub title: String,
+    pub course_id: Uuid,
+    pub chapter_id: Option<Uuid>,
+    pub exercise_id: Option<Uuid>,
+    /// The sum of the maximum scores of the exercises in the content. A gradebook column is only
+    /// created for content that has points.
+    pub score_maximum: i32,
+}
+
+/// The signed response that the browser posts to the platform in a form field named `JWT`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct DeepLinkingResponse {
+    pub deep_link_return_url: String,
+    pub jwt: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LtiResourceLinkItem {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub title: String,
+    pub url: String,
+    pub custom: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_item: Option<LineItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LineItem {
+    pub label: String,
+    pub score_maximum: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DeepLinkingResponseClaims {
+    /// We are the issuer: the client id the platform gave us.
+    iss: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+    nonce: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/message_type")]
+    message_type: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/version")]
+    version: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti/claim/deployment_id")]
+    deployment_id: String,
+    #[serde(rename = "https://purl.imsglobal.org/spec/lti-dl/claim/content_items")]
+    content_items: Vec<LtiResourceLinkItem>,
+    #[serde(
+        rename = "https://purl.imsglobal.org/spec/lti-dl/claim/data",
+        skip_serializing_if = "Option::is_none"
+    )]
+    data: Option<String>,
+}
+
+pub fn build_content_items(
+    base_url: &str,
+    selected: &[SelectedContent],
+) -> Vec<LtiResourceLinkItem> {
+    selected
+        .iter()
+        .map(|content| {
+            let mut custom =
+                HashMap::from([(CUSTOM_COURSE_ID.to_string(), content.course_id.to_string())]);
+            if let Some(chapter_id) = content.chapter_id {
+                custom.insert(CUSTOM_CHAPTER_ID.to_string(), chapter_id.to_string());
+            }
+            if let Some(exercise_id) = content.exercise_id {
+                custom.insert(CUSTOM_EXERCISE_ID.to_string(), exercise_id.to_string());
+            }
+            LtiResourceLinkItem {
+                type_: "ltiResourceLink".to_string(),
+                title: content.title.clone(),
+                url: launch_url(base_url),
+                custom,
+                line_item: (content.score_maximum > 0).then(|| LineItem {
+                    label: content.title.clone(),
+                    score_maximum: content.score_maximum,
+                }),
+            }
+        })
+        .collect()
+}
+
+pub fn build_response(
+    platform: &LtiPlatform,
+    request: &LtiDeepLinkingRequest,
+    selected: &[SelectedContent],
+    app_conf: &ApplicationConfiguration,
+) -> ControllerResult<DeepLinkingResponse> {
+    if selected.len() > 1 && !request.accept_multiple {
+        return Err(controller_err!(
+            BadRequest,
+            "The platform accepts only one piece of content.".to_string()
+        ));
+    }
+    let now = Utc::now().timestamp();
+    let claims = DeepLinkingResponseClaims {
+        iss: platform.client_id.clone(),
+        aud: platform.issuer.clone(),
+        iat: now,
+        exp: now + RESPONSE_LIFETIME_SECONDS,
+        nonce: Uuid::new_v4().to_string(),
+        message_type: DEEP_LINKING_RESPONSE.to_string(),
+        version: LTI_VERSION.to_string(),
+        deployment_id: request.deployment_id.clone(),
+        content_items: build_content_items(&app_conf.base_url, selected),
+        data: request.data.clone(),
+    };
+    Ok(DeepLinkingResponse {
+        deep_link_return_url: request.deep_link_return_url.clone(),
+        jwt: sign_tool_jwt(&claims, app_conf)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn content_items_launch_the_picked_content() {
+        let course_id = Uuid::new_v4();
+        let exercise_id = Uuid::new_v4();
+        let items = build_content_items(
+            "http://project-331.local/",
+            &[
+                SelectedContent {
+                    title: "Exercise".to_string(),
+                    course_id,
+                    chapter_id: None,
+                    exercise_id: Some(exercise_id),
+                    score_maximum: 2,
+                },
+                SelectedContent {
+                    title: "Course".to_string(),
+                    course_id,
+                    chapter_id: None,
+                    exercise_id: None,
+                    score_maximum: 0,
+                },
+            ],
+        );
+        assert_eq!(
+            serde_json::to_value(&items[0]).unwrap(),
+            serde_json::json!({
+                "type": "ltiResourceLink",
+                "title": "Exercise",
+                "url": "http://project-331.local/api/v0/lti/launch",
+                "custom": {
+                    "course_id": course_id.to_string(),
+                    "exercise_id": exercise_id.to_string()
+                },
+                "lineItem": { "label": "Exercise", "scoreMaximum": 2 }
+            })
+        );
+        assert_eq!(items[1].line_item, None);
+    }
+}
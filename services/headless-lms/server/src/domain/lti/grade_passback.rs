@@ -0,0 +1,170 @@
+//! Passes the scores of the users who have launched a resource link back to the gradebook of the
+//! platform.
+//!
+//! A score is sent whenever the state of the user in any of the exercises of the link has changed since
+//! the last successful sync. A failed sync is retried after [`RETRY_FAILED_AFTER_MINUTES`].
+
+use std::collections::HashMap;
+
+use models::{
+    exercises::{ActivityProgress, GradingProgress},
+    lti_resource_link_users::{self, PendingScoreSync},
+};
+
+use super::{
+    AGS_SCOPE_SCORE,
+    services::{self, AgsScore},
+};
+use crate::prelude::*;
+
+pub const RETRY_FAILED_AFTER_MINUTES: i64 = 30;
+
+/// How many scores are sent in one run.
+const BATCH_SIZE: i64 = 500;
+
+/// The columns `score_sync_error` fits in.
+const MAX_ERROR_MESSAGE_LENGTH: usize = 1000;
+
+/// Sends the pending scores. Returns how many were sent successfully.
+pub async fn sync_pending_scores(
+    conn: &mut PgConnection,
+    app_conf: &ApplicationConfiguration,
+) -> anyhow::Result<usize> {
+    let retry_failed_before = Utc::now() - chrono::Duration::minutes(RETRY_FAILED_AFTER_MINUTES);
+    let pending =
+        lti_resource_link_users::get_pending_score_syncs(conn, retry_failed_before, BATCH_SIZE)
+            .await?;
+    // One access token per platform for the whole run.
+    let mut access_tokens: HashMap<Uuid, Result<String, String>> = HashMap::new();
+    let mut synced = 0;
+    for score in pending {
+        if !access_tokens.contains_key(&score.lti_platform_id) {
+            let platform = models::lti_platforms::get_by_id(conn, score.lti_platform_id).await?;
+            let token = services::get_access_token(&platform, &[AGS_SCOPE_SCORE], app_conf)
+                .await
+                .map_err(|err| format!("Failed to get an access token: {err:#}"));
+            access_tokens.insert(score.lti_platform_id, token);
+        }
+        let result = match &access_tokens[&score.lti_platform_id] {
+            Ok(token) => services::post_score(&score.ags_lineitem_url, token, &build_score(&score))
+                .await
+                .map_err(|err| format!("Failed to send the score: {err:#}")),
+            Err(err) => Err(err.clone()),
+        };
+        match result {
+            Ok(()) => {
+                lti_resource_link_users::mark_score_synced(
+                    conn,
+                    score.lti_resource_link_user_id,
+                    score.latest_state_change,
+                )
+                .await?;
+                synced += 1;
+            }
+            Err(err) => {
+                warn!(
+                    "Passing back the LTI score {} failed: {}",
+                    score.lti_resource_link_user_id, err
+                );
+                let err = err
+                    .chars()
+                    .take(MAX_ERROR_MESSAGE_LENGTH)
+                    .collect::<String>();
+                lti_resource_link_users::mark_score_sync_failed(
+                    conn,
+                    score.lti_resource_link_user_id,
+                    &err,
+                )
+                .await?;
+            }
+        }
+    }
+    Ok(synced)
+}
+
+/// The score is final only once the user has a fully graded state in every exercise of the link.
+pub fn build_score(pending: &PendingScoreSync) -> AgsScore {
+    let all_started = pending.started_exercise_count >= pending.exercise_count;
+    let activity_progress = if all_started {
+        pending
+            .least_activity_progress
+            .unwrap_or(ActivityProgress::Initialized)
+    } else {
+        ActivityProgress::InProgress
+    };
+    let grading_progress = if all_started {
+        pending
+            .least_grading_progress
+            .unwrap_or(GradingProgress::NotReady)
+    } else {
+        GradingProgress::Pending
+    };
+    let (score_given, score_maximum) = if pending.score_maximum > 0 {
+        (
+            Some(pending.score_given.min(pending.score_maximum as f32)),
+            Some(pending.score_maximum as f32),
+        )
+    } else {
+        (None, None)
+    };
+    AgsScore {
+        user_id: pending.subject.clone(),
+        score_given,
+        score_maximum,
+        activity_progress,
+        grading_progress,
+        timestamp: pending
+            .latest_state_change
+            .to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pending(started_exercise_count: i64) -> PendingScoreSync {
+        PendingScoreSync {
+            lti_resource_link_user_id: Uuid::new_v4(),
+            lti_platform_id: Uuid::new_v4(),
+            ags_lineitem_url: "https://lms.example.com/lineitems/1".to_string(),
+            subject: "user-1".to_string(),
+            score_given: 3.0,
+            score_maximum: 4,
+            exercise_count: 2,
+            started_exercise_count,
+            least_activity_progress: Some(ActivityProgress::Completed),
+            least_grading_progress: Some(GradingProgress::FullyGraded),
+            latest_state_change: DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+        }
+    }
+
+    #[test]
+    fn score_is_final_when_every_exercise_is_graded() {
+        let score = build_score(&pending(2));
+        assert_eq!(score.user_id, "user-1");
+        assert_eq!(score.score_given, Some(3.0));
+        assert_eq!(score.score_maximum, Some(4.0));
+        assert_eq!(score.activity_progress, ActivityProgress::Completed);
+        assert_eq!(score.grading_progress, GradingProgress::FullyGraded);
+        assert_eq!(score.timestamp, "2026-01-01T12:00:00.000Z");
+    }
+
+    #[test]
+    fn score_is_partial_while_exercises_are_left() {
+        let score = build_score(&pending(1));
+        assert_eq!(score.activity_progress, ActivityProgress::InProgress);
+        assert_eq!(score.grading_progress, GradingProgress::Pending);
+    }
+
+    #[test]
+    fn content_without_points_has_no_score() {
+        let mut pending = pending(2);
+        pending.score_maximum = 0;
+        let score = build_score(&pending);
+        assert_eq!(score.score_given, None);
+        assert_eq!(score.score_maximum, None);
+    }
+}
@@ -0,0 +1,172 @@
+//! Validates the ID token a platform posts in a launch.
+//!
+//! See <https://www.imsglobal.org/spec/security/v1p0#authentication-response-validation>.
+
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header, jwk::JwkSet};
+use models::{lti_login_states::LtiLoginState, lti_platforms::LtiPlatform};
+
+use super::{
+    DEEP_LINKING_REQUEST, LTI_VERSION, RESOURCE_LINK_REQUEST, claims::LaunchClaims, services,
+};
+use crate::prelude::*;
+
+/// Clock skew allowed between us and the platform.
+const LEEWAY_SECONDS: u64 = 60;
+
+/// Verifies the signature of the token with the keys of the platform and checks its claims.
+pub async fn validate_id_token(
+    id_token: &str,
+    platform: &LtiPlatform,
+    login_state: &LtiLoginState,
+) -> ControllerResult<LaunchClaims> {
+    let header = decode_header(id_token).map_err(|err| {
+        controller_err!(
+            BadRequest,
+            "The launch token is malformed.".to_string(),
+            anyhow::Error::from(err)
+        )
+    })?;
+    let jwks = services::fetch_platform_jwks(&platform.jwks_url).await?;
+    let key = select_key(&jwks, header.kid.as_deref())?;
+
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.leeway = LEEWAY_SECONDS;
+    validation.set_issuer(&[&platform.issuer]);
+    validation.set_audience(&[&platform.client_id]);
+    validation.set_required_spec_claims(&["exp", "iat", "iss", "aud", "sub"]);
+    let claims = decode::<LaunchClaims>(id_token, &key, &validation)
+        .map_err(|err| {
+            controller_err!(
+                Unauthorized,
+                format!("The launch token is not valid: {err}"),
+                anyhow::Error::from(err)
+            )
+        })?
+        .claims;
+    check_claims(&claims, platform, &login_state.nonce).map_err(|message| {
+        controller_err!(Unauthorized, format!("The launch was rejected: {message}"))
+    })?;
+    Ok(claims)
+}
+
+fn select_key(jwks: &JwkSet, kid: Option<&str>) -> ControllerResult<DecodingKey> {
+    let jwk = match kid {
+        Some(kid) => jwks.find(kid),
+        // A platform with a single key may leave out the key id.
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+    .ok_or_else(|| {
+        controller_err!(
+            Unauthorized,
+            "The launch token was not signed with any of the keys of the platform.".to_string()
+        )
+    })?;
+    Ok(DecodingKey::from_jwk(jwk)?)
+}
+
+/// The checks the signature validation does not cover.
+fn check_claims(
+    claims: &LaunchClaims,
+    platform: &LtiPlatform,
+    expected_nonce: &str,
+) -> Result<(), String> {
+    if claims.nonce != expected_nonce {
+        return Err("the nonce does not match the login.".to_string());
+    }
+    if claims.aud.is_multiple() && claims.azp.as_deref() != Some(platform.client_id.as_str()) {
+        return Err("the token is not authorized for this tool.".to_string());
+    }
+    if let Some(azp) = &claims.azp
+        && azp != &platform.client_id
+    {
+        return Err("the token is not authorized for this tool.".to_string());
+    }
+    if claims.version != LTI_VERSION {
+        return Err(format!("LTI version {} is not supported.", claims.version));
+    }
+    if !platform
+        .deployment_ids
+        .iter()
+        .any(|id| id == &claims.deployment_id)
+    {
+        return Err(format!(
+            "the deployment '{}' is not registered for the platform.",
+            claims.deployment_id
+        ));
+    }
+    match claims.message_type.as_str() {
+        RESOURCE_LINK_REQUEST if claims.resource_link.is_none() => {
+            Err("the resource link is missing.".to_string())
+        }
+        DEEP_LINKING_REQUEST if claims.deep_linking_settings.is_none() => {
+            Err("the deep linking settings are missing.".to_string())
+        }
+        RESOURCE_LINK_REQUEST | DEEP_LINKING_REQUEST => Ok(()),
+        other => Err(format!("the message type '{other}' is not supported.")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn platform() -> LtiPlatform {
+        LtiPlatform {
+            id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+            organization_id: Uuid::new_v4(),
+            name: "Platform".to_string(),
+            issuer: "https://lms.example.com".to_string(),
+            client_id: "client".to_string(),
+            deployment_ids: vec!["1".to_string()],
+            auth_login_url: "https://lms.example.com/auth".to_string(),
+            auth_token_url: "https://lms.example.com/token".to_string(),
+            jwks_url: "https://lms.example.com/jwks".to_string(),
+        }
+    }
+
+    fn claims() -> LaunchClaims {
+        serde_json::from_value(serde_json::json!({
+            "iss": "https://lms.example.com",
+            "sub": "user-1",
+            "aud": "client",
+            "exp": 2000000000,
+            "iat": 1000000000,
+            "nonce": "nonce",
+            "https://purl.imsglobal.org/spec/lti/claim/message_type": "LtiResourceLinkRequest",
+            "https://purl.imsglobal.org/spec/lti/claim/version": "1.3.0",
+            "https://purl.imsglobal.org/spec/lti/claim/deployment_id": "1",
+            "https://purl.imsglobal.org/spec/lti/claim/resource_link": { "id": "link-1" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn accepts_a_valid_launch() {
+        assert_eq!(check_claims(&claims(), &platform(), "nonce"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_replayed_or_foreign_launches() {
+        assert!(check_claims(&claims(), &platform(), "other-nonce").is_err());
+
+        let mut unknown_deployment = claims();
+        unknown_deployment.deployment_id = "2".to_string();
+        assert!(check_claims(&unknown_deployment, &platform(), "nonce").is_err());
+
+        let mut other_party = claims();
+        other_party.azp = Some("someone-else".to_string());
+        assert!(check_claims(&other_party, &platform(), "nonce").is_err());
+
+        let mut deep_linking_without_settings = claims();
+        deep_linking_without_settings.message_type = DEEP_LINKING_REQUEST.to_string();
+        assert!(check_claims(&deep_linking_without_settings, &platform(), "nonce").is_err());
+
+        let mut old_version = claims();
+        old_version.version = "1.1".to_string();
+        assert!(check_claims(&old_version, &platform(), "nonce").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+/*!
+LTI 1.3 tool support, which lets partner universities embed our course material in their own learning
+management systems, such as Moodle or Canvas.
+
+An organization admin registers the platform with [`models::lti_platforms`]. A launch then goes as follows:
+
+1. The platform starts a third-party-initiated login at `/api/v0/lti/login`. We store a state and a nonce,
+   set the state in a cookie and redirect the browser to the authorization endpoint of the platform.
+2. The platform posts a signed ID token to `/api/v0/lti/launch`. We check that the state matches the
+   cookie, so that the launch comes from the browser that started the login, validate the token against
+   the JWKS of the platform in [`launch`] and log the user in as the user linked to the platform user.
+3. A resource link launch takes the user to the linked course, chapter or exercise. A Deep Linking launch
+   lets a teacher pick chapters and exercises, which are returned to the platform as a signed response,
+   see [`deep_linking`].
+
+The scores of the users who have launched a link are passed back to the gradebook of the platform with the
+Assignment and Grade Services by the `lti-grade-syncer` program, see [`grade_passback`]. Teachers can list
+the members of a platform course with the Names and Role Provisioning Services, see [`services`].
+
+Everything we sign, the Deep Linking responses and the client assertions, is signed with the key of our
+OAuth server, so the platform is configured with `/api/v0/main-frontend/oauth/jwks.json` as the tool
+keyset URL.
+
+Our session cookie is `SameSite=Strict`, so the links should be opened in a new window instead of an iframe.
+*/
+
+pub mod claims;
+pub mod deep_linking;
+pub mod grade_passback;
+pub mod launch;
+pub mod services;
+
+/// The only LTI version we support.
+pub const LTI_VERSION: &str = "1.3.0";
+
+pub const RESOURCE_LINK_REQUEST: &str = "LtiResourceLinkRequest";
+pub const DEEP_LINKING_REQUEST: &str = "LtiDeepLinkingRequest";
+pub const DEEP_LINKING_RESPONSE: &str = "LtiDeepLinkingResponse";
+
+pub const AGS_SCOPE_SCORE: &str = "https://purl.imsglobal.org/spec/lti-ags/scope/score";
+pub const NRPS_SCOPE_MEMBERSHIP_READONLY: &str =
+    "https://purl.imsglobal.org/spec/lti-nrps/scope/contextmembership.readonly";
+
+/// The custom parameters of the links we create with Deep Linking, which tell what a link launches.
+pub const CUSTOM_COURSE_ID: &str = "course_id";
+pub const CUSTOM_CHAPTER_ID: &str = "chapter_id";
+pub const CUSTOM_EXERCISE_ID: &str = "exercise_id";
+
+/// The path the platforms post launches to.
+pub fn launch_url(base_url: &str) -> String {
+    format!("{}/api/v0/lti/launch", base_url.trim_end_matches('/'))
+}
@@ -0,0 +1,227 @@
+//! Requests to the services of a platform: its keys, access tokens, the Assignment and Grade Services
+//! and the Names and Role Provisioning Services.
+
+use headless_lms_utils::http::REQWEST_CLIENT;
+use jsonwebtoken::{Algorithm, EncodingKey, Header, encode, jwk::JwkSet};
+use models::{
+    exercises::{ActivityProgress, GradingProgress},
+    lti_platforms::LtiPlatform,
+};
+use reqwest::header;
+use secrecy::ExposeSecret;
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::{domain::oauth::oidc::rsa_n_e_and_kid_from_pem, prelude::*};
+
+const CLIENT_ASSERTION_TYPE: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+const SCORE_CONTENT_TYPE: &str = "application/vnd.ims.lis.v1.score+json";
+const MEMBERSHIP_CONTAINER_CONTENT_TYPE: &str =
+    "application/vnd.ims.lti-nrps.v2.membershipcontainer+json";
+
+/// A score passed back to a gradebook column.
+///
+/// See <https://www.imsglobal.org/spec/lti-ags/v2p0#score-publish-service>.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AgsScore {
+    pub user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_given: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_maximum: Option<f32>,
+    pub activity_progress: ActivityProgress,
+    pub grading_progress: GradingProgress,
+    pub timestamp: String,
+}
+
+/// The roster of a platform course.
+///
+/// See <https://www.imsglobal.org/spec/lti-nrps/v2p0#membership-container-media-type>.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct MembershipContainer {
+    pub id: String,
+    pub members: Vec<Member>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
+pub struct Member {
+    /// The subject of the user on the platform, as in the `sub` claim of the launches.
+    pub user_id: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    pub status: Option<String>,
+    pub name: Option<String>,
+    pub given_name: Option<String>,
+    pub family_name: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClientAssertionClaims {
+    iss: String,
+    sub: String,
+    aud: String,
+    iat: i64,
+    exp: i64,
+    jti: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+}
+
+pub async fn fetch_platform_jwks(jwks_url: &str) -> anyhow::Result<JwkSet> {
+    let jwks = REQWEST_CLIENT
+        .get(jwks_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<JwkSet>()
+        .await?;
+    Ok(jwks)
+}
+
+/// Signs a JWT with the key of our OAuth server, whose public key the platform gets from our JWKS.
+pub fn sign_tool_jwt<T: Serialize>(
+    claims: &T,
+    app_conf: &ApplicationConfiguration,
+) -> ControllerResult<String> {
+    let (_, _, kid) =
+        rsa_n_e_and_kid_from_pem(&app_conf.oauth_server_configuration.rsa_public_key)?;
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(kid);
+    let key = EncodingKey::from_rsa_pem(
+        app_conf
+            .oauth_server_configuration
+            .rsa_private_key
+            .expose_secret()
+            .as_bytes(),
+    )?;
+    Ok(encode(&header, claims, &key)?)
+}
+
+/// Gets an access token for the services of the platform with the OAuth 2.0 client credentials grant,
+/// authenticating with a signed client assertion.
+///
+/// See <https://www.imsglobal.org/spec/security/v1p0#using-json-web-tokens-with-oauth-2-0-client-credentials-grant>.
+pub async fn get_access_token(
+    platform: &LtiPlatform,
+    scopes: &[&str],
+    app_conf: &ApplicationConfiguration,
+) -> anyhow::Result<String> {
+    let now = Utc::now().timestamp();
+    let assertion = sign_tool_jwt(
+        &ClientAssertionClaims {
+            iss: platform.client_id.clone(),
+            sub: platform.client_id.clone(),
+            aud: platform.auth_token_url.clone(),
+            iat: now,
+            exp: now + 300,
+            jti: Uuid::new_v4().to_string(),
+        },
+        app_conf,
+    )
+    .map_err(|err| anyhow::anyhow!("Failed to sign the client assertion: {err}"))?;
+    let body = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("grant_type", "client_credentials")
+        .append_pair("client_assertion_type", CLIENT_ASSERTION_TYPE)
+        .append_pair("client_assertion", &assertion)
+        .append_pair("scope", &scopes.join(" "))
+        .finish();
+    let response = REQWEST_CLIENT
+        .post(&platform.auth_token_url)
+        .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<AccessTokenResponse>()
+        .await?;
+    Ok(response.access_token)
+}
+
+/// The scores endpoint of a gradebook column. The column URL may have a query string, so the path
+/// segment is inserted before it.
+pub fn scores_url(lineitem_url: &str) -> anyhow::Result<String> {
+    let mut url = Url::parse(lineitem_url)?;
+    let path = format!("{}/scores", url.path().trim_end_matches('/'));
+    url.set_path(&path);
+    Ok(url.to_string())
+}
+
+pub async fn post_score(
+    lineitem_url: &str,
+    access_token: &str,
+    score: &AgsScore,
+) -> anyhow::Result<()> {
+    REQWEST_CLIENT
+        .post(scores_url(lineitem_url)?)
+        .bearer_auth(access_token)
+        .header(header::CONTENT_TYPE, SCORE_CONTENT_TYPE)
+        .body(serde_json::to_vec(score)?)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+pub async fn get_memberships(
+    context_memberships_url: &str,
+    access_token: &str,
+) -> anyhow::Result<MembershipContainer> {
+    let memberships = REQWEST_CLIENT
+        .get(context_memberships_url)
+        .bearer_auth(access_token)
+        .header(header::ACCEPT, MEMBERSHIP_CONTAINER_CONTENT_TYPE)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<MembershipContainer>()
+        .await?;
+    Ok(memberships)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scores_url_keeps_the_query_string() {
+        assert_eq!(
+            scores_url("https://lms.example.com/api/lti/courses/1/line_items/2").unwrap(),
+            "https://lms.example.com/api/lti/courses/1/line_items/2/scores"
+        );
+        assert_eq!(
+            scores_url(
+                "https://lms.example.com/mod/lti/services.php/2/lineitems/3/lineitem?type_id=1"
+            )
+            .unwrap(),
+            "https://lms.example.com/mod/lti/services.php/2/lineitems/3/lineitem/scores?type_id=1"
+        );
+    }
+
+    #[test]
+    fn score_is_serialized_in_ags_format() {
+        let score = AgsScore {
+            user_id: "user-1".to_string(),
+            score_given: Some(3.0),
+            score_maximum: Some(4.0),
+            activity_progress: ActivityProgress::Completed,
+            grading_progress: GradingProgress::FullyGraded,
+            timestamp: "2026-01-01T00:00:00.000Z".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&score).unwrap(),
+            serde_json::json!({
+                "userId": "user-1",
+                "scoreGiven": 3.0,
+                "scoreMaximum": 4.0,
+                "activityProgress": "Completed",
+                "gradingProgress": "FullyGraded",
+                "timestamp": "2026-01-01T00:00:00.000Z"
+            })
+        );
+    }
+}
@@ -3,18 +3,26 @@ Helpful structs and functions that are not related to database tables.
 */
 
 pub mod authorization;
+pub mod certificate_bulk_exports;
+pub mod certificate_credentials;
+pub mod common_cartridge;
+pub mod course_archives;
 pub mod credit_registration;
 pub mod credit_registration_phases;
 pub mod csv_export;
 pub mod email_ownership_verification;
 pub mod error;
+pub mod exam_accommodations;
 pub mod exercise_repositories;
 pub mod exercise_services;
 pub mod exercises;
 pub mod internal_error_reporting;
+pub mod lti;
 pub mod models_requests;
 pub mod oauth;
 pub mod rate_limit_middleware_builder;
 pub mod request_id;
 pub mod request_span_middleware;
 pub mod system_health;
+pub mod webhooks;
+pub mod xapi;
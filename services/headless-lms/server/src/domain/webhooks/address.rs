@@ -0,0 +1,151 @@
+//! Keeping the webhooks from being pointed at our own network.
+//!
+//! The URL of a subscription is checked when it is saved, and the address it resolves to is checked
+//! again before every delivery, as the DNS record can be changed after the subscription was saved.
+
+use std::net::{IpAddr, SocketAddr};
+
+use url::{Host, Url};
+
+/// Whether the address is on the public internet. Loopback, link-local, private and other special
+/// purpose addresses are not.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // 0.0.0.0/8, "this network"
+                || a == 0
+                // 100.64.0.0/10, carrier-grade NAT
+                || (a == 100 && (b & 0xc0) == 64)
+                // 192.0.0.0/24, protocol assignments
+                || (a == 192 && b == 0 && ip.octets()[2] == 0)
+                // 198.18.0.0/15, benchmarking
+                || (a == 198 && (b & 0xfe) == 18)
+                // 240.0.0.0/4, reserved
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(mapped));
+            }
+            let segments = ip.segments();
+            let first = segments[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // 64:ff9b::/96, NAT64 to any IPv4 address
+                || segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0]
+                // 64:ff9b:1::/48, local-use NAT64
+                || segments[..3] == [0x64, 0xff9b, 1]
+                // fc00::/7, unique local
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10, link-local
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum AddressError {
+    /// The host has no addresses right now.
+    Unresolved(String),
+    /// The host resolves to an address that is not public.
+    NotPublic(IpAddr),
+}
+
+impl std::fmt::Display for AddressError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unresolved(message) => write!(f, "{message}"),
+            Self::NotPublic(ip) => write!(
+                f,
+                "The webhook URL points to {ip}, which is not a public address."
+            ),
+        }
+    }
+}
+
+/// Resolves the host of the URL. Fails if it does not resolve, or if any of the addresses is not
+/// public and `allow_private_addresses` is not set.
+pub async fn resolve(
+    url: &Url,
+    allow_private_addresses: bool,
+) -> Result<Vec<SocketAddr>, AddressError> {
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| AddressError::Unresolved("The webhook URL has no port.".to_string()))?;
+    let addresses = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(IpAddr::V4(ip), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(IpAddr::V6(ip), port)],
+        Some(Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+            .await
+            .map_err(|err| AddressError::Unresolved(format!("Failed to resolve {domain}: {err}")))?
+            .collect(),
+        None => {
+            return Err(AddressError::Unresolved(
+                "The webhook URL has no host.".to_string(),
+            ));
+        }
+    };
+    if addresses.is_empty() {
+        return Err(AddressError::Unresolved(
+            "The host of the webhook URL has no addresses.".to_string(),
+        ));
+    }
+    if !allow_private_addresses
+        && let Some(address) = addresses.iter().find(|address| !is_public(address.ip()))
+    {
+        return Err(AddressError::NotPublic(address.ip()));
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::7f00:1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b:1::a00:1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn private_addresses_are_only_resolved_when_allowed() {
+        let url = Url::parse("https://127.0.0.1:8443/hook").unwrap();
+        assert_eq!(
+            resolve(&url, false).await,
+            Err(AddressError::NotPublic("127.0.0.1".parse().unwrap()))
+        );
+        assert_eq!(
+            resolve(&url, true).await.unwrap(),
+            vec!["127.0.0.1:8443".parse().unwrap()]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Posting a delivery to the endpoint of its subscription.
+
+use std::time::Duration;
+
+use models::webhook_deliveries::ClaimedWebhookDelivery;
+use reqwest::{StatusCode, header, redirect};
+use url::Url;
+
+use super::{address, signing};
+
+/// Receivers are expected to acknowledge the delivery right away and do their work afterwards.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub struct DeliveryError {
+    /// The status of the response, if the receiver responded.
+    pub status: Option<StatusCode>,
+    /// Whether attempting the same delivery again may succeed.
+    pub transient: bool,
+    pub message: String,
+}
+
+impl std::fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Posts the payload of the delivery, signed with the secret of its subscription. Returns the status
+/// of the response if the receiver accepted it with a 2xx status.
+///
+/// The request is sent to the addresses checked with [`address::resolve`], and redirects are not
+/// followed, so the endpoint can't send the request on to our own network.
+pub async fn deliver(
+    delivery: &ClaimedWebhookDelivery,
+    allow_private_addresses: bool,
+) -> Result<StatusCode, DeliveryError> {
+    let url = Url::parse(&delivery.url).map_err(|err| DeliveryError {
+        status: None,
+        transient: false,
+        message: format!("Invalid webhook URL: {err}"),
+    })?;
+    let addresses = address::resolve(&url, allow_private_addresses)
+        .await
+        .map_err(|err| DeliveryError {
+            status: None,
+            // A host that does not resolve right now may resolve later.
+            transient: matches!(err, address::AddressError::Unresolved(_)),
+            message: err.to_string(),
+        })?;
+    let mut client = reqwest::Client::builder()
+        .https_only(!allow_private_addresses)
+        .redirect(redirect::Policy::none());
+    if let Some(domain) = url.domain() {
+        client = client.resolve_to_addrs(domain, &addresses);
+    }
+    let client = client.build().map_err(|err| DeliveryError {
+        status: None,
+        transient: true,
+        message: format!("Failed to build the HTTP client: {err}"),
+    })?;
+    let body = serde_json::to_vec(&delivery.payload).map_err(|err| DeliveryError {
+        status: None,
+        transient: false,
+        message: format!("Failed to serialize the payload: {err}"),
+    })?;
+    let id = delivery.event_id.to_string();
+    let timestamp = chrono::Utc::now().timestamp();
+    let signature = signing::sign(&delivery.secret, &id, timestamp, &body);
+    let response = client
+        .post(url)
+        .timeout(REQUEST_TIMEOUT)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(signing::HEADER_ID, id)
+        .header(signing::HEADER_TIMESTAMP, timestamp.to_string())
+        .header(signing::HEADER_SIGNATURE, signature)
+        .body(body)
+        .send()
+        .await
+        .map_err(|err| DeliveryError {
+            status: None,
+            transient: true,
+            message: format!("Failed to reach the endpoint: {err}"),
+        })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(status);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(DeliveryError {
+        status: Some(status),
+        transient: is_transient_status(status),
+        message: format!(
+            "The endpoint responded with {status}: {}",
+            body.chars().take(500).collect::<String>()
+        ),
+    })
+}
+
+/// Other client errors mean that the receiver does not want the delivery, so it is not retried and
+/// becomes a dead letter right away.
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_server_errors_and_throttling_are_retried() {
+        assert!(is_transient_status(StatusCode::BAD_GATEWAY));
+        assert!(is_transient_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_transient_status(StatusCode::GONE));
+        assert!(!is_transient_status(StatusCode::UNAUTHORIZED));
+    }
+}
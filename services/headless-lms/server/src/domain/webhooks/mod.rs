@@ -0,0 +1,22 @@
+/*!
+Outbound webhooks: posting the events of an organization, or of one of its courses, to the endpoints
+of the teams that integrate with us, so that they do not have to poll the completions stream or the
+exports.
+
+The `webhook-deliverer` program runs the pipeline in two steps:
+
+1. [`outbox::enqueue_new_events`] reads the new rows of the source table of each event type, such as
+   the module completions and the credit registration events, and adds a delivery of the event to
+   [`models::webhook_deliveries`] for every subscription in [`models::webhook_subscriptions`] it
+   matches.
+2. [`outbox::send_due_deliveries`] posts the due deliveries with [`delivery::deliver`], signed with
+   [`signing::sign`], retrying with an exponential backoff. Deliveries that are rejected, or keep
+   failing for longer than the retry window, become dead letters that can be redelivered by hand.
+
+The endpoints must be on the public internet, see [`address`].
+*/
+
+pub mod address;
+pub mod delivery;
+pub mod outbox;
+pub mod signing;
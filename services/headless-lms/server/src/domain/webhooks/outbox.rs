@@ -0,0 +1,213 @@
+//! Filling the outbox from the source tables and posting the due deliveries.
+
+use models::{
+    webhook_deliveries::{
+        self, ClaimedWebhookDelivery, FETCH_LIMIT, NewWebhookDelivery, RETRY_WINDOW_SECS,
+        WebhookSourceEvent,
+    },
+    webhook_subscriptions::{self, WebhookEventType},
+};
+use serde_json::{Value, json};
+
+use super::delivery::{self, DeliveryError};
+use crate::prelude::*;
+
+/// How many rows of a source are read at a time.
+const SOURCE_BATCH_SIZE: i64 = 500;
+
+/// Rows are read only once they are this old. A row gets its timestamp when its transaction starts,
+/// so a newer row may become visible before an older one, and reading right up to the present could
+/// move the cursor past rows that are not committed yet.
+const SOURCE_LAG_SECS: i64 = 60;
+
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 6 * 60 * 60;
+const JITTER_SECS: i64 = 30;
+
+/// Adds the new events of every type to the outbox. Returns how many events were read.
+pub async fn enqueue_new_events(conn: &mut PgConnection) -> anyhow::Result<usize> {
+    let until = Utc::now() - chrono::Duration::seconds(SOURCE_LAG_SECS);
+    let mut total = 0;
+    for event_type in WebhookEventType::ALL {
+        loop {
+            let mut tx = conn.begin().await?;
+            let subscriptions =
+                webhook_subscriptions::get_enabled_by_event_type(&mut tx, event_type).await?;
+            let cursor = webhook_deliveries::get_cursor(&mut tx, event_type).await?;
+            // Without subscriptions the events are skipped, so that a new subscription does not get
+            // a backlog of old events.
+            let events = if subscriptions.is_empty() {
+                vec![]
+            } else {
+                webhook_deliveries::get_source_events(
+                    &mut tx,
+                    event_type,
+                    cursor,
+                    until,
+                    SOURCE_BATCH_SIZE,
+                )
+                .await?
+            };
+            for event in &events {
+                // Shared by the deliveries of the event, so that a receiver with several
+                // subscriptions can tell that they are the same event.
+                let event_id = Uuid::new_v4();
+                let payload = build_payload(event_id, event_type, event);
+                for subscription in subscriptions.iter().filter(|subscription| {
+                    subscription.matches(event.organization_id, event.course_id)
+                }) {
+                    webhook_deliveries::insert(
+                        &mut tx,
+                        &NewWebhookDelivery {
+                            subscription_id: subscription.id,
+                            event_id,
+                            event_type,
+                            source_id: event.source_id,
+                            payload: payload.clone(),
+                        },
+                    )
+                    .await?;
+                }
+            }
+            let done = (events.len() as i64) < SOURCE_BATCH_SIZE;
+            match events.last() {
+                // More rows may follow, continue from the last one.
+                Some(last) if !done => {
+                    webhook_deliveries::set_cursor(
+                        &mut tx,
+                        event_type,
+                        last.cursor_at,
+                        last.source_id,
+                    )
+                    .await?
+                }
+                _ => {
+                    webhook_deliveries::set_cursor(&mut tx, event_type, until, Uuid::nil()).await?
+                }
+            }
+            tx.commit().await?;
+            total += events.len();
+            if done {
+                break;
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// The body that is posted to the subscribers.
+pub fn build_payload(
+    event_id: Uuid,
+    event_type: WebhookEventType,
+    event: &WebhookSourceEvent,
+) -> Value {
+    json!({
+        "id": event_id,
+        "type": event_type,
+        "occurred_at": event.cursor_at,
+        "organization_id": event.organization_id,
+        "course_id": event.course_id,
+        "data": event.data,
+    })
+}
+
+/// Posts the deliveries that are due. Returns how many were delivered.
+///
+/// `allow_private_addresses` lets the tests deliver to receivers on the local network, see
+/// [`super::address`].
+pub async fn send_due_deliveries(
+    conn: &mut PgConnection,
+    allow_private_addresses: bool,
+) -> anyhow::Result<usize> {
+    let due = webhook_deliveries::claim_due(conn, FETCH_LIMIT).await?;
+    let mut delivered = 0;
+    for claimed in &due {
+        match delivery::deliver(claimed, allow_private_addresses).await {
+            Ok(status) => {
+                webhook_deliveries::mark_delivered(conn, claimed.id, i32::from(status.as_u16()))
+                    .await?;
+                delivered += 1;
+            }
+            Err(err) => record_failure(conn, claimed, &err).await?,
+        }
+    }
+    Ok(delivered)
+}
+
+async fn record_failure(
+    conn: &mut PgConnection,
+    claimed: &ClaimedWebhookDelivery,
+    err: &DeliveryError,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    warn!(
+        "Webhook delivery {} failed (attempt {}, transient={}): {}",
+        claimed.id, claimed.attempt_count, err.transient, err
+    );
+    let status = err.status.map(|status| i32::from(status.as_u16()));
+    let window_expired = claimed
+        .first_failed_at
+        .is_some_and(|first_failed_at| (now - first_failed_at).num_seconds() > RETRY_WINDOW_SECS);
+    if err.transient && !window_expired {
+        let next_attempt_at = compute_next_attempt_at(now, claimed.attempt_count);
+        webhook_deliveries::schedule_retry(conn, claimed.id, next_attempt_at, status, &err.message)
+            .await?;
+    } else {
+        webhook_deliveries::mark_dead(conn, claimed.id, status, &err.message).await?;
+    }
+    Ok(())
+}
+
+fn compute_next_attempt_at(now: DateTime<Utc>, attempt_count: i32) -> DateTime<Utc> {
+    let backoff = backoff_secs(attempt_count);
+    let jitter = rand::rng().random_range(0..=JITTER_SECS);
+    now + chrono::Duration::seconds(backoff + jitter)
+}
+
+/// The first retry is after the base backoff, and every retry after that waits twice as long.
+fn backoff_secs(attempt_count: i32) -> i64 {
+    let exponent = (attempt_count - 1).max(0) as u32;
+    let multiplier = 2_i64.checked_pow(exponent).unwrap_or(i64::MAX);
+    BASE_BACKOFF_SECS
+        .saturating_mul(multiplier)
+        .min(MAX_BACKOFF_SECS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_six_hours() {
+        assert_eq!(backoff_secs(1), 30);
+        assert_eq!(backoff_secs(2), 60);
+        assert_eq!(backoff_secs(5), 30 * 16);
+        assert_eq!(backoff_secs(30), MAX_BACKOFF_SECS);
+        assert_eq!(backoff_secs(i32::MAX), MAX_BACKOFF_SECS);
+    }
+
+    #[test]
+    fn payload_wraps_the_event() {
+        let event_id = Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap();
+        let event = WebhookSourceEvent {
+            source_id: Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap(),
+            cursor_at: DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            organization_id: Uuid::parse_str("33333333-3333-3333-3333-333333333333").unwrap(),
+            course_id: None,
+            data: json!({ "exam_id": "44444444-4444-4444-4444-444444444444" }),
+        };
+        assert_eq!(
+            build_payload(event_id, WebhookEventType::ExamEnded, &event),
+            json!({
+                "id": "11111111-1111-1111-1111-111111111111",
+                "type": "exam-ended",
+                "occurred_at": "2026-01-01T12:00:00Z",
+                "organization_id": "33333333-3333-3333-3333-333333333333",
+                "course_id": null,
+                "data": { "exam_id": "44444444-4444-4444-4444-444444444444" }
+            })
+        );
+    }
+}
@@ -0,0 +1,80 @@
+//! Signing the deliveries so that the receivers can check that they come from us and have not been
+//! replayed.
+//!
+//! Follows the Standard Webhooks scheme: the signature is the base64 encoded HMAC-SHA256 of
+//! `{webhook-id}.{webhook-timestamp}.{body}` with the secret of the subscription, sent as
+//! `v1,{signature}` in the `webhook-signature` header.
+
+use base64::Engine;
+use hmac::{KeyInit, Mac};
+
+use crate::domain::oauth::hmac_sha256::HmacSha256;
+
+pub const HEADER_ID: &str = "webhook-id";
+pub const HEADER_TIMESTAMP: &str = "webhook-timestamp";
+pub const HEADER_SIGNATURE: &str = "webhook-signature";
+
+/// The prefix of the generated secrets, so that they are recognizable if they leak.
+pub const SECRET_PREFIX: &str = "whsec_";
+
+pub fn generate_secret() -> String {
+    format!(
+        "{SECRET_PREFIX}{}",
+        headless_lms_utils::strings::generate_random_string(40)
+    )
+}
+
+/// The value of the `webhook-signature` header.
+pub fn sign(secret: &str, id: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(id.as_bytes());
+    mac.update(b".");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let signature = mac.finalize().into_bytes();
+    format!(
+        "v1,{}",
+        base64::engine::general_purpose::STANDARD.encode(signature)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn signature_covers_the_id_the_timestamp_and_the_body() {
+        let signature = sign("whsec_test", "event-1", 1_700_000_000, b"{}");
+        assert!(signature.starts_with("v1,"));
+        assert_eq!(
+            signature,
+            sign("whsec_test", "event-1", 1_700_000_000, b"{}")
+        );
+        assert_ne!(
+            signature,
+            sign("whsec_other", "event-1", 1_700_000_000, b"{}")
+        );
+        assert_ne!(
+            signature,
+            sign("whsec_test", "event-2", 1_700_000_000, b"{}")
+        );
+        assert_ne!(
+            signature,
+            sign("whsec_test", "event-1", 1_700_000_001, b"{}")
+        );
+        assert_ne!(
+            signature,
+            sign("whsec_test", "event-1", 1_700_000_000, b"[]")
+        );
+    }
+
+    #[test]
+    fn secrets_are_prefixed_and_random() {
+        let secret = generate_secret();
+        assert!(secret.starts_with(SECRET_PREFIX));
+        assert_eq!(secret.len(), SECRET_PREFIX.len() + 40);
+        assert_ne!(secret, generate_secret());
+    }
+}
@@ -0,0 +1,87 @@
+//! The client of the learning record store.
+
+use headless_lms_base::config::XapiConfiguration;
+use headless_lms_utils::http::REQWEST_CLIENT;
+use reqwest::{StatusCode, header};
+use secrecy::ExposeSecret;
+use serde_json::Value;
+
+pub const XAPI_VERSION: &str = "1.0.3";
+
+#[derive(Debug)]
+pub struct LrsError {
+    /// Whether sending the same statements again may succeed.
+    pub transient: bool,
+    pub message: String,
+}
+
+impl std::fmt::Display for LrsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Sends the statements in one request.
+///
+/// The statements carry their ids, so the store ignores the ones it already has. A conflict means
+/// that a statement with the same id but different content is stored, which cannot be fixed by
+/// sending again, so it is not treated as transient.
+pub async fn send_statements(
+    conf: &XapiConfiguration,
+    statements: &[Value],
+) -> Result<(), LrsError> {
+    let url = conf.endpoint.join("statements").map_err(|err| LrsError {
+        transient: false,
+        message: format!("Invalid statements URL: {err}"),
+    })?;
+    let response = REQWEST_CLIENT
+        .post(url)
+        .basic_auth(&conf.username, Some(conf.password.expose_secret()))
+        .header("X-Experience-API-Version", XAPI_VERSION)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(statements).map_err(|err| LrsError {
+            transient: false,
+            message: format!("Failed to serialize the statements: {err}"),
+        })?)
+        .send()
+        .await
+        .map_err(|err| LrsError {
+            transient: true,
+            message: format!("Failed to reach the learning record store: {err}"),
+        })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(LrsError {
+        transient: is_transient_status(status),
+        message: format!(
+            "The learning record store responded with {status}: {}",
+            body.chars().take(500).collect::<String>()
+        ),
+    })
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::UNAUTHORIZED
+        || status == StatusCode::FORBIDDEN
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_rejected_statements_are_not_retried() {
+        assert!(is_transient_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_transient_status(StatusCode::TOO_MANY_REQUESTS));
+        // Bad credentials are a configuration problem that is fixed without touching the statements.
+        assert!(is_transient_status(StatusCode::UNAUTHORIZED));
+        assert!(!is_transient_status(StatusCode::BAD_REQUEST));
+        assert!(!is_transient_status(StatusCode::CONFLICT));
+    }
+}
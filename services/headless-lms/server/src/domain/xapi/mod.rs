@@ -0,0 +1,20 @@
+/*!
+Sending learning events to an external learning record store as xAPI statements, for the learning
+analytics research.
+
+The `xapi-sender` program runs the pipeline in two steps:
+
+1. [`outbox::enqueue_new_events`] reads the new rows of the source tables, such as the submissions and
+   the module completions, makes statements of them with [`statements::build_statement`] and adds the
+   statements to the outbox in [`models::xapi_statements`].
+2. [`outbox::send_due_statements`] sends the statements in the outbox to the store in batches with
+   [`lrs::send_statements`], retrying with an exponential backoff like the email deliveries.
+
+Only the events of courses that have opted in with [`models::course_xapi_opt_ins`] are sent, and only for
+users who have given their research consent. The statements identify users by their id, never by their
+email or name.
+*/
+
+pub mod lrs;
+pub mod outbox;
+pub mod statements;
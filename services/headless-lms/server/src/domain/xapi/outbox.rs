@@ -0,0 +1,196 @@
+//! Filling the outbox from the source tables and sending it to the learning record store.
+
+use headless_lms_base::config::XapiConfiguration;
+use models::xapi_statements::{
+    self, FETCH_LIMIT, NewXapiStatement, RETRY_WINDOW_SECS, XapiStatement, XapiStatementSource,
+};
+
+use super::{
+    lrs::{self, LrsError},
+    statements::build_statement,
+};
+use crate::prelude::*;
+
+/// How many rows of a source are read at a time.
+const SOURCE_BATCH_SIZE: i64 = 500;
+
+/// Rows are read only once they are this old. A row gets its timestamp when its transaction starts,
+/// so a newer row may become visible before an older one, and reading right up to the present could
+/// move the cursor past rows that are not committed yet.
+const SOURCE_LAG_SECS: i64 = 60;
+
+/// How many statements are sent in one request.
+const SEND_BATCH_SIZE: usize = 50;
+
+const BASE_BACKOFF_SECS: i64 = 60;
+const MAX_BACKOFF_SECS: i64 = 24 * 60 * 60;
+const JITTER_SECS: i64 = 30;
+
+/// Makes statements of the new rows of every source. Returns how many rows were read.
+pub async fn enqueue_new_events(conn: &mut PgConnection, base_url: &str) -> anyhow::Result<usize> {
+    let until = Utc::now() - chrono::Duration::seconds(SOURCE_LAG_SECS);
+    let mut total = 0;
+    for source in XapiStatementSource::ALL {
+        loop {
+            let mut tx = conn.begin().await?;
+            let cursor = xapi_statements::get_cursor(&mut tx, source).await?;
+            let events = xapi_statements::get_source_events(
+                &mut tx,
+                source,
+                cursor,
+                until,
+                SOURCE_BATCH_SIZE,
+            )
+            .await?;
+            for event in &events {
+                let id = Uuid::new_v4();
+                let statement = match build_statement(base_url, id, source, event) {
+                    Ok(statement) => statement,
+                    Err(err) => {
+                        warn!(
+                            "Skipping the {:?} event {}: {:#}",
+                            source, event.source_id, err
+                        );
+                        continue;
+                    }
+                };
+                xapi_statements::insert(
+                    &mut tx,
+                    &NewXapiStatement {
+                        id,
+                        source,
+                        source_id: event.source_id,
+                        course_id: event.course_id,
+                        user_id: event.user_id,
+                        statement,
+                    },
+                )
+                .await?;
+            }
+            let done = (events.len() as i64) < SOURCE_BATCH_SIZE;
+            match events.last() {
+                // More rows may follow, continue from the last one.
+                Some(last) if !done => {
+                    xapi_statements::set_cursor(&mut tx, source, last.cursor_at, last.source_id)
+                        .await?
+                }
+                // Everything up to `until` has been read. The rows that were skipped because of the
+                // opt-ins or the consents are not read again.
+                _ => xapi_statements::set_cursor(&mut tx, source, until, Uuid::nil()).await?,
+            }
+            tx.commit().await?;
+            total += events.len();
+            if done {
+                break;
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Sends the statements that are due. Returns how many were sent.
+pub async fn send_due_statements(
+    conn: &mut PgConnection,
+    conf: &XapiConfiguration,
+) -> anyhow::Result<usize> {
+    let deleted = xapi_statements::delete_unsent_without_consent(conn).await?;
+    if deleted > 0 {
+        info!(
+            "Deleted {} unsent xAPI statements of users without a research consent",
+            deleted
+        );
+    }
+    let due = xapi_statements::claim_due(conn, FETCH_LIMIT).await?;
+    let mut sent = 0;
+    for batch in due.chunks(SEND_BATCH_SIZE) {
+        let bodies = batch
+            .iter()
+            .map(|statement| statement.statement.clone())
+            .collect::<Vec<_>>();
+        match lrs::send_statements(conf, &bodies).await {
+            Ok(()) => {
+                let ids = batch
+                    .iter()
+                    .map(|statement| statement.id)
+                    .collect::<Vec<_>>();
+                xapi_statements::mark_sent(conn, &ids).await?;
+                sent += batch.len();
+            }
+            Err(err) if err.transient || batch.len() == 1 => {
+                for statement in batch {
+                    record_failure(conn, statement, &err).await?;
+                }
+            }
+            // One rejected statement fails the whole batch, so the statements are sent one by one
+            // to find out which.
+            Err(_) => {
+                for statement in batch {
+                    match lrs::send_statements(conf, std::slice::from_ref(&statement.statement))
+                        .await
+                    {
+                        Ok(()) => {
+                            xapi_statements::mark_sent(conn, &[statement.id]).await?;
+                            sent += 1;
+                        }
+                        Err(err) => record_failure(conn, statement, &err).await?,
+                    }
+                }
+            }
+        }
+    }
+    Ok(sent)
+}
+
+async fn record_failure(
+    conn: &mut PgConnection,
+    statement: &XapiStatement,
+    err: &LrsError,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    warn!(
+        "Sending the xAPI statement {} failed (attempt {}, transient={}): {}",
+        statement.id,
+        statement.retry_count + 1,
+        err.transient,
+        err
+    );
+    let window_expired = statement
+        .first_failed_at
+        .is_some_and(|first_failed_at| (now - first_failed_at).num_seconds() > RETRY_WINDOW_SECS);
+    if err.transient && !window_expired {
+        let next_retry_at = compute_next_retry_at(now, statement.retry_count);
+        xapi_statements::schedule_retry(conn, statement.id, next_retry_at, &err.message).await?;
+    } else {
+        xapi_statements::mark_non_retryable(conn, statement.id, &err.message).await?;
+    }
+    Ok(())
+}
+
+fn compute_next_retry_at(now: DateTime<Utc>, retry_count: i32) -> DateTime<Utc> {
+    let backoff = backoff_secs(retry_count);
+    let jitter = rand::rng().random_range(0..=JITTER_SECS);
+    now + chrono::Duration::seconds(backoff + jitter)
+}
+
+fn backoff_secs(retry_count: i32) -> i64 {
+    // Saturating math + MAX_BACKOFF_SECS clamp handles outlier values safely.
+    let exponent = retry_count.max(0) as u32;
+    let multiplier = 2_i64.checked_pow(exponent).unwrap_or(i64::MAX);
+    BASE_BACKOFF_SECS
+        .saturating_mul(multiplier)
+        .min(MAX_BACKOFF_SECS)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_a_day() {
+        assert_eq!(backoff_secs(0), 60);
+        assert_eq!(backoff_secs(1), 120);
+        assert_eq!(backoff_secs(5), 60 * 32);
+        assert_eq!(backoff_secs(20), MAX_BACKOFF_SECS);
+        assert_eq!(backoff_secs(1000), MAX_BACKOFF_SECS);
+    }
+}
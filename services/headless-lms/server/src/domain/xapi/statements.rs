@@ -0,0 +1,264 @@
+//! Building xAPI statements from the source events.
+//!
+//! See <https://github.com/adlnet/xAPI-Spec/blob/master/xAPI-Data.md#statements>.
+
+use models::xapi_statements::{XapiSourceEvent, XapiStatementSource};
+use serde_json::{Map, Value, json};
+
+use crate::prelude::*;
+
+pub const VERB_ANSWERED: &str = "http://adlnet.gov/expapi/verbs/answered";
+pub const VERB_SCORED: &str = "http://adlnet.gov/expapi/verbs/scored";
+pub const VERB_EXPERIENCED: &str = "http://adlnet.gov/expapi/verbs/experienced";
+pub const VERB_REVIEWED: &str = "http://id.tincanapi.com/verb/reviewed";
+pub const VERB_COMPLETED: &str = "http://adlnet.gov/expapi/verbs/completed";
+
+const ACTIVITY_TYPE_ASSESSMENT: &str = "http://adlnet.gov/expapi/activities/assessment";
+const ACTIVITY_TYPE_PAGE: &str = "http://activitystrea.ms/schema/1.0/page";
+const ACTIVITY_TYPE_MODULE: &str = "http://adlnet.gov/expapi/activities/module";
+const ACTIVITY_TYPE_COURSE: &str = "http://adlnet.gov/expapi/activities/course";
+
+/// The names come from course content in any language, so they are tagged as undetermined.
+const LANGUAGE_UNDETERMINED: &str = "und";
+
+pub fn build_statement(
+    base_url: &str,
+    id: Uuid,
+    source: XapiStatementSource,
+    event: &XapiSourceEvent,
+) -> anyhow::Result<Value> {
+    let base_url = base_url.trim_end_matches('/');
+    let (verb, verb_display, object_path, object_type) = match source {
+        XapiStatementSource::ExerciseSlideSubmission => (
+            VERB_ANSWERED,
+            "answered",
+            "exercises",
+            ACTIVITY_TYPE_ASSESSMENT,
+        ),
+        XapiStatementSource::ExerciseTaskGrading => {
+            (VERB_SCORED, "scored", "exercises", ACTIVITY_TYPE_ASSESSMENT)
+        }
+        XapiStatementSource::PageVisit => {
+            (VERB_EXPERIENCED, "experienced", "pages", ACTIVITY_TYPE_PAGE)
+        }
+        XapiStatementSource::PeerOrSelfReviewSubmission => (
+            VERB_REVIEWED,
+            "reviewed",
+            "exercises",
+            ACTIVITY_TYPE_ASSESSMENT,
+        ),
+        XapiStatementSource::CourseModuleCompletion => (
+            VERB_COMPLETED,
+            "completed",
+            "course-modules",
+            ACTIVITY_TYPE_MODULE,
+        ),
+    };
+
+    let mut object_definition = Map::new();
+    object_definition.insert("type".to_string(), json!(object_type));
+    if let Some(name) = &event.object_name {
+        object_definition.insert("name".to_string(), json!({ LANGUAGE_UNDETERMINED: name }));
+    }
+
+    let mut statement = json!({
+        "id": id,
+        "actor": actor(base_url, event)?,
+        "verb": {
+            "id": verb,
+            "display": { "en-US": verb_display }
+        },
+        "object": {
+            "objectType": "Activity",
+            "id": activity_id(base_url, object_path, event.object_id),
+            "definition": object_definition
+        },
+        "context": {
+            "contextActivities": {
+                "parent": [{
+                    "objectType": "Activity",
+                    "id": activity_id(base_url, "courses", event.course_id),
+                    "definition": { "type": ACTIVITY_TYPE_COURSE }
+                }]
+            }
+        },
+        "timestamp": event.cursor_at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+    });
+    if let Some(result) = result(base_url, event) {
+        statement["result"] = result;
+    }
+    Ok(statement)
+}
+
+/// Users are identified by their id only. Page visits are not tied to users, so their actor is the
+/// daily anonymous visitor identifier, which cannot be linked to a user or across days.
+fn actor(base_url: &str, event: &XapiSourceEvent) -> anyhow::Result<Value> {
+    let account = match (event.user_id, &event.anonymous_identifier) {
+        (Some(user_id), _) => json!({ "homePage": base_url, "name": user_id }),
+        (None, Some(anonymous_identifier)) => json!({
+            "homePage": format!("{base_url}/anonymous-visitors"),
+            "name": anonymous_identifier
+        }),
+        (None, None) => anyhow::bail!("The event {} has no actor", event.source_id),
+    };
+    Ok(json!({ "objectType": "Agent", "account": account }))
+}
+
+fn activity_id(base_url: &str, path: &str, id: Uuid) -> String {
+    format!("{base_url}/xapi/activities/{path}/{id}")
+}
+
+fn result(base_url: &str, event: &XapiSourceEvent) -> Option<Value> {
+    let mut result = Map::new();
+    if let (Some(score_given), Some(score_maximum)) = (event.score_given, event.score_maximum) {
+        let mut score = json!({ "raw": score_given, "min": 0, "max": score_maximum });
+        if score_maximum > 0 {
+            score["scaled"] = json!((score_given / score_maximum as f32).clamp(0.0, 1.0));
+        }
+        result.insert("score".to_string(), score);
+    }
+    if let Some(passed) = event.passed {
+        result.insert("completion".to_string(), json!(true));
+        result.insert("success".to_string(), json!(passed));
+    }
+    if let Some(grade) = event.grade {
+        result.insert(
+            "extensions".to_string(),
+            json!({ format!("{base_url}/xapi/extensions/grade"): grade }),
+        );
+    }
+    (!result.is_empty()).then_some(Value::Object(result))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event() -> XapiSourceEvent {
+        XapiSourceEvent {
+            source_id: Uuid::parse_str("11111111-1111-1111-1111-111111111111").unwrap(),
+            cursor_at: DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            course_id: Uuid::parse_str("22222222-2222-2222-2222-222222222222").unwrap(),
+            user_id: Some(Uuid::parse_str("33333333-3333-3333-3333-333333333333").unwrap()),
+            anonymous_identifier: None,
+            object_id: Uuid::parse_str("44444444-4444-4444-4444-444444444444").unwrap(),
+            object_name: Some("Exercise 1".to_string()),
+            score_given: Some(3.0),
+            score_maximum: Some(4),
+            passed: None,
+            grade: None,
+        }
+    }
+
+    #[test]
+    fn grading_is_a_scored_statement() {
+        let id = Uuid::parse_str("55555555-5555-5555-5555-555555555555").unwrap();
+        let statement = build_statement(
+            "http://project-331.local/",
+            id,
+            XapiStatementSource::ExerciseTaskGrading,
+            &event(),
+        )
+        .unwrap();
+        assert_eq!(
+            statement,
+            json!({
+                "id": "55555555-5555-5555-5555-555555555555",
+                "actor": {
+                    "objectType": "Agent",
+                    "account": {
+                        "homePage": "http://project-331.local",
+                        "name": "33333333-3333-3333-3333-333333333333"
+                    }
+                },
+                "verb": {
+                    "id": VERB_SCORED,
+                    "display": { "en-US": "scored" }
+                },
+                "object": {
+                    "objectType": "Activity",
+                    "id": "http://project-331.local/xapi/activities/exercises/44444444-4444-4444-4444-444444444444",
+                    "definition": {
+                        "type": ACTIVITY_TYPE_ASSESSMENT,
+                        "name": { "und": "Exercise 1" }
+                    }
+                },
+                "context": {
+                    "contextActivities": {
+                        "parent": [{
+                            "objectType": "Activity",
+                            "id": "http://project-331.local/xapi/activities/courses/22222222-2222-2222-2222-222222222222",
+                            "definition": { "type": ACTIVITY_TYPE_COURSE }
+                        }]
+                    }
+                },
+                "result": {
+                    "score": { "raw": 3.0, "min": 0, "max": 4, "scaled": 0.75 }
+                },
+                "timestamp": "2026-01-01T12:00:00.000Z"
+            })
+        );
+    }
+
+    #[test]
+    fn page_visit_actor_is_anonymous() {
+        let mut event = event();
+        event.user_id = None;
+        event.anonymous_identifier = Some("daily-hash".to_string());
+        event.score_given = None;
+        event.score_maximum = None;
+        let statement = build_statement(
+            "http://project-331.local",
+            Uuid::new_v4(),
+            XapiStatementSource::PageVisit,
+            &event,
+        )
+        .unwrap();
+        assert_eq!(
+            statement["actor"]["account"],
+            json!({
+                "homePage": "http://project-331.local/anonymous-visitors",
+                "name": "daily-hash"
+            })
+        );
+        assert_eq!(statement["verb"]["id"], VERB_EXPERIENCED);
+        assert!(statement.get("result").is_none());
+
+        event.anonymous_identifier = None;
+        assert!(
+            build_statement(
+                "http://project-331.local",
+                Uuid::new_v4(),
+                XapiStatementSource::PageVisit,
+                &event,
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn completion_has_success_and_grade() {
+        let mut event = event();
+        event.score_given = None;
+        event.score_maximum = None;
+        event.passed = Some(true);
+        event.grade = Some(5);
+        let statement = build_statement(
+            "http://project-331.local",
+            Uuid::new_v4(),
+            XapiStatementSource::CourseModuleCompletion,
+            &event,
+        )
+        .unwrap();
+        assert_eq!(
+            statement["result"],
+            json!({
+                "completion": true,
+                "success": true,
+                "extensions": { "http://project-331.local/xapi/extensions/grade": 5 }
+            })
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Runs the certificate bulk exports teachers request for their course instances, see
+//! [`crate::domain::certificate_bulk_exports`].
+
+use std::{env, time::Duration};
+
+use crate::config::{FileStoreRuntimeConfig, program_config::ProgramConfig};
+use crate::domain::certificate_bulk_exports;
+use crate::{setup_file_store, setup_tracing};
+use dotenvy::dotenv;
+use headless_lms_utils::icu4x::Icu4xBlob;
+use sqlx::PgPool;
+
+/**
+Starts a loop that periodically picks up queued certificate bulk exports, as well as running ones whose worker has stopped, and processes them one at a time.
+*/
+pub async fn main() -> anyhow::Result<()> {
+    // TODO: Audit that the environment access only happens in single-threaded code.
+    unsafe { env::set_var("RUST_LOG", "info,actix_web=info,sqlx=warn") };
+    dotenv().ok();
+    setup_tracing()?;
+    let database_url = ProgramConfig::database_url_with_default();
+    let base_url = ProgramConfig::required("BASE_URL")?;
+    let icu4x_blob = Icu4xBlob::new(&ProgramConfig::required("ICU4X_POSTCARD_PATH")?)?;
+    let file_store = setup_file_store(&FileStoreRuntimeConfig::try_from_env()?, &base_url).await;
+
+    let mut interval = tokio::time::interval(Duration::from_secs(10));
+    let mut ticks = 60;
+
+    let db_pool = PgPool::connect(&database_url).await?;
+    let mut conn = db_pool.acquire().await?;
+    loop {
+        interval.tick().await;
+
+        ticks += 1;
+        // 60 10 second intervals = 10 minutes
+        if ticks > 60 {
+            // occasionally prints a reminder that the service is still running
+            ticks = 0;
+            tracing::info!("running the certificate bulk exporter");
+        }
+
+        // runs jobs back to back while there are any, and does not stop the loop on error
+        loop {
+            match certificate_bulk_exports::process_next(&mut conn, file_store.as_ref(), icu4x_blob)
+                .await
+            {
+                Ok(true) => continue,
+                Ok(false) => break,
+                Err(err) => {
+                    tracing::error!("Error in certificate bulk exporter: {:#}", err);
+                    if err.chain().any(|cause| {
+                        matches!(
+                            cause.downcast_ref::<sqlx::Error>(),
+                            Some(sqlx::Error::Io(..))
+                        )
+                    }) {
+                        // this usually happens if the database is reset while running bin/dev etc.
+                        tracing::info!(
+                            "certificate bulk exporter may have lost its connection to the db, trying to reconnect"
+                        );
+                        conn = db_pool.acquire().await?;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Answers and scores the pending chatbot evaluation runs, see
+//! [`headless_lms_chatbot::chatbot_evaluation`].
+
+use std::{env, time::Duration};
+
+use crate::config::program_config::ProgramConfig;
+use crate::setup_tracing;
+use dotenvy::dotenv;
+use headless_lms_base::config::ApplicationConfiguration;
+use headless_lms_chatbot::chatbot_evaluation::run_evaluation;
+use headless_lms_models::chatbot_evaluations;
+use sqlx::{PgConnection, PgPool};
+
+/**
+Starts a loop that periodically picks up the pending evaluation runs and runs them one at a time.
+*/
+pub async fn main() -> anyhow::Result<()> {
+    // TODO: Audit that the environment access only happens in single-threaded code.
+    unsafe { env::set_var("RUST_LOG", "info,actix_web=info,sqlx=warn") };
+    dotenv().ok();
+    setup_tracing()?;
+    let database_url = ProgramConfig::database_url_with_default();
+    let app_config = ApplicationConfiguration::try_from_env()?;
+
+    let mut interval = tokio::time::interval(Duration::from_secs(10));
+    let mut ticks = 60;
+
+    let db_pool = PgPool::connect(&database_url).await?;
+    let mut conn = db_pool.acquire().await?;
+    // Only one runner is deployed, so the runs that are still running were interrupted by a restart.
+    let requeued = chatbot_evaluations::requeue_interrupted_runs(&mut conn).await?;
+    if requeued > 0 {
+        tracing::info!(
+            "continuing {} interrupted chatbot evaluation runs",
+            requeued
+        );
+    }
+    loop {
+        interval.tick().await;
+
+        ticks += 1;
+        // 60 10 second intervals = 10 minutes
+        if ticks > 60 {
+            // occasionally prints a reminder that the service is still running
+            ticks = 0;
+            tracing::info!("running the chatbot evaluation runner");
+        }
+
+        if let Err(err) = process(&mut conn, &db_pool, &app_config).await {
+            tracing::error!("Error in chatbot evaluation runner: {:#}", err);
+            if err.chain().any(|cause| {
+                matches!(
+                    cause.downcast_ref::<sqlx::Error>(),
+                    Some(sqlx::Error::Io(..))
+                )
+            }) {
+                // this usually happens if the database is reset while running bin/dev etc.
+                tracing::info!(
+                    "chatbot evaluation runner may have lost its connection to the db, trying to reconnect"
+                );
+                conn = db_pool.acquire().await?;
+            }
+        }
+    }
+}
+
+async fn process(
+    conn: &mut PgConnection,
+    pool: &PgPool,
+    app_config: &ApplicationConfiguration,
+) -> anyhow::Result<()> {
+    while let Some(run) = chatbot_evaluations::claim_next_pending_run(conn).await? {
+        tracing::info!("starting chatbot evaluation run {}", run.id);
+        match run_evaluation(pool, app_config, &run).await {
+            Ok(()) => {
+                chatbot_evaluations::finish_run(conn, run.id, None).await?;
+                tracing::info!("finished chatbot evaluation run {}", run.id);
+            }
+            Err(err) => {
+                tracing::error!("chatbot evaluation run {} failed: {}", run.id, err);
+                chatbot_evaluations::finish_run(conn, run.id, Some(&err.to_string())).await?;
+            }
+        }
+    }
+    Ok(())
+}
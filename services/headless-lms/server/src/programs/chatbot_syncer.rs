@@ -23,6 +23,7 @@ use headless_lms_chatbot::{
         run_search_indexer_now,
     },
     azure_skillset::{create_skillset, does_skillset_exist},
+    builtin_search::{AzureEmbedder, index_page},
     content_cleaner::convert_material_blocks_to_markdown_with_llm,
 };
 use headless_lms_models::{
@@ -81,6 +82,12 @@ pub async fn main() -> anyhow::Result<()> {
         if let Err(e) = sync_pages(&mut conn, &config, &blob_client).await {
             error!("Error during synchronization: {:?}", e);
         }
+        if let Err(e) = sync_builtin_search_index(&mut conn, &config).await {
+            error!(
+                "Error during built-in search index synchronization: {:?}",
+                e
+            );
+        }
     }
 }
 
@@ -538,6 +545,138 @@ async fn sync_pages_batch(
     Ok(())
 }
 
+/// Indexes the public pages of the courses whose chatbots use the built-in search into
+/// `chatbot_page_chunks`. Pages are reindexed when they have a newer revision than the one their
+/// chunks were made from, and the chunks of pages that are no longer public are removed.
+async fn sync_builtin_search_index(
+    conn: &mut PgConnection,
+    config: &SyncerConfig,
+) -> anyhow::Result<()> {
+    let chatbot_configs =
+        headless_lms_models::chatbot_configurations::get_for_builtin_search_maintenance(conn)
+            .await?;
+    let course_ids: Vec<Uuid> = chatbot_configs
+        .iter()
+        .filter_map(|config| config.course_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    if course_ids.is_empty() {
+        return Ok(());
+    }
+
+    let latest_history_ids =
+        headless_lms_models::page_history::get_latest_page_history_ids_by_course_ids(
+            conn,
+            &course_ids,
+        )
+        .await?;
+    let embedder = AzureEmbedder::new(&config.app_configuration);
+
+    for course_id in course_ids {
+        let pages = headless_lms_models::pages::get_all_by_course_id_and_visibility(
+            conn,
+            course_id,
+            PageVisibility::Public,
+        )
+        .await?;
+        let public_page_ids: Vec<Uuid> = pages.iter().map(|p| p.id).collect();
+        let removed = headless_lms_models::chatbot_page_chunks::delete_for_other_pages_in_course(
+            conn,
+            course_id,
+            &public_page_ids,
+        )
+        .await?;
+        if removed > 0 {
+            info!(
+                "Removed {} chunks of pages that are no longer public from the built-in search index of course id: {}.",
+                removed, course_id
+            );
+        }
+
+        let indexed_history_ids =
+            headless_lms_models::chatbot_page_chunks::get_indexed_page_history_ids(conn, course_id)
+                .await?;
+        let outdated_pages: Vec<(&Page, Uuid)> = pages
+            .iter()
+            .filter_map(|page| {
+                let history_id = *latest_history_ids.get(&page.id)?;
+                (indexed_history_ids.get(&page.id) != Some(&history_id))
+                    .then_some((page, history_id))
+            })
+            .collect();
+        if outdated_pages.is_empty() {
+            continue;
+        }
+        info!(
+            "Indexing {} pages into the built-in search index of course id: {}.",
+            outdated_pages.len(),
+            course_id
+        );
+
+        let history_ids: Vec<Uuid> = outdated_pages.iter().map(|(_, id)| *id).collect();
+        let markdown_contents: HashMap<Uuid, String> =
+            headless_lms_models::course_page_markdown_content::get_latest_by_page_history_ids(
+                conn,
+                &history_ids,
+            )
+            .await?
+            .into_iter()
+            .map(|c| (c.page_history_id, c.markdown_content))
+            .collect();
+        let task_lm = headless_lms_models::application_task_default_language_models::get_for_task(
+            conn,
+            ApplicationTask::ContentCleaning,
+        )
+        .await?;
+
+        for (page, history_id) in outdated_pages {
+            let markdown = if let Some(markdown) = markdown_contents.get(&history_id) {
+                markdown.clone()
+            } else {
+                let parsed_content: Vec<GutenbergBlock> =
+                    serde_json::from_value(page.content.clone())?;
+                let sanitized_blocks = remove_sensitive_attributes(parsed_content);
+                match convert_material_blocks_to_markdown_with_llm(
+                    &sanitized_blocks,
+                    &config.app_configuration,
+                    &task_lm,
+                )
+                .await
+                {
+                    Ok(markdown) => {
+                        headless_lms_models::course_page_markdown_content::insert(
+                            conn,
+                            &markdown,
+                            &history_id,
+                            &page.id,
+                        )
+                        .await?;
+                        markdown
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to clean content with LLM for page {}: {}. Indexing serialized sanitized content instead.",
+                            page.id, e
+                        );
+                        serde_json::to_string(&sanitized_blocks)?
+                    }
+                }
+            };
+
+            match index_page(conn, &embedder, page.id, history_id, &page.title, &markdown).await {
+                Ok(chunk_count) => info!("Indexed page {} as {} chunks.", page.id, chunk_count),
+                Err(e) => warn!(
+                    "Failed to index page {} into the built-in search index: {:?}",
+                    page.id, e
+                ),
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Generates the blob storage path for a given page.
 fn generate_blob_path(page: &Page) -> anyhow::Result<String> {
     let course_id = page
@@ -338,7 +338,8 @@ async fn apply_email_template_replacements(
         | EmailTemplateType::CreditRegistrationAccountLinking
         | EmailTemplateType::CreditRegistrationActionNeeded
         | EmailTemplateType::CreditRegistrationRegistered
-        | EmailTemplateType::CreditRegistrationStudentNumberLinked => {}
+        | EmailTemplateType::CreditRegistrationStudentNumberLinked
+        | EmailTemplateType::PeerReviewDisputeResolved => {}
     }
 
     Ok(TemplateApplyResult::Ready(insert_placeholders(
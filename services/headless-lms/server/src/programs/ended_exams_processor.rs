@@ -5,10 +5,12 @@ use std::{
 
 use crate::config::program_config::ProgramConfig;
 use crate::setup_tracing;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Utc};
 use dotenvy::dotenv;
 use headless_lms_base::error::backend_error::BackendError;
-use headless_lms_models::{self as models, ModelError, ModelErrorType};
+use headless_lms_models::{
+    self as models, ModelError, ModelErrorType, exam_accommodations::ExamTiming,
+};
 use sqlx::{Connection, PgConnection, PgPool};
 use uuid::Uuid;
 
@@ -82,30 +84,44 @@ async fn process_ended_exam_enrollments(conn: &mut PgConnection) -> anyhow::Resu
     let ongoing_exam_enrollments: Vec<headless_lms_models::exams::ExamEnrollment> =
         models::exams::get_ongoing_exam_enrollments(&mut tx).await?;
     let exams = models::exams::get_exams(&mut tx).await?;
+    let accommodations =
+        models::exam_accommodations::get_for_ongoing_exam_enrollments(&mut tx).await?;
+    let deadline_exceptions =
+        models::deadline_exceptions::get_exam_deadlines_for_ongoing_exam_enrollments(&mut tx)
+            .await?;
 
-    let mut needs_ended_at_date: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    let now = Utc::now();
+    let mut needs_ended_at_date: HashMap<Uuid, (Vec<Uuid>, Vec<DateTime<Utc>>)> = HashMap::new();
     for enrollment in ongoing_exam_enrollments {
         let exam = exams
             .get(&enrollment.exam_id)
             .ok_or_else(|| ModelError::new(ModelErrorType::Generic, "Exam not found", None))?;
+        let key = (enrollment.exam_id, enrollment.user_id);
+        let timing = if enrollment.is_teacher_testing {
+            // Teachers can test the exam outside of its window.
+            ExamTiming::new(None, None, exam.time_minutes, None, None)
+        } else {
+            ExamTiming::new(
+                exam.starts_at,
+                exam.ends_at,
+                exam.time_minutes,
+                accommodations.get(&key),
+                deadline_exceptions.get(&key).copied(),
+            )
+        };
 
         //Check if users exams should have ended
-        if Utc::now() > enrollment.started_at + Duration::minutes(exam.time_minutes.into()) {
-            needs_ended_at_date
-                .entry(exam.id)
-                .or_default()
-                .push(enrollment.user_id);
+        let time_ends_at = timing.student_time_ends_at(enrollment.started_at);
+        if now > time_ends_at {
+            let (user_ids, ended_ats) = needs_ended_at_date.entry(exam.id).or_default();
+            user_ids.push(enrollment.user_id);
+            ended_ats.push(time_ends_at);
         }
     }
 
-    for entry in needs_ended_at_date.into_iter() {
-        let exam_id = entry.0;
-        let user_ids = entry.1;
+    for (exam_id, (user_ids, ended_ats)) in needs_ended_at_date.into_iter() {
         match models::exams::update_exam_ended_at_for_users_with_exam_id(
-            &mut tx,
-            exam_id,
-            &user_ids,
-            Utc::now(),
+            &mut tx, exam_id, &user_ids, &ended_ats,
         )
         .await
         {
@@ -0,0 +1,61 @@
+//! Passes the scores of LTI launches back to the gradebooks of the platforms, see
+//! [`crate::domain::lti::grade_passback`].
+
+use std::{env, time::Duration};
+
+use crate::config::program_config::ProgramConfig;
+use crate::domain::lti::grade_passback;
+use crate::setup_tracing;
+use dotenvy::dotenv;
+use headless_lms_base::config::ApplicationConfiguration;
+use sqlx::PgPool;
+
+/**
+Starts a loop that periodically sends the scores that have changed since they were last passed back.
+*/
+pub async fn main() -> anyhow::Result<()> {
+    // TODO: Audit that the environment access only happens in single-threaded code.
+    unsafe { env::set_var("RUST_LOG", "info,actix_web=info,sqlx=warn") };
+    dotenv().ok();
+    setup_tracing()?;
+    let database_url = ProgramConfig::database_url_with_default();
+    // The access token requests are signed with the key of the OAuth server.
+    let app_conf = ApplicationConfiguration::try_from_env()?;
+
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    let mut ticks = 10;
+
+    let db_pool = PgPool::connect(&database_url).await?;
+    let mut conn = db_pool.acquire().await?;
+    loop {
+        interval.tick().await;
+
+        ticks += 1;
+        // 10 60 second intervals = 10 minutes
+        if ticks > 10 {
+            // occasionally prints a reminder that the service is still running
+            ticks = 0;
+            tracing::info!("running the LTI grade syncer");
+        }
+
+        match grade_passback::sync_pending_scores(&mut conn, &app_conf).await {
+            Ok(0) => {}
+            Ok(synced) => tracing::info!("passed back {} LTI scores", synced),
+            Err(err) => {
+                tracing::error!("Error in LTI grade syncer: {:#}", err);
+                if err.chain().any(|cause| {
+                    matches!(
+                        cause.downcast_ref::<sqlx::Error>(),
+                        Some(sqlx::Error::Io(..))
+                    )
+                }) {
+                    // this usually happens if the database is reset while running bin/dev etc.
+                    tracing::info!(
+                        "LTI grade syncer may have lost its connection to the db, trying to reconnect"
+                    );
+                    conn = db_pool.acquire().await?;
+                }
+            }
+        }
+    }
+}
@@ -2,12 +2,15 @@
 Executable programs that can be started. Contains for example the server program, background services, and utility programs.
 */
 pub mod calculate_page_visit_stats;
+pub mod certificate_bulk_exporter;
+pub mod chatbot_evaluation_runner;
 pub mod chatbot_syncer;
 pub mod credit_registrar;
 pub mod doc_file_generator;
 pub mod email_deliver;
 pub mod ended_exams_processor;
 pub mod exercise_service_client_upload_reaper;
+pub mod lti_grade_syncer;
 pub mod mailchimp_syncer;
 pub mod open_university_registration_link_fetcher;
 pub mod peer_review_updater;
@@ -18,3 +21,5 @@ pub mod sorter;
 pub mod start_server;
 pub mod suotar_syncer;
 pub mod sync_tmc_users;
+pub mod webhook_deliverer;
+pub mod xapi_sender;
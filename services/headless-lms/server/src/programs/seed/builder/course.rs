@@ -450,6 +450,8 @@ impl CourseBuilder {
                 certificate_grade_font_size: None,
                 certificate_grade_text_color: None,
                 certificate_grade_text_anchor: None,
+                pdf_enabled: false,
+                pdf_transcript_pages_enabled: false,
             };
             let database_configuration =
                 certificate_configurations::insert(&mut tx, &configuration)
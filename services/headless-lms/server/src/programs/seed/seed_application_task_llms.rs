@@ -2,7 +2,7 @@ use headless_lms_models::{
     application_task_default_language_models::{
         self, ApplicationTask, ApplicationTaskDefaultLanguageModel,
     },
-    chatbot_configurations_models::{self, ModelType, NewChatbotConfigurationModel},
+    chatbot_configurations_models::{self, ModelProvider, ModelType, NewChatbotConfigurationModel},
 };
 
 use crate::prelude::*;
@@ -25,6 +25,9 @@ pub async fn seed_application_task_llms(
             default_model: true,
             model_type: ModelType::GPTNonThinking,
             context_size: 10000,
+            provider: ModelProvider::Azure,
+            api_base_url: None,
+            api_key_env_var: None,
         },
     )
     .await?;
@@ -73,6 +76,17 @@ pub async fn seed_application_task_llms(
     )
     .await?;
 
+    application_task_default_language_models::insert(
+        &mut conn,
+        ApplicationTaskDefaultLanguageModel {
+            model_id: llm.id,
+            task: ApplicationTask::ChatbotEvaluation,
+            context_utilization: 0.75,
+            ..Default::default()
+        },
+    )
+    .await?;
+
     Ok(SeedApplicationLLMsResult {
         llm_default_model_id: llm.id,
         llm_default_model_type: llm.model_type,
@@ -2021,6 +2021,8 @@ pub async fn seed_sample_course(
         certificate_grade_font_size: None,
         certificate_grade_text_color: None,
         certificate_grade_text_anchor: None,
+        pdf_enabled: false,
+        pdf_transcript_pages_enabled: false,
     };
     let database_configuration =
         certificate_configurations::insert(&mut conn, &configuration).await?;
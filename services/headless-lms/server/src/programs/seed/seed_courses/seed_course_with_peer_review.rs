@@ -125,6 +125,8 @@ pub async fn seed_peer_review_course(
                                         reset_answer_if_zero_points_from_review: false,
                                         points_are_all_or_nothing: true,
                                         review_instructions: None,
+                                        calibration_reviews_to_give: 0,
+                                        manual_review_reliability_threshold: None,
                                     }),
                                     Some(vec![CmsPeerOrSelfReviewQuestion {
                                         id: cx.v5(b"peer-review:1:q1"),
@@ -179,6 +181,8 @@ pub async fn seed_peer_review_course(
                                         reset_answer_if_zero_points_from_review: true,
                                         points_are_all_or_nothing: true,
                                         review_instructions: None,
+                                        calibration_reviews_to_give: 0,
+                                        manual_review_reliability_threshold: None,
                                     }),
                                     Some(vec![CmsPeerOrSelfReviewQuestion {
                                         id: cx.v5(b"peer-review:2:q1"),
@@ -205,6 +205,7 @@ pub async fn seed_generic_emails(
     seed_account_linking_templates(&mut conn).await?;
     seed_student_notification_templates(&mut conn).await?;
     seed_student_number_linked_templates(&mut conn).await?;
+    seed_peer_review_dispute_resolved_templates(&mut conn).await?;
 
     Ok(())
 }
@@ -475,6 +476,110 @@ async fn seed_student_number_linked_templates(conn: &mut sqlx::PgConnection) ->
     Ok(())
 }
 
+/// Tells a student that a teacher has resolved their dispute about a peer review. The outcome itself
+/// is shown on the exercise page, so the mail only carries the teacher's response.
+async fn seed_peer_review_dispute_resolved_templates(
+    conn: &mut sqlx::PgConnection,
+) -> anyhow::Result<()> {
+    info!("inserting peer review dispute resolved emails");
+
+    let english_subject = Some("Your peer review dispute has been resolved");
+    let english_body = json!([
+        {
+            "type": "core/paragraph",
+            "isValid": true,
+            "clientId": "e1000000-0000-0000-0000-000000000001",
+            "attributes": {
+                "content": "Hello, a teacher has resolved your dispute about a peer review you received on the exercise {{EXERCISE_NAME}} in the course {{COURSE_NAME}}.",
+                "drop_cap": false
+            },
+            "innerBlocks": []
+        },
+        {
+            "type": "core/paragraph",
+            "isValid": true,
+            "clientId": "e1000000-0000-0000-0000-000000000002",
+            "attributes": {
+                "content": "The teacher's response: {{TEACHER_RESPONSE}}",
+                "drop_cap": false
+            },
+            "innerBlocks": []
+        },
+        {
+            "type": "core/paragraph",
+            "isValid": true,
+            "clientId": "e1000000-0000-0000-0000-000000000003",
+            "attributes": {
+                "content": "You can see the outcome and your points on the exercise page.",
+                "drop_cap": false
+            },
+            "innerBlocks": []
+        }
+    ]);
+
+    insert_email_template(
+        conn,
+        None,
+        EmailTemplateNew {
+            template_type: EmailTemplateType::PeerReviewDisputeResolved,
+            language: Some("en".to_string()),
+            content: Some(english_body),
+            subject: english_subject.map(|s| s.to_string()),
+        },
+        english_subject,
+    )
+    .await?;
+
+    let finnish_subject = Some("Vertaisarviota koskeva valituksesi on käsitelty");
+    let finnish_body = json!([
+        {
+            "type": "core/paragraph",
+            "isValid": true,
+            "clientId": "e2000000-0000-0000-0000-000000000001",
+            "attributes": {
+                "content": "Hei, opettaja on käsitellyt valituksesi vertaisarviosta, jonka sait kurssin {{COURSE_NAME}} tehtävästä {{EXERCISE_NAME}}.",
+                "drop_cap": false
+            },
+            "innerBlocks": []
+        },
+        {
+            "type": "core/paragraph",
+            "isValid": true,
+            "clientId": "e2000000-0000-0000-0000-000000000002",
+            "attributes": {
+                "content": "Opettajan vastaus: {{TEACHER_RESPONSE}}",
+                "drop_cap": false
+            },
+            "innerBlocks": []
+        },
+        {
+            "type": "core/paragraph",
+            "isValid": true,
+            "clientId": "e2000000-0000-0000-0000-000000000003",
+            "attributes": {
+                "content": "Näet lopputuloksen ja pisteesi tehtävän sivulla.",
+                "drop_cap": false
+            },
+            "innerBlocks": []
+        }
+    ]);
+
+    insert_email_template(
+        conn,
+        None,
+        EmailTemplateNew {
+            template_type: EmailTemplateType::PeerReviewDisputeResolved,
+            language: Some("fi".to_string()),
+            content: Some(finnish_body),
+            subject: finnish_subject.map(|s| s.to_string()),
+        },
+        finnish_subject,
+    )
+    .await?;
+
+    Ok(())
+}
+
 /// The mail that carries a student-number linking link. Every placeholder comes from the delivery
 /// row because the recipient may have no account here, and the "you received this because" line has
 /// to stay because the message is unsolicited. A migration cannot insert a row using an enum value
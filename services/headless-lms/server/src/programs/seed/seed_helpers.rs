@@ -6,7 +6,7 @@ use headless_lms_models::{
     exercise_slide_submissions,
     exercise_task_gradings::{self, ExerciseTaskGradingResult, UserPointsUpdateStrategy},
     exercise_task_submissions,
-    exercises::{self, GradingProgress},
+    exercises::{self, ExerciseScoringStrategy, GradingProgress},
     page_history::HistoryChangeReason,
     pages::{
         self, CmsPageExercise, CmsPageExerciseSlide, CmsPageExerciseTask, CmsPageUpdate, NewPage,
@@ -335,6 +335,9 @@ pub fn example_exercise_flexible(
         needs_self_review: false,
         use_course_default_peer_or_self_review_config: false,
         teacher_reviews_answer_after_locking,
+        scoring_strategy: ExerciseScoringStrategy::BestAttempt,
+        scoring_strategy_attempt_count: None,
+        scoring_strategy_penalty_percent: None,
         peer_or_self_review_config,
         peer_or_self_review_questions,
     };
@@ -383,6 +386,9 @@ pub fn quizzes_exercise(
         needs_self_review: false,
         use_course_default_peer_or_self_review_config: true,
         teacher_reviews_answer_after_locking: true,
+        scoring_strategy: ExerciseScoringStrategy::BestAttempt,
+        scoring_strategy_attempt_count: None,
+        scoring_strategy_penalty_percent: None,
         peer_or_self_review_config: None,
         peer_or_self_review_questions: None,
     };
@@ -442,6 +448,9 @@ pub fn tmc_exercise(
         needs_self_review: false,
         use_course_default_peer_or_self_review_config: true,
         teacher_reviews_answer_after_locking: true,
+        scoring_strategy: ExerciseScoringStrategy::BestAttempt,
+        scoring_strategy_attempt_count: None,
+        scoring_strategy_penalty_percent: None,
         peer_or_self_review_config: None,
         peer_or_self_review_questions: None,
     };
@@ -680,6 +689,8 @@ pub async fn create_best_peer_review(
             points_are_all_or_nothing,
             review_instructions: None,
             reset_answer_if_zero_points_from_review: false,
+            calibration_reviews_to_give: 0,
+            manual_review_reliability_threshold: None,
         },
     )
     .await?;
@@ -0,0 +1,69 @@
+//! Posts the events of the organizations and courses to their webhook subscriptions, see
+//! [`crate::domain::webhooks`].
+
+use std::{env, time::Duration};
+
+use crate::config::program_config::ProgramConfig;
+use crate::domain::webhooks::outbox;
+use crate::setup_tracing;
+use dotenvy::dotenv;
+use headless_lms_base::config::bool_env_false_by_default;
+use sqlx::{PgConnection, PgPool};
+
+/**
+Starts a loop that periodically adds the new events to the outbox and posts the deliveries that are due.
+*/
+pub async fn main() -> anyhow::Result<()> {
+    // TODO: Audit that the environment access only happens in single-threaded code.
+    unsafe { env::set_var("RUST_LOG", "info,actix_web=info,sqlx=warn") };
+    dotenv().ok();
+    setup_tracing()?;
+    let database_url = ProgramConfig::database_url_with_default();
+    // The test receivers run locally, elsewhere only public endpoints are posted to.
+    let allow_private_addresses = bool_env_false_by_default("TEST_MODE");
+
+    let mut interval = tokio::time::interval(Duration::from_secs(10));
+    let mut ticks = 60;
+
+    let db_pool = PgPool::connect(&database_url).await?;
+    let mut conn = db_pool.acquire().await?;
+    loop {
+        interval.tick().await;
+
+        ticks += 1;
+        // 60 10 second intervals = 10 minutes
+        if ticks > 60 {
+            // occasionally prints a reminder that the service is still running
+            ticks = 0;
+            tracing::info!("running the webhook deliverer");
+        }
+
+        if let Err(err) = process(&mut conn, allow_private_addresses).await {
+            tracing::error!("Error in webhook deliverer: {:#}", err);
+            if err.chain().any(|cause| {
+                matches!(
+                    cause.downcast_ref::<sqlx::Error>(),
+                    Some(sqlx::Error::Io(..))
+                )
+            }) {
+                // this usually happens if the database is reset while running bin/dev etc.
+                tracing::info!(
+                    "webhook deliverer may have lost its connection to the db, trying to reconnect"
+                );
+                conn = db_pool.acquire().await?;
+            }
+        }
+    }
+}
+
+async fn process(conn: &mut PgConnection, allow_private_addresses: bool) -> anyhow::Result<()> {
+    let read = outbox::enqueue_new_events(conn).await?;
+    if read > 0 {
+        tracing::info!("read {} new events for webhooks", read);
+    }
+    let delivered = outbox::send_due_deliveries(conn, allow_private_addresses).await?;
+    if delivered > 0 {
+        tracing::info!("delivered {} webhook events", delivered);
+    }
+    Ok(())
+}
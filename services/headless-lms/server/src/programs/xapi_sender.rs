@@ -0,0 +1,78 @@
+//! Sends the learning events of the opted-in courses to the learning record store, see
+//! [`crate::domain::xapi`].
+
+use std::{env, time::Duration};
+
+use crate::config::program_config::ProgramConfig;
+use crate::domain::xapi::outbox;
+use crate::setup_tracing;
+use dotenvy::dotenv;
+use headless_lms_base::config::ApplicationConfiguration;
+use sqlx::{PgConnection, PgPool};
+
+/**
+Starts a loop that periodically adds the new events to the outbox and sends the statements that are due.
+*/
+pub async fn main() -> anyhow::Result<()> {
+    // TODO: Audit that the environment access only happens in single-threaded code.
+    unsafe { env::set_var("RUST_LOG", "info,actix_web=info,sqlx=warn") };
+    dotenv().ok();
+    setup_tracing()?;
+    let database_url = ProgramConfig::database_url_with_default();
+    let app_conf = ApplicationConfiguration::try_from_env()?;
+
+    let mut interval = tokio::time::interval(Duration::from_secs(10));
+    let mut ticks = 60;
+
+    let db_pool = PgPool::connect(&database_url).await?;
+    let mut conn = db_pool.acquire().await?;
+    loop {
+        interval.tick().await;
+
+        ticks += 1;
+        // 60 10 second intervals = 10 minutes
+        if ticks > 60 {
+            // occasionally prints a reminder that the service is still running
+            ticks = 0;
+            tracing::info!("running the xAPI sender");
+            if app_conf.xapi_configuration.is_none() {
+                tracing::warn!(
+                    "XAPI_LRS_ENDPOINT is not set, the xAPI statements are queued but not sent"
+                );
+            }
+        }
+
+        if let Err(err) = process(&mut conn, &app_conf).await {
+            tracing::error!("Error in xAPI sender: {:#}", err);
+            if err.chain().any(|cause| {
+                matches!(
+                    cause.downcast_ref::<sqlx::Error>(),
+                    Some(sqlx::Error::Io(..))
+                )
+            }) {
+                // this usually happens if the database is reset while running bin/dev etc.
+                tracing::info!(
+                    "xAPI sender may have lost its connection to the db, trying to reconnect"
+                );
+                conn = db_pool.acquire().await?;
+            }
+        }
+    }
+}
+
+async fn process(
+    conn: &mut PgConnection,
+    app_conf: &ApplicationConfiguration,
+) -> anyhow::Result<()> {
+    let read = outbox::enqueue_new_events(conn, &app_conf.base_url).await?;
+    if read > 0 {
+        tracing::info!("read {} new events for xAPI statements", read);
+    }
+    if let Some(xapi_conf) = &app_conf.xapi_configuration {
+        let sent = outbox::send_due_statements(conn, xapi_conf).await?;
+        if sent > 0 {
+            tracing::info!("sent {} xAPI statements", sent);
+        }
+    }
+    Ok(())
+}
@@ -8,7 +8,8 @@ use actix_http::{Request, body::BoxBody};
 use actix_session::{SessionMiddleware, storage::CookieSessionStore};
 use actix_web::{App, cookie::Key, dev::ServiceResponse, test};
 use headless_lms_base::config::{
-    ApplicationConfiguration, OAuthServerConfiguration, SuotarConfiguration,
+    ApplicationConfiguration, CertificateCredentialConfiguration, OAuthServerConfiguration,
+    SuotarConfiguration,
 };
 use headless_lms_models::{
     PKeyPolicy,
@@ -106,9 +107,12 @@ pub async fn test_config() -> ServerConfig {
             test_chatbot: false,
             test_sisu: false,
             test_suotar: false,
+            test_lti: false,
+            test_lrs: false,
             disable_embedding_vector_creation_when_seeding: false,
             suotar_configuration: SuotarConfiguration::mock_conf("http://project-331.local")
                 .expect("Failed to build the mock Suotar configuration"),
+            xapi_configuration: None,
             tmc_account_creation_origin: None,
             tmc_admin_access_token: SecretString::new("mock-access-token".to_string().into()),
             oauth_server_configuration: OAuthServerConfiguration {
@@ -119,6 +123,10 @@ pub async fn test_config() -> ServerConfig {
                     "test-key".into(),
                 ))),
             },
+            certificate_credential_configuration: CertificateCredentialConfiguration {
+                rsa_public_key: "temp-change-when-needed".into(),
+                rsa_private_key: SecretString::new("test-change".into()),
+            },
         },
         redis_url: SecretString::new("redis://example.com".into()),
         mock_suotar_redis_db_index: 2,
@@ -8,6 +8,7 @@ pub enum JSONType {
     Object,
     Array,
     String,
+    Integer,
 }
 
 /// Defines LLM structured output shape and types